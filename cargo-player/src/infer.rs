@@ -1,21 +1,125 @@
 use std::sync::{Arc, Mutex};
 
-use crate::File;
+use crate::{Dependency, File};
 
 use crates_index::Index;
 use once_cell::sync::OnceCell;
-use syn::{
-    parse_file, Block, Error, Expr, ImplItem, Item, ItemFn, ItemImpl, ItemMod, Stmt, UseTree,
-};
+use syn::{parse_file, Block, Expr, ImplItem, Item, ItemFn, ItemImpl, ItemMod, Stmt, UseTree};
+use thiserror::Error;
 
 const USE_KEYWORDS: &[&str] = &["std", "core", "crate", "self", "alloc", "super"];
 
-pub fn infer_deps(files: &[File]) -> Result<String, syn::Error> {
+const DEP_PREFIX: &str = "//# ";
+
+/// A `//# ` dependency comment which isn't a valid dependency
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid dependency in {file}.rs on line {line}: {message}")]
+pub struct DependencyError {
+    pub file: String,
+    // both start at 1
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+/// A dependency from a `//# ` comment, the text is put into `[dependencies]` as is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HeaderDep {
+    pub name: String,
+    pub text: String,
+}
+
+/// The `//# ` dependency comments at the top of a file, like `//# serde = { version = "1", features = ["derive"] }`.
+/// Only the comment lines at the very start are read, `//> ` lines may be mixed in with them
+pub(crate) fn header_deps(file: &File) -> Result<Vec<HeaderDep>, DependencyError> {
+    let mut deps = vec![];
+
+    for (index, line) in file.code.lines().enumerate() {
+        if line.starts_with("//> ") {
+            continue;
+        }
+
+        let Some(text) = line.strip_prefix(DEP_PREFIX) else {
+            break;
+        };
+
+        let error = |col: usize, message: String| DependencyError {
+            file: file.name.to_string(),
+            line: index + 1,
+            col: col + DEP_PREFIX.len() + 1,
+            message,
+        };
+
+        let table = text.parse::<toml::Table>().map_err(|e| {
+            let col = e.span().map(|span| span.start).unwrap_or_default();
+            error(col, e.message().to_string())
+        })?;
+
+        let mut entries = table.into_iter();
+        let (name, value) = match (entries.next(), entries.next()) {
+            (Some(entry), None) => entry,
+            _ => {
+                return Err(error(
+                    0,
+                    "expected one dependency, like `name = \"1\"`".to_string(),
+                ))
+            }
+        };
+
+        if !matches!(value, toml::Value::String(_) | toml::Value::Table(_)) {
+            return Err(error(
+                text.find('=').unwrap_or_default(),
+                format!("expected a version or a table for `{name}`, like `{name} = \"1\"`"),
+            ));
+        }
+
+        deps.push(HeaderDep {
+            name,
+            text: text.trim().to_string(),
+        });
+    }
+
+    Ok(deps)
+}
+
+// Compare crate names with - or _ being equal
+fn same_crate(a: &str, b: &str) -> bool {
+    let convert_case = |b| -> u8 {
+        // only convert - to _ . Else, it's either _, or something we shouldn't filter
+        if b == b'-' {
+            b'_'
+        } else {
+            b
+        }
+    };
+
+    a.bytes().map(convert_case).eq(b.bytes().map(convert_case))
+}
+
+/// The names of the `//# ` dependencies which one of the `dependencies` replaces
+pub(crate) fn overridden_deps(
+    files: &[File],
+    dependencies: &[Dependency],
+) -> Result<Vec<String>, DependencyError> {
+    let mut overridden = vec![];
+
+    for file in files {
+        for dep in header_deps(file)? {
+            if dependencies.iter().any(|d| same_crate(d.name, &dep.name)) {
+                overridden.push(dep.name);
+            }
+        }
+    }
+
+    Ok(overridden)
+}
+
+pub fn infer_deps(files: &[File], dependencies: &[Dependency]) -> Result<String, DependencyError> {
     let mut deps = vec![];
 
     files
         .iter()
-        .map(|f| -> Result<_, Error> { Ok(parse_file(f.code)?.items) })
+        .map(|f| -> Result<_, syn::Error> { Ok(parse_file(f.code)?.items) })
         .for_each(|f| {
             if let Ok(tokens) = f {
                 // we will keep track of all mod statements used throughout the files
@@ -37,44 +141,45 @@ pub fn infer_deps(files: &[File]) -> Result<String, syn::Error> {
     // stops processing when non ``//# ` is found
     let mut added = 0;
     for file in files {
-        for line in file.code.lines() {
-            if let Some(line) = line.strip_prefix(r#"//# "#) {
-                // find the name of the dependency
-                let name = line.find('=').map(|i| line[0..i].trim());
-
-                // remove dependency with same name to avoid conflicts - user provided deps are overrides
-                if let Some(name) = name {
-                    let index = deps.iter().position(|p| {
-                        let convert_case = |b| -> u8 {
-                            // only convert - to _ . Else, it's either _, or something we shouldn't filter
-                            if b == b'-' {
-                                b'_'
-                            } else {
-                                b
-                            }
-                        };
-
-                        // Compare crate names with - or _ being equal
-                        p.bytes()
-                            .map(convert_case)
-                            .eq(name.bytes().map(convert_case))
-                    });
-
-                    if let Some(i) = index {
-                        deps.remove(i);
-                    }
-                }
+        for dep in header_deps(file)? {
+            // the added dependencies override these in turn
+            if dependencies.iter().any(|d| same_crate(d.name, &dep.name)) {
+                continue;
+            }
 
-                deps.insert(0, line.to_string());
-                added += 1;
+            // remove dependency with same name to avoid conflicts - user provided deps are overrides
+            let index = deps.iter().position(|p| same_crate(p, &dep.name));
 
-                continue;
+            if let Some(i) = index {
+                deps.remove(i);
             }
 
-            break;
+            deps.insert(0, dep.text);
+            added += 1;
         }
     }
 
+    // these were already checked against the comments, the inferred ones are bare names
+    for dependency in dependencies.iter().rev() {
+        if let Some(i) = deps
+            .iter()
+            .skip(added)
+            .position(|p| same_crate(p, dependency.name))
+        {
+            deps.remove(added + i);
+        }
+
+        let version = match dependency.version.trim() {
+            "" => "*",
+            version => version,
+        };
+
+        // a toml string, so quotes in the version can't break out of it
+        let version = toml::Value::String(version.to_string());
+        deps.insert(0, format!("{} = {version}", dependency.name));
+        added += 1;
+    }
+
     // use the crates index to search for package existence and intelligently correct it if possible/needed
     // that way we don't require a custom correction from the user if `use crate_name` is actually named `crate-name` on crates.io
     // this is lazy initialized AND initialized only once to save performance
@@ -291,7 +396,7 @@ mod tests {
                 )*
             ];
 
-            let result = infer_deps(files);
+            let result = infer_deps(files, &[]);
             assert_eq!($result, result.unwrap());
         };
     }
@@ -345,6 +450,80 @@ use baz_bar;
         );
     }
 
+    #[test]
+    fn header_deps_versions_tables_and_git() {
+        let file = File::new(
+            "main",
+            r#"//# rand = "0.8"
+//# serde = { version = "1", features = ["derive"] }
+//> [profile.dev]
+//# regex = { git = "https://github.com/rust-lang/regex", branch = "master" }
+//# tokio.version = "1"
+use serde;
+//# not_a_header = "1"
+"#,
+        );
+
+        let deps = header_deps(&file).unwrap();
+        let names: Vec<_> = deps.iter().map(|dep| dep.name.as_str()).collect();
+
+        assert_eq!(names, ["rand", "serde", "regex", "tokio"]);
+        assert_eq!(
+            deps[1].text,
+            r#"serde = { version = "1", features = ["derive"] }"#
+        );
+    }
+
+    #[test]
+    fn header_deps_malformed_toml() {
+        let file = File::new(
+            "main",
+            r#"//# rand = "0.8"
+//# serde = { version = "1", features = ["derive"]
+"#,
+        );
+
+        let error = header_deps(&file).unwrap_err();
+        assert_eq!(error.file, "main");
+        assert_eq!(error.line, 2);
+        assert!(error.col > 4);
+
+        let file = File::new("main", "//# rand = 1\n");
+        assert_eq!(header_deps(&file).unwrap_err().line, 1);
+
+        let file = File::new("main", "//# rand = \"1\" serde = \"1\"\n");
+        assert!(header_deps(&file).is_err());
+
+        let file = File::new("main", "//# rand = \"1\"\nuse rand;");
+        assert!(infer_deps(&[file], &[]).is_ok());
+        let file = File::new("main", "//# rand = \nuse rand;");
+        assert!(infer_deps(&[file], &[]).is_err());
+    }
+
+    #[test]
+    fn infer_deps_added_dependencies_override() {
+        let files = &[File::new(
+            "main",
+            r#"//# serde = "1.0.100"
+//# regex = "1"
+use serde;
+use rand_core;
+"#,
+        )];
+        let dependencies = &[
+            Dependency::new("serde", "1.0.152"),
+            Dependency::new("rand-core", ""),
+        ];
+
+        assert_eq!(
+            infer_deps(files, dependencies).unwrap(),
+            r#"serde = "1.0.152"
+rand-core = "*"
+regex = "1""#
+        );
+        assert_eq!(overridden_deps(files, dependencies).unwrap(), ["serde"]);
+    }
+
     #[test]
     fn infer_deps_mod() {
         try_infer_deps!(
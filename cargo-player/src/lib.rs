@@ -3,4 +3,6 @@ mod infer;
 mod project;
 mod project_builder;
 
+pub use infer::DependencyError;
 pub use project::*;
+pub use project_builder::ProjectBuildError;
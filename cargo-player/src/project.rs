@@ -7,9 +7,11 @@ use strum_macros::{Display, IntoStaticStr};
 use thiserror::Error;
 
 use crate::cargo_command_builder::CargoCommandBuilder;
+use crate::infer::{overridden_deps, DependencyError};
 use crate::project_builder::{ProjectBuildError, ProjectBuilder};
 
 #[derive(Debug, Clone, Copy, Default, IntoStaticStr, PartialEq, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Edition {
     #[strum(to_string = "2015")]
     E2015,
@@ -18,6 +20,8 @@ pub enum Edition {
     #[default]
     #[strum(to_string = "2021")]
     E2021,
+    #[strum(to_string = "2024")]
+    E2024,
 }
 
 #[derive(Debug, Clone, Copy, Default, IntoStaticStr, PartialEq)]
@@ -52,7 +56,8 @@ pub enum Subcommand {
     Rustfmt,
 }
 
-#[derive(Debug, Clone, Copy, Default, IntoStaticStr, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, IntoStaticStr, PartialEq, Display)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Channel {
     #[default]
     #[strum(to_string = "stable")]
@@ -75,6 +80,7 @@ pub enum Backtrace {
 }
 
 #[derive(Debug, Clone, Copy, Default, IntoStaticStr, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BuildType {
     #[default]
     #[strum(to_string = "")]
@@ -95,21 +101,38 @@ impl<'a> File<'a> {
     }
 }
 
+/// A dependency added outside of the code. These override the `//# ` comments and the inferred ones
+#[derive(Debug, Clone, Copy)]
+pub struct Dependency<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) version: &'a str,
+}
+
+impl<'a> Dependency<'a> {
+    /// An empty version means any version
+    pub fn new(name: &'a str, version: &'a str) -> Self {
+        Self { name, version }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ProjectError {
-    #[error("Failed to build project")]
+    #[error("Failed to build project: {0}")]
     ProjectBuildError(#[from] ProjectBuildError),
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Project<'a> {
     pub(crate) files: Vec<File<'a>>,
+    pub(crate) dependencies: Vec<Dependency<'a>>,
     pub(crate) hash: u64,
     pub(crate) edition: Edition,
     env: Vec<(&'a str, &'a str)>,
     cargo_command_builder: CargoCommandBuilder<'a>,
     pub(crate) location: Option<String>,
     pub(crate) target_prefix: Option<&'a str>,
+    pub(crate) workspace: Option<&'a Path>,
+    pub(crate) name: Option<&'a str>,
 }
 
 impl<'a> Project<'a> {
@@ -138,6 +161,17 @@ impl<'a> Project<'a> {
         self
     }
 
+    /// Add a dependency (append)
+    pub fn dependency(&mut self, dependency: Dependency<'a>) -> &mut Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    /// The `//# ` dependencies in the files which are overridden by the added dependencies
+    pub fn overridden_deps(&self) -> Result<Vec<String>, DependencyError> {
+        overridden_deps(&self.files, &self.dependencies)
+    }
+
     /// Set the toolchain channel to use
     pub fn channel(&mut self, channel: Channel) -> &mut Self {
         self.cargo_command_builder.channel(channel);
@@ -249,6 +283,32 @@ impl<'a> Project<'a> {
         self
     }
 
+    /// Directory to put the project folder in, instead of `<temp dir>/rust`. The folder is reused by every
+    /// project with the same ID, so keeping it somewhere that isn't cleared keeps the build incremental
+    pub fn workspace(&mut self, dir: &'a Path) -> &mut Self {
+        self.workspace = Some(dir);
+        self
+    }
+
+    /// Package name to use in the generated Cargo.toml, instead of `p<id>`. Must be a valid crate name
+    pub fn name(&mut self, name: &'a str) -> &mut Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// The project's folder, once it was created
+    pub fn location(&self) -> Option<&Path> {
+        self.location.as_deref().map(Path::new)
+    }
+
+    /// The package name in the generated Cargo.toml, which is also what the built binary is called
+    pub fn package_name(&self) -> String {
+        match self.name {
+            Some(name) => name.to_string(),
+            None => format!("p{}", self.hash),
+        }
+    }
+
     /// Cargo clean the project. If project wasn't created yet, returns None
     /// TODO: Make lib that can pipe stdout and stderr together
     pub fn clean_project(&mut self) -> Option<Child> {
@@ -280,6 +340,14 @@ impl<'a> Project<'a> {
 
         Ok(command)
     }
+
+    /// Write the project into `dir` as a standalone crate, the way `cargo new` would lay it out. Files
+    /// already in `dir` with the same names are overwritten
+    pub fn export(&mut self, dir: &Path) -> Result<(), ProjectError> {
+        ProjectBuilder::export(self, dir)?;
+
+        Ok(())
+    }
 }
 
 fn fix_paths() {
@@ -1,7 +1,9 @@
-use crate::infer::infer_deps;
+use crate::infer::{infer_deps, DependencyError};
 use crate::Project;
 
 use std::fs;
+use std::io;
+use std::path::Path;
 
 use thiserror::Error;
 
@@ -9,6 +11,8 @@ use thiserror::Error;
 pub enum ProjectBuildError {
     #[error("Io error occurred")]
     Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Dependency(#[from] DependencyError),
 }
 
 pub struct ProjectBuilder<'a, 'b> {
@@ -20,11 +24,13 @@ impl<'a, 'b> ProjectBuilder<'a, 'b> {
         Self { project }
     }
 
-    fn create_cargo_toml(&self) -> String {
+    fn create_cargo_toml(&self) -> Result<String, DependencyError> {
         let edition = self.project.edition;
-        let id = self.project.hash;
-        // if the user has malformed code, or wrong deps that's not our fault. Running cargo will reveal it
-        let dependencies = infer_deps(&self.project.files).unwrap_or_default();
+        let name = self.project.package_name();
+        // if the user has malformed code, or wrong deps that's not our fault. Running cargo will reveal it.
+        // Malformed `//# ` comments are caught here though, cargo's error for them would point at the
+        // generated Cargo.toml instead of the scratch
+        let dependencies = infer_deps(&self.project.files, &self.project.dependencies)?;
 
         // we can add extra cargo toml, but only in the main file
         let mut extra_cargo = String::new();
@@ -51,7 +57,7 @@ impl<'a, 'b> ProjectBuilder<'a, 'b> {
 
         let mut formatted = format!(
             r#"[package]
-name = "p{id}"
+name = "{name}"
 version = "0.1.0"
 edition = "{edition}"
 
@@ -65,35 +71,125 @@ edition = "{edition}"
             formatted.push_str(&extra_cargo);
         }
 
-        formatted
+        Ok(formatted)
     }
 
     pub fn copy(project: &'a mut Project<'b>) -> Result<(), ProjectBuildError> {
         let builder = ProjectBuilder::new(project);
 
-        let cargo_config = builder.create_cargo_toml();
+        let cargo_config = builder.create_cargo_toml()?;
 
         let hash = builder.project.hash;
         let name = builder.project.target_prefix.unwrap_or("cargo-play");
 
         let folder_name = format!("{name}.{hash}");
 
-        let target_dir = std::env::temp_dir().join("rust").join(folder_name);
+        let target_dir = match builder.project.workspace {
+            Some(workspace) => workspace.join(folder_name),
+            None => std::env::temp_dir().join("rust").join(folder_name),
+        };
 
+        builder.write_files(&target_dir, &cargo_config)?;
+        // the dir is reused between runs, and a module which is gone would still be built
+        builder.remove_stale_files(&target_dir.join("src"), "")?;
+
+        builder.project.location = Some(target_dir.to_str().unwrap().to_string());
+
+        Ok(())
+    }
+
+    pub fn export(project: &'a mut Project<'b>, dir: &Path) -> Result<(), ProjectBuildError> {
+        let builder = ProjectBuilder::new(project);
+
+        let cargo_config = builder.create_cargo_toml()?;
+        builder.write_files(dir, &cargo_config)?;
+        write_if_changed(&dir.join(".gitignore"), "/target\n")?;
+
+        Ok(())
+    }
+
+    fn write_files(&self, dir: &Path, cargo_config: &str) -> Result<(), ProjectBuildError> {
         // create all directories straight to src
-        let target_dir_src = target_dir.join("src");
-        if !target_dir_src.exists() {
-            fs::create_dir_all(&target_dir_src)?;
+        let src = dir.join("src");
+        if !src.exists() {
+            fs::create_dir_all(&src)?;
         }
 
-        fs::write(target_dir.join("Cargo.toml"), cargo_config)?;
+        write_if_changed(&dir.join("Cargo.toml"), cargo_config)?;
 
-        for file in &builder.project.files {
-            fs::write(target_dir_src.join(format!("{}.rs", file.name)), file.code)?;
+        for file in &self.project.files {
+            write_if_changed(&src.join(format!("{}.rs", file.name)), file.code)?;
         }
 
-        builder.project.location = Some(target_dir.to_str().unwrap().to_string());
+        Ok(())
+    }
+
+    // Delete the .rs files under `dir` which aren't one of the project's files anymore. `prefix`
+    // is the path of `dir` in module names, for the files in subdirectories. Exported projects
+    // don't get this, their dir may have the user's own files in it
+    fn remove_stale_files(&self, dir: &Path, prefix: &str) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let name = format!("{prefix}{stem}");
+
+            if path.is_dir() {
+                self.remove_stale_files(&path, &format!("{name}/"))?;
+            } else if path.extension().is_some_and(|extension| extension == "rs")
+                && !self.project.files.iter().any(|file| file.name == name)
+            {
+                fs::remove_file(&path)?;
+            }
+        }
 
         Ok(())
     }
 }
+
+// cargo goes by modification times, so rewriting a file that's the same would still rebuild the crate
+fn write_if_changed(path: &Path, contents: &str) -> io::Result<()> {
+    if fs::read(path).ok().as_deref() == Some(contents.as_bytes()) {
+        return Ok(());
+    }
+
+    fs::write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::File;
+
+    #[test]
+    fn removed_modules_are_deleted_from_the_project() {
+        let workspace = std::env::temp_dir().join(format!("cargo-player-{}", std::process::id()));
+
+        let mut project = Project::new("stale modules");
+        project
+            .workspace(&workspace)
+            .file(File::new("main", "mod foo;\nmod bar;\nfn main() {}"))
+            .file(File::new("foo", "pub fn foo() {}"))
+            .file(File::new("bar", "pub fn bar() {}"));
+        ProjectBuilder::copy(&mut project).unwrap();
+
+        let src = project.location().unwrap().join("src");
+        assert!(src.join("foo.rs").exists());
+
+        // built again without one of the modules
+        let mut project = Project::new("stale modules");
+        project
+            .workspace(&workspace)
+            .file(File::new("main", "mod bar;\nfn main() {}"))
+            .file(File::new("bar", "pub fn bar() {}"));
+        ProjectBuilder::copy(&mut project).unwrap();
+
+        assert_eq!(project.location().unwrap().join("src"), src);
+        assert!(!src.join("foo.rs").exists());
+        assert!(src.join("bar.rs").exists());
+        assert!(src.join("main.rs").exists());
+
+        fs::remove_dir_all(&workspace).unwrap();
+    }
+}
@@ -1,18 +1,55 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
 use serde::{Deserialize, Serialize};
 
 use super::dock::DockConfig;
 use super::theme::ThemeConfig;
+use super::Editor;
 use super::GitHub;
+use super::GoToLineWindow;
+use super::Keybindings;
+use super::Logging;
+use super::PickerWindow;
+use super::RecentFiles;
+use super::Runner;
+use super::SettingsWindow;
+use super::StatusBar;
 use super::Terminal;
+use super::UiConfig;
+use crate::utils::key_combo::ComboKey;
 
 #[derive(Default, Serialize, Deserialize)]
+// settings added later on are missing from older settings files
+#[serde(default)]
 pub struct Config {
     pub github: GitHub,
     pub theme: ThemeConfig,
+    pub editor: Editor,
+    pub runner: Runner,
+    // only the terminal settings are persisted, the rest is runtime data
+    pub terminal: Terminal,
+    pub keybindings: Keybindings,
+    pub recent_files: RecentFiles,
+    pub status_bar: StatusBar,
+    pub ui: UiConfig,
+    pub logging: Logging,
 
     // Runtime config and data sharing/saving, not persisted
     #[serde(skip_serializing, skip_deserializing)]
     pub dock: DockConfig,
     #[serde(skip_serializing, skip_deserializing)]
-    pub terminal: Terminal,
+    pub settings: SettingsWindow,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub palette: PickerWindow,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub quick_open: PickerWindow,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub go_to_line: GoToLineWindow,
+    // the keys asked for from the os which were down last frame, so holding one presses it once
+    #[serde(skip_serializing, skip_deserializing)]
+    pub os_keys_down: HashSet<ComboKey>,
+    // whether the os is in light mode, and when it was asked
+    #[serde(skip_serializing, skip_deserializing)]
+    pub system_light: Option<(bool, Instant)>,
 }
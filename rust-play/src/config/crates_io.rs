@@ -0,0 +1,98 @@
+use serde::Deserialize;
+use std::sync::mpsc::{channel, Receiver};
+use thiserror::Error;
+
+// how many results to show for a search
+const SEARCH_RESULTS: &str = "8";
+
+#[derive(Debug, Error)]
+pub enum CratesIoError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("crates.io replied with status {0}")]
+    Status(u16),
+    #[error("Unknown error occurred")]
+    Unknown,
+}
+
+/// A crate found by searching crates.io
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrateMatch {
+    pub name: String,
+    #[serde(rename = "max_version")]
+    pub version: String,
+    // the newest version which isn't a pre-release, if there is one
+    #[serde(rename = "max_stable_version")]
+    pub stable_version: Option<String>,
+    pub description: Option<String>,
+}
+
+impl CrateMatch {
+    /// The version to add the crate with
+    pub fn latest_version(&self) -> &str {
+        self.stable_version.as_deref().unwrap_or(&self.version)
+    }
+}
+
+/// The crates.io search box of a tab's dependencies
+#[derive(Debug, Default)]
+pub struct CrateSearch {
+    pub query: String,
+    pub results: Vec<CrateMatch>,
+    pub error: Option<String>,
+    pub pending: Option<Receiver<Result<Vec<CrateMatch>, CratesIoError>>>,
+}
+
+/// Searches crates.io for crates matching the query
+/// Does not block, but instead returns a receiver you can use to receive the results
+pub fn search_crates(query: &str) -> Receiver<Result<Vec<CrateMatch>, CratesIoError>> {
+    let (tx, rx) = channel();
+
+    let query = query.to_owned();
+
+    std::thread::spawn(move || {
+        let client = reqwest::blocking::Client::new();
+
+        // crates.io refuses requests without a user agent
+        let result = client
+            .get("https://crates.io/api/v1/crates")
+            .header(
+                "User-Agent",
+                "RustPlay (https://github.com/MolotovCherry/RustPlay)",
+            )
+            .query(&[("q", query.as_str()), ("per_page", SEARCH_RESULTS)])
+            .send();
+
+        let reply = match result {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = tx.send(Err(CratesIoError::Network(e)));
+                return;
+            }
+        };
+
+        if !reply.status().is_success() {
+            let _ = tx.send(Err(CratesIoError::Status(reply.status().as_u16())));
+            return;
+        }
+
+        let reply = reply
+            .text()
+            .ok()
+            .and_then(|text| serde_json::from_str::<SearchReply>(&text).ok());
+
+        let Some(reply) = reply else {
+            let _ = tx.send(Err(CratesIoError::Unknown));
+            return;
+        };
+
+        let _ = tx.send(Ok(reply.crates));
+    });
+
+    rx
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchReply {
+    crates: Vec<CrateMatch>,
+}
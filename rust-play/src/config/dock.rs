@@ -1,12 +1,83 @@
-use crate::widgets::dock::{Tree, TreeTabs};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+use super::{CrateSearch, Gist, GitHubError, LineEdit};
+use crate::utils::crash_reports::CrashReport;
+use crate::utils::diagnostics::Diagnostic;
+use crate::utils::drafts::Draft;
+use crate::utils::export::ExportError;
+use crate::utils::file_watch::FileWatcher;
+use crate::utils::lsp::LspClient;
+use crate::utils::single_instance::OpenRequest;
+use crate::utils::wasm;
+use crate::widgets::dock::{Tab, Tree, TreeTabs};
+use cargo_player::{BuildType, Channel, Edition, Subcommand};
 use egui::Id;
-use egui_dock::NodeIndex;
+use egui_dock::{NodeIndex, Split};
+use serde::{Deserialize, Serialize};
+
+/// How many closed tabs can be reopened
+pub const CLOSED_TABS: usize = 20;
+/// How often the session is saved, when it changed
+pub const SESSION_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
 pub struct DockConfig {
     pub tree: Tree,
     pub commands: Vec<Command>,
+    // how many scratches were made, each one's id is made from it
     pub counter: u32,
+    // save dialogs which are currently open, keyed by tab id
+    // receives None if the user cancelled the dialog
+    pub pending_saves: HashMap<Id, Receiver<Option<PathBuf>>>,
+    // scratches being shared as gists, keyed by tab id
+    pub pending_shares: HashMap<Id, ShareState>,
+    // the crates.io search in each tab's dependencies
+    pub crate_searches: HashMap<Id, CrateSearch>,
+    // background checks which are still running, keyed by tab id, with the revision they're checking
+    pub pending_checks: HashMap<Id, (u64, Receiver<Vec<Diagnostic>>)>,
+    // compiler output being generated for a tab, receives the output or cargo's errors
+    pub pending_emits: HashMap<(Id, EmitKind), Receiver<Result<String, String>>>,
+    // `cargo expand`s which are still running, keyed by the tab being expanded
+    pub pending_expands: HashMap<Id, Receiver<Result<String, ExpandError>>>,
+    // wasm builds which are still running, receives the disassembled module once the build succeeds
+    pub pending_wats: HashMap<Id, Receiver<Result<String, String>>>,
+    // the code revision each tab was last run at, so auto-run doesn't run the same code twice
+    pub ran_revisions: HashMap<Id, u64>,
+    // scratches being exported as cargo projects, keyed by tab id
+    pub pending_exports: HashMap<Id, ExportState>,
+    // rust-analyzer running on each tab it was started for, while it's turned on
+    pub lsp_clients: HashMap<Id, LspClient>,
+    // the open dialog, while it's open. Receives None if the user cancelled it
+    pub pending_open: Option<Receiver<Option<PathBuf>>>,
+    // watches the files tabs were opened from or saved to, made on the first frame
+    pub watcher: Option<FileWatcher>,
+    // set once the app can close without losing unsaved changes
+    pub exiting: bool,
+    // the tabs which were closed, the last one first, for reopening them
+    pub closed_tabs: VecDeque<Tab>,
+    // the tabs by when they were last shown, the current one first
+    pub recent_tabs: Vec<Id>,
+    // which of the recent tabs Ctrl+Tab picked, while Ctrl is still held
+    pub tab_switcher: Option<usize>,
+    // when the session was last compared with the one on disk, and what was written there. The
+    // output is only told apart by its revision, it's too big to copy just to compare it
+    pub session_checked: Instant,
+    pub saved_layout: String,
+    pub saved_output: Option<u64>,
+    // when the drafts were last written, and the code revision each tab's draft has
+    pub drafts_saved: Instant,
+    pub drafted: HashMap<Id, u64>,
+    // the focused tab when the drafts were last looked at, they're written when it changes
+    pub draft_focus: Option<Id>,
+    // drafts newer than the session the app started with, until each is restored or discarded
+    pub recovered_drafts: Vec<Draft>,
+    // the reports of panics since the app last ran, shown one after the other
+    pub crash_reports: Vec<CrashReport>,
+    // the files other instances of the app were started with, while this is the single instance
+    pub open_requests: Option<Receiver<OpenRequest>>,
 }
 
 impl Default for DockConfig {
@@ -14,15 +85,276 @@ impl Default for DockConfig {
         Self {
             tree: Tree::init(),
             commands: Default::default(),
-            counter: 0,
+            // the first tab is made along with the tree
+            counter: 1,
+            pending_saves: Default::default(),
+            pending_shares: Default::default(),
+            crate_searches: Default::default(),
+            pending_checks: Default::default(),
+            pending_emits: Default::default(),
+            pending_expands: Default::default(),
+            pending_wats: Default::default(),
+            ran_revisions: Default::default(),
+            pending_exports: Default::default(),
+            lsp_clients: Default::default(),
+            pending_open: None,
+            watcher: None,
+            exiting: false,
+            closed_tabs: Default::default(),
+            recent_tabs: vec![],
+            tab_switcher: None,
+            session_checked: Instant::now(),
+            saved_layout: String::new(),
+            saved_output: None,
+            drafts_saved: Instant::now(),
+            drafted: Default::default(),
+            draft_focus: None,
+            recovered_drafts: vec![],
+            crash_reports: vec![],
+            open_requests: None,
+        }
+    }
+}
+
+/// What the Play button does with a tab's scratch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunMode {
+    #[default]
+    Run,
+    // for scratches which are a bunch of #[test] functions
+    Test,
+    Check,
+    // run on the nightly toolchain under Miri, to find undefined behavior
+    Miri,
+}
+
+impl RunMode {
+    pub const ALL: [RunMode; 4] = [RunMode::Run, RunMode::Test, RunMode::Check, RunMode::Miri];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunMode::Run => "Run",
+            RunMode::Test => "Test",
+            RunMode::Check => "Check",
+            RunMode::Miri => "Miri",
+        }
+    }
+
+    /// The name in the list of modes, where there's room to say more
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunMode::Miri => "Run under Miri",
+            mode => mode.name(),
+        }
+    }
+
+    pub fn subcommand(&self) -> Subcommand {
+        match self {
+            RunMode::Run => Subcommand::Run,
+            RunMode::Test => Subcommand::Test,
+            RunMode::Check => Subcommand::Check,
+            RunMode::Miri => Subcommand::Miri,
+        }
+    }
+}
+
+/// What a tab's scratch is compiled for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    // the machine rust play runs on
+    #[default]
+    Host,
+    // built to look at the .wasm it makes, it can't be run here
+    Wasm32,
+}
+
+impl Target {
+    pub const ALL: [Target; 2] = [Target::Host, Target::Wasm32];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Host => "host",
+            Target::Wasm32 => "wasm32",
+        }
+    }
+
+    /// The target triple passed to cargo, None for the default one
+    pub fn triple(&self) -> Option<&'static str> {
+        match self {
+            Target::Host => None,
+            Target::Wasm32 => Some(wasm::WASM32),
+        }
+    }
+}
+
+/// How long a tab's program may run before it's stopped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Timeout {
+    // the one from the settings
+    #[default]
+    Default,
+    Off,
+    Secs(u64),
+}
+
+impl Timeout {
+    /// The time limit, given the one from the settings
+    pub fn limit(&self, default_secs: Option<u64>) -> Option<Duration> {
+        match self {
+            Timeout::Default => default_secs.map(Duration::from_secs),
+            Timeout::Off => None,
+            Timeout::Secs(secs) => Some(Duration::from_secs(*secs)),
+        }
+    }
+}
+
+/// How a tab's scratch is built and run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunConfig {
+    pub edition: Edition,
+    pub channel: Channel,
+    pub build_type: BuildType,
+    pub mode: RunMode,
+    pub target: Target,
+    pub timeout: Timeout,
+}
+
+impl RunConfig {
+    pub const EDITIONS: [Edition; 4] = [
+        Edition::E2015,
+        Edition::E2018,
+        Edition::E2021,
+        Edition::E2024,
+    ];
+    pub const CHANNELS: [Channel; 3] = [Channel::Stable, Channel::Beta, Channel::Nightly];
+    pub const BUILD_TYPES: [BuildType; 2] = [BuildType::Debug, BuildType::Release];
+
+    pub fn build_type_name(build_type: BuildType) -> &'static str {
+        match build_type {
+            BuildType::Debug => "debug",
+            BuildType::Release => "release",
+        }
+    }
+
+    /// What cargo is run with. Only checking is the same for wasm, the rest becomes a build
+    pub fn subcommand(&self) -> Subcommand {
+        match (self.target, self.mode) {
+            (Target::Host, mode) => mode.subcommand(),
+            (Target::Wasm32, RunMode::Check) => Subcommand::Check,
+            (Target::Wasm32, _) => Subcommand::Build,
+        }
+    }
+
+    /// A short description like `2021 · stable · debug`, with the target when it isn't the host
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} · {} · {}",
+            self.edition,
+            self.channel,
+            Self::build_type_name(self.build_type)
+        );
+
+        if self.target != Target::Host {
+            summary.push_str(&format!(" · {}", self.target.name()));
+        }
+
+        summary
+    }
+}
+
+/// The background `cargo check` of a tab's code
+#[derive(Debug, Clone, Default)]
+pub struct CheckState {
+    // what the last check found, cleared whenever the code changes
+    pub diagnostics: Vec<Diagnostic>,
+    // goes up with every edit
+    pub revision: u64,
+    // the revision the diagnostics are for
+    pub checked: Option<u64>,
+    // when the code was last changed, checks wait for the typing to stop
+    pub edited: Option<Instant>,
+}
+
+impl CheckState {
+    /// The diagnostics point at code which may not be there anymore, so they're dropped until the next
+    /// check
+    pub fn code_changed(&mut self) {
+        self.revision += 1;
+        self.diagnostics.clear();
+        self.edited = Some(Instant::now());
+    }
+}
+
+/// What the compiler can show of a tab's code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmitKind {
+    Assembly,
+    LlvmIr,
+    Mir,
+}
+
+impl EmitKind {
+    pub const ALL: [EmitKind; 3] = [EmitKind::Assembly, EmitKind::LlvmIr, EmitKind::Mir];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmitKind::Assembly => "Assembly",
+            EmitKind::LlvmIr => "LLVM IR",
+            EmitKind::Mir => "MIR",
         }
     }
+
+    /// The value of rustc's `--emit` flag
+    pub fn flag(&self) -> &'static str {
+        match self {
+            EmitKind::Assembly => "asm",
+            EmitKind::LlvmIr => "llvm-ir",
+            EmitKind::Mir => "mir",
+        }
+    }
+
+    /// The language the output is highlighted as. Without a syntax for it, it's shown as plain text
+    pub fn language(&self) -> &'static str {
+        match self {
+            EmitKind::Assembly => "asm",
+            EmitKind::LlvmIr => "ll",
+            // close enough to rust to be readable with its highlighting
+            EmitKind::Mir => "rs",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExpandError {
+    // cargo-expand is a separate install
+    NotInstalled,
+    // cargo's errors
+    Failed(String),
+}
+
+/// A dependency added to a tab from the run configuration, instead of a `//# ` comment
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateDependency {
+    pub name: String,
+    // empty for any version
+    pub version: String,
 }
 
+/// An environment variable set on a tab's process
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvVar {
+    pub key: String,
+    pub value: String,
+}
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone)]
 pub enum Command {
     MenuCommand(MenuCommand),
     TabCommand(TabCommand),
+    DialogCommand(DialogCommand),
+    TerminalCommand(TerminalCommand),
 }
 
 #[derive(Debug, Clone)]
@@ -30,11 +362,162 @@ pub enum MenuCommand {
     Rename(Id),
     Save(Id),
     Share(Id),
+    // copy a play.rust-lang.org link with the tab's code in it, no gist needed
+    Permalink(Id),
+    // run the tab's code through rustfmt
+    Format(Id),
+    // comment out the selected lines of the tab's open file, or uncomment them
+    ToggleComment(Id),
+    // duplicate, move or delete the selected lines of the tab's open file
+    EditLines(Id, LineEdit),
+    // move the cursor of the tab's open file to the bracket matching the one next to it
+    JumpToBracket(Id),
+    // add a cursor on the next place the selection of the tab's open file is in it
+    SelectNextOccurrence(Id),
+    // ask rust-analyzer where what's at the cursor of the tab's main.rs is defined, and go there
+    GoToDefinition(Id),
+    // show the compiler's output for the tab in a new tab
+    Emit(Id, EmitKind),
+    // show the tab's code with its macros expanded, in the tab linked to it
+    Expand(Id),
+    // write the tab out as a cargo project in a folder
+    Export(Id),
+}
+
+/// Where sharing a scratch as a gist is at
+#[derive(Debug)]
+pub enum ShareState {
+    // the options window is open, `update` replaces the gist the tab was shared to before
+    Options {
+        description: String,
+        public: bool,
+        file_name: String,
+        update: bool,
+    },
+    // the gist is being created or updated
+    Sending(Receiver<Result<Gist, GitHubError>>),
+    // the gist's links are shown until the window is closed
+    Done(Gist),
+}
+
+/// Where exporting a scratch as a cargo project is at
+#[derive(Debug)]
+pub enum ExportState {
+    // the options window is open
+    Options {
+        git_init: bool,
+    },
+    // the folder dialog is open, receives None if the user cancelled it
+    Picking {
+        git_init: bool,
+        rx: Receiver<Option<PathBuf>>,
+    },
+    // the folder isn't empty, so the user has to confirm writing into it
+    Confirm {
+        git_init: bool,
+        dir: PathBuf,
+    },
+    // the project is being written, receives the folder once it's done
+    Writing(Receiver<Result<PathBuf, ExportError>>),
 }
 
 #[derive(Debug, Clone)]
 pub enum TabCommand {
     Add(NodeIndex),
-    Close(Id),
+    // clean up after a tab which was closed, the tab is kept for reopening it
+    Close(Box<Tab>),
+    // open a closed tab again, the one closed last unless it's given
+    Reopen(Option<Id>),
     Play(Id),
+    // a Play started by auto-run, which waits for the user's own runs
+    AutoRun(Id),
+    // check the tab with clippy's lints too
+    Clippy(Id),
+    // move the cursor of the file the tab has open to a location, both start at 1
+    GoToLine {
+        tab: Id,
+        line: usize,
+        col: usize,
+    },
+    // build and run the tab's code on play.rust-lang.org instead of here
+    RunOnPlayground(Id),
+    // pick a file with the open dialog, and open it in a new tab
+    Open,
+    // open a file in a new tab, or focus the tab it's already open in
+    OpenFile(PathBuf),
+    // open a new scratch in the focused tab bar, with one of the welcome page's examples in it
+    NewScratch(Option<usize>),
+    // from the welcome page, which can't get to the settings itself
+    OpenSettings,
+    // close the tabs, or exit the app after dealing with their unsaved changes when `exit` is set
+    CloseTabs {
+        tabs: Vec<Id>,
+        unsaved: Unsaved,
+        exit: bool,
+    },
+    // close every tab, asking about the unsaved ones first
+    CloseAll,
+    // close every tab but this one
+    CloseOthers(Id),
+    // close the tabs after this one in its tab bar
+    CloseToRight(Id),
+    // open a copy of the tab next to it
+    Duplicate(Id),
+    // split the tab's group in two, and move the tab or a copy of it into the new one
+    Split {
+        tab: Id,
+        split: Split,
+        copy: bool,
+    },
+    // move the tab over to the other group, when there are two of them
+    MoveToOtherGroup(Id),
+}
+
+/// What closing tabs does with the ones which have unsaved changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unsaved {
+    // ask whether to save them, discard them or cancel the close
+    Ask,
+    // wait for them to be saved, and keep the tabs open if one of them wasn't
+    Save,
+    Discard,
+}
+
+#[derive(Debug, Clone)]
+pub enum TerminalCommand {
+    // move the tab's editor cursor to a location, both start at 1
+    JumpTo { tab: Id, line: usize, col: usize },
+}
+
+// Dialogs stay on screen until the user dismisses them
+#[derive(Debug, Clone)]
+pub enum DialogCommand {
+    Error {
+        id: Id,
+        title: String,
+        message: String,
+    },
+    Info {
+        id: Id,
+        title: String,
+        message: String,
+    },
+}
+
+impl DialogCommand {
+    pub fn error(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Error {
+            id: Id::new(rand::random::<u64>()),
+            title: title.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn info(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Info {
+            id: Id::new(rand::random::<u64>()),
+            title: title.into(),
+            message: message.into(),
+        }
+    }
 }
@@ -0,0 +1,193 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::snippets::{self, Snippet};
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Editor {
+    // run rustfmt on a scratch before saving it
+    pub format_on_save: bool,
+    // typing an opening bracket or quote types its closer after the cursor too
+    pub auto_close_brackets: bool,
+    // a popup of the identifiers in the code while one is being typed
+    pub completion: bool,
+    // how many spaces Tab indents by, unless it indents with tabs
+    pub indent_width: usize,
+    pub indent_with_tabs: bool,
+    // how many steps back each editor can undo
+    pub undo_limit: usize,
+    pub show_whitespace: ShowWhitespace,
+    // a faint line down each level of indentation, brighter for the cursor's scope
+    pub indent_guides: bool,
+    // a scaled down picture of the code down the editor's right side
+    pub minimap: bool,
+    // the types of `let` bindings, guessed from their literals and constructors
+    pub inlay_hints: bool,
+    // modal editing with vim's keys
+    pub vim_mode: bool,
+    pub trim_trailing_whitespace_on_save: bool,
+    pub ensure_final_newline_on_save: bool,
+    // how often the unsaved tabs are written to drafts, which are offered back after a crash
+    pub autosave_interval_secs: u64,
+    // the built in snippets and the user's, read from their own file
+    #[serde(skip_serializing, skip_deserializing)]
+    pub snippets: Vec<Snippet>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub snippets_error: Option<String>,
+    // completions, hovers and going to definitions from rust-analyzer
+    pub lsp: Lsp,
+}
+
+impl Editor {
+    /// How the editors indent, unless their tab says otherwise
+    pub fn indent(&self) -> Indent {
+        Indent {
+            with_tabs: self.indent_with_tabs,
+            width: self.indent_width,
+        }
+    }
+
+    /// Read the snippets again, only the built in ones are left when the user's can't be read
+    pub fn load_snippets(&mut self) {
+        match snippets::load(&snippets::snippets_file()) {
+            Ok(loaded) => {
+                self.snippets = loaded;
+                self.snippets_error = None;
+            }
+            Err(e) => {
+                self.snippets = snippets::builtin();
+                self.snippets_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            format_on_save: false,
+            auto_close_brackets: true,
+            completion: true,
+            indent_width: 4,
+            indent_with_tabs: false,
+            undo_limit: 1000,
+            show_whitespace: ShowWhitespace::Off,
+            indent_guides: true,
+            minimap: false,
+            inlay_hints: false,
+            vim_mode: false,
+            trim_trailing_whitespace_on_save: false,
+            ensure_final_newline_on_save: false,
+            autosave_interval_secs: 30,
+            snippets: snippets::builtin(),
+            snippets_error: None,
+            lsp: Default::default(),
+        }
+    }
+}
+
+/// How Tab indents a line, a tab or a number of spaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Indent {
+    pub with_tabs: bool,
+    pub width: usize,
+}
+
+impl Indent {
+    /// What one level of indentation is
+    pub fn text(self) -> String {
+        if self.with_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.width.max(1))
+        }
+    }
+}
+
+impl fmt::Display for Indent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.with_tabs {
+            write!(f, "Tabs")
+        } else {
+            write!(f, "Spaces: {}", self.width.max(1))
+        }
+    }
+}
+
+/// The rust-analyzer editors ask about the code, which isn't run unless it's turned on
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lsp {
+    pub enabled: bool,
+    // the program to run, looked for on the PATH unless it's a path
+    pub program: String,
+    // why it couldn't be started, it isn't tried again until it's turned back on
+    #[serde(skip_serializing, skip_deserializing)]
+    pub error: Option<String>,
+}
+
+impl Default for Lsp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            program: "rust-analyzer".to_string(),
+            error: None,
+        }
+    }
+}
+
+/// Where the editor draws dots for spaces and arrows for tabs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShowWhitespace {
+    #[default]
+    Off,
+    // only in what's selected
+    Selection,
+    All,
+}
+
+impl ShowWhitespace {
+    pub const ALL: [ShowWhitespace; 3] = [
+        ShowWhitespace::Off,
+        ShowWhitespace::Selection,
+        ShowWhitespace::All,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShowWhitespace::Off => "Off",
+            ShowWhitespace::Selection => "In selection",
+            ShowWhitespace::All => "Everywhere",
+        }
+    }
+}
+
+/// A change to the lines the selection is on, or the cursor's line when nothing is selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEdit {
+    Duplicate,
+    MoveUp,
+    MoveDown,
+    Delete,
+}
+
+/// The find bar of a tab's editor. What was searched for is kept while the app is open
+#[derive(Debug, Default, Clone)]
+pub struct EditorSearch {
+    pub open: bool,
+    // show the replace row too
+    pub replace: bool,
+    pub query: String,
+    pub replacement: String,
+    pub case_sensitive: bool,
+    pub regex: bool,
+    // index of the selected match
+    pub current: usize,
+    // the selected match changed, so it should be scrolled into view
+    pub scroll_to_current: bool,
+    // give the search box focus on the next frame
+    pub focus: bool,
+}
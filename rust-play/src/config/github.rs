@@ -1,94 +1,381 @@
+use reqwest::Method;
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::sync::mpsc::{channel, Receiver};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+#[cfg(target_os = "windows")]
+use crate::os::windows::credentials;
+
+/// What a gist's description starts out as when sharing a tab
+pub const DEFAULT_DESCRIPTION: &str =
+    "Created by Rust Play <https://github.com/MolotovCherry/RustPlay>";
+
+/// The id of the GitHub app signing in goes through, which needs the device flow enabled. It's
+/// given when building, builds without one can only use a pasted token
+pub const CLIENT_ID: Option<&str> = option_env!("RUST_PLAY_GITHUB_CLIENT_ID");
+// where the token is kept in the credential manager
+#[cfg(target_os = "windows")]
+const CREDENTIAL_TARGET: &str = "RustPlay/GitHub";
+
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GitHub {
+    // a pasted personal access token, or the signed in one when the os couldn't store it
     pub access_token: String,
+    // who signed in, None when they didn't or pasted a token
+    pub username: Option<String>,
+    // github turned the token down, so it has to be signed in again
+    #[serde(skip)]
+    pub expired: bool,
 }
 
 #[derive(Debug, Error)]
 pub enum GitHubError {
-    #[error("No access token found")]
+    #[error("Not signed in. Sign in to GitHub in the settings first")]
     NoAuthentication,
+    #[error("GitHub turned down the sign-in, it expired or was revoked. Sign in again")]
+    Unauthorized,
     #[error("Forbidden")]
     Forbidden,
     #[error("Resource not found")]
     NotFound,
     #[error("Validation failed, or the endpoint has been spammed.")]
     ValidationFailed,
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("The code expired before it was entered, sign in again")]
+    CodeExpired,
+    #[error("Signing in was cancelled on GitHub")]
+    AccessDenied,
+    #[error("This build can't sign in to GitHub, paste a personal access token instead")]
+    NoClientId,
     #[error("Unknnown error occurred")]
     Unknown,
 }
 
+/// The code the user enters on GitHub to sign in
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    // in seconds
+    expires_in: u64,
+    interval: u64,
+}
+
+// what polling for the token gets back, the token once the code was entered
+#[derive(Deserialize)]
+struct TokenReply {
+    access_token: Option<String>,
+    error: Option<String>,
+    // how long to wait between polls from now on, when polling too often
+    interval: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+#[derive(Debug)]
+pub enum SignInEvent {
+    // the code to show the user, while waiting for them to enter it
+    Code(DeviceCode),
+    SignedIn { token: String, username: String },
+    Failed(GitHubError),
+}
+
+/// A sign-in going on in the background, it's cancelled when dropped
+pub struct SignIn {
+    pub events: Receiver<SignInEvent>,
+    // the code to enter, once GitHub made one
+    pub code: Option<DeviceCode>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for SignIn {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
 impl GitHub {
-    /// Creates a new github gist using a title and content
+    /// The token requests are made with, the signed in one or the pasted one
+    pub fn token(&self) -> Option<String> {
+        #[cfg(target_os = "windows")]
+        if let Some(token) = credentials::read(CREDENTIAL_TARGET) {
+            return Some(token);
+        }
+
+        (!self.access_token.is_empty()).then(|| self.access_token.clone())
+    }
+
+    /// Whether there's a token to make requests with, which github may still turn down
+    pub fn signed_in(&self) -> bool {
+        self.username.is_some() || !self.access_token.is_empty()
+    }
+
+    /// Keep the token of a sign-in, in the os's credential manager when it can be
+    pub fn store_token(&mut self, token: String, username: String) {
+        #[cfg(target_os = "windows")]
+        let stored = credentials::write(CREDENTIAL_TARGET, &username, &token);
+        #[cfg(not(target_os = "windows"))]
+        let stored = false;
+
+        self.access_token = if stored { String::new() } else { token };
+        self.username = Some(username);
+        self.expired = false;
+    }
+
+    pub fn sign_out(&mut self) {
+        #[cfg(target_os = "windows")]
+        credentials::delete(CREDENTIAL_TARGET);
+
+        self.access_token.clear();
+        self.username = None;
+        self.expired = false;
+    }
+
+    /// Start signing in with GitHub's device flow: it hands out a code, which the user enters on
+    /// github.com while this polls for the token it then gives out
+    pub fn sign_in() -> SignIn {
+        let (tx, rx) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let thread_cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let event = match Self::device_flow(&tx, &thread_cancelled) {
+                Ok(Some((token, username))) => SignInEvent::SignedIn { token, username },
+                Ok(None) => return,
+                Err(e) => SignInEvent::Failed(e),
+            };
+
+            let _ = tx.send(event);
+        });
+
+        SignIn {
+            events: rx,
+            code: None,
+            cancelled,
+        }
+    }
+
+    // The token and who it's for, None when it was cancelled
+    fn device_flow(
+        tx: &Sender<SignInEvent>,
+        cancelled: &AtomicBool,
+    ) -> Result<Option<(String, String)>, GitHubError> {
+        let client_id = CLIENT_ID.ok_or(GitHubError::NoClientId)?;
+        let client = reqwest::blocking::Client::new();
+
+        let reply = client
+            .post("https://github.com/login/device/code")
+            .header("User-Agent", "RustPlay")
+            .header("accept", "application/json")
+            .form(&[("client_id", client_id), ("scope", "gist")])
+            .send()?
+            .text()?;
+        let code = serde_json::from_str::<DeviceCode>(&reply).map_err(|_| GitHubError::Unknown)?;
+
+        let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+        let mut interval = code.interval;
+        let device_code = code.device_code.clone();
+        let _ = tx.send(SignInEvent::Code(code));
+
+        let token = loop {
+            thread::sleep(Duration::from_secs(interval));
+
+            if cancelled.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            if Instant::now() >= deadline {
+                return Err(GitHubError::CodeExpired);
+            }
+
+            let reply = client
+                .post("https://github.com/login/oauth/access_token")
+                .header("User-Agent", "RustPlay")
+                .header("accept", "application/json")
+                .form(&[
+                    ("client_id", client_id),
+                    ("device_code", &device_code),
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ])
+                .send()?
+                .text()?;
+            let reply =
+                serde_json::from_str::<TokenReply>(&reply).map_err(|_| GitHubError::Unknown)?;
+
+            match (reply.access_token, reply.error.as_deref()) {
+                (Some(token), _) => break token,
+                // the user didn't enter the code yet
+                (None, Some("authorization_pending")) => (),
+                (None, Some("slow_down")) => interval = reply.interval.unwrap_or(interval + 5),
+                (None, Some("expired_token")) => return Err(GitHubError::CodeExpired),
+                (None, Some("access_denied")) => return Err(GitHubError::AccessDenied),
+                _ => return Err(GitHubError::Unknown),
+            }
+        };
+
+        // who signed in, for the settings to show
+        let reply = client
+            .get("https://api.github.com/user")
+            .header("User-Agent", "RustPlay")
+            .header("accept", "application/vnd.github+json")
+            .bearer_auth(&token)
+            .send()?
+            .text()?;
+        let user = serde_json::from_str::<User>(&reply).map_err(|_| GitHubError::Unknown)?;
+
+        Ok(Some((token, user.login)))
+    }
+
+    /// Creates a new github gist from files, given by name and content
     /// Does not block, but instead returns a receiver you can use to receive it
-    pub fn create_gist(&self, content: &str) -> Receiver<Result<String, GitHubError>> {
+    pub fn create_gist(
+        &self,
+        files: &[(String, String)],
+        description: &str,
+        public: bool,
+    ) -> Receiver<Result<Gist, GitHubError>> {
+        let body = json!({
+            "description": description,
+            "public": public,
+            "files": gist_files(files, &[]),
+        });
+
+        self.send_gist(
+            Method::POST,
+            "https://api.github.com/gists".to_string(),
+            body,
+        )
+    }
+
+    /// Replaces the files of a gist which was created before, `removed` are the files it had
+    /// which are left out now. Its visibility can't be changed
+    pub fn update_gist(
+        &self,
+        id: &str,
+        files: &[(String, String)],
+        removed: &[String],
+        description: &str,
+    ) -> Receiver<Result<Gist, GitHubError>> {
+        let body = json!({
+            "description": description,
+            "files": gist_files(files, removed),
+        });
+
+        self.send_gist(
+            Method::PATCH,
+            format!("https://api.github.com/gists/{id}"),
+            body,
+        )
+    }
+
+    fn send_gist(
+        &self,
+        method: Method,
+        url: String,
+        body: serde_json::Value,
+    ) -> Receiver<Result<Gist, GitHubError>> {
         let (tx, rx) = channel();
 
         // Error out immediately if no access token was provided
-        if self.access_token.is_empty() {
+        let Some(access_token) = self.token() else {
             let _ = tx.send(Err(GitHubError::NoAuthentication));
             return rx;
-        }
+        };
 
-        let access_token = self.access_token.clone();
-        let content = content.to_owned();
-
-        std::thread::spawn(move || {
+        thread::spawn(move || {
             let client = reqwest::blocking::Client::new();
 
-            let body = json!({
-                "description": "Created by Rust Play <https://github.com/MolotovCherry/RustPlay>",
-                "public": true,
-                "files": {
-                    "playground.rs": {"content": content}
-                }
-            })
-            .to_string();
-
             let result = client
-                .post("https://api.github.com/gists")
+                .request(method, url)
                 .header("User-Agent", "RustPlay")
                 .header("accept", "application/vnd.github+json")
                 .bearer_auth(access_token)
-                .body(body)
+                .body(body.to_string())
                 .send();
 
             let reply = match result {
                 Ok(v) => v,
                 Err(e) => {
-                    if e.is_status() {
-                        let code = e.status().unwrap().as_u16();
-                        let error = match code {
-                            403 => GitHubError::Forbidden,
-                            404 => GitHubError::NotFound,
-                            422 => GitHubError::ValidationFailed,
-                            _ => GitHubError::Unknown,
-                        };
-
-                        let _ = tx.send(Err(error));
-                        return;
-                    }
-
-                    let _ = tx.send(Err(GitHubError::Unknown));
+                    let _ = tx.send(Err(GitHubError::Network(e)));
                     return;
                 }
             };
 
-            let reply = serde_json::from_str::<GitHubReply>(&reply.text().unwrap())
-                .expect("Failed to unwrap github reply");
+            let error = match reply.status().as_u16() {
+                200..=299 => None,
+                401 => Some(GitHubError::Unauthorized),
+                403 => Some(GitHubError::Forbidden),
+                404 => Some(GitHubError::NotFound),
+                422 => Some(GitHubError::ValidationFailed),
+                _ => Some(GitHubError::Unknown),
+            };
+
+            if let Some(error) = error {
+                let _ = tx.send(Err(error));
+                return;
+            }
+
+            let reply = reply
+                .text()
+                .ok()
+                .and_then(|text| serde_json::from_str::<Gist>(&text).ok());
 
-            let _ = tx.send(Ok(reply.id));
+            let Some(reply) = reply else {
+                let _ = tx.send(Err(GitHubError::Unknown));
+                return;
+            };
+
+            let _ = tx.send(Ok(reply));
         });
 
         rx
     }
 }
 
+// The files as the gists api takes them, a file set to null is deleted from the gist
+fn gist_files(files: &[(String, String)], removed: &[String]) -> serde_json::Map<String, Value> {
+    let mut gist_files = removed
+        .iter()
+        .map(|name| (name.clone(), Value::Null))
+        .collect::<serde_json::Map<_, _>>();
+
+    gist_files.extend(
+        files
+            .iter()
+            .map(|(name, content)| (name.clone(), json!({ "content": content }))),
+    );
+
+    gist_files
+}
+
+/// A gist a tab was shared to, so sharing the tab again can update it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedGist {
+    pub id: String,
+    // the names of the files it has
+    pub files: Vec<String>,
+    pub public: bool,
+}
+
+/// A gist which was created
 #[derive(Debug, Deserialize)]
-struct GitHubReply {
-    id: String,
+pub struct Gist {
+    pub id: String,
+    pub public: bool,
+    // the gist's page on github
+    pub html_url: String,
+    // the names of its files
+    pub files: HashMap<String, IgnoredAny>,
 }
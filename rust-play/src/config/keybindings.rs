@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use egui::{Key, Modifiers};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::utils::key_combo::{ComboKey, KeyCombo};
+
+/// Something a keyboard shortcut does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Run,
+    Stop,
+    Format,
+    ToggleComment,
+    DuplicateLines,
+    MoveLinesUp,
+    MoveLinesDown,
+    DeleteLines,
+    JumpToBracket,
+    SelectNextOccurrence,
+    GoToDefinition,
+    Find,
+    Replace,
+    ToggleTerminal,
+    NewTab,
+    CloseTab,
+    ReopenTab,
+    OpenFile,
+    CommandPalette,
+    QuickOpen,
+    GoToLine,
+    NextTab,
+    PreviousTab,
+}
+
+impl Action {
+    /// In the order the settings list them
+    pub const ALL: [Action; 23] = [
+        Action::Run,
+        Action::Stop,
+        Action::Format,
+        Action::ToggleComment,
+        Action::DuplicateLines,
+        Action::MoveLinesUp,
+        Action::MoveLinesDown,
+        Action::DeleteLines,
+        Action::JumpToBracket,
+        Action::SelectNextOccurrence,
+        Action::GoToDefinition,
+        Action::Find,
+        Action::Replace,
+        Action::ToggleTerminal,
+        Action::NewTab,
+        Action::CloseTab,
+        Action::ReopenTab,
+        Action::OpenFile,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::CommandPalette,
+        Action::QuickOpen,
+        Action::GoToLine,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Run => "Run",
+            Action::Stop => "Stop",
+            Action::Format => "Format",
+            Action::ToggleComment => "Toggle line comment",
+            Action::DuplicateLines => "Duplicate lines",
+            Action::MoveLinesUp => "Move lines up",
+            Action::MoveLinesDown => "Move lines down",
+            Action::DeleteLines => "Delete lines",
+            Action::JumpToBracket => "Go to matching bracket",
+            Action::SelectNextOccurrence => "Select next occurrence",
+            Action::GoToDefinition => "Go to definition",
+            Action::Find => "Find",
+            Action::Replace => "Replace",
+            Action::ToggleTerminal => "Toggle terminal",
+            Action::NewTab => "New tab",
+            Action::CloseTab => "Close tab",
+            Action::ReopenTab => "Reopen closed tab",
+            Action::OpenFile => "Open file",
+            Action::CommandPalette => "Command palette",
+            Action::QuickOpen => "Go to tab",
+            Action::GoToLine => "Go to line",
+            Action::NextTab => "Next tab",
+            Action::PreviousTab => "Previous tab",
+        }
+    }
+
+    /// Whether the action edits or moves around in the editor's code, so its shortcut is only
+    /// taken from the editor
+    pub fn acts_on_editor(self) -> bool {
+        matches!(
+            self,
+            Action::ToggleComment
+                | Action::DuplicateLines
+                | Action::MoveLinesUp
+                | Action::MoveLinesDown
+                | Action::DeleteLines
+                | Action::JumpToBracket
+                | Action::SelectNextOccurrence
+                | Action::GoToDefinition
+        )
+    }
+
+    pub fn default_combo(self) -> KeyCombo {
+        match self {
+            Action::Run => KeyCombo::new(Key::Enter).ctrl(),
+            Action::Stop => KeyCombo::new(Key::F5).shift(),
+            Action::Format => KeyCombo::new(Key::F).ctrl().shift(),
+            Action::ToggleComment => KeyCombo::pressed(Modifiers::COMMAND, ComboKey::Slash),
+            Action::DuplicateLines => KeyCombo::new(Key::D).ctrl().shift(),
+            Action::MoveLinesUp => KeyCombo::new(Key::ArrowUp).alt(),
+            Action::MoveLinesDown => KeyCombo::new(Key::ArrowDown).alt(),
+            Action::DeleteLines => KeyCombo::new(Key::K).ctrl().shift(),
+            Action::JumpToBracket => KeyCombo::new(Key::M).ctrl(),
+            Action::SelectNextOccurrence => KeyCombo::new(Key::D).ctrl(),
+            Action::GoToDefinition => KeyCombo::new(Key::F12),
+            Action::Find => KeyCombo::new(Key::F).ctrl(),
+            Action::Replace => KeyCombo::new(Key::H).ctrl(),
+            Action::ToggleTerminal => KeyCombo::pressed(Modifiers::COMMAND, ComboKey::Backtick),
+            Action::NewTab => KeyCombo::new(Key::T).ctrl(),
+            Action::CloseTab => KeyCombo::new(Key::W).ctrl(),
+            Action::ReopenTab => KeyCombo::new(Key::T).ctrl().shift(),
+            Action::OpenFile => KeyCombo::new(Key::O).ctrl(),
+            Action::CommandPalette => KeyCombo::new(Key::P).ctrl().shift(),
+            Action::QuickOpen => KeyCombo::new(Key::P).ctrl(),
+            Action::GoToLine => KeyCombo::new(Key::G).ctrl(),
+            Action::NextTab => KeyCombo::new(Key::PageDown).ctrl(),
+            Action::PreviousTab => KeyCombo::new(Key::PageUp).ctrl(),
+        }
+    }
+}
+
+/// The shortcut each action is bound to, written to the settings file as `action = "Ctrl+Key"`
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct Keybindings(pub HashMap<Action, KeyCombo>);
+
+impl Keybindings {
+    /// The action's shortcut, its default one when the settings file didn't have it
+    pub fn combo(&self, action: Action) -> KeyCombo {
+        self.0
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| action.default_combo())
+    }
+
+    /// The other actions bound to the same shortcut as this one
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+        let combo = self.combo(action);
+
+        Action::ALL
+            .into_iter()
+            .filter(|other| *other != action && self.combo(*other) == combo)
+            .collect()
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self(
+            Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_combo()))
+                .collect(),
+        )
+    }
+}
+
+// Actions and shortcuts which can't be read, say from a newer version or a typo, are left out
+// instead of failing the whole settings file, those actions keep their default shortcut
+impl<'de> Deserialize<'de> for Keybindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = HashMap::<String, String>::deserialize(deserializer)?;
+
+        let mut keybindings = Self::default();
+        for (action, combo) in raw {
+            let action: Result<_, serde::de::value::Error> =
+                Action::deserialize(action.as_str().into_deserializer());
+
+            if let (Ok(action), Ok(combo)) = (action, combo.parse()) {
+                keybindings.0.insert(action, combo);
+            }
+        }
+
+        Ok(keybindings)
+    }
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::log_filter::LogLevel;
+
+/// What the app records of its own events, in the logs view and the log file
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Logging {
+    // the most verbose events which are recorded
+    pub level: LogLevel,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+        }
+    }
+}
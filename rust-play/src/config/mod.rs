@@ -1,12 +1,34 @@
 #[allow(clippy::module_inception)]
 mod config;
+mod crates_io;
 mod dock;
+mod editor;
 mod github;
+mod keybindings;
+mod logging;
+mod picker;
+mod recent;
+mod runner;
+mod session;
+mod settings;
+mod status_bar;
 mod terminal;
 mod theme;
+mod ui;
 
 pub use config::*;
+pub use crates_io::*;
 pub use dock::*;
+pub use editor::*;
 pub use github::*;
+pub use keybindings::*;
+pub use logging::*;
+pub use picker::*;
+pub use recent::*;
+pub use runner::*;
+pub use session::*;
+pub use settings::*;
+pub use status_bar::*;
 pub use terminal::*;
 pub use theme::*;
+pub use ui::*;
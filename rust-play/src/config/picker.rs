@@ -0,0 +1,19 @@
+/// The state of the command palette or the quick open, both are pickers over a list
+#[derive(Default)]
+pub struct PickerWindow {
+    pub open: bool,
+    // what was typed, kept after the picker closes for the next time it's opened
+    pub query: String,
+    // the highlighted item, an index into the ones which match
+    pub selected: usize,
+}
+
+/// The state of the input for going to a line in the focused tab
+#[derive(Default)]
+pub struct GoToLineWindow {
+    pub open: bool,
+    // `line` or `line:column`
+    pub query: String,
+    // Enter was pressed on something which isn't a location
+    pub invalid: bool,
+}
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// How many files File > Open Recent remembers, besides the pinned ones
+pub const RECENT_FILES: usize = 15;
+
+/// The files which were opened or saved lately, the latest first
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentFiles {
+    // kept at the top of the list, and never pushed out of it
+    pub pinned: Vec<PathBuf>,
+    pub recent: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    /// Move a file to the top of the list, or add it there
+    pub fn add(&mut self, path: &Path) {
+        if self.pinned.iter().any(|pinned| pinned == path) {
+            return;
+        }
+
+        self.recent.retain(|recent| recent != path);
+        self.recent.insert(0, path.to_path_buf());
+        self.recent.truncate(RECENT_FILES);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.pinned.retain(|pinned| pinned != path);
+        self.recent.retain(|recent| recent != path);
+    }
+
+    pub fn is_pinned(&self, path: &Path) -> bool {
+        self.pinned.iter().any(|pinned| pinned == path)
+    }
+
+    /// Pin a file, or unpin it back to the top of the recent ones
+    pub fn toggle_pin(&mut self, path: &Path) {
+        if self.is_pinned(path) {
+            self.pinned.retain(|pinned| pinned != path);
+            self.add(path);
+        } else {
+            self.recent.retain(|recent| recent != path);
+            self.pinned.push(path.to_path_buf());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pinned.is_empty() && self.recent.is_empty()
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// How long a run may take when neither the settings nor the tab say otherwise
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+/// How long the code has to stay the same before it's run, when running it after typing stops
+pub const DEFAULT_IDLE_MS: u64 = 1000;
+
+/// When a tab's code is run without pressing Play
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AutoRun {
+    #[default]
+    Off,
+    // whenever the scratch is saved
+    OnSave,
+    // once the code has stayed the same for this many milliseconds
+    OnIdle(u64),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Runner {
+    // programs still running after this long are stopped, None to let them run forever
+    pub timeout_secs: Option<u64>,
+    pub auto_run: AutoRun,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self {
+            timeout_secs: Some(DEFAULT_TIMEOUT_SECS),
+            auto_run: AutoRun::Off,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs;
+
+use egui::Id;
+use egui_dock::{Node, NodeIndex};
+use serde::{Deserialize, Serialize};
+
+use super::{Config, OutputChunk, PastRun};
+use crate::utils::run_history;
+use crate::widgets::dock::Tree;
+use crate::widgets::terminal::Terminal;
+
+// a tab's earlier runs are only saved while they fit in this, the newest first
+const SAVED_HISTORY_BYTES: usize = 1024 * 1024;
+
+/// Everything that's restored the next time the app starts. Processes don't outlive the app, so
+/// only what they printed is kept of them. It's saved with the tree borrowed, and loaded with it
+/// owned
+#[derive(Serialize, Deserialize)]
+pub struct Session<T = Tree> {
+    pub tree: T,
+    // how many scratches were made, so restored tabs don't share ids with new ones
+    pub counter: u32,
+    // the tab shown in the terminal
+    pub active_tab: Option<Id>,
+    pub output: Vec<(Id, Vec<OutputChunk>)>,
+    // each tab's earlier runs, the oldest first
+    #[serde(default)]
+    pub history: Vec<(Id, Vec<PastRun>)>,
+}
+
+impl<'a> Session<&'a Tree> {
+    pub fn new(config: &'a Config) -> Self {
+        let mut history = config
+            .terminal
+            .history
+            .iter()
+            .map(|(id, runs)| {
+                let kept = run_history::within_budget(runs, PastRun::bytes, SAVED_HISTORY_BYTES);
+                (
+                    *id,
+                    runs.iter()
+                        .skip(runs.len() - kept)
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        // in the tabs' order, so the same history is always written the same way
+        history.sort_by_key(|(id, _)| config.dock.tree.tabs().position(|tab| tab.id == *id));
+
+        Self {
+            output: Terminal::output(config),
+            history,
+            ..Self::layout(config)
+        }
+    }
+
+    /// The session without any output, to tell whether anything else changed
+    pub fn layout(config: &'a Config) -> Self {
+        Self {
+            tree: &config.dock.tree,
+            counter: config.dock.counter,
+            active_tab: config.terminal.active_tab,
+            output: vec![],
+            history: vec![],
+        }
+    }
+}
+
+impl Session {
+    /// Put the session's tabs and output back. A session without tabs is left out, the default
+    /// tree is used instead
+    pub fn restore(self, config: &mut Config) {
+        let mut tree = self.tree;
+        if tree.num_tabs() == 0 {
+            return;
+        }
+
+        if tree.focused_leaf().is_none() {
+            if let Some(leaf) = tree.iter().position(Node::is_leaf) {
+                tree.set_focused_node(NodeIndex(leaf));
+            }
+        }
+
+        for node in tree.iter_mut() {
+            let Node::Leaf { tabs, .. } = node else {
+                continue;
+            };
+
+            for tab in tabs.iter_mut().filter(|tab| tab.files.is_empty()) {
+                let Some(code) = tab
+                    .path
+                    .as_ref()
+                    .and_then(|path| fs::read_to_string(path).ok())
+                else {
+                    continue;
+                };
+
+                // edits which weren't saved to the file are still unsaved
+                if code != tab.editor.code {
+                    tab.check.revision += 1;
+                }
+                tab.disk_code = Some(code);
+            }
+        }
+
+        let tabs = tree.tabs().map(|tab| tab.id).collect::<Vec<_>>();
+
+        for (id, output) in self.output {
+            if tabs.contains(&id) {
+                // the terminal only keeps the output of tabs which have content
+                config.terminal.content.insert(id, None);
+                config.terminal.messages.insert(id, output);
+            }
+        }
+
+        for (id, runs) in self.history {
+            if tabs.contains(&id) {
+                // the runs keep their numbers, new ones are numbered after them
+                let next = runs
+                    .iter()
+                    .map(|run| run.number + 1)
+                    .max()
+                    .unwrap_or_default();
+                config.terminal.past_runs = config.terminal.past_runs.max(next);
+                config.terminal.history.insert(id, runs.into());
+            }
+        }
+
+        config.terminal.active_tab = self.active_tab.filter(|id| tabs.contains(id));
+        config.dock.tree = tree;
+        config.dock.counter = self.counter;
+    }
+}
@@ -0,0 +1,32 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+
+use super::{Action, SignIn, ThemeConfig};
+
+#[derive(Default)]
+pub struct SettingsWindow {
+    pub open: bool,
+    // the theme being edited, it's only written to the config when applied
+    pub theme: Option<ThemeConfig>,
+    // the color scheme last loaded into the edited theme, and the keys it was missing
+    pub loaded_scheme: Option<(String, Vec<&'static str>)>,
+    // why the last import failed
+    pub import_error: Option<String>,
+    // the file dialog picking a scheme to import is open, and sends the picked file when it closes
+    pub import_dialog: Option<Receiver<Option<PathBuf>>>,
+    // the build cache is being deleted, and sends how many bytes it freed when it's done
+    pub cleaning: Option<Receiver<io::Result<u64>>>,
+    // what the last clean freed, or why it failed
+    pub clean_result: Option<Result<u64, String>>,
+    // the action whose shortcut is being recorded, the next key pressed is bound to it
+    pub recording: Option<Action>,
+    // why the last key pressed while recording couldn't be bound
+    pub recording_error: Option<&'static str>,
+    // signing in to github is going on, it's cancelled when the window closes
+    pub sign_in: Option<SignIn>,
+    // why the last sign-in failed
+    pub sign_in_error: Option<String>,
+    // a personal access token being pasted, instead of signing in
+    pub pasted_token: String,
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// What the status bar along the bottom of the window shows of the focused editor. It's left out
+/// when it would be empty
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusBar {
+    // the cursor's line:column, clicking it goes to a line
+    pub position: bool,
+    // how many chars and lines are selected, while something is
+    pub selection: bool,
+    // the tab's edition and channel
+    pub run_config: bool,
+    // spaces or tabs and how wide, clicking it changes them for the tab
+    pub indent: bool,
+}
+
+impl StatusBar {
+    pub fn is_empty(&self) -> bool {
+        !(self.position || self.selection || self.run_config || self.indent)
+    }
+}
+
+impl Default for StatusBar {
+    fn default() -> Self {
+        Self {
+            position: true,
+            selection: true,
+            run_config: true,
+            indent: true,
+        }
+    }
+}
@@ -1,32 +1,421 @@
-use egui::Vec2;
-use ringbuf::{Consumer, HeapRb};
-use std::collections::HashMap;
-use std::sync::Arc;
+use egui::{FontFamily, FontId, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use egui::Id;
 
-pub type TermOutput = Consumer<String, Arc<HeapRb<String>>>;
+use super::RunConfig;
+use crate::utils::log_filter::LogLevel;
+use crate::utils::test_results::TestCounts;
+use crate::utils::timestamp::Timestamp;
 
-#[derive(Default)]
+// output of both streams, in the order it arrived
+pub type TermOutput = Receiver<OutputChunk>;
+// how many chunks of a tab's output can wait for the terminal to take them
+pub const OUTPUT_BUFFER: usize = 60;
+// dropping this signals the running process to be killed
+pub type Aborter = Arc<Mutex<Sender<()>>>;
+
+pub const DEFAULT_FONT_SIZE: f32 = 12.0;
+pub const MIN_FONT_SIZE: f32 = 6.0;
+pub const MAX_FONT_SIZE: f32 = 48.0;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Terminal {
-    // the arc mutex string holds access to the terminal buffer
-    // first is stdout, second is stderr
-    pub content: HashMap<Id, Option<(TermOutput, TermOutput)>>,
+    // clear the previous output when starting a new run
+    pub clear_on_run: bool,
+    // the oldest lines of a tab's output are dropped once it gets bigger than this
+    pub max_scrollback_bytes: usize,
+    // how many of a tab's earlier runs are kept to look back at, once clearing on run drops them
+    pub history_depth: usize,
+    // show stdout and stderr interleaved in one view, instead of each in their own
+    pub merge_streams: bool,
+    // when off, long lines are scrolled sideways instead
+    pub wrap_lines: bool,
+    // show the time each line of output arrived next to it
+    pub show_timestamps: bool,
+    pub font_size: f32,
+    pub font_family: FontFamily,
+    // put the selected output on the clipboard as soon as it's selected
+    pub copy_on_select: bool,
+    pub open: bool,
+    // the panel height the user last left it at
+    pub height: Option<f32>,
+
+    // Runtime data, not persisted
+    //
+    // the ring buffer the running process's output is streamed through
+    #[serde(skip)]
+    pub content: HashMap<Id, Option<TermOutput>>,
     // the first Id is simply the tab id, the second is the abort ctx tmp Id
     //
     // this holds access to an abort process signal in ctx tmp memory
     // just remove the tmp ctx entry to drop it
     // the entry is type Arc<Mutex<Sender<()>>>
+    #[serde(skip)]
     pub abortable: HashMap<Id, Id>,
-    // lets terminal know this is a new run
-    pub started_run: bool,
-    pub open: bool,
-    pub scroll_offset: HashMap<Id, Vec2>,
+    // the state of the last run of each tab, shared with the thread running it
+    #[serde(skip)]
+    pub run_state: HashMap<Id, Arc<Mutex<RunState>>>,
+    // sends input to the running process's stdin. Dropping it closes stdin
+    #[serde(skip)]
+    pub stdin: HashMap<Id, Sender<String>>,
+    // the input line's text
+    #[serde(skip)]
+    pub input: String,
+    // messages from rust play itself rather than a process, like rustfmt's errors. They're added to the
+    // tab's output the next time it's shown
+    #[serde(skip)]
+    pub messages: HashMap<Id, Vec<OutputChunk>>,
+    // text pasted from the output's context menu, sent to stdin on the next frame
+    #[serde(skip)]
+    pub paste: Option<String>,
+    // tabs whose output should be cleared on the next frame
+    #[serde(skip)]
+    pub clear: HashSet<Id>,
+    #[serde(skip)]
+    pub search: TerminalSearch,
+    // whether the pointer was over the output last frame, finding then searches it
+    #[serde(skip)]
+    pub hovered: bool,
+    #[serde(skip)]
+    pub filter: TerminalFilter,
+    // the tabs which started a new run since the terminal was last shown, with the exit code of
+    // the run it replaced
+    #[serde(skip)]
+    pub started_runs: HashMap<Id, Option<i32>>,
+    // each tab's earlier runs, the oldest first
+    #[serde(skip)]
+    pub history: HashMap<Id, VecDeque<PastRun>>,
+    // the earlier run a tab shows instead of its latest one, by its number
+    #[serde(skip)]
+    pub viewed_run: HashMap<Id, u64>,
+    // how many runs were put in the history, to number the next one
+    #[serde(skip)]
+    pub past_runs: u64,
+    // the panel was opened without dragging it, so it should open at the height it was last left at
+    #[serde(skip)]
+    pub restore_height: bool,
+    // whether the panel was being resized last frame
+    #[serde(skip)]
+    pub resizing: bool,
+    // keyed by the tab, whether the streams are merged and whether the output is filtered, so each view
+    // keeps its own position
+    #[serde(skip)]
+    pub scroll_offset: HashMap<(Id, bool, bool), Vec2>,
+    // whether each tab's view follows new output
+    #[serde(skip)]
+    pub follow: HashMap<Id, Follow>,
+    // the tab whose output is shown
+    #[serde(skip)]
     pub active_tab: Option<Id>,
+    // the dock's selected tab. Selecting another one shows its output
+    #[serde(skip)]
+    pub focused_tab: Option<Id>,
+    // whether the panel shows the output, the check's problems or the app's log
+    #[serde(skip)]
+    pub view: TerminalView,
+    // the logs view hides the events more verbose than this
+    #[serde(skip)]
+    pub log_level: Option<LogLevel>,
+    // index of the selected row of the problems list, counting across all the tabs
+    #[serde(skip)]
+    pub selected_problem: Option<usize>,
+    #[serde(skip)]
     pub opened_from_close: bool,
+    #[serde(skip)]
     pub opened_from_close_dragging: bool,
-    pub closed_from_open: bool,
-    // keep track of the last valid index before dynamic output was added in stderr
-    // (unstripped, stripped)
-    pub dynamic_index: (usize, usize),
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Self {
+            clear_on_run: true,
+            max_scrollback_bytes: 4 * 1024 * 1024,
+            history_depth: 5,
+            merge_streams: false,
+            wrap_lines: true,
+            show_timestamps: false,
+            font_size: DEFAULT_FONT_SIZE,
+            font_family: FontFamily::Monospace,
+            copy_on_select: false,
+            open: false,
+            height: None,
+            content: Default::default(),
+            abortable: Default::default(),
+            run_state: Default::default(),
+            stdin: Default::default(),
+            input: Default::default(),
+            messages: Default::default(),
+            paste: None,
+            clear: Default::default(),
+            search: Default::default(),
+            hovered: false,
+            filter: Default::default(),
+            started_runs: Default::default(),
+            history: Default::default(),
+            viewed_run: Default::default(),
+            past_runs: 0,
+            restore_height: false,
+            resizing: false,
+            scroll_offset: Default::default(),
+            follow: Default::default(),
+            active_tab: None,
+            focused_tab: None,
+            view: TerminalView::default(),
+            log_level: None,
+            selected_problem: None,
+            opened_from_close: false,
+            opened_from_close_dragging: false,
+        }
+    }
+}
+
+// Which output stream of the process some text came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+    // input the user sent, echoed into the output. It's shown along with stdout
+    Stdin,
+}
+
+// What the bottom panel shows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalView {
+    #[default]
+    Output,
+    // the diagnostics of the background check, for every tab
+    Problems,
+    // the app's own log
+    Logs,
+}
+
+// Real terminals only scroll to new output when they're already at the bottom
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Follow {
+    // the user scrolled up away from the new output
+    pub paused: bool,
+    // output came in while paused
+    pub unread: bool,
+}
+
+// A piece of a process's output, usually a line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputChunk {
+    pub stream: Stream,
+    pub text: String,
+    pub time: Timestamp,
+}
+
+impl OutputChunk {
+    pub fn new(stream: Stream, text: String) -> Self {
+        Self {
+            stream,
+            text,
+            time: Timestamp::now(),
+        }
+    }
+}
+
+// A run from before a tab's latest one, kept so what it printed can still be looked at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PastRun {
+    // tells a tab's runs apart
+    pub number: u64,
+    // what was run, as it was printed
+    pub command_line: Option<String>,
+    // when its output started and stopped arriving
+    pub started: Timestamp,
+    pub finished: Timestamp,
+    // None when it didn't exit by itself, like when it was stopped
+    pub exit_code: Option<i32>,
+    pub output: Vec<OutputChunk>,
+}
+
+impl PastRun {
+    pub fn bytes(&self) -> usize {
+        self.output.iter().map(|chunk| chunk.text.len()).sum()
+    }
+
+    /// Its time and how it ended, to pick it by
+    pub fn label(&self) -> String {
+        match self.exit_code {
+            Some(code) => format!("{} · exited with code {code}", self.started),
+            None => format!("{} · stopped", self.started),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TerminalSearch {
+    pub open: bool,
+    pub query: String,
+    pub case_sensitive: bool,
+    // index of the selected match, counting in the order the output is shown
+    pub current: usize,
+    // the selected match changed, so it should be scrolled into view
+    pub scroll_to_current: bool,
+    // give the search box focus on the next frame
+    pub focus: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct TerminalFilter {
+    pub open: bool,
+    // a substring, or a regex between slashes
+    pub query: String,
+    // lines logged at a more verbose level than this are hidden
+    pub level: Option<LogLevel>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RunState {
+    pub start: Instant,
+    // how long the process ran for, only set once it's finished
+    pub duration: Option<Duration>,
+    pub status: RunStatus,
+    // what the tab was run with, the tab's configuration may have changed since
+    pub run_config: RunConfig,
+    // the test results so far, for runs in test mode
+    pub tests: Option<TestCounts>,
+    // Miri found undefined behavior
+    pub undefined_behavior: bool,
+    // the size of the .wasm a wasm build made
+    pub wasm_size: Option<u64>,
+    // when the program gets stopped, set once cargo starts it
+    pub deadline: Option<Instant>,
+    // started by auto-run rather than the Play button
+    pub automatic: bool,
+}
+
+impl Default for RunState {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            duration: None,
+            status: RunStatus::Running,
+            run_config: RunConfig::default(),
+            tests: None,
+            undefined_behavior: false,
+            wasm_size: None,
+            deadline: None,
+            automatic: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Running,
+    // None if the process was terminated by a signal
+    Exited(Option<i32>),
+    // stopped by the user
+    Killed,
+    // stopped for running longer than the time limit
+    TimedOut(Duration),
+    // the project couldn't be created, so nothing ran
+    Failed,
+}
+
+impl Terminal {
+    /// The font the output is shown in
+    pub fn font_id(&self) -> FontId {
+        FontId::new(self.font_size, self.font_family.clone())
+    }
+
+    /// Whether the tab has a process which is still running
+    pub fn is_running(&self, ctx: &egui::Context, id: Id) -> bool {
+        self.abortable
+            .get(&id)
+            .map(|abort_id| ctx.data().get_temp::<Aborter>(*abort_id).is_some())
+            .unwrap_or_default()
+    }
+
+    /// Whether the tab's running process was started with the Play button, rather than by auto-run
+    pub fn is_running_manually(&self, ctx: &egui::Context, id: Id) -> bool {
+        self.is_running(ctx, id)
+            && matches!(
+                self.run_state.get(&id),
+                Some(run_state) if !run_state.lock().unwrap().automatic
+            )
+    }
+
+    /// Open or close the panel. It opens at the height it was last left at
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.restore_height = self.open;
+    }
+
+    /// Show a message in a tab's output, opening the terminal to it
+    pub fn show_message(&mut self, id: Id, stream: Stream, text: String) {
+        self.messages
+            .entry(id)
+            .or_default()
+            .push(OutputChunk::new(stream, text));
+
+        self.active_tab = Some(id);
+        if !self.open {
+            self.toggle();
+        }
+    }
+
+    /// Drop everything kept for a tab's output, for when the tab is closed
+    pub fn remove_tab(&mut self, id: Id) {
+        self.content.remove(&id);
+        self.run_state.remove(&id);
+        self.stdin.remove(&id);
+        self.messages.remove(&id);
+        self.clear.remove(&id);
+        self.scroll_offset.retain(|(tab, ..), _| *tab != id);
+        self.follow.remove(&id);
+        self.started_runs.remove(&id);
+        self.history.remove(&id);
+        self.viewed_run.remove(&id);
+
+        if self.active_tab == Some(id) {
+            self.active_tab = None;
+        }
+        if self.focused_tab == Some(id) {
+            self.focused_tab = None;
+        }
+    }
+
+    /// Keep the state of a tab's new run, letting the terminal know the last one is over
+    pub fn start_run(&mut self, id: Id, run_state: Arc<Mutex<RunState>>) {
+        let exit_code = self.run_state.insert(id, run_state).and_then(|last| {
+            match last.lock().unwrap().status {
+                RunStatus::Exited(code) => code,
+                _ => None,
+            }
+        });
+
+        self.started_runs.insert(id, exit_code);
+    }
+
+    /// The earlier run the tab shows instead of its latest one, if one was picked
+    pub fn viewed_run(&self, id: Id) -> Option<&PastRun> {
+        let number = self.viewed_run.get(&id)?;
+        self.history
+            .get(&id)?
+            .iter()
+            .find(|run| run.number == *number)
+    }
+
+    /// Kill the tab's running process, if there is one
+    pub fn kill(&mut self, ctx: &egui::Context, id: Id) {
+        let Some(abort_id) = self.abortable.remove(&id) else {
+            return;
+        };
+
+        let mut data = ctx.data();
+        if let Some(aborter) = data.get_temp::<Aborter>(abort_id) {
+            let _ = aborter.lock().unwrap().send(());
+            data.remove::<Aborter>(abort_id);
+        }
+    }
 }
@@ -1,49 +1,251 @@
-use egui::Color32;
+use egui::{Color32, Visuals};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::utils::syntax::Highlight;
+
+/// Whether the ui is dark or light, or follows what the os is set to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeMode {
+    #[default]
+    Dark,
+    Light,
+    System,
+}
+
+impl ThemeMode {
+    pub const ALL: [ThemeMode; 3] = [ThemeMode::Dark, ThemeMode::Light, ThemeMode::System];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeMode::Dark => "Dark",
+            ThemeMode::Light => "Light",
+            ThemeMode::System => "Follow system",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ThemeConfig {
+    #[serde(default)]
+    pub mode: ThemeMode,
+    // whether the light colors are the ones in use, the mode's resolved against the os
+    #[serde(skip)]
+    pub light: bool,
+    // the dark and light modes each keep their own colors, so changing one leaves the other alone
     ansi_colors: AnsiColors,
+    #[serde(default = "AnsiColors::light")]
+    light_ansi_colors: AnsiColors,
     pub force_bright: bool,
+    // terminal text is made lighter or darker until it has at least this contrast ratio with its
+    // background, from 1 to 21
+    pub minimum_contrast: Option<f32>,
+    // the terminal's text and background where the output doesn't color them, the ui's colors if unset
+    pub text_color: Option<Rgb>,
+    pub background: Option<Rgb>,
+    // the editor's rust highlighting
+    #[serde(default)]
+    syntax: SyntaxColors,
+    #[serde(default = "SyntaxColors::light")]
+    light_syntax: SyntaxColors,
 }
 
 impl Default for ThemeConfig {
     fn default() -> Self {
         Self {
+            mode: ThemeMode::Dark,
+            light: false,
             ansi_colors: Default::default(),
+            light_ansi_colors: AnsiColors::light(),
             force_bright: true,
+            minimum_contrast: None,
+            text_color: None,
+            background: None,
+            syntax: Default::default(),
+            light_syntax: SyntaxColors::light(),
         }
     }
 }
 
 impl ThemeConfig {
     pub fn get_ansi_colors(&self) -> AnsiColors {
+        let colors = self.palette();
+
         if self.force_bright {
             AnsiColors {
-                black: self.ansi_colors.bright_black,
-                red: self.ansi_colors.bright_red,
-                green: self.ansi_colors.bright_green,
-                yellow: self.ansi_colors.bright_yellow,
-                blue: self.ansi_colors.bright_blue,
-                magenta: self.ansi_colors.bright_magenta,
-                cyan: self.ansi_colors.bright_cyan,
-                white: self.ansi_colors.bright_white,
-                bright_black: self.ansi_colors.bright_black,
-                bright_red: self.ansi_colors.bright_red,
-                bright_green: self.ansi_colors.bright_green,
-                bright_yellow: self.ansi_colors.bright_yellow,
-                bright_blue: self.ansi_colors.bright_blue,
-                bright_magenta: self.ansi_colors.bright_magenta,
-                bright_cyan: self.ansi_colors.bright_cyan,
-                bright_white: self.ansi_colors.bright_white,
+                black: colors.bright_black,
+                red: colors.bright_red,
+                green: colors.bright_green,
+                yellow: colors.bright_yellow,
+                blue: colors.bright_blue,
+                magenta: colors.bright_magenta,
+                cyan: colors.bright_cyan,
+                white: colors.bright_white,
+                ..colors
             }
+        } else {
+            colors
+        }
+    }
+
+    /// Whether the light colors are used, given what the os is set to
+    pub fn is_light(&self, system_light: bool) -> bool {
+        match self.mode {
+            ThemeMode::Dark => false,
+            ThemeMode::Light => true,
+            ThemeMode::System => system_light,
+        }
+    }
+
+    /// The ui's colors for the mode in use
+    pub fn visuals(&self) -> Visuals {
+        if self.light {
+            Visuals::light()
+        } else {
+            Visuals::dark()
+        }
+    }
+
+    /// The mode in use's colors as they're set, without `force_bright` applied
+    pub fn palette(&self) -> AnsiColors {
+        if self.light {
+            self.light_ansi_colors
         } else {
             self.ansi_colors
         }
     }
+
+    pub fn set_palette(&mut self, colors: AnsiColors) {
+        if self.light {
+            self.light_ansi_colors = colors;
+        } else {
+            self.ansi_colors = colors;
+        }
+    }
+
+    /// The mode in use's rust highlighting
+    pub fn syntax(&self) -> &SyntaxColors {
+        if self.light {
+            &self.light_syntax
+        } else {
+            &self.syntax
+        }
+    }
+
+    pub fn set_syntax(&mut self, colors: SyntaxColors) {
+        if self.light {
+            self.light_syntax = colors;
+        } else {
+            self.syntax = colors;
+        }
+    }
+
+    /// The color of terminal text without a color of its own
+    pub fn text_color(&self, visuals: &Visuals) -> Color32 {
+        self.text_color
+            .map(Rgb::to_color32)
+            .unwrap_or_else(|| visuals.text_color())
+    }
+
+    /// The terminal's background
+    pub fn background(&self, visuals: &Visuals) -> Color32 {
+        self.background
+            .map(Rgb::to_color32)
+            .unwrap_or(visuals.panel_fill)
+    }
+
+    /// Put the mode in use's colors back to the defaults, leaving the other settings alone
+    pub fn reset_colors(&mut self) {
+        let default = Self {
+            light: self.light,
+            ..Self::default()
+        };
+
+        self.set_palette(default.palette());
+        self.text_color = default.text_color;
+        self.background = default.background;
+        self.set_syntax(*default.syntax());
+    }
+}
+
+/// The colors rust code is highlighted with in the editor
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct SyntaxColors {
+    pub keyword: Rgb,
+    pub lifetime: Rgb,
+    pub string: Rgb,
+    pub char: Rgb,
+    pub number: Rgb,
+    pub comment: Rgb,
+    pub doc_comment: Rgb,
+    pub attribute: Rgb,
+    pub macro_call: Rgb,
+}
+
+// made to stand out on black, the light mode has its own
+impl Default for SyntaxColors {
+    fn default() -> Self {
+        Self {
+            keyword: Rgb(86, 156, 214),
+            lifetime: Rgb(78, 201, 176),
+            string: Rgb(206, 145, 120),
+            char: Rgb(215, 186, 125),
+            number: Rgb(181, 206, 168),
+            comment: Rgb(106, 153, 85),
+            doc_comment: Rgb(135, 180, 110),
+            attribute: Rgb(156, 156, 220),
+            macro_call: Rgb(220, 220, 170),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, Hash)]
+impl SyntaxColors {
+    /// The colors for the light mode, dark enough to read on white
+    pub fn light() -> Self {
+        Self {
+            keyword: Rgb(0, 0, 255),
+            lifetime: Rgb(38, 127, 153),
+            string: Rgb(163, 21, 21),
+            char: Rgb(129, 31, 63),
+            number: Rgb(9, 134, 88),
+            comment: Rgb(0, 128, 0),
+            doc_comment: Rgb(0, 100, 0),
+            attribute: Rgb(175, 0, 219),
+            macro_call: Rgb(121, 94, 38),
+        }
+    }
+
+    /// The colors with their names, in the order the settings list them
+    pub fn named_mut(&mut self) -> [(&'static str, &mut Rgb); 9] {
+        [
+            ("keywords", &mut self.keyword),
+            ("lifetimes", &mut self.lifetime),
+            ("strings", &mut self.string),
+            ("chars", &mut self.char),
+            ("numbers", &mut self.number),
+            ("comments", &mut self.comment),
+            ("doc comments", &mut self.doc_comment),
+            ("attributes", &mut self.attribute),
+            ("macros", &mut self.macro_call),
+        ]
+    }
+
+    pub fn color(&self, highlight: Highlight) -> Rgb {
+        match highlight {
+            Highlight::Keyword => self.keyword,
+            Highlight::Lifetime => self.lifetime,
+            Highlight::Str => self.string,
+            Highlight::Char => self.char,
+            Highlight::Number => self.number,
+            Highlight::Comment => self.comment,
+            Highlight::DocComment => self.doc_comment,
+            Highlight::Attribute => self.attribute,
+            Highlight::Macro => self.macro_call,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct AnsiColors {
     pub black: Rgb,
     pub red: Rgb,
@@ -86,7 +288,189 @@ impl Default for AnsiColors {
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone, Hash)]
+impl AnsiColors {
+    /// The light mode's colors, which all stay readable on a light background
+    pub fn light() -> Self {
+        Self::from_array([
+            Rgb(0, 0, 0),
+            Rgb(205, 49, 49),
+            Rgb(0, 188, 0),
+            Rgb(148, 152, 0),
+            Rgb(4, 81, 165),
+            Rgb(188, 5, 188),
+            Rgb(5, 152, 188),
+            Rgb(85, 85, 85),
+            Rgb(102, 102, 102),
+            Rgb(205, 49, 49),
+            Rgb(20, 206, 20),
+            Rgb(181, 186, 0),
+            Rgb(4, 81, 165),
+            Rgb(188, 5, 188),
+            Rgb(5, 152, 188),
+            Rgb(165, 165, 165),
+        ])
+    }
+
+    /// The names of the colors, in the order of their ansi codes
+    pub const NAMES: [&'static str; 16] = [
+        "black",
+        "red",
+        "green",
+        "yellow",
+        "blue",
+        "magenta",
+        "cyan",
+        "white",
+        "bright black",
+        "bright red",
+        "bright green",
+        "bright yellow",
+        "bright blue",
+        "bright magenta",
+        "bright cyan",
+        "bright white",
+    ];
+
+    /// The colors in the order of their ansi codes
+    pub fn to_array(self) -> [Rgb; 16] {
+        [
+            self.black,
+            self.red,
+            self.green,
+            self.yellow,
+            self.blue,
+            self.magenta,
+            self.cyan,
+            self.white,
+            self.bright_black,
+            self.bright_red,
+            self.bright_green,
+            self.bright_yellow,
+            self.bright_blue,
+            self.bright_magenta,
+            self.bright_cyan,
+            self.bright_white,
+        ]
+    }
+
+    pub fn from_array(colors: [Rgb; 16]) -> Self {
+        let [black, red, green, yellow, blue, magenta, cyan, white, bright_black, bright_red, bright_green, bright_yellow, bright_blue, bright_magenta, bright_cyan, bright_white] =
+            colors;
+
+        Self {
+            black,
+            red,
+            green,
+            yellow,
+            blue,
+            magenta,
+            cyan,
+            white,
+            bright_black,
+            bright_red,
+            bright_green,
+            bright_yellow,
+            bright_blue,
+            bright_magenta,
+            bright_cyan,
+            bright_white,
+        }
+    }
+
+    /// Color schemes which come with the app, by name. The default colors are Windows Terminal's Campbell
+    pub fn presets() -> Vec<(&'static str, AnsiColors)> {
+        vec![
+            ("Campbell", AnsiColors::default()),
+            ("Light", AnsiColors::light()),
+            (
+                "Solarized Dark",
+                AnsiColors::from_array([
+                    Rgb(0, 43, 54),
+                    Rgb(220, 50, 47),
+                    Rgb(133, 153, 0),
+                    Rgb(181, 137, 0),
+                    Rgb(38, 139, 210),
+                    Rgb(211, 54, 130),
+                    Rgb(42, 161, 152),
+                    Rgb(238, 232, 213),
+                    Rgb(7, 54, 66),
+                    Rgb(203, 75, 22),
+                    Rgb(88, 110, 117),
+                    Rgb(101, 123, 131),
+                    Rgb(131, 148, 150),
+                    Rgb(108, 113, 196),
+                    Rgb(147, 161, 161),
+                    Rgb(253, 246, 227),
+                ]),
+            ),
+            (
+                "Solarized Light",
+                AnsiColors::from_array([
+                    Rgb(253, 246, 227),
+                    Rgb(220, 50, 47),
+                    Rgb(133, 153, 0),
+                    Rgb(181, 137, 0),
+                    Rgb(38, 139, 210),
+                    Rgb(108, 113, 196),
+                    Rgb(42, 161, 152),
+                    Rgb(88, 110, 117),
+                    Rgb(131, 148, 150),
+                    Rgb(203, 75, 22),
+                    Rgb(133, 153, 0),
+                    Rgb(181, 137, 0),
+                    Rgb(38, 139, 210),
+                    Rgb(211, 54, 130),
+                    Rgb(42, 161, 152),
+                    Rgb(0, 43, 54),
+                ]),
+            ),
+            (
+                "Dracula",
+                AnsiColors::from_array([
+                    Rgb(33, 34, 44),
+                    Rgb(255, 85, 85),
+                    Rgb(80, 250, 123),
+                    Rgb(241, 250, 140),
+                    Rgb(189, 147, 249),
+                    Rgb(255, 121, 198),
+                    Rgb(139, 233, 253),
+                    Rgb(248, 248, 242),
+                    Rgb(98, 114, 164),
+                    Rgb(255, 110, 110),
+                    Rgb(105, 255, 148),
+                    Rgb(255, 255, 165),
+                    Rgb(214, 172, 255),
+                    Rgb(255, 146, 223),
+                    Rgb(164, 255, 255),
+                    Rgb(255, 255, 255),
+                ]),
+            ),
+            (
+                "Gruvbox",
+                AnsiColors::from_array([
+                    Rgb(40, 40, 40),
+                    Rgb(204, 36, 29),
+                    Rgb(152, 151, 26),
+                    Rgb(215, 153, 33),
+                    Rgb(69, 133, 136),
+                    Rgb(177, 98, 134),
+                    Rgb(104, 157, 106),
+                    Rgb(168, 153, 132),
+                    Rgb(146, 131, 116),
+                    Rgb(251, 73, 52),
+                    Rgb(184, 187, 38),
+                    Rgb(250, 189, 47),
+                    Rgb(131, 165, 152),
+                    Rgb(211, 134, 155),
+                    Rgb(142, 192, 124),
+                    Rgb(235, 219, 178),
+                ]),
+            ),
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Rgb(pub u8, pub u8, pub u8);
 
 impl Rgb {
@@ -0,0 +1,43 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// How far the whole ui can be zoomed out and in
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+/// How much each Ctrl+= and Ctrl+- zooms by
+pub const ZOOM_STEP: f32 = 0.1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    // scales everything on top of the display's own scaling, the editor and terminal font sizes
+    // scale along with it
+    pub zoom: f32,
+    // when the zoom last changed, it's shown for a moment after
+    #[serde(skip_serializing, skip_deserializing)]
+    pub zoomed_at: Option<Instant>,
+    // the welcome page with the examples is opened when the app starts
+    pub show_welcome_on_startup: bool,
+    // files opened while the app's running go to it instead of another window, from the next start
+    pub single_instance: bool,
+}
+
+impl UiConfig {
+    pub fn set_zoom(&mut self, zoom: f32) {
+        // rounded, so steps don't drift off from whole percentages
+        self.zoom = ((zoom * 100.0).round() / 100.0).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoomed_at = Some(Instant::now());
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            zoomed_at: None,
+            show_welcome_on_startup: true,
+            single_instance: false,
+        }
+    }
+}
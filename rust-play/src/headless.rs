@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+
+use cargo_player::Subcommand;
+use egui::Id;
+
+use crate::config::RunConfig;
+use crate::utils::build_cache;
+use crate::widgets::dock::tab_project;
+
+/// Run a file without a window, the way a tab of it would run with the default settings. The
+/// build and the program print straight to the terminal, and the program's exit code is returned
+pub fn run(path: &Path) -> i32 {
+    let code = match fs::read_to_string(path) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: failed to read {}: {e}", path.display());
+            return 1;
+        }
+    };
+
+    // the same project a tab of the file builds in, so it's built already if it was run there
+    let cache_dir = build_cache::cache_dir();
+    let mut project = tab_project(
+        Id::new(path),
+        &code,
+        &[],
+        RunConfig::default(),
+        &[],
+        &cache_dir,
+    );
+    project
+        .subcommand(Subcommand::Run)
+        .target_prefix("rust-play");
+
+    let mut command = match project.create() {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return 1;
+        }
+    };
+
+    let status = command
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status();
+
+    match status {
+        // killed by something else when there's no code
+        Ok(status) => status.code().unwrap_or(1),
+        Err(e) => {
+            eprintln!("error: failed to run cargo: {e}");
+            1
+        }
+    }
+}
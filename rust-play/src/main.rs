@@ -8,6 +8,7 @@
 mod os;
 
 mod config;
+mod headless;
 mod panic;
 mod popup;
 mod utils;
@@ -16,8 +17,10 @@ mod widgets;
 #[cfg(target_os = "windows")]
 use {
     os::windows::{
+        console,
         custom_frame::{self},
         init::load_app_icon,
+        single_instance::{self, Instance},
         win_version::is_supported_os,
     },
     std::sync::mpsc::{channel, Sender},
@@ -25,18 +28,32 @@ use {
 
 use std::env;
 use std::fs;
+use std::path;
+use std::process;
 use std::rc::Rc;
 use std::sync::mpsc::Receiver;
+use std::time::Instant;
 
-use config::Config;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+use config::{Command, Config, Session, TabCommand, Unsaved, SESSION_INTERVAL};
 use egui::{CentralPanel, Frame, Id, Rect, Ui, Vec2};
 use panic::set_hook;
 use popup::{display_popup, MessageBoxIcon};
+use utils::cli::{self, Args};
+use utils::single_instance::OpenRequest;
+use utils::{crash_reports, drafts, logs, session};
 use widgets::dock::{Dock, TabEvents};
+use widgets::palette::{CommandPalette, GoToLine, QuickOpen};
 
 use eframe::{egui, NativeOptions};
+use widgets::settings::Settings;
+use widgets::shortcuts::Shortcuts;
+use widgets::status_bar::StatusBar;
 use widgets::terminal::Terminal;
 use widgets::titlebar::custom_window_frame;
+use widgets::zoom::Zoom;
 
 // Each rectangle is an entire tree; not a single tab
 #[cfg(target_os = "windows")]
@@ -46,6 +63,31 @@ fn main() {
     // set up custom panic hook
     set_hook();
 
+    // --help and invalid arguments are printed to the terminal the app was started from
+    let args = match cli::parse(env::args_os()) {
+        Ok(args) => args,
+        Err(e) => {
+            #[cfg(target_os = "windows")]
+            console::attach_parent();
+            e.exit();
+        }
+    };
+    // tabs are keyed by their file's path, which shouldn't depend on where the app started
+    let args = Args {
+        files: args
+            .files
+            .iter()
+            .map(|file| path::absolute(file).unwrap_or_else(|_| file.clone()))
+            .collect(),
+        ..args
+    };
+
+    if args.headless {
+        #[cfg(target_os = "windows")]
+        console::attach_parent();
+        process::exit(headless::run(&args.files[0]));
+    }
+
     // check windows version
     #[cfg(target_os = "windows")]
     if !is_supported_os() {
@@ -57,9 +99,28 @@ fn main() {
         return;
     }
 
+    let config = App::load_config();
+
+    // the running instance opens the files instead, when there's one
     #[cfg(target_os = "windows")]
-    let app = {
-        let (app, rx) = App::new();
+    let listener = match config.ui.single_instance.then(single_instance::claim) {
+        Some(Ok(Instance::First(listener))) => Some(listener),
+        Some(Ok(Instance::Running)) => {
+            let request = OpenRequest {
+                files: args.files.clone(),
+                run: args.run,
+            };
+            if single_instance::send(&request.encode()).is_ok() {
+                return;
+            }
+            None
+        }
+        _ => None,
+    };
+
+    #[cfg(target_os = "windows")]
+    let mut app = {
+        let (app, rx) = App::new(config, &args);
 
         custom_frame::init(rx);
 
@@ -67,9 +128,13 @@ fn main() {
     };
 
     #[cfg(not(target_os = "windows"))]
-    let app = App::new();
+    let app = App::new(config, &args);
 
-    tracing_subscriber::fmt::init();
+    // stderr gets what it always did, the logs view and the log file go by the settings
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(LevelFilter::INFO))
+        .with(logs::layer())
+        .init();
 
     let options = NativeOptions {
         icon_data: Some(load_app_icon()),
@@ -83,7 +148,27 @@ fn main() {
         ..Default::default()
     };
 
-    eframe::run_native("Rust Play", options, Box::new(|_cc| Box::new(app)));
+    eframe::run_native(
+        "Rust Play",
+        options,
+        Box::new(|_cc| {
+            // the requests are handled with the other commands, the ui is woken up for them
+            #[cfg(target_os = "windows")]
+            if let Some(listener) = listener {
+                let (tx, rx) = channel();
+                let ctx = _cc.egui_ctx.clone();
+                listener.listen(move |message| {
+                    if let Some(request) = OpenRequest::decode(&message) {
+                        let _ = tx.send(request);
+                        ctx.request_repaint();
+                    }
+                });
+                app.config.dock.open_requests = Some(rx);
+            }
+
+            Box::new(app)
+        }),
+    );
 }
 
 struct App {
@@ -95,28 +180,63 @@ struct App {
 }
 
 impl App {
-    #[cfg(target_os = "windows")]
-    fn new() -> (Self, Receiver<CaptionMaxRect>) {
-        let (tx, rx) = channel();
-
+    // the settings next to the exe, or the defaults when there's none yet
+    fn load_config() -> Config {
         let current_dir = env::current_exe().unwrap().parent().unwrap().to_owned();
         let file = current_dir.join("settings.toml");
 
-        let mut config = if file.exists() {
+        if file.exists() {
             let content = fs::read_to_string(file).expect("Failed to read config file");
             toml::from_str::<Config>(&content).unwrap_or_default()
         } else {
             Config::default()
-        };
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn new(mut config: Config, args: &Args) -> (Self, Receiver<CaptionMaxRect>) {
+        let (tx, rx) = channel();
+
+        config.editor.load_snippets();
+        logs::set_level(config.logging.level);
+
+        // a session which can't be read was kept as a backup, the app starts over without it
+        if let Ok(Some(session)) = session::load::<Session>(&session::session_file()) {
+            session.restore(&mut config);
+        }
+        // drafts written after the session are from a crash, the others are out of date
+        let session_saved = fs::metadata(session::session_file()).and_then(|meta| meta.modified());
+        let (recovered, stale): (Vec<_>, Vec<_>) = drafts::load(&drafts::drafts_dir())
+            .into_iter()
+            .partition(|draft| {
+                session_saved
+                    .as_ref()
+                    .map_or(true, |saved| draft.modified > *saved)
+            });
+        for draft in stale {
+            let _ = fs::remove_file(draft.path);
+        }
+        config.dock.recovered_drafts = recovered;
+        config.dock.crash_reports = crash_reports::unread(&crash_reports::reports_dir());
 
         // initialize the terminal data
-        config.terminal.active_tab = Some(config.dock.tree.find_active().unwrap().1.id);
+        let active_tab = config
+            .terminal
+            .active_tab
+            .unwrap_or_else(|| config.dock.tree.find_active().unwrap().1.id);
+        config.terminal.active_tab = Some(active_tab);
         config.terminal.scroll_offset.insert(
-            config.dock.tree.find_active().unwrap().1.id,
+            (active_tab, config.terminal.merge_streams, false),
             Vec2::default(),
         );
+        // egui doesn't keep the panel state between sessions, so put back the height it was left at
+        config.terminal.restore_height = config.terminal.open;
 
-        config.dock.counter = 2;
+        // the files it was started with are what's wanted, not the welcome page
+        if args.files.is_empty() && config.ui.show_welcome_on_startup {
+            Dock::show_welcome(&mut config.dock.tree);
+        }
+        Dock::open_startup_files(&mut config, &args.files, args.run);
 
         let app = Self {
             tx: Rc::new(tx),
@@ -127,10 +247,8 @@ impl App {
     }
 
     #[cfg(not(target_os = "windows"))]
-    fn new() -> Self {
-        Self {
-            config: Config::default(),
-        }
+    fn new(config: Config, _: &Args) -> Self {
+        Self { config }
     }
 
     fn show_dock(&mut self, ctx: &egui::Context, ui: &mut Ui) {
@@ -148,10 +266,80 @@ impl App {
     fn show_terminal_closed_handle(&mut self, ctx: &egui::Context) {
         Terminal::show_closed_handle(ctx, &mut self.config);
     }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        Shortcuts::handle(ctx, &mut self.config);
+    }
+
+    fn show_settings(&mut self, ctx: &egui::Context) {
+        Settings::show(ctx, &mut self.config);
+    }
+
+    fn show_palette(&mut self, ctx: &egui::Context) {
+        CommandPalette::show(ctx, &mut self.config);
+        QuickOpen::show(ctx, &mut self.config);
+        GoToLine::show(ctx, &mut self.config);
+    }
+
+    // Write the session if it changed since it was last written. Returns whether the one on disk
+    // is up to date
+    fn save_session(&mut self) -> bool {
+        self.config.dock.session_checked = Instant::now();
+
+        // the output is only copied into the session when something changed, it can be megabytes
+        let output = Terminal::output_revision(&self.config);
+        let Ok(layout) = session::serialize(&Session::layout(&self.config)) else {
+            return false;
+        };
+
+        let dock = &self.config.dock;
+        if layout == dock.saved_layout && Some(output) == dock.saved_output {
+            return true;
+        }
+
+        // a session that couldn't be written is tried again next time
+        let saved = session::serialize(&Session::new(&self.config))
+            .is_ok_and(|content| session::save(&session::session_file(), &content).is_ok());
+        if saved {
+            let dock = &mut self.config.dock;
+            dock.saved_layout = layout;
+            dock.saved_output = Some(output);
+        }
+
+        saved
+    }
 }
 
 impl eframe::App for App {
     fn on_close_event(&mut self) -> bool {
+        // unsaved changes are asked about first, the app closes again once they're dealt with
+        let dirty = self
+            .config
+            .dock
+            .tree
+            .tabs()
+            .filter(|tab| tab.is_dirty())
+            .map(|tab| tab.id)
+            .collect::<Vec<_>>();
+
+        if !self.config.dock.exiting && !dirty.is_empty() {
+            self.config
+                .dock
+                .commands
+                .push(Command::TabCommand(TabCommand::CloseTabs {
+                    tabs: dirty,
+                    unsaved: Unsaved::Ask,
+                    exit: true,
+                }));
+
+            return false;
+        }
+
+        // the drafts are only needed when the session couldn't be written
+        if self.save_session() {
+            drafts::clear(&drafts::drafts_dir());
+        }
+
         // Write config to settings.toml
 
         let config_string =
@@ -171,11 +359,18 @@ impl eframe::App for App {
     }
 
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        Settings::apply_theme(ctx, &mut self.config);
+        self.handle_shortcuts(ctx);
+        Terminal::receive(&mut self.config);
+
+        // shown first, so it's below the terminal
+        StatusBar::show(ctx, &mut self.config);
         if self.config.terminal.open {
             self.show_terminal(ctx);
         } else {
             self.show_terminal_closed_handle(ctx);
         }
+        Zoom::handle(ctx, frame, &mut self.config);
 
         CentralPanel::default()
             .frame(Frame::none())
@@ -184,6 +379,7 @@ impl eframe::App for App {
                     ctx,
                     frame,
                     ui,
+                    &mut self.config,
                     #[cfg(target_os = "windows")]
                     Rc::clone(&self.tx),
                 );
@@ -193,6 +389,19 @@ impl eframe::App for App {
 
         self.handle_tabs(ctx);
 
+        if self.config.dock.exiting {
+            frame.close();
+        }
+
+        self.show_settings(ctx);
+        self.show_palette(ctx);
+
+        if self.config.dock.session_checked.elapsed() >= SESSION_INTERVAL {
+            self.save_session();
+        }
+        // the last changes before the app goes idle are saved too
+        ctx.request_repaint_after(SESSION_INTERVAL);
+
         let counter = ctx
             .memory()
             .data
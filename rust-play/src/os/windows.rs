@@ -1,4 +1,10 @@
+pub mod clipboard;
+pub mod console;
+pub mod credentials;
 pub mod custom_frame;
 pub mod dwm_win32;
 pub mod init;
+pub mod keyboard;
+pub mod single_instance;
+pub mod theme;
 pub mod win_version;
@@ -0,0 +1,46 @@
+use windows::Win32::{
+    Foundation::HWND,
+    System::{
+        DataExchange::{CloseClipboard, GetClipboardData, OpenClipboard},
+        Memory::{GlobalLock, GlobalUnlock},
+    },
+};
+
+// the clipboard format for utf-16 text
+const CF_UNICODETEXT: u32 = 13;
+
+/// The text on the clipboard, if there is any. egui only hands over pasted text to a focused text edit,
+/// so it has to be read directly
+pub fn get_text() -> Option<String> {
+    unsafe {
+        if !OpenClipboard(HWND(0)).as_bool() {
+            return None;
+        }
+
+        let text = read_text();
+        CloseClipboard();
+
+        text
+    }
+}
+
+// the clipboard has to be open
+unsafe fn read_text() -> Option<String> {
+    let handle = GetClipboardData(CF_UNICODETEXT).ok()?;
+
+    let data = GlobalLock(handle.0) as *const u16;
+    if data.is_null() {
+        return None;
+    }
+
+    // the text is nul terminated
+    let mut len = 0;
+    while *data.add(len) != 0 {
+        len += 1;
+    }
+
+    let text = String::from_utf16_lossy(std::slice::from_raw_parts(data, len));
+    GlobalUnlock(handle.0);
+
+    Some(text)
+}
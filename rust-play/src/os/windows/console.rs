@@ -0,0 +1,9 @@
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+/// Print to the console the app was started from. Release builds don't get one of their own,
+/// they're a windowed app. Does nothing when it wasn't started from one
+pub fn attach_parent() {
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
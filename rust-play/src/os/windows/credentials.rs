@@ -0,0 +1,68 @@
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Security::Credentials::{
+    CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_FLAGS,
+    CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+};
+
+// the nul terminated utf-16 the credential functions take
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain([0]).collect()
+}
+
+/// The secret stored in the Windows Credential Manager under `target`
+pub fn read(target: &str) -> Option<String> {
+    let target = wide(target);
+    let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+
+    unsafe {
+        if !CredReadW(
+            PCWSTR(target.as_ptr()),
+            CRED_TYPE_GENERIC.0,
+            0,
+            &mut credential,
+        )
+        .as_bool()
+        {
+            return None;
+        }
+
+        let blob = std::slice::from_raw_parts(
+            (*credential).CredentialBlob,
+            (*credential).CredentialBlobSize as usize,
+        );
+        let secret = String::from_utf8(blob.to_vec()).ok();
+        CredFree(credential as *const _);
+
+        secret
+    }
+}
+
+/// Store a secret in the Windows Credential Manager, replacing the one under `target`. It stays
+/// on this machine, for this user
+pub fn write(target: &str, user: &str, secret: &str) -> bool {
+    let mut target = wide(target);
+    let mut user = wide(user);
+    let mut blob = secret.as_bytes().to_vec();
+
+    let credential = CREDENTIALW {
+        Flags: CRED_FLAGS(0),
+        Type: CRED_TYPE_GENERIC,
+        TargetName: PWSTR(target.as_mut_ptr()),
+        CredentialBlobSize: blob.len() as u32,
+        CredentialBlob: blob.as_mut_ptr(),
+        Persist: CRED_PERSIST_LOCAL_MACHINE,
+        UserName: PWSTR(user.as_mut_ptr()),
+        ..Default::default()
+    };
+
+    unsafe { CredWriteW(&credential, 0).as_bool() }
+}
+
+/// Remove the secret under `target`, if there is one
+pub fn delete(target: &str) {
+    let target = wide(target);
+
+    unsafe {
+        CredDeleteW(PCWSTR(target.as_ptr()), CRED_TYPE_GENERIC.0, 0);
+    }
+}
@@ -31,6 +31,8 @@ use super::dwm_win32::apply_acrylic;
 const WC_DIALOG: u32 = 0x8002;
 
 static MAX_RECT: OnceCell<RwLock<CaptionMaxRect>> = OnceCell::new();
+// the first window the subclass was set on, which is the app's
+static MAIN_WINDOW: OnceCell<HWND> = OnceCell::new();
 
 // macro_rules! RGB {
 //     ($r:expr, $g:expr, $b:expr) => {{
@@ -107,6 +109,7 @@ unsafe extern "system" fn window_hook_callback(
                 if !res {
                     panic!("Failed to set subclass proc");
                 }
+                let _ = MAIN_WINDOW.set(hwnd);
 
                 *counter += 1;
             }
@@ -117,6 +120,11 @@ unsafe extern "system" fn window_hook_callback(
     CallNextHookEx(None, code, wparam, lparam)
 }
 
+/// The app's window, once it's made
+pub fn main_window() -> Option<HWND> {
+    MAIN_WINDOW.get().copied()
+}
+
 pub unsafe fn is_dwm_enabled() -> bool {
     let dwm_enabled_result = DwmIsCompositionEnabled();
 
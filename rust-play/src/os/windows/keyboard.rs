@@ -0,0 +1,18 @@
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, VIRTUAL_KEY, VK_OEM_2, VK_OEM_3,
+};
+
+/// Whether the ` key is held down. winit doesn't translate it for egui, so it has to be asked for directly
+pub fn is_backtick_down() -> bool {
+    is_down(VK_OEM_3)
+}
+
+/// Whether the / key is held down, asked for directly since winit doesn't translate it either
+pub fn is_slash_down() -> bool {
+    is_down(VK_OEM_2)
+}
+
+fn is_down(key: VIRTUAL_KEY) -> bool {
+    // the most significant bit is set while the key is down
+    unsafe { GetAsyncKeyState(key.0 as i32) as u16 & 0x8000 != 0 }
+}
@@ -0,0 +1,155 @@
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::windows::io::FromRawHandle;
+use std::thread;
+use std::time::Duration;
+
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{
+    GetLastError, ERROR_ACCESS_DENIED, ERROR_FILE_NOT_FOUND, ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED,
+    HANDLE,
+};
+use windows::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+    PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AllowSetForegroundWindow, IsIconic, SetForegroundWindow, ShowWindow, ASFW_ANY, SW_RESTORE,
+};
+
+use super::custom_frame;
+
+// how many times sending waits for the running instance to be ready for another request
+const SEND_ATTEMPTS: usize = 20;
+
+/// Whether another instance was running already when the app started
+pub enum Instance {
+    // this is the first one, the ones after it send it what they were started with
+    First(Listener),
+    Running,
+}
+
+// one pipe for each user, so their instances don't see each other
+fn pipe_name() -> String {
+    let user = env::var("USERNAME").unwrap_or_default();
+    format!(r"\\.\pipe\RustPlay-{user}")
+}
+
+fn create_pipe(first: bool) -> io::Result<HANDLE> {
+    let mut mode = PIPE_ACCESS_INBOUND;
+    if first {
+        mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            &HSTRING::from(pipe_name()),
+            mode,
+            PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            0,
+            4096,
+            0,
+            None,
+        )
+    };
+
+    if pipe.is_invalid() {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(pipe)
+    }
+}
+
+/// Become the instance the others send their files to, unless there's one already
+pub fn claim() -> io::Result<Instance> {
+    match create_pipe(true) {
+        Ok(pipe) => Ok(Instance::First(Listener { pipe })),
+        // only the first instance of the pipe can be made with the flag
+        Err(e) if e.raw_os_error() == Some(ERROR_ACCESS_DENIED.0 as i32) => Ok(Instance::Running),
+        Err(e) => Err(e),
+    }
+}
+
+/// Send a message to the running instance, which may take its window to the front
+pub fn send(message: &str) -> io::Result<()> {
+    unsafe {
+        AllowSetForegroundWindow(ASFW_ANY);
+    }
+
+    let retry_codes = [ERROR_PIPE_BUSY, ERROR_FILE_NOT_FOUND].map(|code| Some(code.0 as i32));
+
+    let mut attempt = 0;
+    let mut pipe = loop {
+        match OpenOptions::new().write(true).open(pipe_name()) {
+            Ok(pipe) => break pipe,
+            // it's busy with another request, or the running instance is still starting or
+            // going away
+            Err(e) if retry_codes.contains(&e.raw_os_error()) => {
+                attempt += 1;
+                if attempt == SEND_ATTEMPTS {
+                    return Err(e);
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    pipe.write_all(message.as_bytes())
+}
+
+/// The first instance's end of the pipe
+pub struct Listener {
+    pipe: HANDLE,
+}
+
+// the handle is only used by the thread it's moved to
+unsafe impl Send for Listener {}
+
+impl Listener {
+    /// Wait for the other instances' messages on a thread of its own, each one is given to
+    /// `received` as it was sent
+    pub fn listen(self, received: impl Fn(String) + Send + 'static) {
+        thread::spawn(move || {
+            let mut pipe = self.pipe;
+            loop {
+                let connected = unsafe { ConnectNamedPipe(pipe, None) }.as_bool()
+                    || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+
+                // the next instance of the pipe is made before this one's closed, so there's always
+                // one to take the next request, and no other instance can claim the pipe as first
+                let next = create_pipe(false);
+
+                // closed along with the file
+                let mut file = unsafe { File::from_raw_handle(pipe.0 as _) };
+                let mut message = String::new();
+                if connected && file.read_to_string(&mut message).is_ok() {
+                    received(message);
+                }
+                drop(file);
+
+                match next {
+                    Ok(next) => pipe = next,
+                    Err(_) => break,
+                }
+            }
+        });
+    }
+}
+
+/// Bring the app's window to the front, restoring it when it's minimized
+pub fn raise() {
+    let Some(window) = custom_frame::main_window() else {
+        return;
+    };
+
+    unsafe {
+        if IsIconic(window).as_bool() {
+            ShowWindow(window, SW_RESTORE);
+        }
+        SetForegroundWindow(window);
+    }
+}
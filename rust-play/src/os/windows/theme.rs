@@ -0,0 +1,26 @@
+use std::ffi::c_void;
+use std::mem;
+
+use windows::w;
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+
+/// Whether Windows is set to light mode for apps, None when the setting can't be read
+pub fn apps_use_light_theme() -> Option<bool> {
+    let mut value = 0u32;
+    let mut size = mem::size_of::<u32>() as u32;
+
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            w!("AppsUseLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut c_void),
+            Some(&mut size),
+        )
+    };
+
+    (result == ERROR_SUCCESS).then_some(value != 0)
+}
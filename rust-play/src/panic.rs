@@ -1,17 +1,33 @@
+use std::backtrace::Backtrace;
 use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 #[cfg(debug_assertions)]
-use {regex::Regex, std::backtrace::Backtrace};
+use regex::Regex;
 
 use crate::popup::{display_popup, MessageBoxIcon};
+use crate::utils::{crash_reports, drafts};
+
+// set while the hook runs, a panic inside of it only shows the popup
+static PANICKING: AtomicBool = AtomicBool::new(false);
 
 pub fn set_hook() {
     panic::set_hook(Box::new(|v| {
+        let panic_msg = v.to_string();
+        if PANICKING.swap(true, Ordering::SeqCst) {
+            display_popup("RustPlay panicked :(", &panic_msg, MessageBoxIcon::Error);
+            return;
+        }
+
+        let full_backtrace = Backtrace::force_capture().to_string();
+
+        // the report is shown the next time the app starts, and the unsaved code is written to
+        // the drafts so it can be recovered along with it
+        let _ = crash_reports::write(&crash_reports::reports_dir(), &panic_msg, &full_backtrace);
+        drafts::flush_pending(&drafts::drafts_dir());
+
         #[cfg(debug_assertions)]
         {
-            let panic_msg = v.to_string();
-            let backtrace = Backtrace::force_capture();
-
-            let full_backtrace = backtrace.to_string();
             let raw_frames = full_backtrace.split("\n").collect::<Vec<_>>();
 
             // Sort frames into a single frame depending on frame content
@@ -74,10 +90,7 @@ pub fn set_hook() {
             eprintln!("{}\n\nstack backtrace:\n{}", panic_msg, frames.join("\n"));
         }
 
-        display_popup(
-            "RustPlay panicked :(",
-            &v.to_string(),
-            MessageBoxIcon::Error,
-        );
+        display_popup("RustPlay panicked :(", &panic_msg, MessageBoxIcon::Error);
+        PANICKING.store(false, Ordering::SeqCst);
     }));
 }
@@ -1,8 +1,11 @@
+use std::ops::Range;
+
 use ansi_parser::AnsiSequence;
 use ansi_parser::{AnsiParser as ParseAnsi, Output};
 
-// parse color mode 5
-fn parse_rgb(color: u8) -> Color {
+/// Resolve a color from the 256 color palette. 0-15 are the regular colors, 16-231 are a 6x6x6 color
+/// cube and 232-255 are a grayscale ramp
+pub fn indexed_color(color: u8) -> Color {
     // 0-15 are regular colors, even in color mode 5
     if color < 16 {
         return match color {
@@ -43,330 +46,537 @@ fn parse_rgb(color: u8) -> Color {
     }
 }
 
-pub fn parse(text: &str) -> Parsed {
-    let parsed = text.ansi_parse();
-
-    let mut properties = vec![];
-
-    // represent text style state
-    let mut bold = false;
-    let mut dim = false;
-    let mut italic = false;
-    let mut underline = false;
-    let mut blink = false;
-    let mut reverse = false;
-    let mut hidden = false;
-    let mut strikethrough = false;
-
-    let mut fg = None;
-    let mut bg = None;
-
-    let mut text_counter = 0usize;
-
-    for chunk in parsed {
-        process_chunk(
-            chunk,
-            &mut properties,
-            &mut bold,
-            &mut dim,
-            &mut italic,
-            &mut underline,
-            &mut blink,
-            &mut reverse,
-            &mut hidden,
-            &mut strikethrough,
-            &mut fg,
-            &mut bg,
-            &mut text_counter,
-        );
+// parse the arguments of a 38 or 48 code, either 5;n for the 256 color palette or 2;r;g;b
+fn parse_extended_color(codes: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match codes.next()? {
+        5 => codes.next().map(Color::Indexed),
+        2 => Some(Color::Rgb(codes.next()?, codes.next()?, codes.next()?)),
+        _ => None,
     }
+}
 
-    Parsed { properties }
+// An extended color's parameter. The last one has the rest of the text after it
+fn color_param(param: Option<&str>) -> Option<u8> {
+    let param = param?;
+    let end = param.find('m').unwrap_or(param.len());
+    param[..end].parse().ok()
 }
 
-#[allow(clippy::too_many_arguments)]
-fn process_chunk(
-    chunk: Output,
-    properties: &mut Vec<TextProperty>,
-    bold: &mut bool,
-    dim: &mut bool,
-    italic: &mut bool,
-    underline: &mut bool,
-    blink: &mut bool,
-    reverse: &mut bool,
-    hidden: &mut bool,
-    strikethrough: &mut bool,
-    fg: &mut Option<Color>,
-    bg: &mut Option<Color>,
-    text_counter: &mut usize,
-) {
-    match chunk {
-        Output::TextBlock(mut t) => {
-            // ansi-parser fails to strip escape codes in some text
-            // https://gitlab.com/davidbittner/ansi-parser/-/issues/9
-            // Due to this bug, I am forced to do this ugly workaround so I can actually process everything
-            let stripped;
-            if t.contains('\x1b') {
-                let mut graphics_chunk = vec![];
-
-                let mut i = t.split(';');
-                while let Some(mut c) = i.next() {
-                    if c.starts_with('\x1b') {
-                        c = c.strip_prefix("\x1b[").unwrap();
-                    }
+// represent text style state, which carries over from one chunk of text to the next
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct State {
+    style: TextStyle,
+    fg: Option<Color>,
+    bg: Option<Color>,
+    // index into the parser's links
+    link: Option<usize>,
+    // the text came from stderr, when both streams are parsed together
+    stderr: bool,
+    // the text is input the user sent, see Parser::push_echo
+    echo: bool,
+}
 
-                    let c = c.parse::<u8>().unwrap();
+// a single character on the current line
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Cell {
+    ch: char,
+    state: State,
+}
 
-                    match c {
-                        0..=5 | 7..=9 | 30..=37 | 39 | 40..=47 | 49 | 90..=97 | 100..=107 => {
-                            let mut v = heapless::Vec::<u8, heapless::consts::U5>::new();
-                            v.push(c).unwrap(); // graphics id
-                            let output = Output::Escape(AnsiSequence::SetGraphicsMode(v));
-                            graphics_chunk.push(output);
-                        }
+impl Cell {
+    // what erased characters are replaced with
+    fn blank() -> Self {
+        Self {
+            ch: ' ',
+            state: State::default(),
+        }
+    }
+}
 
-                        38 | 48 => {
-                            let graphics_type = i.next().unwrap().parse::<u8>().unwrap();
-                            let mut v = heapless::Vec::<u8, heapless::consts::U5>::new();
-
-                            if graphics_type == 2 {
-                                v.push(c).unwrap(); // 38 or 48
-                                v.push(graphics_type).unwrap(); // 2
-
-                                // r
-                                v.push(i.next().unwrap().parse::<u8>().unwrap()).unwrap();
-                                // g
-                                v.push(i.next().unwrap().parse::<u8>().unwrap()).unwrap();
-                                // b - but this one needs to be fixed as it may have the rest of the string in it
-                                let mut text = i.next().unwrap();
-                                let pos = text.chars().position(|c| c == 'm');
-                                if let Some(pos) = pos {
-                                    // slice off the text and leave only the number
-                                    text = &text[..pos];
-                                }
+// escape sequences ansi-parser doesn't understand, so they're handled before it sees them
+#[derive(Debug, PartialEq, Eq)]
+enum Sequence<'a> {
+    // ESC[nK, with its mode. ansi-parser only understands ESC[K
+    EraseLine(u8),
+    // OSC 8, ESC]8;params;URL ST. An empty url ends the link
+    Hyperlink(&'a str),
+    // any other OSC sequence, like setting the window title
+    Ignored,
+}
 
-                                let num = text.parse::<u8>().unwrap();
-                                v.push(num).unwrap();
-                            } else if graphics_type == 5 {
-                                v.push(c).unwrap(); // 38 or 48
-                                v.push(graphics_type).unwrap(); // 5
-
-                                // color - but this one needs to be fixed as it may have the rest of the string in it
-                                let mut text = i.next().unwrap();
-                                let pos = text.chars().position(|c| c == 'm');
-                                if let Some(pos) = pos {
-                                    // slice off the text and leave only the number
-                                    text = &text[..pos];
-                                }
+// find the next sequence ansi-parser needs help with. Returns its byte range as well
+fn find_sequence(text: &str) -> Option<(Range<usize>, Sequence<'_>)> {
+    text.match_indices('\x1b').find_map(|(start, _)| {
+        let rest = &text[start + 1..];
 
-                                let num = text.parse::<u8>().unwrap();
-                                v.push(num).unwrap();
-                            }
+        if let Some(params) = rest.strip_prefix('[') {
+            let digits = params.bytes().take_while(u8::is_ascii_digit).count();
 
-                            let output = Output::Escape(AnsiSequence::SetGraphicsMode(v));
-                            graphics_chunk.push(output);
-                        }
+            return (params.as_bytes().get(digits) == Some(&b'K')).then(|| {
+                let mode = params[..digits].parse().unwrap_or(0);
+                (start..start + digits + 3, Sequence::EraseLine(mode))
+            });
+        }
+
+        // OSC is terminated by either BEL or ST (ESC\)
+        let body = rest.strip_prefix(']')?;
+        let (len, terminator) = body.char_indices().find_map(|(i, c)| match c {
+            '\x07' => Some((i, 1)),
+            '\x1b' if body[i + 1..].starts_with('\\') => Some((i, 2)),
+            _ => None,
+        })?;
+
+        let range = start..start + 2 + len + terminator;
+
+        let sequence = match body[..len].strip_prefix("8;") {
+            Some(link) => Sequence::Hyperlink(link.split_once(';').map_or("", |(_, url)| url)),
+            None => Sequence::Ignored,
+        };
+
+        Some((range, sequence))
+    })
+}
+
+/// Parses text incrementally, so output can be parsed as it comes in instead of all over again.
+///
+/// Finished lines are never touched again, but the line the cursor is on can still be overwritten by
+/// carriage returns, cursor movement and erase sequences, so it's kept as cells and redrawn onto the
+/// end of the text whenever it changes
+#[derive(Debug, Clone, Default)]
+pub struct Parser {
+    state: State,
+    // stderr's styles when it's interleaved with stdout, swapped into state while parsing it
+    stderr_state: State,
+    /// The text with all the escape codes applied and stripped out
+    pub text: String,
+    pub properties: Vec<TextProperty>,
+    /// Targets of the OSC 8 hyperlinks, which properties refer to by index
+    pub links: Vec<String>,
+    line: Vec<Cell>,
+    // column of the cursor in the current line
+    cursor: usize,
+    // where the current line starts in text and properties
+    line_start: usize,
+    line_properties: usize,
+}
 
-                        _ => (),
+impl Parser {
+    /// Parse more text, continuing on from the end of the text which was already parsed.
+    /// Returns the index of the first property that was added or changed
+    pub fn push(&mut self, text: &str) -> usize {
+        // only the current line and anything after it can change
+        let changed = self.line_properties;
+
+        let mut rest = text;
+        while let Some((range, sequence)) = find_sequence(rest) {
+            self.parse(&rest[..range.start]);
+
+            match sequence {
+                Sequence::EraseLine(mode) => self.erase_line(mode),
+                Sequence::Hyperlink("") => self.state.link = None,
+                Sequence::Hyperlink(url) => {
+                    if self.links.last().map(String::as_str) != Some(url) {
+                        self.links.push(url.to_string());
                     }
+
+                    self.state.link = Some(self.links.len() - 1);
                 }
+                Sequence::Ignored => (),
+            }
+
+            rest = &rest[range.end..];
+        }
+        self.parse(rest);
+
+        self.draw_line();
+
+        changed
+    }
+
+    /// Parse stderr output interleaved with stdout. The two streams share the screen, but have their
+    /// own styles and the text is marked as coming from stderr
+    pub fn push_stderr(&mut self, text: &str) -> usize {
+        std::mem::swap(&mut self.state, &mut self.stderr_state);
+        self.state.stderr = true;
+
+        let changed = self.push(text);
+
+        std::mem::swap(&mut self.state, &mut self.stderr_state);
+
+        changed
+    }
+
+    /// Show input the user sent to the program. It's written at the cursor like the program's own
+    /// output, but isn't parsed for escape codes, and the text is marked as an echo
+    pub fn push_echo(&mut self, text: &str) -> usize {
+        let changed = self.line_properties;
+
+        let echo = State {
+            echo: true,
+            ..State::default()
+        };
+        let state = std::mem::replace(&mut self.state, echo);
+
+        for ch in text.chars() {
+            self.put(ch);
+        }
+
+        self.state = state;
+        self.draw_line();
+
+        changed
+    }
+
+    fn parse(&mut self, text: &str) {
+        for chunk in text.ansi_parse() {
+            self.process_chunk(chunk);
+        }
+    }
+
+    // write a character at the cursor
+    fn put(&mut self, ch: char) {
+        match ch {
+            '\r' => self.cursor = 0,
+
+            // backspace
+            '\x08' => self.cursor = self.cursor.saturating_sub(1),
+
+            '\n' => {
+                self.line.push(Cell {
+                    ch,
+                    state: self.state,
+                });
+                self.draw_line();
+
+                // the line is finished, so leave it as it is and start a new one
+                self.line.clear();
+                self.cursor = 0;
+                self.line_start = self.text.len();
+                self.line_properties = self.properties.len();
+            }
+
+            _ => {
+                let cell = Cell {
+                    ch,
+                    state: self.state,
+                };
 
-                // now, run this method again to process all the reaiming sequences that were missed
-                for chunk in graphics_chunk {
-                    process_chunk(
-                        chunk,
-                        properties,
-                        bold,
-                        dim,
-                        italic,
-                        underline,
-                        blink,
-                        reverse,
-                        hidden,
-                        strikethrough,
-                        fg,
-                        bg,
-                        text_counter,
-                    );
+                match self.line.get_mut(self.cursor) {
+                    Some(old) => *old = cell,
+                    None => self.line.push(cell),
                 }
 
-                // cleanup the text before continuing to process the text block
-                stripped = strip_ansi_escapes::strip(t.as_bytes()).unwrap();
-                t = std::str::from_utf8(&stripped).unwrap();
+                self.cursor += 1;
+            }
+        }
+    }
+
+    // ESC[0K erases to the end of the line, ESC[1K to the start, and ESC[2K the whole line.
+    // The cursor doesn't move
+    fn erase_line(&mut self, mode: u8) {
+        match mode {
+            0 => self.line.truncate(self.cursor),
+            1 => {
+                let end = (self.cursor + 1).min(self.line.len());
+                self.line[..end].fill(Cell::blank());
             }
+            2 => {
+                self.line.truncate(self.cursor);
+                self.line.fill(Cell::blank());
+            }
+            _ => (),
+        }
+    }
 
-            let style = TextStyle {
-                bold: *bold,
-                dim: *dim,
-                italic: *italic,
-                underline: *underline,
-                blink: *blink,
-                reverse: *reverse,
-                hidden: *hidden,
-                strikethrough: *strikethrough,
-            };
-
-            let len = t.len();
-
-            let property = TextProperty {
-                start: *text_counter,
-                end: *text_counter + len,
+    // redraw the current line onto the end of the text and properties
+    fn draw_line(&mut self) {
+        self.text.truncate(self.line_start);
+        self.properties.truncate(self.line_properties);
+
+        for cell in &self.line {
+            let start = self.text.len();
+            self.text.push(cell.ch);
+            let end = self.text.len();
+
+            let State {
                 style,
-                fg: *fg,
-                bg: *bg,
-            };
+                fg,
+                bg,
+                link,
+                stderr,
+                echo,
+            } = cell.state;
+
+            // continue on the last property if the style didn't change
+            match self.properties[self.line_properties..].last_mut() {
+                Some(last)
+                    if last.style == style
+                        && last.fg == fg
+                        && last.bg == bg
+                        && last.link == link
+                        && last.stderr == stderr
+                        && last.echo == echo =>
+                {
+                    last.end = end;
+                }
 
-            if property.end > 0 {
-                properties.push(property);
+                _ => self.properties.push(TextProperty {
+                    start,
+                    end,
+                    style,
+                    fg,
+                    bg,
+                    link,
+                    stderr,
+                    echo,
+                }),
             }
-
-            *text_counter += len;
         }
+    }
 
-        Output::Escape(e) => {
-            match e {
-                AnsiSequence::SetGraphicsMode(m) => {
-                    // parse multi color codes independently
-                    match m[0] {
-                        38 => {
-                            if m[1] == 5 {
-                                *fg = Some(parse_rgb(m[2]));
-                            } else if m[1] == 2 {
-                                *fg = Some(Color::Rgb(m[2], m[3], m[4]));
-                            }
+    fn process_chunk(&mut self, chunk: Output) {
+        match chunk {
+            Output::TextBlock(mut t) => {
+                // ansi-parser fails to strip escape codes in some text
+                // https://gitlab.com/davidbittner/ansi-parser/-/issues/9
+                // Due to this bug, I am forced to do this ugly workaround so I can actually process everything
+                let stripped;
+                if t.contains('\x1b') {
+                    let mut graphics_chunk = vec![];
+
+                    let mut i = t.split(';');
+                    while let Some(mut c) = i.next() {
+                        // escapes other than CSI ones, like `ESC 7`, don't parse as a mode below
+                        if c.starts_with('\x1b') {
+                            c = c.strip_prefix("\x1b[").unwrap_or(c);
                         }
-                        48 => {
-                            if m[1] == 5 {
-                                *bg = Some(parse_rgb(m[2]));
-                            } else if m[1] == 2 {
-                                *bg = Some(Color::Rgb(m[2], m[3], m[4]));
+
+                        // not a graphics mode, so there's nothing else to get out of it
+                        let Ok(c) = c.parse::<u8>() else {
+                            break;
+                        };
+
+                        match c {
+                            0..=5
+                            | 7..=9
+                            | 22..=25
+                            | 27..=29
+                            | 30..=37
+                            | 39
+                            | 40..=47
+                            | 49
+                            | 90..=97
+                            | 100..=107 => {
+                                let mut v = heapless::Vec::<u8, heapless::consts::U5>::new();
+                                v.push(c).unwrap(); // graphics id
+                                let output = Output::Escape(AnsiSequence::SetGraphicsMode(v));
+                                graphics_chunk.push(output);
+                            }
+
+                            38 | 48 => {
+                                // a missing or out of range parameter leaves the color out
+                                let params = match color_param(i.next()) {
+                                    Some(2) => [i.next(), i.next(), i.next()]
+                                        .map(color_param)
+                                        .into_iter()
+                                        .collect::<Option<Vec<_>>>()
+                                        .map(|rgb| [vec![c, 2], rgb].concat()),
+                                    Some(5) => color_param(i.next()).map(|color| vec![c, 5, color]),
+                                    _ => None,
+                                };
+                                let Some(params) = params else {
+                                    break;
+                                };
+
+                                let mut v = heapless::Vec::<u8, heapless::consts::U5>::new();
+                                for param in params {
+                                    v.push(param).unwrap();
+                                }
+
+                                let output = Output::Escape(AnsiSequence::SetGraphicsMode(v));
+                                graphics_chunk.push(output);
                             }
+
+                            _ => (),
                         }
+                    }
 
-                        _ => (),
+                    // now, run this method again to process all the reaiming sequences that were missed
+                    for chunk in graphics_chunk {
+                        self.process_chunk(chunk);
                     }
 
-                    // these can have multiple commands, so loop them
-                    for c in m {
-                        match c {
-                            // reset all modes
-                            0 => {
-                                *bold = false;
-                                *dim = false;
-                                *italic = false;
-                                *underline = false;
-                                *blink = false;
-                                *reverse = false;
-                                *hidden = false;
-                                *strikethrough = false;
-                                *fg = None;
-                                *bg = None;
-                            }
+                    // cleanup the text before continuing to process the text block.
+                    // ansi-parser splits text blocks at every escape, so the escape is always at the start.
+                    // Only that gets cut off, since stripping everything would lose the control characters
+                    if let Some(rest) = t.strip_prefix("\x1b[") {
+                        let params = rest
+                            .bytes()
+                            .take_while(|b| (0x20..0x40).contains(b))
+                            .count();
+                        // skip the final byte of the sequence too
+                        t = rest.get(params + 1..).unwrap_or_default();
+                    } else {
+                        stripped = strip_ansi_escapes::strip(t.as_bytes()).unwrap();
+                        t = std::str::from_utf8(&stripped).unwrap();
+                    }
+                }
 
-                            // set bold -> 22 reset
-                            1 => *bold = true,
+                for ch in t.chars() {
+                    self.put(ch);
+                }
+            }
 
-                            // set dim/faint -> 22 reset
-                            2 => *dim = true,
+            Output::Escape(e) => {
+                let state = &mut self.state;
+                let style = &mut state.style;
+
+                match e {
+                    AnsiSequence::SetGraphicsMode(m) => {
+                        // these can have multiple commands, so loop them
+                        let mut codes = m.into_iter();
+                        while let Some(c) = codes.next() {
+                            match c {
+                                // reset all modes
+                                0 => {
+                                    style.bold = false;
+                                    style.dim = false;
+                                    style.italic = false;
+                                    style.underline = false;
+                                    style.blink = false;
+                                    style.reverse = false;
+                                    style.hidden = false;
+                                    style.strikethrough = false;
+                                    state.fg = None;
+                                    state.bg = None;
+                                }
 
-                            // set italic -> 23 reset
-                            3 => *italic = true,
+                                // set bold -> 22 reset
+                                1 => style.bold = true,
 
-                            // set underline -> 24 reset
-                            4 => *underline = true,
+                                // set dim/faint -> 22 reset
+                                2 => style.dim = true,
 
-                            // set blink -> 25 reset
-                            5 => *blink = true,
+                                // set italic -> 23 reset
+                                3 => style.italic = true,
 
-                            // set inverse/reverse -> 27 reset
-                            7 => *reverse = true,
+                                // set underline -> 24 reset
+                                4 => style.underline = true,
 
-                            // set hidden -> 28 reset
-                            8 => *hidden = true,
+                                // set blink -> 25 reset
+                                5 => style.blink = true,
 
-                            // set strikethrough -> 29 reset
-                            9 => *strikethrough = true,
+                                // set inverse/reverse -> 27 reset
+                                7 => style.reverse = true,
 
-                            30 => *fg = Some(Color::Black),
-                            40 => *bg = Some(Color::Black),
+                                // set hidden -> 28 reset
+                                8 => style.hidden = true,
 
-                            31 => *fg = Some(Color::Red),
-                            41 => *bg = Some(Color::Red),
+                                // set strikethrough -> 29 reset
+                                9 => style.strikethrough = true,
 
-                            32 => *fg = Some(Color::Green),
-                            42 => *bg = Some(Color::Green),
+                                // reset bold and dim
+                                22 => {
+                                    style.bold = false;
+                                    style.dim = false;
+                                }
 
-                            33 => *fg = Some(Color::Yellow),
-                            43 => *bg = Some(Color::Yellow),
+                                23 => style.italic = false,
+                                24 => style.underline = false,
+                                25 => style.blink = false,
+                                27 => style.reverse = false,
+                                28 => style.hidden = false,
+                                29 => style.strikethrough = false,
 
-                            34 => *fg = Some(Color::Blue),
-                            44 => *bg = Some(Color::Blue),
+                                30 => state.fg = Some(Color::Black),
+                                40 => state.bg = Some(Color::Black),
 
-                            35 => *fg = Some(Color::Magenta),
-                            45 => *bg = Some(Color::Magenta),
+                                31 => state.fg = Some(Color::Red),
+                                41 => state.bg = Some(Color::Red),
 
-                            36 => *fg = Some(Color::Cyan),
-                            46 => *bg = Some(Color::Cyan),
+                                32 => state.fg = Some(Color::Green),
+                                42 => state.bg = Some(Color::Green),
 
-                            37 => *fg = Some(Color::White),
-                            47 => *bg = Some(Color::White),
+                                33 => state.fg = Some(Color::Yellow),
+                                43 => state.bg = Some(Color::Yellow),
 
-                            // Default
-                            39 => *fg = None,
-                            49 => *bg = None,
+                                34 => state.fg = Some(Color::Blue),
+                                44 => state.bg = Some(Color::Blue),
 
-                            90 => *fg = Some(Color::BrightBlack),
-                            100 => *bg = Some(Color::BrightBlack),
+                                35 => state.fg = Some(Color::Magenta),
+                                45 => state.bg = Some(Color::Magenta),
 
-                            91 => *fg = Some(Color::BrightRed),
-                            101 => *bg = Some(Color::BrightRed),
+                                36 => state.fg = Some(Color::Cyan),
+                                46 => state.bg = Some(Color::Cyan),
 
-                            92 => *fg = Some(Color::BrightGreen),
-                            102 => *bg = Some(Color::BrightGreen),
+                                37 => state.fg = Some(Color::White),
+                                47 => state.bg = Some(Color::White),
 
-                            93 => *fg = Some(Color::BrightYellow),
-                            103 => *bg = Some(Color::BrightYellow),
+                                // Default
+                                39 => state.fg = None,
+                                49 => state.bg = None,
 
-                            94 => *fg = Some(Color::BrightBlue),
-                            104 => *bg = Some(Color::BrightBlue),
+                                90 => state.fg = Some(Color::BrightBlack),
+                                100 => state.bg = Some(Color::BrightBlack),
 
-                            95 => *fg = Some(Color::BrightMagenta),
-                            105 => *bg = Some(Color::BrightMagenta),
+                                91 => state.fg = Some(Color::BrightRed),
+                                101 => state.bg = Some(Color::BrightRed),
 
-                            96 => *fg = Some(Color::BrightCyan),
-                            106 => *bg = Some(Color::BrightCyan),
+                                92 => state.fg = Some(Color::BrightGreen),
+                                102 => state.bg = Some(Color::BrightGreen),
 
-                            97 => *fg = Some(Color::BrightWhite),
-                            107 => *bg = Some(Color::BrightWhite),
+                                93 => state.fg = Some(Color::BrightYellow),
+                                103 => state.bg = Some(Color::BrightYellow),
 
-                            _ => break,
+                                94 => state.fg = Some(Color::BrightBlue),
+                                104 => state.bg = Some(Color::BrightBlue),
+
+                                95 => state.fg = Some(Color::BrightMagenta),
+                                105 => state.bg = Some(Color::BrightMagenta),
+
+                                96 => state.fg = Some(Color::BrightCyan),
+                                106 => state.bg = Some(Color::BrightCyan),
+
+                                97 => state.fg = Some(Color::BrightWhite),
+                                107 => state.bg = Some(Color::BrightWhite),
+
+                                // extended colors take their arguments from the following codes
+                                38 => state.fg = parse_extended_color(&mut codes),
+                                48 => state.bg = parse_extended_color(&mut codes),
+
+                                _ => break,
+                            }
                         }
                     }
-                }
 
-                AnsiSequence::SetMode(_) => todo!(),
-                AnsiSequence::ResetMode(_) => todo!(),
-                _ => (),
+                    AnsiSequence::CursorBackward(columns) => {
+                        self.cursor = self.cursor.saturating_sub(columns as usize);
+                    }
+
+                    // screen modes and the rest don't mean anything in a scrollback
+                    _ => (),
+                }
             }
         }
     }
 }
 
-#[derive(Debug)]
-pub struct Parsed {
-    pub properties: Vec<TextProperty>,
-}
-
-#[derive(Debug, Hash, Copy, Clone)]
+#[derive(Debug, Hash, Copy, Clone, PartialEq, Eq)]
 pub struct TextProperty {
     pub start: usize,
     pub end: usize,
     pub style: TextStyle,
     pub fg: Option<Color>,
     pub bg: Option<Color>,
+    // index into the parser's links, if this text is a hyperlink
+    pub link: Option<usize>,
+    // the text came from stderr, see Parser::push_stderr
+    pub stderr: bool,
+    // the text is input the user sent, see Parser::push_echo
+    pub echo: bool,
 }
 
-#[derive(Debug, Copy, Clone, Default, Hash)]
+#[derive(Debug, Copy, Clone, Default, Hash, PartialEq, Eq)]
 pub struct TextStyle {
     pub bold: bool,
     pub dim: bool,
@@ -378,7 +588,7 @@ pub struct TextStyle {
     pub strikethrough: bool,
 }
 
-#[derive(Debug, Copy, Clone, Hash)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum Color {
     Black,
     Red,
@@ -396,5 +606,382 @@ pub enum Color {
     BrightMagenta,
     BrightCyan,
     BrightWhite,
+    // from the 256 color palette, see indexed_color
+    Indexed(u8),
     Rgb(u8, u8, u8),
 }
+
+impl Color {
+    /// The bright variant of a regular color, other colors are left as they are
+    pub fn bright(self) -> Self {
+        match self {
+            Color::Black => Color::BrightBlack,
+            Color::Red => Color::BrightRed,
+            Color::Green => Color::BrightGreen,
+            Color::Yellow => Color::BrightYellow,
+            Color::Blue => Color::BrightBlue,
+            Color::Magenta => Color::BrightMagenta,
+            Color::Cyan => Color::BrightCyan,
+            Color::White => Color::BrightWhite,
+            Color::Indexed(index @ 0..=7) => Color::Indexed(index + 8),
+            color => color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<TextProperty> {
+        let mut parser = Parser::default();
+        parser.push(text);
+        parser.properties
+    }
+
+    const PIECES: &[&str] = &[
+        "hello",
+        " wörld ",
+        "\n",
+        "\r",
+        "\x1b[K",
+        "\x1b[2K",
+        "\x1b[1K",
+        "\x1b[3D",
+        "\x1b]8;;https://example.com\x1b\\",
+        "\x1b]8;;\x07",
+        "\x1b[31m",
+        "\x1b[42m",
+        "\x1b[1;32m",
+        "\x1b[4m",
+        "\x1b[2;7m",
+        "\x1b[22m",
+        "\x1b[27m",
+        "\x1b[38;2;10;20;30m",
+        "\x1b[38;5;208m",
+        "\x1b[1;48;5;21m",
+        "\x1b[39m",
+        "\x1b[0m",
+    ];
+
+    // small deterministic rng, so failures are reproducible
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    // random messages, each made up of whole pieces like real output would be
+    fn random_messages(seed: u64) -> Vec<String> {
+        let mut rng = seed;
+
+        (0..xorshift(&mut rng) % 20 + 1)
+            .map(|_| {
+                (0..xorshift(&mut rng) % 6 + 1)
+                    .map(|_| PIECES[xorshift(&mut rng) as usize % PIECES.len()])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn incremental_matches_full_parse() {
+        for seed in 1..=500 {
+            let messages = random_messages(seed);
+
+            let mut parser = Parser::default();
+            for message in &messages {
+                let changed = parser.push(message);
+                assert!(changed <= parser.properties.len());
+            }
+
+            let mut full = Parser::default();
+            full.push(&messages.concat());
+            assert_eq!(full.text, parser.text, "seed {seed}");
+            assert_eq!(full.properties, parser.properties, "seed {seed}");
+            assert_consistent(&parser);
+        }
+    }
+
+    #[test]
+    fn push_returns_first_changed_property() {
+        let mut parser = Parser::default();
+
+        assert_eq!(parser.push("\x1b[31mred"), 0);
+        assert_eq!(parser.properties.len(), 1);
+
+        // same style, so the last property is extended
+        assert_eq!(parser.push(" still red"), 0);
+        assert_eq!(parser.properties.len(), 1);
+        assert_eq!(parser.properties[0].end, 13);
+
+        assert_eq!(parser.push("\x1b[32mgreen\n"), 0);
+        assert_eq!(parser.properties.len(), 2);
+
+        // finished lines don't change anymore
+        assert_eq!(parser.push("\x1b[0mplain"), 2);
+        assert_eq!(parser.properties.len(), 3);
+    }
+
+    #[test]
+    fn indexed_color_mapping() {
+        assert_eq!(indexed_color(1), Color::Red);
+        assert_eq!(indexed_color(15), Color::BrightWhite);
+
+        // color cube
+        assert_eq!(indexed_color(16), Color::Rgb(0, 0, 0));
+        assert_eq!(indexed_color(196), Color::Rgb(255, 0, 0));
+        assert_eq!(indexed_color(208), Color::Rgb(255, 135, 0));
+        assert_eq!(indexed_color(231), Color::Rgb(255, 255, 255));
+
+        // grayscale ramp
+        assert_eq!(indexed_color(232), Color::Rgb(8, 8, 8));
+        assert_eq!(indexed_color(255), Color::Rgb(238, 238, 238));
+    }
+
+    #[test]
+    fn indexed_fg_and_bg() {
+        let properties = parse("\x1b[38;5;208mfg\x1b[48;5;21mbg");
+
+        assert_eq!(properties[0].fg, Some(Color::Indexed(208)));
+        assert_eq!(properties[0].bg, None);
+        assert_eq!(properties[1].fg, Some(Color::Indexed(208)));
+        assert_eq!(properties[1].bg, Some(Color::Indexed(21)));
+    }
+
+    #[test]
+    fn indexed_mixed_with_other_codes() {
+        let properties = parse("\x1b[1;38;5;208mbold orange\x1b[0m plain");
+
+        assert!(properties[0].style.bold);
+        assert_eq!(properties[0].fg, Some(Color::Indexed(208)));
+
+        assert!(!properties[1].style.bold);
+        assert_eq!(properties[1].fg, None);
+    }
+
+    #[test]
+    fn rgb_color() {
+        let properties = parse("\x1b[38;2;10;20;30mrgb");
+
+        assert_eq!(properties[0].fg, Some(Color::Rgb(10, 20, 30)));
+    }
+
+    #[test]
+    fn malformed_sequences_are_ignored() {
+        let mut parser = Parser::default();
+        parser.push("a\x1b[38;5;300mb\x1b[38;2;1;2mc\x1b7d\x1b[=7he\x1b[?25lf\n");
+
+        assert_eq!(parser.text, "abcdef\n");
+        assert!(parser
+            .properties
+            .iter()
+            .all(|property| property.fg.is_none()));
+
+        // what follows is still colored
+        let properties = parse("\x1b[38;5;300mx\x1b[31my");
+        assert_eq!(properties.last().unwrap().fg, Some(Color::Red));
+    }
+
+    #[test]
+    fn attributes_stack() {
+        let properties = parse("\x1b[1mbold\x1b[2mdim\x1b[7mreverse");
+
+        assert!(properties[0].style.bold);
+        assert!(!properties[0].style.dim);
+
+        assert!(properties[1].style.bold && properties[1].style.dim);
+
+        let style = properties[2].style;
+        assert!(style.bold && style.dim && style.reverse);
+    }
+
+    #[test]
+    fn attributes_reset() {
+        let properties = parse("\x1b[1;2;7mall\x1b[22mreverse\x1b[27mplain");
+
+        let style = properties[1].style;
+        assert!(!style.bold && !style.dim && style.reverse);
+
+        assert_eq!(properties[2].style, TextStyle::default());
+    }
+
+    #[test]
+    fn reset_keeps_colors() {
+        let properties = parse("\x1b[1;31mbold red\x1b[22mred");
+
+        assert!(properties[0].style.bold);
+        assert!(!properties[1].style.bold);
+        assert_eq!(properties[1].fg, Some(Color::Red));
+    }
+
+    #[test]
+    fn bright_colors() {
+        assert_eq!(Color::Red.bright(), Color::BrightRed);
+        assert_eq!(Color::BrightRed.bright(), Color::BrightRed);
+        assert_eq!(Color::Indexed(1).bright(), Color::Indexed(9));
+        assert_eq!(Color::Indexed(208).bright(), Color::Indexed(208));
+        assert_eq!(Color::Rgb(1, 2, 3).bright(), Color::Rgb(1, 2, 3));
+    }
+
+    // every byte of the text should be covered by exactly one property, in order
+    fn assert_consistent(parser: &Parser) {
+        let mut end = 0;
+        for property in &parser.properties {
+            assert_eq!(property.start, end);
+            assert!(property.end > property.start);
+            end = property.end;
+        }
+
+        assert_eq!(end, parser.text.len());
+    }
+
+    #[test]
+    fn carriage_return_overwrites() {
+        let mut parser = Parser::default();
+        parser.push("abcdef\r");
+        parser.push("xy\n");
+
+        assert_eq!(parser.text, "xycdef\n");
+        assert_consistent(&parser);
+    }
+
+    #[test]
+    fn erase_line() {
+        let mut parser = Parser::default();
+        parser.push("abcdef\rxy\x1b[K\n");
+        parser.push("abcdef\x1b[2Kxy\n");
+        parser.push("abcdef\x1b[3D\x1b[1Kxy\n");
+
+        assert_eq!(parser.text, "xy\n      xy\n   xyf\n");
+        assert_consistent(&parser);
+    }
+
+    #[test]
+    fn cursor_backward() {
+        let mut parser = Parser::default();
+        parser.push("abc\x1b[2Dz\x08\x08y\n");
+
+        assert_eq!(parser.text, "yzc\n");
+    }
+
+    #[test]
+    fn overwrite_keeps_styles_per_character() {
+        let properties = parse("\x1b[31mred\r\x1b[32mg");
+
+        assert_eq!(properties.len(), 2);
+        assert_eq!((properties[0].start, properties[0].end), (0, 1));
+        assert_eq!(properties[0].fg, Some(Color::Green));
+        assert_eq!((properties[1].start, properties[1].end), (1, 3));
+        assert_eq!(properties[1].fg, Some(Color::Red));
+    }
+
+    #[test]
+    fn progress_bar() {
+        let mut parser = Parser::default();
+        parser.push("   Compiling foo v0.1.0\n");
+
+        // what indicatif sends when redrawing a bar
+        for i in 0..=10 {
+            let bar = format!("{}{}", "#".repeat(i), "-".repeat(10 - i));
+            parser.push(&format!("\r\x1b[2K\x1b[32m[{bar}]\x1b[0m {i}/10"));
+        }
+        parser.push("\n");
+
+        assert_eq!(parser.text, "   Compiling foo v0.1.0\n[##########] 10/10\n");
+        assert_consistent(&parser);
+
+        let mut full = Parser::default();
+        full.push(&format!(
+            "   Compiling foo v0.1.0\n{}\n",
+            (0..=10)
+                .map(|i| format!(
+                    "\r\x1b[2K\x1b[32m[{}{}]\x1b[0m {i}/10",
+                    "#".repeat(i),
+                    "-".repeat(10 - i)
+                ))
+                .collect::<String>()
+        ));
+        assert_eq!(full.text, parser.text);
+        assert_eq!(full.properties, parser.properties);
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let mut parser = Parser::default();
+        parser.push("see \x1b]8;;https://example.com\x1b\\here\x1b]8;;\x1b\\ and ");
+        parser.push("\x1b]8;id=1;https://rust-lang.org\x07there\x1b]8;;\x07\n");
+
+        assert_eq!(parser.text, "see here and there\n");
+        assert_eq!(
+            parser.links,
+            ["https://example.com", "https://rust-lang.org"]
+        );
+
+        let links = parser
+            .properties
+            .iter()
+            .filter_map(|p| Some((&parser.text[p.start..p.end], p.link?)))
+            .collect::<Vec<_>>();
+        assert_eq!(links, [("here", 0), ("there", 1)]);
+        assert_consistent(&parser);
+    }
+
+    #[test]
+    fn other_osc_sequences_are_stripped() {
+        let mut parser = Parser::default();
+        parser.push("\x1b]0;window title\x07text\n");
+
+        assert_eq!(parser.text, "text\n");
+        assert!(parser.properties.iter().all(|p| p.link.is_none()));
+    }
+
+    #[test]
+    fn interleaved_streams_keep_their_own_styles() {
+        let mut parser = Parser::default();
+        parser.push("\x1b[32mgreen ");
+        parser.push_stderr("error\n");
+        parser.push("still green\n");
+
+        let properties = parser
+            .properties
+            .iter()
+            .map(|p| (&parser.text[p.start..p.end], p.fg, p.stderr))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            properties,
+            [
+                ("green ", Some(Color::Green), false),
+                ("error\n", None, true),
+                ("still green\n", Some(Color::Green), false),
+            ]
+        );
+        assert_consistent(&parser);
+    }
+
+    #[test]
+    fn echo_is_not_parsed() {
+        let mut parser = Parser::default();
+        parser.push("\x1b[32mname: ");
+        parser.push_echo("\x1b[1mcherry\n");
+        parser.push("hi\n");
+
+        assert_eq!(parser.text, "name: \x1b[1mcherry\nhi\n");
+
+        let properties = parser
+            .properties
+            .iter()
+            .map(|p| (&parser.text[p.start..p.end], p.fg, p.echo))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            properties,
+            [
+                ("name: ", Some(Color::Green), false),
+                ("\x1b[1mcherry\n", None, true),
+                ("hi\n", Some(Color::Green), false),
+            ]
+        );
+        assert_consistent(&parser);
+    }
+}
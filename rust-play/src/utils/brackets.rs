@@ -0,0 +1,148 @@
+use super::lexer;
+
+/// The bracket next to the cursor and where it's closed or opened, by byte offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BracketMatch {
+    Matched { bracket: usize, partner: usize },
+    Unmatched(usize),
+}
+
+fn closer(opener: u8) -> Option<u8> {
+    match opener {
+        b'(' => Some(b')'),
+        b'[' => Some(b']'),
+        b'{' => Some(b'}'),
+        _ => None,
+    }
+}
+
+/// The byte offset of each bracket in the code, with the offset of the one it pairs with when
+/// there is one. Brackets in comments, strings and chars don't count. A closer which doesn't
+/// close the last opener is left unmatched, so `(]` has no pairs
+pub fn pairs(code: &str) -> Vec<(usize, Option<usize>)> {
+    let tokens = lexer::tokens(code);
+    let mut tokens = tokens.iter().peekable();
+    let mut pairs: Vec<(usize, Option<usize>)> = vec![];
+    // indices into `pairs` of the openers not closed yet
+    let mut open: Vec<usize> = vec![];
+
+    for (at, byte) in code.bytes().enumerate() {
+        while tokens.next_if(|token| token.range.end <= at).is_some() {}
+        if tokens.peek().is_some_and(|token| token.range.start <= at) {
+            continue;
+        }
+
+        if closer(byte).is_some() {
+            open.push(pairs.len());
+            pairs.push((at, None));
+        } else if matches!(byte, b')' | b']' | b'}') {
+            let opener = open
+                .last()
+                .copied()
+                .filter(|index| closer(code.as_bytes()[pairs[*index].0]) == Some(byte));
+
+            match opener {
+                Some(index) => {
+                    open.pop();
+                    pairs[index].1 = Some(at);
+                    pairs.push((at, Some(pairs[index].0)));
+                }
+                None => pairs.push((at, None)),
+            }
+        }
+    }
+
+    pairs
+}
+
+/// The bracket right after the cursor, or else the one right before it, and its partner.
+/// `cursor` is a byte offset
+pub fn find_match(code: &str, cursor: usize) -> Option<BracketMatch> {
+    let pairs = pairs(code);
+    let after = [Some(cursor), cursor.checked_sub(1)];
+
+    after.into_iter().flatten().find_map(|at| {
+        let index = pairs
+            .binary_search_by_key(&at, |(bracket, _)| *bracket)
+            .ok()?;
+
+        Some(match pairs[index] {
+            (bracket, Some(partner)) => BracketMatch::Matched { bracket, partner },
+            (bracket, None) => BracketMatch::Unmatched(bracket),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the partner of the bracket at the first `|`, with the `|` taken out
+    fn partner(code: &str) -> Option<BracketMatch> {
+        let cursor = code.find('|').unwrap();
+        find_match(&code.replace('|', ""), cursor)
+    }
+
+    #[test]
+    fn nested_brackets_are_matched() {
+        let code = "fn a() { if b { c[0]; } }";
+
+        assert_eq!(
+            partner("fn a() |{ if b { c[0]; } }"),
+            Some(BracketMatch::Matched {
+                bracket: 7,
+                partner: code.len() - 1
+            })
+        );
+        // the one before the cursor, when there's none after it
+        assert_eq!(
+            partner("fn a() { if b { c[0]|; } }"),
+            Some(BracketMatch::Matched {
+                bracket: 19,
+                partner: 17
+            })
+        );
+        assert_eq!(partner("fn a() { |if b"), None);
+    }
+
+    #[test]
+    fn unmatched_brackets_are_found() {
+        assert_eq!(partner("|(]"), Some(BracketMatch::Unmatched(0)));
+        assert_eq!(partner("(|]"), Some(BracketMatch::Unmatched(1)));
+        assert_eq!(partner("foo(|"), Some(BracketMatch::Unmatched(3)));
+        // the stray closer doesn't take the opener's partner away
+        assert_eq!(partner("(|])"), Some(BracketMatch::Unmatched(1)));
+        assert_eq!(
+            partner("|(])"),
+            Some(BracketMatch::Matched {
+                bracket: 0,
+                partner: 2
+            })
+        );
+    }
+
+    #[test]
+    fn brackets_in_literals_dont_count() {
+        assert_eq!(
+            partner(r##"|(r#"(")"#, "]", ')', /* { */ b']') // )"##),
+            Some(BracketMatch::Matched {
+                bracket: 0,
+                partner: 33
+            })
+        );
+        assert_eq!(
+            partner("fn f<'a>|(x: &'a str) -> char { '{' }"),
+            Some(BracketMatch::Matched {
+                bracket: 8,
+                partner: 19
+            })
+        );
+        assert_eq!(
+            partner("fn f<'a>(x: &'a str) -> char |{ '{' }"),
+            Some(BracketMatch::Matched {
+                bracket: 29,
+                partner: 35
+            })
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the scratches are built. The projects are kept between runs, one per tab, so only what changed
+/// gets rebuilt and tabs running at the same time never share a target directory
+pub fn cache_dir() -> PathBuf {
+    env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("RustPlay")
+        .join("builds")
+}
+
+/// The size of all the files in a directory. Symlinks aren't followed
+pub fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+/// Delete a directory and everything in it, returning how many bytes were freed. A directory which
+/// doesn't exist frees nothing
+pub fn clean(path: &Path) -> io::Result<u64> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let size = dir_size(path)?;
+    fs::remove_dir_all(path)?;
+
+    Ok(size)
+}
+
+/// A size in bytes the way file explorers show it, like `1.5 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn cleans_and_measures_directories() {
+        let dir = test_dir("cache");
+        fs::create_dir_all(dir.join("target").join("debug")).unwrap();
+        fs::write(dir.join("Cargo.toml"), [0; 100]).unwrap();
+        fs::write(dir.join("target").join("debug").join("main.exe"), [0; 2000]).unwrap();
+
+        assert_eq!(dir_size(&dir).unwrap(), 2100);
+        assert_eq!(clean(&dir).unwrap(), 2100);
+        assert!(!dir.exists());
+        // already gone
+        assert_eq!(clean(&dir).unwrap(), 0);
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(0), "0 bytes");
+        assert_eq!(format_size(1023), "1023 bytes");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+    }
+}
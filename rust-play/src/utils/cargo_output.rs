@@ -0,0 +1,27 @@
+/// Whether a line of cargo's output is the one it prints right before starting the program, like
+/// `Running `target/debug/p1``. `cargo test` prints one for each test binary
+pub fn starts_program(line: &str) -> bool {
+    // the status is colored
+    let line = strip_ansi_escapes::strip(line.as_bytes())
+        .map(|stripped| String::from_utf8_lossy(&stripped).into_owned())
+        .unwrap_or_else(|_| line.to_string());
+
+    line.trim_start().starts_with("Running ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_running_line() {
+        assert!(starts_program(
+            "\x1b[0m\x1b[0m\x1b[1m\x1b[32m     Running\x1b[0m\x1b[0m `target\\debug\\p1.exe`\n"
+        ));
+        assert!(starts_program(
+            "     Running unittests src\\main.rs (target\\debug\\deps\\p1-2f1c.exe)\n"
+        ));
+        assert!(!starts_program("   Compiling p1 v0.1.0\n"));
+        assert!(!starts_program("println!(\"Running late\");\n"));
+    }
+}
@@ -0,0 +1,168 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use clap::error::ErrorKind;
+use clap::{value_parser, Arg, ArgAction, Command};
+
+/// What the app was started with from the command line
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    // opened in tabs on startup, file:// urls are given by their path
+    pub files: Vec<PathBuf>,
+    // the files are run once they're open
+    pub run: bool,
+    // the file is run without a window, its output goes to the terminal the app was started from
+    pub headless: bool,
+}
+
+fn command() -> Command<'static> {
+    Command::new("rustplay")
+        .about("A playground for Rust scratches")
+        .arg(
+            Arg::new("files")
+                .help("The .rs files to open in tabs")
+                .value_parser(value_parser!(PathBuf))
+                .multiple_values(true),
+        )
+        .arg(
+            Arg::new("run")
+                .long("run")
+                .help("Run the files once they're open")
+                .action(ArgAction::SetTrue)
+                .requires("files"),
+        )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .help("Run the file without a window, exiting with its exit code")
+                .action(ArgAction::SetTrue)
+                .requires("run"),
+        )
+}
+
+// The path of a file:// url, which is what some programs open files with. Anything else is a path
+// already
+fn file_path(arg: &Path) -> PathBuf {
+    let Some(url) = arg.to_str().and_then(|arg| arg.strip_prefix("file://")) else {
+        return arg.to_path_buf();
+    };
+
+    // file:///C:/foo.rs has the drive after the slash
+    let url = match url.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &url[1..],
+        _ => url,
+    };
+
+    let bytes = url.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut at = 0;
+    while at < bytes.len() {
+        let escaped = bytes
+            .get(at + 1..at + 3)
+            .filter(|_| bytes[at] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                at += 3;
+            }
+            None => {
+                decoded.push(bytes[at]);
+                at += 1;
+            }
+        }
+    }
+
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Parse the arguments, the first being the app itself. The error is clap's, which also covers
+/// --help
+pub fn parse<I, T>(args: I) -> Result<Args, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut command = command();
+    let matches = command.try_get_matches_from_mut(args)?;
+
+    let args = Args {
+        files: matches
+            .get_many::<PathBuf>("files")
+            .map(|files| files.map(|file| file_path(file)).collect())
+            .unwrap_or_default(),
+        run: matches.get_flag("run"),
+        headless: matches.get_flag("headless"),
+    };
+
+    // there's only the one terminal to print to and exit code to exit with
+    if args.headless && args.files.len() > 1 {
+        return Err(command.error(
+            ErrorKind::WrongNumberOfValues,
+            "--headless runs a single file",
+        ));
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, ErrorKind> {
+        super::parse(["rustplay"].iter().chain(args)).map_err(|e| e.kind())
+    }
+
+    #[test]
+    fn files_are_opened_and_run() {
+        assert_eq!(parse(&[]), Ok(Args::default()));
+
+        let args = parse(&["foo.rs", "bar.rs"]).unwrap();
+        assert_eq!(
+            args.files,
+            [PathBuf::from("foo.rs"), PathBuf::from("bar.rs")]
+        );
+        assert!(!args.run);
+
+        let args = parse(&["--run", "foo.rs"]).unwrap();
+        assert_eq!(args.files, [PathBuf::from("foo.rs")]);
+        assert!(args.run && !args.headless);
+
+        let args = parse(&["--headless", "--run", "foo.rs"]).unwrap();
+        assert!(args.run && args.headless);
+    }
+
+    #[test]
+    fn file_urls_are_paths() {
+        let args = parse(&[
+            "file:///C:/My%20Scratches/foo.rs",
+            "file:///tmp/b%C3%A4r.rs",
+        ]);
+        let files = args.unwrap().files;
+        assert_eq!(files[0], PathBuf::from("C:/My Scratches/foo.rs"));
+        assert_eq!(files[1], PathBuf::from("/tmp/bär.rs"));
+
+        // a % which doesn't escape anything is kept
+        let files = parse(&["file:///C:/100%.rs", "50%.rs"]).unwrap().files;
+        assert_eq!(
+            files,
+            [PathBuf::from("C:/100%.rs"), PathBuf::from("50%.rs")]
+        );
+    }
+
+    #[test]
+    fn flags_need_what_they_work_on() {
+        assert_eq!(parse(&["--run"]), Err(ErrorKind::MissingRequiredArgument));
+        assert_eq!(
+            parse(&["--headless", "foo.rs"]),
+            Err(ErrorKind::MissingRequiredArgument)
+        );
+        assert_eq!(
+            parse(&["--headless", "--run", "foo.rs", "bar.rs"]),
+            Err(ErrorKind::WrongNumberOfValues)
+        );
+        assert_eq!(parse(&["--walk"]), Err(ErrorKind::UnknownArgument));
+    }
+}
@@ -0,0 +1,353 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use thiserror::Error;
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::config::{AnsiColors, Rgb};
+
+// the keys of each format's colors, in the order of their ansi codes
+const ITERM_KEYS: [&str; 16] = [
+    "Ansi 0 Color",
+    "Ansi 1 Color",
+    "Ansi 2 Color",
+    "Ansi 3 Color",
+    "Ansi 4 Color",
+    "Ansi 5 Color",
+    "Ansi 6 Color",
+    "Ansi 7 Color",
+    "Ansi 8 Color",
+    "Ansi 9 Color",
+    "Ansi 10 Color",
+    "Ansi 11 Color",
+    "Ansi 12 Color",
+    "Ansi 13 Color",
+    "Ansi 14 Color",
+    "Ansi 15 Color",
+];
+
+const WINDOWS_TERMINAL_KEYS: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "purple",
+    "cyan",
+    "white",
+    "brightBlack",
+    "brightRed",
+    "brightGreen",
+    "brightYellow",
+    "brightBlue",
+    "brightPurple",
+    "brightCyan",
+    "brightWhite",
+];
+
+// base16 only has 8 accent colors, so the bright ones are the same except for black and white. This is
+// the mapping base16-shell uses
+const BASE16_KEYS: [&str; 16] = [
+    "base00", "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base05", "base03",
+    "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base07",
+];
+
+#[derive(Debug, Error)]
+pub enum SchemeError {
+    #[error("Failed to read the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unknown color scheme format. Expected .itermcolors, .json or .yaml")]
+    UnknownFormat,
+    #[error("Invalid iTerm2 color scheme: {0}")]
+    Plist(#[from] plist::Error),
+    #[error("Invalid Windows Terminal color scheme: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid base16 color scheme: {0}")]
+    Yaml(#[from] yaml_rust::ScanError),
+    #[error("Invalid color for {key}: {value}")]
+    InvalidColor { key: String, value: String },
+    #[error("No colors found in the file")]
+    NoColors,
+}
+
+/// The file formats color schemes can be imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemeFormat {
+    // .itermcolors plist
+    Iterm,
+    // a scheme from the schemes list of the settings json, or a settings file with one
+    WindowsTerminal,
+    // .yaml
+    Base16,
+}
+
+impl SchemeFormat {
+    /// The format of a file going by the extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "itermcolors" | "plist" => Some(Self::Iterm),
+            "json" => Some(Self::WindowsTerminal),
+            "yaml" | "yml" => Some(Self::Base16),
+            _ => None,
+        }
+    }
+}
+
+/// A color scheme read from a file. Colors missing from it are left as they were
+#[derive(Debug, Clone)]
+pub struct ImportedScheme {
+    pub colors: AnsiColors,
+    // the keys which weren't in the file
+    pub missing: Vec<&'static str>,
+}
+
+/// Read a color scheme file, filling in any missing colors from `current`
+pub fn import_scheme(path: &Path, current: AnsiColors) -> Result<ImportedScheme, SchemeError> {
+    let format = SchemeFormat::from_path(path).ok_or(SchemeError::UnknownFormat)?;
+    let content = fs::read(path)?;
+
+    parse_scheme(&content, format, current)
+}
+
+/// Parse a color scheme, filling in any missing colors from `current`
+pub fn parse_scheme(
+    content: &[u8],
+    format: SchemeFormat,
+    current: AnsiColors,
+) -> Result<ImportedScheme, SchemeError> {
+    match format {
+        SchemeFormat::Iterm => {
+            let plist = plist::Value::from_reader(Cursor::new(content))?;
+            let colors = plist.as_dictionary();
+
+            fill_colors(current, &ITERM_KEYS, |key| {
+                let Some(color) = colors.and_then(|colors| colors.get(key)) else {
+                    return Ok(None);
+                };
+
+                // the components go from 0 to 1
+                let component = |name| {
+                    color
+                        .as_dictionary()
+                        .and_then(|color| color.get(name))
+                        .and_then(|component| component.as_real())
+                        .map(|component| (component.clamp(0.0, 1.0) * 255.0).round() as u8)
+                };
+
+                match (
+                    component("Red Component"),
+                    component("Green Component"),
+                    component("Blue Component"),
+                ) {
+                    (Some(r), Some(g), Some(b)) => Ok(Some(Rgb(r, g, b))),
+                    _ => Err(SchemeError::InvalidColor {
+                        key: key.to_string(),
+                        value: format!("{color:?}"),
+                    }),
+                }
+            })
+        }
+
+        SchemeFormat::WindowsTerminal => {
+            let json: serde_json::Value = serde_json::from_slice(content)?;
+
+            // a whole settings file has the scheme in a list, take the first one
+            let scheme = match json.get("schemes") {
+                Some(schemes) => schemes.get(0).cloned().unwrap_or_default(),
+                None => json,
+            };
+
+            fill_colors(current, &WINDOWS_TERMINAL_KEYS, |key| {
+                match scheme.get(key) {
+                    Some(serde_json::Value::String(hex)) => parse_hex(key, hex).map(Some),
+                    Some(value) => Err(SchemeError::InvalidColor {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    }),
+                    None => Ok(None),
+                }
+            })
+        }
+
+        SchemeFormat::Base16 => {
+            let content = String::from_utf8_lossy(content);
+            let documents = YamlLoader::load_from_str(&content)?;
+            let scheme = documents.into_iter().next().unwrap_or(Yaml::BadValue);
+
+            fill_colors(current, &BASE16_KEYS, |key| {
+                // unquoted hex can come out as a number
+                let hex = match &scheme[key] {
+                    Yaml::String(hex) | Yaml::Real(hex) => hex.clone(),
+                    Yaml::Integer(hex) => format!("{hex:06}"),
+                    Yaml::BadValue | Yaml::Null => return Ok(None),
+                    value => {
+                        return Err(SchemeError::InvalidColor {
+                            key: key.to_string(),
+                            value: format!("{value:?}"),
+                        })
+                    }
+                };
+
+                parse_hex(key, &hex).map(Some)
+            })
+        }
+    }
+}
+
+// Look up each color by its key, keeping the current one for the missing keys
+fn fill_colors(
+    current: AnsiColors,
+    keys: &[&'static str; 16],
+    mut lookup: impl FnMut(&str) -> Result<Option<Rgb>, SchemeError>,
+) -> Result<ImportedScheme, SchemeError> {
+    let mut colors = current.to_array();
+    let mut missing = vec![];
+    let mut found = false;
+
+    for (color, key) in colors.iter_mut().zip(keys) {
+        match lookup(key)? {
+            Some(rgb) => {
+                *color = rgb;
+                found = true;
+            }
+            // base16 keys are used for several colors, only list them once
+            None if !missing.contains(key) => missing.push(*key),
+            None => (),
+        }
+    }
+
+    if !found {
+        return Err(SchemeError::NoColors);
+    }
+
+    Ok(ImportedScheme {
+        colors: AnsiColors::from_array(colors),
+        missing,
+    })
+}
+
+// #rrggbb, the # is optional
+fn parse_hex(key: &str, hex: &str) -> Result<Rgb, SchemeError> {
+    let invalid = || SchemeError::InvalidColor {
+        key: key.to_string(),
+        value: hex.to_string(),
+    };
+
+    let digits = hex.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+
+    let channel = |index: usize| u8::from_str_radix(&digits[index..index + 2], 16);
+
+    match (channel(0), channel(2), channel(4)) {
+        (Ok(r), Ok(g), Ok(b)) => Ok(Rgb(r, g, b)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str, format: SchemeFormat) -> Result<ImportedScheme, SchemeError> {
+        parse_scheme(content.as_bytes(), format, AnsiColors::default())
+    }
+
+    #[test]
+    fn windows_terminal_scheme() {
+        let scheme = parse(
+            r##"{ "name": "Test", "black": "#000000", "red": "#FF0000", "brightWhite": "ffffff" }"##,
+            SchemeFormat::WindowsTerminal,
+        )
+        .unwrap();
+
+        assert_eq!(scheme.colors.black, Rgb(0, 0, 0));
+        assert_eq!(scheme.colors.red, Rgb(255, 0, 0));
+        assert_eq!(scheme.colors.bright_white, Rgb(255, 255, 255));
+        // the rest stay as they were
+        assert_eq!(scheme.colors.green, AnsiColors::default().green);
+        assert_eq!(scheme.missing.len(), 13);
+        assert!(scheme.missing.contains(&"purple"));
+    }
+
+    #[test]
+    fn windows_terminal_settings_file() {
+        let scheme = parse(
+            r##"{ "schemes": [{ "name": "Test", "blue": "#0000ff" }] }"##,
+            SchemeFormat::WindowsTerminal,
+        )
+        .unwrap();
+
+        assert_eq!(scheme.colors.blue, Rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn iterm_scheme() {
+        let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Ansi 1 Color</key>
+    <dict>
+        <key>Blue Component</key>
+        <real>0.0</real>
+        <key>Green Component</key>
+        <real>0.5</real>
+        <key>Red Component</key>
+        <real>1</real>
+    </dict>
+</dict>
+</plist>"#;
+
+        let scheme = parse(plist, SchemeFormat::Iterm).unwrap();
+
+        assert_eq!(scheme.colors.red, Rgb(255, 128, 0));
+        assert_eq!(scheme.missing.len(), 15);
+    }
+
+    #[test]
+    fn base16_scheme() {
+        let yaml =
+            "scheme: Test\nbase00: \"181818\"\nbase05: d8d8d8\nbase08: ab4642\nbase07: 101010\n";
+
+        let scheme = parse(yaml, SchemeFormat::Base16).unwrap();
+
+        assert_eq!(scheme.colors.black, Rgb(0x18, 0x18, 0x18));
+        assert_eq!(scheme.colors.white, Rgb(0xd8, 0xd8, 0xd8));
+        assert_eq!(scheme.colors.red, Rgb(0xab, 0x46, 0x42));
+        assert_eq!(scheme.colors.bright_red, Rgb(0xab, 0x46, 0x42));
+        // unquoted digits are parsed as a number
+        assert_eq!(scheme.colors.bright_white, Rgb(0x10, 0x10, 0x10));
+        assert!(!scheme.missing.contains(&"base08"));
+        assert_eq!(
+            scheme
+                .missing
+                .iter()
+                .filter(|key| **key == "base0B")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn rejects_bad_schemes() {
+        assert!(matches!(
+            parse(r##"{ "red": "#12345" }"##, SchemeFormat::WindowsTerminal),
+            Err(SchemeError::InvalidColor { .. })
+        ));
+        assert!(matches!(
+            parse(r#"{ "name": "nothing" }"#, SchemeFormat::WindowsTerminal),
+            Err(SchemeError::NoColors)
+        ));
+        assert!(parse("not json", SchemeFormat::WindowsTerminal).is_err());
+        assert_eq!(SchemeFormat::from_path(Path::new("a.txt")), None);
+        assert_eq!(
+            SchemeFormat::from_path(Path::new("Dracula.itermcolors")),
+            Some(SchemeFormat::Iterm)
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::folding::changed_lines;
+use super::fuzzy;
+use super::lexer::{self, LineState};
+
+/// Names in the std prelude and the common macros, offered before the code uses them
+const PRELUDE: &[&str] = &[
+    "AsMut",
+    "AsRef",
+    "Box",
+    "Clone",
+    "Copy",
+    "Default",
+    "DoubleEndedIterator",
+    "Drop",
+    "Eq",
+    "Err",
+    "ExactSizeIterator",
+    "Extend",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "From",
+    "FromIterator",
+    "Into",
+    "IntoIterator",
+    "Iterator",
+    "None",
+    "Ok",
+    "Option",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "Result",
+    "Send",
+    "Sized",
+    "Some",
+    "String",
+    "Sync",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+    "Unpin",
+    "Vec",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "dbg",
+    "drop",
+    "eprintln",
+    "format",
+    "panic",
+    "print",
+    "println",
+    "todo",
+    "unimplemented",
+    "unreachable",
+    "vec",
+    "write",
+    "writeln",
+];
+
+// how many lines away from the cursor words are looked for, to rank the closest ones higher
+const NEARBY_LINES: usize = 1000;
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// The identifiers in the code part of a line, `r#` included for raw ones. Numbers, which can
+// have letters in them like `1u8` or `0xff`, aren't identifiers
+fn idents(line: &str, state: LineState) -> (Vec<String>, LineState) {
+    let lexed = lexer::lex_line(line, state);
+    let mut idents = vec![];
+    let mut code_start = 0;
+
+    let gaps = lexed
+        .tokens
+        .iter()
+        .map(|token| token.range.clone())
+        .chain(std::iter::once(line.len()..line.len()));
+    for token in gaps {
+        let code = &line[code_start..token.start];
+        let mut chars = code.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            if !is_ident(c) {
+                continue;
+            }
+
+            let mut end = start + c.len_utf8();
+            while let Some((at, c)) = chars.next_if(|(_, c)| is_ident(*c)) {
+                end = at + c.len_utf8();
+            }
+            let raw = &code[start..end] == "r"
+                && code[end..].starts_with('#')
+                && code[end + 1..].starts_with(|c: char| is_ident(c) && !c.is_ascii_digit());
+            if raw {
+                chars.next();
+                while let Some((at, c)) = chars.next_if(|(_, c)| is_ident(*c)) {
+                    end = at + c.len_utf8();
+                }
+            }
+
+            if !c.is_ascii_digit() {
+                idents.push(code[start..end].to_string());
+            }
+        }
+
+        code_start = token.end;
+    }
+
+    (idents, lexed.state)
+}
+
+// a line's identifiers, along with what it starts and ends off in
+#[derive(Debug, Clone)]
+struct Line {
+    start: LineState,
+    end: LineState,
+    words: Vec<String>,
+}
+
+impl Line {
+    fn new(line: &str, start: LineState) -> Self {
+        let (words, end) = idents(line, start);
+        Self { start, end, words }
+    }
+}
+
+/// The identifiers in an editor's code to complete what's being typed with. It's told the code
+/// after each change, and only looks at the lines which changed again, along with the ones a
+/// comment or string opened or closed in them runs on into
+#[derive(Debug, Clone, Default)]
+pub struct Words {
+    lines: Vec<Line>,
+    // how many times each word is in the code
+    counts: HashMap<String, usize>,
+    // the code the lines are from
+    code: String,
+}
+
+impl Words {
+    pub fn update(&mut self, code: &str) {
+        if !self.lines.is_empty() && self.code == code {
+            return;
+        }
+
+        let (first, old_last, new_last) = if self.lines.is_empty() {
+            (0, 0, code.matches('\n').count())
+        } else {
+            changed_lines(&self.code, code)
+        };
+        let old_last = old_last.min(self.lines.len().saturating_sub(1));
+
+        for line in self.lines.get(first..=old_last).unwrap_or_default() {
+            forget(&mut self.counts, line);
+        }
+        let mut state = first
+            .checked_sub(1)
+            .map_or(LineState::Code, |before| self.lines[before].end);
+        let mut lines = code.split('\n').skip(first);
+        let changed = (first..=new_last)
+            .map(|_| {
+                let line = Line::new(lines.next().unwrap_or_default(), state);
+                state = line.end;
+                remember(&mut self.counts, &line);
+                line
+            })
+            .collect::<Vec<_>>();
+        let end = (old_last + 1).min(self.lines.len());
+        self.lines.splice(first..end, changed);
+
+        // the lines after start off in something else now, like a comment which was opened
+        for line in self.lines.iter_mut().skip(new_last + 1) {
+            let text = lines.next().unwrap_or_default();
+            if line.start == state {
+                break;
+            }
+
+            forget(&mut self.counts, line);
+            *line = Line::new(text, state);
+            remember(&mut self.counts, line);
+            state = line.end;
+        }
+
+        self.code = code.to_string();
+    }
+
+    /// The byte range of the identifier being typed at `cursor`, a byte offset, from its start up
+    /// to the cursor. There's none in comments and strings
+    pub fn typing(&self, code: &str, cursor: usize) -> Option<Range<usize>> {
+        let line_start = code[..cursor].rfind('\n').map_or(0, |at| at + 1);
+        let line_end = code[cursor..]
+            .find('\n')
+            .map_or(code.len(), |at| cursor + at);
+        let index = code[..line_start].matches('\n').count();
+        let state = self
+            .lines
+            .get(index)
+            .map_or(LineState::Code, |line| line.start);
+
+        let column = cursor - line_start;
+        let lexed = lexer::lex_line(&code[line_start..line_end], state);
+        if lexed
+            .tokens
+            .iter()
+            .any(|token| token.range.start < column && column <= token.range.end)
+        {
+            return None;
+        }
+
+        let before = &code[line_start..cursor];
+        let mut start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_ident(*c))
+            .last()?
+            .0;
+        if before[..start].ends_with("r#")
+            && !before[..start - 2].ends_with(is_ident)
+            && !before[start..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            start -= 2;
+        }
+        if before[start..].starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        Some(line_start + start..cursor)
+    }
+
+    /// What the identifier in `typed` could be, the best first: the words in the code and the
+    /// prelude's, matched fuzzily with the first char the same, and the ones closer to it first
+    /// when they match as well
+    pub fn complete(&self, code: &str, typed: Range<usize>, limit: usize) -> Vec<String> {
+        let typed_word = &code[typed.clone()];
+        let query = typed_word.strip_prefix("r#").unwrap_or(typed_word);
+        let Some(first) = query.chars().next() else {
+            return vec![];
+        };
+
+        // the whole word the cursor's in, which isn't a completion unless it's somewhere else too
+        let rest = code[typed.end..]
+            .char_indices()
+            .find(|(_, c)| !is_ident(*c))
+            .map_or(code.len(), |(at, _)| typed.end + at);
+        let current = &code[typed.start..rest];
+
+        let line = code[..typed.start].matches('\n').count();
+        let mut distances: HashMap<&str, usize> = HashMap::new();
+        for distance in 0..NEARBY_LINES {
+            let lines = [line.checked_sub(distance), Some(line + distance)];
+            for line in lines
+                .into_iter()
+                .flatten()
+                .filter_map(|at| self.lines.get(at))
+            {
+                for word in &line.words {
+                    distances.entry(word).or_insert(distance);
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut matches = self
+            .counts
+            .iter()
+            .filter(|(word, count)| *word != current || **count > 1)
+            .map(|(word, _)| word.as_str())
+            .chain(PRELUDE.iter().copied())
+            .filter(|word| *word != typed_word && seen.insert(*word))
+            .filter_map(|word| {
+                let name = word.strip_prefix("r#").unwrap_or(word);
+                let same_start = name.chars().next()?.to_lowercase().eq(first.to_lowercase());
+                let score = fuzzy::score(query, name).filter(|_| same_start)?;
+
+                // the closest lines count for a few points, a thousand lines away for none
+                let nearby = distances.get(word).map_or(0, |distance| {
+                    (10 - ((*distance + 1) as f32).log2() as i32).max(0)
+                });
+                Some((score + nearby, word))
+            })
+            .collect::<Vec<_>>();
+
+        matches.sort_by(|(a, a_word), (b, b_word)| {
+            b.cmp(a)
+                .then(a_word.len().cmp(&b_word.len()))
+                .then(a_word.cmp(b_word))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, word)| word.to_string())
+            .collect()
+    }
+}
+
+fn remember(counts: &mut HashMap<String, usize>, line: &Line) {
+    for word in &line.words {
+        *counts.entry(word.clone()).or_default() += 1;
+    }
+}
+
+fn forget(counts: &mut HashMap<String, usize>, line: &Line) {
+    for word in &line.words {
+        if let Some(count) = counts.get_mut(word) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(word);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "fn main() {
+    let r#type = 1u8 + 0xff;
+    // commented_out
+    let text = \"in_string\"; /* block
+    in_block */ let value = r#type;
+}";
+
+    fn words(code: &str) -> Words {
+        let mut words = Words::default();
+        words.update(code);
+        words
+    }
+
+    #[test]
+    fn identifiers_are_found_in_code_only() {
+        let words = words(CODE);
+        let mut found = words.counts.keys().cloned().collect::<Vec<_>>();
+        found.sort();
+
+        assert_eq!(found, ["fn", "let", "main", "r#type", "text", "value"]);
+        assert_eq!(words.counts["r#type"], 2);
+    }
+
+    #[test]
+    fn only_changed_lines_are_looked_at_again() {
+        let mut words = words(CODE);
+
+        // an edit in a line, then one closing the block comment before it ends
+        let code = CODE.replace("let text", "let texts");
+        words.update(&code);
+        let code = code.replace("/* block", "/* block */ after");
+        words.update(&code);
+        // and one adding lines at the top
+        let code = format!("use std::fmt;\n\n{code}");
+        words.update(&code);
+
+        let fresh = self::words(&code);
+        assert_eq!(words.counts, fresh.counts);
+        assert!(words.counts.contains_key("in_block"));
+        assert!(!words.counts.contains_key("text"));
+    }
+
+    #[test]
+    fn the_typed_identifier_is_found() {
+        let words = words(CODE);
+        let at = |text: &str| CODE.find(text).unwrap() + text.len();
+
+        assert_eq!(words.typing(CODE, at("let r#ty")), Some(20..24));
+        assert_eq!(words.typing(CODE, at("ma")), Some(3..5));
+        assert_eq!(words.typing(CODE, at("1u")), None);
+        assert_eq!(words.typing(CODE, at("commented")), None);
+        assert_eq!(words.typing(CODE, at("in_str")), None);
+        assert_eq!(words.typing(CODE, at("in_bl")), None);
+        assert_eq!(words.typing(CODE, at("fn ")), None);
+    }
+
+    #[test]
+    fn completions_are_ranked() {
+        let code = "let values = 1;\nlet value = 2;\nlet velocity = 3;\nve";
+        let words = words(code);
+        let typed = code.len() - 2..code.len();
+
+        // chars next to each other match better, then closer lines and shorter words win
+        let completions = words.complete(code, typed.clone(), 10);
+        assert_eq!(completions, ["velocity", "value", "Vec", "vec", "values"]);
+        assert_eq!(words.complete(code, typed, 1), ["velocity"]);
+
+        // the word being typed isn't one, raw ones are matched without their r#
+        let code = "let r#type = 1;\nty";
+        let words = self::words(code);
+        assert_eq!(words.complete(code, 16..18, 1), ["r#type"]);
+    }
+}
@@ -0,0 +1,122 @@
+use egui::Color32;
+
+/// The highest contrast ratio there is, black on white
+pub const MAX_CONTRAST: f32 = 21.0;
+
+// an sRGB channel in linear light
+fn linear(channel: u8) -> f32 {
+    let channel = channel as f32 / 255.0;
+
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// How bright a color looks, from 0 for black to 1 for white. Alpha is ignored
+pub fn relative_luminance(color: Color32) -> f32 {
+    0.2126 * linear(color.r()) + 0.7152 * linear(color.g()) + 0.0722 * linear(color.b())
+}
+
+/// The WCAG contrast ratio between two colors, from 1 for the same color to 21 for black on white
+pub fn contrast_ratio(a: Color32, b: Color32) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    let (lighter, darker) = if a > b { (a, b) } else { (b, a) };
+
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// Mix two colors, t is how much of the second color to use
+pub fn blend(a: Color32, b: Color32, t: f32) -> Color32 {
+    let mix = |a: u8, b: u8| egui::lerp(a as f32..=b as f32, t).round() as u8;
+    Color32::from_rgb(mix(a.r(), b.r()), mix(a.g(), b.g()), mix(a.b(), b.b()))
+}
+
+/// Move the foreground toward white or black, whichever the background contrasts with more, just far
+/// enough to reach the minimum contrast ratio with the background. Colors that already have enough
+/// contrast are left alone
+pub fn ensure_contrast(foreground: Color32, background: Color32, minimum: f32) -> Color32 {
+    let minimum = minimum.clamp(1.0, MAX_CONTRAST);
+
+    if contrast_ratio(foreground, background) >= minimum {
+        return foreground;
+    }
+
+    let target = if contrast_ratio(Color32::WHITE, background)
+        >= contrast_ratio(Color32::BLACK, background)
+    {
+        Color32::WHITE
+    } else {
+        Color32::BLACK
+    };
+
+    // the contrast only goes up moving toward the target, so search for the least change that's enough
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..12 {
+        let t = (low + high) / 2.0;
+
+        if contrast_ratio(blend(foreground, target, t), background) >= minimum {
+            high = t;
+        } else {
+            low = t;
+        }
+    }
+
+    blend(foreground, target, high)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn luminance_of_black_and_white() {
+        assert_eq!(relative_luminance(Color32::BLACK), 0.0);
+        assert!((relative_luminance(Color32::WHITE) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn contrast_ratios() {
+        assert!((contrast_ratio(Color32::BLACK, Color32::WHITE) - MAX_CONTRAST).abs() < 1e-3);
+        assert!((contrast_ratio(Color32::WHITE, Color32::BLACK) - MAX_CONTRAST).abs() < 1e-3);
+
+        let gray = Color32::from_rgb(118, 118, 118);
+        assert_eq!(contrast_ratio(gray, gray), 1.0);
+        // the usual mid gray which just passes WCAG AA on white
+        assert!((contrast_ratio(gray, Color32::WHITE) - 4.54).abs() < 0.01);
+    }
+
+    #[test]
+    fn lightens_dark_text_on_dark_backgrounds() {
+        let background = Color32::from_rgb(27, 27, 27);
+        let blue = Color32::from_rgb(0, 55, 218);
+
+        let adjusted = ensure_contrast(blue, background, 4.5);
+        let ratio = contrast_ratio(adjusted, background);
+
+        assert!(ratio >= 4.5);
+        // only just enough to get there
+        assert!(ratio < 4.7);
+        assert!(relative_luminance(adjusted) > relative_luminance(blue));
+    }
+
+    #[test]
+    fn darkens_light_text_on_light_backgrounds() {
+        let background = Color32::from_rgb(248, 248, 248);
+        let yellow = Color32::from_rgb(249, 241, 165);
+
+        let adjusted = ensure_contrast(yellow, background, 3.0);
+
+        assert!(contrast_ratio(adjusted, background) >= 3.0);
+        assert!(relative_luminance(adjusted) < relative_luminance(yellow));
+    }
+
+    #[test]
+    fn leaves_readable_colors_alone() {
+        let background = Color32::from_rgb(27, 27, 27);
+        let white = Color32::from_rgb(242, 242, 242);
+
+        assert_eq!(ensure_contrast(white, background, 4.5), white);
+    }
+}
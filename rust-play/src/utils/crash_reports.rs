@@ -0,0 +1,112 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::data_dir;
+
+// how much of the message and of the backtrace a report keeps, a panic can't be left to write
+// out however much there is
+const MAX_MESSAGE: usize = 16 * 1024;
+const MAX_BACKTRACE: usize = 64 * 1024;
+
+/// A report a panic left behind, which wasn't shown yet
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// Where the reports are written, next to the drafts
+pub fn reports_dir() -> PathBuf {
+    data_dir().join("crash-reports")
+}
+
+/// Write a report named after when it happened. Both parts are cut short when they're too long
+pub fn write(dir: &Path, message: &str, backtrace: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("{time}.txt"));
+    let text = format!(
+        "Rust Play {} panicked\n\n{}\n\nstack backtrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        truncate(message, MAX_MESSAGE),
+        truncate(backtrace, MAX_BACKTRACE),
+    );
+
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// The reports which weren't shown yet, the newest first
+pub fn unread(dir: &Path) -> Vec<CrashReport> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut reports = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .filter_map(|path| {
+            let text = fs::read_to_string(&path).ok()?;
+            Some(CrashReport { path, text })
+        })
+        .collect::<Vec<_>>();
+
+    reports.sort_by_key(|report| Reverse(report.path.clone()));
+    reports
+}
+
+/// Keep a report which was shown, without showing it again
+pub fn mark_read(report: &CrashReport) {
+    let _ = fs::rename(&report.path, report.path.with_extension("read"));
+}
+
+// the text up to `max` bytes, cut at a char boundary
+fn truncate(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+
+    let end = (0..=max)
+        .rev()
+        .find(|at| text.is_char_boundary(*at))
+        .unwrap_or(0);
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn reports_are_read_until_marked() {
+        let dir = test_dir("crashes-read");
+
+        let path = write(&dir, "index out of bounds", "0: main").unwrap();
+        let reports = unread(&dir);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].path, path);
+        assert!(reports[0].text.contains("index out of bounds"));
+        assert!(reports[0].text.contains("0: main"));
+
+        mark_read(&reports[0]);
+        assert!(unread(&dir).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn long_text_is_cut_at_a_char() {
+        assert_eq!(truncate("héllo", 2), "h");
+        assert_eq!(truncate("héllo", 3), "hé");
+        assert_eq!(truncate("hi", 10), "hi");
+    }
+}
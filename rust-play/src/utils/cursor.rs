@@ -0,0 +1,76 @@
+/// A location typed as `line` or `line:column`, both starting at 1. The column is None when only
+/// the line was given
+pub fn parse_location(text: &str) -> Option<(usize, Option<usize>)> {
+    let (line, col) = match text.trim().split_once(':') {
+        Some((line, col)) => (line, Some(col)),
+        None => (text.trim(), None),
+    };
+
+    let line = line.trim().parse().ok().filter(|line| *line > 0)?;
+    let col = match col.map(str::trim) {
+        Some(col) => Some(col.parse().ok().filter(|col| *col > 0)?),
+        None => None,
+    };
+
+    Some((line, col))
+}
+
+/// The index of the line and of the char at a line and column, both starting at 1. Locations
+/// past the end of the code are clamped to its last line, and past a line's end to its end
+pub fn char_index(code: &str, line: usize, col: usize) -> (usize, usize) {
+    let line_index = line.clamp(1, code.lines().count().max(1)) - 1;
+    let line_start = code
+        .split_inclusive('\n')
+        .take(line_index)
+        .map(|line| line.chars().count())
+        .sum::<usize>();
+    let line_len = code
+        .lines()
+        .nth(line_index)
+        .map_or(0, |line| line.chars().count());
+
+    (line_index, line_start + (col.max(1) - 1).min(line_len))
+}
+
+/// How far down to scroll for the row at `line_index` to be in the middle of a view
+/// `view_height` tall. Lines near the top can't be centered, the view stays at the top for them
+pub fn centered_offset(line_index: usize, row_height: f32, view_height: f32) -> f32 {
+    let center = (line_index as f32 + 0.5) * row_height;
+    (center - view_height / 2.0).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_are_parsed() {
+        assert_eq!(parse_location("12"), Some((12, None)));
+        assert_eq!(parse_location(" 12 : 5 "), Some((12, Some(5))));
+        assert_eq!(parse_location("0"), None);
+        assert_eq!(parse_location("3:0"), None);
+        assert_eq!(parse_location("3:"), None);
+        assert_eq!(parse_location("abc"), None);
+        assert_eq!(parse_location(""), None);
+    }
+
+    #[test]
+    fn locations_are_clamped_to_the_code() {
+        let code = "fn main() {\n    println!();\n}\n";
+
+        assert_eq!(char_index(code, 1, 1), (0, 0));
+        assert_eq!(char_index(code, 2, 5), (1, 16));
+        // past the end of the line, then past the last line
+        assert_eq!(char_index(code, 2, 100), (1, 27));
+        assert_eq!(char_index(code, 100, 1), (2, 28));
+        assert_eq!(char_index("", 5, 5), (0, 0));
+    }
+
+    #[test]
+    fn lines_are_centered() {
+        // the middle of the row ends up halfway down the view
+        assert_eq!(centered_offset(20, 10.0, 100.0), 155.0);
+        assert_eq!(centered_offset(2, 10.0, 100.0), 0.0);
+        assert_eq!(centered_offset(0, 10.0, 0.0), 5.0);
+    }
+}
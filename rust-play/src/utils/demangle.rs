@@ -0,0 +1,45 @@
+use std::borrow::Cow;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+lazy_static! {
+    // legacy `_ZN..E` and v0 `_R..` mangled rust symbols, with the extra underscore some platforms add
+    static ref SYMBOL: Regex = Regex::new(r"\b_{1,2}(ZN[0-9A-Za-z_$.]+E|R[0-9A-Za-z_]+)").unwrap();
+}
+
+/// Replace the mangled rust symbols in assembly with their readable names, without the hashes
+pub fn demangle_symbols(text: &str) -> Cow<'_, str> {
+    SYMBOL.replace_all(text, |captures: &Captures| {
+        let symbol = &captures[0];
+
+        match rustc_demangle::try_demangle(symbol) {
+            Ok(demangled) => format!("{demangled:#}"),
+            // just looked like one
+            Err(_) => symbol.to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangles_rust_symbols() {
+        let asm = "\tcall\t_ZN3std2io5stdio6_print17h0123456789abcdefE\n\
+                   _ZN10playground4main17hfedcba9876543210E:\n";
+
+        assert_eq!(
+            demangle_symbols(asm),
+            "\tcall\tstd::io::stdio::_print\nplayground::main:\n"
+        );
+    }
+
+    #[test]
+    fn leaves_other_names_alone() {
+        let asm = "\tmovq\t_RANDOM_LABEL(%rip), %rax\n\tcall\tmemcpy\n";
+
+        assert_eq!(demangle_symbols(asm), asm);
+    }
+}
@@ -0,0 +1,313 @@
+use std::ops::Range;
+
+use serde::Deserialize;
+
+/// How bad a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// What found a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Rustc,
+    // a lint only clippy has
+    Clippy,
+}
+
+/// A fix the compiler is sure of, which can be applied without looking it over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String,
+    // byte ranges in the checked file and what to replace each with
+    pub edits: Vec<(Range<usize>, String)>,
+}
+
+/// A compiler message about part of the scratch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub tool: Tool,
+    // byte range in the checked file
+    pub range: Range<usize>,
+    // where the range starts, both start at 1
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+    pub suggestion: Option<Suggestion>,
+}
+
+// one line of cargo's json output. Only the parts needed here are read
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    level: String,
+    message: String,
+    code: Option<DiagnosticCode>,
+    #[serde(default)]
+    spans: Vec<Span>,
+    #[serde(default)]
+    children: Vec<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+    label: Option<String>,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+}
+
+/// Read the diagnostics about `file` (like `src/main.rs`) out of `cargo check --message-format=json`
+/// or `cargo clippy` output. Lines which aren't compiler messages are skipped
+pub fn parse_diagnostics(output: &str, file: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    for line in output.lines() {
+        let Ok(CargoMessage {
+            reason,
+            message: Some(message),
+        }) = serde_json::from_str(line)
+        else {
+            continue;
+        };
+
+        if reason != "compiler-message" {
+            continue;
+        }
+
+        let severity = match message.level.as_str() {
+            "error" | "error: internal compiler error" => Severity::Error,
+            "warning" => Severity::Warning,
+            // notes like "aborting due to previous error" aren't about the code
+            _ => continue,
+        };
+
+        let tool = match &message.code {
+            Some(code) if code.code.starts_with("clippy::") => Tool::Clippy,
+            _ => Tool::Rustc,
+        };
+
+        // only the suggestions which are known to be right, and are all in the file
+        let suggestion = message.children.iter().find_map(|child| {
+            let edits = child
+                .spans
+                .iter()
+                .map(
+                    |span| match (&span.suggested_replacement, &span.suggestion_applicability) {
+                        (Some(replacement), Some(applicability))
+                            if applicability == "MachineApplicable" && span.file_name == file =>
+                        {
+                            Some((span.byte_start..span.byte_end, replacement.clone()))
+                        }
+                        _ => None,
+                    },
+                )
+                .collect::<Option<Vec<_>>>()?;
+
+            (!edits.is_empty()).then(|| Suggestion {
+                message: child.message.clone(),
+                edits,
+            })
+        });
+
+        // the help and notes rustc prints under the message
+        let children: String = message
+            .children
+            .iter()
+            .map(|child| format!("\n{}: {}", child.level, child.message))
+            .collect();
+
+        for span in message.spans.iter().filter(|span| span.is_primary) {
+            if span.file_name != file {
+                continue;
+            }
+
+            let mut text = message.message.clone();
+            if let Some(label) = span.label.as_deref().filter(|label| !label.is_empty()) {
+                text.push('\n');
+                text.push_str(label);
+            }
+            text.push_str(&children);
+
+            diagnostics.push(Diagnostic {
+                severity,
+                tool,
+                range: span.byte_start..span.byte_end.max(span.byte_start),
+                line: span.line_start,
+                col: span.column_start,
+                message: text,
+                suggestion: suggestion.clone(),
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// The byte range of a line, without its line ending. Lines start at 1
+pub fn line_range(text: &str, line: usize) -> Option<Range<usize>> {
+    let mut start = 0;
+
+    for (index, content) in text.split('\n').enumerate() {
+        if index + 1 == line {
+            let content = content.strip_suffix('\r').unwrap_or(content);
+            return Some(start..start + content.len());
+        }
+
+        start += content.len() + 1;
+    }
+
+    None
+}
+
+/// The code with a suggestion applied to it, or None if the suggestion doesn't fit the code
+pub fn apply_suggestion(code: &str, suggestion: &Suggestion) -> Option<String> {
+    let mut edits = suggestion.edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|(range, _)| range.start);
+
+    let mut fixed = String::with_capacity(code.len());
+    let mut end = 0;
+
+    for (range, replacement) in edits {
+        // overlapping edits can't both be made
+        if range.start < end || code.get(range.clone()).is_none() {
+            return None;
+        }
+
+        fixed.push_str(&code[end..range.start]);
+        fixed.push_str(replacement);
+        end = range.end;
+    }
+
+    fixed.push_str(code.get(end..)?);
+    Some(fixed)
+}
+
+/// How many errors and warnings there are
+pub fn counts(diagnostics: &[Diagnostic]) -> (usize, usize) {
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+
+    (errors, diagnostics.len() - errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = r#"{"reason":"compiler-artifact","package_id":"rand 0.8.5","target":{"name":"rand"}}
+{"reason":"compiler-message","message":{"rendered":"error[E0308]: mismatched types\n","children":[],"level":"error","message":"mismatched types","spans":[{"byte_end":31,"byte_start":28,"column_end":20,"column_start":17,"file_name":"src/main.rs","is_primary":true,"label":"expected `u8`, found `&str`","line_end":2,"line_start":2},{"byte_end":25,"byte_start":23,"column_end":14,"column_start":12,"file_name":"src/main.rs","is_primary":false,"label":"expected due to this","line_end":2,"line_start":2}],"code":{"code":"E0308"}}}
+{"reason":"compiler-message","message":{"rendered":"warning: unused variable: `y`\n","children":[{"children":[],"level":"help","message":"if this is intentional, prefix it with an underscore: `_y`","spans":[]}],"level":"warning","message":"unused variable: `y`","spans":[{"byte_end":44,"byte_start":43,"column_end":10,"column_start":9,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":3,"line_start":3}]}}
+{"reason":"compiler-message","message":{"rendered":"warning: in a dependency\n","children":[],"level":"warning","message":"in a dependency","spans":[{"byte_end":10,"byte_start":1,"column_end":10,"column_start":1,"file_name":"/home/.cargo/registry/src/lib.rs","is_primary":true,"label":null,"line_end":1,"line_start":1}]}}
+{"reason":"compiler-message","message":{"rendered":"error: aborting due to previous error\n","children":[],"level":"failure-note","message":"For more information about this error, try `rustc --explain E0308`.","spans":[]}}
+not json
+{"reason":"build-finished","success":false}"#;
+
+    #[test]
+    fn parses_cargo_check_output() {
+        let diagnostics = parse_diagnostics(OUTPUT, "src/main.rs");
+
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic {
+                    severity: Severity::Error,
+                    tool: Tool::Rustc,
+                    range: 28..31,
+                    line: 2,
+                    col: 17,
+                    message: "mismatched types\nexpected `u8`, found `&str`".to_string(),
+                    suggestion: None,
+                },
+                Diagnostic {
+                    severity: Severity::Warning,
+                    tool: Tool::Rustc,
+                    range: 43..44,
+                    line: 3,
+                    col: 9,
+                    message: "unused variable: `y`\nhelp: if this is intentional, prefix it with an underscore: `_y`"
+                        .to_string(),
+                    suggestion: None,
+                },
+            ]
+        );
+        assert_eq!(counts(&diagnostics), (1, 1));
+    }
+
+    #[test]
+    fn parses_clippy_suggestions() {
+        let output = r#"{"reason":"compiler-message","message":{"rendered":"warning: length comparison to zero\n","children":[{"children":[],"code":null,"level":"help","message":"for further information visit https://rust-lang.github.io/rust-clippy/master/index.html#len_zero","spans":[]},{"children":[],"code":null,"level":"help","message":"using `is_empty` is clearer and more explicit","spans":[{"byte_end":33,"byte_start":21,"column_end":17,"column_start":5,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":"v.is_empty()","suggestion_applicability":"MachineApplicable"}]}],"code":{"code":"clippy::len_zero","explanation":null},"level":"warning","message":"length comparison to zero","spans":[{"byte_end":33,"byte_start":21,"column_end":17,"column_start":5,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2}]}}"#;
+
+        let diagnostics = parse_diagnostics(output, "src/main.rs");
+        assert_eq!(diagnostics.len(), 1);
+
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.tool, Tool::Clippy);
+
+        let suggestion = diagnostic.suggestion.as_ref().unwrap();
+        assert_eq!(
+            suggestion.message,
+            "using `is_empty` is clearer and more explicit"
+        );
+        assert_eq!(suggestion.edits, [(21..33, "v.is_empty()".to_string())]);
+
+        let code = "fn f(v: &[u8]) {\n\tif v.len() == 0 {}\n}";
+        assert_eq!(
+            apply_suggestion(code, suggestion).unwrap(),
+            "fn f(v: &[u8]) {\n\tif v.is_empty() {}\n}"
+        );
+    }
+
+    #[test]
+    fn rejects_suggestions_which_dont_fit() {
+        let suggestion = |edits: &[(Range<usize>, &str)]| Suggestion {
+            message: String::new(),
+            edits: edits
+                .iter()
+                .map(|(range, text)| (range.clone(), text.to_string()))
+                .collect(),
+        };
+
+        assert_eq!(
+            apply_suggestion("abcdef", &suggestion(&[(4..5, "E"), (0..1, "A")])).as_deref(),
+            Some("AbcdEf")
+        );
+        assert_eq!(apply_suggestion("abc", &suggestion(&[(2..9, "x")])), None);
+        assert_eq!(
+            apply_suggestion("abcdef", &suggestion(&[(0..3, "x"), (2..4, "y")])),
+            None
+        );
+        // the middle of a char
+        assert_eq!(apply_suggestion("é", &suggestion(&[(1..2, "e")])), None);
+    }
+
+    #[test]
+    fn finds_line_ranges() {
+        let text = "fn main() {\r\n    x\n}";
+
+        assert_eq!(line_range(text, 1), Some(0..11));
+        assert_eq!(&text[line_range(text, 2).unwrap()], "    x");
+        assert_eq!(line_range(text, 3), Some(19..20));
+        assert_eq!(line_range(text, 4), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, TryLockError};
+use std::time::SystemTime;
+
+use egui::Id;
+
+use super::data_dir;
+
+// The code of tabs which changed since their draft was written, along with its revision. It's
+// kept outside the ui so a panic can write it out
+static PENDING: Mutex<Vec<(Id, u64, String)>> = Mutex::new(Vec::new());
+
+/// A tab's code as it was written out before the app went away without saving it
+#[derive(Debug, Clone)]
+pub struct Draft {
+    // which tab it's the code of, the file is named after it
+    pub key: String,
+    pub path: PathBuf,
+    pub code: String,
+    pub modified: SystemTime,
+}
+
+/// Where the drafts are written, next to the session
+pub fn drafts_dir() -> PathBuf {
+    data_dir().join("drafts")
+}
+
+/// What a tab's draft is named after
+pub fn key(id: Id) -> String {
+    format!("{id:?}")
+}
+
+/// Write a tab's code to its draft. It's written next to it first, so a crash halfway through
+/// never leaves half a draft
+pub fn save(dir: &Path, id: Id, code: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let path = dir.join(format!("{}.rs", key(id)));
+    let temp = path.with_extension("rs.tmp");
+    fs::write(&temp, code)?;
+    fs::rename(temp, path)
+}
+
+/// Delete a tab's draft, when it has one
+pub fn remove(dir: &Path, id: Id) {
+    let _ = fs::remove_file(dir.join(format!("{}.rs", key(id))));
+}
+
+/// Keep the code of a tab whose draft is behind, until it's written. It's only copied when the
+/// revision changed
+pub fn set_pending(id: Id, revision: u64, code: &str) {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+
+    match pending.iter_mut().find(|(pending, ..)| *pending == id) {
+        Some((_, kept, _)) if *kept == revision => (),
+        Some(entry) => *entry = (id, revision, code.to_string()),
+        None => pending.push((id, revision, code.to_string())),
+    }
+}
+
+/// Forget a tab's pending code, once its draft is written or it's saved or closed
+pub fn clear_pending(id: Id) {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    pending.retain(|(pending, ..)| *pending != id);
+}
+
+/// Forget the pending code of the tabs which were closed
+pub fn retain_pending(open: impl Fn(Id) -> bool) {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    pending.retain(|(id, ..)| open(*id));
+}
+
+/// Write out the pending code right away, for a panic. It doesn't wait for the lock, the thread
+/// which panicked may be the one holding it
+pub fn flush_pending(dir: &Path) {
+    let pending = match PENDING.try_lock() {
+        Ok(pending) => pending,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+
+    for (id, _, code) in pending.iter() {
+        let _ = save(dir, *id, code);
+    }
+}
+
+/// The drafts which were left behind, the newest first. Ones which can't be read are skipped
+pub fn load(dir: &Path) -> Vec<Draft> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut drafts = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rs"))
+        .filter_map(|path| {
+            let key = path.file_stem()?.to_string_lossy().into_owned();
+            let code = fs::read_to_string(&path).ok()?;
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            Some(Draft {
+                key,
+                path,
+                code,
+                modified,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    drafts.sort_by_key(|draft| Reverse(draft.modified));
+    drafts
+}
+
+/// Delete every draft, once everything's in the session
+pub fn clear(dir: &Path) {
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn drafts_are_written_and_read_back() {
+        let dir = test_dir("drafts-saved");
+        let (a, b) = (Id::new("a"), Id::new("b"));
+
+        save(&dir, a, "fn a() {}").unwrap();
+        save(&dir, b, "fn b() {}").unwrap();
+        save(&dir, a, "fn a2() {}").unwrap();
+
+        let mut drafts = load(&dir);
+        drafts.sort_by_key(|draft| draft.code.clone());
+        let codes = drafts
+            .iter()
+            .map(|draft| draft.code.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(codes, ["fn a2() {}", "fn b() {}"]);
+        assert_eq!(drafts[0].key, key(a));
+
+        // nothing's left over from writing them
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        clear(&dir);
+    }
+
+    #[test]
+    fn pending_drafts_are_flushed() {
+        let dir = test_dir("drafts-pending");
+        let (a, b) = (Id::new("pending a"), Id::new("pending b"));
+
+        set_pending(a, 1, "fn a() {}");
+        set_pending(b, 1, "fn b() {}");
+        set_pending(a, 2, "fn a2() {}");
+        clear_pending(b);
+        flush_pending(&dir);
+
+        let drafts = load(&dir);
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].code, "fn a2() {}");
+
+        clear_pending(a);
+        clear(&dir);
+    }
+
+    #[test]
+    fn drafts_are_removed() {
+        let dir = test_dir("drafts-removed");
+        let id = Id::new("a");
+
+        save(&dir, id, "fn main() {}").unwrap();
+        remove(&dir, id);
+        assert!(load(&dir).is_empty());
+
+        save(&dir, id, "fn main() {}").unwrap();
+        clear(&dir);
+        assert!(load(&dir).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+/// Code the welcome tab offers to start from, opened in a new scratch. The `//# ` comments at the
+/// top are the dependencies it needs
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub code: &'static str,
+}
+
+/// The welcome tab's examples, in the order it shows them
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "Hello world",
+        description: "Print something and run it",
+        code: r#"fn main() {
+    println!("Hello, world!");
+}
+"#,
+    },
+    Example {
+        name: "Error handling",
+        description: "Results, `?` and an error type of your own",
+        code: r#"use std::fmt;
+use std::num::ParseIntError;
+
+#[derive(Debug)]
+enum AgeError {
+    NotANumber(ParseIntError),
+    TooOld(u32),
+}
+
+impl fmt::Display for AgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AgeError::NotANumber(e) => write!(f, "not a number: {e}"),
+            AgeError::TooOld(age) => write!(f, "{age} is too old"),
+        }
+    }
+}
+
+impl From<ParseIntError> for AgeError {
+    fn from(e: ParseIntError) -> Self {
+        AgeError::NotANumber(e)
+    }
+}
+
+fn parse_age(text: &str) -> Result<u32, AgeError> {
+    let age = text.trim().parse()?;
+    if age > 150 {
+        return Err(AgeError::TooOld(age));
+    }
+
+    Ok(age)
+}
+
+fn main() {
+    for text in ["42", "abc", "200"] {
+        match parse_age(text) {
+            Ok(age) => println!("{text}: {age}"),
+            Err(e) => println!("{text}: {e}"),
+        }
+    }
+}
+"#,
+    },
+    Example {
+        name: "Async with tokio",
+        description: "Tasks running at the same time on tokio",
+        code: r#"//# tokio = { version = "1", features = ["full"] }
+
+use std::time::Duration;
+
+use tokio::time::sleep;
+
+async fn work(name: &str, millis: u64) -> String {
+    sleep(Duration::from_millis(millis)).await;
+    format!("{name} done after {millis}ms")
+}
+
+#[tokio::main]
+async fn main() {
+    let slow = tokio::spawn(work("slow", 300));
+    let fast = tokio::spawn(work("fast", 100));
+
+    println!("{}", fast.await.unwrap());
+    println!("{}", slow.await.unwrap());
+}
+"#,
+    },
+    Example {
+        name: "Iterator pipeline",
+        description: "Filter, map and fold data without loops",
+        code: r#"use std::collections::HashMap;
+
+fn main() {
+    let text = "the quick brown fox jumps over the lazy dog the end";
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for word in text.split_whitespace() {
+        *counts.entry(word).or_default() += 1;
+    }
+
+    let mut common = counts
+        .into_iter()
+        .filter(|(word, _)| word.len() > 2)
+        .collect::<Vec<_>>();
+    common.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    for (word, count) in common.iter().take(3) {
+        println!("{word}: {count}");
+    }
+
+    let letters: usize = common.iter().map(|(word, count)| word.len() * count).sum();
+    println!("{letters} letters in the longer words");
+}
+"#,
+    },
+];
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use cargo_player::ProjectError;
+use thiserror::Error;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(target_os = "windows")]
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("Failed to write the project: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Project(#[from] ProjectError),
+    // what git printed
+    #[error("The project was written, but git init failed\n\n{0}")]
+    Git(String),
+}
+
+/// A valid package name from a folder name, `My Scratch` becomes `my-scratch`
+pub fn package_name(name: &str) -> String {
+    let mut package = String::new();
+
+    for char in name.trim().chars() {
+        if char.is_ascii_alphanumeric() || char == '_' {
+            package.push(char.to_ascii_lowercase());
+        } else if !package.is_empty() && !package.ends_with('-') {
+            package.push('-');
+        }
+    }
+
+    let package = package.trim_end_matches('-');
+
+    // package names can't start with a digit
+    match package.chars().next() {
+        None => "scratch".to_string(),
+        Some(char) if char.is_ascii_digit() => format!("scratch-{package}"),
+        Some(_) => package.to_string(),
+    }
+}
+
+/// Whether a folder has anything in it. One which doesn't exist yet is empty
+pub fn is_empty_dir(dir: &Path) -> std::io::Result<bool> {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Make the folder a git repository
+pub fn git_init(dir: &Path) -> Result<(), ExportError> {
+    let mut command = Command::new("git");
+    command.arg("init").current_dir(dir).stdin(Stdio::null());
+
+    // hide the console window from command. Very important.
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW.0);
+
+    let output = command
+        .output()
+        .map_err(|e| ExportError::Git(format!("Failed to run git: {e}")))?;
+
+    if !output.status.success() {
+        return Err(ExportError::Git(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Show the folder in the file manager
+pub fn open_folder(dir: &Path) {
+    // explorer's exit code doesn't say whether it opened, so there's nothing to report
+    let _ = Command::new("explorer").arg(dir).spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_names_are_valid() {
+        assert_eq!(package_name("My Scratch"), "my-scratch");
+        assert_eq!(package_name("snake_case"), "snake_case");
+        assert_eq!(package_name("  a  (b)  "), "a-b");
+        assert_eq!(package_name("2023 day 1"), "scratch-2023-day-1");
+        assert_eq!(package_name("!!"), "scratch");
+    }
+
+    #[test]
+    fn missing_folders_are_empty() {
+        let dir = std::env::temp_dir().join("rust-play-export-test");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(is_empty_dir(&dir).unwrap());
+
+        fs::create_dir_all(&dir).unwrap();
+        assert!(is_empty_dir(&dir).unwrap());
+
+        fs::write(dir.join("main.rs"), "").unwrap();
+        assert!(!is_empty_dir(&dir).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
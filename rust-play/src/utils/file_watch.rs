@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches the folders of open files, and says which of the files changed. The folders are watched
+/// instead of the files, editors which save by replacing the file would otherwise end the watch
+#[derive(Debug)]
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    rx: Receiver<PathBuf>,
+    folders: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    /// `on_change` is called from the watcher's thread whenever something changed
+    pub fn new(on_change: impl Fn() + Send + 'static) -> notify::Result<Self> {
+        let (tx, rx) = channel();
+
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+
+                on_change();
+            }
+        })?;
+
+        Ok(Self {
+            watcher,
+            rx,
+            folders: HashSet::new(),
+        })
+    }
+
+    /// Watch the folders of exactly these files, the ones of files which aren't open anymore stop
+    /// being watched
+    pub fn watch<'a>(&mut self, files: impl IntoIterator<Item = &'a Path>) {
+        let folders = folders(files);
+
+        for folder in self.folders.difference(&folders) {
+            let _ = self.watcher.unwatch(folder);
+        }
+
+        // a folder which can't be watched, like one that was deleted, is tried again next time
+        self.folders = folders
+            .into_iter()
+            .filter(|folder| {
+                self.folders.contains(folder)
+                    || self
+                        .watcher
+                        .watch(folder, RecursiveMode::NonRecursive)
+                        .is_ok()
+            })
+            .collect();
+    }
+
+    /// The files which changed since the last call, along with anything else in their folders
+    pub fn changed(&self) -> HashSet<PathBuf> {
+        self.rx.try_iter().collect()
+    }
+}
+
+// the folders the files are in
+fn folders<'a>(files: impl IntoIterator<Item = &'a Path>) -> HashSet<PathBuf> {
+    files
+        .into_iter()
+        .filter_map(|file| file.parent())
+        .filter(|folder| !folder.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn watches_each_folder_once() {
+        let folders = folders([
+            Path::new("/a/main.rs"),
+            Path::new("/a/other.rs"),
+            Path::new("/b/main.rs"),
+            Path::new("main.rs"),
+        ]);
+
+        assert_eq!(folders.len(), 2);
+        assert!(folders.contains(Path::new("/a")));
+        assert!(folders.contains(Path::new("/b")));
+    }
+
+    #[test]
+    fn reports_changed_files() {
+        let dir = std::env::temp_dir().join("rust-play-watch-test");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("main.rs");
+        fs::write(&file, "fn main() {}").unwrap();
+
+        let mut watcher = FileWatcher::new(|| ()).unwrap();
+        watcher.watch([file.as_path()]);
+
+        fs::write(&file, "fn main() { println!() }").unwrap();
+
+        // events come in from another thread
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut changed = HashSet::new();
+        while !changed.contains(&file) && Instant::now() < deadline {
+            changed.extend(watcher.changed());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(changed.contains(&file));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
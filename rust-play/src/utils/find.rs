@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+/// What the editor's find bar looks for
+#[derive(Debug, Clone, Copy)]
+pub struct Query<'a> {
+    pub text: &'a str,
+    pub case_sensitive: bool,
+    // the text is a regex rather than plain text, and replacements can use its `$1` captures
+    pub regex: bool,
+}
+
+impl Query<'_> {
+    fn regex(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex {
+            self.text.to_string()
+        } else {
+            regex::escape(self.text)
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()
+    }
+}
+
+/// The byte ranges of the query's matches in the code, in order. Empty matches, like those of
+/// `a*`, can't be highlighted so they're left out
+pub fn find(code: &str, query: Query) -> Result<Vec<Range<usize>>, regex::Error> {
+    if query.text.is_empty() {
+        return Ok(vec![]);
+    }
+
+    Ok(query
+        .regex()?
+        .find_iter(code)
+        .map(|m| m.range())
+        .filter(|range| !range.is_empty())
+        .collect())
+}
+
+/// The code with the match at `range` replaced, None if there's no match there anymore
+pub fn replace(code: &str, query: Query, range: Range<usize>, replacement: &str) -> Option<String> {
+    let regex = query.regex().ok()?;
+    let captures = regex
+        .captures_iter(code)
+        .find(|captures| captures.get(0).map(|m| m.range()) == Some(range.clone()))?;
+
+    let mut replaced = code[..range.start].to_string();
+    expand(&mut replaced, &captures, query, replacement);
+    replaced.push_str(&code[range.end..]);
+
+    Some(replaced)
+}
+
+/// The code with every match replaced, and how many there were. It's all one change, so undoing
+/// it brings all of them back
+pub fn replace_all(
+    code: &str,
+    query: Query,
+    replacement: &str,
+) -> Result<(String, usize), regex::Error> {
+    if query.text.is_empty() {
+        return Ok((code.to_string(), 0));
+    }
+
+    let regex = query.regex()?;
+    let mut replaced = String::with_capacity(code.len());
+    let mut last = 0;
+    let mut count = 0;
+
+    for captures in regex.captures_iter(code) {
+        let Some(m) = captures.get(0).filter(|m| !m.range().is_empty()) else {
+            continue;
+        };
+
+        replaced.push_str(&code[last..m.start()]);
+        expand(&mut replaced, &captures, query, replacement);
+        last = m.end();
+        count += 1;
+    }
+    replaced.push_str(&code[last..]);
+
+    Ok((replaced, count))
+}
+
+// Only regex replacements have captures to fill in, a plain one is used as it is
+fn expand(dst: &mut String, captures: &regex::Captures, query: Query, replacement: &str) {
+    if query.regex {
+        captures.expand(replacement, dst);
+    } else {
+        dst.push_str(replacement);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(text: &str, case_sensitive: bool, regex: bool) -> Query<'_> {
+        Query {
+            text,
+            case_sensitive,
+            regex,
+        }
+    }
+
+    #[test]
+    fn plain_text_is_found() {
+        let code = "let Foo = foo.foo();";
+
+        assert_eq!(
+            find(code, query("foo", false, false)),
+            Ok(vec![4..7, 10..13, 14..17])
+        );
+        assert_eq!(
+            find(code, query("foo", true, false)),
+            Ok(vec![10..13, 14..17])
+        );
+        // regex syntax is taken as it is
+        assert_eq!(
+            find("a.b axb", query("a.b", true, false)),
+            Ok(vec![Range { start: 0, end: 3 }])
+        );
+        assert_eq!(find(code, query("", true, false)), Ok(vec![]));
+    }
+
+    #[test]
+    fn regexes_are_found() {
+        assert_eq!(
+            find("a.b axb", query("a.b", true, true)),
+            Ok(vec![0..3, 4..7])
+        );
+        assert_eq!(
+            find("fn a\nfn b", query("^fn", true, true)),
+            Ok(vec![0..2, 5..7])
+        );
+        // the empty matches are left out
+        assert_eq!(
+            find("baab", query("a*", true, true)),
+            Ok(vec![Range { start: 1, end: 3 }])
+        );
+        assert!(find("", query("(", true, true)).is_err());
+    }
+
+    #[test]
+    fn one_match_is_replaced() {
+        let code = "foo(1); foo(2);";
+
+        assert_eq!(
+            replace(code, query("foo", true, false), 8..11, "bar"),
+            Some("foo(1); bar(2);".to_string())
+        );
+        assert_eq!(
+            replace(code, query(r"foo\((\d)\)", true, true), 0..6, "bar($1, $1)"),
+            Some("bar(1, 1); foo(2);".to_string())
+        );
+        // the code changed since the match was found
+        assert_eq!(replace(code, query("foo", true, false), 1..4, "bar"), None);
+    }
+
+    #[test]
+    fn every_match_is_replaced() {
+        let code = "foo(1); Foo(2);";
+
+        assert_eq!(
+            replace_all(code, query("foo", false, false), "$1"),
+            Ok(("$1(1); $1(2);".to_string(), 2))
+        );
+        assert_eq!(
+            replace_all(code, query(r"(\w+)\((\d)\)", true, true), "$2.$1()"),
+            Ok(("1.foo(); 2.Foo();".to_string(), 2))
+        );
+        assert_eq!(
+            replace_all(code, query("bar", true, false), "baz"),
+            Ok((code.to_string(), 0))
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+
+use super::brackets;
+
+/// A brace block which can be folded away, by the lines it opens and closes on, starting at 0.
+/// Folded, the lines after its opener up to and including its closer are hidden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Fold {
+    /// How many lines are hidden when it's folded
+    pub fn hidden_lines(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether folding it hides `line`
+    pub fn hides(&self, line: usize) -> bool {
+        line > self.start && line <= self.end
+    }
+}
+
+// the line each byte offset is on, by the offsets lines start at
+fn line_of(line_starts: &[usize], at: usize) -> usize {
+    line_starts.partition_point(|start| *start <= at) - 1
+}
+
+/// The blocks which can be folded: each brace block at the top level or one level deep, like
+/// functions and the items in an impl or module, which goes on over more than one line. Lines
+/// opening more than one only fold the first
+pub fn regions(code: &str) -> Vec<Fold> {
+    let line_starts = std::iter::once(0)
+        .chain(code.match_indices('\n').map(|(at, _)| at + 1))
+        .collect::<Vec<_>>();
+    let mut regions: Vec<Fold> = vec![];
+    // the closers of the braces the next one is inside of
+    let mut open: Vec<usize> = vec![];
+
+    for (at, partner) in brackets::pairs(code) {
+        if code.as_bytes()[at] != b'{' {
+            continue;
+        }
+        while open.last().is_some_and(|closer| *closer < at) {
+            open.pop();
+        }
+        let Some(closer) = partner else {
+            continue;
+        };
+
+        let (start, end) = (line_of(&line_starts, at), line_of(&line_starts, closer));
+        let taken = regions.last().is_some_and(|last| last.start == start);
+        if open.len() <= 1 && end > start && !taken {
+            regions.push(Fold { start, end });
+        }
+        open.push(closer);
+    }
+
+    regions
+}
+
+/// The folded blocks of an editor's code. It's told the code after each change, and moves its
+/// folds along with the lines around them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Folds {
+    folds: Vec<Fold>,
+    // the code the folds are for, only kept while there are any
+    #[serde(skip)]
+    code: Option<String>,
+}
+
+impl Folds {
+    pub fn folds(&self) -> &[Fold] {
+        &self.folds
+    }
+
+    /// The fold hiding `line`, the outermost when they're inside each other
+    pub fn hiding(&self, line: usize) -> Option<Fold> {
+        self.folds.iter().copied().find(|fold| fold.hides(line))
+    }
+
+    /// How many of the lines above `line` are hidden
+    pub fn hidden_above(&self, line: usize) -> usize {
+        (0..line)
+            .filter(|line| self.hiding(*line).is_some())
+            .count()
+    }
+
+    /// Take in the code as it is now. Folds before or after an edit move with it, folds around it
+    /// take in the lines it added or took away, and folds it cuts across or which aren't a
+    /// foldable block anymore are unfolded
+    pub fn update(&mut self, code: &str) {
+        if self.folds.is_empty() {
+            self.code = None;
+            return;
+        }
+        if self.code.as_deref() == Some(code) {
+            return;
+        }
+
+        if let Some(old) = &self.code {
+            let (first, old_last, new_last) = changed_lines(old, code);
+            self.folds.retain_mut(|fold| {
+                if old_last < fold.start {
+                    fold.start = fold.start + new_last - old_last;
+                    fold.end = fold.end + new_last - old_last;
+                    true
+                } else {
+                    first > fold.end || (first >= fold.start && old_last <= fold.end)
+                }
+            });
+        }
+
+        // the fold's block may close somewhere else now
+        let regions = regions(code);
+        self.folds = self
+            .folds
+            .iter()
+            .filter_map(|fold| regions.iter().find(|region| region.start == fold.start))
+            .copied()
+            .collect();
+        self.folds.dedup();
+        self.code = Some(code.to_string());
+    }
+
+    /// Fold `region` away, or unfold it when it's folded
+    pub fn toggle(&mut self, code: &str, region: Fold) {
+        match self
+            .folds
+            .iter()
+            .position(|fold| fold.start == region.start)
+        {
+            Some(index) => {
+                self.folds.remove(index);
+            }
+            None => {
+                self.folds.push(region);
+                self.folds.sort_by_key(|fold| fold.start);
+            }
+        }
+        self.code = Some(code.to_string());
+    }
+
+    /// Unfold everything hiding any of the lines from `first` to `last`. Returns whether anything
+    /// was
+    pub fn reveal(&mut self, first: usize, last: usize) -> bool {
+        let before = self.folds.len();
+        self.folds
+            .retain(|fold| !(first..=last).any(|line| fold.hides(line)));
+
+        self.folds.len() != before
+    }
+}
+
+/// The line the code first differs on, and the last lines of the difference before and after
+pub fn changed_lines(old: &str, new: &str) -> (usize, usize, usize) {
+    let prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .bytes()
+        .rev()
+        .zip(new[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let lines = |text: &str| text.bytes().filter(|byte| *byte == b'\n').count();
+    let first = lines(&old[..prefix]);
+
+    (
+        first,
+        first + lines(&old[prefix..old.len() - suffix]),
+        first + lines(&new[prefix..new.len() - suffix]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "struct A {
+    a: u8,
+}
+
+impl A {
+    fn new() -> Self {
+        if true {
+            Self { a: 1 }
+        } else {
+            todo!(\"{\")
+        }
+    }
+}
+
+fn main() { A::new(); }";
+
+    #[test]
+    fn blocks_one_level_deep_can_be_folded() {
+        assert_eq!(
+            regions(CODE),
+            vec![
+                Fold { start: 0, end: 2 },
+                Fold { start: 4, end: 12 },
+                Fold { start: 5, end: 11 },
+            ]
+        );
+        assert_eq!(regions("fn main() {"), vec![]);
+    }
+
+    #[test]
+    fn folds_move_with_edits() {
+        let mut folds = Folds::default();
+        folds.toggle(CODE, Fold { start: 5, end: 11 });
+        assert!(folds.hiding(8).is_some());
+        assert_eq!(folds.hidden_above(13), 6);
+
+        // lines added above move it down
+        let code = format!("use std::fmt;\n\n{CODE}");
+        folds.update(&code);
+        assert_eq!(folds.folds(), [Fold { start: 7, end: 13 }]);
+
+        // and inside it make it longer
+        let code = code.replace("todo!", "\n\ntodo!");
+        folds.update(&code);
+        assert_eq!(folds.folds(), [Fold { start: 7, end: 15 }]);
+
+        // lines taken away across its closer unfold it
+        let code = code.replace("    }\n}", "");
+        folds.update(&code);
+        assert_eq!(folds.folds(), []);
+    }
+
+    #[test]
+    fn folds_are_revealed() {
+        let mut folds = Folds::default();
+        folds.toggle(CODE, Fold { start: 0, end: 2 });
+        folds.toggle(CODE, Fold { start: 4, end: 12 });
+
+        assert!(!folds.reveal(3, 3));
+        assert!(folds.reveal(7, 7));
+        assert_eq!(folds.folds(), [Fold { start: 0, end: 2 }]);
+
+        folds.toggle(CODE, Fold { start: 0, end: 2 });
+        assert_eq!(folds.folds(), []);
+    }
+}
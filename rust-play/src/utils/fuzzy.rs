@@ -0,0 +1,107 @@
+/// How well `query` matches `text`, if every char of it is in `text` in the same order, ignoring
+/// case. Higher is better, chars at the start of a word and runs of chars next to each other count
+/// for more, and the chars skipped in between for less
+pub fn score(query: &str, text: &str) -> Option<i32> {
+    matched_chars(query, text).map(|(score, _)| score)
+}
+
+/// The score of the match, along with which chars of `text` matched, counted in chars
+pub fn matched_chars(query: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let mut score = 0;
+    let mut matched = vec![];
+    let mut query = query
+        .chars()
+        .filter(|char| !char.is_whitespace())
+        .peekable();
+    let mut previous: Option<char> = None;
+    // how many chars were skipped since the last match, None before the first one
+    let mut gap = None;
+
+    for (index, char) in text.chars().enumerate() {
+        let Some(wanted) = query.peek() else {
+            break;
+        };
+
+        if char.to_lowercase().eq(wanted.to_lowercase()) {
+            let word_start = match previous {
+                None => true,
+                Some(previous) => {
+                    !previous.is_alphanumeric() || (previous.is_lowercase() && char.is_uppercase())
+                }
+            };
+
+            score += 1;
+            if word_start {
+                score += 10;
+            }
+            match gap {
+                Some(0) => score += 5,
+                Some(gap) => score -= gap.min(5),
+                None => (),
+            }
+
+            gap = Some(0);
+            matched.push(index);
+            query.next();
+        } else if let Some(gap) = gap.as_mut() {
+            *gap += 1;
+        }
+
+        previous = Some(char);
+    }
+
+    query.peek().is_none().then_some((score, matched))
+}
+
+/// The indices of the items whose text matches `query`, the best match first. Items which match
+/// equally well keep their order, and an empty query matches everything
+pub fn filter<T>(query: &str, items: &[T], text: impl Fn(&T) -> &str) -> Vec<usize> {
+    let mut matches = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| score(query, text(item)).map(|score| (index, score)))
+        .collect::<Vec<_>>();
+
+    matches.sort_by_key(|(_, score)| -score);
+    matches.into_iter().map(|(index, _)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_chars_in_order() {
+        assert!(score("fmt", "Format").is_some());
+        assert!(score("FORMAT", "format").is_some());
+        assert!(score("close tab", "Close tab").is_some());
+        assert!(score("tmf", "Format").is_none());
+        assert!(score("formats", "Format").is_none());
+        assert_eq!(score("", "Format"), Some(0));
+    }
+
+    #[test]
+    fn says_which_chars_matched() {
+        assert_eq!(matched_chars("fmt", "Format").unwrap().1, [0, 3, 5]);
+        assert_eq!(matched_chars("ct", "Close tab").unwrap().1, [0, 6]);
+        // counted in chars, not bytes
+        assert_eq!(matched_chars("ab", "äab").unwrap().1, [1, 2]);
+    }
+
+    #[test]
+    fn word_starts_and_runs_rank_higher() {
+        let commands = [
+            "Close other tabs",
+            "Toggle terminal",
+            "Open settings",
+            "Show problems",
+            "Open file…",
+        ];
+        // the t of Toggle terminal starts a word, the one in Close other tabs doesn't
+        assert_eq!(filter("te", &commands, |command| command), [1, 0]);
+        assert_eq!(filter("open", &commands, |command| command), [2, 4]);
+        assert_eq!(filter("sp", &commands, |command| command), [3]);
+        assert_eq!(filter("t", &commands, |command| command)[0], 1);
+        assert_eq!(filter("", &commands, |command| command), [0, 1, 2, 3, 4]);
+    }
+}
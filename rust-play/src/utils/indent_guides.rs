@@ -0,0 +1,111 @@
+use std::ops::RangeInclusive;
+
+use egui::epaint::text::TAB_SIZE;
+
+/// How many indentation guides each line has, one for each level it's indented by. A level is
+/// `level_width` columns, with tabs taking up as many as the editor draws them with. Blank lines
+/// get the fewer guides of the lines around them, so the guides run on through them
+pub fn levels(code: &str, level_width: usize) -> Vec<usize> {
+    let level_width = level_width.max(1);
+    let indents = code
+        .split('\n')
+        .map(|line| {
+            if line.trim().is_empty() {
+                return None;
+            }
+
+            let columns = line
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .map(|c| if c == '\t' { TAB_SIZE } else { 1 })
+                .sum::<usize>();
+            Some(columns / level_width)
+        })
+        .collect::<Vec<_>>();
+
+    // the level of the next line which isn't blank, from the bottom up
+    let mut after = vec![0; indents.len()];
+    let mut next = 0;
+    for (line, indent) in indents.iter().enumerate().rev() {
+        next = indent.unwrap_or(next);
+        after[line] = next;
+    }
+
+    let mut before = 0;
+    indents
+        .iter()
+        .zip(after)
+        .map(|(indent, after)| match indent {
+            Some(level) => {
+                before = *level;
+                *level
+            }
+            None => before.min(after),
+        })
+        .collect()
+}
+
+/// The guide of the scope the cursor's line is in, as its level counting from 0 at the left, and
+/// the lines it runs down. On the line opening a block it's the block's guide, None outside of
+/// any
+pub fn active(levels: &[usize], line: usize) -> Option<(usize, RangeInclusive<usize>)> {
+    let here = *levels.get(line)?;
+    let (level, scope_line) = match levels.get(line + 1) {
+        Some(next) if *next > here => (*next, line + 1),
+        _ => (here, line),
+    };
+    if level == 0 {
+        return None;
+    }
+
+    let in_scope = |line: &usize| levels[*line] >= level;
+    let first = (0..scope_line).rev().take_while(in_scope).last();
+    let last = (scope_line..levels.len()).take_while(in_scope).last();
+
+    Some((
+        level - 1,
+        first.unwrap_or(scope_line)..=last.unwrap_or(scope_line),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "fn main() {
+    let a = 1;
+
+    if a > 0 {
+        println!();
+
+    }
+}
+
+struct A;";
+
+    #[test]
+    fn lines_get_a_guide_for_each_level() {
+        assert_eq!(levels(CODE, 4), vec![0, 1, 1, 1, 2, 1, 1, 0, 0, 0]);
+        assert_eq!(levels(CODE, 2), vec![0, 2, 2, 2, 4, 2, 2, 0, 0, 0]);
+        // a tab is drawn as wide as four spaces
+        assert_eq!(levels("a\n\tb\n\t  c", 2), vec![0, 2, 3]);
+        assert_eq!(levels("", 4), vec![0]);
+    }
+
+    #[test]
+    fn the_cursors_scope_is_found() {
+        let levels = levels(CODE, 4);
+
+        // inside the if, then on the line opening it
+        assert_eq!(active(&levels, 4), Some((1, 4..=4)));
+        assert_eq!(active(&levels, 3), Some((1, 4..=4)));
+        // in the function, from the blank line in it too
+        assert_eq!(active(&levels, 1), Some((0, 1..=6)));
+        assert_eq!(active(&levels, 2), Some((0, 1..=6)));
+        assert_eq!(active(&levels, 0), Some((0, 1..=6)));
+        // outside of any
+        assert_eq!(active(&levels, 9), None);
+        assert_eq!(active(&levels, 7), None);
+        assert_eq!(active(&levels, 20), None);
+    }
+}
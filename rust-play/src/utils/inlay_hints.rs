@@ -0,0 +1,423 @@
+use proc_macro2::LineColumn;
+use syn::visit::{self, Visit};
+use syn::{
+    BinOp, Expr, GenericArgument, Lit, Local, Pat, Path, PathArguments, RangeLimits, Type, UnOp,
+};
+
+// the std types which are generic over their items, with how many parameters they have
+const CONTAINERS: &[(&str, usize)] = &[
+    ("Vec", 1),
+    ("VecDeque", 1),
+    ("LinkedList", 1),
+    ("HashSet", 1),
+    ("BTreeSet", 1),
+    ("BinaryHeap", 1),
+    ("HashMap", 2),
+    ("BTreeMap", 2),
+];
+
+// the std types whose `new` wraps what it's given
+const WRAPPERS: &[&str] = &["Box", "Rc", "Arc", "Cell", "RefCell", "Mutex", "RwLock"];
+
+/// A type guessed for a `let` binding, shown after its pattern at the byte offset `at`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    pub at: usize,
+    pub label: String,
+}
+
+/// The guessed types of the `let` bindings which don't write theirs out in `code`, in the order
+/// they're in. They're only guessed from literals and constructors, so they can be wrong, and
+/// code which doesn't parse has none
+pub fn hints(code: &str) -> Vec<Hint> {
+    let Ok(file) = syn::parse_file(code) else {
+        return vec![];
+    };
+
+    let mut lets = Lets {
+        code,
+        line_starts: std::iter::once(0)
+            .chain(code.match_indices('\n').map(|(at, _)| at + 1))
+            .collect(),
+        hints: vec![],
+    };
+    lets.visit_file(&file);
+
+    lets.hints.sort_by_key(|hint| hint.at);
+    lets.hints
+}
+
+/// The hints of an editor's code, only guessed again when the code changes
+#[derive(Debug, Clone, Default)]
+pub struct InlayHints {
+    code: Option<String>,
+    hints: Vec<Hint>,
+}
+
+impl InlayHints {
+    pub fn update(&mut self, code: &str) -> &[Hint] {
+        if self.code.as_deref() != Some(code) {
+            self.hints = hints(code);
+            self.code = Some(code.to_string());
+        }
+
+        &self.hints
+    }
+}
+
+struct Lets<'a> {
+    code: &'a str,
+    line_starts: Vec<usize>,
+    hints: Vec<Hint>,
+}
+
+impl Lets<'_> {
+    // spans count lines from 1 and chars from 0 in them
+    fn offset(&self, at: LineColumn) -> usize {
+        let Some(start) = self.line_starts.get(at.line.wrapping_sub(1)) else {
+            return self.code.len();
+        };
+
+        self.code[*start..]
+            .char_indices()
+            .nth(at.column)
+            .map_or(self.code.len(), |(offset, _)| start + offset)
+    }
+}
+
+impl<'ast> Visit<'ast> for Lets<'_> {
+    fn visit_local(&mut self, local: &'ast Local) {
+        // `let x: T = ...` is a Pat::Type, destructuring patterns aren't guessed into
+        if let (Pat::Ident(pat), Some((_, init))) = (&local.pat, &local.init) {
+            if pat.subpat.is_none() && pat.ident != "_" {
+                if let Some(ty) = guess(init) {
+                    self.hints.push(Hint {
+                        at: self.offset(pat.ident.span().end()),
+                        label: format!(": {ty}"),
+                    });
+                }
+            }
+        }
+
+        visit::visit_local(self, local);
+    }
+}
+
+// The type `expr` is most likely to have, when it's a literal or a constructor
+fn guess(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(expr) => literal(&expr.lit),
+        Expr::Paren(expr) => guess(&expr.expr),
+        Expr::Group(expr) => guess(&expr.expr),
+        Expr::Reference(expr) => {
+            let mutability = if expr.mutability.is_some() {
+                "mut "
+            } else {
+                ""
+            };
+            Some(format!("&{mutability}{}", guess(&expr.expr)?))
+        }
+        Expr::Cast(expr) => type_name(&expr.ty),
+        Expr::Tuple(expr) => {
+            let items = expr.elems.iter().map(guess_or_infer).collect::<Vec<_>>();
+            Some(match items.as_slice() {
+                [item] => format!("({item},)"),
+                _ => format!("({})", items.join(", ")),
+            })
+        }
+        Expr::Array(expr) => {
+            let item = expr.elems.first().map_or("_".to_string(), guess_or_infer);
+            Some(format!("[{item}; {}]", expr.elems.len()))
+        }
+        Expr::Repeat(expr) => {
+            let Expr::Lit(len) = &*expr.len else {
+                return None;
+            };
+            let Lit::Int(len) = &len.lit else {
+                return None;
+            };
+            Some(format!(
+                "[{}; {}]",
+                guess_or_infer(&expr.expr),
+                len.base10_digits()
+            ))
+        }
+        Expr::Struct(expr) => type_path(&expr.path, expr.path.segments.len()),
+        Expr::Call(expr) => {
+            let Expr::Path(func) = &*expr.func else {
+                return None;
+            };
+            call(&func.path, expr.args.first())
+        }
+        Expr::MethodCall(expr) => match expr.method.to_string().as_str() {
+            "to_string" | "to_uppercase" | "to_lowercase" => Some("String".to_string()),
+            "to_owned" if matches!(guess(&expr.receiver)?.as_str(), "&str") => {
+                Some("String".to_string())
+            }
+            "len" | "count" => Some("usize".to_string()),
+            "clone" => guess(&expr.receiver),
+            "collect" | "parse" | "into" => {
+                let turbofish = expr.turbofish.as_ref()?;
+                match turbofish.args.first()? {
+                    syn::GenericMethodArgument::Type(ty) => type_name(ty),
+                    syn::GenericMethodArgument::Const(_) => None,
+                }
+                .map(|ty| {
+                    if expr.method == "parse" {
+                        format!("Result<{ty}, _>")
+                    } else {
+                        ty
+                    }
+                })
+            }
+            _ => None,
+        },
+        Expr::Macro(expr) => {
+            let name = expr.mac.path.segments.last()?.ident.to_string();
+            match name.as_str() {
+                "format" => Some("String".to_string()),
+                // vec![a, b] and vec![a; n] are guessed the way arrays are
+                "vec" => {
+                    let items = syn::parse_str::<Expr>(&format!("[{}]", expr.mac.tokens)).ok()?;
+                    let item = match &items {
+                        Expr::Array(array) => array.elems.first().map(guess_or_infer),
+                        Expr::Repeat(repeat) => Some(guess_or_infer(&repeat.expr)),
+                        _ => None,
+                    };
+                    Some(format!("Vec<{}>", item.unwrap_or_else(|| "_".to_string())))
+                }
+                _ => None,
+            }
+        }
+        Expr::Range(expr) => {
+            let (Some(from), Some(to)) = (&expr.from, &expr.to) else {
+                return None;
+            };
+            let item = either(guess(from), guess(to))?;
+            Some(match expr.limits {
+                RangeLimits::HalfOpen(_) => format!("Range<{item}>"),
+                RangeLimits::Closed(_) => format!("RangeInclusive<{item}>"),
+            })
+        }
+        Expr::Binary(expr) => match expr.op {
+            BinOp::Eq(_)
+            | BinOp::Ne(_)
+            | BinOp::Lt(_)
+            | BinOp::Le(_)
+            | BinOp::Gt(_)
+            | BinOp::Ge(_)
+            | BinOp::And(_)
+            | BinOp::Or(_) => Some("bool".to_string()),
+            // shifting by something doesn't make it that thing's type
+            BinOp::Shl(_) | BinOp::Shr(_) => guess(&expr.left),
+            _ => either(guess(&expr.left), guess(&expr.right)),
+        },
+        Expr::Unary(expr) => match expr.op {
+            UnOp::Not(_) | UnOp::Neg(_) => guess(&expr.expr),
+            UnOp::Deref(_) => None,
+        },
+        _ => None,
+    }
+}
+
+// The type of two sides which have the same one, a number with a suffix saying what it is
+// rather than one which only falls back to i32 or f64
+fn either(left: Option<String>, right: Option<String>) -> Option<String> {
+    match (left, right) {
+        (Some(left), Some(right)) if left == "i32" || left == "f64" => Some(right),
+        (left, right) => left.or(right),
+    }
+}
+
+fn guess_or_infer(expr: &Expr) -> String {
+    guess(expr).unwrap_or_else(|| "_".to_string())
+}
+
+fn literal(lit: &Lit) -> Option<String> {
+    let ty = match lit {
+        Lit::Str(_) => "&str".to_string(),
+        Lit::ByteStr(bytes) => format!("&[u8; {}]", bytes.value().len()),
+        Lit::Byte(_) => "u8".to_string(),
+        Lit::Char(_) => "char".to_string(),
+        Lit::Bool(_) => "bool".to_string(),
+        // what rustc falls back to for numbers without a suffix
+        Lit::Int(int) if int.suffix().is_empty() => "i32".to_string(),
+        Lit::Int(int) => int.suffix().to_string(),
+        Lit::Float(float) if float.suffix().is_empty() => "f64".to_string(),
+        Lit::Float(float) => float.suffix().to_string(),
+        Lit::Verbatim(_) => return None,
+    };
+
+    Some(ty)
+}
+
+// A call of a tuple struct, an enum variant or a constructor like `Type::new`
+fn call(path: &Path, first_arg: Option<&Expr>) -> Option<String> {
+    let segments = &path.segments;
+    let last = segments.last()?.ident.to_string();
+    let is_type = |name: &str| name.starts_with(char::is_uppercase);
+
+    if segments.len() == 1 {
+        return match last.as_str() {
+            "Some" => Some(format!(
+                "Option<{}>",
+                first_arg.map_or("_".into(), guess_or_infer)
+            )),
+            "Ok" => Some(format!(
+                "Result<{}, _>",
+                first_arg.map_or("_".into(), guess_or_infer)
+            )),
+            "Err" => Some(format!(
+                "Result<_, {}>",
+                first_arg.map_or("_".into(), guess_or_infer)
+            )),
+            name if is_type(name) => type_path(path, 1),
+            _ => None,
+        };
+    }
+
+    let ty = segments[segments.len() - 2].ident.to_string();
+    if !is_type(&ty) {
+        return None;
+    }
+    // enum variants have the enum's type
+    if is_type(&last) {
+        return type_path(path, segments.len() - 1);
+    }
+
+    match last.as_str() {
+        "new" if WRAPPERS.contains(&ty.as_str()) => Some(format!(
+            "{ty}<{}>",
+            first_arg.map_or("_".into(), guess_or_infer)
+        )),
+        "new" | "default" | "with_capacity" | "from" | "from_iter" => {
+            type_path(path, segments.len() - 1)
+        }
+        _ => None,
+    }
+}
+
+// The type named by the first `len` segments of a path, which is shown by its last segment along
+// with the generics it's given
+fn type_path(path: &Path, len: usize) -> Option<String> {
+    let segment = path.segments.iter().nth(len.checked_sub(1)?)?;
+    let name = segment.ident.to_string();
+    if name == "Self" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => {
+            let arguments = arguments
+                .args
+                .iter()
+                .map(|argument| match argument {
+                    GenericArgument::Type(ty) => type_name(ty),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("{name}<{}>", arguments.join(", ")))
+        }
+        PathArguments::Parenthesized(_) => None,
+        PathArguments::None => {
+            let generics = CONTAINERS
+                .iter()
+                .find(|(container, _)| *container == name)
+                .map_or(0, |(_, count)| *count);
+            if generics == 0 {
+                Some(name)
+            } else {
+                Some(format!("{name}<{}>", vec!["_"; generics].join(", ")))
+            }
+        }
+    }
+}
+
+// How a type written in the code is shown, None for the ones which are too involved
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(ty) if ty.qself.is_none() => type_path(&ty.path, ty.path.segments.len()),
+        Type::Reference(ty) => {
+            let mutability = if ty.mutability.is_some() { "mut " } else { "" };
+            Some(format!("&{mutability}{}", type_name(&ty.elem)?))
+        }
+        Type::Slice(ty) => Some(format!("[{}]", type_name(&ty.elem)?)),
+        Type::Tuple(ty) => {
+            let items = ty.elems.iter().map(type_name).collect::<Option<Vec<_>>>()?;
+            Some(format!("({})", items.join(", ")))
+        }
+        Type::Infer(_) => Some("_".to_string()),
+        Type::Paren(ty) => type_name(&ty.elem),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(code: &str) -> Vec<String> {
+        hints(code).into_iter().map(|hint| hint.label).collect()
+    }
+
+    #[test]
+    fn literals_and_constructors_are_guessed() {
+        let code = r#"fn main() {
+    let a = 1;
+    let b = 2.5f32;
+    let c = "c";
+    let d = String::from("d");
+    let e = vec![1u8, 2];
+    let f = Vec::new();
+    let g = Some('g');
+    let h = Point { x: 1, y: 2 };
+    let i = (true, b'i');
+    let j = Box::new([0; 4]);
+    let k = Shape::Circle(1.0);
+    let l = 0..10usize;
+    let m = a as u64;
+}"#;
+
+        assert_eq!(
+            labels(code),
+            [
+                ": i32",
+                ": f32",
+                ": &str",
+                ": String",
+                ": Vec<u8>",
+                ": Vec<_>",
+                ": Option<char>",
+                ": Point",
+                ": (bool, u8)",
+                ": Box<[i32; 4]>",
+                ": Shape",
+                ": Range<usize>",
+                ": u64",
+            ]
+        );
+    }
+
+    #[test]
+    fn hints_go_after_the_pattern() {
+        let code = "fn main() {\n    let mut café = 1;\n}";
+        let hints = hints(code);
+
+        assert_eq!(hints.len(), 1);
+        assert_eq!(&code[..hints[0].at], "fn main() {\n    let mut café");
+    }
+
+    #[test]
+    fn only_untyped_simple_bindings_get_hints() {
+        let code = "fn main() {
+    let typed: u8 = 1;
+    let (a, b) = (1, 2);
+    let unknown = other();
+    let _ = 1;
+    let closure = |x| { let inner = 'i'; x };
+}";
+
+        assert_eq!(labels(code), [": char"]);
+        assert!(hints("fn main() { let a = ").is_empty());
+    }
+}
@@ -0,0 +1,372 @@
+use std::fmt;
+use std::str::FromStr;
+
+use egui::{Context, Id, Key, Modifiers};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// every key a shortcut can be bound to, looked up by name when parsing one
+const KEYS: [Key; 73] = [
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::ArrowUp,
+    Key::Escape,
+    Key::Tab,
+    Key::Backspace,
+    Key::Enter,
+    Key::Space,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Minus,
+    Key::PlusEquals,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum KeyComboError {
+    #[error("The shortcut has no key")]
+    NoKey,
+    #[error("Unknown key {0}")]
+    UnknownKey(String),
+}
+
+/// The key a shortcut is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComboKey {
+    Key(Key),
+    // winit doesn't translate ` or / for egui, they're asked for from the os instead
+    Backtick,
+    Slash,
+}
+
+impl ComboKey {
+    pub fn name(self) -> &'static str {
+        match self {
+            ComboKey::Key(key) => key.name(),
+            ComboKey::Backtick => "`",
+            ComboKey::Slash => "/",
+        }
+    }
+}
+
+/// A keyboard shortcut, written like `Ctrl+Shift+P` in the settings file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyCombo {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub key: ComboKey,
+}
+
+impl KeyCombo {
+    pub const fn new(key: Key) -> Self {
+        Self {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            key: ComboKey::Key(key),
+        }
+    }
+
+    pub const fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub const fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    pub const fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// The combo for a key press egui saw
+    pub fn pressed(modifiers: Modifiers, key: ComboKey) -> Self {
+        Self {
+            ctrl: modifiers.command,
+            shift: modifiers.shift,
+            alt: modifiers.alt,
+            key,
+        }
+    }
+
+    /// The modifiers egui has to see along with the key
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            alt: self.alt,
+            ctrl: false,
+            shift: self.shift,
+            mac_cmd: false,
+            command: self.ctrl,
+        }
+    }
+
+    /// Whether pressing the combo would also type text, which it then can't be bound to. Shift
+    /// alone only changes what's typed, the function keys never type anything
+    pub fn types_text(&self) -> bool {
+        let function_key = matches!(
+            self.key,
+            ComboKey::Key(key) if key.name().len() > 1 && key.name().starts_with('F')
+        );
+
+        !self.ctrl && !self.alt && !function_key
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl"),
+            (self.shift, "Shift"),
+            (self.alt, "Alt"),
+        ] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        f.write_str(self.key.name())
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = KeyComboError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut combo = KeyCombo::new(Key::Enter);
+
+        // the key itself can be +, so only the parts before the last one are modifiers
+        let (modifiers, name) = match s.trim().rsplit_once('+') {
+            Some((modifiers, "")) if modifiers.ends_with('+') => {
+                (&modifiers[..modifiers.len() - 1], "+")
+            }
+            Some((modifiers, name)) => (modifiers, name),
+            None => ("", s.trim()),
+        };
+
+        for modifier in modifiers.split('+').filter(|part| !part.is_empty()) {
+            match modifier.trim().to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" => combo.ctrl = true,
+                "shift" => combo.shift = true,
+                "alt" => combo.alt = true,
+                _ => return Err(KeyComboError::UnknownKey(modifier.to_string())),
+            }
+        }
+
+        combo.key = match name.trim() {
+            "" => return Err(KeyComboError::NoKey),
+            "`" => ComboKey::Backtick,
+            "/" => ComboKey::Slash,
+            "+" | "=" => ComboKey::Key(Key::PlusEquals),
+            "-" => ComboKey::Key(Key::Minus),
+            name => KEYS
+                .into_iter()
+                .find(|key| key.name().eq_ignore_ascii_case(name))
+                .map(ComboKey::Key)
+                .ok_or_else(|| KeyComboError::UnknownKey(name.to_string()))?,
+        };
+
+        Ok(combo)
+    }
+}
+
+impl TryFrom<String> for KeyCombo {
+    type Error = KeyComboError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<KeyCombo> for String {
+    fn from(combo: KeyCombo) -> Self {
+        combo.to_string()
+    }
+}
+
+/// Whether the shortcuts acting on the editor's code get their keys, when it has the focus or
+/// nothing does. Another widget with the focus, like the terminal's input, keeps them
+pub fn editor_takes_keys(ctx: &Context, editor: Option<Id>) -> bool {
+    let focus = ctx.memory().focus();
+    focus.is_none() || focus == editor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combos_round_trip() {
+        let combos = [
+            KeyCombo::new(Key::P).ctrl().shift(),
+            KeyCombo::new(Key::Enter).ctrl(),
+            KeyCombo::new(Key::F5).shift(),
+            KeyCombo::new(Key::PageDown).ctrl().alt(),
+            KeyCombo::new(Key::PlusEquals).ctrl(),
+            KeyCombo::new(Key::Num0).ctrl(),
+            KeyCombo::pressed(Modifiers::COMMAND, ComboKey::Backtick),
+            KeyCombo::pressed(Modifiers::COMMAND, ComboKey::Slash),
+        ];
+
+        for combo in combos {
+            assert_eq!(combo.to_string().parse(), Ok(combo));
+        }
+
+        assert_eq!(
+            KeyCombo::new(Key::P).ctrl().shift().to_string(),
+            "Ctrl+Shift+P"
+        );
+        assert_eq!(
+            KeyCombo::new(Key::PlusEquals).ctrl().to_string(),
+            "Ctrl+Plus"
+        );
+    }
+
+    #[test]
+    fn parsing_is_forgiving_about_spelling() {
+        let ctrl_shift_p = Ok(KeyCombo::new(Key::P).ctrl().shift());
+
+        assert_eq!("ctrl+shift+p".parse(), ctrl_shift_p);
+        assert_eq!(" Shift + Ctrl + P ".parse(), ctrl_shift_p);
+        assert_eq!("Ctrl++".parse(), Ok(KeyCombo::new(Key::PlusEquals).ctrl()));
+        assert_eq!("Ctrl+-".parse(), Ok(KeyCombo::new(Key::Minus).ctrl()));
+        assert_eq!("F5".parse(), Ok(KeyCombo::new(Key::F5)));
+
+        assert_eq!("Ctrl+".parse::<KeyCombo>(), Err(KeyComboError::NoKey));
+        assert_eq!(
+            "Ctrl+Banana".parse::<KeyCombo>(),
+            Err(KeyComboError::UnknownKey("Banana".to_string()))
+        );
+        assert_eq!(
+            "Hyper+P".parse::<KeyCombo>(),
+            Err(KeyComboError::UnknownKey("Hyper".to_string()))
+        );
+    }
+
+    #[test]
+    fn combos_serialize_as_text() {
+        let combo = KeyCombo::new(Key::T).ctrl().shift();
+        let json = serde_json::to_string(&combo).unwrap();
+
+        assert_eq!(json, r#""Ctrl+Shift+T""#);
+        assert_eq!(serde_json::from_str::<KeyCombo>(&json).unwrap(), combo);
+        assert!(serde_json::from_str::<KeyCombo>(r#""Ctrl+Nope""#).is_err());
+    }
+
+    #[test]
+    fn the_focused_terminal_input_keeps_ctrl_d() {
+        let ctx = Context::default();
+        let editor = Id::new("code_editor");
+        let mut input = String::new();
+
+        // the terminal's input is focused while a tab is open
+        let _ = ctx.run(Default::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut input));
+                response.request_focus();
+            });
+        });
+
+        let ctrl_d = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key: Key::D,
+                pressed: true,
+                modifiers: Modifiers::COMMAND,
+            }],
+            modifiers: Modifiers::COMMAND,
+            ..Default::default()
+        };
+        let mut closed = false;
+        let _ = ctx.run(ctrl_d, |ctx| {
+            // the shortcuts go first, like select next occurrence on the same keys
+            if editor_takes_keys(ctx, Some(editor)) {
+                ctx.input_mut().consume_key(Modifiers::COMMAND, Key::D);
+            }
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut input));
+                closed =
+                    response.has_focus() && ui.input_mut().consume_key(Modifiers::COMMAND, Key::D);
+            });
+        });
+        assert!(closed);
+
+        ctx.memory().request_focus(editor);
+        assert!(editor_takes_keys(&ctx, Some(editor)));
+        ctx.memory().surrender_focus(editor);
+        assert!(editor_takes_keys(&ctx, Some(editor)));
+    }
+
+    #[test]
+    fn typing_keys_need_a_modifier() {
+        assert!(KeyCombo::new(Key::A).types_text());
+        assert!(KeyCombo::new(Key::A).shift().types_text());
+        assert!(!KeyCombo::new(Key::A).ctrl().types_text());
+        assert!(!KeyCombo::new(Key::F5).types_text());
+        assert!(!KeyCombo::new(Key::F12).shift().types_text());
+    }
+}
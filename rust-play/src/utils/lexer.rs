@@ -0,0 +1,416 @@
+use std::ops::Range;
+
+/// What a stretch of code which isn't plain code is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Comment,
+    // string literals, raw strings included
+    Str,
+    // char and byte literals
+    Char,
+    // lifetimes and loop labels, which start with a ' like chars do
+    Lifetime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub range: Range<usize>,
+}
+
+/// What a line starts off in, comments and strings can go on from the line before
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineState {
+    #[default]
+    Code,
+    BlockComment {
+        depth: usize,
+    },
+    Str,
+    RawStr {
+        hashes: usize,
+    },
+}
+
+/// A line as the lexer saw it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexedLine {
+    // by byte range in the line
+    pub tokens: Vec<Token>,
+    // the first token is the rest of a comment or string from the line before
+    pub continued: bool,
+    // what the next line starts off in
+    pub state: LineState,
+}
+
+/// The comments, literals and lifetimes in the code, in order and by byte range. Whatever is
+/// between them is plain code. It's not a full lexer, just enough to tell where brackets can be
+/// and to highlight the code
+pub fn tokens(code: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut state = LineState::Code;
+    let mut line_start = 0;
+
+    for line in code.split('\n') {
+        let lexed = lex_line(line, state);
+
+        for (index, token) in lexed.tokens.into_iter().enumerate() {
+            let range = line_start + token.range.start..line_start + token.range.end;
+
+            match tokens.last_mut() {
+                Some(last) if index == 0 && lexed.continued => last.range.end = range.end,
+                _ => tokens.push(Token {
+                    kind: token.kind,
+                    range,
+                }),
+            }
+        }
+
+        state = lexed.state;
+        line_start += line.len() + 1;
+    }
+
+    tokens
+}
+
+/// Lex a line, without its newline, which starts off in `state`. Strings can go over lines, but
+/// when the quote closing one has a word right after it, it likely opens the next string instead.
+/// The string was left open while it's being typed, so the line is lexed as code
+pub fn lex_line(line: &str, state: LineState) -> LexedLine {
+    let bytes = line.as_bytes();
+    let mut tokens = vec![];
+
+    // the rest of the comment or string from the line before
+    let (mut i, state) = match state {
+        LineState::Code => (0, LineState::Code),
+        LineState::BlockComment { depth } => {
+            let (end, state) = block_comment_end(bytes, 0, depth);
+            tokens.push(Token {
+                kind: TokenKind::Comment,
+                range: 0..end,
+            });
+            (end, state)
+        }
+        LineState::Str => match string_end(bytes, 0) {
+            Some(end) if bytes.get(end).is_some_and(|byte| is_word(*byte)) => (0, LineState::Code),
+            Some(end) => {
+                tokens.push(Token {
+                    kind: TokenKind::Str,
+                    range: 0..end,
+                });
+                (end, LineState::Code)
+            }
+            None => {
+                tokens.push(Token {
+                    kind: TokenKind::Str,
+                    range: 0..bytes.len(),
+                });
+                (bytes.len(), LineState::Str)
+            }
+        },
+        LineState::RawStr { hashes } => {
+            let end = raw_string_end(bytes, 0, hashes);
+            tokens.push(Token {
+                kind: TokenKind::Str,
+                range: 0..end.unwrap_or(bytes.len()),
+            });
+            match end {
+                Some(end) => (end, LineState::Code),
+                None => (bytes.len(), state),
+            }
+        }
+    };
+    let continued = !tokens.is_empty();
+    if state != LineState::Code {
+        return LexedLine {
+            tokens,
+            continued,
+            state,
+        };
+    }
+
+    // only ascii bytes are looked for, so stepping into the middle of a char skips over it
+    while i < bytes.len() {
+        let start = i;
+        let mut state = LineState::Code;
+
+        let kind = match (bytes[i], bytes.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                i = bytes.len();
+                TokenKind::Comment
+            }
+            (b'/', Some(b'*')) => {
+                (i, state) = block_comment_end(bytes, i + 2, 1);
+                TokenKind::Comment
+            }
+            (b'"', _) => {
+                match string_end(bytes, i + 1) {
+                    Some(end) => i = end,
+                    None => (i, state) = (bytes.len(), LineState::Str),
+                }
+                TokenKind::Str
+            }
+            (b'r', _) if starts_raw_string(bytes, i) => {
+                let hashes = bytes[i + 1..]
+                    .iter()
+                    .take_while(|byte| **byte == b'#')
+                    .count();
+                match raw_string_end(bytes, i + hashes + 2, hashes) {
+                    Some(end) => i = end,
+                    None => (i, state) = (bytes.len(), LineState::RawStr { hashes }),
+                }
+                TokenKind::Str
+            }
+            (b'\'', _) => match char_end(line, i + 1) {
+                Some(end) => {
+                    i = end;
+                    TokenKind::Char
+                }
+                None => {
+                    i = word_end(bytes, i + 1);
+                    TokenKind::Lifetime
+                }
+            },
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            range: start..i,
+        });
+        if state != LineState::Code {
+            return LexedLine {
+                tokens,
+                continued,
+                state,
+            };
+        }
+    }
+
+    LexedLine {
+        tokens,
+        continued,
+        state: LineState::Code,
+    }
+}
+
+/// Whether the byte can be part of a name or number. Any byte of a non-ascii char can be
+pub fn is_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii()
+}
+
+/// The end of the name or number starting at `start`
+pub fn word_end(bytes: &[u8], start: usize) -> usize {
+    start
+        + bytes[start..]
+            .iter()
+            .take_while(|byte| is_word(**byte))
+            .count()
+}
+
+// The end of a block comment `depth` deep at `start`, past its last */, and what the next line
+// starts off in. /* comments nest, unlike in c
+fn block_comment_end(bytes: &[u8], start: usize, depth: usize) -> (usize, LineState) {
+    let mut depth = depth;
+    let mut i = start;
+
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1]) {
+            (b'/', b'*') => {
+                depth += 1;
+                i += 2;
+            }
+            (b'*', b'/') => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return (i, LineState::Code);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    (bytes.len(), LineState::BlockComment { depth })
+}
+
+// The end of a string whose contents start at `start`, past its closing quote. None when it goes
+// on to the next line
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
+// r"…" and r#"…"#, with a b in front for byte strings. Not r#ident, a raw identifier
+fn starts_raw_string(bytes: &[u8], at: usize) -> bool {
+    let prefix_start = match at.checked_sub(1).map(|before| bytes[before]) {
+        Some(b'b') => at - 1,
+        _ => at,
+    };
+    let starts_word = prefix_start == 0 || !is_word(bytes[prefix_start - 1]);
+    let hashes = bytes[at + 1..]
+        .iter()
+        .take_while(|byte| **byte == b'#')
+        .count();
+
+    starts_word && bytes.get(at + 1 + hashes) == Some(&b'"')
+}
+
+// The end of a raw string with `hashes` hashes whose contents start at `start`, past its last
+// hash. Escapes don't count in them, only a quote with as many hashes after it ends one. None when
+// it goes on to the next line
+fn raw_string_end(bytes: &[u8], start: usize, hashes: usize) -> Option<usize> {
+    (start..bytes.len())
+        .find(|i| {
+            bytes[*i] == b'"'
+                && bytes[i + 1..].len() >= hashes
+                && bytes[i + 1..i + 1 + hashes]
+                    .iter()
+                    .all(|byte| *byte == b'#')
+        })
+        .map(|quote| quote + 1 + hashes)
+}
+
+// The end of a char literal whose contents start at `start`, None when the ' starts a lifetime
+// instead. `'a'` is a char but `'a` on its own isn't, so it's told apart by the closing quote
+fn char_end(code: &str, start: usize) -> Option<usize> {
+    let mut chars = code[start..].char_indices();
+    let (_, first) = chars.next()?;
+
+    if first == '\\' {
+        // past the escaped char, so '\'' ends at the right quote
+        chars.next()?;
+        return chars
+            .take_while(|(_, c)| *c != '\n')
+            .find(|(_, c)| *c == '\'')
+            .map(|(offset, _)| start + offset + 1);
+    }
+
+    match chars.next()? {
+        (offset, '\'') if first != '\'' && first != '\n' => Some(start + offset + 1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(code: &str) -> Vec<(TokenKind, &str)> {
+        tokens(code)
+            .into_iter()
+            .map(|token| (token.kind, &code[token.range]))
+            .collect()
+    }
+
+    #[test]
+    fn literals_and_comments_are_found() {
+        assert_eq!(
+            kinds(r#"let s = "a \" b"; // done"#),
+            vec![
+                (TokenKind::Str, r#""a \" b""#),
+                (TokenKind::Comment, "// done")
+            ]
+        );
+        assert_eq!(
+            kinds("a /* b /* c */ d */ e"),
+            vec![(TokenKind::Comment, "/* b /* c */ d */")]
+        );
+        assert_eq!(
+            kinds(r"'a' '\n' '\'' b'{'"),
+            vec![
+                (TokenKind::Char, "'a'"),
+                (TokenKind::Char, r"'\n'"),
+                (TokenKind::Char, r"'\''"),
+                (TokenKind::Char, "'{'")
+            ]
+        );
+    }
+
+    #[test]
+    fn open_strings_end_before_the_next_string() {
+        assert_eq!(
+            kinds("let a = \"hel\nlet b = \"world\";"),
+            vec![(TokenKind::Str, "\"hel"), (TokenKind::Str, "\"world\"")]
+        );
+        assert_eq!(
+            kinds("\"a\n}\nfoo(\"b\")"),
+            vec![(TokenKind::Str, "\"a\n}"), (TokenKind::Str, "\"b\"")]
+        );
+        // but strings going over lines on purpose are kept whole
+        assert_eq!(
+            kinds("\"a\nb\";\n\"c\\\nd\".len()"),
+            vec![(TokenKind::Str, "\"a\nb\""), (TokenKind::Str, "\"c\\\nd\"")]
+        );
+    }
+
+    #[test]
+    fn states_carry_over_lines() {
+        assert_eq!(
+            lex_line("a /* b /* c */", LineState::Code).state,
+            LineState::BlockComment { depth: 1 }
+        );
+        assert_eq!(
+            lex_line("*/ x", LineState::BlockComment { depth: 1 }),
+            LexedLine {
+                tokens: vec![Token {
+                    kind: TokenKind::Comment,
+                    range: 0..2
+                }],
+                continued: true,
+                state: LineState::Code
+            }
+        );
+        assert_eq!(
+            lex_line("let s = r##\"a\"#", LineState::Code).state,
+            LineState::RawStr { hashes: 2 }
+        );
+        assert_eq!(
+            lex_line("\"# \"##;", LineState::RawStr { hashes: 2 }).state,
+            LineState::Code
+        );
+    }
+
+    #[test]
+    fn raw_strings_end_at_their_hashes() {
+        assert_eq!(
+            kinds(r###"r#"a "quoted" b"# r"\" br##"x"#"## r#type"###),
+            vec![
+                (TokenKind::Str, r##"r#"a "quoted" b"#"##),
+                (TokenKind::Str, r#"r"\""#),
+                (TokenKind::Str, r###"r##"x"#"##"###),
+            ]
+        );
+    }
+
+    #[test]
+    fn lifetimes_arent_chars() {
+        assert_eq!(
+            kinds("fn f<'a>(x: &'a str) -> char { 'x' }"),
+            vec![
+                (TokenKind::Lifetime, "'a"),
+                (TokenKind::Lifetime, "'a"),
+                (TokenKind::Char, "'x'")
+            ]
+        );
+        assert_eq!(
+            kinds("'outer: loop { break 'outer; }"),
+            vec![
+                (TokenKind::Lifetime, "'outer"),
+                (TokenKind::Lifetime, "'outer")
+            ]
+        );
+    }
+}
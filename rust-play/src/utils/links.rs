@@ -0,0 +1,146 @@
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // path:line:col, where the path has an extension. Windows paths may start with a drive letter
+    static ref FILE_LOCATION: Regex =
+        Regex::new(r#"(?:[A-Za-z]:)?[^\s:'"`()<>]*\.\w+:(\d+):(\d+)"#).unwrap();
+}
+
+/// A `path:line:col` reference to a source file, like rustc prints for errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLocation {
+    pub path: String,
+    // both start at 1
+    pub line: usize,
+    pub col: usize,
+}
+
+// characters which can't be part of a bare url
+fn is_url_end(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'' | '`' | '<' | '>')
+}
+
+/// Find the bare http(s) urls in some text, returning their byte ranges
+pub fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = vec![];
+
+    let mut offset = 0;
+    while let Some(index) = text[offset..].find("http") {
+        let start = offset + index;
+        let candidate = &text[start..];
+
+        let len = candidate.find(is_url_end).unwrap_or(candidate.len());
+        offset = start + len.max(1);
+
+        // don't start in the middle of a word
+        if text[..start].ends_with(char::is_alphanumeric) {
+            continue;
+        }
+
+        let Some(rest) = candidate
+            .strip_prefix("https://")
+            .or_else(|| candidate.strip_prefix("http://"))
+        else {
+            continue;
+        };
+
+        // punctuation at the end is usually part of the sentence, not the url
+        let url = candidate[..len].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']']);
+
+        if url.len() > candidate.len() - rest.len() {
+            urls.push(start..start + url.len());
+        }
+    }
+
+    urls
+}
+
+/// Find the `path:line:col` references in some text, returning their byte ranges
+pub fn find_file_locations(text: &str) -> Vec<(Range<usize>, FileLocation)> {
+    FILE_LOCATION
+        .captures_iter(text)
+        .filter_map(|captures| {
+            let all = captures.get(0)?;
+            let line = captures.get(1)?;
+
+            let location = FileLocation {
+                path: text[all.start()..line.start() - 1].to_string(),
+                line: line.as_str().parse().ok()?,
+                col: captures.get(2)?.as_str().parse().ok()?,
+            };
+
+            Some((all.range(), location))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(text: &str) -> Vec<&str> {
+        find_urls(text).into_iter().map(|r| &text[r]).collect()
+    }
+
+    #[test]
+    fn finds_urls() {
+        assert_eq!(
+            urls("see https://doc.rust-lang.org/error-index.html#E0308 for more"),
+            ["https://doc.rust-lang.org/error-index.html#E0308"]
+        );
+        assert_eq!(
+            urls("http://a.com and https://b.com"),
+            ["http://a.com", "https://b.com"]
+        );
+    }
+
+    #[test]
+    fn trims_trailing_punctuation() {
+        assert_eq!(
+            urls("(see https://example.com/foo)."),
+            ["https://example.com/foo"]
+        );
+        assert_eq!(urls("<https://example.com>"), ["https://example.com"]);
+    }
+
+    #[test]
+    fn ignores_non_urls() {
+        assert!(urls("http https:// xhttps://example.com httpserver").is_empty());
+    }
+
+    #[test]
+    fn finds_file_locations() {
+        let text = "error[E0308]: mismatched types\n --> src/main.rs:12:5\n";
+        let locations = find_file_locations(text);
+
+        assert_eq!(locations.len(), 1);
+        assert_eq!(&text[locations[0].0.clone()], "src/main.rs:12:5");
+        assert_eq!(
+            locations[0].1,
+            FileLocation {
+                path: "src/main.rs".into(),
+                line: 12,
+                col: 5
+            }
+        );
+    }
+
+    #[test]
+    fn finds_panic_and_windows_locations() {
+        let text = "panicked at 'oops', src/main.rs:3:9\nC:\\project\\src\\main.rs:1:2";
+        let paths = find_file_locations(text)
+            .into_iter()
+            .map(|(_, location)| location.path)
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths, ["src/main.rs", "C:\\project\\src\\main.rs"]);
+    }
+
+    #[test]
+    fn ignores_times() {
+        assert!(find_file_locations("finished at 12:30:45").is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    // the level tag env_logger and tracing put near the start of each line
+    static ref LEVEL_TAG: Regex = Regex::new(r"\b(ERROR|WARN|INFO|DEBUG|TRACE)\b").unwrap();
+}
+
+// how far into a line the level tag is looked for, so a level mentioned in the message doesn't count
+const LEVEL_TAG_SEARCH_LEN: usize = 64;
+
+/// The level of a log line, from the least to the most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        };
+
+        f.pad(name)
+    }
+}
+
+/// The level tag of a log line, if it has one
+pub fn line_level(line: &str) -> Option<LogLevel> {
+    let mut end = line.len().min(LEVEL_TAG_SEARCH_LEN);
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let tag = LEVEL_TAG.find(&line[..end])?;
+    let level = match tag.as_str() {
+        "ERROR" => LogLevel::Error,
+        "WARN" => LogLevel::Warn,
+        "INFO" => LogLevel::Info,
+        "DEBUG" => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    };
+
+    Some(level)
+}
+
+/// Which lines of the output to keep
+#[derive(Debug, Clone)]
+pub enum LineFilter {
+    // case insensitive
+    Substring(String),
+    Regex(Regex),
+}
+
+impl LineFilter {
+    /// A plain substring, or a regex between slashes like `/^warn/`. Empty queries don't filter anything
+    pub fn parse(query: &str) -> Result<Option<Self>, regex::Error> {
+        if query.is_empty() {
+            return Ok(None);
+        }
+
+        let filter = match query
+            .strip_prefix('/')
+            .and_then(|query| query.strip_suffix('/'))
+        {
+            Some(pattern) => Self::Regex(RegexBuilder::new(pattern).build()?),
+            None => Self::Substring(query.to_lowercase()),
+        };
+
+        Ok(Some(filter))
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        match self {
+            Self::Substring(substring) => line.to_lowercase().contains(substring.as_str()),
+            Self::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_env_logger_levels() {
+        assert_eq!(
+            line_level("[2023-01-01T00:00:00Z DEBUG my_crate] connecting"),
+            Some(LogLevel::Debug)
+        );
+        assert_eq!(
+            line_level("2023-01-01T00:00:00.000000Z  WARN my_crate: slow"),
+            Some(LogLevel::Warn)
+        );
+        assert_eq!(line_level("just some output"), None);
+    }
+
+    #[test]
+    fn levels_in_the_message_dont_count() {
+        let line = format!("{} ERROR", "x".repeat(LEVEL_TAG_SEARCH_LEN));
+        assert_eq!(line_level(&line), None);
+        assert_eq!(line_level("INFOS and DEBUGGING"), None);
+    }
+
+    #[test]
+    fn parses_substrings_and_regexes() {
+        let filter = LineFilter::parse("Conn").unwrap().unwrap();
+        assert!(filter.matches("[INFO] connected"));
+        assert!(!filter.matches("[INFO] done"));
+
+        let filter = LineFilter::parse("/^\\d+ items$/").unwrap().unwrap();
+        assert!(filter.matches("12 items"));
+        assert!(!filter.matches("got 12 items"));
+
+        assert!(LineFilter::parse("").unwrap().is_none());
+        assert!(LineFilter::parse("/(/").is_err());
+    }
+}
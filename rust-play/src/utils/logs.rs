@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use super::data_dir;
+use super::log_filter::LogLevel;
+use super::timestamp::Timestamp;
+
+// how many records the logs view keeps, the oldest are dropped first
+const MAX_RECORDS: usize = 5000;
+// how many records can wait to be written before new ones are dropped, so logging never waits on
+// the writer
+const QUEUE_LEN: usize = 4096;
+// how big the log file gets before it's rotated, and how many of the rotated ones are kept
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const KEEP_FILES: usize = 3;
+
+// the most verbose level which is recorded, as its index in LogLevel::ALL
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+// bumped whenever a record is added, so the view only lays them out again when there's new ones
+static VERSION: AtomicU64 = AtomicU64::new(0);
+
+/// One of the app's own log events
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub time: Timestamp,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogRecord {
+    /// The record as a line, with the level colored by ansi codes when `ansi` is set
+    pub fn line(&self, ansi: bool) -> String {
+        let color = match self.level {
+            LogLevel::Error => "31",
+            LogLevel::Warn => "33",
+            LogLevel::Info => "32",
+            LogLevel::Debug => "34",
+            LogLevel::Trace => "35",
+        };
+        let Self {
+            time,
+            level,
+            target,
+            message,
+        } = self;
+
+        if ansi {
+            format!("{time} \x1b[{color}m{level:>5}\x1b[0m \x1b[2m{target}:\x1b[0m {message}\n")
+        } else {
+            format!("{time} {level:>5} {target}: {message}\n")
+        }
+    }
+}
+
+/// Where the log files are written
+pub fn logs_dir() -> PathBuf {
+    data_dir().join("logs")
+}
+
+/// Record the events logged at `level` and the less verbose ones from now on
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+fn log_level(level: &Level) -> LogLevel {
+    match *level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        Level::TRACE => LogLevel::Trace,
+    }
+}
+
+fn enabled(level: &Level) -> bool {
+    log_level(level) as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Changes whenever a record is added
+pub fn version() -> u64 {
+    VERSION.load(Ordering::Relaxed)
+}
+
+/// Look at the records logged so far, the oldest first
+pub fn with_records<T>(f: impl FnOnce(&VecDeque<LogRecord>) -> T) -> T {
+    let records = RECORDS.lock().unwrap_or_else(|e| e.into_inner());
+    f(&records)
+}
+
+/// The layer recording the app's events for the logs view and the log file. Logging only queues
+/// the record, a thread of its own writes it out
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_LEN);
+    let dir = logs_dir();
+    thread::spawn(move || write_records(receiver, &dir));
+
+    LogLayer { sender }.with_filter(filter::filter_fn(|metadata| enabled(metadata.level())))
+}
+
+fn write_records(receiver: Receiver<LogRecord>, dir: &Path) {
+    let _ = fs::create_dir_all(dir);
+    let mut file = LogFile::new(dir.join("rust-play.log"), MAX_FILE_BYTES);
+
+    for record in receiver {
+        let _ = file.write(&record.line(false));
+
+        let mut records = RECORDS.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() == MAX_RECORDS {
+            records.pop_front();
+        }
+        records.push_back(record);
+        VERSION.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub struct LogLayer {
+    sender: SyncSender<LogRecord>,
+}
+
+impl<S: Subscriber> Layer<S> for LogLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        let record = LogRecord {
+            time: Timestamp::now(),
+            level: log_level(metadata.level()),
+            // events from the log crate say where they're from in a field
+            target: fields
+                .target
+                .unwrap_or_else(|| metadata.target().to_string()),
+            message: fields.message,
+        };
+
+        // dropped when the writer's behind
+        let _ = self.sender.try_send(record);
+    }
+}
+
+// The message of an event, with its other fields after it
+#[derive(Default)]
+struct Fields {
+    message: String,
+    target: Option<String>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "log.target" => self.target = Some(value.to_string()),
+            _ => self.record_debug(field, &value),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" if self.message.is_empty() => {
+                let _ = write!(self.message, "{value:?}");
+            }
+            "message" => {
+                let _ = write!(self.message, " {value:?}");
+            }
+            // the rest of what the log crate adds
+            name if name.starts_with("log.") => (),
+            name => {
+                let _ = write!(self.message, " {name}={value:?}");
+            }
+        }
+    }
+}
+
+// A log file which is rotated once it gets too big. The older files are numbered, the oldest
+// being deleted
+struct LogFile {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Option<File>,
+    len: u64,
+}
+
+impl LogFile {
+    fn new(path: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            path,
+            max_bytes,
+            file: None,
+            len: 0,
+        }
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        self.path.with_extension(format!("{index}.log"))
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.len >= self.max_bytes {
+            self.file = None;
+            let _ = fs::remove_file(self.rotated(KEEP_FILES));
+            for index in (1..KEEP_FILES).rev() {
+                let _ = fs::rename(self.rotated(index), self.rotated(index + 1));
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?;
+                self.len = file.metadata()?.len();
+                self.file.insert(file)
+            }
+        };
+
+        file.write_all(line.as_bytes())?;
+        self.len += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn records_are_written_as_lines() {
+        let record = LogRecord {
+            time: Timestamp::default(),
+            level: LogLevel::Warn,
+            target: "rust_play::share".to_string(),
+            message: "gist failed".to_string(),
+        };
+
+        assert_eq!(
+            record.line(false),
+            "00:00:00.000  WARN rust_play::share: gist failed\n"
+        );
+        assert!(record.line(true).contains("\x1b[33m WARN\x1b[0m"));
+    }
+
+    #[test]
+    fn log_files_are_rotated() {
+        let dir = test_dir("logs");
+        fs::create_dir_all(&dir).unwrap();
+
+        // each of the long lines fills the file, the short ones share one
+        let mut file = LogFile::new(dir.join("rust-play.log"), 10);
+        let lines = [
+            "zeroth line\n",
+            "first line\n",
+            "second line\n",
+            "third line\n",
+        ];
+        for line in lines.into_iter().chain(["fourth\n", "fifth\n"]) {
+            file.write(line).unwrap();
+        }
+
+        let read = |index| fs::read_to_string(file.rotated(index)).unwrap();
+        assert_eq!(fs::read_to_string(&file.path).unwrap(), "fourth\nfifth\n");
+        assert_eq!(read(1), "third line\n");
+        assert_eq!(read(2), "second line\n");
+        assert_eq!(read(3), "first line\n");
+        assert!(!file.rotated(4).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
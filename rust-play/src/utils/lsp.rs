@@ -0,0 +1,696 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use serde_json::{json, Value};
+use thiserror::Error;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(target_os = "windows")]
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+#[derive(Debug, Error)]
+pub enum LspError {
+    #[error("Failed to run rust-analyzer: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid message from rust-analyzer: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("A message from rust-analyzer didn't say how long it is")]
+    NoLength,
+}
+
+/// A message the way it's sent over stdio, behind the header saying how long it is
+pub fn encode(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    let mut bytes = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    bytes.extend_from_slice(body.as_bytes());
+
+    bytes
+}
+
+/// Read the next message, None once the other side closed the stream
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>, LspError> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        // the only other header is the content type, which is always the same
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse().map_err(|_| LspError::NoLength)?);
+            }
+        }
+    }
+
+    let mut body = vec![0; length.ok_or(LspError::NoLength)?];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Where something is in a file the way LSP counts it: by its line, and how many UTF-16 code
+/// units into the line it is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    fn to_json(self) -> Value {
+        json!({ "line": self.line, "character": self.character })
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Self {
+            line: value.get("line")?.as_u64()? as usize,
+            character: value.get("character")?.as_u64()? as usize,
+        })
+    }
+}
+
+/// The position of a byte offset in `text`
+pub fn position(text: &str, byte: usize) -> Position {
+    let before = &text[..byte];
+    let line_start = before.rfind('\n').map_or(0, |at| at + 1);
+
+    Position {
+        line: before.matches('\n').count(),
+        character: before[line_start..].encode_utf16().count(),
+    }
+}
+
+/// The byte offset of a position in `text`. Positions past the end of their line are at its end
+pub fn offset(text: &str, position: Position) -> usize {
+    let line_start = text
+        .match_indices('\n')
+        .nth(position.line.wrapping_sub(1))
+        .map_or(0, |(at, _)| at + 1);
+    if position.line > 0 && line_start == 0 {
+        return text.len();
+    }
+
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (at, c) in line.char_indices() {
+        if units >= position.character {
+            return line_start + at;
+        }
+        units += c.len_utf16();
+    }
+
+    line_start + line.len()
+}
+
+/// The change which turns `old` into `new`, as the range of `old` which was replaced and what it
+/// was replaced with. Only what changed is sent, so typing in big files stays quick
+pub fn change(old: &str, new: &str) -> Value {
+    let mut prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let mut suffix = old[prefix..]
+        .bytes()
+        .rev()
+        .zip(new[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+
+    json!({
+        "range": {
+            "start": position(old, prefix).to_json(),
+            "end": position(old, old.len() - suffix).to_json(),
+        },
+        "text": &new[prefix..new.len() - suffix],
+    })
+}
+
+/// The file:// URI of a path, with the chars which can't be in one escaped
+pub fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    uri
+}
+
+/// The path of a file:// URI
+pub fn uri_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+
+    let mut bytes = vec![];
+    let mut rest = path.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| std::str::from_utf8(after.get(..2)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &after[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = after;
+            }
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+
+    // windows paths are written like /C:/Users
+    let drive = path.len() > 2 && path.as_bytes()[2] == b':';
+    Some(PathBuf::from(if drive { &path[1..] } else { &path[..] }))
+}
+
+/// What rust-analyzer answered, along with the byte offset it was asked about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    // the names which could go there, the best first
+    Completions(usize, Vec<String>),
+    // the type and docs of what's there, as plain text
+    Hover(usize, String),
+    // where what's there is defined
+    Definition(usize, PathBuf, Position),
+}
+
+// what a request asked for, to read its reply with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Initialize,
+    Completion,
+    Hover,
+    Definition,
+}
+
+// the names of completion items, in the order the server ranked them
+fn completions(result: &Value) -> Vec<String> {
+    let items = match result {
+        Value::Array(items) => items,
+        Value::Object(list) => match list.get("items") {
+            Some(Value::Array(items)) => items,
+            _ => return vec![],
+        },
+        _ => return vec![],
+    };
+
+    let mut items = items
+        .iter()
+        .filter_map(|item| {
+            let label = item.get("label")?.as_str()?;
+            let sort = item
+                .get("sortText")
+                .and_then(Value::as_str)
+                .unwrap_or(label);
+            Some((sort, label))
+        })
+        .collect::<Vec<_>>();
+    items.sort_by_key(|(sort, _)| *sort);
+
+    let mut names: Vec<String> = vec![];
+    for (_, label) in items {
+        // labels like `push(…)` or `println!(…)` are cut down to what's typed
+        let name_end = label
+            .find(|c: char| !c.is_alphanumeric() && c != '_' && c != '#')
+            .unwrap_or(label.len());
+        let macro_bang = label[name_end..].starts_with('!') as usize;
+        let name = &label[..name_end + macro_bang];
+
+        if !name.is_empty() && !names.iter().any(|other| other == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    names
+}
+
+// the text of a hover, without the fences around its code
+fn hover(result: &Value) -> Option<String> {
+    fn text(contents: &Value) -> String {
+        match contents {
+            Value::String(text) => text.clone(),
+            Value::Array(parts) => parts.iter().map(text).collect::<Vec<_>>().join("\n"),
+            Value::Object(part) => part
+                .get("value")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            _ => String::new(),
+        }
+    }
+
+    let text = text(result.get("contents")?);
+    let text = text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = text.trim();
+
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// where the first of the definitions is, from a location or a link to one
+fn definition(result: &Value) -> Option<(PathBuf, Position)> {
+    let location = match result {
+        Value::Array(locations) => locations.first()?,
+        location => location,
+    };
+
+    let (uri, range) = match location.get("targetUri") {
+        Some(uri) => (uri, location.get("targetSelectionRange")?),
+        None => (location.get("uri")?, location.get("range")?),
+    };
+
+    Some((
+        uri_path(uri.as_str()?)?,
+        Position::from_json(range.get("start")?)?,
+    ))
+}
+
+/// rust-analyzer running on a tab's project, with the tab's main.rs open in it. It's talked to
+/// over stdio from threads of its own, so a busy server never holds up the editor
+#[derive(Debug)]
+pub struct LspClient {
+    child: Child,
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Value>,
+    next_id: u64,
+    // the requests waiting on an answer, with what they asked about
+    pending: HashMap<u64, (Request, usize)>,
+    // set once the server answered the initialize request
+    ready: bool,
+    // whether changes can be sent as the ranges they changed, instead of the whole file
+    incremental: bool,
+    main: PathBuf,
+    uri: String,
+    // the code as the server has it, None until it's been opened
+    text: Option<String>,
+    version: i32,
+}
+
+impl LspClient {
+    /// Start `program` on the project in `root`. `on_message` is called from the reading thread
+    /// whenever a message comes in
+    pub fn start(
+        program: &str,
+        root: &Path,
+        main: &Path,
+        on_message: impl Fn() + Send + 'static,
+    ) -> Result<Self, LspError> {
+        let mut command = Command::new(program);
+        command
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        // hide the console window from command. Very important.
+        #[cfg(target_os = "windows")]
+        command.creation_flags(CREATE_NO_WINDOW.0);
+
+        let mut child = command.spawn()?;
+
+        let (tx, writer_rx) = channel::<Vec<u8>>();
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        thread::spawn(move || {
+            for bytes in writer_rx {
+                if stdin.write_all(&bytes).and_then(|_| stdin.flush()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (reader_tx, rx) = channel();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if reader_tx.send(message).is_err() {
+                    break;
+                }
+                on_message();
+            }
+        });
+
+        let mut client = Self {
+            child,
+            tx,
+            rx,
+            next_id: 0,
+            pending: HashMap::new(),
+            ready: false,
+            incremental: false,
+            main: main.to_path_buf(),
+            uri: file_uri(main),
+            text: None,
+            version: 0,
+        };
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": file_uri(root),
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": { "dynamicRegistration": false },
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                    "hover": { "contentFormat": ["plaintext", "markdown"] },
+                    "definition": { "linkSupport": true },
+                },
+            },
+            // the tab is checked with cargo already, and builds shouldn't wait on its lock
+            "initializationOptions": {
+                "checkOnSave": false,
+                "cargo": { "targetDir": true },
+            },
+        });
+        client.request("initialize", params, Request::Initialize, 0);
+
+        Ok(client)
+    }
+
+    /// The main.rs the server has open
+    pub fn main(&self) -> &Path {
+        &self.main
+    }
+
+    /// Whether the server is still running
+    pub fn running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Tell the server what the code is now, opening the file the first time
+    pub fn sync(&mut self, code: &str) {
+        if !self.ready {
+            return;
+        }
+
+        match &self.text {
+            None => {
+                let document = json!({
+                    "uri": self.uri,
+                    "languageId": "rust",
+                    "version": self.version,
+                    "text": code,
+                });
+                self.notify("textDocument/didOpen", json!({ "textDocument": document }));
+            }
+            Some(text) if text != code => {
+                let change = if self.incremental {
+                    change(text, code)
+                } else {
+                    json!({ "text": code })
+                };
+                self.version += 1;
+                let document = json!({ "uri": self.uri, "version": self.version });
+                let params = json!({ "textDocument": document, "contentChanges": [change] });
+                self.notify("textDocument/didChange", params);
+            }
+            Some(_) => return,
+        }
+
+        self.text = Some(code.to_string());
+    }
+
+    /// Ask what could be typed at the byte offset `at` of `code`
+    pub fn completion(&mut self, code: &str, at: usize) {
+        self.ask("textDocument/completion", code, at, Request::Completion);
+    }
+
+    /// Ask for the type and docs of what's at `at`
+    pub fn hover(&mut self, code: &str, at: usize) {
+        self.ask("textDocument/hover", code, at, Request::Hover);
+    }
+
+    /// Ask where what's at `at` is defined
+    pub fn definition(&mut self, code: &str, at: usize) {
+        self.ask("textDocument/definition", code, at, Request::Definition);
+    }
+
+    /// The answers which came in since the last call
+    pub fn poll(&mut self) -> Vec<Reply> {
+        let mut replies = vec![];
+
+        while let Ok(message) = self.rx.try_recv() {
+            let id = message.get("id").and_then(Value::as_u64);
+
+            // the server asks things of its own, it's told there's nothing to say
+            if let (Some(method), Some(id)) = (message.get("method"), message.get("id")) {
+                let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                let result = match method.as_str() {
+                    Some("workspace/configuration") => Value::Array(vec![Value::Null; items]),
+                    _ => Value::Null,
+                };
+                self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+                continue;
+            }
+
+            let Some((request, at)) = id.and_then(|id| self.pending.remove(&id)) else {
+                continue;
+            };
+            let result = &message["result"];
+            match request {
+                Request::Initialize => {
+                    let sync = &result["capabilities"]["textDocumentSync"];
+                    let kind = sync.get("change").unwrap_or(sync).as_u64();
+                    self.incremental = kind == Some(2);
+                    self.ready = true;
+                    self.notify("initialized", json!({}));
+                }
+                Request::Completion => replies.push(Reply::Completions(at, completions(result))),
+                Request::Hover => replies.extend(hover(result).map(|text| Reply::Hover(at, text))),
+                Request::Definition => replies.extend(
+                    definition(result)
+                        .map(|(path, position)| Reply::Definition(at, path, position)),
+                ),
+            }
+        }
+
+        replies
+    }
+
+    // Ask about a position, with the server knowing the code as it is now
+    fn ask(&mut self, method: &str, code: &str, at: usize, request: Request) {
+        if !self.ready {
+            return;
+        }
+
+        self.sync(code);
+        let params = json!({
+            "textDocument": { "uri": self.uri },
+            "position": position(code, at).to_json(),
+        });
+        self.request(method, params, request, at);
+    }
+
+    fn request(&mut self, method: &str, params: Value, request: Request, at: usize) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, (request, at));
+
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn send(&self, message: Value) {
+        // the writing thread is gone once the server exits, which `running` tells
+        let _ = self.tx.send(encode(&message));
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn messages_are_framed() {
+        let first = json!({ "jsonrpc": "2.0", "id": 1, "result": null });
+        let second = json!({ "jsonrpc": "2.0", "method": "exit", "params": { "text": "ü\r\n" } });
+
+        let mut stream = encode(&first);
+        stream.extend(b"Content-Type: application/vscode-jsonrpc; charset=utf-8\r\n");
+        stream.extend(encode(&second));
+        let mut reader = Cursor::new(stream);
+
+        assert_eq!(read_message(&mut reader).unwrap(), Some(first));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(second));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+
+        let mut reader = Cursor::new(b"Content-Type: text\r\n\r\n{}".to_vec());
+        assert!(matches!(read_message(&mut reader), Err(LspError::NoLength)));
+        let mut reader = Cursor::new(b"Content-Length: 10\r\n\r\n{}".to_vec());
+        assert!(matches!(read_message(&mut reader), Err(LspError::Io(_))));
+    }
+
+    #[test]
+    fn positions_count_utf16() {
+        let text = "fn a() {}\nlet 😀 = \"é\";\n";
+        let at = text.find('=').unwrap();
+
+        assert_eq!(
+            position(text, at),
+            Position {
+                line: 1,
+                character: 7
+            }
+        );
+        assert_eq!(
+            offset(
+                text,
+                Position {
+                    line: 1,
+                    character: 7
+                }
+            ),
+            at
+        );
+        assert_eq!(
+            offset(
+                text,
+                Position {
+                    line: 0,
+                    character: 3
+                }
+            ),
+            3
+        );
+        // past the end of a line, and of the text
+        assert_eq!(
+            offset(
+                text,
+                Position {
+                    line: 0,
+                    character: 40
+                }
+            ),
+            9
+        );
+        assert_eq!(
+            offset(
+                text,
+                Position {
+                    line: 9,
+                    character: 0
+                }
+            ),
+            text.len()
+        );
+    }
+
+    #[test]
+    fn only_the_changed_range_is_sent() {
+        let change = change("let a = 1;\nlet b = 2;\n", "let a = 1;\nlet bc = 22;\n");
+        assert_eq!(
+            change,
+            json!({
+                "range": {
+                    "start": { "line": 1, "character": 5 },
+                    "end": { "line": 1, "character": 8 },
+                },
+                "text": "c = 2",
+            })
+        );
+
+        // the chars on either side of a multi-byte one aren't split
+        let change = super::change("aéb", "aèb");
+        assert_eq!(change["range"]["start"]["character"], 1);
+        assert_eq!(change["range"]["end"]["character"], 2);
+        assert_eq!(change["text"], "è");
+    }
+
+    #[test]
+    fn paths_go_to_uris_and_back() {
+        let uri = file_uri(Path::new("C:\\Users\\a b\\main.rs"));
+        assert_eq!(uri, "file:///C:/Users/a%20b/main.rs");
+        assert_eq!(uri_path(&uri), Some(PathBuf::from("C:/Users/a b/main.rs")));
+        // rust-analyzer escapes the drive's colon
+        assert_eq!(
+            uri_path("file:///c%3A/src/lib.rs"),
+            Some(PathBuf::from("c:/src/lib.rs"))
+        );
+        assert_eq!(
+            uri_path("file:///tmp/x.rs"),
+            Some(PathBuf::from("/tmp/x.rs"))
+        );
+        assert_eq!(uri_path("https://example.com"), None);
+    }
+
+    #[test]
+    fn replies_are_read() {
+        let list = json!({ "isIncomplete": false, "items": [
+            { "label": "push(…)", "sortText": "b" },
+            { "label": "println!(…)", "sortText": "c" },
+            { "label": "pop()", "sortText": "a" },
+            { "label": "push", "sortText": "d" },
+        ]});
+        assert_eq!(completions(&list), ["pop", "push", "println!"]);
+        assert_eq!(completions(&Value::Null), Vec::<String>::new());
+
+        let markup = json!({ "contents": {
+            "kind": "markdown",
+            "value": "```rust\nfn push(&mut self, value: T)\n```\n\nAppends an element.",
+        }});
+        let expected = "fn push(&mut self, value: T)\n\nAppends an element.";
+        assert_eq!(hover(&markup).as_deref(), Some(expected));
+        assert_eq!(hover(&json!({ "contents": "" })), None);
+
+        let link = json!([{
+            "targetUri": "file:///tmp/main.rs",
+            "targetRange": { "start": { "line": 0, "character": 0 } },
+            "targetSelectionRange": { "start": { "line": 2, "character": 4 } },
+        }]);
+        assert_eq!(
+            definition(&link),
+            Some((
+                PathBuf::from("/tmp/main.rs"),
+                Position {
+                    line: 2,
+                    character: 4
+                }
+            ))
+        );
+        let start = json!({ "line": 1, "character": 0 });
+        let location = json!({ "uri": "file:///a.rs", "range": { "start": start } });
+        assert!(definition(&location).is_some());
+        assert_eq!(definition(&Value::Null), None);
+    }
+}
@@ -0,0 +1,91 @@
+use std::process::{Command, Stdio};
+
+use thiserror::Error;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(target_os = "windows")]
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+#[derive(Debug, Error)]
+pub enum MiriError {
+    #[error("Failed to run rustup: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Miri needs the nightly toolchain. Install it along with Miri with\n\nrustup toolchain install nightly --component miri")]
+    NoNightly,
+    #[error("Miri isn't installed. Add it to the nightly toolchain with\n\nrustup +nightly component add miri")]
+    NotInstalled,
+}
+
+/// Whether the nightly toolchain has the miri component, going by `rustup component list`
+pub fn check_installed() -> Result<(), MiriError> {
+    let mut command = Command::new("rustup");
+    command
+        .args(["component", "list", "--installed", "--toolchain", "nightly"])
+        .stdin(Stdio::null());
+
+    // hide the console window from command. Very important.
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW.0);
+
+    let output = command.output()?;
+
+    // rustup fails when the toolchain isn't installed
+    if !output.status.success() {
+        return Err(MiriError::NoNightly);
+    }
+
+    if has_component(&String::from_utf8_lossy(&output.stdout), "miri") {
+        Ok(())
+    } else {
+        Err(MiriError::NotInstalled)
+    }
+}
+
+// the components are listed by their name and target, like `miri-x86_64-pc-windows-msvc`
+fn has_component(list: &str, name: &str) -> bool {
+    list.lines().any(|line| {
+        let component = line.split_whitespace().next().unwrap_or_default();
+        component == name || component.starts_with(&format!("{name}-"))
+    })
+}
+
+/// Whether a line of Miri's output is the header of an undefined behavior error
+pub fn is_undefined_behavior(line: &str) -> bool {
+    // the header is colored
+    let line = strip_ansi_escapes::strip(line.as_bytes())
+        .map(|stripped| String::from_utf8_lossy(&stripped).into_owned())
+        .unwrap_or_else(|_| line.to_string());
+
+    line.trim_start().starts_with("error: Undefined Behavior")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_installed_components() {
+        let list = "cargo-x86_64-pc-windows-msvc\nmiri-x86_64-pc-windows-msvc\nrust-src\n";
+
+        assert!(has_component(list, "miri"));
+        assert!(has_component(list, "rust-src"));
+        assert!(!has_component(list, "clippy"));
+    }
+
+    #[test]
+    fn finds_undefined_behavior_headers() {
+        assert!(is_undefined_behavior(
+            "\x1b[0m\x1b[1m\x1b[38;5;9merror\x1b[0m\x1b[0m\x1b[1m: Undefined Behavior: dereferencing pointer failed\x1b[0m\n"
+        ));
+        assert!(is_undefined_behavior(
+            "error: Undefined Behavior: out-of-bounds pointer use\n"
+        ));
+        assert!(!is_undefined_behavior(
+            "error: could not compile `playground`\n"
+        ));
+        assert!(!is_undefined_behavior(
+            "println!(\"error: Undefined Behavior\")"
+        ));
+    }
+}
@@ -1,2 +1,66 @@
+use std::env;
+#[cfg(test)]
+use std::fs;
+use std::path::PathBuf;
+
 pub mod ansi_parser;
+pub mod brackets;
+pub mod build_cache;
+pub mod cargo_output;
+pub mod cli;
+pub mod color_scheme;
+pub mod completion;
+pub mod contrast;
+pub mod crash_reports;
+pub mod cursor;
 pub mod data;
+pub mod demangle;
+pub mod diagnostics;
+pub mod drafts;
+pub mod examples;
+pub mod export;
+pub mod file_watch;
+pub mod find;
+pub mod folding;
+pub mod fuzzy;
+pub mod indent_guides;
+pub mod inlay_hints;
+pub mod key_combo;
+pub mod lexer;
+pub mod links;
+pub mod log_filter;
+pub mod logs;
+pub mod lsp;
+pub mod miri;
+pub mod multi_cursor;
+pub mod playground;
+pub mod run_history;
+pub mod rustfmt;
+pub mod session;
+pub mod single_instance;
+pub mod snippets;
+pub mod syntax;
+pub mod tab_names;
+pub mod test_results;
+pub mod timestamp;
+pub mod typing;
+pub mod undo;
+pub mod vim;
+pub mod wasm;
+pub mod whitespace;
+
+/// Where the app keeps its own files, like the session, the drafts and the logs
+pub fn data_dir() -> PathBuf {
+    env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("RustPlay")
+}
+
+/// An empty directory under the temp dir for a test to write in, one per test run
+#[cfg(test)]
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rust-play-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
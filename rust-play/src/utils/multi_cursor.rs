@@ -0,0 +1,385 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+/// One of the editor's cursors, by char index. The anchor is the other end of what it has
+/// selected, the same as the cursor when nothing is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+    pub anchor: usize,
+    pub cursor: usize,
+}
+
+impl Selection {
+    pub fn new(anchor: usize, cursor: usize) -> Self {
+        Self { anchor, cursor }
+    }
+
+    /// A cursor with nothing selected
+    pub fn at(cursor: usize) -> Self {
+        Self::new(cursor, cursor)
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.anchor.min(self.cursor)..self.anchor.max(self.cursor)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.cursor
+    }
+
+    fn overlaps(&self, other: &Selection) -> bool {
+        let (a, b) = (self.range(), other.range());
+        a == b || (a.start < b.end && b.start < a.end)
+    }
+}
+
+/// A change made to the code at every cursor
+#[derive(Debug, Clone, Copy)]
+pub enum Edit<'a> {
+    // typed or pasted, in place of what's selected
+    Insert(&'a str),
+    // a new line, indented as much as the cursor's
+    Enter,
+    Backspace,
+    Delete,
+}
+
+/// A way every cursor can move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Home,
+    End,
+}
+
+/// Make the edit at every selection, earlier ones moving the cursors after them over by what they
+/// added or removed. The selections after come back in the same order, with the ones which ran
+/// into each other merged into the later one
+pub fn edit(code: &mut String, selections: &[Selection], edit: Edit) -> Vec<Selection> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut order: Vec<usize> = (0..selections.len()).collect();
+    order.sort_by_key(|&index| selections[index].range().start);
+
+    let mut edited = String::with_capacity(code.len());
+    let mut after = selections.to_vec();
+    // how far into the old code has been copied, and how many chars longer the new code is so far
+    let mut copied = 0;
+    let mut shift = 0isize;
+
+    for index in order {
+        let range = selections[index].range();
+        let (removed, inserted) = match edit {
+            Edit::Insert(text) => (range, text.to_string()),
+            Edit::Enter => {
+                let line_start = line_start(&chars, range.start);
+                let indent = chars[line_start..]
+                    .iter()
+                    .take_while(|c| **c == ' ' || **c == '\t')
+                    .take(range.start - line_start);
+                (range, std::iter::once(&'\n').chain(indent).collect())
+            }
+            Edit::Backspace if range.is_empty() => {
+                (range.start.saturating_sub(1)..range.end, String::new())
+            }
+            Edit::Delete if range.is_empty() => {
+                (range.start..(range.end + 1).min(chars.len()), String::new())
+            }
+            Edit::Backspace | Edit::Delete => (range, String::new()),
+        };
+        // an earlier cursor's edit may have removed some of this one's already
+        let removed = removed.start.max(copied)..removed.end.max(copied);
+
+        edited.extend(&chars[copied..removed.start]);
+        edited.push_str(&inserted);
+        copied = removed.end;
+
+        let inserted_len = inserted.chars().count();
+        let cursor = (removed.start as isize + shift) as usize + inserted_len;
+        after[index] = Selection::at(cursor);
+        shift += inserted_len as isize - removed.len() as isize;
+    }
+    edited.extend(&chars[copied..]);
+
+    *code = edited;
+    merge(after)
+}
+
+/// Move every cursor, keeping their anchors where they are when `select` is set. Otherwise a
+/// selection moved left or right collapses to the side it moved to
+pub fn move_cursors(
+    code: &str,
+    selections: &[Selection],
+    motion: Motion,
+    select: bool,
+) -> Vec<Selection> {
+    let chars: Vec<char> = code.chars().collect();
+
+    let moved = selections.iter().map(|selection| {
+        let range = selection.range();
+        let cursor = match motion {
+            Motion::Left if !select && !selection.is_empty() => range.start,
+            Motion::Right if !select && !selection.is_empty() => range.end,
+            Motion::Left => selection.cursor.saturating_sub(1),
+            Motion::Right => (selection.cursor + 1).min(chars.len()),
+            Motion::Home => line_start(&chars, selection.cursor),
+            Motion::End => {
+                let rest = chars[selection.cursor..].iter();
+                selection.cursor + rest.take_while(|c| **c != '\n').count()
+            }
+        };
+
+        if select {
+            Selection::new(selection.anchor, cursor)
+        } else {
+            Selection::at(cursor)
+        }
+    });
+
+    merge(moved.collect())
+}
+
+/// Add a selection on the next place the last selection's text is in the code, after it and then
+/// from the top. With nothing selected it selects the word at the cursor instead. The new
+/// selection is the last one, they're the same as before when there's nothing more to select
+pub fn select_next(code: &str, selections: &[Selection]) -> Vec<Selection> {
+    let mut selections = selections.to_vec();
+    let Some(last) = selections.last().copied() else {
+        return selections;
+    };
+    let chars: Vec<char> = code.chars().collect();
+
+    if last.is_empty() {
+        let is_word = |c: &&char| c.is_alphanumeric() || **c == '_';
+        let start = last.cursor
+            - chars[..last.cursor]
+                .iter()
+                .rev()
+                .take_while(is_word)
+                .count();
+        let end = last.cursor + chars[last.cursor..].iter().take_while(is_word).count();
+
+        if start < end {
+            *selections.last_mut().unwrap() = Selection::new(start, end);
+        }
+        return selections;
+    }
+
+    let range = last.range();
+    let text: String = chars[range.clone()].iter().collect();
+    let mut found: Vec<Selection> = vec![];
+    let mut char_index = 0;
+    let mut byte_index = 0;
+    for (start, _) in code.match_indices(&text) {
+        char_index += code[byte_index..start].chars().count();
+        byte_index = start;
+        found.push(Selection::new(char_index, char_index + range.len()));
+    }
+
+    let next = found
+        .iter()
+        .filter(|selection| selection.range().start >= range.end)
+        .chain(&found)
+        .find(|selection| !selections.iter().any(|other| other.overlaps(selection)));
+    if let Some(next) = next {
+        selections.push(*next);
+    }
+
+    selections
+}
+
+/// What's selected at every cursor, top to bottom, a line each
+pub fn selected_text(code: &str, selections: &[Selection]) -> String {
+    let chars: Vec<char> = code.chars().collect();
+    let mut ranges: Vec<Range<usize>> = selections.iter().map(Selection::range).collect();
+    ranges.sort_by_key(|range| range.start);
+
+    ranges
+        .into_iter()
+        .filter(|range| !range.is_empty())
+        .map(|range| chars[range].iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn line_start(chars: &[char], index: usize) -> usize {
+    index
+        - chars[..index]
+            .iter()
+            .rev()
+            .take_while(|c| **c != '\n')
+            .count()
+}
+
+// Selections which overlap become one, covering both, in the place of the later one so the last
+// selection stays last
+fn merge(selections: Vec<Selection>) -> Vec<Selection> {
+    let mut merged: Vec<Selection> = vec![];
+
+    for mut selection in selections {
+        while let Some(index) = merged.iter().position(|other| other.overlaps(&selection)) {
+            let other = merged.remove(index).range();
+            let range = selection.range();
+            let (start, end) = (range.start.min(other.start), range.end.max(other.end));
+
+            selection = if selection.anchor <= selection.cursor {
+                Selection::new(start, end)
+            } else {
+                Selection::new(end, start)
+            };
+        }
+        merged.push(selection);
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the code with each selection's cursor marked by a `|`, in front of anything else there
+    fn marked(code: &str, selections: &[Selection]) -> String {
+        let mut cursors: Vec<usize> = selections.iter().map(|s| s.cursor).collect();
+        cursors.sort_unstable();
+
+        let mut marked = String::new();
+        for (index, c) in code.chars().enumerate() {
+            marked.extend(
+                cursors
+                    .iter()
+                    .filter(|cursor| **cursor == index)
+                    .map(|_| '|'),
+            );
+            marked.push(c);
+        }
+        let end = code.chars().count();
+        marked.extend(cursors.iter().filter(|cursor| **cursor == end).map(|_| '|'));
+
+        marked
+    }
+
+    // the code with a cursor at each `|` in it
+    fn cursors(marked: &str) -> (String, Vec<Selection>) {
+        let mut selections = vec![];
+        let mut code = String::new();
+        for c in marked.chars() {
+            if c == '|' {
+                selections.push(Selection::at(code.chars().count()));
+            } else {
+                code.push(c);
+            }
+        }
+
+        (code, selections)
+    }
+
+    fn edit_at_cursors(marked_code: &str, edits: &[Edit]) -> String {
+        let (mut code, mut selections) = cursors(marked_code);
+        for change in edits {
+            selections = edit(&mut code, &selections, *change);
+        }
+
+        marked(&code, &selections)
+    }
+
+    #[test]
+    fn typing_happens_at_every_cursor() {
+        assert_eq!(
+            edit_at_cursors("a|b\nc|d\n|", &[Edit::Insert("xy"), Edit::Insert("ä")]),
+            "axyä|b\ncxyä|d\nxyä|"
+        );
+        assert_eq!(
+            edit_at_cursors("    a|\n  b|", &[Edit::Enter, Edit::Insert("c")]),
+            "    a\n    c|\n  b\n  c|"
+        );
+
+        // each selection is replaced
+        let mut code = "let foo = foo + 1;".to_string();
+        let selections = [Selection::new(4, 7), Selection::new(13, 10)];
+        let after = edit(&mut code, &selections, Edit::Insert("bar"));
+        assert_eq!(code, "let bar = bar + 1;");
+        assert_eq!(after, vec![Selection::at(7), Selection::at(13)]);
+    }
+
+    #[test]
+    fn deleting_happens_at_every_cursor() {
+        assert_eq!(
+            edit_at_cursors("ab|c\nde|f", &[Edit::Backspace, Edit::Backspace]),
+            "|c\n|f"
+        );
+        assert_eq!(
+            edit_at_cursors("|abc\n|def", &[Edit::Delete, Edit::Delete]),
+            "|c\n|f"
+        );
+        // cursors which run into each other become one
+        assert_eq!(
+            edit_at_cursors("ab|c|d", &[Edit::Backspace, Edit::Backspace]),
+            "|d"
+        );
+        assert_eq!(edit_at_cursors("|a|", &[Edit::Delete]), "|");
+    }
+
+    #[test]
+    fn cursors_move_together() {
+        let (code, selections) = cursors("a|b\n  cd|e\n|");
+
+        let moved = move_cursors(&code, &selections, Motion::Home, false);
+        assert_eq!(marked(&code, &moved), "|ab\n|  cde\n|");
+        let moved = move_cursors(&code, &moved, Motion::End, true);
+        assert_eq!(marked(&code, &moved), "ab|\n  cde|\n|");
+        assert_eq!(moved[0], Selection::new(0, 2));
+
+        // selections collapse to the side they're moved to
+        let moved = move_cursors(&code, &moved, Motion::Left, false);
+        assert_eq!(marked(&code, &moved), "|ab\n|  cde|\n");
+        let moved = move_cursors(&code, &moved, Motion::Left, false);
+        assert_eq!(marked(&code, &moved), "|ab|\n  cd|e\n");
+    }
+
+    #[test]
+    fn next_occurrences_are_selected() {
+        let code = "let foo = 1;\nfoo += foo_bar;\nfood";
+
+        // the word at the cursor first
+        let selections = select_next(code, &[Selection::at(15)]);
+        assert_eq!(selections, vec![Selection::new(13, 16)]);
+
+        // then the next places it's in, wrapping back to the top
+        let selections = select_next(code, &selections);
+        assert_eq!(selections[1], Selection::new(20, 23));
+        let selections = select_next(code, &selections);
+        let selections = select_next(code, &selections);
+        assert_eq!(
+            &selections[2..],
+            [Selection::new(29, 32), Selection::new(4, 7)]
+        );
+        assert_eq!(select_next(code, &selections).len(), 4);
+
+        assert_eq!(
+            selected_text(code, &selections[..2]),
+            "foo\nfoo".to_string()
+        );
+        assert_eq!(select_next(" ", &[Selection::at(0)]), [Selection::at(0)]);
+    }
+
+    #[test]
+    fn edits_follow_earlier_ones() {
+        // a name renamed everywhere it's used at once, then its end typed over
+        let mut code = "fn a(x: u8) -> u8 { x * x }".to_string();
+        let mut selections = vec![Selection::at(6)];
+        for _ in 0..3 {
+            selections = select_next(&code, &selections);
+        }
+        assert_eq!(selections.len(), 3);
+
+        selections = edit(&mut code, &selections, Edit::Insert("value"));
+        assert_eq!(code, "fn a(value: u8) -> u8 { value * value }");
+        selections = edit(&mut code, &selections, Edit::Backspace);
+        selections = move_cursors(&code, &selections, Motion::Left, true);
+        assert_eq!(selected_text(&code, &selections), "u\nu\nu");
+        edit(&mut code, &selections, Edit::Insert("x"));
+        assert_eq!(code, "fn a(valx: u8) -> u8 { valx * valx }");
+    }
+}
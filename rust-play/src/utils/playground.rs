@@ -0,0 +1,227 @@
+use std::fmt::Write;
+
+use cargo_player::{BuildType, Channel, Edition};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Links longer than this may be cut short by browsers or the servers on the way, past it sharing
+/// as a gist is the way to go
+pub const MAX_URL_BYTES: usize = 16 * 1024;
+
+const EXECUTE_URL: &str = "https://play.rust-lang.org/execute";
+
+#[derive(Debug, Error)]
+pub enum PlaygroundError {
+    #[error("The playground is getting too many requests right now, try again in a minute")]
+    RateLimited,
+    #[error("The playground couldn't run it: {0}")]
+    Api(String),
+    #[error("{0}")]
+    Network(#[from] reqwest::Error),
+    #[error("The playground's reply couldn't be read")]
+    InvalidReply,
+}
+
+/// What the playground printed building and running the code. Compile errors come in stderr, as
+/// they would from cargo
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Execution {
+    pub success: bool,
+    // like `Exited with status 101`, empty when it exited normally
+    #[serde(default)]
+    pub exit_detail: String,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Execution {
+    /// The program's exit code, None if it was stopped by a signal
+    pub fn exit_code(&self) -> Option<i32> {
+        if self.success {
+            return Some(0);
+        }
+
+        match self.exit_detail.strip_prefix("Exited with status ") {
+            Some(code) => code.trim().parse().ok(),
+            None if self.exit_detail.contains("signal") => None,
+            None => Some(1),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecuteRequest<'a> {
+    channel: String,
+    mode: &'static str,
+    edition: String,
+    crate_type: &'static str,
+    tests: bool,
+    code: &'a str,
+    backtrace: bool,
+}
+
+// the api answers errors with just a message
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Reply {
+    Ran(Execution),
+    Failed { error: String },
+}
+
+/// A play.rust-lang.org link which opens the playground with `code` and the tab's settings, no
+/// gist needed
+pub fn permalink(code: &str, channel: Channel, edition: Edition, build_type: BuildType) -> String {
+    format!(
+        "https://play.rust-lang.org/?version={channel}&mode={}&edition={edition}&code={}",
+        mode(build_type),
+        encode(code)
+    )
+}
+
+/// Build and run `code` on play.rust-lang.org, running its tests instead when `tests` is set. It
+/// blocks until the playground answers
+pub fn execute(
+    code: &str,
+    channel: Channel,
+    edition: Edition,
+    build_type: BuildType,
+    tests: bool,
+) -> Result<Execution, PlaygroundError> {
+    let body = request_body(code, channel, edition, build_type, tests);
+
+    let response = reqwest::blocking::Client::new()
+        .post(EXECUTE_URL)
+        .header("User-Agent", "RustPlay")
+        .header("content-type", "application/json")
+        .body(body)
+        .send()?;
+
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        return Err(PlaygroundError::RateLimited);
+    }
+
+    parse_reply(&response.text()?)
+}
+
+fn request_body(
+    code: &str,
+    channel: Channel,
+    edition: Edition,
+    build_type: BuildType,
+    tests: bool,
+) -> String {
+    let request = ExecuteRequest {
+        channel: channel.to_string(),
+        mode: mode(build_type),
+        edition: edition.to_string(),
+        crate_type: "bin",
+        tests,
+        code,
+        backtrace: false,
+    };
+
+    serde_json::to_string(&request).unwrap()
+}
+
+fn parse_reply(reply: &str) -> Result<Execution, PlaygroundError> {
+    match serde_json::from_str(reply) {
+        Ok(Reply::Ran(execution)) => Ok(execution),
+        Ok(Reply::Failed { error }) => Err(PlaygroundError::Api(error)),
+        Err(_) => Err(PlaygroundError::InvalidReply),
+    }
+}
+
+fn mode(build_type: BuildType) -> &'static str {
+    match build_type {
+        BuildType::Debug => "debug",
+        BuildType::Release => "release",
+    }
+}
+
+// Percent-encode everything but the chars urls keep as they are
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_is_percent_encoded() {
+        assert_eq!(encode("fn main() {}"), "fn%20main%28%29%20%7B%7D");
+        assert_eq!(encode("a+b=c&d\n"), "a%2Bb%3Dc%26d%0A");
+        assert_eq!(encode("ä"), "%C3%A4");
+        assert_eq!(encode("snake_case-1.0~"), "snake_case-1.0~");
+    }
+
+    #[test]
+    fn links_have_the_tabs_settings() {
+        let link = permalink(
+            "fn main() {}",
+            Channel::Nightly,
+            Edition::E2018,
+            BuildType::Release,
+        );
+
+        assert_eq!(
+            link,
+            "https://play.rust-lang.org/?version=nightly&mode=release&edition=2018\
+             &code=fn%20main%28%29%20%7B%7D"
+        );
+    }
+
+    #[test]
+    fn requests_have_the_tabs_settings() {
+        let body = request_body(
+            "fn main() {}",
+            Channel::Beta,
+            Edition::E2021,
+            BuildType::Debug,
+            true,
+        );
+
+        assert_eq!(
+            body,
+            r#"{"channel":"beta","mode":"debug","edition":"2021","crateType":"bin","tests":true,"#
+                .to_string()
+                + r#""code":"fn main() {}","backtrace":false}"#
+        );
+    }
+
+    #[test]
+    fn replies_are_read() {
+        let ran = parse_reply(
+            r#"{"success":false,"exitDetail":"Exited with status 101","stdout":"","stderr":"e"}"#,
+        )
+        .unwrap();
+        assert_eq!(ran.stderr, "e");
+        assert_eq!(ran.exit_code(), Some(101));
+
+        let ran = parse_reply(r#"{"success":true,"stdout":"hi\n","stderr":""}"#).unwrap();
+        assert_eq!(ran.stdout, "hi\n");
+        assert_eq!(ran.exit_code(), Some(0));
+
+        assert!(matches!(
+            parse_reply(r#"{"error":"Code is too long"}"#),
+            Err(PlaygroundError::Api(error)) if error == "Code is too long"
+        ));
+        assert!(matches!(
+            parse_reply("<html>"),
+            Err(PlaygroundError::InvalidReply)
+        ));
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+
+use super::ansi_parser::Parser;
+
+/// The command line a run printed as its first line, with its colors taken out. Output which
+/// doesn't start with one, like rust play's own messages, has none
+pub fn command_line(first_line: &str) -> Option<String> {
+    let mut parser = Parser::default();
+    parser.push(first_line);
+
+    let command = parser.text.trim_end().strip_prefix("$ ")?;
+    Some(command.to_string())
+}
+
+/// Add a run after the others, dropping the oldest ones so only `depth` are left
+pub fn push<T>(runs: &mut VecDeque<T>, run: T, depth: usize) {
+    runs.push_back(run);
+
+    while runs.len() > depth {
+        runs.pop_front();
+    }
+}
+
+/// How many of the newest runs fit in `budget` bytes together. Counting stops at the first one
+/// which doesn't fit, so what's kept never skips a run
+pub fn within_budget<T>(runs: &VecDeque<T>, size: impl Fn(&T) -> usize, budget: usize) -> usize {
+    let mut total = 0;
+
+    runs.iter()
+        .rev()
+        .take_while(|run| {
+            total += size(run);
+            total <= budget
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_command_line_is_found() {
+        assert_eq!(
+            command_line("\x1b[90m$ cargo run --release\x1b[0m\n").as_deref(),
+            Some("cargo run --release")
+        );
+        assert_eq!(command_line("error: rustfmt failed\n"), None);
+    }
+
+    #[test]
+    fn the_oldest_runs_are_dropped() {
+        let mut runs = VecDeque::new();
+        for run in 0..5 {
+            push(&mut runs, run, 3);
+        }
+        assert_eq!(runs, [2, 3, 4]);
+
+        push(&mut runs, 5, 1);
+        assert_eq!(runs, [5]);
+    }
+
+    #[test]
+    fn only_the_newest_runs_fit_the_budget() {
+        let runs = VecDeque::from(["aaaa", "bb", "ccc", "d"]);
+
+        assert_eq!(within_budget(&runs, |run| run.len(), 6), 3);
+        assert_eq!(within_budget(&runs, |run| run.len(), 10), 4);
+        // a run which doesn't fit isn't skipped for an older one which would
+        assert_eq!(within_budget(&runs, |run| run.len(), 5), 2);
+        assert_eq!(within_budget(&runs, |run| run.len(), 0), 0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use thiserror::Error;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(target_os = "windows")]
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+#[derive(Debug, Error)]
+pub enum FormatError {
+    #[error("Failed to run rustfmt: {0}")]
+    Io(#[from] std::io::Error),
+    // what rustfmt printed, usually a syntax error
+    #[error("{0}")]
+    Rustfmt(String),
+}
+
+/// Format code with rustfmt, `edition` is like `2021`
+pub fn format(code: &str, edition: &str) -> Result<String, FormatError> {
+    let mut command = Command::new("rustfmt");
+    command
+        .args(["--edition", edition])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // hide the console window from command. Very important.
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW.0);
+
+    let mut child = command.spawn()?;
+
+    // written from another thread, big files would otherwise fill the output pipe while waiting on this
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let code = code.to_string();
+    let writer = thread::spawn(move || stdin.write_all(code.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    writer.join().expect("writing to rustfmt panicked")?;
+
+    if !output.status.success() {
+        return Err(FormatError::Rustfmt(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Where a char index in `old` is in `new`, going by the code around it. Formatting mostly moves
+/// whitespace around, so the cursor stays next to the same code
+pub fn map_cursor(old: &str, new: &str, index: usize) -> usize {
+    let code_before = old
+        .chars()
+        .take(index)
+        .filter(|char| !char.is_whitespace())
+        .count();
+
+    // a cursor at the start of a word stays there, instead of going to the end of the one before it
+    let previous = index
+        .checked_sub(1)
+        .and_then(|index| old.chars().nth(index));
+    let at_word_start = matches!(previous, Some(char) if char.is_whitespace())
+        && matches!(old.chars().nth(index), Some(char) if !char.is_whitespace());
+
+    let mut seen = 0;
+    for (position, char) in new.chars().enumerate() {
+        if char.is_whitespace() {
+            continue;
+        }
+
+        if at_word_start && seen == code_before {
+            return position;
+        }
+
+        seen += 1;
+        if !at_word_start && seen == code_before {
+            return position + 1;
+        }
+    }
+
+    if code_before == 0 {
+        0
+    } else {
+        new.chars().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_stays_next_to_the_same_code() {
+        let old = "fn main(){let x=1;}";
+        let new = "fn main() {\n    let x = 1;\n}\n";
+
+        // after `x`
+        let index = old.find("x=").unwrap() + 1;
+        let mapped = map_cursor(old, new, index);
+        assert_eq!(&new[..mapped], "fn main() {\n    let x");
+
+        assert_eq!(map_cursor(old, new, 0), 0);
+        assert_eq!(map_cursor(old, new, old.len()), new.len() - 1);
+    }
+
+    #[test]
+    fn cursor_at_the_start_of_a_word_stays_there() {
+        let old = "fn main() {\nlet x = 1;\n}";
+        let new = "fn main() {\n    let x = 1;\n}\n";
+
+        let index = old.find("let").unwrap();
+        let mapped = map_cursor(old, new, index);
+        assert_eq!(&new[mapped..mapped + 3], "let");
+    }
+}
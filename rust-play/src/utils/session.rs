@@ -0,0 +1,145 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::data_dir;
+
+/// The session format's version. Bump it when a change would make older sessions fail to parse, and
+/// migrate them in `migrate`
+pub const SESSION_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("Failed to read the session: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid session: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The session is from a newer version of Rust Play ({0})")]
+    UnknownVersion(u32),
+}
+
+// what's written to the file, the session along with its version
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    session: T,
+}
+
+/// Where the session is kept between launches
+pub fn session_file() -> PathBuf {
+    data_dir().join("session.json")
+}
+
+/// Read the session back. A missing file is no session, and one which can't be parsed is moved to
+/// `.bak` so the next save doesn't overwrite it
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, SessionError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    parse(&content).map(Some).inspect_err(|_| {
+        let _ = fs::rename(path, path.with_extension("json.bak"));
+    })
+}
+
+/// Write a session from `serialize`, replacing the last one. It's written next to it first, so
+/// closing the app halfway through never leaves half a session
+pub fn save(path: &Path, content: &str) -> Result<(), SessionError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, content)?;
+    fs::rename(temp, path)?;
+
+    Ok(())
+}
+
+/// The session as it's written to the file
+pub fn serialize<T: Serialize>(session: &T) -> Result<String, SessionError> {
+    Ok(serde_json::to_string(&Versioned {
+        version: SESSION_VERSION,
+        session,
+    })?)
+}
+
+fn parse<T: DeserializeOwned>(content: &str) -> Result<T, SessionError> {
+    let versioned: Versioned<Value> = serde_json::from_str(content)?;
+    let session = migrate(versioned.version, versioned.session)?;
+
+    Ok(serde_json::from_value(session)?)
+}
+
+// Bring an older session up to the current format
+fn migrate(version: u32, session: Value) -> Result<Value, SessionError> {
+    match version {
+        SESSION_VERSION => Ok(session),
+        // a newer app may have written things this one doesn't know the meaning of
+        _ => Err(SessionError::UnknownVersion(version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Session {
+        tabs: Vec<String>,
+    }
+
+    fn session() -> Session {
+        Session {
+            tabs: vec!["Scratch 1".to_string(), "Scratch 2".to_string()],
+        }
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let content = serialize(&session()).unwrap();
+
+        assert!(content.contains(r#""version":1"#));
+        assert_eq!(parse::<Session>(&content).unwrap(), session());
+    }
+
+    #[test]
+    fn newer_sessions_are_rejected() {
+        let content = r#"{ "version": 999, "session": { "tabs": [] } }"#;
+
+        assert!(matches!(
+            parse::<Session>(content),
+            Err(SessionError::UnknownVersion(999))
+        ));
+    }
+
+    #[test]
+    fn corrupt_sessions_are_kept_as_backup() {
+        let dir = env::temp_dir().join("rust-play-session-test");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("session.json");
+
+        assert!(load::<Session>(&path).unwrap().is_none());
+
+        save(&path, &serialize(&session()).unwrap()).unwrap();
+        assert_eq!(load::<Session>(&path).unwrap(), Some(session()));
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(load::<Session>(&path).is_err());
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("session.json.bak")).unwrap(),
+            "{ not json"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+// what an open request starts with, so anything else which connects is ignored. The ones asking
+// for the files to be run say so
+const HEADER: &str = "rust-play open";
+const RUN_HEADER: &str = "rust-play run";
+
+/// What another instance of the app was started with, for the running one to open
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct OpenRequest {
+    pub files: Vec<PathBuf>,
+    pub run: bool,
+}
+
+impl OpenRequest {
+    /// The message sent to the running instance. It comes to the front even when there's no files
+    pub fn encode(&self) -> String {
+        let header = if self.run { RUN_HEADER } else { HEADER };
+        let mut message = format!("{header}\n");
+        for file in &self.files {
+            message.push_str(&file.to_string_lossy());
+            message.push('\n');
+        }
+
+        message
+    }
+
+    /// The request a message is, None when it isn't one
+    pub fn decode(message: &str) -> Option<Self> {
+        let mut lines = message.lines();
+        let run = match lines.next()? {
+            HEADER => false,
+            RUN_HEADER => true,
+            _ => return None,
+        };
+
+        let files = lines
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        Some(Self { files, run })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_read_back() {
+        let request = OpenRequest {
+            files: vec![
+                PathBuf::from(r"C:\scratches\foo.rs"),
+                PathBuf::from("bar baz.rs"),
+            ],
+            run: true,
+        };
+        assert_eq!(OpenRequest::decode(&request.encode()), Some(request));
+
+        let empty = OpenRequest::default();
+        assert_eq!(OpenRequest::decode(&empty.encode()), Some(empty));
+
+        assert_eq!(OpenRequest::decode("GET / HTTP/1.1\n"), None);
+        assert_eq!(OpenRequest::decode(""), None);
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::{data_dir, lexer};
+
+/// A template typed out in place of its trigger when Tab is pressed right after it. In the body
+/// `$1`, `$2` and so on are the stops Tab goes through, `${1:text}` starts a stop off with text
+/// in it, and `$0` is where the cursor ends up. A stop's number used again is a copy of it, and
+/// each tab starting a line is a level of the editor's indentation
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Snippet {
+    pub trigger: String,
+    pub body: String,
+}
+
+// snippets.toml, a [[snippet]] table for each
+#[derive(Deserialize)]
+struct SnippetsFile {
+    #[serde(default)]
+    snippet: Vec<Snippet>,
+}
+
+#[derive(Debug, Error)]
+pub enum SnippetsError {
+    #[error("Failed to read the snippets: {0}")]
+    Io(#[from] io::Error),
+    #[error("Invalid snippets: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("fnmain", "fn main() {\n\t$0\n}"),
+    ("fn", "fn ${1:name}($2) {\n\t$0\n}"),
+    ("test", "#[test]\nfn ${1:it_works}() {\n\t$0\n}"),
+    ("derive", "#[derive(${1:Debug, Clone})]$0"),
+    ("forloop", "for ${1:item} in ${2:iter} {\n\t$0\n}"),
+    ("iflet", "if let Some(${1:value}) = ${2:option} {\n\t$0\n}"),
+    ("match", "match ${1:value} {\n\t${2:_} => $0,\n}"),
+    ("struct", "struct ${1:Name} {\n\t$0\n}"),
+    ("impl", "impl ${1:Type} {\n\t$0\n}"),
+    (
+        "display",
+        "impl std::fmt::Display for ${1:Type} {\n\tfn fmt(&self, f: &mut std::fmt::Formatter) \
+         -> std::fmt::Result {\n\t\twrite!(f, \"$0\")\n\t}\n}",
+    ),
+    (
+        "new",
+        "impl ${1:Type} {\n\tpub fn new($2) -> Self {\n\t\t$1 { $0 }\n\t}\n}",
+    ),
+];
+
+/// The snippets which come with the app
+pub fn builtin() -> Vec<Snippet> {
+    BUILTIN
+        .iter()
+        .map(|(trigger, body)| Snippet {
+            trigger: trigger.to_string(),
+            body: body.to_string(),
+        })
+        .collect()
+}
+
+/// Where the user's own snippets are kept
+pub fn snippets_file() -> PathBuf {
+    data_dir().join("snippets.toml")
+}
+
+/// The built in snippets along with the user's from `path`, which take the place of the built in
+/// ones with the same trigger. Without the file there are only the built in ones
+pub fn load(path: &Path) -> Result<Vec<Snippet>, SnippetsError> {
+    let mut snippets = builtin();
+    if !path.exists() {
+        return Ok(snippets);
+    }
+
+    let file = toml::from_str::<SnippetsFile>(&fs::read_to_string(path)?)?;
+    for snippet in file.snippet {
+        snippets.retain(|other| other.trigger != snippet.trigger);
+        snippets.push(snippet);
+    }
+
+    Ok(snippets)
+}
+
+// a body split up into its text and its stops, by their number and the text they start with
+#[derive(Debug, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Stop(usize, Option<String>),
+}
+
+fn pieces(body: &str) -> Vec<Piece> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' && matches!(chars.peek(), Some('$' | '\\')) {
+            text.extend(chars.next());
+            continue;
+        }
+        if c != '$' {
+            text.push(c);
+            continue;
+        }
+
+        // `$` which doesn't start a stop is just a `$`
+        let rest = chars.clone().collect::<String>();
+        let braced = rest.starts_with('{');
+        let digits = rest
+            .chars()
+            .skip(braced as usize)
+            .take_while(char::is_ascii_digit)
+            .count();
+        let number = rest[braced as usize..braced as usize + digits].parse::<usize>();
+        let Ok(number) = number else {
+            text.push(c);
+            continue;
+        };
+
+        let after = &rest[braced as usize + digits..];
+        let (placeholder, used) = if !braced {
+            (None, digits)
+        } else if after.starts_with('}') {
+            (None, digits + 2)
+        } else if let Some(placeholder) = after.strip_prefix(':').and_then(|after| {
+            let end = after.find('}')?;
+            Some(&after[..end])
+        }) {
+            (
+                Some(placeholder.to_string()),
+                digits + placeholder.chars().count() + 3,
+            )
+        } else {
+            text.push(c);
+            continue;
+        };
+
+        chars.nth(used - 1);
+        pieces.push(Piece::Text(std::mem::take(&mut text)));
+        pieces.push(Piece::Stop(number, placeholder));
+    }
+    pieces.push(Piece::Text(text));
+    pieces.retain(|piece| *piece != Piece::Text(String::new()));
+
+    pieces
+}
+
+// Write out the body's text, indenting the lines after the first by `line_indent` and each tab
+// starting a line by `indent`. Returns the text and the char ranges of each stop in the order
+// Tab goes through them, the copies of a stop after the first of it
+fn fill(body: &str, indent: &str, line_indent: &str) -> (String, Vec<Vec<Range<usize>>>) {
+    let pieces = pieces(body);
+    let placeholder = |number| {
+        pieces.iter().find_map(|piece| match piece {
+            Piece::Stop(other, Some(text)) if *other == number => Some(text.as_str()),
+            _ => None,
+        })
+    };
+
+    let mut text = String::new();
+    let mut len = 0;
+    let mut line_start = true;
+    let mut push = |text: &mut String, len: &mut usize, c: char| {
+        if c == '\t' && line_start {
+            text.push_str(indent);
+            *len += indent.chars().count();
+            return;
+        }
+
+        text.push(c);
+        *len += 1;
+        line_start = c == '\n';
+        if line_start {
+            text.push_str(line_indent);
+            *len += line_indent.chars().count();
+        }
+    };
+
+    // by the order Tab goes through them in, $0 last
+    let mut stops: BTreeMap<usize, Vec<Range<usize>>> = BTreeMap::new();
+    for piece in &pieces {
+        match piece {
+            Piece::Text(piece) => piece.chars().for_each(|c| push(&mut text, &mut len, c)),
+            Piece::Stop(number, _) => {
+                let start = len;
+                placeholder(*number)
+                    .unwrap_or_default()
+                    .chars()
+                    .for_each(|c| push(&mut text, &mut len, c));
+
+                let order = if *number == 0 { usize::MAX } else { *number };
+                stops.entry(order).or_default().push(start..len);
+            }
+        }
+    }
+
+    // the end of the snippet is its $0 when it doesn't have one
+    let end = stops.entry(usize::MAX).or_default();
+    if end.is_empty() {
+        end.push(len..len);
+    }
+
+    (text, stops.into_values().collect())
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn byte_at(code: &str, char_index: usize) -> usize {
+    code.char_indices()
+        .nth(char_index)
+        .map_or(code.len(), |(at, _)| at)
+}
+
+/// A snippet being filled in: the char ranges of each of its stops, the first of each being the
+/// one typed in and the rest copies of it
+#[derive(Debug, Clone, Default)]
+pub struct Expansion {
+    stops: Vec<Vec<Range<usize>>>,
+    current: usize,
+    // the code as it was last looked at, to see what was typed in the stop
+    code: String,
+}
+
+impl Expansion {
+    /// Expand the snippet whose trigger is the word right before `cursor`, a char index, indenting
+    /// its lines like the cursor's line. Words in comments and strings are left alone. The cursor
+    /// goes to the expansion's `selection`
+    pub fn expand(
+        code: &mut String,
+        cursor: usize,
+        snippets: &[Snippet],
+        indent: &str,
+    ) -> Option<Self> {
+        let end = byte_at(code, cursor);
+        let start = code[..end]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_word(*c))
+            .last()?
+            .0;
+        let snippet = snippets
+            .iter()
+            .find(|snippet| snippet.trigger == code[start..end])?;
+
+        let in_token = lexer::tokens(code)
+            .iter()
+            .any(|token| token.range.start <= start && start < token.range.end);
+        if in_token {
+            return None;
+        }
+
+        let line_start = code[..start].rfind('\n').map_or(0, |at| at + 1);
+        let line_indent = code[line_start..start]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect::<String>();
+        let (text, mut stops) = fill(&snippet.body, indent, &line_indent);
+
+        let offset = cursor - code[start..end].chars().count();
+        for range in stops.iter_mut().flatten() {
+            *range = offset + range.start..offset + range.end;
+        }
+        code.replace_range(start..end, &text);
+
+        Some(Self {
+            stops,
+            current: 0,
+            code: code.clone(),
+        })
+    }
+
+    /// What the cursor selects at the current stop
+    pub fn selection(&self) -> Range<usize> {
+        self.stops[self.current][0].clone()
+    }
+
+    /// Whether it's at $0, where there's nothing left to fill in
+    pub fn finished(&self) -> bool {
+        self.current + 1 == self.stops.len()
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1).min(self.stops.len() - 1);
+    }
+
+    pub fn previous(&mut self) {
+        self.current = self.current.saturating_sub(1);
+    }
+
+    /// Take in the code after it was shown, with the cursor's char range. What was typed in the
+    /// current stop is copied to its other places, and the stops after it move along. Returns
+    /// false once it's done with, when the code changed outside of the stop or the cursor left it
+    pub fn update(&mut self, code: &mut String, cursor: Range<usize>) -> bool {
+        let stop = self.selection();
+        let (old_len, new_len) = (self.code.chars().count(), code.chars().count());
+
+        if *code != self.code {
+            let (before, after) = (
+                byte_at(&self.code, stop.start),
+                byte_at(&self.code, stop.end),
+            );
+            let typed_in = new_len >= stop.start + old_len - stop.end
+                && code.starts_with(&self.code[..before])
+                && code.ends_with(&self.code[after..]);
+            if !typed_in {
+                return false;
+            }
+
+            let end = new_len - (old_len - stop.end);
+            self.moved(0, end);
+            let typed = code[byte_at(code, stop.start)..byte_at(code, end)].to_string();
+
+            for copy in 1..self.stops[self.current].len() {
+                let range = self.stops[self.current][copy].clone();
+                let bytes = byte_at(code, range.start)..byte_at(code, range.end);
+                code.replace_range(bytes, &typed);
+                self.moved(copy, range.start + typed.chars().count());
+            }
+            self.code = code.clone();
+        }
+
+        let stop = self.selection();
+        let inside = |at: usize| stop.start <= at && at <= stop.end;
+        inside(cursor.start) && inside(cursor.end)
+    }
+
+    // The text of the current stop's `copy` now ends at `end`, the ranges after it move along
+    fn moved(&mut self, copy: usize, end: usize) {
+        let range = self.stops[self.current][copy].clone();
+        let shift = |at: usize| (at as isize + end as isize - range.end as isize) as usize;
+
+        for (stop, ranges) in self.stops.iter_mut().enumerate() {
+            for (index, other) in ranges.iter_mut().enumerate() {
+                if (stop, index) == (self.current, copy) {
+                    other.end = end;
+                } else if other.start >= range.end {
+                    *other = shift(other.start)..shift(other.end);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn snippet(trigger: &str, body: &str) -> Snippet {
+        Snippet {
+            trigger: trigger.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn bodies_are_split_into_stops() {
+        assert_eq!(
+            pieces("for ${1:item} in $2 {$0} \\$1 $ ${x}"),
+            vec![
+                Piece::Text("for ".to_string()),
+                Piece::Stop(1, Some("item".to_string())),
+                Piece::Text(" in ".to_string()),
+                Piece::Stop(2, None),
+                Piece::Text(" {".to_string()),
+                Piece::Stop(0, None),
+                Piece::Text("} $1 $ ${x}".to_string()),
+            ]
+        );
+
+        // copies get the stop's text, and tabs starting lines are indentation
+        let (text, stops) = fill("a(${1:x}) {\n\t$1$2\n}", "  ", "    ");
+        assert_eq!(text, "a(x) {\n      x\n    }");
+        assert_eq!(stops, vec![vec![2..3, 13..14], vec![14..14], vec![20..20]]);
+    }
+
+    #[test]
+    fn triggers_expand_in_code_only() {
+        let snippets = vec![snippet("forloop", "for ${1:item} in $2 {\n\t$0\n}")];
+
+        let mut code = "fn main() {\n    forloop\n}".to_string();
+        let expansion = Expansion::expand(&mut code, 23, &snippets, "    ").unwrap();
+        assert_eq!(code, "fn main() {\n    for item in  {\n        \n    }\n}");
+        assert_eq!(expansion.selection(), 20..24);
+
+        for code in ["// forloop", "\"forloop\"", "for loop", "xforloop"] {
+            let mut code = code.to_string();
+            let cursor = code.len() - code.ends_with('"') as usize;
+            assert!(Expansion::expand(&mut code, cursor, &snippets, "    ").is_none());
+        }
+    }
+
+    #[test]
+    fn stops_are_filled_in_and_copied() {
+        let snippets = vec![snippet("new", "impl ${1:A} { fn $2() -> $1 { $0 } }")];
+        let mut code = "new".to_string();
+        let mut expansion = Expansion::expand(&mut code, 3, &snippets, "    ").unwrap();
+        assert_eq!(code, "impl A { fn () -> A {  } }");
+        assert_eq!(expansion.selection(), 5..6);
+
+        // typing over the stop's text copies it
+        code.replace_range(5..6, "Foo");
+        assert!(expansion.update(&mut code, 8..8));
+        assert_eq!(code, "impl Foo { fn () -> Foo {  } }");
+        code.replace_range(8..8, "d");
+        assert!(expansion.update(&mut code, 9..9));
+        assert_eq!(code, "impl Food { fn () -> Food {  } }");
+
+        // the stops after it moved along
+        expansion.next();
+        assert_eq!(expansion.selection(), 15..15);
+        expansion.next();
+        assert!(expansion.finished());
+        assert_eq!(expansion.selection(), 28..28);
+        expansion.previous();
+        assert_eq!(expansion.selection(), 15..15);
+
+        // typing somewhere else is the end of it
+        code.insert(0, ' ');
+        assert!(!expansion.update(&mut code, 1..1));
+    }
+
+    #[test]
+    fn user_snippets_replace_builtin_ones() {
+        let path = env::temp_dir().join("rust-play-snippets-test.toml");
+        fs::write(
+            &path,
+            "[[snippet]]\ntrigger = \"fnmain\"\nbody = \"fn main() {}\"\n\n\
+             [[snippet]]\ntrigger = \"hi\"\nbody = \"println!(\\\"hi\\\");\"\n",
+        )
+        .unwrap();
+
+        let snippets = load(&path).unwrap();
+        assert_eq!(snippets.len(), builtin().len() + 1);
+        assert!(snippets.contains(&snippet("fnmain", "fn main() {}")));
+        assert!(snippets.contains(&snippet("hi", "println!(\"hi\");")));
+
+        fs::write(&path, "[[snippet]]\ntrigger = 1").unwrap();
+        assert!(load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(load(&path).unwrap(), builtin());
+    }
+}
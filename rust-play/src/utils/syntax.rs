@@ -0,0 +1,427 @@
+use std::ops::Range;
+
+use super::lexer::{self, LineState, Token, TokenKind};
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// What a piece of rust code is highlighted as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Keyword,
+    Lifetime,
+    Str,
+    Char,
+    Number,
+    Comment,
+    DocComment,
+    Attribute,
+    // the name of a macro being called and its !
+    Macro,
+}
+
+// What a line starts off in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct State {
+    lexer: LineState,
+    // how deep in an attribute's brackets the code is, literals in one keep their own color
+    attribute_depth: usize,
+    // the block comment going on from the line before is a doc comment
+    doc_comment: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    // where it is in the code, without its newline
+    range: Range<usize>,
+    start: State,
+    end: State,
+    // by byte range in the line
+    highlights: Vec<(Range<usize>, Highlight)>,
+}
+
+/// Rust highlighting which is kept between edits, so long code stays quick to type in. An edit
+/// only has the lines from the first changed one lexed again, until one ends the way it did
+/// before, since the ones after it then come out the same as they did
+#[derive(Debug, Clone, Default)]
+pub struct LineHighlighter {
+    code: String,
+    lines: Vec<Line>,
+}
+
+impl LineHighlighter {
+    /// The code it was last updated with
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Highlight the code as it is now. Returns how many of its lines had to be lexed
+    pub fn update(&mut self, code: &str) -> usize {
+        if code == self.code && !self.lines.is_empty() {
+            return 0;
+        }
+
+        let mut new = vec![];
+        let mut line_start = 0;
+        for line in code.split('\n') {
+            new.push(line_start..line_start + line.len());
+            line_start += line.len() + 1;
+        }
+
+        // the lines before the first change and after the last one
+        let old = &self.lines;
+        let same = |(old, new): &(&Line, &Range<usize>)| {
+            self.code[old.range.clone()] == code[(*new).clone()]
+        };
+        let prefix = old.iter().zip(&new).take_while(same).count();
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(old.len().min(new.len()) - prefix)
+            .take_while(same)
+            .count();
+
+        let mut lines = old[..prefix].to_vec();
+        let mut state = lines.last().map_or_else(State::default, |line| line.end);
+        let mut lexed = 0;
+
+        for (index, range) in new.iter().enumerate().skip(prefix) {
+            // past the change, a line starting off the way it did before is left as it was, and
+            // so are the ones after it
+            if index >= new.len() - suffix {
+                let old_index = index + old.len() - new.len();
+
+                if old[old_index].start == state {
+                    let moved_by = range.start as isize - old[old_index].range.start as isize;
+                    lines.extend(old[old_index..].iter().map(|line| Line {
+                        range: line.range.start.saturating_add_signed(moved_by)
+                            ..line.range.end.saturating_add_signed(moved_by),
+                        ..line.clone()
+                    }));
+                    break;
+                }
+            }
+
+            let (highlights, end) = highlight_line(&code[range.clone()], state);
+            lines.push(Line {
+                range: range.clone(),
+                start: state,
+                end,
+                highlights,
+            });
+            state = end;
+            lexed += 1;
+        }
+
+        self.lines = lines;
+        self.code = code.to_string();
+        lexed
+    }
+
+    /// The highlighted pieces of the code, in order and by byte range. Whatever is between them is
+    /// plain code, like names and punctuation
+    pub fn highlights(&self) -> impl Iterator<Item = (Range<usize>, Highlight)> + '_ {
+        self.lines.iter().flat_map(|line| {
+            let start = line.range.start;
+            line.highlights
+                .iter()
+                .map(move |(range, kind)| (start + range.start..start + range.end, *kind))
+        })
+    }
+}
+
+/// The highlighted pieces of the code, lexed from scratch
+pub fn highlight(code: &str) -> Vec<(Range<usize>, Highlight)> {
+    let mut highlighter = LineHighlighter::default();
+    highlighter.update(code);
+    highlighter.highlights().collect()
+}
+
+// A line's highlights, by byte range in it, and what the next line starts off in
+fn highlight_line(line: &str, state: State) -> (Vec<(Range<usize>, Highlight)>, State) {
+    let lexed = lexer::lex_line(line, state.lexer);
+    let mut highlights = vec![];
+    let mut attribute_depth = state.attribute_depth;
+    let mut plain_start = 0;
+    let mut last = None;
+
+    for (index, token) in lexed.tokens.iter().enumerate() {
+        let plain = plain_start..token.range.start;
+        highlight_plain(
+            line,
+            plain,
+            Some(token),
+            &mut attribute_depth,
+            &mut highlights,
+        );
+
+        let kind = if index == 0 && lexed.continued && state.doc_comment {
+            Highlight::DocComment
+        } else {
+            token_highlight(token, &line[token.range.clone()])
+        };
+        if !token.range.is_empty() {
+            highlights.push((token.range.clone(), kind));
+        }
+
+        plain_start = token.range.end;
+        last = Some(kind);
+    }
+
+    let plain = plain_start..line.len();
+    highlight_plain(line, plain, None, &mut attribute_depth, &mut highlights);
+
+    let end = State {
+        lexer: lexed.state,
+        attribute_depth,
+        doc_comment: matches!(lexed.state, LineState::BlockComment { .. })
+            && last == Some(Highlight::DocComment),
+    };
+    (highlights, end)
+}
+
+fn token_highlight(token: &Token, text: &str) -> Highlight {
+    match token.kind {
+        TokenKind::Comment if is_doc_comment(text) => Highlight::DocComment,
+        TokenKind::Comment => Highlight::Comment,
+        TokenKind::Str => Highlight::Str,
+        TokenKind::Char => Highlight::Char,
+        TokenKind::Lifetime => Highlight::Lifetime,
+    }
+}
+
+// `///` and `//!`, and their block versions. `////` and `/**/` are plain comments
+fn is_doc_comment(comment: &str) -> bool {
+    (comment.starts_with("///") && !comment.starts_with("////"))
+        || comment.starts_with("//!")
+        || (comment.starts_with("/**") && !comment.starts_with("/**/"))
+        || comment.starts_with("/*!")
+}
+
+// Keywords, numbers, attributes and macros in code without comments or literals in it. `next` is
+// the token right after it, which a b prefix belongs to
+fn highlight_plain(
+    code: &str,
+    range: Range<usize>,
+    next: Option<&Token>,
+    attribute_depth: &mut usize,
+    highlights: &mut Vec<(Range<usize>, Highlight)>,
+) {
+    let bytes = code.as_bytes();
+    let mut i = range.start;
+
+    while i < range.end {
+        let start = i;
+        let opens_attribute = bytes[i] == b'#'
+            && (bytes[i + 1..].starts_with(b"[") || bytes[i + 1..].starts_with(b"!["));
+
+        if *attribute_depth > 0 || opens_attribute {
+            while i < range.end {
+                match bytes[i] {
+                    b'[' => *attribute_depth += 1,
+                    b']' => *attribute_depth -= 1,
+                    _ => (),
+                }
+                i += 1;
+
+                if *attribute_depth == 0 && bytes[i - 1] == b']' {
+                    break;
+                }
+            }
+
+            highlights.push((start..i, Highlight::Attribute));
+        } else if bytes[i].is_ascii_digit() {
+            i = number_end(bytes, i);
+            highlights.push((start..i, Highlight::Number));
+        } else if lexer::is_word(bytes[i]) {
+            i = lexer::word_end(bytes, i);
+            let word = &code[start..i];
+            let after = &bytes[i..];
+
+            if after.starts_with(b"!") && !after.starts_with(b"!=") {
+                i += 1;
+                highlights.push((start..i, Highlight::Macro));
+            } else if word == "r" && after.starts_with(b"#") {
+                // a raw identifier, `r#type` is a name and not a keyword
+                i = lexer::word_end(bytes, i + 1);
+            } else if let Some(token) = next.filter(|token| word == "b" && token.range.start == i) {
+                // a byte string or char, colored like the rest of it
+                let kind = token_highlight(token, &code[token.range.clone()]);
+                highlights.push((start..i, kind));
+            } else if KEYWORDS.contains(&word) {
+                highlights.push((start..i, Highlight::Keyword));
+            }
+        } else {
+            i += 1;
+        }
+    }
+}
+
+// Numbers take in their suffix, like 1u8, and their fraction and exponent, like 1.5e-3. Not the
+// dots of ranges or method calls, 1..2 and 1.max(2)
+fn number_end(bytes: &[u8], start: usize) -> usize {
+    let hex = bytes[start..].starts_with(b"0x");
+    let mut i = lexer::word_end(bytes, start);
+
+    loop {
+        let after = &bytes[i..];
+        let digit_next = after.get(1).is_some_and(u8::is_ascii_digit);
+        let fraction = after.first() == Some(&b'.');
+        let exponent = !hex
+            && matches!(bytes[i - 1], b'e' | b'E')
+            && matches!(after.first(), Some(b'+' | b'-'));
+
+        if (fraction || exponent) && digit_next {
+            i = lexer::word_end(bytes, i + 1);
+        } else {
+            return i;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(code: &str) -> Vec<(&str, Highlight)> {
+        highlight(code)
+            .into_iter()
+            .map(|(range, kind)| (&code[range], kind))
+            .collect()
+    }
+
+    #[test]
+    fn keywords_and_macros_are_highlighted() {
+        assert_eq!(
+            highlighted("pub fn main() { println!(\"hi\"); }"),
+            vec![
+                ("pub", Highlight::Keyword),
+                ("fn", Highlight::Keyword),
+                ("println!", Highlight::Macro),
+                ("\"hi\"", Highlight::Str)
+            ]
+        );
+        // not names which only start like one, raw identifiers or a != after a name
+        assert_eq!(
+            highlighted("let format = r#type != iffy;"),
+            vec![("let", Highlight::Keyword)]
+        );
+    }
+
+    #[test]
+    fn numbers_are_highlighted() {
+        assert_eq!(
+            highlighted("1u8 + 1.5e-3 + 0xff_e - x.max(2)"),
+            vec![
+                ("1u8", Highlight::Number),
+                ("1.5e-3", Highlight::Number),
+                ("0xff_e", Highlight::Number),
+                ("2", Highlight::Number)
+            ]
+        );
+        assert_eq!(
+            highlighted("0..10 1.max(2)"),
+            vec![
+                ("0", Highlight::Number),
+                ("10", Highlight::Number),
+                ("1", Highlight::Number),
+                ("2", Highlight::Number)
+            ]
+        );
+    }
+
+    #[test]
+    fn attributes_keep_their_literals() {
+        assert_eq!(
+            highlighted("#[cfg(feature = \"x\")]\n#![allow(dead_code)] fn"),
+            vec![
+                ("#[cfg(feature = ", Highlight::Attribute),
+                ("\"x\"", Highlight::Str),
+                (")]", Highlight::Attribute),
+                ("#![allow(dead_code)]", Highlight::Attribute),
+                ("fn", Highlight::Keyword)
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_and_literals_are_highlighted() {
+        assert_eq!(
+            highlighted("/// doc\n// plain\nb'a' b\"b\" 'a: loop {}"),
+            vec![
+                ("/// doc", Highlight::DocComment),
+                ("// plain", Highlight::Comment),
+                ("b", Highlight::Char),
+                ("'a'", Highlight::Char),
+                ("b", Highlight::Str),
+                ("\"b\"", Highlight::Str),
+                ("'a", Highlight::Lifetime),
+                ("loop", Highlight::Keyword)
+            ]
+        );
+    }
+
+    #[test]
+    fn block_comments_go_on_over_lines() {
+        assert_eq!(
+            highlighted("/** doc\n\n*/ fn /* a\n/* b */ c */ 1"),
+            vec![
+                ("/** doc", Highlight::DocComment),
+                ("*/", Highlight::DocComment),
+                ("fn", Highlight::Keyword),
+                ("/* a", Highlight::Comment),
+                ("/* b */ c */", Highlight::Comment),
+                ("1", Highlight::Number)
+            ]
+        );
+    }
+
+    #[test]
+    fn edits_only_lex_the_lines_they_change() {
+        let code = (0..5000)
+            .map(|i| format!("    let x{i} = \"{i}\"; // {i}\n"))
+            .collect::<String>();
+        let mut highlighter = LineHighlighter::default();
+        assert_eq!(highlighter.update(&code), 5001);
+        assert_eq!(highlighter.update(&code), 0);
+
+        let edited = code.replacen("let x2500 =", "let mut x2500 =", 1);
+        assert_eq!(highlighter.update(&edited), 1);
+        assert!(highlighter.highlights().eq(highlight(&edited)));
+
+        // opening a comment changes every line after it
+        let commented = edited.replacen("let mut", "/* let mut", 1);
+        assert_eq!(highlighter.update(&commented), 2501);
+        assert!(highlighter.highlights().eq(highlight(&commented)));
+    }
+
+    #[test]
+    fn edits_highlight_like_from_scratch() {
+        let mut code = "fn main() {\n    let a = 1;\n    let b = \"b\";\n}\n".to_string();
+        let edits = [
+            ("let a", "/* let a"),
+            ("let b", "*/ let b"),
+            ("\"b\"", "\"b"),
+            ("\n}", "\n\"}"),
+            ("/* ", "/** "),
+            ("1;", "1;\n    #[cfg(test)]\n    let c = r#\"\n\"#;"),
+            ("\"b", "\"b\""),
+            ("fn main", "// fn main"),
+        ];
+
+        let mut highlighter = LineHighlighter::default();
+        highlighter.update(&code);
+        for (from, to) in edits {
+            code = code.replacen(from, to, 1);
+            highlighter.update(&code);
+
+            assert!(highlighter.highlights().eq(highlight(&code)), "{code}");
+            assert_eq!(highlighter.code(), code);
+        }
+    }
+}
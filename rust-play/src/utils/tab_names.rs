@@ -0,0 +1,197 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use egui::Id;
+
+/// The name for a new scratch, `Scratch N` with the lowest N which none of the open tabs has
+pub fn scratch_name<'a>(names: impl IntoIterator<Item = &'a str>) -> String {
+    let used = names
+        .into_iter()
+        .filter_map(scratch_number)
+        .collect::<HashSet<_>>();
+
+    let number = (1..).find(|number| !used.contains(number)).unwrap_or(1);
+    format!("Scratch {number}")
+}
+
+/// The id for a new scratch, from how many scratches were made before it. Unlike names, ids are
+/// never reused, egui keeps the state of the old tab's widgets under them
+pub fn scratch_id(count: u32) -> Id {
+    Id::new(("scratch", count))
+}
+
+/// How wide each tab gets and how many of them are shown, when `count` tabs share `width`. Tabs
+/// shrink down to `min_width`, past that the last ones are left out, along with `overflow_width`
+/// for the button listing them
+pub fn fit_tabs(width: f32, count: usize, min_width: f32, overflow_width: f32) -> (f32, usize) {
+    if count == 0 || width / count as f32 >= min_width {
+        return (width / count.max(1) as f32, count);
+    }
+
+    let width = width - overflow_width;
+    let shown = ((width / min_width) as usize).clamp(1, count);
+    (width / shown as f32, shown)
+}
+
+/// `text` cut short with `…` so `width` says it fits in `max_width`. It keeps as much of the start
+/// as it can, down to only the `…`
+pub fn elide(text: &str, max_width: f32, width: impl Fn(&str) -> f32) -> Cow<'_, str> {
+    if width(text) <= max_width {
+        return Cow::Borrowed(text);
+    }
+
+    let elided = |end: usize| format!("{}…", text[..end].trim_end());
+
+    // where each start of the text ends, the longer it is the wider it gets
+    let ends = text
+        .char_indices()
+        .map(|(index, _)| index)
+        .skip(1)
+        .collect::<Vec<_>>();
+    let fitting = ends.partition_point(|end| width(&elided(*end)) <= max_width);
+
+    match fitting {
+        0 => Cow::Borrowed("…"),
+        fitting => Cow::Owned(elided(ends[fitting - 1])),
+    }
+}
+
+/// The file a tab's code is shared as, its name in lowercase with dashes between the words
+pub fn gist_file_name(name: &str) -> String {
+    let mut slug = String::new();
+
+    for char in name.chars() {
+        if char.is_alphanumeric() || char == '_' {
+            slug.extend(char.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    match slug.trim_end_matches('-') {
+        "" => "playground.rs".to_string(),
+        slug => format!("{slug}.rs"),
+    }
+}
+
+// the N of `Scratch N`
+fn scratch_number(name: &str) -> Option<u32> {
+    name.strip_prefix("Scratch ")?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the names and ids of the open tabs, and how many were made
+    #[derive(Default)]
+    struct Tabs {
+        open: Vec<(String, Id)>,
+        count: u32,
+    }
+
+    impl Tabs {
+        fn add(&mut self) -> String {
+            let name = scratch_name(self.open.iter().map(|(name, _)| name.as_str()));
+            self.open.push((name.clone(), scratch_id(self.count)));
+            self.count += 1;
+            name
+        }
+
+        fn close(&mut self, name: &str) {
+            self.open.retain(|(open, _)| open != name);
+        }
+
+        fn assert_unique(&self) {
+            let names = self
+                .open
+                .iter()
+                .map(|(name, _)| name)
+                .collect::<HashSet<_>>();
+            let ids = self.open.iter().map(|(_, id)| id).collect::<HashSet<_>>();
+
+            assert_eq!(names.len(), self.open.len());
+            assert_eq!(ids.len(), self.open.len());
+        }
+    }
+
+    #[test]
+    fn names_fill_the_lowest_gap() {
+        let mut tabs = Tabs::default();
+
+        assert_eq!(tabs.add(), "Scratch 1");
+        assert_eq!(tabs.add(), "Scratch 2");
+        assert_eq!(tabs.add(), "Scratch 3");
+
+        tabs.close("Scratch 2");
+        assert_eq!(tabs.add(), "Scratch 2");
+        assert_eq!(tabs.add(), "Scratch 4");
+        tabs.assert_unique();
+    }
+
+    #[test]
+    fn closing_every_tab_starts_over_with_new_ids() {
+        let mut tabs = Tabs::default();
+        tabs.add();
+        let (_, first_id) = tabs.open[0];
+
+        tabs.close("Scratch 1");
+        assert_eq!(tabs.add(), "Scratch 1");
+        assert_ne!(tabs.open[0].1, first_id);
+
+        for _ in 0..5 {
+            tabs.add();
+        }
+        tabs.close("Scratch 3");
+        tabs.close("Scratch 5");
+        tabs.add();
+        tabs.add();
+        tabs.assert_unique();
+    }
+
+    #[test]
+    fn renamed_tabs_free_their_number() {
+        assert_eq!(
+            scratch_name(["Scratch 1", "My tab", "Scratch 3"]),
+            "Scratch 2"
+        );
+        // only exact scratch names count
+        assert_eq!(scratch_name(["Scratch 1 (copy)", "Scratch x"]), "Scratch 1");
+    }
+
+    #[test]
+    fn tabs_shrink_then_overflow() {
+        assert_eq!(fit_tabs(400.0, 4, 80.0, 20.0), (100.0, 4));
+        assert_eq!(fit_tabs(400.0, 5, 80.0, 20.0), (80.0, 5));
+
+        // the overflow button takes room from the tabs which are still shown
+        let (width, shown) = fit_tabs(400.0, 12, 80.0, 20.0);
+        assert_eq!(shown, 4);
+        assert_eq!(width, 95.0);
+
+        // one tab at least is always shown
+        assert_eq!(fit_tabs(50.0, 3, 80.0, 20.0), (30.0, 1));
+        assert_eq!(fit_tabs(400.0, 0, 80.0, 20.0), (400.0, 0));
+    }
+
+    #[test]
+    fn long_names_are_elided() {
+        // every char is 1 wide
+        let width = |text: &str| text.chars().count() as f32;
+
+        assert_eq!(elide("Scratch 1", 9.0, width), "Scratch 1");
+        assert_eq!(elide("Scratch 1", 8.0, width), "Scratch…");
+        assert_eq!(elide("Scratch 1", 5.0, width), "Scra…");
+        assert_eq!(elide("Scratch 1", 0.5, width), "…");
+        assert_eq!(elide("📌 • äöü", 4.0, width), "📌 •…");
+    }
+
+    #[test]
+    fn gist_files_are_named_after_the_tab() {
+        assert_eq!(gist_file_name("Scratch 1"), "scratch-1.rs");
+        assert_eq!(gist_file_name("  Parse (fast!) v2 "), "parse-fast-v2.rs");
+        assert_eq!(gist_file_name("my_tests"), "my_tests.rs");
+        assert_eq!(gist_file_name("Größe"), "größe.rs");
+        assert_eq!(gist_file_name("📌"), "playground.rs");
+    }
+}
@@ -0,0 +1,60 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // the summary libtest prints after each test binary. The result word may be colored
+    static ref SUMMARY: Regex =
+        Regex::new(r"^test result: .*?(\d+) passed; (\d+) failed; (\d+) ignored").unwrap();
+}
+
+/// How many tests passed and failed, added up over every test binary of a `cargo test` run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestCounts {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+}
+
+impl TestCounts {
+    /// Add the counts from a `test result:` line. Other lines are ignored
+    pub fn add_summary(&mut self, line: &str) -> bool {
+        let Some(captures) = SUMMARY.captures(line.trim_start()) else {
+            return false;
+        };
+
+        let count = |index: usize| captures[index].parse::<usize>().unwrap_or_default();
+        self.passed += count(1);
+        self.failed += count(2);
+        self.ignored += count(3);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_up_every_summary() {
+        let mut counts = TestCounts::default();
+
+        assert!(counts.add_summary(
+            "test result: ok. 3 passed; 0 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s\n"
+        ));
+        assert!(counts.add_summary(
+            "test result: \x1b[31mFAILED\x1b[0m. 1 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out\n"
+        ));
+        assert!(!counts.add_summary("test tests::it_works ... ok\n"));
+        assert!(!counts.add_summary("running 3 tests\n"));
+
+        assert_eq!(
+            counts,
+            TestCounts {
+                passed: 4,
+                failed: 2,
+                ignored: 1
+            }
+        );
+    }
+}
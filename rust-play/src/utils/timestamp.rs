@@ -0,0 +1,57 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+const MILLIS_PER_DAY: u32 = 24 * 60 * 60 * 1000;
+
+/// A local time of day, with millisecond precision
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Timestamp {
+    // milliseconds since midnight
+    millis: u32,
+}
+
+impl Timestamp {
+    pub fn now() -> Self {
+        #[cfg(target_os = "windows")]
+        let millis = {
+            use windows::Win32::System::SystemInformation::GetLocalTime;
+
+            let time = unsafe { GetLocalTime() };
+            ((time.wHour as u32 * 60 + time.wMinute as u32) * 60 + time.wSecond as u32) * 1000
+                + time.wMilliseconds as u32
+        };
+
+        // there's no timezone to go by, so this is in UTC
+        #[cfg(not(target_os = "windows"))]
+        let millis = {
+            use std::time::{SystemTime, UNIX_EPOCH};
+
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            (since_epoch.as_millis() % MILLIS_PER_DAY as u128) as u32
+        };
+
+        Self { millis }
+    }
+}
+
+// HH:MM:SS.mmm
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.millis % MILLIS_PER_DAY;
+        let seconds = millis / 1000;
+
+        write!(
+            f,
+            "{:02}:{:02}:{:02}.{:03}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            millis % 1000
+        )
+    }
+}
@@ -0,0 +1,533 @@
+// how many spaces a tab takes up, for taking a level of indentation off lines indented with
+// spaces when indenting with tabs
+const TAB_WIDTH: usize = 4;
+
+// the closer auto-closing inserts after each opener
+fn closer(opener: char) -> Option<char> {
+    match opener {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        _ => None,
+    }
+}
+
+// the byte offset of a char index, its end when it's past the last char
+fn byte_index(code: &str, cursor: usize) -> usize {
+    code.char_indices()
+        .nth(cursor)
+        .map_or(code.len(), |(byte, _)| byte)
+}
+
+fn line_start(code: &str, at: usize) -> usize {
+    code[..at].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+// how many spaces make up a level of indentation
+fn level(indent: &str) -> usize {
+    if indent == "\t" {
+        TAB_WIDTH
+    } else {
+        indent.len()
+    }
+}
+
+/// Press Enter at `cursor`, a char index. The new line gets the indentation of the one it was
+/// split from, one `indent` more after an opening bracket. Between a `{}` pair, the closer goes
+/// on a line of its own under them. Returns where the cursor ends up
+pub fn enter(code: &mut String, cursor: usize, indent: &str) -> usize {
+    let at = byte_index(code, cursor);
+    let line = &code[line_start(code, at)..at];
+
+    let indentation = line
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect::<String>();
+    let opener = line
+        .trim_end()
+        .chars()
+        .next_back()
+        .filter(|c| matches!(c, '{' | '(' | '['));
+    let between_pair = opener
+        .and_then(closer)
+        .is_some_and(|closer| code[at..].starts_with(closer));
+
+    let mut inserted = format!("\n{indentation}");
+    if opener.is_some() {
+        inserted.push_str(indent);
+    }
+    let moved = inserted.chars().count();
+
+    if between_pair {
+        inserted.push('\n');
+        inserted.push_str(&indentation);
+    }
+
+    code.insert_str(at, &inserted);
+    cursor + moved
+}
+
+/// Type `typed` at `cursor`, a char index. A closing bracket typed on a line with only
+/// indentation before it goes back a level. With `auto_close`, openers get their closer after the
+/// cursor, and typing a closer which is already next steps over it. Returns where the cursor ends
+/// up
+pub fn type_char(
+    code: &mut String,
+    cursor: usize,
+    typed: char,
+    indent: &str,
+    auto_close: bool,
+) -> usize {
+    let mut cursor = cursor;
+    let mut at = byte_index(code, cursor);
+    let prev = code[..at].chars().next_back();
+    let next = code[at..].chars().next();
+
+    let closes = matches!(typed, ')' | ']' | '}' | '"' | '\'');
+    if auto_close && closes && next == Some(typed) {
+        return cursor + 1;
+    }
+
+    if matches!(typed, ')' | ']' | '}') {
+        let before = &code[line_start(code, at)..at];
+
+        if !before.is_empty() && before.chars().all(|c| c == ' ' || c == '\t') {
+            let dedent = if before.ends_with('\t') {
+                1
+            } else {
+                before.len() - before.trim_end_matches(' ').len()
+            }
+            .min(level(indent));
+
+            code.replace_range(at - dedent..at, "");
+            at -= dedent;
+            cursor -= dedent;
+        }
+    }
+
+    let close = auto_close
+        && match typed {
+            // lifetimes like `&'a` and `<'a>` don't get closed, and neither do quotes right
+            // before a word
+            '\'' => {
+                !matches!(prev, Some(c) if c.is_alphanumeric() || matches!(c, '&' | '<' | '_'))
+                    && !matches!(next, Some(c) if c.is_alphanumeric())
+            }
+            '"' => !matches!(next, Some(c) if c.is_alphanumeric()),
+            // only in front of the end of something, not in the middle of code
+            _ => matches!(
+                next,
+                None | Some(')' | ']' | '}' | ';' | ',' | ' ' | '\t' | '\n')
+            ),
+        };
+
+    code.insert(at, typed);
+    if let Some(closer) = closer(typed).filter(|_| close) {
+        code.insert(at + typed.len_utf8(), closer);
+    }
+
+    cursor + 1
+}
+
+// A char index as the line it's on and its column in that line
+fn line_col(code: &str, cursor: usize) -> (usize, usize) {
+    let at = byte_index(code, cursor);
+    let line = code[..at].matches('\n').count();
+    let col = code[line_start(code, at)..at].chars().count();
+
+    (line, col)
+}
+
+// Change each selected line with `edit`, which gives the column to change it at, how many chars
+// to take out there and what to put in instead. `a` and `b` are the selection's ends as char
+// indices, which move along with the text they're next to. A selection ending at the very start
+// of a line doesn't take in that line
+fn edit_lines(
+    code: &mut String,
+    a: usize,
+    b: usize,
+    edit: impl Fn(&str) -> Option<(usize, usize, String)>,
+) -> (usize, usize) {
+    let (a, b) = (line_col(code, a), line_col(code, b));
+    let (start, end) = (a.min(b), a.max(b));
+    let last = if end.0 > start.0 && end.1 == 0 {
+        end.0 - 1
+    } else {
+        end.0
+    };
+
+    let mut lines = code.split('\n').map(str::to_string).collect::<Vec<_>>();
+    let mut changes = vec![None; lines.len()];
+
+    for (line, change) in lines
+        .iter_mut()
+        .zip(&mut changes)
+        .take(last + 1)
+        .skip(start.0)
+    {
+        if let Some((col, removed, inserted)) = edit(line) {
+            let from = byte_index(line, col);
+            let to = byte_index(line, col + removed);
+            line.replace_range(from..to, &inserted);
+            *change = Some((col, removed, inserted.chars().count()));
+        }
+    }
+
+    *code = lines.join("\n");
+
+    let moved = |(line, col): (usize, usize)| {
+        let col = match changes[line] {
+            Some((at, _, _)) if col <= at => col,
+            Some((at, removed, inserted)) if col < at + removed => at + inserted.min(col - at),
+            Some((_, removed, inserted)) => col - removed + inserted,
+            None => col,
+        };
+
+        lines[..line]
+            .iter()
+            .map(|line| line.chars().count() + 1)
+            .sum::<usize>()
+            + col
+    };
+
+    (moved(a), moved(b))
+}
+
+// The first and last lines a selection takes in, by index. One ending at the very start of a line
+// doesn't take in that line
+fn line_span(code: &str, a: usize, b: usize) -> (usize, usize) {
+    let (start, end) = (line_col(code, a.min(b)).0, line_col(code, a.max(b)));
+    let last = if end.0 > start && end.1 == 0 {
+        end.0 - 1
+    } else {
+        end.0
+    };
+
+    (start, last)
+}
+
+// how many chars the lines take up, with a newline after each
+fn lines_len(lines: &[&str]) -> usize {
+    lines.iter().map(|line| line.chars().count() + 1).sum()
+}
+
+/// Comment out the selected lines with `// `, or uncomment them when every line which isn't
+/// blank already is. The comments line up at the least indented line, so the code keeps its
+/// indentation. Returns where the selection's ends moved to
+pub fn toggle_comment(code: &mut String, a: usize, b: usize) -> (usize, usize) {
+    let (start, last) = line_span(code, a, b);
+
+    let selected = code
+        .split('\n')
+        .take(last + 1)
+        .skip(start)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let uncomment = !selected.is_empty()
+        && selected
+            .iter()
+            .all(|line| line.trim_start().starts_with("//"));
+    let column = selected
+        .iter()
+        .map(|line| line.chars().count() - line.trim_start().chars().count())
+        .min()
+        .unwrap_or_default();
+
+    edit_lines(code, a, b, |line| {
+        let indentation = line.chars().count() - line.trim_start().chars().count();
+
+        if line.trim().is_empty() {
+            None
+        } else if uncomment {
+            let rest = line.trim_start();
+            let removed = if rest.starts_with("// ") { 3 } else { 2 };
+            Some((indentation, removed, String::new()))
+        } else {
+            Some((column, 0, "// ".to_string()))
+        }
+    })
+}
+
+/// Indent the selected lines by one `indent`, leaving the empty ones alone. Returns where the
+/// selection's ends moved to
+pub fn indent_lines(code: &mut String, a: usize, b: usize, indent: &str) -> (usize, usize) {
+    edit_lines(code, a, b, |line| {
+        (!line.is_empty()).then(|| (0, 0, indent.to_string()))
+    })
+}
+
+/// Take one level of indentation off the selected lines, as much of it as they have. Returns
+/// where the selection's ends moved to
+pub fn dedent_lines(code: &mut String, a: usize, b: usize, indent: &str) -> (usize, usize) {
+    edit_lines(code, a, b, |line| {
+        // the level comes off the start, where indenting put it
+        let removed = if line.starts_with('\t') {
+            1
+        } else {
+            line.chars()
+                .take_while(|c| *c == ' ')
+                .count()
+                .min(level(indent))
+        };
+        (removed > 0).then(|| (0, removed, String::new()))
+    })
+}
+
+/// Copy the selected lines, the cursor's when nothing is selected, in below them. The selection
+/// moves down onto the copy. Returns where its ends moved to
+pub fn duplicate_lines(code: &mut String, a: usize, b: usize) -> (usize, usize) {
+    let (first, last) = line_span(code, a, b);
+    let mut lines = code.split('\n').collect::<Vec<_>>();
+    let copied = lines[first..=last].to_vec();
+    let moved_by = lines_len(&copied);
+
+    lines.splice(last + 1..last + 1, copied);
+    *code = lines.join("\n");
+
+    (a + moved_by, b + moved_by)
+}
+
+/// Swap the selected lines with the one above them, or below when `up` isn't set, keeping the
+/// selection on them. They stay put at the top or bottom of the code. Returns where the
+/// selection's ends moved to
+pub fn move_lines(code: &mut String, a: usize, b: usize, up: bool) -> (usize, usize) {
+    let (first, last) = line_span(code, a, b);
+    let mut lines = code.split('\n').collect::<Vec<_>>();
+    // the empty line after a trailing newline stays last, so the code keeps ending with one
+    let movable = if code.ends_with('\n') && last + 1 < lines.len() {
+        lines.len() - 1
+    } else {
+        lines.len()
+    };
+
+    if up && first > 0 {
+        let above = lines.remove(first - 1);
+        let moved_by = above.chars().count() + 1;
+        lines.insert(last, above);
+        *code = lines.join("\n");
+
+        (a - moved_by, b - moved_by)
+    } else if !up && last + 1 < movable {
+        let below = lines.remove(last + 1);
+        let moved_by = below.chars().count() + 1;
+        lines.insert(first, below);
+        *code = lines.join("\n");
+
+        // a selection ending at the start of the line after may now be past the end
+        let len = code.chars().count();
+        ((a + moved_by).min(len), (b + moved_by).min(len))
+    } else {
+        (a, b)
+    }
+}
+
+/// Delete the selected lines, the cursor's when nothing is selected. The cursor keeps its column
+/// on the line which takes their place, or the one above when they were the last. Returns where
+/// it ended up
+pub fn delete_lines(code: &mut String, a: usize, b: usize) -> usize {
+    let (first, last) = line_span(code, a, b);
+    let col = line_col(code, b).1;
+    let mut lines = code.split('\n').collect::<Vec<_>>();
+
+    lines.drain(first..=last);
+    if lines.is_empty() {
+        code.clear();
+        return 0;
+    }
+
+    let line = first.min(lines.len() - 1);
+    let cursor = lines_len(&lines[..line]) + col.min(lines[line].chars().count());
+    *code = lines.join("\n");
+
+    cursor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Start from `code` with the cursor at its `|`, and type `keys` one by one. A newline in them
+    // presses Enter. Returns the code with a `|` where the cursor ended up
+    fn type_keys(code: &str, keys: &str, auto_close: bool) -> String {
+        let mut cursor = code.find('|').unwrap();
+        let mut code = code.replace('|', "");
+
+        for key in keys.chars() {
+            cursor = match key {
+                '\n' => enter(&mut code, cursor, "    "),
+                key => type_char(&mut code, cursor, key, "    ", auto_close),
+            };
+        }
+
+        code.insert(byte_index(&code, cursor), '|');
+        code
+    }
+
+    #[test]
+    fn new_lines_keep_the_indentation() {
+        assert_eq!(
+            type_keys("    let a = 1;|", "\n", true),
+            "    let a = 1;\n    |"
+        );
+        assert_eq!(type_keys("\tfoo();|", "\n", true), "\tfoo();\n\t|");
+        assert_eq!(type_keys("    foo(|", "\n", false), "    foo(\n        |");
+        assert_eq!(type_keys("let v = [ |", "\n", false), "let v = [ \n    |");
+    }
+
+    #[test]
+    fn braces_open_onto_three_lines() {
+        assert_eq!(
+            type_keys("fn main() |", "{\n", true),
+            "fn main() {\n    |\n}"
+        );
+        assert_eq!(
+            type_keys("    if a |", "{\nb();", true),
+            "    if a {\n        b();|\n    }"
+        );
+        assert_eq!(type_keys("foo(|)", "\n", true), "foo(\n    |\n)");
+    }
+
+    #[test]
+    fn closers_go_back_a_level() {
+        assert_eq!(
+            type_keys("fn main() {\n    a();\n    |", "}", true),
+            "fn main() {\n    a();\n}|"
+        );
+        assert_eq!(type_keys("\t\t|", "]", false), "\t]|");
+        // not when there's code before it on the line
+        assert_eq!(type_keys("    a|", ")", false), "    a)|");
+    }
+
+    #[test]
+    fn openers_are_closed() {
+        assert_eq!(type_keys("|", "(", true), "(|)");
+        assert_eq!(type_keys("let s = |;", "\"", true), "let s = \"|\";");
+        assert_eq!(type_keys("|", "vec![1", true), "vec![1|]");
+        assert_eq!(type_keys("|", "(", false), "(|");
+        // in the middle of code the closer would be in the way
+        assert_eq!(type_keys("|foo", "(", true), "(|foo");
+    }
+
+    #[test]
+    fn closers_are_stepped_over() {
+        assert_eq!(type_keys("|", "foo()", true), "foo()|");
+        assert_eq!(type_keys("|", "\"hi\";", true), "\"hi\";|");
+        assert_eq!(type_keys("|", "[[]]", true), "[[]]|");
+        // without auto-closing, a typed closer is just typed
+        assert_eq!(type_keys("|)", ")", false), ")|)");
+    }
+
+    // Run `edit` on `code` with the selection between its two `|`s, or at its one `|`. Returns the
+    // code with `|`s where the selection's ends ended up
+    fn edit_selection(
+        code: &str,
+        edit: impl Fn(&mut String, usize, usize) -> (usize, usize),
+    ) -> String {
+        let a = code.find('|').unwrap();
+        let b = code.rfind('|').unwrap();
+        let b = if a == b { a } else { b - 1 };
+        let mut code = code.replace('|', "");
+
+        let (a, b) = edit(&mut code, a, b);
+        code.insert(byte_index(&code, b), '|');
+        if a != b {
+            code.insert(byte_index(&code, a), '|');
+        }
+        code
+    }
+
+    #[test]
+    fn lines_are_commented() {
+        assert_eq!(
+            edit_selection("fn main() {\n    |a();\n\n        b();|\n}", toggle_comment),
+            "fn main() {\n    |// a();\n\n    //     b();|\n}"
+        );
+        assert_eq!(edit_selection("  a|();", toggle_comment), "  // a|();");
+        // the line the selection ends at the start of is left alone
+        assert_eq!(
+            edit_selection("|a();\n|b();", toggle_comment),
+            "|// a();\n|b();"
+        );
+    }
+
+    #[test]
+    fn lines_are_uncommented() {
+        assert_eq!(
+            edit_selection("    // |a();\n\n    //b();|", toggle_comment),
+            "    |a();\n\n    b();|"
+        );
+        // only when they all are
+        assert_eq!(
+            edit_selection("|// a();\nb();|", toggle_comment),
+            "|// // a();\n// b();|"
+        );
+    }
+
+    #[test]
+    fn lines_are_indented() {
+        let indent = |code: &mut String, a, b| indent_lines(code, a, b, "    ");
+        let dedent = |code: &mut String, a, b| dedent_lines(code, a, b, "    ");
+
+        assert_eq!(
+            edit_selection("|a();\n\n  b();|", indent),
+            "|    a();\n\n      b();|"
+        );
+        assert_eq!(
+            edit_selection("      |a();\n\tb();\nc();|", dedent),
+            "  |a();\nb();\nc();|"
+        );
+        // a selection starting in the indentation taken off stays at the line's start
+        assert_eq!(edit_selection("  | a();|", dedent), "|a();|");
+        let tabs = |code: &mut String, a, b| indent_lines(code, a, b, "\t");
+        assert_eq!(edit_selection("|a();\nb();|", tabs), "|\ta();\n\tb();|");
+    }
+
+    #[test]
+    fn lifetimes_arent_closed() {
+        assert_eq!(type_keys("|", "&'a", true), "&'a|");
+        assert_eq!(type_keys("|", "<'a>", true), "<'a>|");
+        assert_eq!(type_keys("|", "'x'", true), "'x'|");
+    }
+
+    #[test]
+    fn lines_are_duplicated() {
+        let duplicate = |code: &mut String, a, b| duplicate_lines(code, a, b);
+
+        assert_eq!(edit_selection("a|b\nc", duplicate), "ab\na|b\nc");
+        assert_eq!(edit_selection("|a\nb|", duplicate), "a\nb\n|a\nb|");
+        assert_eq!(edit_selection("a\n|b\n", duplicate), "a\nb\n|b\n");
+        assert_eq!(edit_selection("|", duplicate), "\n|");
+    }
+
+    #[test]
+    fn lines_are_moved() {
+        let up = |code: &mut String, a, b| move_lines(code, a, b, true);
+        let down = |code: &mut String, a, b| move_lines(code, a, b, false);
+
+        assert_eq!(edit_selection("a\nb|c\nd", up), "b|c\na\nd");
+        assert_eq!(edit_selection("|a\nb|\nc", down), "c\n|a\nb|");
+        assert_eq!(edit_selection("a\n|b\n|c", up), "|b\n|a\nc");
+        // the first and last lines stay, and so does a trailing newline
+        assert_eq!(edit_selection("|a\nb", up), "|a\nb");
+        assert_eq!(edit_selection("a\n|b", down), "a\n|b");
+        assert_eq!(edit_selection("a\n|b\n", down), "a\n|b\n");
+        // the line the selection ends at the start of is left where it is
+        assert_eq!(edit_selection("|a\n|b", down), "b\n|a|");
+    }
+
+    #[test]
+    fn lines_are_deleted() {
+        let delete = |code: &mut String, a, b| {
+            let cursor = delete_lines(code, a, b);
+            (cursor, cursor)
+        };
+
+        assert_eq!(edit_selection("a\nb|c\nd", delete), "a\nd|");
+        assert_eq!(edit_selection("a\nbc|", delete), "a|");
+        assert_eq!(edit_selection("|a\nb|\nc", delete), "c|");
+        assert_eq!(edit_selection("a\n|b\n", delete), "a\n|");
+        assert_eq!(edit_selection("a|", delete), "|");
+    }
+}
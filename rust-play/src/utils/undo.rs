@@ -0,0 +1,325 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use super::multi_cursor::Selection;
+
+/// How many undo steps are written to the session, so it doesn't grow with every edit made
+pub const SAVED_STEPS: usize = 50;
+
+// typing after stopping for this long starts a new undo step
+const PAUSE_SECS: f64 = 1.0;
+
+/// What an edit was made by. Typing and deleting chars one after another are undone together,
+/// everything else, like pasting or a new line, is undone by itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    Typing,
+    Deleting,
+    Other,
+}
+
+/// One undo step: the text at `start` which was replaced, and the cursors before and after
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    // by byte offset
+    start: usize,
+    removed: String,
+    inserted: String,
+    before: Selection,
+    after: Selection,
+}
+
+impl Change {
+    // What changed from `old` to `new`, everything between what they start and end the same with
+    fn between(old: &str, new: &str, before: Selection, after: Selection) -> Self {
+        let mut start = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(start) || !new.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        let mut end = old[start..]
+            .bytes()
+            .rev()
+            .zip(new[start..].bytes().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - end) || !new.is_char_boundary(new.len() - end) {
+            end -= 1;
+        }
+
+        Self {
+            start,
+            removed: old[start..old.len() - end].to_string(),
+            inserted: new[start..new.len() - end].to_string(),
+            before,
+            after,
+        }
+    }
+
+    // Fold the next change into this one, when it's only changing what this one inserted or
+    // deleting right up to it. Returns whether it could
+    fn merge(&mut self, next: &Change) -> bool {
+        let inserted_end = self.start + self.inserted.len();
+        let next_removed_end = next.start + next.removed.len();
+
+        if next.start >= self.start && next_removed_end <= inserted_end {
+            let at = next.start - self.start;
+            self.inserted
+                .replace_range(at..at + next.removed.len(), &next.inserted);
+        } else if self.inserted.is_empty() && next.inserted.is_empty() {
+            // backspacing from before this one, or deleting forward from the same place
+            if next_removed_end == self.start {
+                self.removed.insert_str(0, &next.removed);
+                self.start = next.start;
+            } else if next.start == self.start {
+                self.removed.push_str(&next.removed);
+            } else {
+                return false;
+            }
+        } else {
+            return false;
+        }
+
+        self.after = next.after;
+        true
+    }
+}
+
+/// An editor's undo and redo steps, most recent last. It's told the code after each frame, and
+/// any change since the last one becomes a step
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct History {
+    #[serde(serialize_with = "recent")]
+    undo: VecDeque<Change>,
+    #[serde(serialize_with = "recent")]
+    redo: VecDeque<Change>,
+    // the code and its cursor as they were last seen, None before the first time
+    #[serde(skip)]
+    last: Option<(String, Selection)>,
+    // the kind of the last step and when it was made, while it can still have more added to it
+    #[serde(skip)]
+    group: Option<(EditKind, f64)>,
+}
+
+impl History {
+    /// Take in the code as it is now. Changes since the last time become an undo step, or are
+    /// added onto the last one when they're more of the typing or deleting it was. Past `limit`
+    /// steps the oldest are forgotten
+    pub fn update(
+        &mut self,
+        code: &str,
+        selection: Selection,
+        kind: EditKind,
+        time: f64,
+        limit: usize,
+    ) {
+        let Some((last_code, last_selection)) = &mut self.last else {
+            self.last = Some((code.to_string(), selection));
+            return;
+        };
+
+        if last_code == code {
+            // moving the cursor away from where the typing was starts a new step
+            if *last_selection != selection {
+                *last_selection = selection;
+                self.group = None;
+            }
+            return;
+        }
+
+        let change = Change::between(last_code, code, *last_selection, selection);
+        let merged = match (self.group, self.undo.back_mut()) {
+            (Some((group_kind, group_time)), Some(step))
+                if group_kind == kind
+                    && kind != EditKind::Other
+                    && time - group_time < PAUSE_SECS =>
+            {
+                step.merge(&change)
+            }
+            _ => false,
+        };
+        if !merged {
+            self.undo.push_back(change);
+        }
+        while self.undo.len() > limit.max(1) {
+            self.undo.pop_front();
+        }
+
+        self.redo.clear();
+        self.group = Some((kind, time));
+        self.last = Some((code.to_string(), selection));
+    }
+
+    /// Undo the last step in the code. Returns the cursor from before it, None when there's
+    /// nothing to undo
+    pub fn undo(&mut self, code: &mut String) -> Option<Selection> {
+        let change = self.undo.pop_back()?;
+        let end = change.start + change.inserted.len();
+        if code.get(change.start..end) != Some(change.inserted.as_str()) {
+            // the code doesn't have the step in it, so the history is no good anymore
+            self.forget(code);
+            return None;
+        }
+
+        code.replace_range(change.start..end, &change.removed);
+        let selection = change.before;
+        self.redo.push_back(change);
+        self.reset_to(code, selection);
+
+        Some(selection)
+    }
+
+    /// Redo the last undone step. Returns the cursor from after it
+    pub fn redo(&mut self, code: &mut String) -> Option<Selection> {
+        let change = self.redo.pop_back()?;
+        let end = change.start + change.removed.len();
+        if code.get(change.start..end) != Some(change.removed.as_str()) {
+            self.forget(code);
+            return None;
+        }
+
+        code.replace_range(change.start..end, &change.inserted);
+        let selection = change.after;
+        self.undo.push_back(change);
+        self.reset_to(code, selection);
+
+        Some(selection)
+    }
+
+    fn reset_to(&mut self, code: &str, selection: Selection) {
+        self.last = Some((code.to_string(), selection));
+        self.group = None;
+    }
+
+    fn forget(&mut self, code: &str) {
+        let selection = self.last.as_ref().map_or(Selection::at(0), |(_, s)| *s);
+        *self = Self::default();
+        self.reset_to(code, selection);
+    }
+}
+
+fn recent<S: Serializer>(changes: &VecDeque<Change>, serializer: S) -> Result<S::Ok, S::Error> {
+    let skipped = changes.len().saturating_sub(SAVED_STEPS);
+    serializer.collect_seq(changes.iter().skip(skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // type each text in after the other, at the end of the code, a frame each
+    fn type_in(
+        history: &mut History,
+        code: &mut String,
+        texts: &[&str],
+        kind: EditKind,
+        time: f64,
+    ) {
+        for text in texts {
+            code.push_str(text);
+            let end = code.chars().count();
+            history.update(code, Selection::at(end), kind, time, 100);
+        }
+    }
+
+    #[test]
+    fn typing_is_undone_together() {
+        let mut history = History::default();
+        let mut code = "fn".to_string();
+        history.update(&code, Selection::at(2), EditKind::Other, 0.0, 100);
+
+        type_in(
+            &mut history,
+            &mut code,
+            &[" ", "m", "a"],
+            EditKind::Typing,
+            0.1,
+        );
+        type_in(&mut history, &mut code, &["\n"], EditKind::Other, 0.2);
+        type_in(&mut history, &mut code, &["x"], EditKind::Typing, 0.3);
+        // after a pause
+        type_in(&mut history, &mut code, &["y"], EditKind::Typing, 5.0);
+
+        let undone = |history: &mut History, code: &mut String| {
+            history.undo(code);
+            code.clone()
+        };
+        assert_eq!(undone(&mut history, &mut code), "fn ma\nx");
+        assert_eq!(undone(&mut history, &mut code), "fn ma\n");
+        assert_eq!(undone(&mut history, &mut code), "fn ma");
+        assert_eq!(history.undo(&mut code), Some(Selection::at(2)));
+        assert_eq!(code, "fn");
+        assert_eq!(history.undo(&mut code), None);
+
+        assert_eq!(history.redo(&mut code), Some(Selection::at(5)));
+        assert_eq!(code, "fn ma");
+        // a new edit can't be redone past
+        type_in(&mut history, &mut code, &["!"], EditKind::Other, 6.0);
+        assert_eq!(history.redo(&mut code), None);
+    }
+
+    #[test]
+    fn typing_inside_the_typed_text_is_undone_with_it() {
+        let mut history = History::default();
+        history.update("a", Selection::at(1), EditKind::Other, 0.0, 100);
+
+        // an auto closed bracket typed into, then backspaced over
+        history.update("a()", Selection::at(2), EditKind::Typing, 0.1, 100);
+        history.update("a(x)", Selection::at(3), EditKind::Typing, 0.2, 100);
+        history.update("a(", Selection::at(2), EditKind::Deleting, 0.3, 100);
+        history.update("a", Selection::at(1), EditKind::Deleting, 0.4, 100);
+
+        let mut code = "a".to_string();
+        assert_eq!(history.undo(&mut code), Some(Selection::at(3)));
+        assert_eq!(code, "a(x)");
+        assert_eq!(history.undo(&mut code), Some(Selection::at(1)));
+        assert_eq!(code, "a");
+    }
+
+    #[test]
+    fn deleting_is_undone_together() {
+        let mut history = History::default();
+        history.update("let abc = 1;", Selection::at(7), EditKind::Other, 0.0, 100);
+
+        for (code, cursor) in [("let ab = 1;", 6), ("let a = 1;", 5), ("let  = 1;", 4)] {
+            history.update(code, Selection::at(cursor), EditKind::Deleting, 0.1, 100);
+        }
+        // then deleting forward from there
+        history.update("let = 1;", Selection::at(4), EditKind::Deleting, 0.2, 100);
+
+        let mut code = "let = 1;".to_string();
+        assert_eq!(history.undo(&mut code), Some(Selection::at(7)));
+        assert_eq!(code, "let abc = 1;");
+        assert_eq!(history.undo(&mut code), None);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut history = History::default();
+        let mut code = String::new();
+        for cursor in 0..6 {
+            history.update(&code, Selection::at(cursor), EditKind::Other, 0.0, 3);
+            code.push('a');
+        }
+        assert_eq!(history.undo.len(), 3);
+
+        let texts = vec!["a"; SAVED_STEPS + 10];
+        type_in(&mut history, &mut code, &texts, EditKind::Other, 0.0);
+        assert_eq!(history.undo.len(), 3 + texts.len());
+
+        // only the last steps are saved
+        let saved = serde_json::to_string(&history).unwrap();
+        let mut loaded: History = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded.undo.len(), SAVED_STEPS);
+        assert_eq!(loaded.undo.back(), history.undo.back());
+
+        // code which doesn't have the steps in it can't be undone
+        assert_eq!(loaded.undo(&mut "b".to_string()), None);
+        assert!(loaded.undo.is_empty());
+    }
+}
@@ -0,0 +1,782 @@
+use regex::Regex;
+
+use super::multi_cursor::Selection;
+
+/// What the keys typed in vim mode do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+}
+
+impl Mode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+        }
+    }
+}
+
+/// A key as vim mode sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Char(char),
+    Escape,
+    Enter,
+    Backspace,
+    // Ctrl+R
+    Redo,
+}
+
+/// What's left for the editor to do after a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    // vim mode took the key
+    Done,
+    // the key goes on to the editor like without vim mode, for typing in insert mode
+    Typed,
+    Undo,
+    Redo,
+    // `:w` and `:q`, which are up to the tab
+    Save,
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Down,
+    Up,
+    Right,
+    WordStart,
+    WordBack,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+}
+
+const MOTIONS: &[(&str, Motion)] = &[
+    ("h", Motion::Left),
+    ("j", Motion::Down),
+    ("k", Motion::Up),
+    ("l", Motion::Right),
+    ("w", Motion::WordStart),
+    ("b", Motion::WordBack),
+    ("e", Motion::WordEnd),
+    ("0", Motion::LineStart),
+    ("$", Motion::LineEnd),
+    ("gg", Motion::FirstLine),
+    ("G", Motion::LastLine),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+const OPERATORS: &[(&str, Operator)] = &[
+    ("d", Operator::Delete),
+    ("c", Operator::Change),
+    ("y", Operator::Yank),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Insert,
+    Append,
+    InsertAtLineStart,
+    AppendAtLineEnd,
+    OpenBelow,
+    OpenAbove,
+    DeleteChar,
+    PasteAfter,
+    PasteBefore,
+    Undo,
+    Visual,
+    CommandLine,
+}
+
+const ACTIONS: &[(&str, Action)] = &[
+    ("i", Action::Insert),
+    ("a", Action::Append),
+    ("I", Action::InsertAtLineStart),
+    ("A", Action::AppendAtLineEnd),
+    ("o", Action::OpenBelow),
+    ("O", Action::OpenAbove),
+    ("x", Action::DeleteChar),
+    ("p", Action::PasteAfter),
+    ("P", Action::PasteBefore),
+    ("u", Action::Undo),
+    ("v", Action::Visual),
+    (":", Action::CommandLine),
+];
+
+// A whole command typed in normal or visual mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Action(Action),
+    Move(Motion),
+    // an operator over a motion, or over the cursor's line when it's typed twice, like `dd`
+    Operate(Operator, Option<Motion>),
+}
+
+// How far into a command the keys are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Parsed<T> {
+    Complete(T),
+    // the start of a longer one, like `g` of `gg`
+    Partial,
+    Invalid,
+}
+
+fn lookup<T: Copy>(table: &[(&str, T)], keys: &str) -> Parsed<T> {
+    if let Some((_, found)) = table.iter().find(|(typed, _)| *typed == keys) {
+        Parsed::Complete(*found)
+    } else if table.iter().any(|(typed, _)| typed.starts_with(keys)) {
+        Parsed::Partial
+    } else {
+        Parsed::Invalid
+    }
+}
+
+// In visual mode operators don't wait for a motion, they're used on the selection
+fn parse(keys: &str, visual: bool) -> Parsed<Command> {
+    let operator = OPERATORS.iter().find(|(typed, _)| keys.starts_with(typed));
+    if let Some((typed, operator)) = operator {
+        let rest = &keys[typed.len()..];
+        if visual || rest == *typed {
+            return Parsed::Complete(Command::Operate(*operator, None));
+        }
+        return match lookup(MOTIONS, rest) {
+            _ if rest.is_empty() => Parsed::Partial,
+            Parsed::Complete(motion) => Parsed::Complete(Command::Operate(*operator, Some(motion))),
+            Parsed::Partial => Parsed::Partial,
+            Parsed::Invalid => Parsed::Invalid,
+        };
+    }
+
+    match (lookup(ACTIONS, keys), lookup(MOTIONS, keys)) {
+        (Parsed::Complete(action), _) => Parsed::Complete(Command::Action(action)),
+        (_, Parsed::Complete(motion)) => Parsed::Complete(Command::Move(motion)),
+        (Parsed::Partial, _) | (_, Parsed::Partial) => Parsed::Partial,
+        _ => Parsed::Invalid,
+    }
+}
+
+// How much of the code a motion covers when an operator is used with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Span {
+    // up to the char it moves to
+    Exclusive,
+    // and the char it moves to too
+    Inclusive,
+    // all of the lines between
+    Lines,
+}
+
+// the code by char, for moving around it by char index
+struct Chars(Vec<char>);
+
+impl Chars {
+    fn line_start(&self, at: usize) -> usize {
+        self.0[..at]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |newline| newline + 1)
+    }
+
+    fn line_end(&self, at: usize) -> usize {
+        self.0[at..]
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(self.0.len(), |newline| at + newline)
+    }
+
+    fn first_non_blank(&self, line_start: usize) -> usize {
+        let end = self.line_end(line_start);
+        (line_start..end)
+            .find(|at| !matches!(self.0[*at], ' ' | '\t'))
+            .unwrap_or(end)
+    }
+
+    // the same column on the line starting at `line_start`, or its end when it's shorter
+    fn column_on(&self, line_start: usize, column: usize) -> usize {
+        (line_start + column).min(self.line_end(line_start))
+    }
+
+    // 0 for whitespace, 1 for words and 2 for the punctuation between them
+    fn class(&self, at: usize) -> u8 {
+        match self.0.get(at) {
+            None => 0,
+            Some(c) if c.is_whitespace() => 0,
+            Some(c) if c.is_alphanumeric() || *c == '_' => 1,
+            _ => 2,
+        }
+    }
+
+    fn motion(&self, at: usize, motion: Motion) -> (usize, Span) {
+        let len = self.0.len();
+        let start = self.line_start(at);
+
+        match motion {
+            Motion::Left => (at.saturating_sub(1).max(start), Span::Exclusive),
+            Motion::Right => ((at + 1).min(self.line_end(at)), Span::Exclusive),
+            Motion::Down => {
+                let end = self.line_end(at);
+                let to = if end < len {
+                    self.column_on(end + 1, at - start)
+                } else {
+                    at
+                };
+                (to, Span::Lines)
+            }
+            Motion::Up => {
+                let to = if start > 0 {
+                    self.column_on(self.line_start(start - 1), at - start)
+                } else {
+                    at
+                };
+                (to, Span::Lines)
+            }
+            Motion::WordStart => {
+                let class = self.class(at);
+                let mut to = at;
+                while to < len && class != 0 && self.class(to) == class {
+                    to += 1;
+                }
+                while to < len && self.class(to) == 0 {
+                    to += 1;
+                }
+                (to, Span::Exclusive)
+            }
+            Motion::WordBack => {
+                let mut to = at;
+                while to > 0 && self.class(to - 1) == 0 {
+                    to -= 1;
+                }
+                let class = self.class(to.saturating_sub(1));
+                while to > 0 && self.class(to - 1) == class {
+                    to -= 1;
+                }
+                (to, Span::Exclusive)
+            }
+            Motion::WordEnd => {
+                let mut to = (at + 1).min(len);
+                while to < len && self.class(to) == 0 {
+                    to += 1;
+                }
+                let class = self.class(to);
+                while to + 1 < len && self.class(to + 1) == class {
+                    to += 1;
+                }
+                (to.min(len.saturating_sub(1)), Span::Inclusive)
+            }
+            Motion::LineStart => (start, Span::Exclusive),
+            Motion::LineEnd => (self.line_end(at), Span::Exclusive),
+            Motion::FirstLine => (self.first_non_blank(0), Span::Lines),
+            Motion::LastLine => (self.first_non_blank(self.line_start(len)), Span::Lines),
+        }
+    }
+}
+
+fn byte_index(code: &str, char_index: usize) -> usize {
+    code.char_indices()
+        .nth(char_index)
+        .map_or(code.len(), |(byte, _)| byte)
+}
+
+/// The state of vim mode in one editor: its mode, the keys of the command being typed, and what
+/// was last yanked or deleted
+#[derive(Debug, Clone, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    pending: String,
+    register: String,
+    // the register holds whole lines, which are put on lines of their own
+    linewise: bool,
+    /// The `:` line while it's typed in
+    pub command_line: Option<String>,
+    /// Why the last `:` command couldn't be run
+    pub error: Option<String>,
+}
+
+impl Vim {
+    /// Take in a key. `selection` is the editor's cursor, and the text selected from it in visual
+    /// mode
+    pub fn key(&mut self, code: &mut String, selection: &mut Selection, input: Input) -> Outcome {
+        if let Some(line) = &mut self.command_line {
+            match input {
+                Input::Char(c) => line.push(c),
+                Input::Backspace if line.pop().is_none() => self.command_line = None,
+                Input::Backspace | Input::Redo => (),
+                Input::Escape => self.command_line = None,
+                Input::Enter => {
+                    let line = self.command_line.take().unwrap_or_default();
+                    return self.run_command_line(code, selection, &line);
+                }
+            }
+            return Outcome::Done;
+        }
+
+        match (self.mode, input) {
+            (Mode::Insert, Input::Escape) => {
+                self.mode = Mode::Normal;
+                let chars = Chars(code.chars().collect());
+                let cursor = selection.cursor;
+                *selection = Selection::at(cursor.saturating_sub(1).max(chars.line_start(cursor)));
+                Outcome::Done
+            }
+            (Mode::Insert, _) => Outcome::Typed,
+            (_, Input::Escape) => {
+                self.pending.clear();
+                self.mode = Mode::Normal;
+                *selection = Selection::at(selection.cursor);
+                Outcome::Done
+            }
+            (_, Input::Redo) => Outcome::Redo,
+            (_, Input::Char(c)) => {
+                self.pending.push(c);
+                match parse(&self.pending, self.mode == Mode::Visual) {
+                    Parsed::Complete(command) => {
+                        self.pending.clear();
+                        let outcome = self.run(code, selection, command);
+                        if self.mode == Mode::Normal {
+                            let chars = Chars(code.chars().collect());
+                            let cursor = selection.cursor.min(chars.0.len());
+                            // the cursor sits on a char, not after the line's last one
+                            let on_char = if cursor > chars.line_start(cursor)
+                                && cursor == chars.line_end(cursor)
+                            {
+                                cursor - 1
+                            } else {
+                                cursor
+                            };
+                            *selection = Selection::at(on_char);
+                        }
+                        outcome
+                    }
+                    Parsed::Partial => Outcome::Done,
+                    Parsed::Invalid => {
+                        self.pending.clear();
+                        Outcome::Done
+                    }
+                }
+            }
+            (_, Input::Enter | Input::Backspace) => Outcome::Done,
+        }
+    }
+
+    fn run(&mut self, code: &mut String, selection: &mut Selection, command: Command) -> Outcome {
+        let chars = Chars(code.chars().collect());
+        let cursor = selection.cursor.min(chars.0.len());
+
+        if self.mode == Mode::Visual {
+            match command {
+                Command::Move(motion) => {
+                    selection.cursor = chars.motion(cursor, motion).0;
+                    return Outcome::Done;
+                }
+                Command::Operate(operator, _) => {
+                    let range = selection.range();
+                    let end = (range.end + 1).min(chars.0.len());
+                    self.mode = Mode::Normal;
+                    return self.operate(code, selection, operator, range.start..end, false);
+                }
+                Command::Action(Action::DeleteChar) => {
+                    return self.run(code, selection, Command::Operate(Operator::Delete, None));
+                }
+                Command::Action(Action::Visual) => {
+                    self.mode = Mode::Normal;
+                    *selection = Selection::at(cursor);
+                    return Outcome::Done;
+                }
+                Command::Action(_) => return Outcome::Done,
+            }
+        }
+
+        match command {
+            Command::Move(motion) => {
+                *selection = Selection::at(chars.motion(cursor, motion).0);
+                Outcome::Done
+            }
+            Command::Operate(operator, None) => {
+                let (start, end) = (chars.line_start(cursor), chars.line_end(cursor));
+                self.operate_lines(code, selection, operator, start, end)
+            }
+            Command::Operate(operator, Some(motion)) => {
+                // `cw` changes up to the end of the word, leaving the space after it
+                let motion = match (operator, motion) {
+                    (Operator::Change, Motion::WordStart) if chars.class(cursor) != 0 => {
+                        Motion::WordEnd
+                    }
+                    _ => motion,
+                };
+                let (to, span) = chars.motion(cursor, motion);
+                let (from, to) = (cursor.min(to), cursor.max(to));
+
+                match span {
+                    Span::Lines => {
+                        let (start, end) = (chars.line_start(from), chars.line_end(to));
+                        self.operate_lines(code, selection, operator, start, end)
+                    }
+                    Span::Inclusive => {
+                        let end = (to + 1).min(chars.0.len());
+                        self.operate(code, selection, operator, from..end, false)
+                    }
+                    Span::Exclusive => self.operate(code, selection, operator, from..to, false),
+                }
+            }
+            Command::Action(action) => self.act(code, selection, &chars, cursor, action),
+        }
+    }
+
+    // Delete, change or yank the lines from the one starting at `start` to the one ending at `end`
+    fn operate_lines(
+        &mut self,
+        code: &mut String,
+        selection: &mut Selection,
+        operator: Operator,
+        start: usize,
+        end: usize,
+    ) -> Outcome {
+        let len = code.chars().count();
+        let range = match operator {
+            // changing them keeps an empty line to type on
+            Operator::Change => start..end,
+            // with the newline after them, or before them when they're the last
+            _ if end < len => start..end + 1,
+            _ => start.saturating_sub(1)..end,
+        };
+
+        let outcome = self.operate(code, selection, operator, range, true);
+        match operator {
+            Operator::Delete => {
+                let chars = Chars(code.chars().collect());
+                let at = selection.cursor.min(chars.0.len());
+                *selection = Selection::at(chars.first_non_blank(chars.line_start(at)));
+            }
+            Operator::Yank => *selection = Selection::at(start),
+            Operator::Change => (),
+        }
+
+        outcome
+    }
+
+    fn operate(
+        &mut self,
+        code: &mut String,
+        selection: &mut Selection,
+        operator: Operator,
+        range: std::ops::Range<usize>,
+        linewise: bool,
+    ) -> Outcome {
+        let bytes = byte_index(code, range.start)..byte_index(code, range.end);
+        self.register = code[bytes.clone()].to_string();
+        self.linewise = linewise;
+        // lines taken from the end have their newline in front
+        if linewise && self.register.starts_with('\n') {
+            self.register.remove(0);
+            self.register.push('\n');
+        } else if linewise && !self.register.ends_with('\n') {
+            self.register.push('\n');
+        }
+
+        if operator != Operator::Yank {
+            code.replace_range(bytes, "");
+        }
+        if operator == Operator::Change {
+            self.mode = Mode::Insert;
+        }
+        *selection = Selection::at(range.start);
+
+        Outcome::Done
+    }
+
+    fn act(
+        &mut self,
+        code: &mut String,
+        selection: &mut Selection,
+        chars: &Chars,
+        cursor: usize,
+        action: Action,
+    ) -> Outcome {
+        let line_start = chars.line_start(cursor);
+        let line_end = chars.line_end(cursor);
+
+        let insert_at = match action {
+            Action::Insert => Some(cursor),
+            Action::Append => Some((cursor + 1).min(line_end)),
+            Action::InsertAtLineStart => Some(chars.first_non_blank(line_start)),
+            Action::AppendAtLineEnd => Some(line_end),
+            Action::OpenBelow => {
+                code.insert(byte_index(code, line_end), '\n');
+                Some(line_end + 1)
+            }
+            Action::OpenAbove => {
+                code.insert(byte_index(code, line_start), '\n');
+                Some(line_start)
+            }
+            _ => None,
+        };
+        if let Some(at) = insert_at {
+            self.mode = Mode::Insert;
+            *selection = Selection::at(at);
+            return Outcome::Done;
+        }
+
+        match action {
+            Action::DeleteChar if cursor < line_end => {
+                return self.operate(code, selection, Operator::Delete, cursor..cursor + 1, false);
+            }
+            Action::DeleteChar => (),
+            Action::PasteAfter | Action::PasteBefore => {
+                let after = action == Action::PasteAfter;
+                let pasted = self.register.chars().count();
+                let (at, text) = match (self.linewise, after) {
+                    (true, true) if line_end == chars.0.len() => {
+                        let text = format!("\n{}", self.register.trim_end_matches('\n'));
+                        (line_end, text)
+                    }
+                    (true, true) => (line_end + 1, self.register.clone()),
+                    (true, false) => (line_start, self.register.clone()),
+                    (false, true) => ((cursor + 1).min(line_end), self.register.clone()),
+                    (false, false) => (cursor, self.register.clone()),
+                };
+                code.insert_str(byte_index(code, at), &text);
+
+                *selection = if self.linewise {
+                    let line = if text.starts_with('\n') { at + 1 } else { at };
+                    let chars = Chars(code.chars().collect());
+                    Selection::at(chars.first_non_blank(line))
+                } else {
+                    Selection::at((at + pasted).saturating_sub(1))
+                };
+            }
+            Action::Undo => return Outcome::Undo,
+            Action::Visual => {
+                self.mode = Mode::Visual;
+                *selection = Selection::at(cursor);
+            }
+            Action::CommandLine => {
+                self.command_line = Some(String::new());
+                self.error = None;
+            }
+            _ => (),
+        }
+
+        Outcome::Done
+    }
+
+    // `:w`, `:q`, and `:s/pattern/replacement/g` on the cursor's line or on every line with `%`
+    fn run_command_line(
+        &mut self,
+        code: &mut String,
+        selection: &mut Selection,
+        line: &str,
+    ) -> Outcome {
+        let line = line.trim();
+        match line {
+            "w" => return Outcome::Save,
+            "q" => return Outcome::Close,
+            "" => return Outcome::Done,
+            _ => (),
+        }
+
+        let (every_line, substitute) = match line.strip_prefix('%') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let Some(substitute) = substitute.strip_prefix("s/") else {
+            self.error = Some(format!("Not an editor command: {line}"));
+            return Outcome::Done;
+        };
+
+        let mut parts = substitute.splitn(3, '/');
+        let pattern = parts.next().unwrap_or_default();
+        let Some(replacement) = parts.next().filter(|_| !pattern.is_empty()) else {
+            self.error = Some("Usage: :%s/pattern/replacement/g".to_string());
+            return Outcome::Done;
+        };
+        let global = parts.next().is_some_and(|flags| flags.contains('g'));
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return Outcome::Done;
+            }
+        };
+
+        let chars = Chars(code.chars().collect());
+        let cursor_line = chars.0[..chars.line_start(selection.cursor.min(chars.0.len()))]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count();
+        let replaced = code
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                if !every_line && index != cursor_line {
+                    line.to_string()
+                } else if global {
+                    regex.replace_all(line, replacement).into_owned()
+                } else {
+                    regex.replace(line, replacement).into_owned()
+                }
+            })
+            .collect::<String>();
+
+        if replaced == *code {
+            self.error = Some(format!("Pattern not found: {pattern}"));
+        } else {
+            *code = replaced;
+        }
+
+        Outcome::Done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // type the keys in normal mode with the cursor at `cursor`, returning the code and where the
+    // cursor ended up
+    fn typed(code: &str, cursor: usize, keys: &str) -> (String, usize, Vim) {
+        let mut vim = Vim::default();
+        let mut code = code.to_string();
+        let mut selection = Selection::at(cursor);
+
+        for key in keys.chars() {
+            let input = match key {
+                '⎋' => Input::Escape,
+                '⏎' => Input::Enter,
+                key => Input::Char(key),
+            };
+            if vim.key(&mut code, &mut selection, input) == Outcome::Typed {
+                let at = byte_index(&code, selection.cursor);
+                code.insert(at, key);
+                selection = Selection::at(selection.cursor + 1);
+            }
+        }
+
+        (code, selection.cursor, vim)
+    }
+
+    const CODE: &str = "fn main() {\n    let a = 1;\n}";
+
+    #[test]
+    fn motions_move_the_cursor() {
+        let cursor = |keys| typed(CODE, 0, keys).1;
+
+        assert_eq!(cursor("w"), 3);
+        assert_eq!(cursor("ww"), 7);
+        assert_eq!(cursor("e"), 1);
+        assert_eq!(cursor("$"), 10);
+        assert_eq!(cursor("$0"), 0);
+        assert_eq!(cursor("j"), 12);
+        assert_eq!(cursor("jlll$b"), 24);
+        assert_eq!(cursor("G"), 27);
+        assert_eq!(cursor("Ggg"), 0);
+        // past the first line's start, and into a shorter line
+        assert_eq!(cursor("hk"), 0);
+        assert_eq!(cursor("$jj"), 27);
+    }
+
+    #[test]
+    fn operators_take_motions() {
+        assert_eq!(typed(CODE, 0, "dw").0, "main() {\n    let a = 1;\n}");
+        assert_eq!(
+            typed(CODE, 0, "cwpub fn⎋").0,
+            "pub fn main() {\n    let a = 1;\n}"
+        );
+        assert_eq!(typed(CODE, 0, "d$").0, "\n    let a = 1;\n}");
+        assert_eq!(typed(CODE, 0, "jdd").0, "fn main() {\n}");
+        assert_eq!(typed(CODE, 0, "dj").0, "}");
+        assert_eq!(typed(CODE, 0, "Gdd").0, "fn main() {\n    let a = 1;");
+        assert_eq!(typed(CODE, 16, "cc}⎋").0, "fn main() {\n}\n}");
+        assert_eq!(typed(CODE, 0, "xxx").0, "main() {\n    let a = 1;\n}");
+    }
+
+    #[test]
+    fn yanked_text_is_put() {
+        // whole lines go on lines of their own
+        let (code, cursor, _) = typed(CODE, 0, "jyyP");
+        assert_eq!(code, "fn main() {\n    let a = 1;\n    let a = 1;\n}");
+        assert_eq!(cursor, 16);
+        assert_eq!(
+            typed(CODE, 0, "GyykP").0,
+            "fn main() {\n}\n    let a = 1;\n}"
+        );
+        assert_eq!(typed(CODE, 0, "Gddp").0, "fn main() {\n    let a = 1;\n}");
+
+        assert_eq!(typed("abc", 0, "xp").0, "bac");
+        assert_eq!(typed("abc", 0, "ywP").0, "abcabc");
+        // a selection is yanked with the char under the cursor
+        assert_eq!(typed("abc def", 0, "velyP").0, "abc abc def");
+    }
+
+    #[test]
+    fn modes_are_switched() {
+        let (code, cursor, vim) = typed(CODE, 0, "A // hi⎋");
+        assert_eq!(code, "fn main() { // hi\n    let a = 1;\n}");
+        assert_eq!((cursor, vim.mode), (16, Mode::Normal));
+
+        let (code, cursor, vim) = typed(CODE, 0, "oa();");
+        assert_eq!(code, "fn main() {\na();\n    let a = 1;\n}");
+        assert_eq!((cursor, vim.mode), (16, Mode::Insert));
+
+        let (code, _, vim) = typed(CODE, 0, "vjd");
+        assert_eq!(code, "   let a = 1;\n}");
+        assert_eq!(vim.mode, Mode::Normal);
+
+        let mut vim = Vim::default();
+        let mut code = CODE.to_string();
+        let mut selection = Selection::at(0);
+        assert_eq!(
+            vim.key(&mut code, &mut selection, Input::Char('u')),
+            Outcome::Undo
+        );
+        assert_eq!(
+            vim.key(&mut code, &mut selection, Input::Redo),
+            Outcome::Redo
+        );
+    }
+
+    #[test]
+    fn command_line_commands_are_run() {
+        let outcome = |keys: &str| {
+            let mut vim = Vim::default();
+            let mut code = CODE.to_string();
+            let mut selection = Selection::at(0);
+            let mut outcome = Outcome::Done;
+            for key in keys.chars() {
+                outcome = vim.key(&mut code, &mut selection, Input::Char(key));
+            }
+            outcome = match outcome {
+                Outcome::Done => vim.key(&mut code, &mut selection, Input::Enter),
+                outcome => outcome,
+            };
+            (outcome, code, vim.error)
+        };
+
+        assert_eq!(outcome(":w").0, Outcome::Save);
+        assert_eq!(outcome(":q").0, Outcome::Close);
+
+        let (_, code, _) = outcome(":%s/([a-z]+)/<$1>/g");
+        assert_eq!(code, "<fn> <main>() {\n    <let> <a> = 1;\n}");
+        let (_, code, _) = outcome(":s/ //g");
+        assert_eq!(code, "fnmain(){\n    let a = 1;\n}");
+
+        assert!(outcome(":%s/(/x/").2.is_some());
+        assert!(outcome(":e").2.is_some());
+        assert!(typed(CODE, 0, ":wxyz⎋").2.command_line.is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use std::process::{Command, Stdio};
+
+use cargo_player::Channel;
+use thiserror::Error;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(target_os = "windows")]
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+pub const WASM32: &str = "wasm32-unknown-unknown";
+
+#[derive(Debug, Error)]
+pub enum TargetError {
+    #[error("Failed to run rustup: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("The {channel} toolchain isn't installed. Install it along with the target with\n\nrustup toolchain install {channel} --target {target}")]
+    NoToolchain { channel: Channel, target: String },
+    #[error("The {target} target isn't installed. Add it with\n\n{command}")]
+    NotInstalled { target: String, command: String },
+}
+
+/// The rustup command which adds a target to a toolchain
+pub fn install_command(channel: Channel, target: &str) -> String {
+    format!("rustup target add {target} --toolchain {channel}")
+}
+
+/// Whether the toolchain can build for a target, going by `rustup target list`
+pub fn check_target_installed(channel: Channel, target: &str) -> Result<(), TargetError> {
+    let mut command = Command::new("rustup");
+    command
+        .args(["target", "list", "--installed", "--toolchain"])
+        .arg(channel.to_string())
+        .stdin(Stdio::null());
+
+    // hide the console window from command. Very important.
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW.0);
+
+    let output = command.output()?;
+
+    // rustup fails when the toolchain isn't installed
+    if !output.status.success() {
+        return Err(TargetError::NoToolchain {
+            channel,
+            target: target.to_string(),
+        });
+    }
+
+    if has_target(&String::from_utf8_lossy(&output.stdout), target) {
+        Ok(())
+    } else {
+        Err(TargetError::NotInstalled {
+            target: target.to_string(),
+            command: install_command(channel, target),
+        })
+    }
+}
+
+// one target per line
+fn has_target(list: &str, target: &str) -> bool {
+    list.lines().any(|line| line.trim() == target)
+}
+
+/// The text format of a WebAssembly module
+pub fn disassemble(wasm: &[u8]) -> Result<String, String> {
+    wasmprinter::print_bytes(wasm).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_installed_targets() {
+        let list = "wasm32-unknown-unknown\nx86_64-pc-windows-msvc\n";
+
+        assert!(has_target(list, WASM32));
+        assert!(!has_target(list, "wasm32-wasi"));
+        assert!(!has_target("", WASM32));
+    }
+
+    #[test]
+    fn disassembles_modules() {
+        // the smallest module there is, just the header
+        let wat = disassemble(b"\0asm\x01\0\0\0").unwrap();
+        assert_eq!(wat.trim(), "(module)");
+
+        assert!(disassemble(b"not wasm").is_err());
+    }
+
+    #[test]
+    fn install_command_names_the_toolchain() {
+        assert_eq!(
+            install_command(Channel::Nightly, WASM32),
+            "rustup target add wasm32-unknown-unknown --toolchain nightly"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::ops::Range;
+
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+/// The byte ranges of the spaces and tabs at the end of each line, which do nothing but get in
+/// the way of diffs
+pub fn trailing(code: &str) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut line_start = 0;
+
+    for line in code.split('\n') {
+        let content = line.strip_suffix('\r').unwrap_or(line);
+        let trimmed = content.trim_end_matches(is_blank);
+        if trimmed.len() < content.len() {
+            ranges.push(line_start + trimmed.len()..line_start + content.len());
+        }
+
+        line_start += line.len() + 1;
+    }
+
+    ranges
+}
+
+/// The code as it's written to disk on save: with the trailing whitespace of each line taken off
+/// when `trim` is set, and ending in a newline when `final_newline` is
+pub fn clean(code: &str, trim: bool, final_newline: bool) -> String {
+    let mut cleaned = String::with_capacity(code.len() + 1);
+    let mut end = 0;
+
+    if trim {
+        for range in trailing(code) {
+            cleaned.push_str(&code[end..range.start]);
+            end = range.end;
+        }
+    }
+    cleaned.push_str(&code[end..]);
+
+    if final_newline && !cleaned.is_empty() && !cleaned.ends_with('\n') {
+        cleaned.push('\n');
+    }
+
+    cleaned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_whitespace_is_found() {
+        let code = "fn main() {  \n\t\n    a(); \t\r\n}";
+
+        assert_eq!(trailing(code), vec![11..13, 14..15, 24..26]);
+        assert_eq!(trailing("a\n b"), vec![]);
+    }
+
+    #[test]
+    fn code_is_cleaned() {
+        let code = "fn main() {  \n\t\n    a(); \r\n}";
+
+        assert_eq!(clean(code, true, true), "fn main() {\n\n    a();\r\n}\n");
+        assert_eq!(clean(code, false, true), format!("{code}\n"));
+        assert_eq!(clean("a \n", true, false), "a\n");
+        // empty code stays empty
+        assert_eq!(clean("", true, true), "");
+    }
+}
@@ -1,14 +1,80 @@
 // ----------------------------------------------------------------------------
 
-use egui::text::LayoutJob;
-use egui::{vec2, Color32, FontSelection, Id, Layout, Rect, Rounding, Stroke, Vec2};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::sync::Arc;
+
+use egui::text::{CCursor, Galley, LayoutJob};
+use egui::text_edit::{CCursorRange, TextEditOutput, TextEditState};
+use egui::{
+    pos2, vec2, Align, Align2, Button, Color32, CursorIcon, Event, FontId, FontSelection, Frame,
+    Id, Key, Layout, Order, Pos2, Rect, RichText, Rounding, SelectableLabel, Sense, Shape, Stroke,
+    TextBuffer, TextEdit, Vec2, Visuals,
+};
 use serde::{Deserialize, Serialize};
 
-/// Memoized Code highlighting
-pub fn highlight(ctx: &egui::Context, theme: &CodeTheme, code: &str, language: &str) -> LayoutJob {
-    impl egui::util::cache::ComputerMut<(&CodeTheme, &str, &str), LayoutJob> for Highlighter {
-        fn compute(&mut self, (theme, code, lang): (&CodeTheme, &str, &str)) -> LayoutJob {
-            self.highlight(theme, code, lang)
+use crate::config::{Editor, EditorSearch, Indent, LineEdit, ShowWhitespace, SyntaxColors};
+use crate::utils::brackets::{self, BracketMatch};
+use crate::utils::completion::Words;
+use crate::utils::cursor;
+use crate::utils::diagnostics::{self, Diagnostic, Severity, Tool};
+use crate::utils::find::{self, Query};
+use crate::utils::folding::{self, Fold, Folds};
+use crate::utils::fuzzy;
+use crate::utils::indent_guides;
+use crate::utils::inlay_hints::{Hint, InlayHints};
+use crate::utils::multi_cursor::{self, Edit, Motion, Selection};
+use crate::utils::snippets::{Expansion, Snippet};
+use crate::utils::syntax::{self, Highlight, LineHighlighter};
+use crate::utils::typing;
+use crate::utils::undo::{EditKind, History};
+use crate::utils::vim::{self, Mode, Vim};
+use crate::utils::whitespace;
+
+use super::minimap::Minimap;
+use super::terminal::restyle_ranges;
+
+// clippy's warnings are picked out from the compiler's
+const CLIPPY_COLOR: Color32 = Color32::from_rgb(160, 110, 230);
+// room between the line numbers and the code for the suggestion lightbulbs, and as much again
+// for the fold markers
+const GLYPH_WIDTH: f32 = 14.0;
+// the line numbers always have room for this many digits, so the code only moves over at 100 lines
+const MIN_NUMBER_DIGITS: usize = 2;
+// the same as the terminal's search highlights
+const MATCH_COLOR: Color32 = Color32::from_rgba_premultiplied(60, 49, 0, 60);
+const CURRENT_MATCH_COLOR: Color32 = Color32::from_rgba_premultiplied(160, 88, 0, 160);
+// behind the bracket next to the cursor and its partner
+const BRACKET_COLOR: Color32 = Color32::from_rgba_premultiplied(70, 70, 70, 90);
+const UNMATCHED_BRACKET_COLOR: Color32 = Color32::from_rgba_premultiplied(140, 20, 20, 140);
+// how long a bracket with no partner to jump to flashes for
+const FLASH_SECS: f64 = 0.5;
+const TRAILING_WHITESPACE_COLOR: Color32 = Color32::from_rgba_premultiplied(60, 10, 10, 60);
+// the dots and arrows drawn over spaces and tabs
+const WHITESPACE_COLOR: Color32 = Color32::from_gray(75);
+const GUIDE_COLOR: Color32 = Color32::from_gray(45);
+// the guide of the scope the cursor is in
+const ACTIVE_GUIDE_COLOR: Color32 = Color32::from_gray(95);
+// behind what a folded block shows in place of its lines
+const FOLD_COLOR: Color32 = Color32::from_gray(40);
+// the block vim mode's cursor is drawn as outside of insert mode
+const VIM_CURSOR_COLOR: Color32 = Color32::from_rgba_premultiplied(120, 120, 120, 120);
+// how many words the completion popup lists at most
+const MAX_COMPLETIONS: usize = 8;
+
+/// Memoized Code highlighting. It's only redone when the code or the colors change
+pub fn highlight(
+    ctx: &egui::Context,
+    theme: &CodeTheme,
+    syntax: &SyntaxColors,
+    code: &str,
+    language: &str,
+) -> LayoutJob {
+    type Key<'a> = (&'a CodeTheme, &'a SyntaxColors, &'a str, &'a str);
+
+    impl egui::util::cache::ComputerMut<Key<'_>, LayoutJob> for Highlighter {
+        fn compute(&mut self, (theme, syntax, code, lang): Key) -> LayoutJob {
+            self.highlight(theme, syntax, code, lang)
         }
     }
 
@@ -16,12 +82,12 @@ pub fn highlight(ctx: &egui::Context, theme: &CodeTheme, code: &str, language: &
 
     let mut memory = ctx.memory();
     let highlight_cache = memory.caches.cache::<HighlightCache>();
-    highlight_cache.get((theme, code, language))
+    highlight_cache.get((theme, syntax, code, language))
 }
 
 // ----------------------------------------------------------------------------
 
-#[derive(Clone, Copy, Hash, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Deserialize, Serialize)]
 enum SyntectTheme {
     Base16EightiesDark,
     Base16MochaDark,
@@ -83,7 +149,7 @@ impl SyntectTheme {
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Hash, PartialEq, Deserialize, Serialize)]
 #[serde(default)]
 pub struct CodeTheme {
     dark_mode: bool,
@@ -152,17 +218,23 @@ impl Default for Highlighter {
 
 impl Highlighter {
     #[allow(clippy::unused_self, clippy::unnecessary_wraps)]
-    fn highlight(&self, theme: &CodeTheme, code: &str, lang: &str) -> LayoutJob {
+    fn highlight(
+        &self,
+        theme: &CodeTheme,
+        syntax: &SyntaxColors,
+        code: &str,
+        lang: &str,
+    ) -> LayoutJob {
+        if lang == "rs" {
+            return highlight_rust(theme, syntax, code, syntax::highlight(code));
+        }
+
         self.highlight_impl(theme, code, lang).unwrap_or_else(|| {
             // Fallback:
             LayoutJob::simple(
                 code.into(),
                 egui::FontId::monospace(12.0),
-                if theme.dark_mode {
-                    egui::Color32::LIGHT_GRAY
-                } else {
-                    egui::Color32::DARK_GRAY
-                },
+                plain_color(theme),
                 f32::INFINITY,
             )
         })
@@ -217,6 +289,76 @@ impl Highlighter {
     }
 }
 
+// the color of code which isn't highlighted
+fn plain_color(theme: &CodeTheme) -> Color32 {
+    if theme.dark_mode {
+        egui::Color32::LIGHT_GRAY
+    } else {
+        egui::Color32::DARK_GRAY
+    }
+}
+
+// Rust is highlighted by our own lexer rather than syntect, so its colors can be set in the
+// settings
+fn highlight_rust(
+    theme: &CodeTheme,
+    syntax: &SyntaxColors,
+    code: &str,
+    highlights: impl IntoIterator<Item = (Range<usize>, Highlight)>,
+) -> LayoutJob {
+    use egui::text::{LayoutSection, TextFormat};
+
+    let font_id = FontId::monospace(12.0);
+    let mut job = LayoutJob {
+        text: code.into(),
+        ..Default::default()
+    };
+    let mut section = |byte_range: Range<usize>, color: Color32| {
+        job.sections.push(LayoutSection {
+            leading_space: 0.0,
+            byte_range,
+            format: TextFormat::simple(font_id.clone(), color),
+        });
+    };
+
+    let mut end = 0;
+    for (range, kind) in highlights {
+        if range.start > end {
+            section(end..range.start, plain_color(theme));
+        }
+
+        end = range.end;
+        section(range, syntax.color(kind).to_color32());
+    }
+    if end < code.len() {
+        section(end..code.len(), plain_color(theme));
+    }
+
+    job
+}
+
+// Rust highlighting kept between frames, so an edit to long code only lexes the lines it changed
+#[derive(Debug, Clone, Default)]
+struct RustHighlighting {
+    lines: LineHighlighter,
+    job: Option<(CodeTheme, SyntaxColors, LayoutJob)>,
+}
+
+impl RustHighlighting {
+    fn layout_job(&mut self, theme: &CodeTheme, syntax: &SyntaxColors, code: &str) -> LayoutJob {
+        if let Some((job_theme, job_syntax, job)) = &self.job {
+            if job_theme == theme && job_syntax == syntax && self.lines.code() == code {
+                return job.clone();
+            }
+        }
+
+        self.lines.update(code);
+        let job = highlight_rust(theme, syntax, code, self.lines.highlights());
+        self.job = Some((theme.clone(), *syntax, job.clone()));
+        job
+    }
+}
+
 fn as_byte_range(whole: &str, range: &str) -> std::ops::Range<usize> {
     let whole_start = whole.as_ptr() as usize;
     let range_start = range.as_ptr() as usize;
@@ -230,6 +372,59 @@ fn as_byte_range(whole: &str, range: &str) -> std::ops::Range<usize> {
 pub struct CodeEditor {
     language: String,
     pub code: String,
+    // for showing generated code, which can be selected but not edited
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(skip)]
+    highlighting: RustHighlighting,
+    // kept with the tab rather than the text edit, so it lasts through moving the tab around
+    #[serde(default)]
+    history: History,
+    #[serde(default)]
+    folds: Folds,
+    #[serde(skip)]
+    minimap: Minimap,
+    #[serde(skip)]
+    vim: Vim,
+    // the snippet whose stops Tab is going through
+    #[serde(skip)]
+    snippet: Option<Expansion>,
+    #[serde(skip)]
+    completions: Completions,
+    #[serde(skip)]
+    lsp: LspState,
+    #[serde(skip)]
+    inlay_hints: InlayHints,
+}
+
+// The words offered for the identifier being typed, and which of them is picked. The popup is
+// closed while there are none
+#[derive(Debug, Clone, Default)]
+struct Completions {
+    words: Words,
+    items: Vec<String>,
+    selected: usize,
+    // the char range of what was typed, which the picked word takes the place of
+    typed: Range<usize>,
+    // the byte offset rust-analyzer was asked to complete at, while its answer is still wanted
+    asked: Option<usize>,
+}
+
+/// What an editor wants to ask rust-analyzer about, by byte offsets into its code
+#[derive(Debug, Clone, Default)]
+pub struct LspAsks {
+    pub completion: Option<usize>,
+    pub hover: Option<usize>,
+}
+
+// What's asked of rust-analyzer for the editor and what it said about the word under the pointer
+#[derive(Debug, Clone, Default)]
+struct LspState {
+    // what the dock hasn't taken to ask yet
+    asks: LspAsks,
+    // the start of the word the pointer was over with Ctrl held
+    hovered: Option<usize>,
+    hover: Option<String>,
 }
 
 impl Default for CodeEditor {
@@ -272,22 +467,2073 @@ fn main() {
 }
 "#
             .into(),
+            read_only: false,
+            highlighting: RustHighlighting::default(),
+            history: History::default(),
+            folds: Folds::default(),
+            minimap: Minimap::default(),
+            vim: Vim::default(),
+            snippet: None,
+            completions: Completions::default(),
+            lsp: LspState::default(),
+            inlay_hints: InlayHints::default(),
         }
     }
 }
 
 impl CodeEditor {
-    pub fn show(&mut self, id: Id, ui: &mut egui::Ui, scroll_offset: Vec2) -> Vec2 {
-        let Self { language, code } = self;
+    /// An editor for `code` in `language`
+    pub fn new(language: &str, code: String) -> Self {
+        Self {
+            language: language.to_string(),
+            code,
+            read_only: false,
+            highlighting: RustHighlighting::default(),
+            history: History::default(),
+            folds: Folds::default(),
+            minimap: Minimap::default(),
+            vim: Vim::default(),
+            snippet: None,
+            completions: Completions::default(),
+            lsp: LspState::default(),
+            inlay_hints: InlayHints::default(),
+        }
+    }
+
+    /// An editor for looking at code in `language`, which can't be edited
+    pub fn new_read_only(language: &str, code: String) -> Self {
+        Self {
+            language: language.to_string(),
+            code,
+            read_only: true,
+            highlighting: RustHighlighting::default(),
+            history: History::default(),
+            folds: Folds::default(),
+            minimap: Minimap::default(),
+            vim: Vim::default(),
+            snippet: None,
+            completions: Completions::default(),
+            lsp: LspState::default(),
+            inlay_hints: InlayHints::default(),
+        }
+    }
+
+    /// Move the editor's cursor to a line and column, both starting at 1, and focus it, unfolding
+    /// the line when it's folded away. Locations past the end of the code are clamped to it.
+    /// Returns the scroll offset which puts the line in the middle of the editor
+    pub fn reveal(&mut self, ctx: &egui::Context, id: Id, line: usize, col: usize) -> Vec2 {
+        let (line_index, char_index) = cursor::char_index(&self.code, line, col);
+        self.folds.update(&self.code);
+        self.folds.reveal(line_index, line_index);
+
+        let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+        state.set_ccursor_range(Some(CCursorRange::one(CCursor::new(char_index))));
+        state.store(ctx, id);
+
+        ctx.memory().request_focus(id);
+
+        let row_height = ctx
+            .fonts()
+            .row_height(&FontId::monospace(Gutter::FONT_SIZE));
+        // an editor which wasn't shown yet is taken to be empty, so the line ends up at the top
+        let view_height = ctx
+            .data()
+            .get_temp::<f32>(id.with("view_height"))
+            .unwrap_or_default();
+
+        let row_index = line_index - self.folds.hidden_above(line_index);
+        vec2(
+            0.0,
+            cursor::centered_offset(row_index, row_height, view_height),
+        )
+    }
+
+    /// Comment out the selected lines, or the cursor's when nothing is selected, or uncomment
+    /// them. The selection still covers the same code after. Returns whether the code changed
+    pub fn toggle_comment(&mut self, ctx: &egui::Context, id: Id) -> bool {
+        self.edit_selection(ctx, id, typing::toggle_comment)
+    }
+
+    /// Duplicate, move or delete the selected lines, or the cursor's. Returns whether the code
+    /// changed
+    pub fn edit_lines(&mut self, ctx: &egui::Context, id: Id, edit: LineEdit) -> bool {
+        self.edit_selection(ctx, id, |code, anchor, cursor| match edit {
+            LineEdit::Duplicate => typing::duplicate_lines(code, anchor, cursor),
+            LineEdit::MoveUp => typing::move_lines(code, anchor, cursor, true),
+            LineEdit::MoveDown => typing::move_lines(code, anchor, cursor, false),
+            LineEdit::Delete => {
+                let cursor = typing::delete_lines(code, anchor, cursor);
+                (cursor, cursor)
+            }
+        })
+    }
+
+    // Change the code around the selection, which `edit` gives the new ends of
+    fn edit_selection(
+        &mut self,
+        ctx: &egui::Context,
+        id: Id,
+        edit: impl FnOnce(&mut String, usize, usize) -> (usize, usize),
+    ) -> bool {
+        let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+        let range = state.ccursor_range().unwrap_or_default();
+
+        let before = self.code.clone();
+        let (anchor, cursor) = edit(&mut self.code, range.secondary.index, range.primary.index);
+
+        state.set_ccursor_range(Some(CCursorRange {
+            primary: CCursor::new(cursor),
+            secondary: CCursor::new(anchor),
+        }));
+        state.store(ctx, id);
+        // lines moved past the top or bottom of the view are followed
+        let at = byte_index(&self.code, cursor);
+        ctx.data().insert_temp(id.with("scroll_to"), at..at);
+        // the palette may have had it
+        ctx.memory().request_focus(id);
+
+        self.code != before
+    }
+
+    /// Move the cursor to the bracket which pairs with the one next to it. A bracket without one
+    /// flashes red instead
+    pub fn jump_to_bracket(&self, ctx: &egui::Context, id: Id) {
+        let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+        let cursor = state.ccursor_range().unwrap_or_default().primary.index;
+
+        match brackets::find_match(&self.code, byte_index(&self.code, cursor)) {
+            Some(BracketMatch::Matched { partner, .. }) => {
+                let partner_index = char_index(&self.code, partner);
+                state.set_ccursor_range(Some(CCursorRange::one(CCursor::new(partner_index))));
+                state.store(ctx, id);
+                // it may be off screen
+                ctx.data()
+                    .insert_temp(id.with("scroll_to"), partner..partner + 1);
+            }
+            Some(BracketMatch::Unmatched(bracket)) => {
+                let now = ctx.input().time;
+                ctx.data()
+                    .insert_temp(id.with("unmatched_bracket"), (bracket, now));
+            }
+            None => (),
+        }
+
+        ctx.memory().request_focus(id);
+    }
+
+    /// The byte offset of the editor's cursor
+    pub fn cursor(&self, ctx: &egui::Context, id: Id) -> usize {
+        let state = TextEdit::load_state(ctx, id).unwrap_or_default();
+        let cursor = state.ccursor_range().unwrap_or_default().primary.index;
+
+        byte_index(&self.code, cursor)
+    }
+
+    /// What the editor wants to ask rust-analyzer since this was last called
+    pub fn take_lsp_asks(&mut self) -> LspAsks {
+        std::mem::take(&mut self.lsp.asks)
+    }
+
+    /// Put what rust-analyzer would complete at the byte offset `at` first in the popup, when the
+    /// word being typed still ends there
+    pub fn offer_completions(&mut self, at: usize, names: Vec<String>) {
+        let completions = &mut self.completions;
+        if completions.asked != Some(at) {
+            return;
+        }
+        completions.asked = None;
+
+        let start = byte_index(&self.code, completions.typed.start);
+        let Some(typed) = self.code.get(start..at) else {
+            return;
+        };
+        let query = typed.strip_prefix("r#").unwrap_or(typed);
+        let mut items = names
+            .into_iter()
+            .filter(|name| name != typed && fuzzy::score(query, name).is_some())
+            .collect::<Vec<_>>();
+        for word in completions.items.drain(..) {
+            if !items.contains(&word) {
+                items.push(word);
+            }
+        }
+        items.truncate(MAX_COMPLETIONS);
+
+        if completions.selected >= items.len() {
+            completions.selected = 0;
+        }
+        completions.items = items;
+    }
+
+    /// Show what rust-analyzer said about the word starting at the byte offset `at`, while the
+    /// pointer is still over it
+    pub fn show_hover(&mut self, at: usize, text: String) {
+        if self.lsp.hovered == Some(at) {
+            self.lsp.hover = Some(text);
+        }
+    }
+
+    /// Add a cursor selecting the next place the selection is in the code, or select the word at
+    /// the cursor when nothing is selected
+    pub fn select_next_occurrence(&self, ctx: &egui::Context, id: Id) {
+        let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+        let mut selections = extra_cursors(ctx, id, &self.code);
+        selections.push(primary_selection(&state));
+
+        let mut selections = multi_cursor::select_next(&self.code, &selections);
+        let primary = selections.pop().unwrap();
+        state.set_ccursor_range(Some(CCursorRange {
+            primary: CCursor::new(primary.cursor),
+            secondary: CCursor::new(primary.anchor),
+        }));
+        state.store(ctx, id);
+        ctx.data().insert_temp(id.with("extra_cursors"), selections);
+
+        // it may be off screen
+        let range = primary.range();
+        let bytes = byte_index(&self.code, range.start)..byte_index(&self.code, range.end);
+        ctx.data().insert_temp(id.with("scroll_to"), bytes);
+    }
+}
+
+pub struct CodeEditorResponse {
+    pub scroll_offset: Vec2,
+    // the code was edited
+    pub changed: bool,
+    // `:w` or `:q` was typed in vim mode
+    pub save: bool,
+    pub close: bool,
+}
+
+// the char index of a byte offset, rounded down to the char it's in
+fn char_index(text: &str, byte: usize) -> usize {
+    let mut byte = byte.min(text.len());
+    while !text.is_char_boundary(byte) {
+        byte -= 1;
+    }
+
+    text[..byte].chars().count()
+}
+
+// The cursor when the editor has focus and nothing is selected
+fn plain_cursor(ui: &egui::Ui, id: Id, state: Option<TextEditState>) -> Option<usize> {
+    let range = state?.ccursor_range()?;
+
+    (ui.memory().has_focus(id) && range.primary == range.secondary).then_some(range.primary.index)
+}
+
+// The cursors other than the text edit's own, which is the one added last. The code may have been
+// changed from outside the editor since they were placed, so they're kept inside it
+fn extra_cursors(ctx: &egui::Context, id: Id, code: &str) -> Vec<Selection> {
+    let len = code.chars().count();
+    let extras = ctx
+        .data()
+        .get_temp::<Vec<Selection>>(id.with("extra_cursors"));
+
+    extras
+        .unwrap_or_default()
+        .into_iter()
+        .map(|selection| Selection::new(selection.anchor.min(len), selection.cursor.min(len)))
+        .collect()
+}
+
+fn primary_selection(state: &TextEditState) -> Selection {
+    let range = state.ccursor_range().unwrap_or_default();
+    Selection::new(range.secondary.index, range.primary.index)
+}
+
+// the byte offset of a char index, the end of the text when it's past the last char
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(byte, _)| byte)
+}
+
+// A row of the code as it was laid out. Rows wrapped onto from the one before have no line
+struct GutterRow {
+    top: f32,
+    bottom: f32,
+    line: Option<usize>,
+}
+
+// What was clicked in the gutter
+enum GutterAction {
+    SelectLine(usize),
+    // the index of the diagnostic whose suggestion to apply
+    Fix(usize),
+    ToggleFold(Fold),
+}
+
+// The column left of the code, with its line numbers, the lightbulbs of the diagnostics which
+// can be fixed and the markers of the blocks which can be folded. It's outside the scroll area,
+// so it's given where each row of the code ended up
+struct Gutter {
+    rect: Rect,
+    // the line numbers are right-aligned in this much of it, the glyphs go right of them
+    numbers_width: f32,
+}
+
+impl Gutter {
+    const FONT_SIZE: f32 = 12.0;
+
+    // A gutter along the left of `rect`, wide enough for the numbers of `line_count` lines
+    fn new(ui: &egui::Ui, rect: Rect, line_count: usize) -> Self {
+        let digits = line_count.to_string().len().max(MIN_NUMBER_DIGITS);
+        let char_width = ui
+            .fonts()
+            .glyph_width(&FontId::monospace(Self::FONT_SIZE), '0');
+        let numbers_width = digits as f32 * char_width + 4.0;
+
+        let mut rect = rect;
+        rect.set_width(numbers_width + 2.0 * GLYPH_WIDTH);
+
+        Self {
+            rect,
+            numbers_width,
+        }
+    }
+
+    fn width(&self) -> f32 {
+        self.rect.width()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn show(
+        &self,
+        ui: &egui::Ui,
+        id: Id,
+        rows: &[GutterRow],
+        lightbulbs: &[(f32, usize)],
+        diagnostics: &[Diagnostic],
+        foldable: &[Fold],
+        folded: &[Fold],
+    ) -> Option<GutterAction> {
+        let mut action = None;
+
+        // rows scrolled partly out of view are cut off at its edges
+        let painter = ui.painter_at(self.rect);
+        let font_id = FontId::monospace(Self::FONT_SIZE);
+        let color = ui.visuals().weak_text_color();
+        let numbers_right = self.rect.left() + self.numbers_width - 4.0;
+
+        for row in rows {
+            if let Some(line) = row.line {
+                if row.bottom >= self.rect.top() && row.top <= self.rect.bottom() {
+                    painter.text(
+                        pos2(numbers_right, (row.top + row.bottom) / 2.0),
+                        Align2::RIGHT_CENTER,
+                        (line + 1).to_string(),
+                        font_id.clone(),
+                        color,
+                    );
+                }
+            }
+        }
+
+        let mut numbers_rect = self.rect;
+        numbers_rect.set_width(self.numbers_width);
+        let response = ui.interact(numbers_rect, id.with("line_numbers"), Sense::click());
+
+        if response.clicked() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                // clicking a wrapped row selects the line it's part of
+                let line = rows
+                    .iter()
+                    .take_while(|row| row.top <= pointer.y)
+                    .filter_map(|row| row.line)
+                    .last();
+                action = line.map(GutterAction::SelectLine);
+            }
+        }
+
+        for &(y, index) in lightbulbs {
+            if y < self.rect.top() || y > self.rect.bottom() {
+                continue;
+            }
+
+            let Some(suggestion) = &diagnostics[index].suggestion else {
+                continue;
+            };
+
+            let row_height = ui.fonts().row_height(&font_id);
+            let rect = Rect::from_center_size(
+                pos2(self.rect.right() - GLYPH_WIDTH * 1.5, y),
+                vec2(GLYPH_WIDTH, row_height),
+            );
+            let response = ui
+                .interact(rect, id.with(("suggestion", index)), Sense::click())
+                .on_hover_text(format!("Fix: {}", suggestion.message));
+
+            let color = if response.hovered() {
+                ui.visuals().strong_text_color()
+            } else {
+                ui.visuals().warn_fg_color
+            };
+            ui.painter().text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                "💡",
+                FontId::proportional(12.0),
+                color,
+            );
+
+            if response.clicked() {
+                action = Some(GutterAction::Fix(index));
+            }
+        }
+
+        let mut folds_rect = self.rect;
+        folds_rect.set_left(self.rect.right() - GLYPH_WIDTH);
+        let response = ui.interact(folds_rect, id.with("fold_markers"), Sense::click());
+        let center_x = folds_rect.center().x;
+
+        for row in rows {
+            let Some(region) = row
+                .line
+                .and_then(|line| foldable.iter().find(|region| region.start == line))
+            else {
+                continue;
+            };
+            if row.bottom < self.rect.top() || row.top > self.rect.bottom() {
+                continue;
+            }
+
+            let is_folded = folded.iter().any(|fold| fold.start == region.start);
+            let y = (row.top + row.bottom) / 2.0;
+            // pointing at the code when it's folded, down along it when it isn't
+            let points = if is_folded {
+                vec![
+                    pos2(center_x - 2.0, y - 4.0),
+                    pos2(center_x + 2.0, y),
+                    pos2(center_x - 2.0, y + 4.0),
+                ]
+            } else {
+                vec![
+                    pos2(center_x - 4.0, y - 2.0),
+                    pos2(center_x + 4.0, y - 2.0),
+                    pos2(center_x, y + 2.0),
+                ]
+            };
+            let color = if is_folded {
+                ui.visuals().text_color()
+            } else {
+                color
+            };
+            painter.add(Shape::convex_polygon(points, color, Stroke::NONE));
+
+            let clicked = response.clicked()
+                && response
+                    .interact_pointer_pos()
+                    .is_some_and(|pointer| pointer.y >= row.top && pointer.y <= row.bottom);
+            if clicked {
+                action = Some(GutterAction::ToggleFold(*region));
+            }
+        }
+
+        action
+    }
+}
+
+// Select a whole line of the editor, its newline included
+fn select_line(ctx: &egui::Context, id: Id, code: &str, line: usize) {
+    let mut lines = code.split_inclusive('\n').map(|line| line.chars().count());
+    let start: usize = lines.by_ref().take(line).sum();
+    let end = start + lines.next().unwrap_or_default();
+
+    let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+    state.set_ccursor_range(Some(CCursorRange::two(
+        CCursor::new(start),
+        CCursor::new(end),
+    )));
+    state.store(ctx, id);
+
+    ctx.memory().request_focus(id);
+}
+
+// Put a selection into the text edit's state
+fn store_selection(ctx: &egui::Context, id: Id, selection: Selection) {
+    let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+    state.set_ccursor_range(Some(CCursorRange::two(
+        CCursor::new(selection.anchor),
+        CCursor::new(selection.cursor),
+    )));
+    state.store(ctx, id);
+}
+
+// Dragging the selection moves it to where it's dropped, or copies it there with Ctrl held. The
+// press which starts the drag is taken by the text edit as a click, so the selection is put back,
+// and the text edit doesn't take the pointer until the drop. Returns the dropped text's selection
+// once it's in the code
+fn drag_text(
+    ui: &egui::Ui,
+    id: Id,
+    output: &TextEditOutput,
+    code: &mut String,
+    selection_before: Selection,
+    dragging: Option<Range<usize>>,
+) -> Option<Selection> {
+    let pointer = ui.input().pointer.interact_pos();
+    let at = |pos: Pos2| {
+        let cursor = output.galley.cursor_from_pos(pos - output.text_draw_pos);
+        cursor.ccursor.index
+    };
+
+    let Some(range) = dragging else {
+        let input = ui.input();
+        let pressed = input.pointer.any_pressed()
+            && input.pointer.primary_down()
+            && !input.modifiers.shift
+            && !input.modifiers.alt
+            && output.response.hovered();
+        drop(input);
+
+        let range = selection_before.range();
+        if pressed && pointer.is_some_and(|pos| range.contains(&at(pos))) {
+            store_selection(ui.ctx(), id, selection_before);
+            ui.data().insert_temp(id.with("text_drag"), range);
+        }
+        return None;
+    };
+
+    let (down, copy) = {
+        let input = ui.input();
+        (input.pointer.primary_down(), input.modifiers.command)
+    };
+    let target = pointer.map(at);
+    if down {
+        // a caret where it'd be dropped
+        if let Some(target) = target {
+            let visuals = ui.visuals();
+            let stroke = Stroke::new(visuals.text_cursor_width, visuals.selection.stroke.color);
+            let cursor = output.galley.from_ccursor(CCursor::new(target));
+            let rect = output
+                .galley
+                .pos_from_cursor(&cursor)
+                .translate(output.text_draw_pos.to_vec2());
+            ui.painter()
+                .line_segment([rect.center_top(), rect.center_bottom()], stroke);
+        }
+        ui.output().cursor_icon = if copy {
+            CursorIcon::Copy
+        } else {
+            CursorIcon::Grabbing
+        };
+        return None;
+    }
+
+    ui.data().remove::<Range<usize>>(id.with("text_drag"));
+    ui.memory().request_focus(id);
+    let target = target?;
+
+    // dropped back onto itself it was only a click
+    if (range.start..=range.end).contains(&target) {
+        store_selection(ui.ctx(), id, Selection::at(target));
+        return None;
+    }
+
+    let bytes = byte_index(code, range.start)..byte_index(code, range.end);
+    let text = code[bytes.clone()].to_string();
+    let mut insert_at = target;
+    if !copy {
+        code.replace_range(bytes, "");
+        if target > range.end {
+            insert_at -= range.len();
+        }
+    }
+    code.insert_str(byte_index(code, insert_at), &text);
+
+    let selection = Selection::new(insert_at, insert_at + range.len());
+    store_selection(ui.ctx(), id, selection);
+    Some(selection)
+}
+
+// What was clicked in the find bar's replace row
+enum FindAction {
+    Replace,
+    ReplaceAll,
+}
+
+fn query(search: &EditorSearch) -> Query<'_> {
+    Query {
+        text: &search.query,
+        case_sensitive: search.case_sensitive,
+        regex: search.regex,
+    }
+}
+
+// The find bar over the editor, with the replace row under it when replacing. Enter goes to the
+// next match, and replaces it in the replace box
+fn find_bar(
+    ui: &mut egui::Ui,
+    id: Id,
+    search: &mut EditorSearch,
+    match_count: usize,
+    invalid: bool,
+    read_only: bool,
+) -> Option<FindAction> {
+    let mut action = None;
+
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            TextEdit::singleline(&mut search.query)
+                .hint_text("Find")
+                .desired_width(200.0),
+        );
+
+        if search.focus {
+            response.request_focus();
+            search.focus = false;
+        }
+
+        let (enter, escape, shift) = {
+            let input = ui.input();
+            (
+                input.key_pressed(Key::Enter),
+                input.key_pressed(Key::Escape),
+                input.modifiers.shift,
+            )
+        };
+
+        let enter = response.lost_focus() && enter;
+        if enter {
+            // keep the focus so you can keep pressing enter
+            response.request_focus();
+        }
+
+        let case_sensitive = ui
+            .toggle_value(&mut search.case_sensitive, "Aa")
+            .on_hover_text("Match case");
+        let regex = ui
+            .toggle_value(&mut search.regex, ".*")
+            .on_hover_text("Use a regex, replacements can use its captures like $1");
+
+        if response.changed() || case_sensitive.changed() || regex.changed() {
+            search.current = 0;
+            search.scroll_to_current = true;
+        }
+
+        if invalid {
+            ui.colored_label(ui.visuals().error_fg_color, "Invalid regex");
+        } else if match_count == 0 {
+            ui.label("No results");
+        } else {
+            ui.label(format!("{}/{match_count}", search.current + 1));
+        }
 
-        let frame_rect = ui.max_rect().shrink(6.0);
-        let code_rect = frame_rect.shrink(5.0);
+        let previous = ui
+            .button("⏶")
+            .on_hover_text("Previous match (Shift+Enter)")
+            .clicked();
+        let next = ui.button("⏷").on_hover_text("Next match (Enter)").clicked();
+
+        if match_count > 0 {
+            if previous || (enter && shift) {
+                search.current = (search.current + match_count - 1) % match_count;
+                search.scroll_to_current = true;
+            } else if next || enter {
+                search.current = (search.current + 1) % match_count;
+                search.scroll_to_current = true;
+            }
+        }
+
+        if !read_only {
+            ui.toggle_value(&mut search.replace, "Replace");
+        }
+
+        let close = ui.button("🗙").on_hover_text("Close (Escape)").clicked();
+        if close || (response.lost_focus() && escape) {
+            search.open = false;
+            ui.memory().request_focus(id);
+        }
+    });
+
+    if search.open && search.replace && !read_only {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                TextEdit::singleline(&mut search.replacement)
+                    .hint_text("Replace")
+                    .desired_width(200.0),
+            );
+
+            if response.lost_focus() && ui.input().key_pressed(Key::Enter) {
+                response.request_focus();
+                action = Some(FindAction::Replace);
+            }
+
+            let any = match_count > 0;
+            if ui.add_enabled(any, Button::new("Replace")).clicked() {
+                action = Some(FindAction::Replace);
+            }
+            if ui.add_enabled(any, Button::new("Replace all")).clicked() {
+                action = Some(FindAction::ReplaceAll);
+            }
+        });
+    }
+
+    action
+}
+
+// Typing is handled here before the text edit sees it, for the indentation, the brackets and the
+// snippets. Tab expands the snippet before a plain cursor and then goes through its stops, with
+// Shift+Tab going back. Otherwise they indent and dedent the selected lines. The rest only
+// happens with a plain cursor, typing over a selection is left to the text edit. Returns whether
+// the code changed
+fn handle_typing(
+    ui: &egui::Ui,
+    id: Id,
+    code: &mut String,
+    settings: &Editor,
+    indent: &str,
+    snippets: &[Snippet],
+    snippet: &mut Option<Expansion>,
+) -> bool {
+    if !ui.memory().has_focus(id) {
+        return false;
+    }
+
+    let ctx = ui.ctx();
+    let Some(mut state) = TextEdit::load_state(ctx, id) else {
+        return false;
+    };
+    let Some(range) = state.ccursor_range() else {
+        return false;
+    };
+
+    let (mut anchor, mut cursor) = (range.secondary.index, range.primary.index);
+    let mut changed = false;
+
+    ctx.input_mut().events.retain(|event| {
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+
+        match event {
+            Event::Key {
+                key: Key::Tab,
+                pressed: true,
+                modifiers,
+            } if modifiers.shift_only() => match snippet {
+                Some(expansion) => {
+                    expansion.previous();
+                    Range {
+                        start: anchor,
+                        end: cursor,
+                    } = expansion.selection();
+                }
+                None => (anchor, cursor) = typing::dedent_lines(code, anchor, cursor, indent),
+            },
+            Event::Key {
+                key: Key::Tab,
+                pressed: true,
+                modifiers,
+            } if modifiers.is_none() => {
+                if let Some(expansion) = snippet {
+                    expansion.next();
+                } else if start == end {
+                    *snippet = Expansion::expand(code, cursor, snippets, indent);
+                }
+                if let Some(expansion) = snippet.take() {
+                    Range {
+                        start: anchor,
+                        end: cursor,
+                    } = expansion.selection();
+                    // at $0 there's nothing left to go through
+                    if !expansion.finished() {
+                        *snippet = Some(expansion);
+                    }
+                    changed = true;
+                    return false;
+                }
+
+                let lines = code
+                    .chars()
+                    .skip(start)
+                    .take(end - start)
+                    .any(|c| c == '\n');
+
+                if lines {
+                    (anchor, cursor) = typing::indent_lines(code, anchor, cursor, indent);
+                } else {
+                    code.delete_char_range(start..end);
+                    cursor = start + code.insert_text(indent, start);
+                    anchor = cursor;
+                }
+            }
+            Event::Key {
+                key: Key::Enter,
+                pressed: true,
+                modifiers,
+            } if modifiers.is_none() && start == end => {
+                cursor = typing::enter(code, cursor, indent);
+                anchor = cursor;
+            }
+            Event::Text(text) if start == end => {
+                for typed in text.chars() {
+                    cursor = typing::type_char(
+                        code,
+                        cursor,
+                        typed,
+                        indent,
+                        settings.auto_close_brackets,
+                    );
+                }
+                anchor = cursor;
+            }
+            _ => return true,
+        }
+
+        changed = true;
+        false
+    });
+
+    if changed {
+        state.set_ccursor_range(Some(CCursorRange {
+            primary: CCursor::new(cursor),
+            secondary: CCursor::new(anchor),
+        }));
+        state.store(ctx, id);
+    }
+
+    changed
+}
+
+// Whether the frame's input types an identifier, and whether it backspaces
+fn typed_or_deleted(events: &[Event]) -> (bool, bool) {
+    let typed = events.iter().any(|event| {
+        matches!(event, Event::Text(text) if text.chars().all(|c| c.is_alphanumeric() || c == '_'))
+    });
+    let deleted = events.iter().any(|event| {
+        matches!(
+            event,
+            Event::Key {
+                key: Key::Backspace,
+                pressed: true,
+                ..
+            }
+        )
+    });
+
+    (typed, deleted)
+}
+
+// While the completion popup is open its keys are taken before the text edit sees them: the
+// arrows pick a word, Tab and Enter type it in place of what was typed and Escape closes the
+// popup. Returns whether the code changed
+fn handle_completion(
+    ui: &egui::Ui,
+    id: Id,
+    code: &mut String,
+    completions: &mut Completions,
+) -> bool {
+    if completions.items.is_empty() || !ui.memory().has_focus(id) {
+        return false;
+    }
+
+    let count = completions.items.len();
+    let mut open = true;
+    let mut picked = None;
+    ui.ctx().input_mut().events.retain(|event| {
+        let Event::Key {
+            key,
+            pressed: true,
+            modifiers,
+        } = event
+        else {
+            return true;
+        };
+        if !open || !modifiers.is_none() {
+            return true;
+        }
+
+        match key {
+            Key::ArrowDown => completions.selected = (completions.selected + 1) % count,
+            Key::ArrowUp => completions.selected = (completions.selected + count - 1) % count,
+            Key::Tab | Key::Enter => {
+                picked = Some(completions.selected);
+                open = false;
+            }
+            Key::Escape => open = false,
+            _ => return true,
+        }
+        false
+    });
+
+    let picked = picked.map(|index| completions.items[index].clone());
+    if !open {
+        completions.items.clear();
+    }
+    let Some(word) = picked else {
+        return false;
+    };
+    let Some(mut state) = TextEdit::load_state(ui.ctx(), id) else {
+        return false;
+    };
+
+    let typed = completions.typed.clone();
+    code.delete_char_range(typed.clone());
+    let cursor = typed.start + code.insert_text(&word, typed.start);
+    state.set_ccursor_range(Some(CCursorRange::one(CCursor::new(cursor))));
+    state.store(ui.ctx(), id);
+
+    true
+}
+
+// Open the popup when an identifier is typed, and keep its words up to date while it's typed on
+// or backspaced. Anything else, like the cursor moving, closes it
+fn update_completions(
+    completions: &mut Completions,
+    code: &str,
+    cursor: Option<usize>,
+    typed: bool,
+    deleted: bool,
+    changed: bool,
+    lsp: bool,
+) {
+    let open = !completions.items.is_empty();
+    if !(typed || deleted && open) {
+        if changed || cursor != Some(completions.typed.end) {
+            completions.items.clear();
+            completions.asked = None;
+        }
+        return;
+    }
+
+    completions.items.clear();
+    completions.asked = None;
+    let Some(cursor) = cursor else {
+        return;
+    };
+    completions.words.update(code);
+    let Some(range) = completions.words.typing(code, byte_index(code, cursor)) else {
+        return;
+    };
+
+    completions.items = completions
+        .words
+        .complete(code, range.clone(), MAX_COMPLETIONS);
+    completions.selected = 0;
+    completions.typed = char_index(code, range.start)..cursor;
+    completions.asked = lsp.then_some(range.end);
+}
+
+// While Ctrl is held over a word, rust-analyzer is asked about it, and what it said is shown by
+// the pointer once it answers
+fn show_lsp_hover(ui: &egui::Ui, id: Id, output: &TextEditOutput, code: &str, lsp: &mut LspState) {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let pointer = ui.input().pointer.hover_pos();
+    let held = ui.input().modifiers.command && output.response.hovered();
+
+    let word = pointer.filter(|_| held).and_then(|pos| {
+        let cursor = output.galley.cursor_from_pos(pos - output.text_draw_pos);
+        let at = byte_index(code, cursor.ccursor.index);
+        let start = code[..at]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_ident(*c))
+            .last()
+            .map_or(at, |(start, _)| start);
+        code[start..].starts_with(is_ident).then_some(start)
+    });
+    let Some(start) = word else {
+        lsp.hovered = None;
+        lsp.hover = None;
+        return;
+    };
+
+    if lsp.hovered != Some(start) {
+        lsp.hovered = Some(start);
+        lsp.hover = None;
+        lsp.asks.hover = Some(start);
+    }
+    if let Some(text) = &lsp.hover {
+        egui::show_tooltip_at_pointer(ui.ctx(), id.with("lsp_hover"), |ui| {
+            ui.label(RichText::new(text).monospace());
+        });
+    }
+}
 
+// The popup under the start of the word being typed. It can't be clicked, so the editor keeps
+// the focus
+fn show_completions(ui: &egui::Ui, id: Id, output: &TextEditOutput, completions: &Completions) {
+    if completions.items.is_empty() {
+        return;
+    }
+
+    let galley = &output.galley;
+    let start = galley.pos_from_cursor(&galley.from_ccursor(CCursor::new(completions.typed.start)));
+    let pos = start.left_bottom() + output.text_draw_pos.to_vec2();
+    // scrolled out of view
+    if !ui.clip_rect().contains(pos) {
+        return;
+    }
+
+    egui::Area::new(id.with("completions"))
+        .order(Order::Foreground)
+        .fixed_pos(pos)
+        .interactable(false)
+        .show(ui.ctx(), |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                for (index, word) in completions.items.iter().enumerate() {
+                    let text = RichText::new(word).monospace();
+                    ui.add(SelectableLabel::new(index == completions.selected, text));
+                }
+            });
+        });
+}
+
+// Run a key through vim, undoing and redoing with the editor's history. Returns what vim did and
+// whether the code changed
+fn vim_key(
+    vim: &mut Vim,
+    history: &mut History,
+    code: &mut String,
+    selection: &mut Selection,
+    input: vim::Input,
+) -> (vim::Outcome, bool) {
+    let old = code.clone();
+    let outcome = vim.key(code, selection, input);
+    let mut changed = *code != old;
+
+    let moved_to = match outcome {
+        vim::Outcome::Undo => history.undo(code),
+        vim::Outcome::Redo => history.redo(code),
+        _ => None,
+    };
+    if let Some(moved_to) = moved_to {
+        *selection = Selection::at(moved_to.cursor);
+        changed = true;
+    }
+
+    (outcome, changed)
+}
+
+// In vim mode keys go through vim first. The text edit only gets what's typed in insert mode,
+// and the keys vim doesn't use, like the arrows. Returns whether the code changed and the cursor
+// moved, and the `:w` or `:q` typed
+fn handle_vim(
+    ui: &egui::Ui,
+    id: Id,
+    code: &mut String,
+    vim: &mut Vim,
+    history: &mut History,
+) -> (bool, bool, Option<vim::Outcome>) {
+    if !ui.memory().has_focus(id) {
+        return (false, false, None);
+    }
+
+    let ctx = ui.ctx();
+    let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+    let before = primary_selection(&state);
+    let mut selection = before;
+    let mut changed = false;
+    let mut requested = None;
+
+    let events = std::mem::take(&mut ctx.input_mut().events);
+    let mut kept = vec![];
+    for event in events {
+        // typing in insert mode is the editor's own, as are the keys vim doesn't use
+        let typing = vim.mode == Mode::Insert && vim.command_line.is_none();
+        let inputs = match &event {
+            Event::Text(text) if !typing => text.chars().map(vim::Input::Char).collect(),
+            Event::Key {
+                key: Key::Escape,
+                pressed: true,
+                ..
+            } => vec![vim::Input::Escape],
+            Event::Key {
+                key: Key::R,
+                pressed: true,
+                modifiers,
+            } if modifiers.command_only() && !typing => vec![vim::Input::Redo],
+            Event::Key {
+                key: Key::Enter,
+                pressed: true,
+                ..
+            } if !typing => vec![vim::Input::Enter],
+            Event::Key {
+                key: Key::Backspace,
+                pressed: true,
+                ..
+            } if !typing => vec![vim::Input::Backspace],
+            // outside of insert mode the code is only changed through vim
+            Event::Key {
+                key: Key::Delete | Key::Tab | Key::Enter | Key::Backspace,
+                ..
+            }
+            | Event::Paste(_)
+            | Event::Cut
+                if !typing =>
+            {
+                vec![]
+            }
+            _ => {
+                kept.push(event);
+                continue;
+            }
+        };
+
+        for input in inputs {
+            let (outcome, edited) = vim_key(vim, history, code, &mut selection, input);
+            changed |= edited;
+
+            match (outcome, input) {
+                // a key which went into insert mode, with the rest of the text after it
+                (vim::Outcome::Typed, vim::Input::Char(c)) => {
+                    let at = selection.cursor;
+                    selection = Selection::at(at + code.insert_text(&c.to_string(), at));
+                    changed = true;
+                }
+                (vim::Outcome::Save | vim::Outcome::Close, _) => requested = Some(outcome),
+                _ => (),
+            }
+        }
+    }
+    ctx.input_mut().events = kept;
+
+    let moved = selection != before;
+    if moved {
+        state.set_ccursor_range(Some(CCursorRange {
+            primary: CCursor::new(selection.cursor),
+            secondary: CCursor::new(selection.anchor),
+        }));
+        state.store(ctx, id);
+    }
+
+    (changed, moved, requested)
+}
+
+// Vim mode's block cursor over the char it's on, kept in view when vim moved it
+fn show_vim_cursor(ui: &egui::Ui, id: Id, output: &TextEditOutput, moved: bool) {
+    let Some(range) = output.cursor_range else {
+        return;
+    };
+    if !ui.memory().has_focus(id) {
+        return;
+    }
+
+    let galley = &output.galley;
+    let start = galley.pos_from_cursor(&range.primary);
+    let next = galley.from_ccursor(range.primary.ccursor + 1);
+    let end = galley.pos_from_cursor(&next);
+    // at the end of a line there's no char, so it's as wide as a space
+    let width = if end.top() == start.top() && end.left() > start.left() {
+        end.left() - start.left()
+    } else {
+        ui.fonts().glyph_width(&FontId::monospace(12.0), ' ')
+    };
+
+    let rect = Rect::from_min_size(start.min, vec2(width, start.height()))
+        .translate(output.text_draw_pos.to_vec2());
+    ui.painter()
+        .rect_filled(rect, Rounding::none(), VIM_CURSOR_COLOR);
+    if moved {
+        ui.scroll_to_rect(rect, None);
+    }
+}
+
+// The mode vim is in down the editor's bottom right, and the `:` line or why the last one failed
+// along its bottom
+fn show_vim_status(ui: &egui::Ui, frame_rect: Rect, vim: &Vim) {
+    let painter = ui.painter();
+    let font_id = FontId::monospace(12.0);
+    let bottom = frame_rect.shrink(6.0).left_bottom();
+
+    let (message, color) = match (&vim.command_line, &vim.error) {
+        (Some(line), _) => (format!(":{line}"), ui.visuals().text_color()),
+        (None, Some(error)) => (error.clone(), ui.visuals().error_fg_color),
+        (None, None) => (String::new(), ui.visuals().text_color()),
+    };
+    if !message.is_empty() {
+        let galley = ui.fonts().layout_no_wrap(message, font_id.clone(), color);
+        let rect = Rect::from_min_size(bottom - vec2(0.0, galley.size().y), galley.size());
+        painter.rect_filled(rect.expand(2.0), Rounding::same(2.0), Color32::BLACK);
+        painter.galley(rect.min, galley);
+    }
+
+    let mode = format!("-- {} --", vim.mode.name());
+    let galley = ui
+        .fonts()
+        .layout_no_wrap(mode, font_id, ui.visuals().weak_text_color());
+    let right = frame_rect.shrink(6.0).right_bottom();
+    let rect = Rect::from_min_size(right - galley.size(), galley.size());
+    painter.rect_filled(rect.expand(2.0), Rounding::same(2.0), Color32::BLACK);
+    painter.galley(rect.min, galley);
+}
+
+// What the frame's input edits the code with, to tell which undo step it goes in
+fn edit_kind(events: &[Event]) -> EditKind {
+    let mut kinds = events.iter().filter_map(|event| match event {
+        Event::Text(text) if !text.contains('\n') => Some(EditKind::Typing),
+        Event::Key {
+            key: Key::Backspace | Key::Delete,
+            pressed: true,
+            modifiers,
+        } if modifiers.is_none() => Some(EditKind::Deleting),
+        Event::Text(_) | Event::Paste(_) | Event::Cut => Some(EditKind::Other),
+        Event::Key {
+            key: Key::Enter | Key::Tab,
+            pressed: true,
+            ..
+        } => Some(EditKind::Other),
+        _ => None,
+    });
+
+    let first = kinds.next().unwrap_or(EditKind::Other);
+    if kinds.all(|kind| kind == first) {
+        first
+    } else {
+        EditKind::Other
+    }
+}
+
+// Ctrl+Z and Ctrl+Shift+Z, or Ctrl+Y, go through the editor's own history instead of the text
+// edit's
+fn handle_undo(ui: &egui::Ui, id: Id, code: &mut String, history: &mut History) -> bool {
+    if !ui.memory().has_focus(id) {
+        return false;
+    }
+
+    let mut selection = None;
+    ui.ctx().input_mut().events.retain(|event| match event {
+        Event::Key {
+            key: key @ (Key::Z | Key::Y),
+            pressed: true,
+            modifiers,
+        } if modifiers.command => {
+            let redo = *key == Key::Y || modifiers.shift;
+            let moved_to = if redo {
+                history.redo(code)
+            } else {
+                history.undo(code)
+            };
+            selection = moved_to.or(selection);
+
+            false
+        }
+        _ => true,
+    });
+
+    let Some(selection) = selection else {
+        return false;
+    };
+    let mut state = TextEdit::load_state(ui.ctx(), id).unwrap_or_default();
+    state.set_ccursor_range(Some(CCursorRange {
+        primary: CCursor::new(selection.cursor),
+        secondary: CCursor::new(selection.anchor),
+    }));
+    state.store(ui.ctx(), id);
+
+    true
+}
+
+// With more than one cursor, typing and moving happen at all of them before the text edit sees
+// the keys, it only knows about its own cursor. Escape, and the keys which can't be done at all
+// of them, like moving up a line or undoing, go back to just that one
+fn handle_cursors(ui: &egui::Ui, id: Id, code: &mut String, indent: &str, read_only: bool) -> bool {
+    let ctx = ui.ctx();
+    let extras = extra_cursors(ctx, id, code);
+    if extras.is_empty() || !ui.memory().has_focus(id) {
+        return false;
+    }
+    let Some(mut state) = TextEdit::load_state(ctx, id) else {
+        return false;
+    };
+
+    let mut selections = extras;
+    selections.push(primary_selection(&state));
+    let mut changed = false;
+    let mut collapse = false;
+    let mut copied = None;
+
+    ctx.input_mut().events.retain(|event| {
+        if collapse {
+            return true;
+        }
+
+        let edit = match event {
+            Event::Text(text) | Event::Paste(text) => Edit::Insert(text),
+            Event::Copy | Event::Cut => {
+                copied = Some(multi_cursor::selected_text(code, &selections));
+                if matches!(event, Event::Copy) {
+                    return false;
+                }
+                Edit::Insert("")
+            }
+            Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+            } => match key {
+                Key::ArrowLeft | Key::ArrowRight | Key::Home | Key::End
+                    if !modifiers.alt && !modifiers.command =>
+                {
+                    let motion = match key {
+                        Key::ArrowLeft => Motion::Left,
+                        Key::ArrowRight => Motion::Right,
+                        Key::Home => Motion::Home,
+                        _ => Motion::End,
+                    };
+                    selections =
+                        multi_cursor::move_cursors(code, &selections, motion, modifiers.shift);
+                    return false;
+                }
+                Key::Escape if modifiers.is_none() => {
+                    collapse = true;
+                    return false;
+                }
+                Key::Enter if modifiers.is_none() => Edit::Enter,
+                Key::Tab if modifiers.is_none() => Edit::Insert(indent),
+                Key::Backspace if modifiers.is_none() => Edit::Backspace,
+                Key::Delete if modifiers.is_none() => Edit::Delete,
+                Key::ArrowUp | Key::ArrowDown | Key::PageUp | Key::PageDown | Key::Tab => {
+                    collapse = true;
+                    return true;
+                }
+                Key::Z | Key::Y if modifiers.command => {
+                    collapse = true;
+                    return true;
+                }
+                _ => return true,
+            },
+            _ => return true,
+        };
+
+        if !read_only {
+            selections = multi_cursor::edit(code, &selections, edit);
+            changed = true;
+        }
+        false
+    });
+
+    if let Some(copied) = copied.filter(|copied| !copied.is_empty()) {
+        ctx.output().copied_text = copied;
+    }
+
+    let primary = selections.pop().unwrap();
+    if collapse {
+        selections.clear();
+    }
+    state.set_ccursor_range(Some(CCursorRange {
+        primary: CCursor::new(primary.cursor),
+        secondary: CCursor::new(primary.anchor),
+    }));
+    state.store(ctx, id);
+    ctx.data().insert_temp(id.with("extra_cursors"), selections);
+
+    changed
+}
+
+// Draw the cursors other than the text edit's own. Alt clicking adds one where its cursor was
+// before the click, a plain click leaves just its own
+fn show_extra_cursors(
+    ui: &egui::Ui,
+    id: Id,
+    output: &TextEditOutput,
+    extras: &[Selection],
+    primary_before: Option<Selection>,
+) {
+    if output.response.hovered() && ui.input().pointer.any_pressed() {
+        let mut extras = extras.to_vec();
+        match primary_before {
+            Some(primary) if ui.input().modifiers.alt => extras.push(primary),
+            _ => extras.clear(),
+        }
+        ui.data().insert_temp(id.with("extra_cursors"), extras);
+    }
+
+    if !ui.memory().has_focus(id) {
+        return;
+    }
+
+    let visuals = ui.visuals();
+    let stroke = Stroke::new(visuals.text_cursor_width, visuals.selection.stroke.color);
+    for selection in extras {
+        let cursor = output.galley.from_ccursor(CCursor::new(selection.cursor));
+        let rect = output
+            .galley
+            .pos_from_cursor(&cursor)
+            .translate(output.text_draw_pos.to_vec2())
+            .expand(1.5);
+
+        ui.painter()
+            .line_segment([rect.center_top(), rect.center_bottom()], stroke);
+    }
+}
+
+// Draw a dot over each space and an arrow over each tab, on top of the laid out code so they
+// take up just the room the whitespace already does
+fn show_whitespace(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    show: ShowWhitespace,
+    extras: &[Selection],
+) {
+    let selected = match show {
+        ShowWhitespace::Off => return,
+        ShowWhitespace::Selection => {
+            let primary = output.cursor_range.map(|range| {
+                Selection::new(range.secondary.ccursor.index, range.primary.ccursor.index)
+            });
+            let ranges = extras.iter().chain(&primary).map(Selection::range);
+            Some(ranges.filter(|range| !range.is_empty()).collect::<Vec<_>>())
+        }
+        ShowWhitespace::All => None,
+    };
+    if selected.as_ref().is_some_and(Vec::is_empty) {
+        return;
+    }
+
+    let painter = ui.painter();
+    let origin = output.text_draw_pos.to_vec2();
+    let clip = ui.clip_rect();
+    let stroke = Stroke::new(1.0, WHITESPACE_COLOR);
+    let mut row_start = 0;
+
+    for row in &output.galley.rows {
+        let row_rect = row.rect.translate(origin);
+        let chars = row_start..row_start + row.char_count_excluding_newline();
+        row_start += row.char_count_including_newline();
+
+        // folded away rows have no height
+        if row_rect.height() <= 0.0 || !row_rect.intersects(clip) {
+            continue;
+        }
+
+        for (index, glyph) in chars.zip(&row.glyphs) {
+            let shown = selected
+                .as_ref()
+                .is_none_or(|ranges| ranges.iter().any(|range| range.contains(&index)));
+            if !shown || (glyph.chr != ' ' && glyph.chr != '\t') {
+                continue;
+            }
+
+            let left = origin.x + glyph.pos.x;
+            let center = pos2(left + glyph.size.x / 2.0, row_rect.center().y);
+            if glyph.chr == ' ' {
+                painter.circle_filled(center, 1.0, WHITESPACE_COLOR);
+            } else {
+                let (start, end) = (
+                    pos2(left + 2.0, center.y),
+                    pos2(left + glyph.size.x - 2.0, center.y),
+                );
+                painter.line_segment([start, end], stroke);
+                painter.line_segment([end, end + vec2(-3.0, -3.0)], stroke);
+                painter.line_segment([end, end + vec2(-3.0, 3.0)], stroke);
+            }
+        }
+    }
+}
+
+// A line down each level of indentation, left of the code. Rows wrapped onto from the one before
+// don't get them, so they only run down the lines as they're written
+fn show_indent_guides(ui: &egui::Ui, output: &TextEditOutput, code: &str, indent: Indent) {
+    let level_width = if indent.with_tabs {
+        egui::epaint::text::TAB_SIZE
+    } else {
+        indent.width.max(1)
+    };
+    let levels = indent_guides::levels(code, level_width);
+    let cursor_line = output
+        .cursor_range
+        .map(|range| line_at(code, range.primary.ccursor.index));
+    let active = cursor_line.and_then(|line| indent_guides::active(&levels, line));
+
+    let painter = ui.painter();
+    let origin = output.text_draw_pos;
+    let clip = ui.clip_rect();
+    let level_x = level_width as f32 * ui.fonts().glyph_width(&FontId::monospace(12.0), ' ');
+    let mut line = 0;
+    let mut starts_line = true;
+
+    for row in &output.galley.rows {
+        let row_rect = row.rect.translate(origin.to_vec2());
+        let (row_line, row_starts_line) = (line, starts_line);
+        starts_line = row.ends_with_newline;
+        if row.ends_with_newline {
+            line += 1;
+        }
+
+        if !row_starts_line || row_rect.height() <= 0.0 || !row_rect.intersects(clip) {
+            continue;
+        }
+
+        for level in 0..levels.get(row_line).copied().unwrap_or(0) {
+            let is_active = active
+                .as_ref()
+                .is_some_and(|(active, lines)| *active == level && lines.contains(&row_line));
+            let color = if is_active {
+                ACTIVE_GUIDE_COLOR
+            } else {
+                GUIDE_COLOR
+            };
+
+            let x = (origin.x + level as f32 * level_x).round() + 0.5;
+            painter.line_segment(
+                [pos2(x, row_rect.top()), pos2(x, row_rect.bottom())],
+                Stroke::new(1.0, color),
+            );
+        }
+    }
+}
+
+// Hide the rows of the folded lines, squashing them to nothing and moving the ones below up into
+// their place. Their chars are still there, so the text edit's cursors count them like before
+fn fold_rows(galley: &mut Galley, folded: &[Fold]) {
+    let mut line = 0;
+    let mut shift = 0.0;
+
+    for row in &mut galley.rows {
+        let row_line = line;
+        if row.ends_with_newline {
+            line += 1;
+        }
+
+        if folded.iter().any(|fold| fold.hides(row_line)) {
+            let top = row.rect.top() - shift;
+            shift += row.rect.height();
+            row.rect = Rect::from_min_max(pos2(row.rect.left(), top), pos2(row.rect.right(), top));
+
+            galley.num_vertices -= row.visuals.mesh.vertices.len();
+            galley.num_indices -= row.visuals.mesh.indices.len();
+            row.visuals.mesh.clear();
+            for glyph in &mut row.glyphs {
+                glyph.pos.y = top;
+            }
+        } else if shift > 0.0 {
+            let up = vec2(0.0, -shift);
+            row.rect = row.rect.translate(up);
+            row.visuals.mesh.translate(up);
+            row.visuals.mesh_bounds = row.visuals.mesh_bounds.translate(up);
+            for glyph in &mut row.glyphs {
+                glyph.pos.y -= shift;
+            }
+        }
+    }
+
+    galley.rect.max.y -= shift;
+    galley.mesh_bounds.max.y -= shift;
+}
+
+// What each folded block shows after its opening line: how many lines it hides, and its closer.
+// Returns the fold whose placeholder was clicked, to unfold it
+fn show_fold_placeholders(
+    ui: &egui::Ui,
+    id: Id,
+    output: &TextEditOutput,
+    folded: &[Fold],
+) -> Option<Fold> {
+    let origin = output.text_draw_pos.to_vec2();
+    let font_id = FontId::monospace(12.0);
+    let mut line = 0;
+    let mut clicked = None;
+
+    for row in &output.galley.rows {
+        let row_line = line;
+        if !row.ends_with_newline {
+            continue;
+        }
+        line += 1;
+
+        // the outermost fold opening on the line, when it isn't folded away itself
+        let Some(fold) = folded.iter().find(|fold| {
+            fold.start == row_line && !folded.iter().any(|other| other.hides(row_line))
+        }) else {
+            continue;
+        };
+
+        let text = format!("… {} lines }}", fold.hidden_lines());
+        let galley =
+            ui.fonts()
+                .layout_no_wrap(text, font_id.clone(), ui.visuals().weak_text_color());
+        let rect = Rect::from_min_size(
+            pos2(row.rect.right() + 6.0, row.rect.top()) + origin,
+            vec2(galley.size().x + 8.0, row.rect.height()),
+        );
+        if !rect.intersects(ui.clip_rect()) {
+            continue;
+        }
+
+        let response = ui
+            .interact(rect, id.with(("fold", fold.start)), Sense::click())
+            .on_hover_text("Unfold");
+        let fill = if response.hovered() {
+            ui.visuals().widgets.hovered.bg_fill
+        } else {
+            FOLD_COLOR
+        };
+        ui.painter().rect_filled(rect, Rounding::same(3.0), fill);
+        ui.painter().galley(
+            pos2(rect.left() + 4.0, rect.center().y - galley.size().y / 2.0),
+            galley,
+        );
+
+        if response.clicked() {
+            clicked = Some(*fold);
+        }
+    }
+
+    clicked
+}
+
+fn hint_width(ui: &egui::Ui, hint: &Hint) -> f32 {
+    let font_id = FontId::monospace(12.0);
+    let fonts = ui.fonts();
+
+    hint.label
+        .chars()
+        .map(|c| fonts.glyph_width(&font_id, c))
+        .sum()
+}
+
+// Leave room in the layout for each hint, before the char it's at, by splitting the section that
+// char is in. The text itself is left as it is, so cursors and diagnostics stay where they are
+fn make_room(job: &mut LayoutJob, room: &[(usize, f32)]) {
+    use egui::text::LayoutSection;
+
+    let mut sections = Vec::with_capacity(job.sections.len() + room.len());
+    let mut room = room.iter().peekable();
+
+    for mut section in job.sections.drain(..) {
+        while let Some((at, width)) = room.next_if(|(at, _)| *at < section.byte_range.end) {
+            if *at > section.byte_range.start {
+                sections.push(LayoutSection {
+                    byte_range: section.byte_range.start..*at,
+                    ..section.clone()
+                });
+                section.byte_range.start = *at;
+                section.leading_space = 0.0;
+            }
+            section.leading_space += width;
+        }
+        sections.push(section);
+    }
+
+    job.sections = sections;
+}
+
+// The guessed types, faint and in the room left for them, unless their lines are folded away
+fn show_inlay_hints(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    code: &str,
+    hints: &[Hint],
+    folded: &[Fold],
+) {
+    let origin = output.text_draw_pos.to_vec2();
+    let font_id = FontId::monospace(12.0);
+    let color = ui.visuals().weak_text_color();
+    let galley = &output.galley;
+
+    for hint in hints {
+        let index = char_index(code, hint.at);
+        if folded.iter().any(|fold| fold.hides(line_at(code, index))) {
+            continue;
+        }
+
+        let at = galley.pos_from_cursor(&galley.from_ccursor(CCursor::new(index)));
+        let label = ui
+            .fonts()
+            .layout_no_wrap(hint.label.clone(), font_id.clone(), color);
+        let pos = pos2(at.left() - label.size().x, at.top()) + origin;
+        if ui.clip_rect().contains(pos) {
+            ui.painter().galley(pos, label);
+        }
+    }
+}
+
+// the line a char index is on, starting at 0
+fn line_at(code: &str, char_index: usize) -> usize {
+    code.chars().take(char_index).filter(|c| *c == '\n').count()
+}
+
+// The text edit's cursor can be moved onto folded away lines, by the arrow keys and by clicking
+// right between rows. It's moved past them the way it was going, and typing there, like a new
+// line on the opening line, unfolds them instead
+fn keep_cursor_unfolded(
+    ui: &egui::Ui,
+    id: Id,
+    code: &str,
+    folds: &mut Folds,
+    changed: bool,
+    before: Option<Selection>,
+) {
+    let Some(mut state) = TextEdit::load_state(ui.ctx(), id) else {
+        return;
+    };
+    let Some(range) = state.ccursor_range() else {
+        return;
+    };
+    let line = line_at(code, range.primary.index);
+    let Some(fold) = folds.hiding(line) else {
+        return;
+    };
+
+    if changed {
+        folds.reveal(line, line);
+    } else {
+        let down = before.is_some_and(|before| range.primary.index > before.cursor);
+        let below = (fold.end + 1 < code.lines().count()).then_some(fold.end + 2);
+        let to = match below.filter(|_| down) {
+            Some(below) => cursor::char_index(code, below, 1).1,
+            None => cursor::char_index(code, fold.start + 1, usize::MAX).1,
+        };
+
+        let secondary = if range.primary == range.secondary {
+            CCursor::new(to)
+        } else {
+            range.secondary
+        };
+        state.set_ccursor_range(Some(CCursorRange {
+            primary: CCursor::new(to),
+            secondary,
+        }));
+        state.store(ui.ctx(), id);
+    }
+    ui.ctx().request_repaint();
+}
+
+/// The color a diagnostic is shown in
+pub fn diagnostic_color(visuals: &Visuals, diagnostic: &Diagnostic) -> Color32 {
+    match (diagnostic.severity, diagnostic.tool) {
+        (Severity::Error, _) => visuals.error_fg_color,
+        (Severity::Warning, Tool::Clippy) => CLIPPY_COLOR,
+        (Severity::Warning, Tool::Rustc) => visuals.warn_fg_color,
+    }
+}
+
+// Squiggles under the code each diagnostic is about, and their messages when hovered
+fn show_diagnostics(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    code: &str,
+    diagnostics: &[Diagnostic],
+) {
+    let galley = &output.galley;
+    let origin = output.text_draw_pos;
+    let pointer = ui.ctx().pointer_hover_pos();
+
+    let mut hovered = vec![];
+
+    for diagnostic in diagnostics {
+        let color = diagnostic_color(ui.visuals(), diagnostic);
+
+        let start = char_index(code, diagnostic.range.start);
+        // empty spans, like a missing semicolon, still get a mark
+        let end = char_index(code, diagnostic.range.end).max(start + 1);
+
+        let mut row_start = 0;
+        for row in &galley.rows {
+            let row_end = row_start + row.char_count_including_newline();
+
+            if start < row_end && end > row_start && row.rect.height() > 0.0 {
+                let chars = row.char_count_excluding_newline();
+                let from = start.max(row_start) - row_start;
+                let to = (end.min(row_end) - row_start).min(chars);
+
+                let left = origin.x + row.x_offset(from.min(chars));
+                let right = (origin.x + row.x_offset(to)).max(left + 4.0);
+                let bottom = origin.y + row.rect.bottom();
+
+                let points = (0..=((right - left) / 2.0).ceil() as usize)
+                    .map(|step| {
+                        let x = (left + step as f32 * 2.0).min(right);
+                        let y = if step % 2 == 0 { bottom } else { bottom - 2.0 };
+                        pos2(x, y)
+                    })
+                    .collect();
+                ui.painter()
+                    .add(Shape::line(points, Stroke::new(1.0, color)));
+
+                let rect =
+                    Rect::from_min_max(pos2(left, origin.y + row.rect.top()), pos2(right, bottom));
+                if output.response.hovered()
+                    && matches!(pointer, Some(pointer) if rect.contains(pointer))
+                {
+                    hovered.push((color, diagnostic));
+                }
+            }
+
+            row_start = row_end;
+        }
+    }
+
+    if !hovered.is_empty() {
+        egui::show_tooltip_at_pointer(ui.ctx(), Id::new("code_editor_diagnostic"), |ui| {
+            for (color, diagnostic) in hovered {
+                ui.colored_label(color, &diagnostic.message);
+            }
+        });
+    }
+}
+
+impl CodeEditor {
+    #[allow(clippy::too_many_arguments)]
+    pub fn show(
+        &mut self,
+        id: Id,
+        ui: &mut egui::Ui,
+        scroll_offset: Vec2,
+        diagnostics: &[Diagnostic],
+        search: &mut EditorSearch,
+        settings: &Editor,
+        indent: Indent,
+        syntax: &SyntaxColors,
+    ) -> CodeEditorResponse {
+        let Self {
+            language,
+            code,
+            read_only,
+            highlighting,
+            history,
+            folds,
+            minimap,
+            vim,
+            snippet,
+            completions,
+            lsp,
+            inlay_hints,
+        } = self;
+
+        let mut frame_rect = ui.max_rect().shrink(6.0);
+
+        let found = search.open.then(|| find::find(code, query(search)));
+        let mut matches = found.clone().and_then(Result::ok).unwrap_or_default();
+
+        let mut find_action = None;
+        if search.open {
+            let invalid = matches!(found, Some(Err(_)));
+            let mut bar_ui = ui.child_ui(frame_rect, Layout::top_down(Align::Min));
+            find_action = find_bar(&mut bar_ui, id, search, matches.len(), invalid, *read_only);
+            frame_rect.set_top(bar_ui.min_rect().bottom() + 4.0);
+        }
+        if search.current >= matches.len() {
+            search.current = 0;
+        }
+
+        // replacing is one change to the code, so the editor undoes it in one step
+        let replaced = match find_action {
+            Some(FindAction::Replace) => matches.get(search.current).and_then(|range| {
+                find::replace(code, query(search), range.clone(), &search.replacement)
+            }),
+            Some(FindAction::ReplaceAll) => {
+                search.current = 0;
+                find::replace_all(code, query(search), &search.replacement)
+                    .ok()
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(replaced, _)| replaced)
+            }
+            None => None,
+        };
+        // changes made from outside the editor, like formatting, are a step of their own
+        let kind = edit_kind(&ui.input().events);
+        let time = ui.input().time;
+        let selection_before = TextEdit::load_state(ui.ctx(), id)
+            .map_or(Selection::at(0), |state| primary_selection(&state));
+        if !*read_only {
+            history.update(
+                code,
+                selection_before,
+                EditKind::Other,
+                time,
+                settings.undo_limit,
+            );
+        }
+
+        let mut changed = replaced.is_some();
+        let vim_mode = settings.vim_mode && !*read_only;
+        let (vim_changed, vim_moved, vim_outcome) = if vim_mode {
+            handle_vim(ui, id, code, vim, history)
+        } else {
+            (false, false, None)
+        };
+        changed |= vim_changed;
+        // vim's commands are undone one at a time, even when they're typed
+        let kind = if vim_changed { EditKind::Other } else { kind };
+        // vim has taken what's typed outside of insert mode by now
+        let complete = settings.completion
+            && !*read_only
+            && language == "rs"
+            && (!vim_mode || vim.mode == Mode::Insert);
+        let lsp_enabled = settings.lsp.enabled && !*read_only && language == "rs";
+        let (typed, deleted) = typed_or_deleted(&ui.input().events);
+        changed |= handle_cursors(ui, id, code, &indent.text(), *read_only);
+        if !*read_only {
+            changed |= handle_undo(ui, id, code, history);
+            changed |= handle_completion(ui, id, code, completions);
+            if ui.input().key_pressed(Key::Escape) {
+                *snippet = None;
+            }
+            // the snippets are all rust
+            let snippets = if language == "rs" {
+                settings.snippets.as_slice()
+            } else {
+                &[]
+            };
+            changed |= handle_typing(ui, id, code, settings, &indent.text(), snippets, snippet);
+        }
+        if let Some(replaced) = replaced {
+            *code = replaced;
+            matches = find::find(code, query(search)).unwrap_or_default();
+            search.scroll_to_current = true;
+        }
+        if search.current >= matches.len() {
+            search.current = 0;
+        }
+
+        let scroll_to = std::mem::take(&mut search.scroll_to_current)
+            .then(|| matches.get(search.current).cloned())
+            .flatten()
+            .or_else(|| {
+                let jumped = ui.data().get_temp(id.with("scroll_to"));
+                ui.data().remove::<Range<usize>>(id.with("scroll_to"));
+                jumped
+            });
+        // what's being jumped to can't stay folded away
+        folds.update(code);
+        if let Some(range) = &scroll_to {
+            let line = |byte| line_at(code, char_index(code, byte));
+            folds.reveal(line(range.start), line(range.end));
+        }
+        let foldable = if language == "rs" {
+            folding::regions(code)
+        } else {
+            vec![]
+        };
+        let folded = folds.folds().to_vec();
+        // the code given to the layouter may have been typed in since, so it's searched again
+        let highlights = search.open.then(|| (search.clone(), search.current));
+        let mut code_rect = frame_rect.shrink(5.0);
+        let gutter = Gutter::new(ui, code_rect, code.split('\n').count());
+        code_rect.set_left(code_rect.left() + gutter.width());
+        let show_minimap = settings.minimap && frame_rect.width() >= Minimap::MIN_EDITOR_WIDTH;
+        let minimap_rect = show_minimap.then(|| {
+            let mut minimap_rect = code_rect;
+            minimap_rect.set_left(code_rect.right() - Minimap::WIDTH);
+            code_rect.set_right(minimap_rect.left() - 4.0);
+            minimap_rect
+        });
+        // for scrolling to a line, which happens outside of showing the editor
+        ui.data()
+            .insert_temp(id.with("view_height"), code_rect.height());
+
+        // the cursor as it was before this frame's typing, the text edit only moves it while
+        // it's shown
+        let cursor = plain_cursor(ui, id, TextEdit::load_state(ui.ctx(), id));
+        let now = ui.input().time;
+        let flash = ui
+            .data()
+            .get_temp::<(usize, f64)>(id.with("unmatched_bracket"))
+            .filter(|(_, flashed)| now - flashed < FLASH_SECS)
+            .map(|(bracket, _)| bracket);
+        if flash.is_some() {
+            ui.ctx().request_repaint();
+        }
+
+        // the other cursors' selections look like the text edit's own
+        let extras = extra_cursors(ui.ctx(), id, code);
+        let primary_before =
+            TextEdit::load_state(ui.ctx(), id).map(|state| primary_selection(&state));
+        let selection_color = ui.visuals().selection.bg_fill;
+        // the selection being dragged, which the text edit doesn't show while it's not taking
+        // the pointer
+        let dragging = ui.data().get_temp::<Range<usize>>(id.with("text_drag"));
+
+        let show_hints = settings.inlay_hints && language == "rs";
+        // guessed for the code the layouter's given, then shown for the same code
+        let inlay_hints = RefCell::new(inlay_hints);
         let theme = CodeTheme::from_memory(ui.ctx());
         let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
-            let mut layout_job = highlight(ui.ctx(), &theme, string, language);
+            let mut layout_job = if language == "rs" {
+                highlighting.layout_job(&theme, syntax, string)
+            } else {
+                highlight(ui.ctx(), &theme, syntax, string, language)
+            };
+            if show_minimap {
+                minimap.update(&layout_job);
+            }
+
+            let trailing = whitespace::trailing(string)
+                .into_iter()
+                .map(|range| (range, TRAILING_WHITESPACE_COLOR))
+                .collect::<Vec<_>>();
+            restyle_ranges(&mut layout_job, &trailing, |format, color| {
+                format.background = *color;
+            });
+
+            let bracket_match =
+                cursor.and_then(|cursor| brackets::find_match(string, byte_index(string, cursor)));
+            let mut bracket_ranges = match bracket_match {
+                Some(BracketMatch::Matched { bracket, partner }) => vec![
+                    (bracket..bracket + 1, BRACKET_COLOR),
+                    (partner..partner + 1, BRACKET_COLOR),
+                ],
+                Some(BracketMatch::Unmatched(bracket)) => {
+                    vec![(bracket..bracket + 1, UNMATCHED_BRACKET_COLOR)]
+                }
+                None => vec![],
+            };
+            // the code may have changed since it flashed
+            if let Some(bracket) = flash.filter(|bracket| *bracket < string.len()) {
+                bracket_ranges.retain(|(range, _)| range.start != bracket);
+                bracket_ranges.push((bracket..bracket + 1, UNMATCHED_BRACKET_COLOR));
+            }
+            bracket_ranges.sort_by_key(|(range, _)| range.start);
+            restyle_ranges(&mut layout_job, &bracket_ranges, |format, color| {
+                format.background = *color;
+            });
+
+            let mut selected = extras
+                .iter()
+                .map(Selection::range)
+                .chain(dragging.clone())
+                .filter(|range| !range.is_empty())
+                .map(|range| {
+                    let bytes = byte_index(string, range.start)..byte_index(string, range.end);
+                    (bytes, selection_color)
+                })
+                .collect::<Vec<_>>();
+            selected.sort_by_key(|(range, _)| range.start);
+            restyle_ranges(&mut layout_job, &selected, |format, color| {
+                format.background = *color;
+            });
+
+            if let Some((search, current)) = &highlights {
+                let ranges = find::find(string, query(search))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, range)| {
+                        let color = if index == *current {
+                            CURRENT_MATCH_COLOR
+                        } else {
+                            MATCH_COLOR
+                        };
+                        (range, color)
+                    })
+                    .collect::<Vec<_>>();
+                restyle_ranges(&mut layout_job, &ranges, |format, color| {
+                    format.background = *color;
+                });
+            }
+
+            // last, the sections split up above would lose the room
+            if show_hints {
+                let room = inlay_hints
+                    .borrow_mut()
+                    .update(string)
+                    .iter()
+                    .map(|hint| (hint.at, hint_width(ui, hint)))
+                    .collect::<Vec<_>>();
+                make_room(&mut layout_job, &room);
+            }
+
             layout_job.wrap.max_width = wrap_width;
-            ui.fonts().layout_job(layout_job)
+            let galley = ui.fonts().layout_job(layout_job);
+            if folded.is_empty() {
+                galley
+            } else {
+                let mut galley = (*galley).clone();
+                fold_rows(&mut galley, &folded);
+                Arc::new(galley)
+            }
         };
 
         let Rect { max, .. } = ui.max_rect();
@@ -308,24 +2554,237 @@ impl CodeEditor {
         let row_height = ui.fonts().row_height(&font_id);
         let rows = ((code_rect.height() - 5.0) / row_height).floor() as usize;
 
-        let text_widget = egui::TextEdit::multiline(code)
-            .font(egui::TextStyle::Monospace) // for cursor height
-            .code_editor()
-            // remove the frame and draw our own
-            .frame(false)
-            .desired_width(f32::INFINITY)
-            .margin(vec2(2.0, 2.0))
-            .layouter(&mut layouter)
-            .cursor_at_end(false)
-            .id(id)
-            .desired_rows(rows);
-
         let scroll_res = egui::ScrollArea::vertical()
             .scroll_offset(scroll_offset)
             .show(&mut frame_ui, |ui| {
-                ui.add(text_widget);
+                // a &str can be selected in, but not changed
+                let mut text = code.as_str();
+                let buffer: &mut dyn TextBuffer = if *read_only { &mut text } else { code };
+
+                let output = egui::TextEdit::multiline(buffer)
+                    .interactive(dragging.is_none())
+                    .font(egui::TextStyle::Monospace) // for cursor height
+                    .code_editor()
+                    // remove the frame and draw our own
+                    .frame(false)
+                    .desired_width(f32::INFINITY)
+                    .margin(vec2(2.0, 2.0))
+                    .layouter(&mut layouter)
+                    .cursor_at_end(false)
+                    .id(id)
+                    .desired_rows(rows)
+                    .show(ui);
+
+                let dropped = if *read_only {
+                    None
+                } else {
+                    drag_text(ui, id, &output, code, selection_before, dragging.clone())
+                };
+                show_diagnostics(ui, &output, code, diagnostics);
+                // what was typed in a snippet's stop is copied to the rest of it
+                if let Some(expansion) = snippet {
+                    let selected = output.cursor_range.map(|range| {
+                        let (a, b) = (range.primary.ccursor.index, range.secondary.ccursor.index);
+                        a.min(b)..a.max(b)
+                    });
+                    if !selected.is_some_and(|selected| expansion.update(code, selected)) {
+                        *snippet = None;
+                    }
+                    if output.response.changed() {
+                        ui.ctx().request_repaint();
+                    }
+                }
+                if complete && extras.is_empty() {
+                    let cursor = plain_cursor(ui, id, TextEdit::load_state(ui.ctx(), id));
+                    let changed = output.response.changed();
+                    let asked = completions.asked;
+                    update_completions(
+                        completions,
+                        code,
+                        cursor,
+                        typed,
+                        deleted,
+                        changed,
+                        lsp_enabled,
+                    );
+                    if completions.asked != asked {
+                        lsp.asks.completion = completions.asked;
+                    }
+                    show_completions(ui, id, &output, completions);
+                } else {
+                    completions.items.clear();
+                    completions.asked = None;
+                }
+                if lsp_enabled {
+                    show_lsp_hover(ui, id, &output, code, lsp);
+                }
+                if !*read_only {
+                    let selection = dropped.unwrap_or_else(|| {
+                        output.cursor_range.map_or(selection_before, |range| {
+                            let (anchor, cursor) = (range.secondary.ccursor, range.primary.ccursor);
+                            Selection::new(anchor.index, cursor.index)
+                        })
+                    });
+                    history.update(code, selection, kind, time, settings.undo_limit);
+                }
+                show_extra_cursors(ui, id, &output, &extras, primary_before);
+                show_whitespace(ui, &output, settings.show_whitespace, &extras);
+                if settings.indent_guides {
+                    show_indent_guides(ui, &output, code, indent);
+                }
+                if show_hints {
+                    let mut inlay_hints = inlay_hints.borrow_mut();
+                    show_inlay_hints(ui, &output, code, inlay_hints.update(code), &folded);
+                }
+                let unfolded = show_fold_placeholders(ui, id, &output, &folded);
+                if vim_mode && vim.mode != Mode::Insert {
+                    show_vim_cursor(ui, id, &output, vim_moved);
+                }
+
+                // the brackets were highlighted for where the cursor was before it moved
+                let moved_to = output
+                    .cursor_range
+                    .filter(|range| range.primary.ccursor == range.secondary.ccursor)
+                    .map(|range| range.primary.ccursor.index);
+                if ui.memory().has_focus(id) && moved_to != cursor {
+                    ui.ctx().request_repaint();
+                }
+
+                if let Some(range) = scroll_to {
+                    let galley = &output.galley;
+                    let start = galley.from_ccursor(CCursor::new(char_index(code, range.start)));
+                    let end = galley.from_ccursor(CCursor::new(char_index(code, range.end)));
+                    let rect = galley
+                        .pos_from_cursor(&start)
+                        .union(galley.pos_from_cursor(&end))
+                        .translate(output.text_draw_pos.to_vec2());
+
+                    ui.scroll_to_rect(rect, Some(Align::Center));
+                }
+
+                let origin = output.text_draw_pos;
+                let mut line = 0;
+                let mut starts_line = true;
+                let rows: Vec<GutterRow> = output
+                    .galley
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        // folded away rows don't get a number
+                        let gutter_row = GutterRow {
+                            top: origin.y + row.rect.top(),
+                            bottom: origin.y + row.rect.bottom(),
+                            line: (starts_line && row.rect.height() > 0.0).then_some(line),
+                        };
+
+                        starts_line = row.ends_with_newline;
+                        if starts_line {
+                            line += 1;
+                        }
+
+                        gutter_row
+                    })
+                    .collect();
+
+                // where the line each fixable diagnostic starts on is, one lightbulb per line
+                let mut lightbulbs: Vec<(f32, usize)> = vec![];
+                for (index, diagnostic) in diagnostics.iter().enumerate() {
+                    let folded_away = || {
+                        let start = char_index(code, diagnostic.range.start);
+                        folded.iter().any(|fold| fold.hides(line_at(code, start)))
+                    };
+                    if diagnostic.suggestion.is_none() || folded_away() {
+                        continue;
+                    }
+
+                    let galley = &output.galley;
+                    let start =
+                        galley.from_ccursor(CCursor::new(char_index(code, diagnostic.range.start)));
+                    let y = output.text_draw_pos.y + galley.pos_from_cursor(&start).center().y;
+
+                    if !lightbulbs.iter().any(|(other, _)| (other - y).abs() < 1.0) {
+                        lightbulbs.push((y, index));
+                    }
+                }
+
+                let edited = output.response.changed() || dropped.is_some();
+                (edited, rows, lightbulbs, unfolded)
             });
 
-        scroll_res.state.offset
+        let (edited, rows, lightbulbs, unfolded) = scroll_res.inner;
+        changed |= edited;
+
+        let mut scroll_offset = scroll_res.state.offset;
+        if let Some(minimap_rect) = minimap_rect {
+            let visible_lines = rows
+                .iter()
+                .filter(|row| row.bottom >= code_rect.top() && row.top <= code_rect.bottom())
+                .filter_map(|row| row.line)
+                .collect::<Vec<_>>();
+            let visible = match (visible_lines.first(), visible_lines.last()) {
+                (Some(first), Some(last)) => *first..last + 1,
+                _ => 0..0,
+            };
+
+            let line_starts = std::iter::once(0)
+                .chain(code.match_indices('\n').map(|(at, _)| at + 1))
+                .collect::<Vec<_>>();
+            let line_of = |byte: usize| line_starts.partition_point(|start| *start <= byte) - 1;
+            let found = matches
+                .iter()
+                .map(|range| (line_of(range.start), MATCH_COLOR));
+            let markers = diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let color = diagnostic_color(ui.visuals(), diagnostic);
+                    (line_of(diagnostic.range.start), color)
+                })
+                .chain(found)
+                .collect::<Vec<_>>();
+
+            if let Some(line) = minimap.show(ui, id, minimap_rect, visible, &markers) {
+                // the line goes in the middle of the editor, from where its row is in the code
+                let row_top = rows
+                    .iter()
+                    .rev()
+                    .find(|row| row.line.is_some_and(|row_line| row_line <= line))
+                    .map_or(0.0, |row| row.top);
+                let top = row_top - scroll_res.inner_rect.top() + scroll_offset.y;
+                scroll_offset.y = (top - code_rect.height() / 2.0).max(0.0);
+                ui.ctx().request_repaint();
+            }
+        }
+
+        folds.update(code);
+        if let Some(fold) = unfolded {
+            folds.toggle(code, fold);
+        }
+        keep_cursor_unfolded(ui, id, code, folds, changed, primary_before);
+
+        match gutter.show(ui, id, &rows, &lightbulbs, diagnostics, &foldable, &folded) {
+            Some(GutterAction::SelectLine(line)) => select_line(ui.ctx(), id, code, line),
+            Some(GutterAction::Fix(index)) => {
+                let suggestion = diagnostics[index].suggestion.as_ref();
+                if let Some(fixed) = suggestion
+                    .and_then(|suggestion| diagnostics::apply_suggestion(code, suggestion))
+                {
+                    *code = fixed;
+                    changed = true;
+                }
+            }
+            Some(GutterAction::ToggleFold(region)) => folds.toggle(code, region),
+            None => (),
+        }
+
+        if vim_mode {
+            show_vim_status(ui, frame_rect, vim);
+        }
+
+        CodeEditorResponse {
+            scroll_offset,
+            changed,
+            save: vim_outcome == Some(vim::Outcome::Save),
+            close: vim_outcome == Some(vim::Outcome::Close),
+        }
     }
 }
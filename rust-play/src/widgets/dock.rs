@@ -1,28 +1,64 @@
 use rand::Rng;
 use std::borrow::Cow;
-use std::io::{BufRead, BufReader, Read};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::mpsc::{channel, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 use windows::Win32::System::Threading::CREATE_NO_WINDOW;
 
-use ringbuf::HeapRb;
-
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
-use cargo_player::{BuildType, Channel, Edition, File, Project, Subcommand};
-use egui::{vec2, Align2, Color32, Id, Ui, Vec2, Window};
-use egui_dock::{DockArea, Node, NodeIndex, Style, TabAddAlign};
+use cargo_player::{
+    Channel, Dependency, File, Project, ProjectBuildError, ProjectError, Subcommand,
+};
+use egui::popup::popup_below_widget;
+use egui::text::{CCursor, LayoutJob, TextFormat};
+use egui::text_edit::CCursorRange;
+use egui::{
+    pos2, vec2, Align, Align2, Area, Button, Color32, CursorIcon, DragValue, Frame, Grid, Id, Key,
+    Layout, Modifiers, Order, Rect, ScrollArea, Sense, TextEdit, TextStyle, Ui, Vec2, Window,
+};
+use egui_dock::{DockArea, Node, NodeIndex, Split, Style, TabAddAlign, TabIndex};
 use serde::{Deserialize, Serialize};
-use smallvec::SmallVec;
 
-use crate::config::{Command, Config, GitHub, MenuCommand, TabCommand};
+use crate::config::{
+    search_crates, Aborter, Action, AutoRun, CheckState, Command, Config, CrateDependency,
+    CrateSearch, DialogCommand, Editor, EditorSearch, EmitKind, EnvVar, ExpandError, ExportState,
+    GitHub, GitHubError, Indent, LineEdit, MenuCommand, OutputChunk, RecentFiles, RunConfig,
+    RunMode, RunState, RunStatus, ShareState, SharedGist, Stream, SyntaxColors, TabCommand, Target,
+    Terminal, TerminalCommand, Timeout, Unsaved, CLOSED_TABS, DEFAULT_DESCRIPTION,
+    DEFAULT_TIMEOUT_SECS, OUTPUT_BUFFER,
+};
+use crate::utils::build_cache;
+use crate::utils::cargo_output;
 use crate::utils::data::Data;
+use crate::utils::demangle;
+use crate::utils::diagnostics::{self, Diagnostic, Severity, Tool};
+use crate::utils::examples::EXAMPLES;
+use crate::utils::export::{self, ExportError};
+use crate::utils::file_watch::FileWatcher;
+use crate::utils::lsp::{self, LspClient, Position, Reply};
+use crate::utils::miri;
+use crate::utils::playground;
+use crate::utils::rustfmt;
+use crate::utils::snippets;
+use crate::utils::tab_names;
+use crate::utils::wasm;
+use crate::utils::whitespace;
+use crate::utils::{crash_reports, drafts};
 
 use super::code_editor::CodeEditor;
+use super::palette::CommandRegistry;
 use super::titlebar::TITLEBAR_HEIGHT;
+#[cfg(target_os = "windows")]
+use crate::os::windows::single_instance;
 
 pub type Tree = egui_dock::Tree<Tab>;
 
@@ -32,35 +68,438 @@ pub struct Tab {
     pub editor: CodeEditor,
     pub id: Id,
     scroll_offset: Option<Vec2>,
+    // where the scratch was last saved to
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub run_config: RunConfig,
+    // added to the generated Cargo.toml on top of the `//# ` comments
+    #[serde(default)]
+    pub dependencies: Vec<CrateDependency>,
+    #[serde(skip)]
+    pub check: CheckState,
+    // the tab this one was generated from and what it shows of it, so generating it again reuses
+    // this tab
+    #[serde(default)]
+    pub source: Option<(Id, Generated)>,
+    // generated code which is still on its way
+    #[serde(skip)]
+    pub loading: bool,
+    // passed to Miri as MIRIFLAGS, like `-Zmiri-disable-isolation`
+    #[serde(default)]
+    pub miri_flags: String,
+    // the program's arguments, split like a shell would
+    #[serde(default)]
+    pub args: String,
+    // set on the tab's process only
+    #[serde(default)]
+    pub env_vars: Vec<EnvVar>,
+    // the scratch's other files next to main.rs, for its `mod`s
+    #[serde(default)]
+    pub files: Vec<ScratchFile>,
+    // which of the files is shown, None for main.rs
+    #[serde(skip)]
+    pub open_file: Option<usize>,
+    // the file's code when it was last opened or saved, to tell other programs' changes apart
+    #[serde(skip)]
+    pub disk_code: Option<String>,
+    // the file's code after another program changed it, until the user picks which code to keep
+    #[serde(skip)]
+    pub disk_change: Option<String>,
+    // the code revision which was last saved or loaded, the tab has unsaved changes past it
+    #[serde(skip)]
+    pub saved_revision: u64,
+    // pinned tabs go first in their tab bar, and can't be closed until they're unpinned
+    #[serde(default)]
+    pub pinned: bool,
+    // the gist the tab was last shared to, sharing it again can update it
+    #[serde(default)]
+    pub gist: Option<SharedGist>,
+    #[serde(skip)]
+    pub search: EditorSearch,
+    // how the tab indents instead of the settings, picked from the status bar
+    #[serde(default)]
+    pub indent: Option<Indent>,
+    // the welcome page with the examples, which has no code of its own
+    #[serde(default)]
+    pub welcome: bool,
+}
+
+/// One of a scratch's module files, written next to its main.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScratchFile {
+    // the module name, without the .rs
+    pub name: String,
+    pub editor: CodeEditor,
+}
+
+impl Tab {
+    /// A new scratch with the starting code
+    pub fn scratch(name: String, id: Id) -> Self {
+        Self {
+            name,
+            editor: CodeEditor::default(),
+            id,
+            scroll_offset: None,
+            path: None,
+            run_config: RunConfig::default(),
+            dependencies: vec![],
+            check: CheckState::default(),
+            source: None,
+            loading: false,
+            miri_flags: String::new(),
+            args: String::new(),
+            env_vars: vec![],
+            files: vec![],
+            open_file: None,
+            disk_code: None,
+            disk_change: None,
+            saved_revision: 0,
+            pinned: false,
+            gist: None,
+            search: Default::default(),
+            indent: None,
+            welcome: false,
+        }
+    }
+
+    /// A tab showing code which can't be edited, like what was generated from another tab, built
+    /// with that tab's run config
+    pub fn read_only(name: String, language: &str, code: String, run_config: RunConfig) -> Self {
+        let id = Id::new(format!("{name}-{}", rand::random::<u64>()));
+
+        Self {
+            editor: CodeEditor::new_read_only(language, code),
+            run_config,
+            ..Self::scratch(name, id)
+        }
+    }
+
+    /// The welcome page, there's only ever one of it
+    pub fn welcome() -> Self {
+        Self {
+            editor: CodeEditor::new_read_only("rs", String::new()),
+            welcome: true,
+            ..Self::scratch("Welcome".to_string(), Id::new("welcome"))
+        }
+    }
+
+    /// Whether the code was edited since it was last saved. Generated code is never saved
+    pub fn is_dirty(&self) -> bool {
+        !self.editor.read_only && self.check.revision != self.saved_revision
+    }
+
+    /// The name and code of each module file, for building the scratch
+    fn module_files(&self) -> Vec<(String, String)> {
+        self.files
+            .iter()
+            .map(|file| (file.name.clone(), file.editor.code.clone()))
+            .collect()
+    }
+
+    // main and then the module files, by name and code
+    fn source_files(&self) -> Vec<(String, String)> {
+        std::iter::once(("main".to_string(), self.editor.code.clone()))
+            .chain(self.module_files())
+            .collect()
+    }
+
+    /// The id of a file's editor, the TextEdit of each file keeps its own cursor
+    pub fn editor_id(&self, file: Option<usize>) -> Id {
+        let id = self.id.with("code_editor");
+
+        match file.and_then(|index| self.files.get(index)) {
+            Some(file) => id.with(&file.name),
+            None => id,
+        }
+    }
+}
+
+/// What a read-only tab shows of the tab it was generated from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Generated {
+    // the code with its macros expanded
+    Expanded,
+    // the text format of the .wasm a wasm build made
+    Wat,
 }
 
+// how long the code has to stay the same before it's checked
+const CHECK_DELAY: Duration = Duration::from_millis(600);
+// tabs shrink down to this, past that the ones which don't fit are listed in the overflow menu
+const MIN_TAB_WIDTH: f32 = 90.0;
+// the room a tab takes besides its title, for the margins and the close button
+const TAB_PADDING: f32 = 40.0;
+// the dock's + button at the end of each tab bar
+const TAB_ADD_WIDTH: f32 = 24.0;
+const OVERFLOW_BTN_WIDTH: f32 = 24.0;
+
+// set once miri was found, it isn't looked for again after that
+static MIRI_FOUND: AtomicBool = AtomicBool::new(false);
+// the toolchains the wasm target was found for
+static WASM_TOOLCHAINS: Mutex<Vec<Channel>> = Mutex::new(vec![]);
+
 pub trait TreeTabs
 where
     Self: Sized,
 {
     fn init() -> Self;
+    fn find_tab_mut(&mut self, id: Id) -> Option<&mut Tab>;
+    fn find_tab_location(&self, id: Id) -> Option<(NodeIndex, TabIndex)>;
 }
 
 // Initialize the initial tabs / tab data
 impl TreeTabs for Tree {
     fn init() -> Self {
-        let tab = Tab {
-            name: "Scratch 1".to_string(),
-            editor: CodeEditor::default(),
-            id: Id::new("Scratch 1"),
-            scroll_offset: None,
-        };
+        let tab = Tab::scratch("Scratch 1".to_string(), tab_names::scratch_id(0));
 
         let mut tree = Tree::new(vec![tab]);
         tree.set_focused_node(NodeIndex::root());
         tree
     }
+
+    fn find_tab_mut(&mut self, id: Id) -> Option<&mut Tab> {
+        self.iter_mut().find_map(|node| {
+            let Node::Leaf { tabs, .. } = node else {
+                return None;
+            };
+
+            tabs.iter_mut().find(|tab| tab.id == id)
+        })
+    }
+
+    fn find_tab_location(&self, id: Id) -> Option<(NodeIndex, TabIndex)> {
+        self.iter().enumerate().find_map(|(node_index, node)| {
+            let Node::Leaf { tabs, .. } = node else {
+                return None;
+            };
+
+            let tab_index = tabs.iter().position(|tab| tab.id == id)?;
+            Some((NodeIndex(node_index), TabIndex(tab_index)))
+        })
+    }
 }
 
 pub struct Dock;
 
 impl Dock {
+    /// The palette's commands for tabs, they act on the focused one
+    pub fn register_commands(registry: &mut CommandRegistry) {
+        type ForTab = fn(Id) -> Command;
+
+        // generated code isn't rust, so it can't be built
+        let code_commands: [(&str, Option<Action>, ForTab); 16] = [
+            ("Run", Some(Action::Run), |id| {
+                Command::TabCommand(TabCommand::Play(id))
+            }),
+            ("Format", Some(Action::Format), |id| {
+                Command::MenuCommand(MenuCommand::Format(id))
+            }),
+            ("Toggle line comment", Some(Action::ToggleComment), |id| {
+                Command::MenuCommand(MenuCommand::ToggleComment(id))
+            }),
+            ("Duplicate lines", Some(Action::DuplicateLines), |id| {
+                Command::MenuCommand(MenuCommand::EditLines(id, LineEdit::Duplicate))
+            }),
+            ("Move lines up", Some(Action::MoveLinesUp), |id| {
+                Command::MenuCommand(MenuCommand::EditLines(id, LineEdit::MoveUp))
+            }),
+            ("Move lines down", Some(Action::MoveLinesDown), |id| {
+                Command::MenuCommand(MenuCommand::EditLines(id, LineEdit::MoveDown))
+            }),
+            ("Delete lines", Some(Action::DeleteLines), |id| {
+                Command::MenuCommand(MenuCommand::EditLines(id, LineEdit::Delete))
+            }),
+            (
+                "Go to matching bracket",
+                Some(Action::JumpToBracket),
+                |id| Command::MenuCommand(MenuCommand::JumpToBracket(id)),
+            ),
+            (
+                "Select next occurrence",
+                Some(Action::SelectNextOccurrence),
+                |id| Command::MenuCommand(MenuCommand::SelectNextOccurrence(id)),
+            ),
+            ("Go to definition", Some(Action::GoToDefinition), |id| {
+                Command::MenuCommand(MenuCommand::GoToDefinition(id))
+            }),
+            ("Clippy", None, |id| {
+                Command::TabCommand(TabCommand::Clippy(id))
+            }),
+            ("Expand macros", None, |id| {
+                Command::MenuCommand(MenuCommand::Expand(id))
+            }),
+            ("Share to Playground", None, |id| {
+                Command::MenuCommand(MenuCommand::Share(id))
+            }),
+            ("Copy playground link", None, |id| {
+                Command::MenuCommand(MenuCommand::Permalink(id))
+            }),
+            ("Run on playground", None, |id| {
+                Command::TabCommand(TabCommand::RunOnPlayground(id))
+            }),
+            ("Export as project…", None, |id| {
+                Command::MenuCommand(MenuCommand::Export(id))
+            }),
+        ];
+
+        for (name, shortcut, command) in code_commands {
+            registry.register_for_tab(name, shortcut, move |config, tab| {
+                if !tab.editor.read_only {
+                    config.dock.commands.push(command(tab.id));
+                }
+            });
+        }
+
+        for kind in EmitKind::ALL {
+            registry.register_for_tab(format!("Emit {}", kind.name()), None, move |config, tab| {
+                if !tab.editor.read_only {
+                    let command = MenuCommand::Emit(tab.id, kind);
+                    config.dock.commands.push(Command::MenuCommand(command));
+                }
+            });
+        }
+
+        let tab_commands: [(&str, Option<Action>, ForTab); 8] = [
+            ("Rename tab", None, |id| {
+                Command::MenuCommand(MenuCommand::Rename(id))
+            }),
+            ("Save…", None, |id| {
+                Command::MenuCommand(MenuCommand::Save(id))
+            }),
+            ("Duplicate tab", None, |id| {
+                Command::TabCommand(TabCommand::Duplicate(id))
+            }),
+            ("Split right", None, |tab| {
+                Command::TabCommand(TabCommand::Split {
+                    tab,
+                    split: Split::Right,
+                    copy: false,
+                })
+            }),
+            ("Split down", None, |tab| {
+                Command::TabCommand(TabCommand::Split {
+                    tab,
+                    split: Split::Below,
+                    copy: false,
+                })
+            }),
+            ("Close tab", Some(Action::CloseTab), |id| {
+                Command::TabCommand(TabCommand::CloseTabs {
+                    tabs: vec![id],
+                    unsaved: Unsaved::Ask,
+                    exit: false,
+                })
+            }),
+            ("Close other tabs", None, |id| {
+                Command::TabCommand(TabCommand::CloseOthers(id))
+            }),
+            ("Close tabs to the right", None, |id| {
+                Command::TabCommand(TabCommand::CloseToRight(id))
+            }),
+        ];
+
+        for (name, shortcut, command) in tab_commands {
+            registry.register_for_tab(name, shortcut, move |config, tab| {
+                config.dock.commands.push(command(tab.id));
+            });
+        }
+
+        registry.register_for_tab("Pin or unpin tab", None, |config, tab| {
+            if let Some(tab) = config.dock.tree.find_tab_mut(tab.id) {
+                tab.pinned = !tab.pinned;
+            }
+        });
+
+        registry.register("Show welcome page", None, |config| {
+            Self::show_welcome(&mut config.dock.tree);
+        });
+
+        registry.register("New tab", Some(Action::NewTab), |config| {
+            if let Some(node) = config.dock.tree.focused_leaf() {
+                config
+                    .dock
+                    .commands
+                    .push(Command::TabCommand(TabCommand::Add(node)));
+            }
+        });
+
+        let commands: [(&str, Option<Action>, TabCommand); 3] = [
+            ("Open file…", Some(Action::OpenFile), TabCommand::Open),
+            (
+                "Reopen closed tab",
+                Some(Action::ReopenTab),
+                TabCommand::Reopen(None),
+            ),
+            ("Close all tabs", None, TabCommand::CloseAll),
+        ];
+
+        for (name, shortcut, command) in commands {
+            registry.register(name, shortcut, move |config| {
+                config
+                    .dock
+                    .commands
+                    .push(Command::TabCommand(command.clone()));
+            });
+        }
+    }
+
+    /// Focus the welcome page, opening it when it isn't open
+    pub fn show_welcome(tree: &mut Tree) {
+        let open = tree.tabs().find(|tab| tab.welcome).map(|tab| tab.id);
+
+        match open.and_then(|id| tree.find_tab_location(id)) {
+            Some((node, tab_index)) => {
+                tree.set_focused_node(node);
+                tree.set_active_tab(node, tab_index);
+            }
+            None => tree.push_to_focused_leaf(Tab::welcome()),
+        }
+    }
+
+    /// Open the files the app was started with in tabs, and start running them when `run` is set.
+    /// The runs start on the first frame
+    pub fn open_startup_files(config: &mut Config, files: &[PathBuf], run: bool) {
+        let mut dialogs = vec![];
+        for path in files {
+            TabEvents::open_file(
+                &mut config.dock.tree,
+                path,
+                &mut config.recent_files,
+                &mut dialogs,
+            );
+        }
+
+        let dock = &mut config.dock;
+        dock.commands
+            .extend(dialogs.into_iter().map(Command::DialogCommand));
+        if run {
+            // the same file always gets the same tab id, the ones which couldn't be read aren't run
+            let opened = files
+                .iter()
+                .map(Id::new)
+                .filter(|id| dock.tree.tabs().any(|tab| tab.id == *id))
+                .map(|id| Command::TabCommand(TabCommand::Play(id)));
+            dock.commands.extend(opened);
+        }
+    }
+
+    /// Show the tab `step` tabs after the active one in the focused tab bar, going around at the
+    /// ends
+    pub fn step_tab(config: &mut Config, step: isize) {
+        let tree = &mut config.dock.tree;
+        let Some(node) = tree.focused_leaf() else {
+            return;
+        };
+
+        if let Node::Leaf { tabs, active, .. } = &mut tree[node] {
+            let count = tabs.len().max(1) as isize;
+            *active = TabIndex((active.0 as isize + step).rem_euclid(count) as usize);
+        }
+    }
+
     pub fn show(ctx: &egui::Context, config: &mut Config, ui: &mut Ui) {
+        Self::switch_tabs(ctx, config);
+
         let tree = &mut config.dock.tree;
 
         let mut style = Style::from_egui(ctx.style().as_ref());
@@ -73,18 +512,49 @@ impl Dock {
         style.show_add_buttons = true;
         style.add_tab_align = TabAddAlign::Left;
         style.show_context_menu = true;
+        style.tab_hover_name = true;
+
+        sort_pinned_first(tree);
 
         let tab_data = TabData::new();
+        let groups = tree.iter().filter(|node| node.is_leaf()).count();
+        let (title_widths, overflowing) = fit_tab_bars(tree);
 
-        let mut tab_viewer = TabViewer::new(ctx, &tab_data);
+        let mut tab_viewer = TabViewer::new(
+            ctx,
+            &tab_data,
+            &mut config.dock.crate_searches,
+            &config.terminal.run_state,
+            config.runner.timeout_secs,
+            &config.editor,
+            config.theme.syntax(),
+            &mut config.ui.show_welcome_on_startup,
+            groups,
+            title_widths,
+        );
 
+        let tab_bar_height = style.tab_bar_height;
         DockArea::new(tree)
             .style(style)
             .show_inside(ui, &mut tab_viewer);
 
-        // keep the terminal active display on the selected tab
+        for (node, first_hidden) in overflowing {
+            Self::overflow_menu(ctx, tree, &tab_data, node, first_hidden, tab_bar_height);
+        }
+
+        // show the selected tab's output in the terminal whenever the selection changes, another tab's
+        // output can still be picked in the terminal in the meantime
         if let Some((_, tab)) = tree.find_active() {
-            config.terminal.active_tab = Some(tab.id);
+            if config.terminal.focused_tab != Some(tab.id) {
+                config.terminal.focused_tab = Some(tab.id);
+                config.terminal.active_tab = Some(tab.id);
+            }
+
+            let recent = &mut config.dock.recent_tabs;
+            if recent.first() != Some(&tab.id) {
+                recent.retain(|id| *id != tab.id);
+                recent.insert(0, tab.id);
+            }
         }
 
         // add data to command vec
@@ -93,18 +563,209 @@ impl Dock {
             .commands
             .extend_from_slice(tab_data.borrow().as_slice());
     }
+
+    // The » button at the end of a tab bar whose tabs don't all fit. It lists the ones which
+    // didn't, and has the New tab of the + button they pushed out
+    fn overflow_menu(
+        ctx: &egui::Context,
+        tree: &mut Tree,
+        tab_data: &TabData,
+        node: NodeIndex,
+        first_hidden: usize,
+        tab_bar_height: f32,
+    ) {
+        // the dock may have closed or moved tabs since the tab bars were fit
+        let Some(Node::Leaf {
+            rect, tabs, active, ..
+        }) = tree.iter_mut().nth(node.0)
+        else {
+            return;
+        };
+        if tabs.len() <= first_hidden {
+            return;
+        }
+
+        let button_rect = Rect::from_min_size(
+            pos2(rect.max.x - OVERFLOW_BTN_WIDTH, rect.min.y),
+            vec2(OVERFLOW_BTN_WIDTH, tab_bar_height),
+        );
+        let mut picked = false;
+
+        Area::new(Id::new(("tab_overflow", node)))
+            .fixed_pos(button_rect.min)
+            .show(ctx, |ui| {
+                let response = ui
+                    .add_sized(button_rect.size(), Button::new("»"))
+                    .on_hover_text("More tabs");
+
+                let popup_id = response.id.with("popup");
+                if response.clicked() {
+                    ui.memory().toggle_popup(popup_id);
+                }
+
+                popup_below_widget(ui, popup_id, &response, |ui| {
+                    ui.set_min_width(150.0);
+
+                    for (index, tab) in tabs.iter().enumerate().skip(first_hidden) {
+                        if ui.selectable_label(active.0 == index, &tab.name).clicked() {
+                            *active = TabIndex(index);
+                            picked = true;
+                        }
+                    }
+
+                    ui.separator();
+                    if ui.button("New tab").clicked() {
+                        tab_data
+                            .borrow_mut()
+                            .push(Command::TabCommand(TabCommand::Add(node)));
+                    }
+                });
+            });
+
+        if picked {
+            tree.set_focused_node(node);
+        }
+    }
+
+    // Ctrl+PageDown and Ctrl+PageUp go through the focused tab bar. Ctrl+Tab goes through all the
+    // tabs by when they were last shown, listing them while Ctrl is held and switching once it's
+    // let go. This is done before the editors are shown, they'd take the Tab for indenting
+    // otherwise
+    fn switch_tabs(ctx: &egui::Context, config: &mut Config) {
+        let dock = &mut config.dock;
+        let mut input = ctx.input_mut();
+
+        let forward = input.consume_key(Modifiers::COMMAND, Key::Tab);
+        let backward = input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Tab);
+        let held = input.modifiers.command;
+        let cancelled = input.key_pressed(Key::Escape);
+        drop(input);
+
+        // the recent tabs which are still open, then the ones which weren't shown yet
+        let tree = &mut dock.tree;
+        dock.recent_tabs
+            .retain(|id| tree.tabs().any(|tab| tab.id == *id));
+        let mut candidates = dock.recent_tabs.clone();
+        candidates.extend(
+            tree.tabs()
+                .map(|tab| tab.id)
+                .filter(|id| !dock.recent_tabs.contains(id)),
+        );
+
+        let count = candidates.len();
+        if count < 2 {
+            dock.tab_switcher = None;
+            return;
+        }
+
+        // the first Ctrl+Tab goes back to the tab before this one
+        dock.tab_switcher = match (dock.tab_switcher, forward, backward) {
+            (None, true, _) => Some(1),
+            (None, _, true) => Some(count - 1),
+            (Some(selected), true, _) => Some((selected + 1) % count),
+            (Some(selected), _, true) => Some((selected + count - 1) % count),
+            (selected, ..) => selected,
+        };
+
+        let Some(mut selected) = dock.tab_switcher else {
+            return;
+        };
+
+        if cancelled {
+            dock.tab_switcher = None;
+            return;
+        }
+
+        let mut picked = !held;
+
+        Area::new("tab_switcher")
+            .order(Order::Foreground)
+            .anchor(Align2::CENTER_TOP, vec2(0.0, TITLEBAR_HEIGHT as f32))
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    for (index, id) in candidates.iter().enumerate() {
+                        let name = tree
+                            .tabs()
+                            .find(|tab| tab.id == *id)
+                            .map_or("", |tab| &tab.name);
+
+                        if ui.selectable_label(index == selected, name).clicked() {
+                            selected = index;
+                            picked = true;
+                        }
+                    }
+                });
+            });
+
+        if !picked {
+            return;
+        }
+
+        dock.tab_switcher = None;
+
+        if let Some((node, tab_index)) = tree.find_tab_location(candidates[selected]) {
+            tree.set_focused_node(node);
+            tree.set_active_tab(node, tab_index);
+        }
+    }
 }
 
 type TabData = Data<Command>;
 
 struct TabViewer<'a> {
-    _ctx: &'a egui::Context,
+    ctx: &'a egui::Context,
     data: &'a TabData,
+    // tabs whose title was already asked for this frame
+    titled: HashSet<Id>,
+    crate_searches: &'a mut HashMap<Id, CrateSearch>,
+    run_states: &'a HashMap<Id, Arc<Mutex<RunState>>>,
+    // the time limit from the settings, shown as the default in the run configuration
+    default_timeout: Option<u64>,
+    // how the editors indent and close brackets
+    editor_settings: &'a Editor,
+    syntax_colors: &'a SyntaxColors,
+    // the welcome page's checkbox
+    show_welcome: &'a mut bool,
+    // how many tab bars the dock is split into
+    groups: usize,
+    // how wide each tab's title can be, longer ones are elided
+    title_widths: HashMap<Id, f32>,
 }
 
 impl<'a> TabViewer<'a> {
-    fn new(ctx: &'a egui::Context, data: &'a TabData) -> Self {
-        Self { _ctx: ctx, data }
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        ctx: &'a egui::Context,
+        data: &'a TabData,
+        crate_searches: &'a mut HashMap<Id, CrateSearch>,
+        run_states: &'a HashMap<Id, Arc<Mutex<RunState>>>,
+        default_timeout: Option<u64>,
+        editor_settings: &'a Editor,
+        syntax_colors: &'a SyntaxColors,
+        show_welcome: &'a mut bool,
+        groups: usize,
+        title_widths: HashMap<Id, f32>,
+    ) -> Self {
+        Self {
+            ctx,
+            data,
+            titled: HashSet::new(),
+            crate_searches,
+            run_states,
+            default_timeout,
+            editor_settings,
+            syntax_colors,
+            show_welcome,
+            groups,
+            title_widths,
+        }
+    }
+
+    // how the tab's last run went
+    fn last_run(&self, id: Id) -> Option<RunState> {
+        self.run_states
+            .get(&id)
+            .map(|run_state| *run_state.lock().unwrap())
     }
 }
 
@@ -112,25 +773,223 @@ impl egui_dock::TabViewer for TabViewer<'_> {
     type Tab = Tab;
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
+        if tab.welcome {
+            let commands = welcome_page(ui, self.show_welcome);
+            self.data
+                .borrow_mut()
+                .extend(commands.into_iter().map(Command::TabCommand));
+            return;
+        }
+
         // multiple tabs may be open on the screen, so we need to know if one is focused or not so we don't steal focus
-        ui.horizontal(|ui| {
-            if ui.button("Play").clicked() {
-                let mut data = self.data.borrow_mut();
-                data.push(Command::TabCommand(TabCommand::Play(tab.id)));
-            }
-        });
+        // generated code can't be run, so there's nothing to configure
+        if !tab.editor.read_only {
+            ui.horizontal(|ui| {
+                // wasm can't be run here, so it's only built
+                let play = match tab.run_config.subcommand() {
+                    Subcommand::Build => "Build",
+                    _ => "Play",
+                };
+
+                if ui.button(play).clicked() {
+                    let mut data = self.data.borrow_mut();
+                    data.push(Command::TabCommand(TabCommand::Play(tab.id)));
+                }
+
+                let mode = &mut tab.run_config.mode;
+                egui::ComboBox::from_id_source(tab.id.with("run_mode"))
+                    .selected_text(mode.name())
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        for option in RunMode::ALL {
+                            ui.selectable_value(mode, option, option.label());
+                        }
+                    });
+
+                if ui.button("Clippy").clicked() {
+                    let mut data = self.data.borrow_mut();
+                    data.push(Command::TabCommand(TabCommand::Clippy(tab.id)));
+                }
+
+                let search = self.crate_searches.entry(tab.id).or_default();
+
+                ui.menu_button("⚙", |ui| {
+                    run_config_menu(ui, &mut tab.run_config, self.default_timeout);
+
+                    if tab.run_config.subcommand() == Subcommand::Miri {
+                        ui.separator();
+
+                        ui.label("MIRIFLAGS");
+                        ui.add(
+                            TextEdit::singleline(&mut tab.miri_flags)
+                                .hint_text("-Zmiri-disable-isolation"),
+                        );
+                    }
+
+                    ui.separator();
+
+                    arguments_editor(ui, &mut tab.args, &mut tab.env_vars);
+
+                    ui.separator();
+
+                    dependencies_editor(ui, &mut tab.dependencies, search);
+                })
+                .response
+                .on_hover_text(format!("Run configuration: {}", tab.run_config.summary()));
+            });
+        }
+
+        if tab.loading {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("Generating…");
+            });
+        }
+
+        if tab.disk_change.is_some() {
+            disk_change_banner(ui, tab);
+        }
+
+        if !tab.editor.read_only {
+            file_strip(ui, tab);
+        }
 
         ui.vertical_centered(|ui| {
-            tab.scroll_offset = Some(tab.editor.show(
-                tab.id.with("code_editor"),
+            let editor_id = tab.editor_id(tab.open_file);
+
+            // the diagnostics are only read for main.rs
+            let (editor, diagnostics) =
+                match tab.open_file.and_then(|index| tab.files.get_mut(index)) {
+                    Some(file) => (&mut file.editor, &[][..]),
+                    None => (&mut tab.editor, &tab.check.diagnostics[..]),
+                };
+
+            let response = editor.show(
+                editor_id,
                 ui,
                 tab.scroll_offset.unwrap_or_default(),
-            ));
+                diagnostics,
+                &mut tab.search,
+                self.editor_settings,
+                tab.indent.unwrap_or_else(|| self.editor_settings.indent()),
+                self.syntax_colors,
+            );
+
+            tab.scroll_offset = Some(response.scroll_offset);
+
+            if response.changed {
+                tab.check.code_changed();
+            }
+            if response.save {
+                self.data
+                    .borrow_mut()
+                    .push(Command::MenuCommand(MenuCommand::Save(tab.id)));
+            }
+            if response.close {
+                self.data
+                    .borrow_mut()
+                    .push(Command::TabCommand(TabCommand::CloseTabs {
+                        tabs: vec![tab.id],
+                        unsaved: Unsaved::Ask,
+                        exit: false,
+                    }));
+            }
         });
     }
 
     fn title(&mut self, tab: &mut Self::Tab) -> egui::WidgetText {
-        (&*tab.name).into()
+        // the first title of the frame is the tab's label. It's only asked for again for the hover
+        // tooltip, which also shows how the tab is run
+        let (errors, warnings) = diagnostics::counts(&tab.check.diagnostics);
+        let last_run = self.last_run(tab.id);
+        let failed_tests = last_run
+            .and_then(|run| run.tests)
+            .map_or(0, |tests| tests.failed);
+        let undefined_behavior = matches!(last_run, Some(run) if run.undefined_behavior);
+
+        let name = match (tab.pinned, tab.is_dirty()) {
+            (true, true) => Cow::Owned(format!("📌 • {}", tab.name)),
+            (true, false) => Cow::Owned(format!("📌 {}", tab.name)),
+            (false, true) => Cow::Owned(format!("• {}", tab.name)),
+            (false, false) => Cow::Borrowed(&tab.name),
+        };
+
+        if !self.titled.insert(tab.id) {
+            let mut tooltip = format!("{}\n{}", tab.name, tab.run_config.summary());
+            if tab.is_dirty() {
+                tooltip.push_str("\nUnsaved changes");
+            }
+            if errors + warnings > 0 {
+                tooltip.push_str(&format!("\n{errors} errors, {warnings} warnings"));
+            }
+            if failed_tests > 0 {
+                tooltip.push_str(&format!("\n{failed_tests} tests failed"));
+            }
+            if undefined_behavior {
+                tooltip.push_str("\nMiri found undefined behavior");
+            }
+
+            return tooltip.into();
+        }
+
+        // a colored count of each, next to the name. The name goes red when tests failed or Miri
+        // found undefined behavior
+        let style = self.ctx.style();
+        let font_id = TextStyle::Button.resolve(&style);
+        let format = |color| TextFormat::simple(font_id.clone(), color);
+
+        let mut badges = vec![];
+        for (count, color) in [
+            (errors, style.visuals.error_fg_color),
+            (warnings, style.visuals.warn_fg_color),
+        ] {
+            if count > 0 {
+                badges.push((format!("●{count}"), color));
+            }
+        }
+        if undefined_behavior {
+            badges.push(("UB".to_string(), style.visuals.error_fg_color));
+        }
+
+        // names which don't fit the tab bar are cut short, the badges are always shown
+        let name = match self.title_widths.get(&tab.id) {
+            Some(max_width) => {
+                let text_width = |text: &str| {
+                    self.ctx
+                        .fonts()
+                        .layout_no_wrap(text.to_string(), font_id.clone(), Color32::WHITE)
+                        .size()
+                        .x
+                };
+                let badges_width = badges
+                    .iter()
+                    .map(|(badge, _)| 6.0 + text_width(badge))
+                    .sum::<f32>();
+
+                Cow::Owned(
+                    tab_names::elide(&name, max_width - badges_width, text_width).into_owned(),
+                )
+            }
+            None => name,
+        };
+
+        if badges.is_empty() && failed_tests == 0 {
+            return (&*name).into();
+        }
+
+        let name_color = if failed_tests > 0 || undefined_behavior {
+            style.visuals.error_fg_color
+        } else {
+            style.visuals.text_color()
+        };
+
+        let mut job = LayoutJob::default();
+        job.append(&name, 0.0, format(name_color));
+        for (badge, color) in badges {
+            job.append(&badge, 6.0, format(color));
+        }
+
+        job.into()
     }
 
     fn on_add(&mut self, node: NodeIndex) {
@@ -141,16 +1000,70 @@ impl egui_dock::TabViewer for TabViewer<'_> {
     fn context_menu(&mut self, ui: &mut Ui, tab: &mut Self::Tab) {
         let mut data = self.data.borrow_mut();
 
+        // there's nothing to rename or save on the welcome page
+        if tab.welcome {
+            data.extend(tab_buttons(ui, tab, self.groups).map(Command::TabCommand));
+            return;
+        }
+
         let rename_btn = ui.button("Rename".to_string()).clicked();
         let save_btn = ui.button("Save...".to_string()).clicked();
         let share_btn = ui.button("Share to Playground".to_string()).clicked();
+        let permalink_btn = ui.button("Copy playground link").clicked();
 
         let mut command = None;
 
+        // generated code isn't rust, so it can't be built
+        if tab.editor.read_only {
+            if rename_btn {
+                command = Some(MenuCommand::Rename(tab.id));
+            } else if save_btn {
+                command = Some(MenuCommand::Save(tab.id));
+            }
+
+            if let Some(command) = command {
+                data.push(Command::MenuCommand(command));
+                ui.close_menu();
+            }
+
+            data.extend(tab_buttons(ui, tab, self.groups).map(Command::TabCommand));
+            return;
+        }
+
+        let format_btn = ui.button("Format".to_string()).clicked();
+        let clippy_btn = ui.button("Clippy".to_string()).clicked();
+        let playground_btn = ui.button("Run on playground").clicked();
+        let expand_btn = ui.button("Expand macros".to_string()).clicked();
+        let export_btn = ui.button("Export as project…".to_string()).clicked();
+
+        ui.menu_button("Emit…", |ui| {
+            for kind in EmitKind::ALL {
+                if ui.button(kind.name()).clicked() {
+                    command = Some(MenuCommand::Emit(tab.id, kind));
+                }
+            }
+        });
+
         if rename_btn {
             command = Some(MenuCommand::Rename(tab.id));
         }
 
+        if format_btn {
+            command = Some(MenuCommand::Format(tab.id));
+        }
+
+        if expand_btn {
+            command = Some(MenuCommand::Expand(tab.id));
+        }
+
+        if export_btn {
+            command = Some(MenuCommand::Export(tab.id));
+        }
+
+        if permalink_btn {
+            command = Some(MenuCommand::Permalink(tab.id));
+        }
+
         if save_btn || share_btn {
             command = Some(if save_btn {
                 MenuCommand::Save(tab.id)
@@ -163,11 +1076,41 @@ impl egui_dock::TabViewer for TabViewer<'_> {
             data.push(Command::MenuCommand(command));
             ui.close_menu();
         }
+
+        if clippy_btn {
+            data.push(Command::TabCommand(TabCommand::Clippy(tab.id)));
+            ui.close_menu();
+        }
+
+        if playground_btn {
+            data.push(Command::TabCommand(TabCommand::RunOnPlayground(tab.id)));
+            ui.close_menu();
+        }
+
+        data.extend(tab_buttons(ui, tab, self.groups).map(Command::TabCommand));
     }
 
     fn on_close(&mut self, tab: &mut Self::Tab) -> bool {
+        if tab.pinned {
+            return false;
+        }
+
         let mut data = self.data.borrow_mut();
-        data.push(Command::TabCommand(TabCommand::Close(tab.id)));
+
+        // closed once the user says what to do with the changes
+        if tab.is_dirty() {
+            data.push(Command::TabCommand(TabCommand::CloseTabs {
+                tabs: vec![tab.id],
+                unsaved: Unsaved::Ask,
+                exit: false,
+            }));
+
+            return false;
+        }
+
+        data.push(Command::TabCommand(TabCommand::Close(Box::new(
+            tab.clone(),
+        ))));
 
         true
     }
@@ -178,40 +1121,179 @@ pub struct TabEvents;
 
 impl TabEvents {
     pub fn show(ctx: &egui::Context, config: &mut Config) {
+        Self::handle_dropped_files(ctx, config);
+        Self::handle_open_requests(config);
+
+        // dialogs raised while handling commands, added to the command list afterwards
+        let mut dialogs = vec![];
+        // tabs which were saved, for auto-running them
+        let mut saved = vec![];
+        // commands for closing tabs once their unsaved changes are dealt with
+        let mut closing = vec![];
+
         // Functions which return false remove their item from the vec.
         config.dock.commands.retain(|i| match i {
             Command::MenuCommand(command) => match command {
                 MenuCommand::Rename(v) => Self::show_rename_window(ctx, *v, &mut config.dock.tree),
-                MenuCommand::Save(_) => todo!(),
-                MenuCommand::Share(v) => {
-                    Self::share_scratch(*v, &mut config.dock.tree, &config.github)
-                }
-            },
-
-            Command::TabCommand(command) => match command {
-                TabCommand::Add(v) => {
-                    let name = format!("Scratch {}", config.dock.counter);
-
-                    let node_tabs = &config.dock.tree[*v];
-
-                    let tab = Tab {
-                        // unique name based on current nodeindex + tabindex
-                        id: Id::new(format!("{name}-{}-{}", v.0, node_tabs.tabs_count() + 1)),
-                        name,
-                        editor: CodeEditor::default(),
-                        scroll_offset: None,
-                    };
-
-                    config.dock.tree.set_focused_node(*v);
-                    config.dock.tree.push_to_focused_leaf(tab);
+                MenuCommand::Save(v) => Self::save_scratch(
+                    ctx,
+                    *v,
+                    &mut config.dock.tree,
+                    &mut config.dock.pending_saves,
+                    &mut dialogs,
+                    &mut saved,
+                    &config.editor,
+                    config.editor.format_on_save.then_some(&mut config.terminal),
+                ),
+                MenuCommand::Share(v) => Self::share_scratch(
+                    ctx,
+                    *v,
+                    &mut config.dock.tree,
+                    &mut config.github,
+                    &mut config.dock.pending_shares,
+                    &mut dialogs,
+                ),
+                MenuCommand::Emit(v, kind) => Self::emit_scratch(
+                    ctx,
+                    *v,
+                    *kind,
+                    &mut config.dock.tree,
+                    &mut config.dock.pending_emits,
+                    &mut config.terminal,
+                ),
+                MenuCommand::Expand(v) => Self::expand_scratch(
+                    ctx,
+                    *v,
+                    &mut config.dock.tree,
+                    &mut config.dock.pending_expands,
+                    &mut config.terminal,
+                    &mut dialogs,
+                ),
+                MenuCommand::Export(v) => Self::export_scratch(
+                    ctx,
+                    *v,
+                    &mut config.dock.tree,
+                    &mut config.dock.pending_exports,
+                    &mut dialogs,
+                ),
+                MenuCommand::Format(v) => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*v) {
+                        Self::format_tab(ctx, tab, &mut config.terminal);
+                    }
 
-                    config.dock.counter += 1;
+                    false
+                }
+                MenuCommand::ToggleComment(v) => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*v) {
+                        Self::edit_code(tab, |editor, id| editor.toggle_comment(ctx, id));
+                    }
 
                     false
                 }
+                MenuCommand::EditLines(v, edit) => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*v) {
+                        Self::edit_code(tab, |editor, id| editor.edit_lines(ctx, id, *edit));
+                    }
 
-                TabCommand::Close(id) => {
-                    // TODO: Remove TextEditState from closed tabs so they aren't reused with the same ID
+                    false
+                }
+                MenuCommand::JumpToBracket(v) => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*v) {
+                        let editor = match tab.open_file.and_then(|index| tab.files.get(index)) {
+                            Some(file) => &file.editor,
+                            None => &tab.editor,
+                        };
+                        editor.jump_to_bracket(ctx, tab.editor_id(tab.open_file));
+                    }
+
+                    false
+                }
+                MenuCommand::SelectNextOccurrence(v) => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*v) {
+                        let editor = match tab.open_file.and_then(|index| tab.files.get(index)) {
+                            Some(file) => &file.editor,
+                            None => &tab.editor,
+                        };
+                        editor.select_next_occurrence(ctx, tab.editor_id(tab.open_file));
+                    }
+
+                    false
+                }
+                MenuCommand::GoToDefinition(v) => {
+                    let tab = config.dock.tree.find_tab_mut(*v);
+                    // the server only has the tab's main.rs open
+                    if let (Some(tab), Some(client)) = (tab, config.dock.lsp_clients.get_mut(v)) {
+                        if tab.open_file.is_none() {
+                            let at = tab.editor.cursor(ctx, tab.editor_id(None));
+                            client.definition(&tab.editor.code, at);
+                        }
+                    }
+
+                    false
+                }
+                MenuCommand::Permalink(v) => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*v) {
+                        dialogs.push(Self::copy_permalink(ctx, tab));
+                    }
+
+                    false
+                }
+            },
+
+            Command::TabCommand(command) => match command {
+                TabCommand::Add(v) => {
+                    let tree = &mut config.dock.tree;
+                    let name = tab_names::scratch_name(tree.tabs().map(|tab| tab.name.as_str()));
+                    let tab = Tab::scratch(name, tab_names::scratch_id(config.dock.counter));
+
+                    tree.set_focused_node(*v);
+                    tree.push_to_focused_leaf(tab);
+
+                    config.dock.counter += 1;
+
+                    false
+                }
+
+                TabCommand::NewScratch(example) => {
+                    let tree = &mut config.dock.tree;
+                    let mut tab = Tab::scratch(
+                        tab_names::scratch_name(tree.tabs().map(|tab| tab.name.as_str())),
+                        tab_names::scratch_id(config.dock.counter),
+                    );
+                    if let Some(example) = example.and_then(|index| EXAMPLES.get(index)) {
+                        tab.name = example.name.to_string();
+                        tab.editor = CodeEditor::new("rs", example.code.to_string());
+                    }
+
+                    tree.push_to_focused_leaf(tab);
+                    config.dock.counter += 1;
+
+                    false
+                }
+
+                TabCommand::OpenSettings => {
+                    config.settings.open = true;
+
+                    false
+                }
+
+                TabCommand::Close(tab) => {
+                    let id = &tab.id;
+
+                    // don't leave the tab's process running in the background
+                    config.terminal.kill(ctx, *id);
+                    config.terminal.remove_tab(*id);
+                    config.dock.crate_searches.remove(id);
+                    config.dock.recent_tabs.retain(|recent| recent != id);
+
+                    let closed_tabs = &mut config.dock.closed_tabs;
+                    closed_tabs.push_front(Tab {
+                        loading: false,
+                        ..(**tab).clone()
+                    });
+                    closed_tabs.truncate(CLOSED_TABS);
+
+                    // TODO: Remove TextEditState from closed tabs so they aren't reused with the same ID
                     let editor_id = id.with("code_edit");
 
                     // cleanup old textedit state
@@ -221,38 +1303,231 @@ impl TabEvents {
                     //ctx.memory().data.remove::<TextEditState>(editor_id);
 
                     if config.dock.tree.num_tabs() == 0 {
-                        let tab = Tab {
-                            name: "Scratch 1".to_string(),
-                            editor: CodeEditor::default(),
-                            id: Id::new("Scratch 1"),
-                            scroll_offset: None,
-                        };
+                        // a new id, egui still has the state of the closed tabs' widgets
+                        let id = tab_names::scratch_id(config.dock.counter);
+                        let tab = Tab::scratch("Scratch 1".to_string(), id);
 
                         config.dock.tree.set_focused_node(NodeIndex(0));
                         config.dock.tree.push_to_focused_leaf(tab);
 
-                        config.dock.counter = 2;
+                        config.dock.counter += 1;
                     }
 
                     false
                 }
 
-                TabCommand::Play(id) => {
-                    let tab = &mut config
+                TabCommand::Clippy(id) => {
+                    let Some(tab) = config.dock.tree.find_tab_mut(*id) else {
+                        return false;
+                    };
+
+                    // a check which is still going only has the compiler's half of it
+                    let rx = Self::check_tab(ctx, tab, Subcommand::Clippy);
+                    config
                         .dock
-                        .tree
-                        .iter_mut()
-                        .filter_map(|node| {
-                            let Node::Leaf { tabs, .. } = node else {
-                                return None;
-                            };
+                        .pending_checks
+                        .insert(tab.id, (tab.check.revision, rx));
 
-                            tabs.iter_mut().find(|tab| tab.id == *id)
-                        })
-                        .collect::<SmallVec<[&mut Tab; 1]>>()[0];
+                    false
+                }
+
+                TabCommand::GoToLine { tab, line, col } => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*tab) {
+                        Self::go_to_line(ctx, tab, *line, *col);
+                    }
+
+                    false
+                }
+
+                TabCommand::RunOnPlayground(id) => {
+                    if let Some(tab) = config.dock.tree.find_tab_mut(*id) {
+                        Self::run_on_playground(ctx, tab, &mut config.terminal);
+                    }
+
+                    false
+                }
+
+                TabCommand::CloseTabs {
+                    tabs,
+                    unsaved,
+                    exit,
+                } => Self::close_tabs(
+                    ctx,
+                    tabs,
+                    *unsaved,
+                    *exit,
+                    &mut config.dock.tree,
+                    &config.dock.pending_saves,
+                    &mut config.dock.exiting,
+                    &mut closing,
+                ),
+
+                TabCommand::CloseAll | TabCommand::CloseOthers(_) | TabCommand::CloseToRight(_) => {
+                    let tree = &config.dock.tree;
+                    let tabs = match command {
+                        TabCommand::CloseOthers(id) => {
+                            tree.tabs().map(|tab| tab.id).filter(|tab| tab != id).collect()
+                        }
+                        TabCommand::CloseToRight(id) => tabs_to_the_right(tree, *id),
+                        _ => tree.tabs().map(|tab| tab.id).collect(),
+                    };
+
+                    closing.push(Command::TabCommand(TabCommand::CloseTabs {
+                        tabs,
+                        unsaved: Unsaved::Ask,
+                        exit: false,
+                    }));
+
+                    false
+                }
+
+                TabCommand::Reopen(id) => {
+                    let closed_tabs = &mut config.dock.closed_tabs;
+                    let index = match id {
+                        Some(id) => closed_tabs.iter().position(|tab| tab.id == *id),
+                        None => Some(0),
+                    };
+
+                    if let Some(tab) = index.and_then(|index| closed_tabs.remove(index)) {
+                        config.dock.tree.push_to_focused_leaf(tab);
+                    }
+
+                    false
+                }
+
+                TabCommand::Duplicate(id) => {
+                    Self::duplicate_tab(&mut config.dock.tree, *id);
+
+                    false
+                }
+
+                TabCommand::Split { tab, split, copy } => {
+                    Self::split_tab(&mut config.dock.tree, *tab, *split, *copy);
+
+                    false
+                }
+
+                TabCommand::MoveToOtherGroup(id) => {
+                    Self::move_to_other_group(&mut config.dock.tree, *id);
+
+                    false
+                }
+
+                TabCommand::Open => Self::open_dialog(
+                    ctx,
+                    &mut config.dock.tree,
+                    &mut config.dock.pending_open,
+                    &mut config.recent_files,
+                    &mut dialogs,
+                ),
+
+                TabCommand::OpenFile(path) => {
+                    Self::open_file(
+                        &mut config.dock.tree,
+                        path,
+                        &mut config.recent_files,
+                        &mut dialogs,
+                    );
+
+                    false
+                }
+
+                TabCommand::Play(id) | TabCommand::AutoRun(id) => {
+                    let Some(tab) = config.dock.tree.find_tab_mut(*id) else {
+                        return false;
+                    };
+
+                    let automatic = matches!(command, TabCommand::AutoRun(_));
+
+                    // the user's own runs aren't interrupted, auto-run picks up again after them
+                    if automatic
+                        && (tab.editor.read_only || config.terminal.is_running_manually(ctx, *id))
+                    {
+                        return false;
+                    }
+
+                    // auto-runs come back with every edit, so they don't keep opening dialogs
+                    let terminal = &mut config.terminal;
+                    let mut report = |title: &str, message: String| {
+                        if automatic {
+                            terminal.show_message(
+                                *id,
+                                Stream::Stderr,
+                                format!("\x1b[1;31merror\x1b[0m: {title}\n\n{message}\n"),
+                            );
+                        } else {
+                            dialogs.push(DialogCommand::error(title, message));
+                        }
+                    };
+
+                    let subcommand = tab.run_config.subcommand();
+
+                    // checked before anything is started, so it can be explained in a dialog
+                    if subcommand == Subcommand::Miri && !MIRI_FOUND.load(Ordering::Relaxed) {
+                        match miri::check_installed() {
+                            Ok(()) => MIRI_FOUND.store(true, Ordering::Relaxed),
+                            Err(e) => {
+                                report("Can't run Miri", e.to_string());
+                                return false;
+                            }
+                        }
+                    }
+
+                    if let Some(triple) = tab.run_config.target.triple() {
+                        let channel = tab.run_config.channel;
+                        let mut found = WASM_TOOLCHAINS.lock().unwrap();
+
+                        if !found.contains(&channel) {
+                            match wasm::check_target_installed(channel, triple) {
+                                Ok(()) => found.push(channel),
+                                Err(e) => {
+                                    report("Can't build for wasm", e.to_string());
+                                    return false;
+                                }
+                            }
+                        }
+                    }
+
+                    // split like a shell would, so quoted arguments stay together
+                    let args = match shell_words::split(&tab.args) {
+                        Ok(args) => args,
+                        Err(e) => {
+                            report(
+                                "Invalid arguments",
+                                format!("The arguments couldn't be split: {e}"),
+                            );
+                            return false;
+                        }
+                    };
 
                     let id = *id;
+                    config.dock.ran_revisions.insert(id, tab.check.revision);
+
                     let code = tab.editor.code.clone();
+                    let files = tab.module_files();
+                    let mut run_config = tab.run_config;
+                    let dependencies = tab.dependencies.clone();
+                    let miri_flags = tab.miri_flags.clone();
+                    let env_vars: Vec<EnvVar> = tab
+                        .env_vars
+                        .iter()
+                        .filter(|env_var| !env_var.key.trim().is_empty())
+                        .cloned()
+                        .collect();
+
+                    let timeout = run_config.timeout.limit(config.runner.timeout_secs);
+
+                    // miri is only on nightly
+                    if subcommand == Subcommand::Miri {
+                        run_config.channel = Channel::Nightly;
+                    }
+
+                    // a wasm build's module is disassembled once it's built
+                    let wat_tx = (subcommand == Subcommand::Build).then(|| {
+                        let (tx, rx) = channel();
+                        config.dock.pending_wats.insert(id, rx);
+                        tx
+                    });
 
                     // this are used as a thread abort signaler
                     let (atx, arx) = channel();
@@ -264,7 +1539,6 @@ impl TabEvents {
 
                     let prev = config.terminal.abortable.insert(id, abort_id);
                     // if there's a previous process running, send the signal abort
-                    type Aborter = Arc<Mutex<Sender<()>>>;
                     if let Some(atx) = prev {
                         let mut mem = ctx.memory();
                         if mem.data.get_temp::<Aborter>(atx).is_some() {
@@ -276,23 +1550,34 @@ impl TabEvents {
                         .data
                         .insert_temp::<Aborter>(abort_id, Arc::new(Mutex::new(atx)));
 
-                    // these are used to stream the terminal output
-                    let rb_stdout = HeapRb::<String>::new(30);
-                    let rb_stderr = HeapRb::<String>::new(30);
+                    // this is used to stream the terminal output. Both streams share it so the output keeps
+                    // the order it arrived in. Sending waits while it's full, and fails once the
+                    // terminal stopped taking the tab's output, like when it's closed or run again
+                    let (output_tx, output_rx) = sync_channel::<OutputChunk>(OUTPUT_BUFFER);
+                    let stdout_tx = output_tx.clone();
+                    let stderr_tx = output_tx.clone();
 
-                    let (mut rb_stdout, rb_stdout_read) = rb_stdout.split();
-                    let (mut rb_stderr, rb_stderr_read) = rb_stderr.split();
+                    config.terminal.content.insert(id, Some(output_rx));
 
-                    config
-                        .terminal
-                        .content
-                        .insert(id, Some((rb_stdout_read, rb_stderr_read)));
+                    // input for the process is sent through this, so the ui never waits on the pipe
+                    let (stdin_tx, stdin_rx) = channel::<String>();
+                    config.terminal.stdin.insert(id, stdin_tx);
+
+                    let run_state = Arc::new(Mutex::new(RunState {
+                        run_config,
+                        automatic,
+                        ..Default::default()
+                    }));
+                    config.terminal.start_run(id, Arc::clone(&run_state));
 
                     let owned_ctx = ctx.clone();
 
-                    config.terminal.started_run = true;
+                    if !config.terminal.open {
+                        config.terminal.toggle();
+                    }
 
                     thread::spawn(move || {
+                        let tab_id = id;
                         let id = Id::new("continuous_mode");
 
                         let ctx = owned_ctx;
@@ -304,53 +1589,216 @@ impl TabEvents {
                             *counter += 1;
                         }
 
-                        let mut command = Project::new(id)
-                            .build_type(BuildType::Debug)
-                            .channel(Channel::Stable)
-                            .file(File::new("main", &code))
-                            .edition(Edition::E2021)
-                            .subcommand(Subcommand::Run)
+                        // record how the run ended, and let the ui know nothing is running anymore
+                        let finish = |status| {
+                            tracing::info!("run of {tab_id:?} finished: {status:?}");
+                            {
+                                let mut run_state = run_state.lock().unwrap();
+                                run_state.duration = Some(run_state.start.elapsed());
+                                run_state.status = status;
+                            }
+
+                            {
+                                let mut mem = ctx.memory();
+                                let counter = mem.data.get_temp_mut_or_default::<u64>(id);
+                                *counter -= 1;
+
+                                let aborter = mem.data.get_temp::<Aborter>(abort_id);
+                                if aborter.is_some() {
+                                    mem.data.remove::<Aborter>(abort_id);
+                                }
+                            }
+
+                            // show the final run status
+                            ctx.request_repaint();
+                        };
+
+                        let cache_dir = build_cache::cache_dir();
+                        let mut project = tab_project(
+                            tab_id,
+                            &code,
+                            &files,
+                            run_config,
+                            &dependencies,
+                            &cache_dir,
+                        );
+                        project
+                            .subcommand(subcommand)
                             .target_prefix("rust-play")
                             .env_var("CARGO_TERM_COLOR", "always")
                             .env_var("CARGO_TERM_PROGRESS_WHEN", "always")
-                            .env_var("CARGO_TERM_PROGRESS_WIDTH", "150")
-                            .create()
-                            .expect("Oh no");
+                            .env_var("CARGO_TERM_PROGRESS_WIDTH", "150");
+
+                        if subcommand == Subcommand::Miri {
+                            project
+                                .subcommand_flag("run")
+                                .env_var("MIRIFLAGS", miri_flags.trim());
+                        }
+
+                        if let Some(triple) = run_config.target.triple() {
+                            project.subcommand_flags(&["--target", triple]);
+                        }
+
+                        // only what runs the program takes its arguments
+                        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                        if matches!(
+                            subcommand,
+                            Subcommand::Run | Subcommand::Test | Subcommand::Miri
+                        ) && !args.is_empty()
+                        {
+                            project.dash_args(&args);
+                        }
+
+                        for env_var in &env_vars {
+                            project.env_var(env_var.key.trim(), &env_var.value);
+                        }
+
+                        // the list wins, but the comment may have been the one that was meant
+                        for name in project.overridden_deps().unwrap_or_default() {
+                            let _ = output_tx.send(OutputChunk::new(
+                                Stream::Stderr,
+                                format!(
+                                    "\x1b[1;33mwarning\x1b[0m: `{name}` is in both the dependencies list and a //# comment, using the list's version\n"
+                                ),
+                            ));
+                        }
+
+                        let mut command = match project.create() {
+                            Ok(command) => command,
+                            Err(e) => {
+                                tracing::warn!("the project for {tab_id:?} couldn't be made: {e}");
+
+                                // shown like a cargo error, so the location links back to the scratch
+                                let message = match &e {
+                                    ProjectError::ProjectBuildError(
+                                        ProjectBuildError::Dependency(e),
+                                    ) => format!(
+                                        "\x1b[1;31merror\x1b[0m: {e}\n  \x1b[1;34m-->\x1b[0m src/{}.rs:{}:{}\n",
+                                        e.file, e.line, e.col
+                                    ),
+                                    e => format!("\x1b[1;31merror\x1b[0m: {e}\n"),
+                                };
+
+                                let _ = output_tx.send(OutputChunk::new(Stream::Stderr, message));
+
+                                finish(RunStatus::Failed);
+                                return;
+                            }
+                        };
 
                         // hide the console window from command. Very important.
                         #[cfg(target_os = "windows")]
                         command.creation_flags(CREATE_NO_WINDOW.0);
 
-                        let mut child = command
+                        // dimmed, so it's clear what was run without it looking like the output
+                        let _ = output_tx.send(OutputChunk::new(
+                            Stream::Stdout,
+                            format!("\x1b[90m$ {}\x1b[0m\n", command_line(&command, &env_vars)),
+                        ));
+
+                        let spawned = command
+                            .stdin(Stdio::piped())
                             .stderr(Stdio::piped())
                             .stdout(Stdio::piped())
-                            .spawn()
-                            .unwrap();
+                            .spawn();
+
+                        // like when cargo isn't on the PATH
+                        let mut child = match spawned {
+                            Ok(child) => child,
+                            Err(e) => {
+                                tracing::warn!("cargo couldn't be started for {tab_id:?}: {e}");
+
+                                let _ = output_tx.send(OutputChunk::new(
+                                    Stream::Stderr,
+                                    format!("\x1b[1;31merror\x1b[0m: failed to run cargo: {e}\n"),
+                                ));
+
+                                finish(RunStatus::Failed);
+                                return;
+                            }
+                        };
+
+                        let (Some(mut stdin), Some(stdout), Some(stderr)) =
+                            (child.stdin.take(), child.stdout.take(), child.stderr.take())
+                        else {
+                            let _ = child.kill();
+                            let _ = output_tx.send(OutputChunk::new(
+                                Stream::Stderr,
+                                "\x1b[1;31merror\x1b[0m: failed to run cargo: no pipes\n".into(),
+                            ));
+
+                            finish(RunStatus::Failed);
+                            return;
+                        };
+
+                        // the terminal dropping the sender closes stdin, which the process sees as EOF
+                        thread::spawn(move || {
+                            for input in stdin_rx {
+                                if stdin
+                                    .write_all(input.as_bytes())
+                                    .and_then(|_| stdin.flush())
+                                    .is_err()
+                                {
+                                    break;
+                                }
+                            }
+                        });
+
+                        // shared with the abort thread, so we can still get the exit status when it's done
+                        let child = Arc::new(Mutex::new(child));
+                        let abort_child = Arc::clone(&child);
 
-                        let stdout = child.stdout.take().unwrap();
-                        let stderr = child.stderr.take().unwrap();
+                        // set when the user stopped the process, as opposed to it just finishing
+                        let killed = Arc::new(AtomicBool::new(false));
+                        let killed_by_user = Arc::clone(&killed);
+                        // set when the process ran out of time
+                        let timed_out = Arc::new(AtomicBool::new(false));
+                        let timed_out_by_limit = Arc::clone(&timed_out);
+                        let abort_run_state = Arc::clone(&run_state);
 
                         // special thread which checks for abort code
                         thread::spawn(move || {
-                            // blocking wait for abort
-                            let _ = arx.recv();
-                            let _ = child.kill();
+                            // wait for abort. A message is only ever sent by the stop button, the
+                            // sender simply gets dropped otherwise. In between, see if the time is up
+                            loop {
+                                match arx.recv_timeout(Duration::from_millis(100)) {
+                                    Ok(_) => {
+                                        killed_by_user.store(true, Ordering::Relaxed);
+                                        break;
+                                    }
+                                    Err(RecvTimeoutError::Disconnected) => break,
+                                    Err(RecvTimeoutError::Timeout) => {
+                                        let deadline = abort_run_state.lock().unwrap().deadline;
+                                        let now = Instant::now();
+                                        if matches!(deadline, Some(deadline) if now >= deadline) {
+                                            timed_out_by_limit.store(true, Ordering::Relaxed);
+                                            break;
+                                        }
+                                    }
+                                }
+                            }
+
+                            let _ = abort_child.lock().unwrap().kill();
                         });
 
+                        // the test summaries are counted as they come in, so the status shows them while
+                        // it's still running
+                        let test_run_state =
+                            (subcommand == Subcommand::Test).then(|| Arc::clone(&run_state));
+
                         let stdout_handle = thread::spawn(move || {
                             let stdout_reader = BufReader::new(stdout);
 
-                            let mut send = move |line| {
-                                if rb_stdout.is_full() {
-                                    while rb_stdout.is_full() {
-                                        if !rb_stdout.is_full() {
-                                            let _ = rb_stdout.push(line);
-                                            break;
-                                        }
+                            let send = move |line: String| {
+                                if let Some(run_state) = &test_run_state {
+                                    let mut run_state = run_state.lock().unwrap();
+                                    let mut tests = run_state.tests.unwrap_or_default();
+                                    if tests.add_summary(&line) {
+                                        run_state.tests = Some(tests);
                                     }
-                                } else {
-                                    let _ = rb_stdout.push(line);
                                 }
+
+                                stdout_tx.send(OutputChunk::new(Stream::Stdout, line))
                             };
 
                             // we need to split lines based on newline OR \r, so we can display dynamic output lines
@@ -360,10 +1808,10 @@ impl TabEvents {
                                     if b == b'\n' || b == b'\r' {
                                         buf.push(b);
 
-                                        let line = String::from_utf8_lossy(&buf);
-                                        match line {
-                                            Cow::Borrowed(b) => send(b.to_string()),
-                                            Cow::Owned(o) => send(o),
+                                        // nothing's shown anymore, closing the pipe tells the child
+                                        let line = String::from_utf8_lossy(&buf).into_owned();
+                                        if send(line).is_err() {
+                                            return;
                                         }
 
                                         buf.clear();
@@ -380,28 +1828,36 @@ impl TabEvents {
                             // flush remaining output
                             if !buf.is_empty() {
                                 buf.push(b'\n');
-                                let line = String::from_utf8_lossy(&buf);
-                                match line {
-                                    Cow::Borrowed(b) => send(b.to_string()),
-                                    Cow::Owned(o) => send(o),
-                                }
+                                let _ = send(String::from_utf8_lossy(&buf).into_owned());
                             }
                         });
 
+                        let miri_run_state =
+                            (subcommand == Subcommand::Miri).then(|| Arc::clone(&run_state));
+                        // the time limit is for the program, so building it doesn't count toward it
+                        let timeout_run_state =
+                            timeout.map(|timeout| (timeout, Arc::clone(&run_state)));
+
                         let stderr_handle = thread::spawn(move || {
                             let stderr_reader = BufReader::new(stderr);
 
-                            let mut send = move |line| {
-                                if rb_stderr.is_full() {
-                                    while rb_stderr.is_full() {
-                                        if !rb_stderr.is_full() {
-                                            let _ = rb_stderr.push(line);
-                                            break;
-                                        }
+                            let send = move |line: String| {
+                                if let Some(run_state) = &miri_run_state {
+                                    if miri::is_undefined_behavior(&line) {
+                                        run_state.lock().unwrap().undefined_behavior = true;
+                                    }
+                                }
+
+                                if let Some((timeout, run_state)) = &timeout_run_state {
+                                    let mut run_state = run_state.lock().unwrap();
+                                    if run_state.deadline.is_none()
+                                        && cargo_output::starts_program(&line)
+                                    {
+                                        run_state.deadline = Some(Instant::now() + *timeout);
                                     }
-                                } else {
-                                    let _ = rb_stderr.push(line);
                                 }
+
+                                stderr_tx.send(OutputChunk::new(Stream::Stderr, line))
                             };
 
                             // we need to split lines based on newline OR \r, so we can display dynamic output lines
@@ -411,10 +1867,10 @@ impl TabEvents {
                                     if b == b'\n' || b == b'\r' {
                                         buf.push(b);
 
-                                        let line = String::from_utf8_lossy(&buf);
-                                        match line {
-                                            Cow::Borrowed(b) => send(b.to_string()),
-                                            Cow::Owned(o) => send(o),
+                                        // nothing's shown anymore, closing the pipe tells the child
+                                        let line = String::from_utf8_lossy(&buf).into_owned();
+                                        if send(line).is_err() {
+                                            return;
                                         }
 
                                         buf.clear();
@@ -431,11 +1887,7 @@ impl TabEvents {
                             // flush remaining output
                             if !buf.is_empty() {
                                 buf.push(b'\n');
-                                let line = String::from_utf8_lossy(&buf);
-                                match line {
-                                    Cow::Borrowed(b) => send(b.to_string()),
-                                    Cow::Owned(o) => send(o),
-                                }
+                                let _ = send(String::from_utf8_lossy(&buf).into_owned());
                             }
                         });
 
@@ -444,54 +1896,2871 @@ impl TabEvents {
                         let _ = stdout_handle.join();
                         let _ = stderr_handle.join();
 
-                        let mut mem = ctx.memory();
-                        let counter = mem.data.get_temp_mut_or_default::<u64>(id);
-                        *counter -= 1;
+                        if killed.load(Ordering::Relaxed) {
+                            let _ = output_tx.send(OutputChunk::new(
+                                Stream::Stderr,
+                                "\x1b[90mprocess terminated by user\x1b[0m\n".to_string(),
+                            ));
+                        }
+
+                        let timed_out_after = timeout.filter(|_| timed_out.load(Ordering::Relaxed));
+                        if let Some(timeout) = timed_out_after {
+                            let _ = output_tx.send(OutputChunk::new(
+                                Stream::Stderr,
+                                format!(
+                                    "\x1b[31mprocess timed out after {}s\x1b[0m\n",
+                                    timeout.as_secs()
+                                ),
+                            ));
+                        }
+
+                        let status = child.lock().unwrap().wait();
 
-                        let aborter = mem.data.get_temp::<Aborter>(abort_id);
-                        if aborter.is_some() {
-                            mem.data.remove::<Aborter>(abort_id);
+                        let succeeded = !killed.load(Ordering::Relaxed)
+                            && timed_out_after.is_none()
+                            && matches!(&status, Ok(status) if status.success());
+
+                        // cargo puts the module in a folder for the target and profile, named after
+                        // the package. A failed build just drops the sender
+                        if let (Some(wat_tx), Some(triple), Some(location), true) = (
+                            wat_tx,
+                            run_config.target.triple(),
+                            project.location(),
+                            succeeded,
+                        ) {
+                            let path = location
+                                .join("target")
+                                .join(triple)
+                                .join(RunConfig::build_type_name(run_config.build_type))
+                                .join(format!("{}.wasm", project.package_name()));
+
+                            let wat = match fs::read(&path) {
+                                Ok(module) => {
+                                    run_state.lock().unwrap().wasm_size = Some(module.len() as u64);
+                                    wasm::disassemble(&module).map_err(|e| {
+                                        format!("\x1b[1;31merror\x1b[0m: failed to disassemble the module: {e}\n")
+                                    })
+                                }
+                                Err(e) => Err(format!(
+                                    "\x1b[1;31merror\x1b[0m: failed to read {}: {e}\n",
+                                    path.display()
+                                )),
+                            };
+
+                            let _ = wat_tx.send(wat);
                         }
+
+                        finish(if killed.load(Ordering::Relaxed) {
+                            RunStatus::Killed
+                        } else if let Some(timeout) = timed_out_after {
+                            RunStatus::TimedOut(timeout)
+                        } else {
+                            RunStatus::Exited(status.ok().and_then(|s| s.code()))
+                        });
                     });
 
                     false
                 }
             },
+
+            Command::DialogCommand(dialog) => Self::show_dialog(ctx, dialog),
+
+            Command::TerminalCommand(command) => match command {
+                TerminalCommand::JumpTo { tab, line, col } => {
+                    Self::jump_to(ctx, *tab, *line, *col, &mut config.dock.tree);
+                    false
+                }
+            },
         });
+
+        config
+            .dock
+            .commands
+            .extend(dialogs.into_iter().map(Command::DialogCommand));
+        config.dock.commands.extend(closing);
+
+        // scratches with module files are saved to a folder, which can't be opened again
+        for id in &saved {
+            if let Some(tab) = config.dock.tree.tabs().find(|tab| tab.id == *id) {
+                if let Some(path) = tab.path.as_deref().filter(|_| tab.files.is_empty()) {
+                    config.recent_files.add(path);
+                }
+            }
+        }
+
+        if config.runner.auto_run == AutoRun::OnSave {
+            config.dock.commands.extend(
+                saved
+                    .into_iter()
+                    .map(|id| Command::TabCommand(TabCommand::AutoRun(id))),
+            );
+        }
+
+        Self::check_active_tab(ctx, config);
+        Self::auto_run_active_tab(ctx, config);
+        Self::show_wats(ctx, config);
+        Self::watch_files(ctx, config);
+        Self::update_lsp(ctx, config);
+        Self::save_drafts(ctx, config);
+        Self::show_recovered_drafts(ctx, config);
+        Self::show_crash_reports(ctx, config);
     }
 
-    fn show_rename_window(ctx: &egui::Context, id: Id, tree: &mut Tree) -> bool {
-        let tab = &mut tree
-            .iter_mut()
-            .filter_map(|node| {
-                let Node::Leaf { tabs, .. } = node else {
-                    return None;
-                };
+    // Ctrl+O opens a file, and so does dropping .rs files on the window. Ctrl+T opens a new tab,
+    // Ctrl+W closes the focused one and Ctrl+Shift+T reopens the last closed one
+    // Open the .rs files dropped onto the window
+    fn handle_dropped_files(ctx: &egui::Context, config: &mut Config) {
+        let commands = &mut config.dock.commands;
+        let input = ctx.input();
 
-                tabs.iter_mut().find(|tab| tab.id == id)
-            })
-            .collect::<SmallVec<[&mut Tab; 1]>>()[0];
+        let dropped = input
+            .raw
+            .dropped_files
+            .iter()
+            .filter_map(|file| file.path.clone())
+            .filter(|path| matches!(path.extension(), Some(extension) if extension == "rs"));
 
-        Window::new(&format!("Rename {}", tab.name))
-            .title_bar(false)
-            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
-            .auto_sized()
-            .show(ctx, |ui| {
-                if ui.button("Done").clicked() {
-                    tab.name = "nice".to_string();
-                    return false;
+        commands.extend(dropped.map(|path| Command::TabCommand(TabCommand::OpenFile(path))));
+    }
+
+    // Open the files another instance was started with, and come to the front for them
+    fn handle_open_requests(config: &mut Config) {
+        let Some(requests) = &config.dock.open_requests else {
+            return;
+        };
+
+        for request in requests.try_iter() {
+            let commands = &mut config.dock.commands;
+            for path in request.files {
+                let id = Id::new(&path);
+                commands.push(Command::TabCommand(TabCommand::OpenFile(path)));
+                if request.run {
+                    commands.push(Command::TabCommand(TabCommand::Play(id)));
                 }
+            }
 
-                true
-            })
-            .unwrap()
-            .inner
-            .unwrap()
+            #[cfg(target_os = "windows")]
+            single_instance::raise();
+        }
     }
 
-    fn share_scratch(id: Id, tree: &mut Tree, github: &GitHub) -> bool {
-        println!("shared scratch token: {}", github.access_token);
+    // Close tabs, or exit, once their unsaved changes were saved or discarded. Asking shows the
+    // Save / Discard / Cancel window, which sends the same command back with the user's pick
+    #[allow(clippy::too_many_arguments)]
+    fn close_tabs(
+        ctx: &egui::Context,
+        tabs: &[Id],
+        unsaved: Unsaved,
+        exit: bool,
+        tree: &mut Tree,
+        pending_saves: &HashMap<Id, Receiver<Option<PathBuf>>>,
+        exiting: &mut bool,
+        closing: &mut Vec<Command>,
+    ) -> bool {
+        // pinned tabs stay open until they're unpinned, only exiting closes them
+        let tabs = tabs
+            .iter()
+            .copied()
+            .filter(|id| exit || !tree.tabs().any(|tab| tab.id == *id && tab.pinned))
+            .collect::<Vec<_>>();
 
-        false
-    }
+        let dirty = tabs
+            .iter()
+            .filter_map(|id| tree.tabs().find(|tab| tab.id == *id))
+            .filter(|tab| tab.is_dirty())
+            .collect::<Vec<_>>();
+
+        match unsaved {
+            Unsaved::Ask if !dirty.is_empty() => {
+                let mut picked = None;
+                let mut open = true;
+
+                Window::new("Unsaved changes")
+                    .id(Id::new(("unsaved_changes", &tabs, exit)))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+                    .show(ctx, |ui| {
+                        match &dirty[..] {
+                            [tab] => ui.label(format!("{} has unsaved changes", tab.name)),
+                            tabs => ui.label(format!(
+                                "These tabs have unsaved changes\n\n{}",
+                                tabs.iter()
+                                    .map(|tab| tab.name.as_str())
+                                    .collect::<Vec<_>>()
+                                    .join("\n")
+                            )),
+                        };
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Save").clicked() {
+                                picked = Some(Unsaved::Save);
+                            }
+                            if ui.button("Discard").clicked() {
+                                picked = Some(Unsaved::Discard);
+                            }
+                            open = !ui.button("Cancel").clicked();
+                        });
+                    });
+
+                if picked == Some(Unsaved::Save) {
+                    closing.extend(
+                        dirty
+                            .iter()
+                            .map(|tab| Command::MenuCommand(MenuCommand::Save(tab.id))),
+                    );
+                }
+
+                if let Some(unsaved) = picked {
+                    closing.push(Command::TabCommand(TabCommand::CloseTabs {
+                        tabs: tabs.clone(),
+                        unsaved,
+                        exit,
+                    }));
+                }
+
+                return open && picked.is_none();
+            }
+            // the save dialogs are still open
+            Unsaved::Save if tabs.iter().any(|id| pending_saves.contains_key(id)) => return true,
+            // a save was cancelled or failed, so there's still something to lose
+            Unsaved::Save if !dirty.is_empty() => return false,
+            _ => (),
+        }
+
+        if exit {
+            *exiting = true;
+            return false;
+        }
+
+        for id in &tabs {
+            if let Some(tab) = tree
+                .find_tab_location(*id)
+                .and_then(|location| tree.remove_tab(location))
+            {
+                closing.push(Command::TabCommand(TabCommand::Close(Box::new(tab))));
+            }
+        }
+
+        false
+    }
+
+    // Open a copy of a tab right after it. The copy isn't saved anywhere yet, and gets its own id
+    // so it has its own output in the terminal
+    fn duplicate_tab(tree: &mut Tree, id: Id) {
+        let Some((node, tab_index)) = tree.find_tab_location(id) else {
+            return;
+        };
+        let Node::Leaf { tabs, .. } = &tree[node] else {
+            return;
+        };
+
+        let copy = Self::copy_tab(&tabs[tab_index.0]);
+
+        tree[node].insert_tab(TabIndex(tab_index.0 + 1), copy);
+        tree.set_focused_node(node);
+    }
+
+    // A copy of the tab's code and settings, under a new name and id
+    fn copy_tab(tab: &Tab) -> Tab {
+        let name = format!("{} (copy)", tab.name);
+
+        Tab {
+            id: Id::new(format!("{name}-{}", rand::random::<u64>())),
+            name,
+            scroll_offset: None,
+            path: None,
+            check: CheckState::default(),
+            // regenerating the original's code replaces the original's tab, not this one
+            source: None,
+            loading: false,
+            open_file: None,
+            disk_code: None,
+            disk_change: None,
+            saved_revision: 0,
+            pinned: false,
+            gist: None,
+            search: Default::default(),
+            welcome: false,
+            ..tab.clone()
+        }
+    }
+
+    // Split the tab's group in two, and move the tab into the new group, which gets focused. A copy
+    // goes there instead when `copy` is set, or when the tab is alone in its group, moving it would
+    // leave the group empty
+    fn split_tab(tree: &mut Tree, id: Id, split: Split, copy: bool) {
+        let Some((node, tab_index)) = tree.find_tab_location(id) else {
+            return;
+        };
+
+        let tab = if copy || tree[node].tabs_count() == 1 {
+            let Node::Leaf { tabs, .. } = &tree[node] else {
+                return;
+            };
+            Self::copy_tab(&tabs[tab_index.0])
+        } else {
+            let Some(tab) = tree.remove_tab((node, tab_index)) else {
+                return;
+            };
+            tab
+        };
+
+        tree.split_tabs(node, split, 0.5, vec![tab]);
+    }
+
+    // Move the tab to the end of the other group's tab bar. A group left without tabs is removed
+    // afterwards, that moves the nodes around, so the tab is looked up again to focus it
+    fn move_to_other_group(tree: &mut Tree, id: Id) {
+        let Some((node, tab_index)) = tree.find_tab_location(id) else {
+            return;
+        };
+        let Some(other) = tree
+            .iter()
+            .enumerate()
+            .position(|(index, other)| other.is_leaf() && index != node.0)
+        else {
+            return;
+        };
+
+        let Some(tab) = tree[node].remove_tab(tab_index) else {
+            return;
+        };
+        tree[NodeIndex(other)].append_tab(tab);
+
+        if let Node::Leaf { tabs, active, .. } = &mut tree[node] {
+            active.0 = active.0.min(tabs.len().saturating_sub(1));
+        }
+        tree.remove_empty_leaf();
+
+        if let Some((node, tab_index)) = tree.find_tab_location(id) {
+            tree.set_focused_node(node);
+            tree.set_active_tab(node, tab_index);
+        }
+    }
+
+    // Show the open dialog, and open the file once one was picked
+    fn open_dialog(
+        ctx: &egui::Context,
+        tree: &mut Tree,
+        pending_open: &mut Option<Receiver<Option<PathBuf>>>,
+        recent_files: &mut RecentFiles,
+        dialogs: &mut Vec<DialogCommand>,
+    ) -> bool {
+        let Some(rx) = pending_open else {
+            let (tx, rx) = channel();
+            let ctx = ctx.clone();
+
+            // the dialog blocks, so keep it off the ui thread
+            thread::spawn(move || {
+                let path = rfd::FileDialog::new()
+                    .add_filter("Rust", &["rs"])
+                    .pick_file();
+
+                let _ = tx.send(path);
+                ctx.request_repaint();
+            });
+
+            *pending_open = Some(rx);
+            return true;
+        };
+
+        match rx.try_recv() {
+            Ok(Some(path)) => Self::open_file(tree, &path, recent_files, dialogs),
+            // dialog is still open, keep waiting
+            Err(TryRecvError::Empty) => return true,
+            // user cancelled
+            Ok(None) | Err(TryRecvError::Disconnected) => (),
+        }
+
+        *pending_open = None;
+        false
+    }
+
+    // Open a file in a new tab named after it, saving writes back to the file. A file which is
+    // open already has its tab focused instead
+    fn open_file(
+        tree: &mut Tree,
+        path: &Path,
+        recent_files: &mut RecentFiles,
+        dialogs: &mut Vec<DialogCommand>,
+    ) {
+        let open = tree
+            .tabs()
+            .find(|tab| tab.path.as_deref() == Some(path) && tab.files.is_empty())
+            .map(|tab| tab.id);
+
+        if let Some((node, tab_index)) = open.and_then(|id| tree.find_tab_location(id)) {
+            tree.set_focused_node(node);
+            tree.set_active_tab(node, tab_index);
+            recent_files.add(path);
+            return;
+        }
+
+        let code = match fs::read_to_string(path) {
+            Ok(code) => code,
+            Err(e) => {
+                dialogs.push(DialogCommand::error(
+                    "Failed to open",
+                    format!("Failed to open {}\n\n{e}", path.display()),
+                ));
+                return;
+            }
+        };
+
+        let name = path
+            .file_stem()
+            .map_or("Scratch".into(), |stem| stem.to_string_lossy())
+            .into_owned();
+
+        tree.push_to_focused_leaf(Tab {
+            editor: CodeEditor::new("rs", code.clone()),
+            path: Some(path.to_path_buf()),
+            disk_code: Some(code),
+            // the same file always gets the same tab id
+            ..Tab::scratch(name, Id::new(path))
+        });
+        recent_files.add(path);
+    }
+
+    // Keep watching the files tabs were opened from or saved to. A file which another program
+    // changed is only reloaded once the user says so, so edits in the tab are never lost
+    fn watch_files(ctx: &egui::Context, config: &mut Config) {
+        let watcher = match &mut config.dock.watcher {
+            Some(watcher) => watcher,
+            None => {
+                let ctx = ctx.clone();
+                match FileWatcher::new(move || ctx.request_repaint()) {
+                    Ok(watcher) => config.dock.watcher.insert(watcher),
+                    Err(_) => return,
+                }
+            }
+        };
+
+        // scratches with module files are saved to a folder, those aren't watched. The user's
+        // snippets are read again whenever they're saved
+        let snippets_file = snippets::snippets_file();
+        let tree = &mut config.dock.tree;
+        watcher.watch(
+            tree.tabs()
+                .filter(|tab| tab.files.is_empty())
+                .filter_map(|tab| tab.path.as_deref())
+                .chain([snippets_file.as_path()]),
+        );
+
+        let changed = watcher.changed();
+        if changed.is_empty() {
+            return;
+        }
+        if changed.contains(&snippets_file) {
+            config.editor.load_snippets();
+        }
+
+        for node in tree.iter_mut() {
+            let Node::Leaf { tabs, .. } = node else {
+                continue;
+            };
+
+            for tab in tabs.iter_mut().filter(|tab| tab.files.is_empty()) {
+                let Some(path) = tab.path.as_ref().filter(|path| changed.contains(*path)) else {
+                    continue;
+                };
+
+                // a file which was deleted or is still being written is left for the next change
+                let Ok(code) = fs::read_to_string(path) else {
+                    continue;
+                };
+
+                if code == tab.editor.code {
+                    // saved from this tab, or the same edit was made in both
+                    tab.disk_change = None;
+                    tab.disk_code = Some(code);
+                } else if tab.disk_code.as_ref() != Some(&code) {
+                    tab.disk_change = Some(code);
+                }
+            }
+        }
+    }
+
+    // Write the unsaved tabs' code to their drafts every so often and whenever another tab is
+    // focused, so a crash loses as little as it can. The drafts of tabs which were saved or closed
+    // since are deleted. Until it's time, the code which isn't written yet is kept for the panic
+    // hook to write out
+    fn save_drafts(ctx: &egui::Context, config: &mut Config) {
+        let dock = &mut config.dock;
+        let focused = dock.tree.find_active_focused().map(|(_, tab)| tab.id);
+        let blurred = focused != dock.draft_focus;
+        dock.draft_focus = focused;
+
+        let tree = &dock.tree;
+        let drafted = &mut dock.drafted;
+        drafts::retain_pending(|id| tree.tabs().any(|tab| tab.id == id));
+
+        let interval = Duration::from_secs(config.editor.autosave_interval_secs.max(1));
+        let waited = dock.drafts_saved.elapsed();
+        if !blurred && waited < interval {
+            let mut undrafted = false;
+            for tab in tree.tabs() {
+                if tab.is_dirty() && drafted.get(&tab.id) != Some(&tab.check.revision) {
+                    drafts::set_pending(tab.id, tab.check.revision, &tab.editor.code);
+                    undrafted = true;
+                } else {
+                    drafts::clear_pending(tab.id);
+                }
+            }
+            if undrafted {
+                ctx.request_repaint_after(interval - waited);
+            }
+            return;
+        }
+        dock.drafts_saved = Instant::now();
+
+        let dir = drafts::drafts_dir();
+        for tab in tree.tabs() {
+            if !tab.is_dirty() {
+                drafts::clear_pending(tab.id);
+                if drafted.remove(&tab.id).is_some() {
+                    drafts::remove(&dir, tab.id);
+                }
+            } else if drafted.get(&tab.id) != Some(&tab.check.revision)
+                && drafts::save(&dir, tab.id, &tab.editor.code).is_ok()
+            {
+                drafts::clear_pending(tab.id);
+                drafted.insert(tab.id, tab.check.revision);
+            }
+        }
+
+        drafted.retain(|id, _| {
+            let open = tree.tabs().any(|tab| tab.id == *id);
+            if !open {
+                drafts::remove(&dir, *id);
+            }
+            open
+        });
+    }
+
+    // After a crash, offer back the drafts which are newer than the session the tabs were restored
+    // from. A restored draft goes back in its tab, or in a new one when the tab wasn't in the
+    // session
+    fn show_recovered_drafts(ctx: &egui::Context, config: &mut Config) {
+        const PREVIEW_LINES: usize = 6;
+
+        if config.dock.recovered_drafts.is_empty() {
+            return;
+        }
+
+        let tree = &config.dock.tree;
+        let mut picked = None;
+        Window::new("Recover drafts")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label("Rust Play didn't close properly, these changes weren't saved:");
+
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for (index, draft) in config.dock.recovered_drafts.iter().enumerate() {
+                            let tab = tree.tabs().find(|tab| drafts::key(tab.id) == draft.key);
+
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                ui.strong(tab.map_or("Closed tab", |tab| tab.name.as_str()));
+                                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                    if ui.button("Discard").clicked() {
+                                        picked = Some((index, false));
+                                    }
+                                    if ui.button("Restore").clicked() {
+                                        picked = Some((index, true));
+                                    }
+                                });
+                            });
+
+                            let preview = draft
+                                .code
+                                .lines()
+                                .take(PREVIEW_LINES)
+                                .collect::<Vec<_>>()
+                                .join("\n");
+                            ui.label(egui::RichText::new(preview).monospace().weak());
+                        }
+                    });
+            });
+
+        let Some((index, restore)) = picked else {
+            return;
+        };
+
+        let dock = &mut config.dock;
+        let draft = dock.recovered_drafts.remove(index);
+        let _ = fs::remove_file(&draft.path);
+        let open = dock
+            .tree
+            .tabs()
+            .find(|tab| drafts::key(tab.id) == draft.key)
+            .map(|tab| tab.id);
+        // written again from the tab when it's still unsaved
+        if let Some(id) = open {
+            dock.drafted.remove(&id);
+        }
+        if !restore {
+            return;
+        }
+
+        match open.and_then(|id| dock.tree.find_tab_mut(id)) {
+            Some(tab) => {
+                tab.editor.code = draft.code;
+                tab.check.revision += 1;
+            }
+            None => {
+                let name = tab_names::scratch_name(dock.tree.tabs().map(|tab| tab.name.as_str()));
+                let mut tab = Tab::scratch(name, tab_names::scratch_id(dock.counter));
+                tab.editor.code = draft.code;
+                tab.check.revision += 1;
+
+                dock.tree.push_to_focused_leaf(tab);
+                dock.counter += 1;
+            }
+        }
+    }
+
+    // Show what the last panics left in their reports, with a way to copy it for an issue. A
+    // report isn't shown again once it's closed
+    fn show_crash_reports(ctx: &egui::Context, config: &mut Config) {
+        const ISSUES: &str = "https://github.com/MolotovCherry/RustPlay/issues";
+
+        // the drafts are offered back first
+        let dock = &mut config.dock;
+        let Some(report) = dock.crash_reports.first() else {
+            return;
+        };
+        if !dock.recovered_drafts.is_empty() {
+            return;
+        }
+
+        let mut close = false;
+        Window::new("Rust Play crashed")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label("Rust Play panicked the last time it ran. This is what it left behind:");
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .max_width(600.0)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new(&report.text).monospace().weak());
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.hyperlink_to("Report an issue", ISSUES);
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        close = ui.button("Close").clicked();
+                        if ui.button("Copy to clipboard").clicked() {
+                            ui.output().copied_text = report.text.clone();
+                        }
+                    });
+                });
+            });
+
+        if close {
+            let report = dock.crash_reports.remove(0);
+            crash_reports::mark_read(&report);
+        }
+    }
+
+    // Keep rust-analyzer running on the active tab while it's turned on. It's told about each
+    // change to the tab's main.rs, asked what the tab's editor wants to know, and its answers are
+    // handed back. The servers of closed tabs are stopped
+    fn update_lsp(ctx: &egui::Context, config: &mut Config) {
+        let lsp = &mut config.editor.lsp;
+        let clients = &mut config.dock.lsp_clients;
+        let tree = &mut config.dock.tree;
+        if !lsp.enabled {
+            clients.clear();
+            return;
+        }
+
+        clients.retain(|id, client| {
+            // not started again, it'd most likely exit again
+            if !client.running() {
+                lsp.error = Some(format!("{} exited", lsp.program));
+                return false;
+            }
+            tree.tabs().any(|tab| tab.id == *id)
+        });
+
+        if lsp.error.is_none() {
+            if let Some((_, tab)) = tree.find_active() {
+                if !tab.editor.read_only && !clients.contains_key(&tab.id) {
+                    match Self::start_lsp(ctx, tab, &lsp.program) {
+                        Ok(client) => {
+                            clients.insert(tab.id, client);
+                        }
+                        Err(e) => lsp.error = Some(e),
+                    }
+                }
+            }
+        }
+
+        let mut definitions = vec![];
+        for (id, client) in clients.iter_mut() {
+            let Some(tab) = tree.find_tab_mut(*id) else {
+                continue;
+            };
+            let editor = &mut tab.editor;
+
+            client.sync(&editor.code);
+            for reply in client.poll() {
+                match reply {
+                    Reply::Completions(at, names) => editor.offer_completions(at, names),
+                    Reply::Hover(at, text) => editor.show_hover(at, text),
+                    Reply::Definition(_, path, position) => {
+                        definitions.push((*id, client.main().to_path_buf(), path, position));
+                    }
+                }
+            }
+
+            let asks = editor.take_lsp_asks();
+            if let Some(at) = asks.completion {
+                client.completion(&editor.code, at);
+            }
+            if let Some(at) = asks.hover {
+                client.hover(&editor.code, at);
+            }
+        }
+
+        for (id, main, path, position) in definitions {
+            Self::go_to_definition(ctx, tree, id, &main, &path, position);
+        }
+    }
+
+    // Start rust-analyzer on a project of the tab's code, in a folder of its own so runs never
+    // rewrite the files it has open
+    fn start_lsp(ctx: &egui::Context, tab: &Tab, program: &str) -> Result<LspClient, String> {
+        let cache_dir = build_cache::cache_dir();
+        let files = tab.module_files();
+        let mut project = tab_project(
+            tab.id,
+            &tab.editor.code,
+            &files,
+            tab.run_config,
+            &tab.dependencies,
+            &cache_dir,
+        );
+        project
+            .subcommand(Subcommand::Check)
+            .target_prefix("rust-play-lsp");
+        project.create().map_err(|e| e.to_string())?;
+
+        let root = project
+            .location()
+            .expect("the project was created")
+            .to_path_buf();
+        let main = root.join("src").join("main.rs");
+        let ctx = ctx.clone();
+        LspClient::start(program, &root, &main, move || ctx.request_repaint())
+            .map_err(|e| e.to_string())
+    }
+
+    // Go to where rust-analyzer said something is defined. In the tab's own main.rs the cursor is
+    // moved there, anywhere else, like std or a dependency, the file is opened in a read-only tab
+    fn go_to_definition(
+        ctx: &egui::Context,
+        tree: &mut Tree,
+        id: Id,
+        main: &Path,
+        path: &Path,
+        position: Position,
+    ) {
+        let line_col = |code: &str| {
+            let at = lsp::offset(code, position);
+            let line_start = code[..at].rfind('\n').map_or(0, |start| start + 1);
+            (
+                code[..at].matches('\n').count() + 1,
+                code[line_start..at].chars().count() + 1,
+            )
+        };
+
+        let same_file = match (main.canonicalize(), path.canonicalize()) {
+            (Ok(main), Ok(path)) => main == path,
+            _ => main == path,
+        };
+        if same_file {
+            let Some(tab) = tree.find_tab_mut(id) else {
+                return;
+            };
+            let (line, col) = line_col(&tab.editor.code);
+            Self::jump_to(ctx, id, line, col, tree);
+            return;
+        }
+
+        let Some(run_config) = tree.find_tab_mut(id).map(|tab| tab.run_config) else {
+            return;
+        };
+        let Ok(code) = fs::read_to_string(path) else {
+            return;
+        };
+        let name = path
+            .file_name()
+            .map_or_else(|| "definition".into(), |name| name.to_string_lossy());
+        let (line, col) = line_col(&code);
+
+        // the file may be open from going to a definition in it before
+        let open = tree
+            .tabs()
+            .find(|tab| tab.editor.read_only && tab.name == name && tab.editor.code == code)
+            .map(|tab| tab.id);
+        let id = match open {
+            Some(id) => id,
+            None => {
+                let tab = Tab::read_only(name.to_string(), "rs", code, run_config);
+                let id = tab.id;
+                tree.push_to_focused_leaf(tab);
+                id
+            }
+        };
+        Self::jump_to(ctx, id, line, col, tree);
+    }
+
+    // Show the disassembled modules of finished wasm builds, each in the tab linked to the tab that
+    // was built. A build which failed sends nothing, cargo's errors are already in the terminal
+    fn show_wats(ctx: &egui::Context, config: &mut Config) {
+        let tree = &mut config.dock.tree;
+        let terminal = &mut config.terminal;
+
+        config.dock.pending_wats.retain(|id, rx| {
+            let wat = match rx.try_recv() {
+                Ok(wat) => wat,
+                Err(TryRecvError::Empty) => {
+                    // nothing wakes up the ui when the build finishes, so keep polling
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                    return true;
+                }
+                Err(TryRecvError::Disconnected) => return false,
+            };
+
+            let wat = match wat {
+                Ok(wat) => wat,
+                Err(e) => {
+                    terminal.show_message(*id, Stream::Stderr, e);
+                    return false;
+                }
+            };
+
+            // building again refreshes the tab from last time
+            if let Some(wat_id) = find_generated(tree, *id, Generated::Wat) {
+                if let Some(tab) = tree.find_tab_mut(wat_id) {
+                    tab.editor.code = wat;
+                }
+
+                if let Some((node, tab_index)) = tree.find_tab_location(wat_id) {
+                    tree.set_focused_node(node);
+                    tree.set_active_tab(node, tab_index);
+                }
+
+                return false;
+            }
+
+            // the tab may have been closed in the meantime
+            let Some(tab) = tree.find_tab_mut(*id) else {
+                return false;
+            };
+
+            let name = format!("{} (wat)", tab.name);
+            let run_config = tab.run_config;
+
+            tree.push_to_focused_leaf(Tab {
+                source: Some((*id, Generated::Wat)),
+                // there's no syntax for it, so it's shown as plain text
+                ..Tab::read_only(name, "wat", wat, run_config)
+            });
+
+            false
+        });
+    }
+
+    // Runs `cargo check` on the active tab once its code stops changing, and collects the results
+    fn check_active_tab(ctx: &egui::Context, config: &mut Config) {
+        let tree = &mut config.dock.tree;
+        let pending_checks = &mut config.dock.pending_checks;
+
+        pending_checks.retain(|id, (revision, rx)| {
+            let diagnostics = match rx.try_recv() {
+                Ok(diagnostics) => diagnostics,
+                Err(TryRecvError::Empty) => {
+                    // nothing wakes up the ui when the check finishes, so keep polling
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                    return true;
+                }
+                Err(TryRecvError::Disconnected) => return false,
+            };
+
+            // edits made while it was checking make the results stale, the next check picks those up
+            if let Some(tab) = tree.find_tab_mut(*id) {
+                if tab.check.revision == *revision {
+                    tab.check.diagnostics = diagnostics;
+                    tab.check.checked = Some(*revision);
+                }
+            }
+
+            false
+        });
+
+        let Some((_, tab)) = tree.find_active() else {
+            return;
+        };
+
+        if tab.editor.read_only {
+            return;
+        }
+
+        if tab.check.checked == Some(tab.check.revision) || pending_checks.contains_key(&tab.id) {
+            return;
+        }
+
+        if let Some(edited) = tab.check.edited {
+            let waited = edited.elapsed();
+            if waited < CHECK_DELAY {
+                ctx.request_repaint_after(CHECK_DELAY - waited);
+                return;
+            }
+        }
+
+        let rx = Self::check_tab(ctx, tab, Subcommand::Check);
+        pending_checks.insert(tab.id, (tab.check.revision, rx));
+    }
+
+    // Runs the active tab once its code stops changing, when auto-run is set to do that
+    fn auto_run_active_tab(ctx: &egui::Context, config: &mut Config) {
+        let AutoRun::OnIdle(delay) = config.runner.auto_run else {
+            return;
+        };
+
+        let Some((_, tab)) = config.dock.tree.find_active() else {
+            return;
+        };
+
+        // only code which was edited, opening the app doesn't run everything
+        let Some(edited) = tab.check.edited else {
+            return;
+        };
+
+        if tab.editor.read_only
+            || config.dock.ran_revisions.get(&tab.id) == Some(&tab.check.revision)
+        {
+            return;
+        }
+
+        // waits for the user's own run, which repaints once it's done
+        if config.terminal.is_running_manually(ctx, tab.id) {
+            return;
+        }
+
+        let delay = Duration::from_millis(delay);
+        let waited = edited.elapsed();
+        if waited < delay {
+            ctx.request_repaint_after(delay - waited);
+            return;
+        }
+
+        let id = tab.id;
+        config
+            .dock
+            .commands
+            .push(Command::TabCommand(TabCommand::AutoRun(id)));
+        ctx.request_repaint();
+    }
+
+    // Check the tab's code in the background with `check` or `clippy`, sending what cargo found about it
+    fn check_tab(
+        ctx: &egui::Context,
+        tab: &Tab,
+        subcommand: Subcommand,
+    ) -> Receiver<Vec<Diagnostic>> {
+        let (tx, rx) = channel();
+
+        let id = tab.id;
+        let code = tab.editor.code.clone();
+        let files = tab.module_files();
+        let run_config = tab.run_config;
+        let dependencies = tab.dependencies.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let cache_dir = build_cache::cache_dir();
+            // a different folder than runs use, so a check never rewrites a program that's building
+            let mut project = tab_project(id, &code, &files, run_config, &dependencies, &cache_dir);
+            project
+                .subcommand(subcommand)
+                .subcommand_flag("--message-format=json")
+                .target_prefix("rust-play-check");
+
+            let diagnostics = match project.create() {
+                Ok(mut command) => {
+                    // hide the console window from command. Very important.
+                    #[cfg(target_os = "windows")]
+                    command.creation_flags(CREATE_NO_WINDOW.0);
+
+                    // the json messages are on stdout, stderr only has the progress
+                    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output();
+
+                    match output {
+                        Ok(output) => diagnostics::parse_diagnostics(
+                            &String::from_utf8_lossy(&output.stdout),
+                            "src/main.rs",
+                        ),
+                        Err(_) => vec![],
+                    }
+                }
+
+                // cargo would only point at the generated Cargo.toml for these
+                Err(ProjectError::ProjectBuildError(ProjectBuildError::Dependency(e))) => {
+                    diagnostics::line_range(&code, e.line)
+                        .map(|range| {
+                            vec![Diagnostic {
+                                severity: Severity::Error,
+                                tool: Tool::Rustc,
+                                range,
+                                line: e.line,
+                                col: e.col,
+                                message: format!("invalid dependency: {}", e.message),
+                                suggestion: None,
+                            }]
+                        })
+                        .unwrap_or_default()
+                }
+
+                Err(_) => vec![],
+            };
+
+            let _ = tx.send(diagnostics);
+            ctx.request_repaint();
+        });
+
+        rx
+    }
+
+    fn show_rename_window(ctx: &egui::Context, id: Id, tree: &mut Tree) -> bool {
+        // the tab may have been closed while the window was open
+        let Some(tab) = tree.find_tab_mut(id) else {
+            return false;
+        };
+
+        let window_id = id.with("rename_window");
+        let buffer_id = window_id.with("buffer");
+        let error_id = window_id.with("error");
+
+        // the buffer is only missing on the first frame the window is shown
+        let (mut name, just_opened) = match ctx.data().get_temp::<String>(buffer_id) {
+            Some(name) => (name, false),
+            None => (tab.name.clone(), true),
+        };
+        let mut show_error = ctx.data().get_temp::<bool>(error_id).unwrap_or_default();
+
+        let mut open = true;
+
+        Window::new(format!("Rename {}", tab.name))
+            .id(window_id)
+            .title_bar(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .auto_sized()
+            .show(ctx, |ui| {
+                ui.label("Tab name");
+
+                let response = ui.text_edit_singleline(&mut name);
+                if just_opened {
+                    response.request_focus();
+                }
+
+                if response.changed() {
+                    show_error = false;
+                }
+
+                let submitted = response.lost_focus() && ui.input().key_pressed(Key::Enter);
+                let cancelled = ui.input().key_pressed(Key::Escape);
+
+                if show_error {
+                    ui.colored_label(ui.visuals().error_fg_color, "Name cannot be empty");
+                }
+
+                ui.horizontal(|ui| {
+                    let done = ui.button("Done").clicked();
+                    let cancel = ui.button("Cancel").clicked();
+
+                    if done || submitted {
+                        let new_name = name.trim();
+
+                        if new_name.is_empty() {
+                            show_error = true;
+                            response.request_focus();
+                        } else {
+                            tab.name = new_name.to_string();
+                            open = false;
+                        }
+                    } else if cancel || cancelled {
+                        open = false;
+                    }
+                });
+            });
+
+        let mut data = ctx.data();
+        if open {
+            data.insert_temp(buffer_id, name);
+            data.insert_temp(error_id, show_error);
+        } else {
+            data.remove::<String>(buffer_id);
+            data.remove::<bool>(error_id);
+        }
+
+        open
+    }
+
+    // Share a tab as a gist. Shows the options first, then the gist's links once it's made. A tab
+    // which was shared before can update its gist instead of making another one
+    fn share_scratch(
+        ctx: &egui::Context,
+        id: Id,
+        tree: &mut Tree,
+        github: &mut GitHub,
+        pending_shares: &mut HashMap<Id, ShareState>,
+        dialogs: &mut Vec<DialogCommand>,
+    ) -> bool {
+        // the tab may have been closed while sharing
+        let Some(tab) = tree.find_tab_mut(id) else {
+            pending_shares.remove(&id);
+            return false;
+        };
+
+        let state = pending_shares
+            .entry(id)
+            .or_insert_with(|| ShareState::Options {
+                description: DEFAULT_DESCRIPTION.to_string(),
+                public: tab.gist.as_ref().is_none_or(|gist| gist.public),
+                file_name: tab_names::gist_file_name(&tab.name),
+                update: tab.gist.is_some(),
+            });
+
+        // the module files keep their names, `mod` looks for them by it
+        let module_files = tab
+            .module_files()
+            .into_iter()
+            .map(|(name, code)| (format!("{name}.rs"), code))
+            .collect::<Vec<_>>();
+
+        let mut open = true;
+        let mut send = None;
+
+        match state {
+            ShareState::Options {
+                description,
+                public,
+                file_name,
+                update,
+            } => {
+                let name = file_name.trim().to_string();
+                let taken = module_files.iter().any(|(module, _)| *module == name);
+
+                Window::new(format!("Share {}", tab.name))
+                    .id(id.with("share_window"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+                    .show(ctx, |ui| {
+                        if tab.gist.is_some() {
+                            ui.horizontal(|ui| {
+                                ui.radio_value(update, true, "Update the gist");
+                                ui.radio_value(update, false, "Create a new gist");
+                            });
+                        }
+
+                        ui.label("Description");
+                        ui.add(TextEdit::multiline(description).desired_rows(2));
+
+                        ui.add_enabled_ui(!*update, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.radio_value(public, true, "Public");
+                                ui.radio_value(public, false, "Secret")
+                                    .on_hover_text("Only people with the link can see it");
+                            })
+                            .response
+                            .on_disabled_hover_text("A gist stays public or secret once it's made");
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("File name");
+                            ui.text_edit_singleline(file_name);
+                        });
+                        if !module_files.is_empty() {
+                            let names = module_files
+                                .iter()
+                                .map(|(name, _)| name.as_str())
+                                .collect::<Vec<_>>();
+                            ui.weak(format!("Along with {}", names.join(", ")));
+                        }
+                        if taken {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                "One of the modules already has that name",
+                            );
+                        }
+
+                        ui.horizontal(|ui| {
+                            let valid = !name.is_empty() && !taken;
+                            if ui.add_enabled(valid, Button::new("Share")).clicked() {
+                                send = Some((name.clone(), description.clone(), *public, *update));
+                            }
+                            open = !ui.button("Cancel").clicked();
+                        });
+                    });
+            }
+
+            ShareState::Sending(rx) => match rx.try_recv() {
+                Ok(Ok(gist)) => {
+                    tracing::info!("shared {:?} as gist {}", tab.id, gist.id);
+                    tab.gist = Some(SharedGist {
+                        id: gist.id.clone(),
+                        files: gist.files.keys().cloned().collect(),
+                        public: gist.public,
+                    });
+                    *state = ShareState::Done(gist);
+                }
+                Ok(Err(e)) => {
+                    tracing::warn!("sharing {:?} failed: {e:?}", tab.id);
+                    match e {
+                        // the settings ask to sign in again
+                        GitHubError::Unauthorized => github.expired = true,
+                        // the gist was deleted, the next share makes a new one
+                        GitHubError::NotFound => tab.gist = None,
+                        _ => (),
+                    }
+
+                    dialogs.push(DialogCommand::error("Failed to share", e.to_string()));
+                    open = false;
+                }
+                Err(TryRecvError::Empty) => {
+                    // nothing wakes up the ui when the request finishes, so keep polling
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                }
+                Err(TryRecvError::Disconnected) => {
+                    tracing::error!("the share request for {:?} went away", tab.id);
+                    let error = GitHubError::Unknown.to_string();
+                    dialogs.push(DialogCommand::error("Failed to share", error));
+                    open = false;
+                }
+            },
+
+            ShareState::Done(gist) => {
+                Window::new("Shared to GitHub")
+                    .id(id.with("share_done_window"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+                    .show(ctx, |ui| {
+                        Grid::new(id.with("share_links")).show(ui, |ui| {
+                            link_row(ui, "Gist", &gist.html_url);
+
+                            // the playground only opens a gist's first file
+                            if gist.files.len() == 1 {
+                                let url = format!("https://play.rust-lang.org/?gist={}", gist.id);
+                                link_row(ui, "Playground", &url);
+                            }
+                        });
+
+                        if gist.files.len() > 1 {
+                            ui.weak(
+                                "The playground only opens one file of a gist, so it has no \
+                                 playground link",
+                            );
+                        }
+
+                        open = !ui.button("Close").clicked();
+                    });
+            }
+        }
+
+        if let Some((file_name, description, public, update)) = send {
+            let mut files = vec![(file_name, tab.editor.code.clone())];
+            files.extend(module_files);
+
+            let rx = match (&tab.gist, update) {
+                (Some(gist), true) => {
+                    let removed = gist
+                        .files
+                        .iter()
+                        .filter(|old| !files.iter().any(|(name, _)| name == *old))
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    github.update_gist(&gist.id, &files, &removed, &description)
+                }
+                _ => github.create_gist(&files, &description, public),
+            };
+
+            *state = ShareState::Sending(rx);
+        }
+
+        if !open {
+            pending_shares.remove(&id);
+        }
+
+        open
+    }
+
+    // Compile a tab's code to show what the compiler made of it, opening the result in a new tab. When
+    // it doesn't compile, cargo's errors are shown in the terminal instead
+    fn emit_scratch(
+        ctx: &egui::Context,
+        id: Id,
+        kind: EmitKind,
+        tree: &mut Tree,
+        pending_emits: &mut HashMap<(Id, EmitKind), Receiver<Result<String, String>>>,
+        terminal: &mut Terminal,
+    ) -> bool {
+        if let Some(rx) = pending_emits.get(&(id, kind)) {
+            let result = match rx.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => {
+                    // nothing wakes up the ui when the build finishes, so keep polling
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                    return true;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    Err("the build stopped unexpectedly\n".to_string())
+                }
+            };
+
+            pending_emits.remove(&(id, kind));
+
+            // the tab may have been closed in the meantime
+            let Some(tab) = tree.find_tab_mut(id) else {
+                return false;
+            };
+
+            match result {
+                Ok(output) => {
+                    let name = format!("{} ({})", tab.name, kind.name());
+
+                    let tab = Tab::read_only(name, kind.language(), output, tab.run_config);
+
+                    tree.push_to_focused_leaf(tab);
+                }
+
+                Err(errors) => terminal.show_message(id, Stream::Stderr, errors),
+            }
+
+            return false;
+        }
+
+        let Some(tab) = tree.find_tab_mut(id) else {
+            return false;
+        };
+
+        let (tx, rx) = channel();
+
+        let code = tab.editor.code.clone();
+        let files = tab.module_files();
+        let run_config = tab.run_config;
+        let dependencies = tab.dependencies.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let cache_dir = build_cache::cache_dir();
+            let output_path = std::env::temp_dir().join(format!(
+                "rust-play-emit-{}.{}",
+                rand::random::<u64>(),
+                kind.flag()
+            ));
+            let emit = format!("--emit={}={}", kind.flag(), output_path.display());
+
+            let mut project = tab_project(id, &code, &files, run_config, &dependencies, &cache_dir);
+            project
+                .subcommand(Subcommand::ASM)
+                .target_prefix("rust-play-emit")
+                .env_var("CARGO_TERM_COLOR", "always")
+                // the output is split over several files otherwise, and only one of them is written
+                .env_var("CARGO_INCREMENTAL", "0")
+                .dash_args(&["-C", "codegen-units=1", &emit]);
+
+            let result = match project.create() {
+                Ok(mut command) => {
+                    // hide the console window from command. Very important.
+                    #[cfg(target_os = "windows")]
+                    command.creation_flags(CREATE_NO_WINDOW.0);
+
+                    match command.stdin(Stdio::null()).output() {
+                        Ok(output) if output.status.success() => fs::read_to_string(&output_path)
+                            .map_err(|e| {
+                                format!("\x1b[1;31merror\x1b[0m: failed to read the output: {e}\n")
+                            }),
+                        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).into_owned()),
+                        Err(e) => Err(format!(
+                            "\x1b[1;31merror\x1b[0m: failed to run cargo: {e}\n"
+                        )),
+                    }
+                }
+
+                Err(e) => Err(format!("\x1b[1;31merror\x1b[0m: {e}\n")),
+            };
+
+            let _ = fs::remove_file(&output_path);
+
+            let result = match kind {
+                EmitKind::Assembly => {
+                    result.map(|asm| demangle::demangle_symbols(&asm).into_owned())
+                }
+                EmitKind::LlvmIr | EmitKind::Mir => result,
+            };
+
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+
+        pending_emits.insert((id, kind), rx);
+
+        true
+    }
+
+    // Show a tab's code with its macros expanded by `cargo expand`, in the tab linked to it. The
+    // linked tab is opened right away, and shows a spinner until the expanded code arrives
+    fn expand_scratch(
+        ctx: &egui::Context,
+        id: Id,
+        tree: &mut Tree,
+        pending_expands: &mut HashMap<Id, Receiver<Result<String, ExpandError>>>,
+        terminal: &mut Terminal,
+        dialogs: &mut Vec<DialogCommand>,
+    ) -> bool {
+        let find_expanded = |tree: &Tree| find_generated(tree, id, Generated::Expanded);
+
+        if let Some(rx) = pending_expands.get(&id) {
+            let result = match rx.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => {
+                    // nothing wakes up the ui when the expansion finishes, so keep polling
+                    ctx.request_repaint_after(Duration::from_millis(100));
+                    return true;
+                }
+                Err(TryRecvError::Disconnected) => Err(ExpandError::Failed(
+                    "cargo expand stopped unexpectedly\n".to_string(),
+                )),
+            };
+
+            pending_expands.remove(&id);
+
+            let Some(expanded_id) = find_expanded(tree) else {
+                return false;
+            };
+
+            let expanded = match result {
+                Ok(code) => Some(code),
+                Err(ExpandError::NotInstalled) => {
+                    dialogs.push(DialogCommand::info(
+                        "cargo expand isn't installed",
+                        "Expanding macros needs the cargo-expand subcommand. Install it with\n\n\
+                         cargo install cargo-expand\n\n\
+                         then try again",
+                    ));
+                    None
+                }
+                Err(ExpandError::Failed(errors)) => {
+                    terminal.show_message(id, Stream::Stderr, errors);
+                    None
+                }
+            };
+
+            let Some(tab) = tree.find_tab_mut(expanded_id) else {
+                return false;
+            };
+
+            tab.loading = false;
+
+            match expanded {
+                Some(code) => tab.editor.code = code,
+                // nothing was ever expanded into it, so it's only in the way
+                None if tab.editor.code.is_empty() => {
+                    if let Some(location) = tree.find_tab_location(expanded_id) {
+                        tree.remove_tab(location);
+                    }
+                }
+                None => (),
+            }
+
+            return false;
+        }
+
+        let Some(tab) = tree.find_tab_mut(id) else {
+            return false;
+        };
+
+        let (tx, rx) = channel();
+
+        let code = tab.editor.code.clone();
+        let files = tab.module_files();
+        let name = tab.name.clone();
+        let run_config = tab.run_config;
+        let dependencies = tab.dependencies.clone();
+
+        // expanding again refreshes the tab from last time
+        match find_expanded(tree) {
+            Some(expanded_id) => {
+                if let Some(expanded) = tree.find_tab_mut(expanded_id) {
+                    expanded.loading = true;
+                }
+
+                if let Some((node, tab_index)) = tree.find_tab_location(expanded_id) {
+                    tree.set_focused_node(node);
+                    tree.set_active_tab(node, tab_index);
+                }
+            }
+            None => {
+                let name = format!("{name} (expanded)");
+
+                tree.push_to_focused_leaf(Tab {
+                    source: Some((id, Generated::Expanded)),
+                    loading: true,
+                    ..Tab::read_only(name, "rs", String::new(), run_config)
+                });
+            }
+        }
+
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let result = if cargo_expand_installed() {
+                let cache_dir = build_cache::cache_dir();
+                let mut project =
+                    tab_project(id, &code, &files, run_config, &dependencies, &cache_dir);
+                project
+                    .subcommand(Subcommand::Expand)
+                    .target_prefix("rust-play-expand");
+
+                match project.create() {
+                    Ok(mut command) => {
+                        // hide the console window from command. Very important.
+                        #[cfg(target_os = "windows")]
+                        command.creation_flags(CREATE_NO_WINDOW.0);
+
+                        match command.stdin(Stdio::null()).output() {
+                            Ok(output) if output.status.success() => {
+                                Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+                            }
+                            Ok(output) => Err(ExpandError::Failed(
+                                String::from_utf8_lossy(&output.stderr).into_owned(),
+                            )),
+                            Err(e) => Err(ExpandError::Failed(format!(
+                                "\x1b[1;31merror\x1b[0m: failed to run cargo: {e}\n"
+                            ))),
+                        }
+                    }
+
+                    Err(e) => Err(ExpandError::Failed(format!(
+                        "\x1b[1;31merror\x1b[0m: {e}\n"
+                    ))),
+                }
+            } else {
+                Err(ExpandError::NotInstalled)
+            };
+
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+
+        pending_expands.insert(id, rx);
+
+        true
+    }
+
+    // Write a tab out as a cargo project. Shows the options first, then the folder dialog, and asks
+    // before writing into a folder which already has files in it
+    fn export_scratch(
+        ctx: &egui::Context,
+        id: Id,
+        tree: &mut Tree,
+        pending_exports: &mut HashMap<Id, ExportState>,
+        dialogs: &mut Vec<DialogCommand>,
+    ) -> bool {
+        // the tab may have been closed while exporting
+        let Some(tab) = tree.find_tab_mut(id) else {
+            pending_exports.remove(&id);
+            return false;
+        };
+
+        let state = pending_exports
+            .entry(id)
+            .or_insert(ExportState::Options { git_init: false });
+
+        let mut write_to = None;
+        let mut open = true;
+
+        match state {
+            ExportState::Options { git_init } => {
+                let mut pick = false;
+
+                Window::new(format!("Export {} as a cargo project", tab.name))
+                    .id(id.with("export_window"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+                    .show(ctx, |ui| {
+                        ui.checkbox(git_init, "Initialize a git repository");
+
+                        ui.horizontal(|ui| {
+                            pick = ui.button("Choose folder…").clicked();
+                            open = !ui.button("Cancel").clicked();
+                        });
+                    });
+
+                if pick {
+                    let (tx, rx) = channel();
+                    let ctx = ctx.clone();
+
+                    // the dialog blocks, so keep it off the ui thread
+                    thread::spawn(move || {
+                        let dir = rfd::FileDialog::new()
+                            .set_title("Export scratch to folder")
+                            .pick_folder();
+
+                        let _ = tx.send(dir);
+                        ctx.request_repaint();
+                    });
+
+                    *state = ExportState::Picking {
+                        git_init: *git_init,
+                        rx,
+                    };
+                }
+            }
+
+            ExportState::Picking { git_init, rx } => match rx.try_recv() {
+                Ok(Some(dir)) => match export::is_empty_dir(&dir) {
+                    Ok(true) => write_to = Some((dir, *git_init)),
+                    Ok(false) => {
+                        *state = ExportState::Confirm {
+                            git_init: *git_init,
+                            dir,
+                        }
+                    }
+                    Err(e) => {
+                        dialogs.push(DialogCommand::error(
+                            "Failed to export",
+                            format!("Failed to read {}\n\n{e}", dir.display()),
+                        ));
+                        open = false;
+                    }
+                },
+                // dialog is still open, keep waiting
+                Err(TryRecvError::Empty) => (),
+                // user cancelled, back to the options
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    *state = ExportState::Options {
+                        git_init: *git_init,
+                    }
+                }
+            },
+
+            ExportState::Confirm { git_init, dir } => {
+                let mut confirmed = false;
+
+                Window::new("Folder isn't empty")
+                    .id(id.with("export_confirm_window"))
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "{} already has files in it. Files the project has will be \
+                             overwritten",
+                            dir.display()
+                        ));
+
+                        ui.horizontal(|ui| {
+                            confirmed = ui.button("Export anyway").clicked();
+                            open = !ui.button("Cancel").clicked();
+                        });
+                    });
+
+                if confirmed {
+                    write_to = Some((dir.clone(), *git_init));
+                }
+            }
+
+            ExportState::Writing(rx) => match rx.try_recv() {
+                Ok(Ok(dir)) => {
+                    export::open_folder(&dir);
+                    open = false;
+                }
+                Ok(Err(e)) => {
+                    dialogs.push(DialogCommand::error("Failed to export", e.to_string()));
+                    open = false;
+                }
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => open = false,
+            },
+        }
+
+        if let Some((dir, git_init)) = write_to {
+            let (tx, rx) = channel();
+
+            let code = tab.editor.code.clone();
+            let files = tab.module_files();
+            let run_config = tab.run_config;
+            let dependencies = tab.dependencies.clone();
+            let ctx = ctx.clone();
+
+            // inferring the dependencies can look them up in the crates index
+            thread::spawn(move || {
+                let write = || -> Result<PathBuf, ExportError> {
+                    let cache_dir = build_cache::cache_dir();
+                    // named after the folder, like `cargo new` would
+                    let name = export::package_name(
+                        &dir.file_name().unwrap_or_default().to_string_lossy(),
+                    );
+
+                    let mut project =
+                        tab_project(id, &code, &files, run_config, &dependencies, &cache_dir);
+                    project.name(&name).export(&dir)?;
+
+                    if git_init {
+                        export::git_init(&dir)?;
+                    }
+
+                    Ok(dir)
+                };
+
+                let _ = tx.send(write());
+                ctx.request_repaint();
+            });
+
+            *state = ExportState::Writing(rx);
+        }
+
+        if !open {
+            pending_exports.remove(&id);
+        }
+
+        open
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn save_scratch(
+        ctx: &egui::Context,
+        id: Id,
+        tree: &mut Tree,
+        pending_saves: &mut HashMap<Id, Receiver<Option<PathBuf>>>,
+        dialogs: &mut Vec<DialogCommand>,
+        saved: &mut Vec<Id>,
+        editor: &Editor,
+        // given when the code should be formatted first, for showing rustfmt's errors
+        format_with: Option<&mut Terminal>,
+    ) -> bool {
+        // the tab may have been closed while the dialog was still open
+        let Some(tab) = tree.find_tab_mut(id) else {
+            pending_saves.remove(&id);
+            return false;
+        };
+
+        // a scratch with module files is saved as a folder of them
+        let multi_file = !tab.files.is_empty();
+
+        let path = if let Some(rx) = pending_saves.get(&id) {
+            match rx.try_recv() {
+                Ok(Some(path)) => path,
+                // dialog is still open, keep waiting
+                Err(TryRecvError::Empty) => return true,
+                // user cancelled
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    pending_saves.remove(&id);
+                    return false;
+                }
+            }
+        } else if let Some(path) = tab.path.as_ref().filter(|path| path.is_dir() == multi_file) {
+            // already saved before, so don't prompt again. A scratch which got more files since is
+            // saved to a folder instead
+            path.clone()
+        } else {
+            let (tx, rx) = channel();
+            let file_name = format!("{}.rs", tab.name);
+            let ctx = ctx.clone();
+
+            // the dialog blocks, so keep it off the ui thread
+            thread::spawn(move || {
+                let dialog = rfd::FileDialog::new();
+                let path = if multi_file {
+                    dialog.set_title("Save scratch to folder").pick_folder()
+                } else {
+                    dialog
+                        .set_file_name(&file_name)
+                        .add_filter("Rust", &["rs"])
+                        .save_file()
+                };
+
+                let _ = tx.send(path);
+                ctx.request_repaint();
+            });
+
+            pending_saves.insert(id, rx);
+            return true;
+        };
+
+        pending_saves.remove(&id);
+
+        // code which can't be formatted is still saved as it is
+        if let Some(terminal) = format_with {
+            Self::format_tab(ctx, tab, terminal);
+        }
+        Self::clean_whitespace(tab, editor);
+
+        let written = if multi_file {
+            tab.source_files().into_iter().try_for_each(|(name, code)| {
+                let file = path.join(format!("{name}.rs"));
+                fs::write(&file, code).map_err(|e| (file, e))
+            })
+        } else {
+            fs::write(&path, &tab.editor.code).map_err(|e| (path.clone(), e))
+        };
+
+        match written {
+            Ok(_) => {
+                tab.disk_code = (!multi_file).then(|| tab.editor.code.clone());
+                tab.disk_change = None;
+                tab.saved_revision = tab.check.revision;
+                tab.path = Some(path);
+                saved.push(id);
+            }
+            Err((file, e)) => dialogs.push(DialogCommand::error(
+                "Failed to save",
+                format!("Failed to save {}\n\n{e}", file.display()),
+            )),
+        }
+
+        false
+    }
+
+    // Copy a playground link with the tab's code in it, saying what it leaves out
+    fn copy_permalink(ctx: &egui::Context, tab: &Tab) -> DialogCommand {
+        let RunConfig {
+            channel,
+            edition,
+            build_type,
+            ..
+        } = tab.run_config;
+        let url = playground::permalink(&tab.editor.code, channel, edition, build_type);
+        ctx.output().copied_text = url.clone();
+
+        let mut message = "Link copied to clipboard".to_string();
+        if url.len() > playground::MAX_URL_BYTES {
+            message += &format!(
+                "\n\nThe link is {} long, browsers may cut it short. Share it as a gist instead",
+                build_cache::format_size(url.len() as u64)
+            );
+        }
+        if !tab.files.is_empty() {
+            message += "\n\nThe playground only has one file, so the modules are left out";
+        }
+
+        DialogCommand::info("Copied playground link", message)
+    }
+
+    // Build and run the tab's code on play.rust-lang.org, showing its output in the terminal like
+    // a local run. The stop button stops waiting for the reply, the playground still runs it
+    fn run_on_playground(ctx: &egui::Context, tab: &Tab, terminal: &mut Terminal) {
+        let id = tab.id;
+        let code = tab.editor.code.clone();
+        let run_config = tab.run_config;
+        let has_modules = !tab.files.is_empty();
+
+        // the same abort signal a local run has, so the stop button works the same way
+        let (atx, arx) = channel::<()>();
+        let abort_rid: u64 = rand::thread_rng().gen();
+        let abort_id = id.with(format!("_thread_aborter_{abort_rid}"));
+
+        if let Some(prev) = terminal.abortable.insert(id, abort_id) {
+            let mut mem = ctx.memory();
+            if mem.data.get_temp::<Aborter>(prev).is_some() {
+                mem.data.remove::<Aborter>(prev);
+            }
+        }
+
+        ctx.memory()
+            .data
+            .insert_temp::<Aborter>(abort_id, Arc::new(Mutex::new(atx)));
+
+        let (output_tx, output_rx) = sync_channel::<OutputChunk>(OUTPUT_BUFFER);
+        terminal.content.insert(id, Some(output_rx));
+        // the program already ran by the time its output arrives, there's nothing to type into
+        terminal.stdin.remove(&id);
+
+        let run_state = Arc::new(Mutex::new(RunState {
+            run_config,
+            ..Default::default()
+        }));
+        terminal.start_run(id, Arc::clone(&run_state));
+
+        if !terminal.open {
+            terminal.toggle();
+        }
+
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let continuous_id = Id::new("continuous_mode");
+            *ctx.memory()
+                .data
+                .get_temp_mut_or_default::<u64>(continuous_id) += 1;
+
+            // the rest is dropped once the terminal stopped taking the tab's output
+            let push = |stream, text: &str| {
+                for line in text.split_inclusive('\n') {
+                    let mut line = line.to_string();
+                    if !line.ends_with('\n') {
+                        line.push('\n');
+                    }
+                    if output_tx.send(OutputChunk::new(stream, line)).is_err() {
+                        break;
+                    }
+                }
+            };
+
+            // so it's clear the output didn't come from this machine
+            push(
+                Stream::Stdout,
+                &format!(
+                    "\x1b[90m[playground] running on play.rust-lang.org · {} · {} · {}\x1b[0m\n",
+                    run_config.edition,
+                    run_config.channel,
+                    RunConfig::build_type_name(run_config.build_type)
+                ),
+            );
+
+            if has_modules {
+                push(
+                    Stream::Stderr,
+                    "\x1b[1;33mwarning\x1b[0m: the playground only has one file, so the modules \
+                     are left out\n",
+                );
+            }
+
+            let (tx, rx) = channel();
+            thread::spawn(move || {
+                let _ = tx.send(playground::execute(
+                    &code,
+                    run_config.channel,
+                    run_config.edition,
+                    run_config.build_type,
+                    run_config.mode == RunMode::Test,
+                ));
+            });
+
+            // a message from the stop button, or a new run dropping the sender, stops the waiting
+            let reply = loop {
+                if let Ok(reply) = rx.recv_timeout(Duration::from_millis(100)) {
+                    break Some(reply);
+                }
+
+                if !matches!(arx.try_recv(), Err(TryRecvError::Empty)) {
+                    break None;
+                }
+            };
+
+            let status = match reply {
+                // the compiler's output comes first, as it would in a local run
+                Some(Ok(execution)) => {
+                    push(Stream::Stderr, &execution.stderr);
+                    push(Stream::Stdout, &execution.stdout);
+                    RunStatus::Exited(execution.exit_code())
+                }
+                Some(Err(e)) => {
+                    push(Stream::Stderr, &format!("\x1b[1;31merror\x1b[0m: {e}\n"));
+                    RunStatus::Failed
+                }
+                None => {
+                    push(Stream::Stderr, "\x1b[90mrequest cancelled by user\x1b[0m\n");
+                    RunStatus::Killed
+                }
+            };
+
+            {
+                let mut run_state = run_state.lock().unwrap();
+                run_state.duration = Some(run_state.start.elapsed());
+                run_state.status = status;
+            }
+
+            {
+                let mut mem = ctx.memory();
+                *mem.data.get_temp_mut_or_default::<u64>(continuous_id) -= 1;
+
+                if mem.data.get_temp::<Aborter>(abort_id).is_some() {
+                    mem.data.remove::<Aborter>(abort_id);
+                }
+            }
+
+            ctx.request_repaint();
+        });
+    }
+
+    // Run each of a tab's files through rustfmt, keeping the cursor next to the same code. Code
+    // which can't be formatted is left alone, and rustfmt's errors are shown in the terminal instead
+    fn format_tab(ctx: &egui::Context, tab: &mut Tab, terminal: &mut Terminal) {
+        let edition = tab.run_config.edition.to_string();
+        let mut changed = false;
+
+        for file in std::iter::once(None).chain((0..tab.files.len()).map(Some)) {
+            let editor_id = tab.editor_id(file);
+            let (name, editor) = match file {
+                Some(index) => (
+                    format!("{}.rs", tab.files[index].name),
+                    &mut tab.files[index].editor,
+                ),
+                None => ("main.rs".to_string(), &mut tab.editor),
+            };
+
+            match rustfmt::format(&editor.code, &edition) {
+                Ok(formatted) => {
+                    changed |= Self::set_formatted(ctx, editor, editor_id, formatted);
+                }
+                // rustfmt's errors point at <stdin>, so say which file they're about
+                Err(e) => terminal.show_message(tab.id, Stream::Stderr, format!("{name}: {e}\n")),
+            }
+        }
+
+        if changed {
+            tab.check.code_changed();
+        }
+    }
+
+    // Replace an editor's code with the formatted code, and move its cursor along. Returns whether
+    // formatting changed anything
+    fn set_formatted(
+        ctx: &egui::Context,
+        editor: &mut CodeEditor,
+        editor_id: Id,
+        formatted: String,
+    ) -> bool {
+        let code = &editor.code;
+        if formatted == *code {
+            return false;
+        }
+
+        if let Some(mut state) = TextEdit::load_state(ctx, editor_id) {
+            if let Some(range) = state.ccursor_range() {
+                let map = |cursor: CCursor| {
+                    CCursor::new(rustfmt::map_cursor(code, &formatted, cursor.index))
+                };
+
+                state.set_ccursor_range(Some(CCursorRange::two(
+                    map(range.secondary),
+                    map(range.primary),
+                )));
+                state.store(ctx, editor_id);
+            }
+        }
+
+        editor.code = formatted;
+        true
+    }
+
+    // Focus a tab and move the cursor of its main.rs to a line and column. Locations past the end
+    // of the code are clamped to it
+    fn jump_to(ctx: &egui::Context, id: Id, line: usize, col: usize, tree: &mut Tree) {
+        let Some((node, tab_index)) = tree.find_tab_location(id) else {
+            return;
+        };
+
+        tree.set_focused_node(node);
+        tree.set_active_tab(node, tab_index);
+
+        let Some(tab) = tree.find_tab_mut(id) else {
+            return;
+        };
+
+        // the locations are all in main.rs
+        tab.open_file = None;
+        tab.scroll_offset = Some(tab.editor.reveal(ctx, tab.editor_id(None), line, col));
+    }
+
+    // Move the cursor of the file a tab has open to a line and column
+    fn go_to_line(ctx: &egui::Context, tab: &mut Tab, line: usize, col: usize) {
+        let editor_id = tab.editor_id(tab.open_file);
+        let editor = match tab.open_file.and_then(|index| tab.files.get_mut(index)) {
+            Some(file) => &mut file.editor,
+            None => &mut tab.editor,
+        };
+
+        let offset = editor.reveal(ctx, editor_id, line, col);
+        tab.scroll_offset = Some(offset);
+    }
+
+    // Comment or uncomment the selected lines of the file a tab has open
+    // Trim the trailing whitespace and add the final newline the settings ask for on save, in the
+    // editors too so it's undone like any other edit
+    fn clean_whitespace(tab: &mut Tab, settings: &Editor) {
+        let trim = settings.trim_trailing_whitespace_on_save;
+        let final_newline = settings.ensure_final_newline_on_save;
+        if !trim && !final_newline {
+            return;
+        }
+
+        let mut changed = false;
+        let editors =
+            std::iter::once(&mut tab.editor).chain(tab.files.iter_mut().map(|f| &mut f.editor));
+        for editor in editors {
+            let cleaned = whitespace::clean(&editor.code, trim, final_newline);
+            if cleaned != editor.code {
+                editor.code = cleaned;
+                changed = true;
+            }
+        }
+
+        if changed {
+            tab.check.code_changed();
+        }
+    }
+
+    // Edit the code of the tab's open file, `edit` says whether it changed it
+    fn edit_code(tab: &mut Tab, edit: impl FnOnce(&mut CodeEditor, Id) -> bool) {
+        let editor_id = tab.editor_id(tab.open_file);
+        let editor = match tab.open_file.and_then(|index| tab.files.get_mut(index)) {
+            Some(file) => &mut file.editor,
+            None => &mut tab.editor,
+        };
+
+        if edit(editor, editor_id) {
+            tab.check.code_changed();
+        }
+    }
+
+    fn show_dialog(ctx: &egui::Context, dialog: &DialogCommand) -> bool {
+        let mut open = true;
+
+        let (id, title, message) = match dialog {
+            DialogCommand::Error { id, title, message }
+            | DialogCommand::Info { id, title, message } => (id, title, message),
+        };
+
+        Window::new(title)
+            .id(*id)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                if let DialogCommand::Error { .. } = dialog {
+                    ui.colored_label(ui.visuals().error_fg_color, message);
+                } else {
+                    ui.label(message);
+                }
+
+                ui.vertical_centered(|ui| {
+                    if ui.button("Ok").clicked() {
+                        open = false;
+                    }
+                });
+            });
+
+        open
+    }
+}
+
+// The edition, toolchain, profile, target and time limit the tab is run with
+// A link with a button copying it, in a grid row
+fn link_row(ui: &mut Ui, label: &str, url: &str) {
+    ui.label(label);
+    ui.hyperlink(url);
+    if ui.button("📋 Copy").clicked() {
+        ui.output().copied_text = url.to_string();
+    }
+    ui.end_row();
+}
+
+fn run_config_menu(ui: &mut Ui, run_config: &mut RunConfig, default_timeout: Option<u64>) {
+    ui.label("Edition");
+    ui.horizontal(|ui| {
+        for edition in RunConfig::EDITIONS {
+            ui.radio_value(&mut run_config.edition, edition, edition.to_string());
+        }
+    });
+
+    ui.separator();
+
+    ui.label("Toolchain");
+    ui.horizontal(|ui| {
+        for channel in RunConfig::CHANNELS {
+            ui.radio_value(&mut run_config.channel, channel, channel.to_string());
+        }
+    });
+
+    ui.separator();
+
+    ui.label("Profile");
+    ui.horizontal(|ui| {
+        for build_type in RunConfig::BUILD_TYPES {
+            ui.radio_value(
+                &mut run_config.build_type,
+                build_type,
+                RunConfig::build_type_name(build_type),
+            );
+        }
+    });
+
+    ui.separator();
+
+    ui.label("Target");
+    ui.horizontal(|ui| {
+        for target in Target::ALL {
+            ui.radio_value(&mut run_config.target, target, target.name())
+                .on_hover_text(target.triple().unwrap_or("The machine rust play runs on"));
+        }
+    });
+
+    ui.separator();
+
+    ui.label("Time limit");
+    ui.horizontal(|ui| {
+        let default = match default_timeout {
+            Some(secs) => format!("Default ({secs}s)"),
+            None => "Default (off)".to_string(),
+        };
+
+        ui.radio_value(&mut run_config.timeout, Timeout::Default, default);
+        ui.radio_value(&mut run_config.timeout, Timeout::Off, "Off");
+
+        let secs = match run_config.timeout {
+            Timeout::Secs(secs) => secs,
+            _ => default_timeout.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        };
+        if ui
+            .radio(matches!(run_config.timeout, Timeout::Secs(_)), "Custom")
+            .clicked()
+        {
+            run_config.timeout = Timeout::Secs(secs);
+        }
+
+        if let Timeout::Secs(secs) = &mut run_config.timeout {
+            ui.add(DragValue::new(secs).clamp_range(1..=86400).suffix("s"));
+        }
+    });
+}
+
+// Pinning, duplicating, splitting and closing tabs from a tab's context menu. Closing asks about
+// unsaved changes first, and the dock adds its own Close after these
+fn tab_buttons(ui: &mut Ui, tab: &mut Tab, groups: usize) -> Option<TabCommand> {
+    let id = tab.id;
+
+    ui.separator();
+    if ui
+        .button(if tab.pinned { "Unpin" } else { "Pin" })
+        .clicked()
+    {
+        tab.pinned = !tab.pinned;
+        ui.close_menu();
+    }
+    let duplicate = ui.button("Duplicate").clicked();
+
+    ui.separator();
+    // with Shift held, a copy is split off and the tab stays where it is
+    let copy = ui.input().modifiers.shift;
+    let split = [("Split right", Split::Right), ("Split down", Split::Below)]
+        .into_iter()
+        .find_map(|(text, split)| {
+            ui.button(text)
+                .on_hover_text("Hold Shift to split off a copy")
+                .clicked()
+                .then_some(split)
+        });
+    let move_group = groups == 2 && ui.button("Move to other group").clicked();
+
+    ui.separator();
+    let close_others = ui.button("Close others").clicked();
+    let close_right = ui.button("Close tabs to the right").clicked();
+    let close_all = ui.button("Close all").clicked();
+
+    let command = if duplicate {
+        TabCommand::Duplicate(id)
+    } else if let Some(split) = split {
+        TabCommand::Split {
+            tab: id,
+            split,
+            copy,
+        }
+    } else if move_group {
+        TabCommand::MoveToOtherGroup(id)
+    } else if close_others {
+        TabCommand::CloseOthers(id)
+    } else if close_right {
+        TabCommand::CloseToRight(id)
+    } else if close_all {
+        TabCommand::CloseAll
+    } else {
+        return None;
+    };
+
+    ui.close_menu();
+    Some(command)
+}
+
+// Says the tab's file was changed by another program, and lets the user pick whose code to keep
+fn disk_change_banner(ui: &mut Ui, tab: &mut Tab) {
+    let file_name = tab
+        .path
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    ui.horizontal(|ui| {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("{file_name} was changed on disk"),
+        );
+
+        if ui
+            .button("Reload")
+            .on_hover_text("Replace the code in the tab with the file's")
+            .clicked()
+        {
+            let code = tab.disk_change.take().unwrap_or_default();
+            tab.editor.code = code.clone();
+            tab.disk_code = Some(code);
+            tab.check.code_changed();
+            tab.saved_revision = tab.check.revision;
+        }
+
+        if ui
+            .button("Keep mine")
+            .on_hover_text("Keep the code in the tab, saving overwrites the file")
+            .clicked()
+        {
+            tab.disk_code = tab.disk_change.take();
+        }
+    });
+}
+
+// The scratch's files, picking one shows it in the editor. Files are added with the + button, and
+// renamed or deleted from their context menu
+fn file_strip(ui: &mut Ui, tab: &mut Tab) {
+    let rename_id = tab.id.with("rename_file");
+    let edit_id = rename_id.with("edit");
+    // the index of the file being renamed, and the name typed in so far
+    let mut renaming = ui.data().get_temp::<(usize, String)>(rename_id);
+    let mut open = tab.open_file;
+    // applied once the files aren't borrowed anymore
+    let mut rename = None;
+    let mut delete = None;
+
+    ui.horizontal(|ui| {
+        if ui.selectable_label(open.is_none(), "main.rs").clicked() {
+            open = None;
+        }
+
+        for (index, file) in tab.files.iter().enumerate() {
+            match &mut renaming {
+                Some((renamed, name)) if *renamed == index => {
+                    let response =
+                        ui.add(TextEdit::singleline(name).id(edit_id).desired_width(80.0));
+
+                    let valid = module_name(name, &tab.files, Some(index));
+                    if let Err(e) = valid {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+
+                    if response.lost_focus() {
+                        let entered = ui.input().key_pressed(Key::Enter);
+
+                        match valid {
+                            Ok(name) if entered => {
+                                renaming = None;
+                                rename = Some((index, name));
+                            }
+                            // keep going until it's a name that works
+                            Err(_) if entered => ui.memory().request_focus(edit_id),
+                            // clicking away cancels it
+                            _ => renaming = None,
+                        }
+                    }
+                }
+
+                _ => {
+                    let response =
+                        ui.selectable_label(open == Some(index), format!("{}.rs", file.name));
+
+                    if response.clicked() {
+                        open = Some(index);
+                    }
+
+                    response.context_menu(|ui| {
+                        if ui.button("Rename").clicked() {
+                            renaming = Some((index, file.name.clone()));
+                            ui.memory().request_focus(edit_id);
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Delete").clicked() {
+                            delete = Some(index);
+                            ui.close_menu();
+                        }
+                    });
+                }
+            }
+        }
+
+        if ui
+            .small_button("+")
+            .on_hover_text("Add a file for a module, like `mod foo;` in main.rs")
+            .clicked()
+        {
+            let name = (1..)
+                .map(|number| format!("module{number}"))
+                .find(|name| tab.files.iter().all(|file| file.name != *name))
+                .expect("there's always a free name");
+
+            // named right away, the default name is only a placeholder
+            renaming = Some((tab.files.len(), name.clone()));
+            ui.memory().request_focus(edit_id);
+
+            tab.files.push(ScratchFile {
+                name,
+                editor: CodeEditor::new("rs", String::new()),
+            });
+            open = Some(tab.files.len() - 1);
+            tab.check.code_changed();
+        }
+    });
+
+    if let Some((index, name)) = rename {
+        if let Some(file) = tab.files.get_mut(index) {
+            file.name = name;
+            tab.check.code_changed();
+        }
+    }
+
+    if let Some(index) = delete {
+        tab.files.remove(index);
+        tab.check.code_changed();
+
+        open = match open {
+            Some(open) if open == index => None,
+            Some(open) if open > index => Some(open - 1),
+            open => open,
+        };
+        renaming = None;
+    }
+
+    match renaming {
+        Some(renaming) => ui.data().insert_temp(rename_id, renaming),
+        None => ui.data().remove::<(usize, String)>(rename_id),
+    }
+
+    // each file has its own place it was scrolled to, which isn't kept
+    if open != tab.open_file {
+        tab.open_file = open;
+        tab.scroll_offset = None;
+    }
+}
+
+// A file name typed in for a module file, like `foo` or `foo.rs`, as the module's name
+fn module_name(
+    name: &str,
+    files: &[ScratchFile],
+    renaming: Option<usize>,
+) -> Result<String, &'static str> {
+    let name = name.trim();
+    let name = name.strip_suffix(".rs").unwrap_or(name);
+
+    let starts_well =
+        matches!(name.chars().next(), Some(char) if char.is_ascii_alphabetic() || char == '_');
+    if !starts_well
+        || !name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_')
+    {
+        return Err("Use letters, digits and underscores, like a module name");
+    }
+
+    // a lib.rs would make the scratch a library as well
+    if name == "main" || name == "lib" {
+        return Err("That name is taken by the crate root");
+    }
+
+    let taken = files
+        .iter()
+        .enumerate()
+        .any(|(index, file)| Some(index) != renaming && file.name == name);
+    if taken {
+        return Err("There's already a file with that name");
+    }
+
+    Ok(name.to_string())
+}
+
+// The program's arguments, and key and value rows for its environment variables
+fn arguments_editor(ui: &mut Ui, args: &mut String, env_vars: &mut Vec<EnvVar>) {
+    ui.label("Arguments");
+    ui.add(
+        TextEdit::singleline(args)
+            .hint_text(r#"--name "with spaces""#)
+            .desired_width(200.0),
+    );
+
+    ui.label("Environment variables");
+
+    let mut remove = None;
+    for (index, env_var) in env_vars.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut env_var.key)
+                    .hint_text("key")
+                    .desired_width(100.0),
+            );
+            ui.label("=");
+            ui.add(
+                TextEdit::singleline(&mut env_var.value)
+                    .hint_text("value")
+                    .desired_width(100.0),
+            );
+
+            if ui.small_button("Remove").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+
+    if let Some(index) = remove {
+        env_vars.remove(index);
+    }
+
+    if ui.button("Add").clicked() {
+        env_vars.push(EnvVar::default());
+    }
+}
+
+// Crate name and version rows, and a crates.io search to add them from
+fn dependencies_editor(
+    ui: &mut Ui,
+    dependencies: &mut Vec<CrateDependency>,
+    search: &mut CrateSearch,
+) {
+    ui.label("Dependencies");
+
+    let mut remove = None;
+    for (index, dependency) in dependencies.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut dependency.name)
+                    .hint_text("name")
+                    .desired_width(120.0),
+            );
+            ui.add(
+                TextEdit::singleline(&mut dependency.version)
+                    .hint_text("*")
+                    .desired_width(60.0),
+            );
+
+            if ui.small_button("Remove").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+
+    if let Some(index) = remove {
+        dependencies.remove(index);
+    }
+
+    if ui.button("Add").clicked() {
+        dependencies.push(CrateDependency::default());
+    }
+
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            TextEdit::singleline(&mut search.query)
+                .hint_text("Search crates.io")
+                .desired_width(150.0),
+        );
+        let submitted = response.lost_focus() && ui.input().key_pressed(Key::Enter);
+
+        let query = search.query.trim();
+        let can_search = search.pending.is_none() && !query.is_empty();
+
+        let clicked = ui.add_enabled(can_search, Button::new("Search")).clicked();
+        if can_search && (clicked || submitted) {
+            search.pending = Some(search_crates(query));
+            search.error = None;
+        }
+
+        if search.pending.is_some() {
+            ui.spinner();
+        }
+    });
+
+    if let Some(rx) = &search.pending {
+        let result = match rx.try_recv() {
+            Ok(result) => Some(result.map_err(|e| e.to_string())),
+            Err(TryRecvError::Empty) => {
+                // nothing wakes up the ui when the request finishes, so keep polling
+                ui.ctx().request_repaint_after(Duration::from_millis(100));
+                None
+            }
+            Err(TryRecvError::Disconnected) => Some(Err("The search stopped".to_string())),
+        };
+
+        if let Some(result) = result {
+            search.pending = None;
+
+            match result {
+                Ok(results) => search.results = results,
+                Err(e) => {
+                    search.results.clear();
+                    search.error = Some(e);
+                }
+            }
+        }
+    }
+
+    if let Some(error) = &search.error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    let mut picked = None;
+    for result in &search.results {
+        let response = ui.selectable_label(
+            false,
+            format!("{} {}", result.name, result.latest_version()),
+        );
+        let response = match &result.description {
+            Some(description) => response.on_hover_text(description.trim()),
+            None => response,
+        };
+
+        if response.clicked() {
+            picked = Some(result.clone());
+        }
+    }
+
+    // picking a crate which is already listed just updates its version
+    if let Some(result) = picked {
+        let version = result.latest_version().to_string();
+
+        match dependencies
+            .iter_mut()
+            .find(|dependency| dependency.name == result.name)
+        {
+            Some(dependency) => dependency.version = version,
+            None => dependencies.push(CrateDependency {
+                name: result.name,
+                version,
+            }),
+        }
+
+        search.query.clear();
+        search.results.clear();
+    }
+}
+
+// The command as it would be typed into a shell, after the tab's own environment variables
+fn command_line(command: &std::process::Command, env_vars: &[EnvVar]) -> String {
+    let env_vars = env_vars.iter().map(|env_var| {
+        format!(
+            "{}={}",
+            env_var.key.trim(),
+            shell_words::quote(&env_var.value)
+        )
+    });
+    let args = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| shell_words::quote(&arg.to_string_lossy()).into_owned());
+
+    env_vars.chain(args).collect::<Vec<_>>().join(" ")
+}
+
+// cargo only knows about subcommands which are installed
+fn cargo_expand_installed() -> bool {
+    let mut command = std::process::Command::new("cargo");
+    command
+        .args(["expand", "--version"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // hide the console window from command. Very important.
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW.0);
+
+    matches!(command.status(), Ok(status) if status.success())
+}
+
+// How wide each tab's title can get so the tabs fit their tab bar, going by where the tab bars were
+// last frame. Also the tab bars which overflow, with the index of their first tab which didn't fit
+fn fit_tab_bars(tree: &Tree) -> (HashMap<Id, f32>, Vec<(NodeIndex, usize)>) {
+    let mut widths = HashMap::new();
+    let mut overflowing = vec![];
+
+    for (index, node) in tree.iter().enumerate() {
+        let Node::Leaf { rect, tabs, .. } = node else {
+            continue;
+        };
+        // not shown yet
+        if rect.width() <= 0.0 {
+            continue;
+        }
+
+        // the tabs which don't fit push the + button out, the overflow button takes its place
+        let (width, shown) = tab_names::fit_tabs(
+            rect.width() - TAB_ADD_WIDTH,
+            tabs.len(),
+            MIN_TAB_WIDTH,
+            OVERFLOW_BTN_WIDTH - TAB_ADD_WIDTH,
+        );
+
+        for (tab_index, tab) in tabs.iter().enumerate() {
+            // the ones which didn't fit take as little room as they can
+            let title_width = if tab_index < shown {
+                width - TAB_PADDING
+            } else {
+                0.0
+            };
+            widths.insert(tab.id, title_width);
+        }
+
+        if shown < tabs.len() {
+            overflowing.push((NodeIndex(index), shown));
+        }
+    }
+
+    (widths, overflowing)
+}
+
+// The welcome page's links and examples, and whether to show it the next time too. Returns what
+// was clicked
+fn welcome_page(ui: &mut Ui, show_welcome: &mut bool) -> Vec<TabCommand> {
+    let mut commands = vec![];
+
+    ScrollArea::vertical().show(ui, |ui| {
+        ui.add_space(16.0);
+        ui.heading("Welcome to Rust Play");
+        ui.weak("Write some Rust and run it, no project needed");
+        ui.add_space(8.0);
+
+        ui.horizontal(|ui| {
+            if ui.button("New scratch").clicked() {
+                commands.push(TabCommand::NewScratch(None));
+            }
+            if ui.button("Open file…").clicked() {
+                commands.push(TabCommand::Open);
+            }
+            if ui.button("Settings").clicked() {
+                commands.push(TabCommand::OpenSettings);
+            }
+        });
+
+        ui.add_space(12.0);
+        ui.label("Start from an example");
+        Grid::new("welcome_examples")
+            .num_columns(2)
+            .spacing(vec2(8.0, 8.0))
+            .show(ui, |ui| {
+                for (index, example) in EXAMPLES.iter().enumerate() {
+                    let response = Frame::group(ui.style())
+                        .show(ui, |ui| {
+                            ui.set_width(220.0);
+                            ui.strong(example.name);
+                            ui.weak(example.description);
+                        })
+                        .response
+                        .interact(Sense::click())
+                        .on_hover_cursor(CursorIcon::PointingHand);
+                    if response.clicked() {
+                        commands.push(TabCommand::NewScratch(Some(index)));
+                    }
+
+                    if index % 2 == 1 {
+                        ui.end_row();
+                    }
+                }
+            });
+
+        ui.add_space(12.0);
+        ui.checkbox(show_welcome, "Show this page on startup");
+    });
+
+    commands
+}
+
+// Move the pinned tabs in front of the others in each tab bar, keeping the same tab active. Tabs
+// dragged in between the pinned ones are moved back behind them the next frame
+fn sort_pinned_first(tree: &mut Tree) {
+    for node in tree.iter_mut() {
+        let Node::Leaf { tabs, active, .. } = node else {
+            continue;
+        };
+
+        if tabs
+            .windows(2)
+            .all(|pair| pair[0].pinned || !pair[1].pinned)
+        {
+            continue;
+        }
+
+        let active_id = tabs.get(active.0).map(|tab| tab.id);
+        // a stable sort, so the tabs keep their order otherwise
+        tabs.sort_by_key(|tab| !tab.pinned);
+
+        if let Some(index) = tabs.iter().position(|tab| Some(tab.id) == active_id) {
+            *active = TabIndex(index);
+        }
+    }
+}
+
+// The tabs after a tab in its tab bar
+fn tabs_to_the_right(tree: &Tree, id: Id) -> Vec<Id> {
+    let Some((node, tab_index)) = tree.find_tab_location(id) else {
+        return vec![];
+    };
+
+    match &tree[node] {
+        Node::Leaf { tabs, .. } => tabs[tab_index.0 + 1..].iter().map(|tab| tab.id).collect(),
+        _ => vec![],
+    }
+}
+
+// The read-only tab showing what was generated from a tab
+fn find_generated(tree: &Tree, id: Id, generated: Generated) -> Option<Id> {
+    tree.tabs()
+        .find(|tab| tab.editor.read_only && tab.source == Some((id, generated)))
+        .map(|tab| tab.id)
+}
+
+/// The project a tab is built as, with everything from its run configuration
+pub fn tab_project<'a>(
+    id: Id,
+    code: &'a str,
+    // the module files, by name and code
+    files: &'a [(String, String)],
+    run_config: RunConfig,
+    dependencies: &'a [CrateDependency],
+    cache_dir: &'a Path,
+) -> Project<'a> {
+    // each tab gets its own project, so the tabs can build at the same time
+    let mut project = Project::new(id);
+    project
+        .workspace(cache_dir)
+        .build_type(run_config.build_type)
+        .channel(run_config.channel)
+        .file(File::new("main", code))
+        .edition(run_config.edition);
+
+    for (name, code) in files {
+        project.file(File::new(name, code));
+    }
+
+    for dependency in dependencies {
+        let name = dependency.name.trim();
+
+        if !name.is_empty() {
+            project.dependency(Dependency::new(name, &dependency.version));
+        }
+    }
+
+    project
 }
@@ -0,0 +1,172 @@
+use std::ops::Range;
+
+use egui::epaint::text::TAB_SIZE;
+use egui::text::LayoutJob;
+use egui::{pos2, vec2, Color32, Id, Mesh, Rect, Rounding, Sense, Shape};
+
+// how tall each line is drawn when they all fit, and how short they get before the map scrolls
+const LINE_HEIGHT: f32 = 2.0;
+const MIN_LINE_HEIGHT: f32 = 1.0;
+const CHAR_WIDTH: f32 = 1.0;
+const VIEWPORT_COLOR: Color32 = Color32::from_rgba_premultiplied(30, 30, 30, 30);
+const ACTIVE_VIEWPORT_COLOR: Color32 = Color32::from_rgba_premultiplied(50, 50, 50, 50);
+const MARKER_WIDTH: f32 = 3.0;
+
+// A line as it's drawn: a block from its indentation to its end, in the color most of it is
+// highlighted with
+#[derive(Debug, Clone, PartialEq)]
+struct Line {
+    indent: usize,
+    len: usize,
+    color: Color32,
+}
+
+// Each line of the highlighted code, weighing the colors by how many chars which aren't
+// whitespace they cover
+fn lines(job: &LayoutJob) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut colors: Vec<(Color32, usize)> = vec![];
+    let mut columns = 0;
+    let mut indent = None;
+
+    let mut end_line = |colors: &mut Vec<(Color32, usize)>, columns, indent: Option<usize>| {
+        let color = colors
+            .iter()
+            .max_by_key(|(_, weight)| *weight)
+            .map_or(Color32::GRAY, |(color, _)| *color);
+        colors.clear();
+        lines.push(Line {
+            indent: indent.unwrap_or(columns),
+            len: columns,
+            color,
+        });
+    };
+
+    for section in &job.sections {
+        let color = section.format.color;
+        for c in job.text[section.byte_range.clone()].chars() {
+            match c {
+                '\n' => {
+                    end_line(&mut colors, columns, indent);
+                    columns = 0;
+                    indent = None;
+                    continue;
+                }
+                '\t' => columns += TAB_SIZE,
+                _ => columns += 1,
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+
+            indent.get_or_insert(columns - 1);
+            match colors.iter_mut().find(|(other, _)| *other == color) {
+                Some((_, weight)) => *weight += 1,
+                None => colors.push((color, 1)),
+            }
+        }
+    }
+    end_line(&mut colors, columns, indent);
+
+    lines
+}
+
+/// A scaled down picture of an editor's code down its right side, showing where the editor is
+/// scrolled to. It's only worked out again when the code or its colors change
+#[derive(Debug, Clone, Default)]
+pub struct Minimap {
+    // the highlighted code the lines are from
+    job: LayoutJob,
+    lines: Vec<Line>,
+    // the lines' blocks from the top of the map, by how tall each line was
+    mesh: Option<(f32, Mesh)>,
+}
+
+impl Minimap {
+    pub const WIDTH: f32 = 80.0;
+    /// Editors narrower than this don't have room for it
+    pub const MIN_EDITOR_WIDTH: f32 = 500.0;
+
+    /// Take in the code as it's highlighted now
+    pub fn update(&mut self, job: &LayoutJob) {
+        if self.job != *job {
+            self.lines = lines(job);
+            self.job = job.clone();
+            self.mesh = None;
+        }
+    }
+
+    /// Show it in `rect`, with the lines the editor shows in `visible` and the `markers` of
+    /// diagnostics and search matches by their line and color. Returns the line clicked or
+    /// dragged to, to scroll the editor to
+    pub fn show(
+        &mut self,
+        ui: &egui::Ui,
+        id: Id,
+        rect: Rect,
+        visible: Range<usize>,
+        markers: &[(usize, Color32)],
+    ) -> Option<usize> {
+        let line_count = self.lines.len().max(1);
+        let line_height = (rect.height() / line_count as f32).clamp(MIN_LINE_HEIGHT, LINE_HEIGHT);
+
+        // too long to fit, it scrolls along with the editor
+        let overflow = (line_count as f32 * line_height - rect.height()).max(0.0);
+        let scrollable = line_count.saturating_sub(visible.len()).max(1);
+        let progress = (visible.start as f32 / scrollable as f32).min(1.0);
+        let top = rect.top() - overflow * progress;
+        let line_y = |line: usize| top + line as f32 * line_height;
+
+        let response = ui.interact(rect, id.with("minimap"), Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+
+        if self.mesh.as_ref().map(|(height, _)| *height) != Some(line_height) {
+            let mut mesh = Mesh::default();
+            for (index, line) in self.lines.iter().enumerate() {
+                if line.len == line.indent {
+                    continue;
+                }
+
+                let y = index as f32 * line_height;
+                let block = Rect::from_min_max(
+                    pos2(line.indent as f32 * CHAR_WIDTH, y),
+                    pos2(line.len as f32 * CHAR_WIDTH, y + line_height),
+                );
+                mesh.add_colored_rect(block, line.color.linear_multiply(0.7));
+            }
+            self.mesh = Some((line_height, mesh));
+        }
+        if let Some((_, mesh)) = &self.mesh {
+            let mut mesh = mesh.clone();
+            mesh.translate(vec2(rect.left() + 2.0, top));
+            painter.add(Shape::mesh(mesh));
+        }
+
+        let viewport = Rect::from_x_y_ranges(
+            rect.x_range(),
+            line_y(visible.start)..=line_y(visible.end.max(visible.start + 1)),
+        );
+        let viewport_color = if response.hovered() || response.dragged() {
+            ACTIVE_VIEWPORT_COLOR
+        } else {
+            VIEWPORT_COLOR
+        };
+        painter.rect_filled(viewport, Rounding::none(), viewport_color);
+
+        for (line, color) in markers {
+            let marker = Rect::from_min_size(
+                pos2(rect.right() - MARKER_WIDTH, line_y(*line)),
+                vec2(MARKER_WIDTH, line_height.max(2.0)),
+            );
+            painter.rect_filled(marker, Rounding::none(), *color);
+        }
+
+        if response.clicked() || response.dragged() {
+            let pointer = response.interact_pointer_pos()?;
+            let line = ((pointer.y - top) / line_height).max(0.0) as usize;
+            return Some(line.min(line_count - 1));
+        }
+
+        None
+    }
+}
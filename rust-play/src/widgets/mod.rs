@@ -1,4 +1,10 @@
 pub mod code_editor;
 pub mod dock;
+pub mod minimap;
+pub mod palette;
+pub mod settings;
+pub mod shortcuts;
+pub mod status_bar;
 pub mod terminal;
 pub mod titlebar;
+pub mod zoom;
@@ -0,0 +1,457 @@
+use egui::text::{LayoutJob, TextFormat};
+use egui::{
+    vec2, Align, Align2, Area, Button, Color32, Frame, Key, Modifiers, Order, ScrollArea, TextEdit,
+    TextStyle, Ui,
+};
+
+use crate::config::{Action, Command, Config, PickerWindow, TabCommand};
+use crate::utils::cursor;
+use crate::utils::fuzzy;
+
+use super::dock::{Dock, Tab, TreeTabs};
+use super::settings::Settings;
+use super::terminal::Terminal;
+use super::titlebar::TITLEBAR_HEIGHT;
+use super::zoom::Zoom;
+
+// how tall the list gets before it scrolls
+const MAX_HEIGHT: f32 = 300.0;
+// how much of a tab's first line of code is shown next to its name
+const HINT_CHARS: usize = 40;
+
+type Run = Box<dyn Fn(&mut Config)>;
+
+/// A command the palette lists, with the action whose shortcut also runs it
+pub struct PaletteCommand {
+    pub name: String,
+    pub shortcut: Option<Action>,
+    run: Run,
+}
+
+/// Every command in the palette. Each widget registers its own commands, the palette only shows
+/// them
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<PaletteCommand>,
+}
+
+impl CommandRegistry {
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        shortcut: Option<Action>,
+        action: impl Fn(&mut Config) + 'static,
+    ) {
+        self.commands.push(PaletteCommand {
+            name: name.into(),
+            shortcut,
+            run: Box::new(action),
+        });
+    }
+
+    /// Register a command which acts on the focused tab, and does nothing without one
+    pub fn register_for_tab(
+        &mut self,
+        name: impl Into<String>,
+        shortcut: Option<Action>,
+        action: impl Fn(&mut Config, &Tab) + 'static,
+    ) {
+        self.register(name, shortcut, move |config| {
+            let Some(tab) = focused_tab(config) else {
+                return;
+            };
+
+            action(config, &tab);
+        });
+    }
+
+    pub fn commands(&self) -> &[PaletteCommand] {
+        &self.commands
+    }
+}
+
+// A copy of the focused tab, so the command can change the config while looking at it
+fn focused_tab(config: &mut Config) -> Option<Tab> {
+    config
+        .dock
+        .tree
+        .find_active_focused()
+        .map(|(_, tab)| tab.clone())
+}
+
+/// One entry in a picker's list
+pub struct PickerItem<'a> {
+    pub name: &'a str,
+    // dim text after the name, to tell apart items with the same name
+    pub hint: Option<String>,
+    // shown on the right, like the command's shortcut
+    pub shortcut: Option<String>,
+}
+
+// The overlay the pickers share: a text input over the items matching it, the best match first.
+// Arrows move the highlight and Enter picks it, Escape or clicking elsewhere closes the picker.
+// Returns the index of the picked item
+fn picker(
+    ctx: &egui::Context,
+    id: &'static str,
+    hint_text: &str,
+    state: &mut PickerWindow,
+    items: &[PickerItem],
+) -> Option<usize> {
+    let matches = items
+        .iter()
+        .map(|item| fuzzy::matched_chars(&state.query, item.name))
+        .collect::<Vec<_>>();
+    let order = fuzzy::filter(&state.query, items, |item| item.name);
+
+    // taken before the text input sees them, it'd move its cursor or lose focus on them
+    let (up, down, enter, escape) = {
+        let mut input = ctx.input_mut();
+        (
+            input.consume_key(Modifiers::NONE, Key::ArrowUp),
+            input.consume_key(Modifiers::NONE, Key::ArrowDown),
+            input.consume_key(Modifiers::NONE, Key::Enter),
+            input.consume_key(Modifiers::NONE, Key::Escape),
+        )
+    };
+
+    let count = order.len();
+    if count > 0 {
+        if up {
+            state.selected = (state.selected + count - 1) % count;
+        }
+        if down {
+            state.selected = (state.selected + 1) % count;
+        }
+    }
+    state.selected = state.selected.min(count.saturating_sub(1));
+
+    let mut picked = enter.then_some(state.selected);
+
+    let response = Area::new(id)
+        .order(Order::Foreground)
+        .anchor(Align2::CENTER_TOP, vec2(0.0, TITLEBAR_HEIGHT as f32))
+        .show(ctx, |ui| {
+            Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_width(400.0);
+
+                let input = ui.add(
+                    TextEdit::singleline(&mut state.query)
+                        .hint_text(hint_text)
+                        .desired_width(f32::INFINITY),
+                );
+                input.request_focus();
+                if input.changed() {
+                    state.selected = 0;
+                }
+
+                ui.separator();
+
+                ScrollArea::vertical()
+                    .max_height(MAX_HEIGHT)
+                    .show(ui, |ui| {
+                        if order.is_empty() {
+                            ui.weak("No matches");
+                        }
+
+                        for (index, item) in order.iter().enumerate() {
+                            let matched =
+                                matches[*item].as_ref().map_or(&[][..], |(_, chars)| chars);
+                            let selected = index == state.selected;
+                            let fill = if selected {
+                                ui.visuals().selection.bg_fill
+                            } else {
+                                Color32::TRANSPARENT
+                            };
+
+                            let item = &items[*item];
+                            let response = ui.add(
+                                Button::new(item_label(ui, item, matched))
+                                    .shortcut_text(item.shortcut.as_deref().unwrap_or_default())
+                                    .fill(fill)
+                                    .min_size(vec2(ui.available_width(), 0.0)),
+                            );
+
+                            if selected && (up || down) {
+                                response.scroll_to_me(Some(Align::Center));
+                            }
+                            if response.clicked() {
+                                picked = Some(index);
+                            }
+                        }
+                    });
+            });
+        })
+        .response;
+
+    if escape || response.clicked_elsewhere() {
+        state.open = false;
+        return None;
+    }
+
+    let picked = picked.and_then(|index| order.get(index).copied());
+    if picked.is_some() {
+        state.open = false;
+        // what was picked is handled by the dock on the next frame
+        ctx.request_repaint();
+    }
+
+    picked
+}
+
+// The item's name with the matched chars highlighted, then its hint
+fn item_label(ui: &Ui, item: &PickerItem, matched: &[usize]) -> LayoutJob {
+    let visuals = ui.visuals();
+    let font_id = TextStyle::Button.resolve(ui.style());
+    let format = |color| TextFormat::simple(font_id.clone(), color);
+
+    let mut job = LayoutJob::default();
+    for (index, char) in item.name.chars().enumerate() {
+        let color = if matched.contains(&index) {
+            visuals.selection.stroke.color
+        } else {
+            visuals.text_color()
+        };
+
+        job.append(char.encode_utf8(&mut [0; 4]), 0.0, format(color));
+    }
+
+    if let Some(hint) = &item.hint {
+        job.append(hint, 8.0, format(visuals.weak_text_color()));
+    }
+
+    job
+}
+
+// Whichever picker was opened, the other one closes
+fn toggle(palette: &mut PickerWindow, other: &mut PickerWindow) {
+    palette.open = !palette.open;
+    palette.selected = 0;
+    other.open = false;
+}
+
+pub struct CommandPalette;
+
+impl CommandPalette {
+    // everything the palette can run
+    fn registry() -> CommandRegistry {
+        let mut registry = CommandRegistry::default();
+
+        Dock::register_commands(&mut registry);
+        Terminal::register_commands(&mut registry);
+        Settings::register_commands(&mut registry);
+        Zoom::register_commands(&mut registry);
+
+        registry.register("Go to tab…", Some(Action::QuickOpen), |config| {
+            config.quick_open.open = true;
+            config.quick_open.selected = 0;
+        });
+        registry.register("Go to line…", Some(Action::GoToLine), GoToLine::toggle);
+
+        registry
+    }
+
+    pub fn toggle(config: &mut Config) {
+        toggle(&mut config.palette, &mut config.quick_open);
+    }
+
+    pub fn show(ctx: &egui::Context, config: &mut Config) {
+        if !config.palette.open {
+            return;
+        }
+
+        let registry = Self::registry();
+        let commands = registry.commands();
+        let items = commands
+            .iter()
+            .map(|command| PickerItem {
+                name: &command.name,
+                hint: None,
+                shortcut: command
+                    .shortcut
+                    .map(|action| config.keybindings.combo(action).to_string()),
+            })
+            .collect::<Vec<_>>();
+
+        let picked = picker(
+            ctx,
+            "command_palette",
+            "Type a command",
+            &mut config.palette,
+            &items,
+        );
+
+        if let Some(command) = picked {
+            (commands[command].run)(config);
+        }
+    }
+}
+
+/// For going to an open tab or reopening a closed one by its name
+pub struct QuickOpen;
+
+impl QuickOpen {
+    pub fn toggle(config: &mut Config) {
+        toggle(&mut config.quick_open, &mut config.palette);
+    }
+
+    pub fn show(ctx: &egui::Context, config: &mut Config) {
+        if !config.quick_open.open {
+            return;
+        }
+
+        // the open tabs by when they were last shown, then the closed ones by when they were closed
+        let dock = &config.dock;
+        let mut open = dock
+            .recent_tabs
+            .iter()
+            .filter_map(|id| dock.tree.tabs().find(|tab| tab.id == *id))
+            .collect::<Vec<_>>();
+        open.extend(
+            dock.tree
+                .tabs()
+                .filter(|tab| !dock.recent_tabs.contains(&tab.id)),
+        );
+        let tabs = open
+            .into_iter()
+            .map(|tab| (tab, false))
+            .chain(dock.closed_tabs.iter().map(|tab| (tab, true)))
+            .collect::<Vec<_>>();
+
+        let items = tabs
+            .iter()
+            .map(|(tab, closed)| {
+                let line = first_line(&tab.editor.code);
+
+                PickerItem {
+                    name: &tab.name,
+                    hint: match (closed, line) {
+                        (true, Some(line)) => Some(format!("closed · {line}")),
+                        (true, None) => Some("closed".to_string()),
+                        (false, line) => line.map(str::to_string),
+                    },
+                    shortcut: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let picked = picker(
+            ctx,
+            "quick_open",
+            "Go to a tab",
+            &mut config.quick_open,
+            &items,
+        )
+        .map(|index| (tabs[index].0.id, tabs[index].1));
+
+        let Some((id, closed)) = picked else {
+            return;
+        };
+
+        if closed {
+            config
+                .dock
+                .commands
+                .push(Command::TabCommand(TabCommand::Reopen(Some(id))));
+        } else if let Some((node, tab_index)) = config.dock.tree.find_tab_location(id) {
+            config.dock.tree.set_focused_node(node);
+            config.dock.tree.set_active_tab(node, tab_index);
+        }
+    }
+}
+
+/// For moving the focused tab's cursor to a line, typed as `line` or `line:column`
+pub struct GoToLine;
+
+impl GoToLine {
+    pub fn toggle(config: &mut Config) {
+        let window = &mut config.go_to_line;
+        window.open = !window.open;
+        window.query.clear();
+        window.invalid = false;
+    }
+
+    pub fn show(ctx: &egui::Context, config: &mut Config) {
+        if !config.go_to_line.open {
+            return;
+        }
+
+        // there's nothing to go to a line in without a tab
+        let Some(tab) = focused_tab(config) else {
+            config.go_to_line.open = false;
+            return;
+        };
+
+        let window = &mut config.go_to_line;
+        let (enter, escape) = {
+            let mut input = ctx.input_mut();
+            (
+                input.consume_key(Modifiers::NONE, Key::Enter),
+                input.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        };
+
+        let response = Area::new("go_to_line")
+            .order(Order::Foreground)
+            .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(200.0);
+
+                    let input = ui.add(
+                        TextEdit::singleline(&mut window.query)
+                            .hint_text("line or line:column")
+                            .desired_width(f32::INFINITY),
+                    );
+                    input.request_focus();
+                    if input.changed() {
+                        window.invalid = false;
+                    }
+
+                    if window.invalid {
+                        ui.colored_label(ui.visuals().error_fg_color, "Not a line number");
+                    }
+                });
+            })
+            .response;
+
+        if escape || response.clicked_elsewhere() {
+            window.open = false;
+            return;
+        }
+
+        if !enter {
+            return;
+        }
+
+        let Some((line, col)) = cursor::parse_location(&window.query) else {
+            window.invalid = true;
+            return;
+        };
+
+        window.open = false;
+        config
+            .dock
+            .commands
+            .push(Command::TabCommand(TabCommand::GoToLine {
+                tab: tab.id,
+                line,
+                col: col.unwrap_or(1),
+            }));
+        // the cursor is moved by the dock on the next frame
+        ctx.request_repaint();
+    }
+}
+
+// The first line of code which isn't a comment, cut short to fit next to the name
+fn first_line(code: &str) -> Option<&str> {
+    let line = code
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("//"))?;
+
+    Some(match line.char_indices().nth(HINT_CHARS) {
+        Some((end, _)) => &line[..end],
+        None => line,
+    })
+}
@@ -0,0 +1,831 @@
+use std::sync::mpsc::{channel, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use egui::color_picker::color_edit_button_srgb;
+use egui::{vec2, Button, DragValue, FontId, Id, RichText, TextEdit};
+
+use crate::config::{
+    Action, AnsiColors, AutoRun, Config, Editor, GitHub, GitHubError, Keybindings, Logging, Rgb,
+    Runner, SettingsWindow, ShowWhitespace, SignInEvent, StatusBar, ThemeConfig, ThemeMode,
+    CLIENT_ID, DEFAULT_IDLE_MS, DEFAULT_TIMEOUT_SECS,
+};
+use crate::utils::log_filter::LogLevel;
+use crate::utils::{build_cache, color_scheme, logs};
+
+use super::code_editor::{self, CodeTheme};
+use super::palette::CommandRegistry;
+use super::terminal::layout_ansi;
+#[cfg(target_os = "windows")]
+use crate::os::windows::theme;
+
+// how often the os is asked whether it's in light mode, while the theme follows it
+const SYSTEM_THEME_INTERVAL: Duration = Duration::from_secs(3);
+
+// something of everything rust is highlighted with
+const SYNTAX_PREVIEW: &str = r#"/// Says hi
+#[derive(Debug)]
+struct Greeting<'a>(&'a str);
+
+fn main() {
+    // the usual
+    let hi = Greeting("hi");
+    println!("{:?} {} {}", hi, 'x', 1.5);
+}"#;
+
+// shows off every color, and what cargo's output looks like
+const PREVIEW_TEXT: &str = "\
+\x1b[30mblack\x1b[0m \x1b[31mred\x1b[0m \x1b[32mgreen\x1b[0m \x1b[33myellow\x1b[0m \
+\x1b[34mblue\x1b[0m \x1b[35mmagenta\x1b[0m \x1b[36mcyan\x1b[0m \x1b[37mwhite\x1b[0m
+\x1b[90mblack\x1b[0m \x1b[91mred\x1b[0m \x1b[92mgreen\x1b[0m \x1b[93myellow\x1b[0m \
+\x1b[94mblue\x1b[0m \x1b[95mmagenta\x1b[0m \x1b[96mcyan\x1b[0m \x1b[97mwhite\x1b[0m
+\x1b[1m\x1b[32m   Compiling\x1b[0m playground v0.0.1
+\x1b[1m\x1b[33mwarning\x1b[0m\x1b[1m: unused variable: `x`\x1b[0m
+\x1b[1m\x1b[31merror[E0308]\x1b[0m\x1b[1m: mismatched types\x1b[0m
+\x1b[1m\x1b[34m --> \x1b[0msrc/main.rs:2:18
+Hello, world!";
+
+// A color button for each of the colors, in their ansi order with the bright ones on the second row
+fn palette_editor(ui: &mut egui::Ui, theme: &mut ThemeConfig) {
+    let mut colors = theme.palette().to_array();
+    let mut changed = false;
+
+    for (row, names) in colors.chunks_mut(8).zip(AnsiColors::NAMES.chunks(8)) {
+        ui.horizontal(|ui| {
+            for (color, name) in row.iter_mut().zip(names) {
+                let Rgb(r, g, b) = *color;
+                let mut srgb = [r, g, b];
+
+                let response = color_edit_button_srgb(ui, &mut srgb)
+                    .on_hover_text(format!("{name} #{r:02x}{g:02x}{b:02x}"));
+
+                if response.changed() {
+                    let [r, g, b] = srgb;
+                    *color = Rgb(r, g, b);
+                    changed = true;
+                }
+            }
+        });
+    }
+
+    if changed {
+        theme.set_palette(AnsiColors::from_array(colors));
+    }
+}
+
+// The rust highlighting colors, with a bit of code to try them on
+fn syntax_editor(ui: &mut egui::Ui, theme: &mut ThemeConfig) {
+    let mut colors = *theme.syntax();
+
+    ui.horizontal_wrapped(|ui| {
+        for (name, color) in colors.named_mut() {
+            let Rgb(r, g, b) = *color;
+            let mut srgb = [r, g, b];
+
+            if color_edit_button_srgb(ui, &mut srgb)
+                .on_hover_text(format!("{name} #{r:02x}{g:02x}{b:02x}"))
+                .changed()
+            {
+                let [r, g, b] = srgb;
+                *color = Rgb(r, g, b);
+            }
+        }
+    });
+    theme.set_syntax(colors);
+
+    ui.add_space(4.0);
+    let code_theme = CodeTheme::from_memory(ui.ctx());
+    let job = code_editor::highlight(ui.ctx(), &code_theme, &colors, SYNTAX_PREVIEW, "rs");
+    egui::Frame::none()
+        .fill(ui.visuals().extreme_bg_color)
+        .inner_margin(6.0)
+        .rounding(2.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(job);
+        });
+}
+
+// A color which falls back to the ui's color when it isn't set
+fn optional_color(ui: &mut egui::Ui, label: &str, color: &mut Option<Rgb>, fallback: Rgb) {
+    ui.horizontal(|ui| {
+        let mut custom = color.is_some();
+        if ui.checkbox(&mut custom, label).changed() {
+            *color = custom.then_some(fallback);
+        }
+
+        if let Some(Rgb(r, g, b)) = color {
+            let mut srgb = [*r, *g, *b];
+            if color_edit_button_srgb(ui, &mut srgb).changed() {
+                let [r, g, b] = srgb;
+                *color = Some(Rgb(r, g, b));
+            }
+        } else {
+            ui.weak("same as the ui");
+        }
+    });
+}
+
+// Check whether the import dialog picked a file, and load it into the edited theme if it did
+fn poll_import(ctx: &egui::Context, settings: &mut SettingsWindow) {
+    let Some(dialog) = &settings.import_dialog else {
+        return;
+    };
+
+    let path = match dialog.try_recv() {
+        Ok(Some(path)) => path,
+        // dialog is still open, keep waiting
+        Err(TryRecvError::Empty) => return,
+        // user cancelled
+        Ok(None) | Err(TryRecvError::Disconnected) => {
+            settings.import_dialog = None;
+            return;
+        }
+    };
+
+    settings.import_dialog = None;
+
+    let Some(theme) = &mut settings.theme else {
+        return;
+    };
+
+    match color_scheme::import_scheme(&path, theme.palette()) {
+        Ok(scheme) => {
+            let name = path
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            theme.set_palette(scheme.colors);
+            settings.loaded_scheme = Some((name, scheme.missing));
+            settings.import_error = None;
+        }
+        Err(e) => settings.import_error = Some(e.to_string()),
+    }
+
+    ctx.request_repaint();
+}
+
+fn whitespace_settings(ui: &mut egui::Ui, editor: &mut Editor) {
+    ui.horizontal(|ui| {
+        ui.label("Show whitespace");
+        egui::ComboBox::from_id_source("show_whitespace")
+            .selected_text(editor.show_whitespace.name())
+            .show_ui(ui, |ui| {
+                for show in ShowWhitespace::ALL {
+                    ui.selectable_value(&mut editor.show_whitespace, show, show.name());
+                }
+            });
+    });
+
+    ui.checkbox(&mut editor.indent_guides, "Indentation guides");
+    ui.checkbox(&mut editor.minimap, "Minimap");
+    ui.checkbox(&mut editor.inlay_hints, "Type hints")
+        .on_hover_text(
+            "Best-effort types after `let` bindings, guessed from their literals and constructors",
+        );
+    ui.checkbox(&mut editor.vim_mode, "Vim mode");
+    ui.checkbox(
+        &mut editor.trim_trailing_whitespace_on_save,
+        "Trim trailing whitespace on save",
+    );
+    ui.checkbox(
+        &mut editor.ensure_final_newline_on_save,
+        "End files with a newline on save",
+    );
+    if let Some(error) = &editor.snippets_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
+
+fn status_bar_settings(ui: &mut egui::Ui, status_bar: &mut StatusBar) {
+    ui.horizontal(|ui| {
+        ui.label("Status bar");
+        ui.checkbox(&mut status_bar.position, "Line:column");
+        ui.checkbox(&mut status_bar.selection, "Selection");
+        ui.checkbox(&mut status_bar.indent, "Indentation");
+        ui.checkbox(&mut status_bar.run_config, "Edition");
+    });
+}
+
+fn logging_settings(ui: &mut egui::Ui, logging: &mut Logging) {
+    let level = logging.level;
+    ui.horizontal(|ui| {
+        ui.label("Log level");
+        egui::ComboBox::from_id_source("log_level")
+            .selected_text(logging.level.to_string())
+            .show_ui(ui, |ui| {
+                for level in LogLevel::ALL {
+                    ui.selectable_value(&mut logging.level, level, level.to_string());
+                }
+            });
+    })
+    .response
+    .on_hover_text("What the logs view and the log file record of the app's own events");
+
+    if logging.level != level {
+        logs::set_level(logging.level);
+    }
+}
+
+fn color_settings(ui: &mut egui::Ui, config: &mut Config) {
+    let settings = &mut config.settings;
+    let theme = settings.theme.get_or_insert_with(|| config.theme.clone());
+
+    // the mode is switched right away, the colors edited below are the ones it uses
+    ui.horizontal(|ui| {
+        ui.label("Theme");
+        egui::ComboBox::from_id_source("theme_mode")
+            .selected_text(config.theme.mode.name())
+            .show_ui(ui, |ui| {
+                for mode in ThemeMode::ALL {
+                    if ui
+                        .selectable_value(&mut config.theme.mode, mode, mode.name())
+                        .clicked()
+                    {
+                        theme.mode = mode;
+                    }
+                }
+            });
+    });
+
+    ui.label("Terminal");
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source("color_scheme_presets")
+            .selected_text("Presets")
+            .show_ui(ui, |ui| {
+                for (name, colors) in AnsiColors::presets() {
+                    if ui.selectable_label(false, name).clicked() {
+                        theme.set_palette(colors);
+                        settings.loaded_scheme = Some((name.to_string(), vec![]));
+                        settings.import_error = None;
+                    }
+                }
+            });
+
+        let import = ui
+            .add_enabled(settings.import_dialog.is_none(), Button::new("Import…"))
+            .on_hover_text("Read an iTerm2, Windows Terminal or base16 color scheme");
+
+        if import.clicked() {
+            let (tx, rx) = channel();
+            let ctx = ui.ctx().clone();
+
+            // the dialog blocks, so keep it off the ui thread
+            thread::spawn(move || {
+                let path = rfd::FileDialog::new()
+                    .add_filter("Color schemes", &["itermcolors", "json", "yaml", "yml"])
+                    .pick_file();
+
+                let _ = tx.send(path);
+                ctx.request_repaint();
+            });
+
+            settings.import_dialog = Some(rx);
+        }
+    });
+
+    if let Some(error) = &settings.import_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    if let Some((name, missing)) = &settings.loaded_scheme {
+        if missing.is_empty() {
+            ui.weak(format!("Loaded {name}"));
+        } else {
+            ui.weak(format!(
+                "Loaded {name}. It's missing {}, so those colors were kept",
+                missing.join(", ")
+            ));
+        }
+    }
+
+    ui.add_space(4.0);
+    palette_editor(ui, theme);
+
+    ui.add_space(4.0);
+    let visuals = ui.visuals().clone();
+    let to_rgb = |color: egui::Color32| Rgb(color.r(), color.g(), color.b());
+    optional_color(
+        ui,
+        "Text",
+        &mut theme.text_color,
+        to_rgb(visuals.text_color()),
+    );
+    optional_color(
+        ui,
+        "Background",
+        &mut theme.background,
+        to_rgb(visuals.panel_fill),
+    );
+
+    //
+    // Preview
+    //
+
+    ui.add_space(4.0);
+    let job = layout_ansi(theme, &visuals, FontId::monospace(12.0), PREVIEW_TEXT);
+    egui::Frame::none()
+        .fill(theme.background(&visuals))
+        .inner_margin(6.0)
+        .rounding(2.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.label(job);
+        });
+
+    ui.add_space(8.0);
+    ui.label("Syntax");
+    syntax_editor(ui, theme);
+
+    let changed = *theme != config.theme;
+
+    ui.add_space(4.0);
+    ui.horizontal(|ui| {
+        if ui.add_enabled(changed, Button::new("Apply")).clicked() {
+            // the terminal's sections remember the colors they were made with, so they're remade with
+            // the new ones on the next frame
+            config.theme = theme.clone();
+            ui.ctx().request_repaint();
+        }
+
+        if ui.button("Reset to default").clicked() {
+            theme.reset_colors();
+            settings.loaded_scheme = None;
+        }
+
+        if ui.add_enabled(changed, Button::new("Revert")).clicked() {
+            *theme = config.theme.clone();
+            settings.loaded_scheme = None;
+        }
+    });
+}
+
+// Deleting the cached projects, which makes the next run of every tab build from scratch
+fn build_cache_settings(ui: &mut egui::Ui, settings: &mut SettingsWindow) {
+    if let Some(rx) = &settings.cleaning {
+        match rx.try_recv() {
+            Ok(result) => {
+                settings.clean_result = Some(result.map_err(|e| e.to_string()));
+                settings.cleaning = None;
+            }
+            Err(TryRecvError::Empty) => (),
+            Err(TryRecvError::Disconnected) => settings.cleaning = None,
+        }
+    }
+
+    let cache_dir = build_cache::cache_dir();
+    ui.weak(cache_dir.display().to_string());
+
+    // the running programs are built in there
+    let running = ui
+        .ctx()
+        .memory()
+        .data
+        .get_temp::<u64>(Id::new("continuous_mode"))
+        .unwrap_or_default()
+        > 0;
+
+    ui.horizontal(|ui| {
+        let clean = ui
+            .add_enabled(
+                !running && settings.cleaning.is_none(),
+                Button::new("Clean build artifacts"),
+            )
+            .on_hover_text("Delete the cached builds. The next run of each tab builds from scratch")
+            .on_disabled_hover_text("Stop the running programs first");
+
+        if clean.clicked() {
+            let (tx, rx) = channel();
+            let ctx = ui.ctx().clone();
+
+            // big target directories take a while to delete
+            thread::spawn(move || {
+                let _ = tx.send(build_cache::clean(&cache_dir));
+                ctx.request_repaint();
+            });
+
+            settings.cleaning = Some(rx);
+            settings.clean_result = None;
+        }
+
+        if settings.cleaning.is_some() {
+            ui.spinner();
+        }
+    });
+
+    match &settings.clean_result {
+        Some(Ok(freed)) => {
+            ui.weak(format!("Freed {}", build_cache::format_size(*freed)));
+        }
+        Some(Err(e)) => {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+        None => (),
+    }
+}
+
+// The time limit runs get unless their tab says otherwise
+fn timeout_settings(ui: &mut egui::Ui, runner: &mut Runner) {
+    ui.horizontal(|ui| {
+        let mut enabled = runner.timeout_secs.is_some();
+
+        if ui
+            .checkbox(&mut enabled, "Stop programs after")
+            .on_hover_text("Stop runs which are still going after this long, like an accidental infinite loop. Building doesn't count")
+            .changed()
+        {
+            runner.timeout_secs = enabled.then_some(DEFAULT_TIMEOUT_SECS);
+        }
+
+        if let Some(secs) = &mut runner.timeout_secs {
+            ui.add(DragValue::new(secs).clamp_range(1..=86400).suffix("s"));
+        }
+    });
+}
+
+// Running the scratch without pressing Play
+fn auto_run_settings(ui: &mut egui::Ui, runner: &mut Runner) {
+    ui.label("Auto-run");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut runner.auto_run, AutoRun::Off, "Off");
+        ui.radio_value(&mut runner.auto_run, AutoRun::OnSave, "On save");
+
+        let idle = ui
+            .radio(
+                matches!(runner.auto_run, AutoRun::OnIdle(_)),
+                "When typing stops",
+            )
+            .on_hover_text("Run the scratch once the code has stayed the same for a while");
+        if idle.clicked() && !matches!(runner.auto_run, AutoRun::OnIdle(_)) {
+            runner.auto_run = AutoRun::OnIdle(DEFAULT_IDLE_MS);
+        }
+
+        if let AutoRun::OnIdle(delay) = &mut runner.auto_run {
+            ui.add(
+                DragValue::new(delay)
+                    .clamp_range(100..=60000)
+                    .speed(10)
+                    .suffix("ms"),
+            );
+        }
+    });
+}
+
+// Each action's shortcut. Clicking one records the next keys pressed as its new shortcut
+fn keybinding_settings(ui: &mut egui::Ui, config: &mut Config) {
+    let settings = &mut config.settings;
+    let keybindings = &mut config.keybindings;
+
+    egui::Grid::new("keybindings")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            for action in Action::ALL {
+                ui.label(action.name());
+
+                let combo = keybindings.combo(action);
+                let recording = settings.recording == Some(action);
+                let text = if recording {
+                    "Press a shortcut…".to_string()
+                } else {
+                    combo.to_string()
+                };
+
+                let response = ui
+                    .add(Button::new(text).min_size(vec2(120.0, 0.0)))
+                    .on_hover_text("Click, then press the new shortcut. Escape cancels");
+                if response.clicked() {
+                    settings.recording = (!recording).then_some(action);
+                    settings.recording_error = None;
+                }
+
+                ui.horizontal(|ui| {
+                    let default = combo == action.default_combo();
+                    if ui.add_enabled(!default, Button::new("Reset")).clicked() {
+                        keybindings.0.insert(action, action.default_combo());
+                    }
+
+                    let conflicts = keybindings.conflicts(action);
+                    if !conflicts.is_empty() {
+                        let names = conflicts
+                            .iter()
+                            .map(|other| other.name())
+                            .collect::<Vec<_>>();
+
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("⚠ Also bound to {}", names.join(", ")),
+                        );
+                    }
+                });
+
+                ui.end_row();
+            }
+        });
+
+    if let Some(error) = settings.recording_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    ui.add_space(4.0);
+    if ui.button("Reset all to default").clicked() {
+        *keybindings = Keybindings::default();
+        settings.recording = None;
+    }
+}
+
+// Check on the github sign-in, and keep its token once it's done
+fn poll_sign_in(ctx: &egui::Context, config: &mut Config) {
+    let settings = &mut config.settings;
+    let Some(sign_in) = &mut settings.sign_in else {
+        return;
+    };
+
+    let event = loop {
+        match sign_in.events.try_recv() {
+            Ok(SignInEvent::Code(code)) => sign_in.code = Some(code),
+            Ok(event) => break event,
+            Err(TryRecvError::Empty) => {
+                // nothing wakes up the ui when github answers, so keep polling
+                ctx.request_repaint_after(Duration::from_millis(500));
+                return;
+            }
+            Err(TryRecvError::Disconnected) => break SignInEvent::Failed(GitHubError::Unknown),
+        }
+    };
+
+    settings.sign_in = None;
+    match event {
+        SignInEvent::SignedIn { token, username } => config.github.store_token(token, username),
+        SignInEvent::Failed(e) => settings.sign_in_error = Some(e.to_string()),
+        SignInEvent::Code(_) => (),
+    }
+}
+
+// Signing in to github for sharing gists, or pasting a token instead
+fn github_settings(ui: &mut egui::Ui, config: &mut Config) {
+    let settings = &mut config.settings;
+    let github = &mut config.github;
+
+    if let Some(sign_in) = &settings.sign_in {
+        if let Some(code) = &sign_in.code {
+            ui.label("Enter this code on GitHub to sign in");
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(&code.user_code)
+                        .monospace()
+                        .size(18.0)
+                        .strong(),
+                );
+                if ui.button("📋 Copy").clicked() {
+                    ui.output().copied_text = code.user_code.clone();
+                }
+            });
+            ui.hyperlink_to(
+                format!("Open {}", code.verification_uri),
+                &code.verification_uri,
+            );
+        }
+
+        let mut cancel = false;
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Waiting for GitHub…");
+            cancel = ui.button("Cancel").clicked();
+        });
+
+        if cancel {
+            settings.sign_in = None;
+        }
+        return;
+    }
+
+    if github.expired {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            "GitHub turned down the sign-in, it expired or was revoked. Sign in again",
+        );
+    }
+
+    if github.signed_in() && !github.expired {
+        ui.horizontal(|ui| {
+            match &github.username {
+                Some(username) => ui.label(format!("Signed in as {username}")),
+                None => ui.label("Using a personal access token"),
+            };
+
+            if ui.button("Sign out").clicked() {
+                github.sign_out();
+            }
+        });
+    } else {
+        let sign_in = ui
+            .add_enabled(CLIENT_ID.is_some(), Button::new("Sign in to GitHub"))
+            .on_hover_text("Sign in on github.com, for sharing scratches as gists")
+            .on_disabled_hover_text(GitHubError::NoClientId.to_string());
+
+        if sign_in.clicked() {
+            github.sign_out();
+            settings.sign_in = Some(GitHub::sign_in());
+            settings.sign_in_error = None;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Or paste a token");
+            ui.add(
+                TextEdit::singleline(&mut settings.pasted_token)
+                    .password(true)
+                    .hint_text("with the gist scope"),
+            );
+
+            let token = settings.pasted_token.trim();
+            if ui
+                .add_enabled(!token.is_empty(), Button::new("Use"))
+                .clicked()
+            {
+                github.sign_out();
+                github.access_token = token.to_string();
+                settings.pasted_token.clear();
+            }
+        });
+    }
+
+    if let Some(error) = &settings.sign_in_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
+
+pub struct Settings;
+
+impl Settings {
+    /// Switch the ui between dark and light when the theme's mode, or the os's, changed. The
+    /// os is asked again every few seconds while the theme follows it
+    pub fn apply_theme(ctx: &egui::Context, config: &mut Config) {
+        let asked = config
+            .system_light
+            .filter(|(_, at)| at.elapsed() < SYSTEM_THEME_INTERVAL);
+        let system_light = match asked {
+            Some((light, _)) => light,
+            None if config.theme.mode == ThemeMode::System => {
+                #[cfg(target_os = "windows")]
+                let light = theme::apps_use_light_theme().unwrap_or_default();
+                #[cfg(not(target_os = "windows"))]
+                let light = false;
+
+                config.system_light = Some((light, Instant::now()));
+                light
+            }
+            None => false,
+        };
+        if config.theme.mode == ThemeMode::System {
+            ctx.request_repaint_after(SYSTEM_THEME_INTERVAL);
+        }
+
+        let light = config.theme.is_light(system_light);
+        if light == config.theme.light && ctx.style().visuals.dark_mode != light {
+            return;
+        }
+
+        // the terminal's sections and the highlighting remember the colors they were made with,
+        // so the output and code are colored again with the new ones
+        config.theme.light = light;
+        if let Some(theme) = &mut config.settings.theme {
+            theme.light = light;
+        }
+        ctx.set_visuals(config.theme.visuals());
+    }
+
+    /// The palette's commands for the settings window
+    pub fn register_commands(registry: &mut CommandRegistry) {
+        registry.register("Open settings", None, |config| {
+            config.settings.open = true;
+        });
+    }
+
+    pub fn show(ctx: &egui::Context, config: &mut Config) {
+        poll_import(ctx, &mut config.settings);
+        poll_sign_in(ctx, config);
+
+        let mut open = config.settings.open;
+
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.heading("Colors");
+                color_settings(ui, config);
+
+                ui.separator();
+
+                ui.heading("Editor");
+                ui.checkbox(&mut config.editor.format_on_save, "Format on save")
+                    .on_hover_text("Run rustfmt on a scratch before saving it");
+                ui.checkbox(
+                    &mut config.editor.auto_close_brackets,
+                    "Auto-close brackets and quotes",
+                );
+                ui.checkbox(&mut config.editor.completion, "Complete identifiers")
+                    .on_hover_text("List the names in the scratch while one is being typed");
+                ui.horizontal(|ui| {
+                    let lsp = &mut config.editor.lsp;
+
+                    // turning it back on or running something else tries starting it again
+                    let toggled = ui
+                        .checkbox(&mut lsp.enabled, "rust-analyzer")
+                        .on_hover_text(
+                            "Completions, types and docs on Ctrl+hover, F12 goes to definitions",
+                        )
+                        .changed();
+                    let program = ui.add_enabled(
+                        lsp.enabled,
+                        TextEdit::singleline(&mut lsp.program).desired_width(150.0),
+                    );
+                    if toggled || program.changed() {
+                        lsp.error = None;
+                        config.dock.lsp_clients.clear();
+                    }
+                });
+                if let Some(error) = &config.editor.lsp.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Autosave drafts every");
+                    ui.add(
+                        DragValue::new(&mut config.editor.autosave_interval_secs)
+                            .clamp_range(1..=600)
+                            .suffix(" s"),
+                    );
+                })
+                .response
+                .on_hover_text("Unsaved tabs are written to drafts, so a crash doesn't lose them");
+                ui.horizontal(|ui| {
+                    let editor = &mut config.editor;
+
+                    ui.label("Indent width");
+                    ui.add_enabled(
+                        !editor.indent_with_tabs,
+                        DragValue::new(&mut editor.indent_width).clamp_range(1..=8),
+                    );
+                    ui.checkbox(&mut editor.indent_with_tabs, "Indent with tabs");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Undo steps");
+                    ui.add(DragValue::new(&mut config.editor.undo_limit).clamp_range(10..=10_000));
+                });
+                whitespace_settings(ui, &mut config.editor);
+                status_bar_settings(ui, &mut config.status_bar);
+
+                ui.separator();
+
+                ui.heading("Runner");
+                timeout_settings(ui, &mut config.runner);
+                auto_run_settings(ui, &mut config.runner);
+
+                ui.separator();
+
+                ui.heading("Build cache");
+                build_cache_settings(ui, &mut config.settings);
+
+                ui.separator();
+
+                ui.heading("GitHub");
+                github_settings(ui, config);
+
+                ui.separator();
+
+                ui.heading("Startup");
+                ui.checkbox(
+                    &mut config.ui.single_instance,
+                    "Open files in the running window",
+                )
+                .on_hover_text(
+                    "Files opened while Rust Play is running go to new tabs instead of \
+                         another window. Takes effect the next time it starts",
+                );
+
+                ui.separator();
+
+                ui.heading("Keybindings");
+                keybinding_settings(ui, config);
+
+                ui.separator();
+
+                ui.heading("Logging");
+                logging_settings(ui, &mut config.logging);
+            });
+
+        // edits which weren't applied are dropped once the window closes
+        if !open {
+            config.settings.theme = None;
+            config.settings.loaded_scheme = None;
+            config.settings.recording = None;
+            config.settings.recording_error = None;
+            config.settings.sign_in = None;
+            config.settings.pasted_token.clear();
+        }
+
+        config.settings.open = open;
+    }
+}
@@ -0,0 +1,213 @@
+use egui::{Event, Key, Modifiers};
+
+use crate::config::{Action, Command, Config, LineEdit, MenuCommand, TabCommand, Unsaved};
+use crate::utils::key_combo::{self, ComboKey, KeyCombo};
+
+use super::dock::Dock;
+use super::palette::{CommandPalette, GoToLine, QuickOpen};
+#[cfg(target_os = "windows")]
+use crate::os::windows::keyboard;
+
+pub struct Shortcuts;
+
+impl Shortcuts {
+    /// Run the actions whose shortcuts were pressed, or bind the pressed keys to the action being
+    /// recorded. It goes before the widgets, so they don't also take the keys as typing
+    pub fn handle(ctx: &egui::Context, config: &mut Config) {
+        let os_key = Self::os_key_pressed(ctx, config);
+
+        if config.settings.recording.is_some() {
+            Self::record(ctx, config, os_key);
+            return;
+        }
+
+        // the terminal's input or a find bar keeps the keys of the shortcuts editing the code
+        let editor = config
+            .dock
+            .tree
+            .find_active_focused()
+            .map(|(_, tab)| tab.editor_id(tab.open_file));
+        let editor_takes_keys = key_combo::editor_takes_keys(ctx, editor);
+
+        for action in Action::ALL {
+            if action.acts_on_editor() && !editor_takes_keys {
+                continue;
+            }
+
+            let combo = config.keybindings.combo(action);
+            let pressed = match combo.key {
+                ComboKey::Key(key) => ctx.input_mut().consume_key(combo.modifiers(), key),
+                ComboKey::Backtick | ComboKey::Slash => os_key == Some(combo),
+            };
+
+            if pressed {
+                Self::run(ctx, config, action);
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    // The combo ` or / was pressed with this frame. winit doesn't send them to egui, so whether
+    // they're down is asked for from the os
+    #[allow(unused_variables)]
+    fn os_key_pressed(ctx: &egui::Context, config: &mut Config) -> Option<KeyCombo> {
+        #[cfg(target_os = "windows")]
+        {
+            let mut pressed = None;
+
+            for (key, down) in [
+                (ComboKey::Backtick, keyboard::is_backtick_down()),
+                (ComboKey::Slash, keyboard::is_slash_down()),
+            ] {
+                if !down {
+                    config.os_keys_down.remove(&key);
+                } else if config.os_keys_down.insert(key) {
+                    pressed = Some(KeyCombo::pressed(ctx.input().modifiers, key));
+                }
+            }
+
+            if pressed.is_some() {
+                return pressed;
+            }
+        }
+
+        None
+    }
+
+    // Bind the first key pressed to the recorded action, Escape cancels
+    fn record(ctx: &egui::Context, config: &mut Config, os_key: Option<KeyCombo>) {
+        let mut input = ctx.input_mut();
+
+        let pressed = input
+            .events
+            .iter()
+            .position(|event| matches!(event, Event::Key { pressed: true, .. }));
+        let combo = match pressed.map(|index| input.events.remove(index)) {
+            Some(Event::Key {
+                key: Key::Escape,
+                modifiers: Modifiers::NONE,
+                ..
+            }) => {
+                config.settings.recording = None;
+                config.settings.recording_error = None;
+                return;
+            }
+            Some(Event::Key { key, modifiers, .. }) => {
+                KeyCombo::pressed(modifiers, ComboKey::Key(key))
+            }
+            _ => match os_key {
+                Some(combo) => combo,
+                None => return,
+            },
+        };
+
+        let settings = &mut config.settings;
+        if combo.types_text() {
+            settings.recording_error = Some("Add Ctrl or Alt, otherwise the key would be typed");
+            return;
+        }
+
+        if let Some(action) = settings.recording.take() {
+            config.keybindings.0.insert(action, combo);
+        }
+        settings.recording_error = None;
+    }
+
+    fn run(ctx: &egui::Context, config: &mut Config, action: Action) {
+        let focused = config
+            .dock
+            .tree
+            .find_active_focused()
+            .map(|(_, tab)| (tab.id, tab.editor.read_only));
+        let commands = &mut config.dock.commands;
+
+        match action {
+            // generated code isn't rust, so it can't be run or formatted
+            Action::Run => {
+                if let Some((id, false)) = focused {
+                    commands.push(Command::TabCommand(TabCommand::Play(id)));
+                }
+            }
+            Action::Format => {
+                if let Some((id, false)) = focused {
+                    commands.push(Command::MenuCommand(MenuCommand::Format(id)));
+                }
+            }
+            Action::ToggleComment => {
+                if let Some((id, false)) = focused {
+                    commands.push(Command::MenuCommand(MenuCommand::ToggleComment(id)));
+                }
+            }
+            Action::DuplicateLines
+            | Action::MoveLinesUp
+            | Action::MoveLinesDown
+            | Action::DeleteLines => {
+                let edit = match action {
+                    Action::DuplicateLines => LineEdit::Duplicate,
+                    Action::MoveLinesUp => LineEdit::MoveUp,
+                    Action::MoveLinesDown => LineEdit::MoveDown,
+                    _ => LineEdit::Delete,
+                };
+                if let Some((id, false)) = focused {
+                    commands.push(Command::MenuCommand(MenuCommand::EditLines(id, edit)));
+                }
+            }
+            Action::JumpToBracket => {
+                if let Some((id, _)) = focused {
+                    commands.push(Command::MenuCommand(MenuCommand::JumpToBracket(id)));
+                }
+            }
+            Action::GoToDefinition => {
+                if let Some((id, false)) = focused {
+                    commands.push(Command::MenuCommand(MenuCommand::GoToDefinition(id)));
+                }
+            }
+            Action::SelectNextOccurrence => {
+                if let Some((id, _)) = focused {
+                    let command = MenuCommand::SelectNextOccurrence(id);
+                    commands.push(Command::MenuCommand(command));
+                }
+            }
+            // the terminal has its own find bar, for when the pointer is over it
+            Action::Find if config.terminal.open && config.terminal.hovered => {
+                config.terminal.search.open = true;
+                config.terminal.search.focus = true;
+            }
+            Action::Find | Action::Replace => {
+                if let Some((_, tab)) = config.dock.tree.find_active_focused() {
+                    let search = &mut tab.search;
+                    search.open = true;
+                    search.focus = true;
+                    search.replace = action == Action::Replace && !tab.editor.read_only;
+                }
+            }
+            Action::Stop => {
+                if let Some((id, _)) = focused {
+                    config.terminal.kill(ctx, id);
+                }
+            }
+            Action::ToggleTerminal => config.terminal.toggle(),
+            Action::NewTab => {
+                if let Some(node) = config.dock.tree.focused_leaf() {
+                    commands.push(Command::TabCommand(TabCommand::Add(node)));
+                }
+            }
+            Action::CloseTab => {
+                if let Some((id, _)) = focused {
+                    commands.push(Command::TabCommand(TabCommand::CloseTabs {
+                        tabs: vec![id],
+                        unsaved: Unsaved::Ask,
+                        exit: false,
+                    }));
+                }
+            }
+            Action::ReopenTab => commands.push(Command::TabCommand(TabCommand::Reopen(None))),
+            Action::OpenFile => commands.push(Command::TabCommand(TabCommand::Open)),
+            Action::NextTab => Dock::step_tab(config, 1),
+            Action::PreviousTab => Dock::step_tab(config, -1),
+            Action::CommandPalette => CommandPalette::toggle(config),
+            Action::QuickOpen => QuickOpen::toggle(config),
+            Action::GoToLine => GoToLine::toggle(config),
+        }
+    }
+}
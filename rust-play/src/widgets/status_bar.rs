@@ -0,0 +1,145 @@
+use egui::{popup, AboveOrBelow, Align, Button, Id, Layout, TextEdit, TopBottomPanel, Ui};
+
+use super::dock::Tab;
+use super::palette::GoToLine;
+use crate::config::{Config, Indent};
+
+// what the indentation popup offers, besides going back to the settings
+const INDENTS: [Indent; 4] = [
+    Indent {
+        with_tabs: false,
+        width: 2,
+    },
+    Indent {
+        with_tabs: false,
+        width: 4,
+    },
+    Indent {
+        with_tabs: false,
+        width: 8,
+    },
+    Indent {
+        with_tabs: true,
+        width: 4,
+    },
+];
+
+/// The focused editor's cursor, selection and indentation, and the tab's edition, along the very
+/// bottom of the window below the terminal
+pub struct StatusBar;
+
+impl StatusBar {
+    pub fn show(ctx: &egui::Context, config: &mut Config) {
+        if config.status_bar.is_empty() {
+            return;
+        }
+
+        let shown = &config.status_bar;
+        let default_indent = config.editor.indent();
+        let mut go_to_line = false;
+
+        TopBottomPanel::bottom("status_bar")
+            .exact_height(20.0)
+            .show(ctx, |ui| {
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    // the welcome page has no code
+                    let focused = config.dock.tree.find_active_focused();
+                    let Some((_, tab)) = focused.filter(|(_, tab)| !tab.welcome) else {
+                        return;
+                    };
+
+                    // added from the right
+                    if shown.run_config && !tab.editor.read_only {
+                        let run_config = &tab.run_config;
+                        ui.label(format!("{} · {}", run_config.edition, run_config.channel))
+                            .on_hover_text("The tab's edition and channel");
+                    }
+                    if shown.indent && !tab.editor.read_only {
+                        indent_menu(ui, tab, default_indent);
+                    }
+
+                    let (code, id) = match tab.open_file.and_then(|index| tab.files.get(index)) {
+                        Some(file) => (&file.editor.code, tab.editor_id(tab.open_file)),
+                        None => (&tab.editor.code, tab.editor_id(None)),
+                    };
+                    let range = TextEdit::load_state(ctx, id)
+                        .and_then(|state| state.ccursor_range())
+                        .unwrap_or_default();
+                    let (cursor, anchor) = (range.primary.index, range.secondary.index);
+
+                    if shown.selection && cursor != anchor {
+                        let lines = line_col(code, cursor.max(anchor)).0
+                            - line_col(code, cursor.min(anchor)).0
+                            + 1;
+                        let chars = cursor.abs_diff(anchor);
+                        let text = if lines > 1 {
+                            format!("{chars} selected ({lines} lines)")
+                        } else {
+                            format!("{chars} selected")
+                        };
+                        ui.label(text);
+                    }
+                    if shown.position {
+                        let (line, col) = line_col(code, cursor);
+                        go_to_line = ui
+                            .add(Button::new(format!("{line}:{col}")).frame(false))
+                            .on_hover_text("Go to line")
+                            .clicked();
+                    }
+                });
+            });
+
+        if go_to_line {
+            GoToLine::toggle(config);
+        }
+    }
+}
+
+// Spaces or tabs for the tab, picked from a popup above it
+fn indent_menu(ui: &mut Ui, tab: &mut Tab, default_indent: Indent) {
+    let indent = tab.indent.unwrap_or(default_indent);
+    let popup_id = Id::new("status_bar_indent");
+
+    let response = ui
+        .add(Button::new(indent.to_string()).frame(false))
+        .on_hover_text("Indentation");
+    if response.clicked() {
+        ui.memory().toggle_popup(popup_id);
+    }
+
+    popup::popup_above_or_below_widget(ui, popup_id, &response, AboveOrBelow::Above, |ui| {
+        ui.set_min_width(150.0);
+
+        for option in INDENTS {
+            let picked = tab.indent == Some(option);
+            if ui.selectable_label(picked, option.to_string()).clicked() {
+                tab.indent = Some(option);
+            }
+        }
+
+        ui.separator();
+        let settings = format!("From the settings ({default_indent})");
+        if ui
+            .selectable_label(tab.indent.is_none(), settings)
+            .clicked()
+        {
+            tab.indent = None;
+        }
+    });
+}
+
+// the 1-based line and column of a char index
+fn line_col(code: &str, cursor: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut col = 1;
+    for c in code.chars().take(cursor) {
+        if c == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+    }
+
+    (line, col)
+}
@@ -1,176 +1,1638 @@
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use egui::mutex::Mutex;
 use egui::panel::PanelState;
-use egui::text::LayoutJob;
-use egui::{pos2, vec2, Color32, CursorIcon, FontId, Id, Rect, Sense, Stroke, TextBuffer, Vec2};
+use egui::text::{CCursor, LayoutJob, LayoutSection, TextFormat};
+use egui::text_edit::{CCursorRange, TextEditOutput, TextEditState};
+use egui::{
+    pos2, vec2, Align, Align2, Color32, CursorIcon, FontId, Id, Key, LayerId, Layout, Modifiers,
+    Order, PointerButton, Rect, RichText, Sense, Stroke, TextBuffer, Visuals,
+};
 use once_cell::sync::OnceCell;
+use regex::RegexBuilder;
+
+use crate::config::{
+    Action, AnsiColors, Command, Config, Follow, OutputChunk, PastRun, RunState, RunStatus, Stream,
+    TerminalCommand, TerminalFilter, TerminalSearch, TerminalView, ThemeConfig, DEFAULT_FONT_SIZE,
+    MAX_FONT_SIZE, MIN_FONT_SIZE,
+};
+use crate::utils::ansi_parser::{self, Color, Parser, TextProperty};
+use crate::utils::build_cache;
+use crate::utils::contrast::{self, blend};
+use crate::utils::links;
+use crate::utils::log_filter::{self, LineFilter, LogLevel};
+use crate::utils::logs::{self, LogRecord};
+use crate::utils::run_history;
+use crate::utils::timestamp::Timestamp;
+
+use super::code_editor::diagnostic_color;
+use super::palette::CommandRegistry;
+use super::titlebar::TITLEBAR_HEIGHT;
+#[cfg(target_os = "windows")]
+use crate::os::windows::clipboard;
 
-use crate::config::{AnsiColors, Config};
-use crate::utils::ansi_parser::{self, Color};
+// A read only string for multiline textedit
+struct ReadOnlyString<'a> {
+    content: &'a str,
+}
 
-use super::titlebar::TITLEBAR_HEIGHT;
+impl<'a> TextBuffer for ReadOnlyString<'a> {
+    fn is_mutable(&self) -> bool {
+        false
+    }
+
+    fn as_str(&self) -> &str {
+        self.content
+    }
+
+    fn insert_text(&mut self, _: &str, _: usize) -> usize {
+        0
+    }
+
+    fn delete_char_range(&mut self, _: std::ops::Range<usize>) {}
+
+    fn clear(&mut self) {}
+
+    fn replace(&mut self, _: &str) {}
+}
+
+impl<'a> ReadOnlyString<'a> {
+    fn new(content: &'a str) -> Self {
+        Self { content }
+    }
+}
+
+fn ansi_to_color32(colors: AnsiColors, color: Color) -> Color32 {
+    match color {
+        Color::Black => colors.black.to_color32(),
+        Color::Red => colors.red.to_color32(),
+        Color::Green => colors.green.to_color32(),
+        Color::Yellow => colors.yellow.to_color32(),
+        Color::Blue => colors.blue.to_color32(),
+        Color::Magenta => colors.magenta.to_color32(),
+        Color::Cyan => colors.cyan.to_color32(),
+        Color::White => colors.white.to_color32(),
+        Color::BrightBlack => colors.bright_black.to_color32(),
+        Color::BrightRed => colors.bright_red.to_color32(),
+        Color::BrightGreen => colors.bright_green.to_color32(),
+        Color::BrightYellow => colors.bright_yellow.to_color32(),
+        Color::BrightBlue => colors.bright_blue.to_color32(),
+        Color::BrightMagenta => colors.bright_magenta.to_color32(),
+        Color::BrightCyan => colors.bright_cyan.to_color32(),
+        Color::BrightWhite => colors.bright_white.to_color32(),
+        // 0-15 resolve to the named colors above, so they follow the theme
+        Color::Indexed(index) => ansi_to_color32(colors, ansi_parser::indexed_color(index)),
+        Color::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
+    }
+}
+
+// What the output's sections are styled with
+struct OutputStyle {
+    default_color: Color32,
+    default_background: Color32,
+    colors: AnsiColors,
+    font_id: FontId,
+    minimum_contrast: Option<f32>,
+}
+
+impl OutputStyle {
+    fn new(theme: &ThemeConfig, visuals: &Visuals, font_id: FontId) -> Self {
+        Self {
+            default_color: theme.text_color(visuals),
+            default_background: theme.background(visuals),
+            colors: theme.get_ansi_colors(),
+            font_id,
+            minimum_contrast: theme.minimum_contrast,
+        }
+    }
+
+    // sections made with a different style have to be remade
+    fn hash(&self) -> u64 {
+        egui::util::hash((
+            self.default_color,
+            self.default_background,
+            self.colors,
+            &self.font_id,
+            self.minimum_contrast.map(f32::to_bits),
+        ))
+    }
+}
+
+// Turns the parsed ansi properties into layout sections
+struct AnsiColorParser;
+
+impl Default for AnsiColorParser {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl AnsiColorParser {
+    fn parse(&self, style: &OutputStyle, properties: &[TextProperty]) -> Vec<LayoutSection> {
+        let OutputStyle {
+            default_color,
+            default_background,
+            colors,
+            ref font_id,
+            minimum_contrast,
+        } = *style;
+
+        let ansi_to_color32 = |color| ansi_to_color32(colors, color);
+
+        let mut sections = Vec::with_capacity(properties.len());
+
+        for chunk in properties {
+            // bold text uses the bright variant of the named colors
+            let fg = if chunk.style.bold {
+                chunk.fg.map(Color::bright)
+            } else {
+                chunk.fg
+            };
+
+            let bg = chunk.bg.map(ansi_to_color32);
+
+            // stderr without a color of its own is tinted, so it stands out when interleaved with stdout.
+            // The user's input is dimmed, so the transcript still reads as the program's output
+            let mut text_color = match fg {
+                Some(fg) => ansi_to_color32(fg),
+                None if chunk.echo => colors.bright_black.to_color32(),
+                None if chunk.stderr => colors.red.to_color32(),
+                None => default_color,
+            };
+            let mut background_color = bg.unwrap_or(Color32::TRANSPARENT);
+
+            // dim text fades toward whatever is behind it
+            if chunk.style.dim {
+                text_color = blend(text_color, bg.unwrap_or(default_background), 0.5);
+            }
+
+            if chunk.style.reverse {
+                (text_color, background_color) = (bg.unwrap_or(default_background), text_color);
+            }
+
+            // keep dark text on a dark background readable
+            if let Some(minimum_contrast) = minimum_contrast {
+                let background = if background_color == Color32::TRANSPARENT {
+                    default_background
+                } else {
+                    background_color
+                };
+
+                text_color = contrast::ensure_contrast(text_color, background, minimum_contrast);
+            }
+
+            let italics = chunk.style.italic;
+            let underline = chunk.style.underline;
+
+            let underline = if underline {
+                Stroke::new(1.0, text_color)
+            } else {
+                Stroke::NONE
+            };
+
+            let strikethrough = if chunk.style.strikethrough {
+                Stroke::new(1.0, text_color)
+            } else {
+                Stroke::NONE
+            };
+
+            sections.push(LayoutSection {
+                leading_space: 0.0,
+                byte_range: chunk.start..chunk.end,
+                format: TextFormat {
+                    font_id: font_id.clone(),
+                    color: text_color,
+                    italics,
+                    underline,
+                    background: background_color,
+                    strikethrough,
+                    ..Default::default()
+                },
+            });
+        }
+
+        sections
+    }
+}
+
+// Find all the matches of the search query in the text
+fn find_matches(search: &TerminalSearch, text: &str) -> Vec<Range<usize>> {
+    if search.query.is_empty() {
+        return vec![];
+    }
+
+    let regex = RegexBuilder::new(&regex::escape(&search.query))
+        .case_insensitive(!search.case_sensitive)
+        .build();
+
+    match regex {
+        Ok(regex) => regex.find_iter(text).map(|m| m.range()).collect(),
+        Err(_) => vec![],
+    }
+}
+
+// Paint a background behind the matches. Sections get split up wherever a match starts or ends
+fn highlight_matches(job: &mut LayoutJob, matches: &[(Range<usize>, Color32)]) {
+    restyle_ranges(job, matches, |format, color| format.background = *color);
+}
+
+// Underline the links in the link color
+fn style_links(ui: &egui::Ui, job: &mut LayoutJob, links: &[(Range<usize>, Link)]) {
+    let color = ui.visuals().hyperlink_color;
+
+    restyle_ranges(job, links, |format, _| {
+        format.color = color;
+        format.underline = Stroke::new(1.0, color);
+    });
+}
+
+/// Change the format of some sorted, non overlapping ranges of the text. Sections get split up
+/// wherever a range starts or ends
+pub fn restyle_ranges<T>(
+    job: &mut LayoutJob,
+    ranges: &[(Range<usize>, T)],
+    restyle: impl Fn(&mut TextFormat, &T),
+) {
+    if ranges.is_empty() {
+        return;
+    }
+
+    let mut sections = Vec::with_capacity(job.sections.len() + ranges.len() * 2);
+
+    for section in job.sections.drain(..) {
+        let Range {
+            start: section_start,
+            end,
+        } = section.byte_range;
+        let mut start = section_start;
+
+        let overlapping = ranges
+            .iter()
+            .filter(|(range, _)| range.start < end && range.end > section_start);
+
+        for (range, value) in overlapping {
+            if range.start > start {
+                sections.push(LayoutSection {
+                    byte_range: start..range.start,
+                    ..section.clone()
+                });
+            }
+
+            let range_end = range.end.min(end);
+
+            let mut format = section.format.clone();
+            restyle(&mut format, value);
+
+            sections.push(LayoutSection {
+                leading_space: 0.0,
+                byte_range: start.max(range.start)..range_end,
+                format,
+            });
+
+            start = range_end;
+        }
+
+        if start < end {
+            sections.push(LayoutSection {
+                byte_range: start..end,
+                ..section
+            });
+        }
+    }
+
+    job.sections = sections;
+}
+
+// The link under the pointer, if there is one
+fn hovered_link<'a>(
+    output: &TextEditOutput,
+    text: &str,
+    links: &'a [(Range<usize>, Link)],
+) -> Option<&'a Link> {
+    let pos = output.response.hover_pos()?;
+
+    // past the end of a line this snaps to the newline, which is never part of a link
+    let cursor = output.galley.cursor_from_pos(pos - output.text_draw_pos);
+
+    let (byte_index, _) = text.char_indices().nth(cursor.ccursor.index)?;
+
+    links
+        .iter()
+        .find(|(range, _)| range.contains(&byte_index))
+        .map(|(_, link)| link)
+}
+
+// Show the link under the pointer, and open it when clicked. Returns the location to jump to if a
+// link into the scratch was clicked
+fn handle_links(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    text: &str,
+    links: &[(Range<usize>, Link)],
+) -> Option<(usize, usize)> {
+    let link = hovered_link(output, text, links)?;
+
+    ui.output().cursor_icon = CursorIcon::PointingHand;
+    egui::show_tooltip_at_pointer(ui.ctx(), output.response.id.with("link"), |ui| match link {
+        Link::Url(url) => ui.label(url),
+        Link::Scratch { line, col } => ui.label(format!("Go to line {line}, column {col}")),
+    });
+
+    if !output.response.clicked() {
+        return None;
+    }
+
+    match link {
+        Link::Url(url) => {
+            ui.output().open_url(url);
+            None
+        }
+        Link::Scratch { line, col } => Some((*line, *col)),
+    }
+}
+
+// Paint the time each line arrived in the gutter to the left of the text
+fn paint_timestamps(
+    ui: &egui::Ui,
+    output: &TextEditOutput,
+    times: &[Timestamp],
+    font_id: &FontId,
+    gutter_width: f32,
+) {
+    let painter = ui.painter();
+    let clip_rect = ui.clip_rect();
+    let color = ui.visuals().weak_text_color();
+
+    // wrapped lines take up several rows, only the first one gets the time
+    let mut line = 0;
+    let mut starts_line = true;
+
+    for row in &output.galley.rows {
+        let rect = row.rect.translate(output.text_draw_pos.to_vec2());
+        if rect.top() > clip_rect.bottom() {
+            break;
+        }
+
+        if starts_line {
+            if let Some(time) = times.get(line) {
+                if rect.bottom() >= clip_rect.top() {
+                    painter.text(
+                        pos2(output.text_draw_pos.x - gutter_width, rect.top()),
+                        Align2::LEFT_TOP,
+                        format!("[{time}]"),
+                        font_id.clone(),
+                        color,
+                    );
+                }
+            }
+
+            line += 1;
+        }
+
+        starts_line = row.ends_with_newline;
+    }
+}
+
+// Scroll the surrounding scrollarea to the position of a byte index in the textedit
+fn scroll_to_byte(ui: &egui::Ui, output: &TextEditOutput, text: &str, byte_index: usize) {
+    let char_index = text[..byte_index].chars().count();
+    let cursor = output.galley.from_ccursor(CCursor::new(char_index));
+
+    let rect = output
+        .galley
+        .pos_from_cursor(&cursor)
+        .translate(output.text_draw_pos.to_vec2());
+
+    ui.scroll_to_rect(rect, Some(Align::Center));
+}
+
+fn search_bar(ui: &mut egui::Ui, search: &mut TerminalSearch, match_count: usize) {
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut search.query)
+                .hint_text("Find")
+                .desired_width(200.0),
+        );
+
+        if search.focus {
+            response.request_focus();
+            search.focus = false;
+        }
+
+        if response.changed() {
+            search.current = 0;
+            search.scroll_to_current = true;
+        }
+
+        let (enter, escape, shift) = {
+            let input = ui.input();
+            (
+                input.key_pressed(Key::Enter),
+                input.key_pressed(Key::Escape),
+                input.modifiers.shift,
+            )
+        };
+
+        let enter = response.lost_focus() && enter;
+        if enter {
+            // keep the focus so you can keep pressing enter
+            response.request_focus();
+        }
+
+        ui.toggle_value(&mut search.case_sensitive, "Aa")
+            .on_hover_text("Match case");
+
+        if match_count == 0 {
+            ui.label("No results");
+        } else {
+            ui.label(format!("{}/{match_count}", search.current + 1));
+        }
+
+        let previous = ui
+            .button("⏶")
+            .on_hover_text("Previous match (Shift+Enter)")
+            .clicked();
+        let next = ui.button("⏷").on_hover_text("Next match (Enter)").clicked();
+
+        if match_count > 0 {
+            if previous || (enter && shift) {
+                search.current = (search.current + match_count - 1) % match_count;
+                search.scroll_to_current = true;
+            } else if next || enter {
+                search.current = (search.current + 1) % match_count;
+                search.scroll_to_current = true;
+            }
+        }
+
+        let close = ui.button("🗙").on_hover_text("Close (Escape)").clicked();
+        if close || (response.lost_focus() && escape) {
+            search.open = false;
+        }
+    });
+}
+
+// The filter box, the level to filter by and how many lines the filter hides
+fn filter_bar(ui: &mut egui::Ui, filter: &mut TerminalFilter, invalid: bool, hidden: usize) {
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut filter.query)
+                .hint_text("Filter lines, or /regex/")
+                .desired_width(200.0),
+        );
+
+        let level_text = match filter.level {
+            Some(level) => level.to_string(),
+            None => "All levels".to_string(),
+        };
+
+        egui::ComboBox::from_id_source("terminal_filter_level")
+            .selected_text(level_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.level, None, "All levels");
+                for level in LogLevel::ALL.into_iter().rev() {
+                    ui.selectable_value(&mut filter.level, Some(level), level.to_string());
+                }
+            })
+            .response
+            .on_hover_text("Hide lines logged at a more verbose level");
+
+        if invalid {
+            ui.colored_label(ui.visuals().error_fg_color, "Invalid regex");
+        } else if hidden == 1 {
+            ui.label("1 line hidden");
+        } else {
+            ui.label(format!("{hidden} lines hidden"));
+        }
+
+        let escape = ui.input().key_pressed(Key::Escape);
+        let close = ui.button("🗙").on_hover_text("Close (Escape)").clicked();
+        if close || (response.lost_focus() && escape) {
+            filter.open = false;
+        }
+    });
+}
+
+// Show whether the output is following new output in the corner of the output. Returns whether it was
+// clicked, which toggles following
+fn follow_pill(ctx: &egui::Context, id: Id, output_rect: Rect, follow: &Follow) -> bool {
+    egui::Area::new(id.with("follow_pill"))
+        .fixed_pos(output_rect.right_bottom() - vec2(8.0, 8.0))
+        .pivot(Align2::RIGHT_BOTTOM)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if follow.unread {
+                    ui.colored_label(ui.visuals().hyperlink_color, "● new output");
+                }
+
+                let (text, hover) = if follow.paused {
+                    ("⏸ paused", "Click to follow the output")
+                } else {
+                    ("⬇ following output", "Click to stop following the output")
+                };
+
+                let pill = egui::Button::new(text)
+                    .small()
+                    .fill(ui.visuals().extreme_bg_color);
+
+                ui.add(pill).on_hover_text(hover).clicked()
+            })
+            .inner
+        })
+        .inner
+}
+
+enum InputAction {
+    // send a line to the program
+    Send(String),
+    // close stdin
+    Close,
+}
+
+// The line for typing input to the running program
+fn input_line(
+    ui: &mut egui::Ui,
+    input: &mut String,
+    font_id: &FontId,
+    enabled: bool,
+) -> Option<InputAction> {
+    let mut action = None;
+
+    ui.add_enabled_ui(enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                let send = ui
+                    .button("Send")
+                    .on_hover_text("Send the line to the program (Enter), Ctrl+D closes its input")
+                    .clicked();
+
+                let hint = if enabled {
+                    "Input for the program"
+                } else {
+                    "Nothing is reading input"
+                };
+
+                let response = ui.add(
+                    egui::TextEdit::singleline(input)
+                        .hint_text(hint)
+                        .font(font_id.clone())
+                        .desired_width(ui.available_width()),
+                );
+
+                let enter = response.lost_focus() && ui.input().key_pressed(Key::Enter);
+                if enter {
+                    // keep the focus so you can keep typing lines
+                    response.request_focus();
+                }
+
+                if send || enter {
+                    action = Some(InputAction::Send(std::mem::take(input)));
+                } else if response.has_focus()
+                    && ui.input_mut().consume_key(Modifiers::COMMAND, Key::D)
+                {
+                    action = Some(InputAction::Close);
+                }
+            });
+        });
+    });
+
+    action
+}
+
+// Something in the output which can be clicked on
+#[derive(Debug, Clone)]
+enum Link {
+    Url(String),
+    // a location in the tab's scratch, both start at 1
+    Scratch { line: usize, col: usize },
+}
+
+// how often the logs view looks for new records while it's shown
+const LOGS_REFRESH: Duration = Duration::from_millis(250);
+
+// The app's own log, colored by level the way the output is. It's only laid out again when
+// there's new records, or the filter or style changed
+fn logs_view(ui: &mut egui::Ui, config: &mut Config) {
+    static LAYOUT: OnceCell<Mutex<(u64, LayoutJob)>> = OnceCell::new();
+
+    let mut copy = false;
+    ui.horizontal(|ui| {
+        let log_level = &mut config.terminal.log_level;
+        egui::ComboBox::from_id_source("logs_level")
+            .selected_text(log_level.map_or("All levels".to_string(), |level| level.to_string()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(log_level, None, "All levels");
+                for level in LogLevel::ALL {
+                    ui.selectable_value(log_level, Some(level), level.to_string());
+                }
+            });
+        copy = ui.button("📋 Copy all").clicked();
+    });
+
+    let log_level = config.terminal.log_level;
+    let keeps = |record: &&LogRecord| log_level.is_none_or(|level| record.level <= level);
+    if copy {
+        let text = logs::with_records(|records| {
+            records
+                .iter()
+                .filter(keeps)
+                .map(|record| record.line(false))
+                .collect::<String>()
+        });
+        ui.output().copied_text = text;
+    }
+
+    let font_id = config.terminal.font_id();
+    let style = OutputStyle::new(&config.theme, ui.visuals(), font_id.clone());
+    let key = egui::util::hash((logs::version(), log_level, style.hash()));
+
+    let mut layout = LAYOUT
+        .get_or_init(|| Mutex::new((u64::MAX, LayoutJob::default())))
+        .lock();
+    if layout.0 != key {
+        let text = logs::with_records(|records| {
+            records
+                .iter()
+                .filter(keeps)
+                .map(|record| record.line(true))
+                .collect::<String>()
+        });
+        *layout = (
+            key,
+            layout_ansi(&config.theme, ui.visuals(), font_id, &text),
+        );
+    }
+
+    if layout.1.text.is_empty() {
+        ui.centered_and_justified(|ui| ui.weak("Nothing logged yet"));
+    } else {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show(ui, |ui| ui.label(layout.1.clone()));
+    }
+
+    // records come in from the other threads without waking the ui
+    ui.ctx().request_repaint_after(LOGS_REFRESH);
+}
+
+// The diagnostics of every tab's last check, grouped by tab. Picking one jumps to it in the editor
+fn problems_view(ui: &mut egui::Ui, id: Id, config: &mut Config) {
+    let problems = config
+        .dock
+        .tree
+        .tabs()
+        .filter(|tab| !tab.check.diagnostics.is_empty())
+        .map(|tab| (tab.id, tab.name.clone(), tab.check.diagnostics.clone()))
+        .collect::<Vec<_>>();
+
+    // the rows in the order they're shown, for the keyboard to move through
+    let rows = problems
+        .iter()
+        .flat_map(|(tab, _, diagnostics)| {
+            diagnostics.iter().map(move |diagnostic| (*tab, diagnostic))
+        })
+        .collect::<Vec<_>>();
+
+    if rows.is_empty() {
+        config.terminal.selected_problem = None;
+
+        let text = if config.dock.pending_checks.is_empty() {
+            "No problems"
+        } else {
+            "Checking…"
+        };
+        ui.centered_and_justified(|ui| ui.weak(text));
+        return;
+    }
+
+    let mut selected = config
+        .terminal
+        .selected_problem
+        .map(|index| index.min(rows.len() - 1));
+    let mut jump = None;
+    let mut scroll_to_selected = false;
+
+    // the list itself takes focus, so the arrow keys work once it's clicked
+    let list = ui.interact(ui.max_rect(), id, Sense::click());
+    if list.clicked() {
+        ui.memory().request_focus(id);
+    }
+
+    if list.has_focus() {
+        let mut input = ui.input_mut();
+
+        if input.consume_key(Modifiers::NONE, Key::ArrowDown) {
+            selected = Some(selected.map_or(0, |index| (index + 1).min(rows.len() - 1)));
+            scroll_to_selected = true;
+        }
+        if input.consume_key(Modifiers::NONE, Key::ArrowUp) {
+            selected = Some(selected.map_or(0, |index| index.saturating_sub(1)));
+            scroll_to_selected = true;
+        }
+        if input.consume_key(Modifiers::NONE, Key::Enter) {
+            jump = selected;
+        }
+    }
+
+    egui::ScrollArea::vertical()
+        .auto_shrink([false; 2])
+        .show(ui, |ui| {
+            let mut index = 0;
+
+            for (_, name, diagnostics) in &problems {
+                ui.strong(name);
+
+                for diagnostic in diagnostics {
+                    let color = diagnostic_color(ui.visuals(), diagnostic);
+                    // the rest of the message is notes and help, which are in the tooltip
+                    let message = diagnostic.message.lines().next().unwrap_or_default();
+
+                    ui.horizontal(|ui| {
+                        ui.colored_label(color, "●");
+
+                        let row = ui
+                            .selectable_label(selected == Some(index), message)
+                            .on_hover_text(&diagnostic.message);
+                        ui.weak(format!("main.rs:{}:{}", diagnostic.line, diagnostic.col));
+
+                        if row.clicked() {
+                            selected = Some(index);
+                            jump = Some(index);
+                        }
+                        if scroll_to_selected && selected == Some(index) {
+                            row.scroll_to_me(None);
+                        }
+                    });
+
+                    index += 1;
+                }
+            }
+        });
+
+    config.terminal.selected_problem = selected;
+
+    if let Some((tab, diagnostic)) = jump.and_then(|index| rows.get(index)) {
+        config
+            .dock
+            .commands
+            .push(Command::TerminalCommand(TerminalCommand::JumpTo {
+                tab: *tab,
+                line: diagnostic.line,
+                col: diagnostic.col,
+            }));
+    }
+}
+
+// Scratches are built as the main.rs of a generated project. Their module files are next to it, but
+// only main.rs is checked, so that's the only file which maps back to the tab
+fn is_scratch_path(path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    path == "src/main.rs" || path.ends_with("/src/main.rs")
+}
+
+// One view of a tab's output. The parser keeps the text with the ansi codes applied and stripped out for
+// display
+#[derive(Default)]
+struct Scrollback {
+    // parsed incrementally as output is appended
+    parser: Parser,
+    sections: Vec<LayoutSection>,
+    // hash of the colors and font the sections were made with
+    sections_style: u64,
+    // index of the first property whose section is out of date
+    dirty_from: usize,
+    // byte ranges of links in the text, and where they go
+    links: Vec<(Range<usize>, Link)>,
+    links_dirty: bool,
+    // when each line of the text arrived
+    line_times: Vec<Timestamp>,
+    // the lines up to here are finished, so their count doesn't change
+    finished_lines: usize,
+    finished_bytes: usize,
+    // bumped whenever the text changes
+    version: u64,
+    // the text with only the lines the filter keeps
+    filtered: FilteredView,
+}
+
+// What the output is filtered by
+struct OutputFilter {
+    lines: Option<LineFilter>,
+    level: Option<LogLevel>,
+    // changes whenever the filter does
+    hash: u64,
+}
+
+impl OutputFilter {
+    // None if it wouldn't hide anything. Invalid regexes are ignored
+    fn new(filter: &TerminalFilter) -> (Option<Self>, bool) {
+        if !filter.open {
+            return (None, false);
+        }
+
+        let (lines, invalid) = match LineFilter::parse(&filter.query) {
+            Ok(lines) => (lines, false),
+            Err(_) => (None, true),
+        };
+
+        if lines.is_none() && filter.level.is_none() {
+            return (None, invalid);
+        }
+
+        let output_filter = Self {
+            lines,
+            level: filter.level,
+            hash: egui::util::hash((&filter.query, filter.level)),
+        };
+
+        (Some(output_filter), invalid)
+    }
+
+    // lines without a level tag are kept, they're usually the rest of a multiline message
+    fn keeps(&self, line: &str) -> bool {
+        let level_kept = match (self.level, log_filter::line_level(line)) {
+            (Some(max_level), Some(level)) => level <= max_level,
+            _ => true,
+        };
+
+        level_kept
+            && match &self.lines {
+                Some(lines) => lines.matches(line),
+                None => true,
+            }
+    }
+}
+
+// A scrollback with only the lines the filter keeps. The sections and links of the full text are moved
+// over, so the lines keep their colors
+#[derive(Default)]
+struct FilteredView {
+    // hash of the filter and the scrollback version it was made from
+    key: u64,
+    text: String,
+    sections: Vec<LayoutSection>,
+    links: Vec<(Range<usize>, Link)>,
+    line_times: Vec<Timestamp>,
+    hidden: usize,
+}
+
+// Move sorted, non overlapping byte ranges of a text over to where they are once only the kept lines
+// are left. Kept lines are their byte range and where they start in the filtered text. The parts of
+// ranges in hidden lines are dropped, and ranges spanning several kept lines get split up
+fn filter_ranges<T: Clone>(
+    ranges: impl IntoIterator<Item = (Range<usize>, T)>,
+    kept: &[(Range<usize>, usize)],
+) -> Vec<(Range<usize>, T)> {
+    let mut filtered = vec![];
+    let mut line = 0;
+
+    for (range, value) in ranges {
+        // the ranges are sorted, so lines before this one don't overlap any of the rest
+        while line < kept.len() && kept[line].0.end <= range.start {
+            line += 1;
+        }
+
+        let overlapping = kept[line..]
+            .iter()
+            .take_while(|(line_range, _)| line_range.start < range.end);
+
+        for (line_range, offset) in overlapping {
+            let start = range.start.max(line_range.start) - line_range.start + offset;
+            let end = range.end.min(line_range.end) - line_range.start + offset;
+
+            filtered.push((start..end, value.clone()));
+        }
+    }
+
+    filtered
+}
+
+// The text of a scrollback to show, filtered or not
+struct View<'a> {
+    text: &'a str,
+    sections: &'a [LayoutSection],
+    links: &'a [(Range<usize>, Link)],
+    line_times: &'a [Timestamp],
+}
+
+impl Scrollback {
+    // Append output, only parsing the new text. Stderr is marked as such, for when it's interleaved
+    // with stdout
+    fn push(&mut self, text: &str, stderr: bool, time: Timestamp) {
+        let changed = if stderr {
+            self.parser.push_stderr(text)
+        } else {
+            self.parser.push(text)
+        };
+
+        self.changed(changed, time);
+    }
+
+    // Append input the user sent, as it was typed
+    fn push_echo(&mut self, text: &str, time: Timestamp) {
+        let changed = self.parser.push_echo(text);
+        self.changed(changed, time);
+    }
+
+    // Mark what changed after a push as out of date, and give any new lines the time it arrived
+    fn changed(&mut self, changed: usize, time: Timestamp) {
+        self.dirty_from = self.dirty_from.min(changed);
+        self.links_dirty = true;
+        self.version += 1;
+
+        let text = &self.parser.text;
+        let new = &text[self.finished_bytes..];
+        if let Some(end) = new.rfind('\n') {
+            self.finished_lines += new[..end].matches('\n').count() + 1;
+            self.finished_bytes += end + 1;
+        }
+
+        // the line in progress can be erased completely, then the next output starts it over
+        let lines = self.finished_lines + usize::from(self.finished_bytes < text.len());
+        self.line_times.truncate(lines);
+        self.line_times.resize(lines, time);
+    }
+
+    // The text with the time each line arrived in front of it
+    fn timestamped_text(&self) -> String {
+        self.parser
+            .text
+            .split_inclusive('\n')
+            .zip(&self.line_times)
+            .map(|(line, time)| format!("[{time}] {line}"))
+            .collect()
+    }
+
+    // Find the links in the text. Hyperlinks sent by the program, bare urls, and file locations in the scratch
+    fn update_links(&mut self) {
+        if !std::mem::take(&mut self.links_dirty) {
+            return;
+        }
+
+        let parser = &self.parser;
+        let mut links: Vec<(Range<usize>, Link)> = vec![];
+
+        for property in &parser.properties {
+            let Some(link) = property.link else {
+                continue;
+            };
+
+            let url = &parser.links[link];
+            match links.last_mut() {
+                Some((range, Link::Url(last_url)))
+                    if range.end == property.start && last_url == url =>
+                {
+                    range.end = property.end;
+                }
+                _ => links.push((property.start..property.end, Link::Url(url.clone()))),
+            }
+        }
+
+        let urls = links::find_urls(&parser.text)
+            .into_iter()
+            .map(|range| (range.clone(), Link::Url(parser.text[range].to_string())));
+
+        let locations = links::find_file_locations(&parser.text)
+            .into_iter()
+            .filter(|(_, location)| is_scratch_path(&location.path))
+            .map(|(range, location)| {
+                let link = Link::Scratch {
+                    line: location.line,
+                    col: location.col,
+                };
+
+                (range, link)
+            });
+
+        // the links found first take priority over any which overlap them
+        for (range, link) in urls.chain(locations) {
+            let overlaps = links
+                .iter()
+                .any(|(other, _)| other.start < range.end && range.start < other.end);
+
+            if !overlaps {
+                links.push((range, link));
+            }
+        }
+
+        links.sort_by_key(|(range, _)| range.start);
+        self.links = links;
+    }
+
+    // Bring the layout sections up to date, only remaking the ones which changed
+    fn update_sections(&mut self, style: &OutputStyle) {
+        let style_hash = style.hash();
+        if self.sections_style != style_hash {
+            self.sections_style = style_hash;
+            self.dirty_from = 0;
+        }
+
+        let properties = &self.parser.properties;
+        let dirty_from = self.dirty_from.min(properties.len());
+
+        self.sections.truncate(dirty_from);
+        self.sections
+            .extend(AnsiColorParser.parse(style, &properties[dirty_from..]));
+        self.dirty_from = properties.len();
+    }
+
+    // Bring the filtered view up to date, only refiltering when the text or the filter changed
+    fn update_filtered(&mut self, filter: &OutputFilter) {
+        let key = egui::util::hash((filter.hash, self.version, self.sections_style));
+        if self.filtered.key == key {
+            return;
+        }
+
+        let mut view = FilteredView {
+            key,
+            ..Default::default()
+        };
+
+        // the byte range of each kept line, and where it starts in the filtered text
+        let mut kept = vec![];
+        let mut start = 0;
+
+        for (line, time) in self.parser.text.split_inclusive('\n').zip(&self.line_times) {
+            let end = start + line.len();
+
+            if filter.keeps(line.trim_end_matches('\n')) {
+                kept.push((start..end, view.text.len()));
+                view.text.push_str(line);
+                view.line_times.push(*time);
+            } else {
+                view.hidden += 1;
+            }
+
+            start = end;
+        }
+
+        let sections = self
+            .sections
+            .iter()
+            .map(|section| (section.byte_range.clone(), &section.format));
+
+        view.sections = filter_ranges(sections, &kept)
+            .into_iter()
+            .map(|(byte_range, format)| LayoutSection {
+                leading_space: 0.0,
+                byte_range,
+                format: format.clone(),
+            })
+            .collect();
+        view.links = filter_ranges(self.links.iter().cloned(), &kept);
+
+        self.filtered = view;
+    }
+
+    fn view(&self, filtered: bool) -> View<'_> {
+        if filtered {
+            View {
+                text: &self.filtered.text,
+                sections: &self.filtered.sections,
+                links: &self.filtered.links,
+                line_times: &self.filtered.line_times,
+            }
+        } else {
+            View {
+                text: &self.parser.text,
+                sections: &self.sections,
+                links: &self.links,
+                line_times: &self.line_times,
+            }
+        }
+    }
+}
+
+// The output of a tab, both streams in the order they arrived. Each view of it is parsed as it comes
+// in, the split views just filter out the other stream
+#[derive(Default)]
+struct TabOutput {
+    chunks: Vec<OutputChunk>,
+    // total length of the chunks
+    bytes: usize,
+    // whether old lines have been dropped
+    truncated: bool,
+    // output came in since it was last shown
+    received: bool,
+    // stderr got its first output since it was last shown
+    new_stderr: bool,
+    stdout: Scrollback,
+    stderr: Scrollback,
+    merged: Scrollback,
+}
+
+impl TabOutput {
+    fn from_chunks(chunks: &[OutputChunk]) -> Self {
+        let mut output = Self::default();
+        for chunk in chunks {
+            output.push(chunk.clone());
+        }
+
+        output
+    }
+
+    fn push(&mut self, chunk: OutputChunk) {
+        self.parse(&chunk);
+
+        self.bytes += chunk.text.len();
+        self.chunks.push(chunk);
+    }
+
+    fn parse(&mut self, chunk: &OutputChunk) {
+        let OutputChunk { stream, text, time } = chunk;
+        let time = *time;
+
+        match stream {
+            Stream::Stdout => {
+                self.stdout.push(text, false, time);
+                self.merged.push(text, false, time);
+            }
+            Stream::Stderr => {
+                self.stderr.push(text, false, time);
+                self.merged.push(text, true, time);
+            }
+            // the program's prompts are usually on stdout, so that's where the input goes
+            Stream::Stdin => {
+                self.stdout.push_echo(text, time);
+                self.merged.push_echo(text, time);
+            }
+        }
+    }
+
+    // Parse everything from scratch, for when the output was changed instead of appended to
+    fn reparse(&mut self) {
+        self.stdout = Scrollback::default();
+        self.stderr = Scrollback::default();
+        self.merged = Scrollback::default();
+
+        for chunk in std::mem::take(&mut self.chunks) {
+            self.parse(&chunk);
+            self.chunks.push(chunk);
+        }
+    }
+
+    // Drop the oldest lines once the output doesn't fit in max_bytes. Output arrives a line at a time,
+    // and only chunks finishing a line are cut after, so an ansi escape never gets cut in half
+    fn trim(&mut self, max_bytes: usize) {
+        if self.bytes <= max_bytes {
+            return;
+        }
+
+        // go a bit below the limit, so the reparse this needs doesn't happen on every new line
+        let excess = self.bytes - max_bytes / 4 * 3;
+
+        // cut after the first line which gets us under the limit. If the excess is all one line, fall
+        // back to the last complete line, anything still in progress is kept
+        let mut dropped = 0;
+        let mut cut = None;
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            dropped += chunk.text.len();
+
+            if chunk.text.ends_with('\n') {
+                cut = Some(index + 1);
+
+                if dropped >= excess {
+                    break;
+                }
+            }
+        }
+
+        let Some(cut) = cut else {
+            return;
+        };
+
+        for chunk in self.chunks.drain(..cut) {
+            self.bytes -= chunk.text.len();
+        }
+
+        self.truncated = true;
+        self.reparse();
+    }
+
+    // Bring the links, sections and filtered text of the views being shown up to date
+    fn update(&mut self, merged: bool, filter: Option<&OutputFilter>, style: &OutputStyle) {
+        let scrollbacks = if merged {
+            vec![&mut self.merged]
+        } else {
+            vec![&mut self.stderr, &mut self.stdout]
+        };
+
+        for scrollback in scrollbacks {
+            scrollback.update_links();
+            scrollback.update_sections(style);
+
+            if let Some(filter) = filter {
+                scrollback.update_filtered(filter);
+            }
+        }
+    }
+
+    // The text to copy for a part of the output. Everything is copied in the order it's shown
+    fn copy_text(&self, target: CopyTarget, format: CopyFormat, merged: bool) -> String {
+        let text = |scrollback: &Scrollback| match format {
+            CopyFormat::Timestamps => scrollback.timestamped_text(),
+            _ => scrollback.parser.text.clone(),
+        };
+
+        if format != CopyFormat::Ansi {
+            return match target {
+                CopyTarget::All if merged => text(&self.merged),
+                CopyTarget::All => text(&self.stderr) + &text(&self.stdout),
+                CopyTarget::Stdout => text(&self.stdout),
+                CopyTarget::Stderr => text(&self.stderr),
+            };
+        }
+
+        let unstripped = |stream: Option<Stream>| {
+            self.chunks
+                .iter()
+                .filter(|chunk| {
+                    // input is shown along with stdout
+                    let chunk_stream = match chunk.stream {
+                        Stream::Stdin => Stream::Stdout,
+                        chunk_stream => chunk_stream,
+                    };
+
+                    stream.is_none() || stream == Some(chunk_stream)
+                })
+                .map(|chunk| chunk.text.as_str())
+                .collect::<String>()
+        };
+
+        match target {
+            CopyTarget::All if merged => unstripped(None),
+            CopyTarget::All => unstripped(Some(Stream::Stderr)) + &unstripped(Some(Stream::Stdout)),
+            CopyTarget::Stdout => unstripped(Some(Stream::Stdout)),
+            CopyTarget::Stderr => unstripped(Some(Stream::Stderr)),
+        }
+    }
+}
+
+// ids of the stdout, stderr and merged output texts
+const PANE_IDS: [&str; 3] = [
+    "term_output_stdout",
+    "term_output_stderr",
+    "term_output_merged",
+];
+
+// One of the texts the output is shown in. The split view has one for each stream, the merged view
+// only has the one
+struct Pane<'a> {
+    heading: Option<&'static str>,
+    id: &'static str,
+    scrollback: &'a Scrollback,
+    view: View<'a>,
+}
+
+// Which part of the terminal output to copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CopyTarget {
+    All,
+    Stdout,
+    Stderr,
+}
+
+// How the copied output is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CopyFormat {
+    // as it's shown
+    Plain,
+    // as the program wrote it
+    Ansi,
+    // as it's shown, with the time each line arrived
+    Timestamps,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputAction {
+    Clear,
+    // drop the tab's earlier runs
+    ClearHistory,
+    Copy {
+        target: CopyTarget,
+        format: CopyFormat,
+    },
+    // these are for the pane with this index
+    CopySelection(usize),
+    SelectAll(usize),
+    Paste,
+}
+
+// Copy entries, shared by the header copy button and the context menu
+fn copy_menu(ui: &mut egui::Ui, action: &mut Option<OutputAction>) {
+    let targets = [
+        ("Copy all", CopyTarget::All),
+        ("Copy stdout", CopyTarget::Stdout),
+        ("Copy stderr", CopyTarget::Stderr),
+    ];
+
+    let copy_buttons = |ui: &mut egui::Ui, action: &mut Option<OutputAction>, format| {
+        for (label, target) in targets {
+            if ui.button(label).clicked() {
+                *action = Some(OutputAction::Copy { target, format });
+                ui.close_menu();
+            }
+        }
+    };
+
+    copy_buttons(ui, action, CopyFormat::Plain);
+
+    ui.menu_button("Copy with ANSI codes", |ui| {
+        copy_buttons(ui, action, CopyFormat::Ansi)
+    });
 
-// A read only string for multiline textedit
-struct ReadOnlyString<'a> {
-    content: &'a str,
+    ui.menu_button("Copy with timestamps", |ui| {
+        copy_buttons(ui, action, CopyFormat::Timestamps)
+    });
 }
 
-impl<'a> TextBuffer for ReadOnlyString<'a> {
-    fn is_mutable(&self) -> bool {
-        false
-    }
+// Context menu for one of the output panes
+fn output_menu(
+    ui: &mut egui::Ui,
+    action: &mut Option<OutputAction>,
+    pane: usize,
+    has_selection: bool,
+    can_paste: bool,
+    has_history: bool,
+    copy_on_select: &mut bool,
+) {
+    let mut entry = |ui: &mut egui::Ui, enabled, label, entry_action| {
+        if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+            *action = Some(entry_action);
+            ui.close_menu();
+        }
+    };
 
-    fn as_str(&self) -> &str {
-        self.content
+    entry(ui, has_selection, "Copy", OutputAction::CopySelection(pane));
+    entry(ui, true, "Select all", OutputAction::SelectAll(pane));
+    if can_paste {
+        entry(ui, true, "Paste to stdin", OutputAction::Paste);
     }
 
-    fn insert_text(&mut self, _: &str, _: usize) -> usize {
-        0
-    }
+    ui.menu_button("Copy output", |ui| copy_menu(ui, action));
 
-    fn delete_char_range(&mut self, _: std::ops::Range<usize>) {}
+    ui.separator();
 
-    fn clear(&mut self) {}
+    if ui.button("Clear").clicked() {
+        *action = Some(OutputAction::Clear);
+        ui.close_menu();
+    }
+    if has_history && ui.button("Clear history").clicked() {
+        *action = Some(OutputAction::ClearHistory);
+        ui.close_menu();
+    }
 
-    fn replace(&mut self, _: &str) {}
+    ui.checkbox(copy_on_select, "Copy on select");
 }
 
-impl<'a> ReadOnlyString<'a> {
-    fn new(content: &'a str) -> Self {
-        Self { content }
+// The text of a range of chars, which is what text edit cursors count in
+fn char_range_text(text: &str, range: Range<usize>) -> String {
+    text.chars().skip(range.start).take(range.len()).collect()
+}
+
+// The text selected in an output pane, if anything is
+fn selected_text(ctx: &egui::Context, id: Id, text: &str) -> Option<String> {
+    let [start, end] = TextEditState::load(ctx, id)?.ccursor_range()?.sorted();
+
+    if start.index == end.index {
+        None
+    } else {
+        Some(char_range_text(text, start.index..end.index))
     }
 }
 
-// Memoized ansi color parsing
-pub fn parse_ansi(
-    ctx: &egui::Context,
-    ansi_colors: AnsiColors,
-    unparsed_text: &str,
-    text: &str,
-) -> LayoutJob {
-    impl egui::util::cache::ComputerMut<(u64, Color32, AnsiColors, &str, &str), LayoutJob>
-        for AnsiColorParser
+// The text on the clipboard
+fn clipboard_text() -> Option<String> {
+    #[cfg(target_os = "windows")]
     {
-        fn compute(
-            &mut self,
-            (_, default_color, ansi_colors, unparsed_text, text): (
-                u64,
-                Color32,
-                AnsiColors,
-                &str,
-                &str,
-            ),
-        ) -> LayoutJob {
-            self.parse(default_color, ansi_colors, unparsed_text, text)
-        }
+        clipboard::get_text()
     }
 
-    type ColorCache = egui::util::cache::FrameCache<LayoutJob, AnsiColorParser>;
+    #[cfg(not(target_os = "windows"))]
+    {
+        None
+    }
+}
 
-    let mut s = DefaultHasher::new();
-    unparsed_text.hash(&mut s);
-    let hash = s.finish();
+// Lay out text with ansi codes the way the terminal would show it with a theme
+pub fn layout_ansi(
+    theme: &ThemeConfig,
+    visuals: &Visuals,
+    font_id: FontId,
+    text: &str,
+) -> LayoutJob {
+    let mut parser = Parser::default();
+    parser.push(text);
 
-    let default_color = { ctx.style().visuals.text_color() };
+    let style = OutputStyle::new(theme, visuals, font_id);
 
-    let mut memory = ctx.memory();
-    let color_cache = memory.caches.cache::<ColorCache>();
-    color_cache.get((hash, default_color, ansi_colors, unparsed_text, text))
+    LayoutJob {
+        sections: AnsiColorParser.parse(&style, &parser.properties),
+        text: parser.text,
+        ..Default::default()
+    }
 }
 
-struct AnsiColorParser;
+pub struct Terminal;
 
-impl Default for AnsiColorParser {
-    fn default() -> Self {
-        Self
-    }
+// what an earlier run of a tab is cached under
+fn past_run_id(tab: Id, number: u64) -> Id {
+    tab.with(("past run", number))
 }
 
-impl AnsiColorParser {
-    fn parse(
-        &self,
-        default_color: Color32,
-        colors: AnsiColors,
-        unparsed_text: &str,
-        text: &str,
-    ) -> LayoutJob {
-        let ansi_to_color32 = |color| match color {
-            Color::Black => colors.black.to_color32(),
-            Color::Red => colors.red.to_color32(),
-            Color::Green => colors.green.to_color32(),
-            Color::Yellow => colors.yellow.to_color32(),
-            Color::Blue => colors.blue.to_color32(),
-            Color::Magenta => colors.magenta.to_color32(),
-            Color::Cyan => colors.cyan.to_color32(),
-            Color::White => colors.white.to_color32(),
-            Color::BrightBlack => colors.bright_black.to_color32(),
-            Color::BrightRed => colors.bright_red.to_color32(),
-            Color::BrightGreen => colors.bright_green.to_color32(),
-            Color::BrightYellow => colors.bright_yellow.to_color32(),
-            Color::BrightBlue => colors.bright_blue.to_color32(),
-            Color::BrightMagenta => colors.bright_magenta.to_color32(),
-            Color::BrightCyan => colors.bright_cyan.to_color32(),
-            Color::BrightWhite => colors.bright_white.to_color32(),
-            Color::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
-        };
+// the parsed output of each tab, kept between frames
+fn output_cache() -> &'static Mutex<HashMap<Id, TabOutput>> {
+    static CACHE: OnceCell<Mutex<HashMap<Id, TabOutput>>> = OnceCell::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-        use egui::text::{LayoutSection, TextFormat};
+impl Terminal {
+    /// The palette's commands for the terminal panel
+    pub fn register_commands(registry: &mut CommandRegistry) {
+        registry.register("Toggle terminal", Some(Action::ToggleTerminal), |config| {
+            config.terminal.toggle();
+        });
+
+        registry.register("Clear terminal", None, |config| {
+            if let Some(tab) = config.terminal.active_tab {
+                config.terminal.clear.insert(tab);
+                config.terminal.viewed_run.remove(&tab);
+            }
+        });
+
+        registry.register("Clear terminal history", None, |config| {
+            if let Some(tab) = config.terminal.active_tab {
+                config.terminal.history.remove(&tab);
+                config.terminal.viewed_run.remove(&tab);
+            }
+        });
+
+        for (name, view) in [
+            ("Show output", TerminalView::Output),
+            ("Show problems", TerminalView::Problems),
+            ("Show logs", TerminalView::Logs),
+        ] {
+            registry.register(name, None, move |config| {
+                config.terminal.view = view;
+                if !config.terminal.open {
+                    config.terminal.toggle();
+                }
+            });
+        }
+    }
 
-        let parsed = ansi_parser::parse(unparsed_text);
+    /// Take what every tab's run sent since the last frame, whether its output is shown or not, so
+    /// the runs never wait on the terminal
+    pub fn receive(config: &mut Config) {
+        let mut cache = output_cache().lock();
+
+        // closed tabs have their content removed, so their output goes with it. The
+        // earlier runs are shown from the cache too, for as long as they're kept
+        let past_ids = config
+            .terminal
+            .history
+            .iter()
+            .flat_map(|(tab, runs)| runs.iter().map(|run| past_run_id(*tab, run.number)))
+            .collect::<HashSet<_>>();
+        let kept = |tab: &Id| config.terminal.content.contains_key(tab) || past_ids.contains(tab);
+        cache.retain(|tab, _| kept(tab));
+        config
+            .terminal
+            .scroll_offset
+            .retain(|(tab, ..), _| kept(tab));
+
+        for (tab, exit_code) in std::mem::take(&mut config.terminal.started_runs) {
+            // otherwise the new output is appended after the old output
+            if !config.terminal.clear_on_run {
+                continue;
+            }
+
+            config.terminal.clear.insert(tab);
+            config.terminal.viewed_run.remove(&tab);
+
+            // the run being replaced is kept in the history, so it can still be looked at
+            let Some(chunks) = cache.remove(&tab).map(|output| output.chunks) else {
+                continue;
+            };
+            let (Some(first), Some(last)) = (chunks.first(), chunks.last()) else {
+                continue;
+            };
 
-        let mut job = LayoutJob {
-            text: text.into(),
-            ..Default::default()
-        };
+            let run = PastRun {
+                number: config.terminal.past_runs,
+                command_line: run_history::command_line(&first.text),
+                started: first.time,
+                finished: last.time,
+                exit_code,
+                output: chunks,
+            };
+            config.terminal.past_runs += 1;
 
-        for chunk in parsed.properties {
-            let text_color = chunk.fg.map(ansi_to_color32).unwrap_or(default_color);
-            let background_color = chunk
-                .bg
-                .map(ansi_to_color32)
-                .unwrap_or(Color32::TRANSPARENT);
+            let depth = config.terminal.history_depth;
+            run_history::push(config.terminal.history.entry(tab).or_default(), run, depth);
+        }
 
-            let italics = chunk.style.italic;
-            let underline = chunk.style.underline;
+        // cleared before the new output comes in, so none of it goes with the old
+        for tab in std::mem::take(&mut config.terminal.clear) {
+            cache.remove(&tab);
+            config
+                .terminal
+                .scroll_offset
+                .retain(|(id, ..), _| *id != tab);
+            config.terminal.follow.remove(&tab);
+        }
 
-            let underline = if underline {
-                Stroke::new(1.0, text_color)
-            } else {
-                Stroke::NONE
-            };
+        for (tab, content) in &config.terminal.content {
+            let mut chunks = content.iter().flat_map(Receiver::try_iter).peekable();
+            if chunks.peek().is_none() {
+                continue;
+            }
+
+            // carriage returns and erase sequences are handled by the parser, so progress bars
+            // overwrite their line instead of adding a new one every time
+            let output = cache.entry(*tab).or_default();
+            let stderr_was_empty = output.stderr.parser.text.is_empty();
+            for chunk in chunks {
+                output.push(chunk);
+            }
+
+            output.received = true;
+            output.new_stderr |= stderr_was_empty && !output.stderr.parser.text.is_empty();
+
+            // drop the oldest lines once the output gets too big to handle every frame
+            output.trim(config.terminal.max_scrollback_bytes);
+        }
+    }
 
-            let strikethrough = if chunk.style.strikethrough {
-                Stroke::new(1.0, text_color)
-            } else {
-                Stroke::NONE
-            };
+    /// Changes whenever what `output` returns or the tabs' history does, without copying any of it.
+    /// Output only grows until it's cleared or trimmed, so how much there is and when the last of
+    /// it came in tell it apart
+    pub fn output_revision(config: &Config) -> u64 {
+        let position = |id: Id| config.dock.tree.tabs().position(|tab| tab.id == id);
+        let cache = output_cache().lock();
+
+        let mut output = cache
+            .iter()
+            .filter(|(id, _)| config.terminal.content.contains_key(id))
+            .map(|(id, output)| {
+                let last = output.chunks.last().map(|chunk| chunk.time);
+                (position(*id), output.chunks.len(), output.bytes, last)
+            })
+            .collect::<Vec<_>>();
+        let mut messages = config
+            .terminal
+            .messages
+            .iter()
+            .map(|(id, messages)| {
+                let last = messages.last().map(|chunk| chunk.time);
+                (position(*id), messages.len(), last)
+            })
+            .collect::<Vec<_>>();
+        let mut history = config
+            .terminal
+            .history
+            .iter()
+            .map(|(id, runs)| (position(*id), runs.iter().map(|run| run.number).collect()))
+            .collect::<Vec<(_, Vec<_>)>>();
+        output.sort();
+        messages.sort();
+        history.sort();
+
+        let mut hasher = DefaultHasher::new();
+        (output, messages, history).hash(&mut hasher);
+        hasher.finish()
+    }
 
-            job.sections.push(LayoutSection {
-                leading_space: 0.0,
-                byte_range: chunk.start..chunk.end,
-                format: TextFormat {
-                    font_id: FontId::monospace(12.0),
-                    color: text_color,
-                    italics,
-                    underline,
-                    background: background_color,
-                    strikethrough,
-                    ..Default::default()
-                },
-            });
+    /// What each tab printed so far, including messages which weren't shown yet, in the tabs' order
+    pub fn output(config: &Config) -> Vec<(Id, Vec<OutputChunk>)> {
+        let cache = output_cache().lock();
+
+        // the earlier runs shown from the cache are in the history already
+        let mut output = cache
+            .iter()
+            .filter(|(id, _)| config.terminal.content.contains_key(id))
+            .map(|(id, output)| (*id, output.chunks.clone()))
+            .collect::<HashMap<_, _>>();
+
+        for (id, messages) in &config.terminal.messages {
+            output
+                .entry(*id)
+                .or_default()
+                .extend(messages.iter().cloned());
         }
 
-        job
+        let mut output = output.into_iter().collect::<Vec<_>>();
+        // so the same output is always written the same way
+        output.sort_by_key(|(id, _)| config.dock.tree.tabs().position(|tab| tab.id == *id));
+        output
     }
-}
-
-pub struct Terminal;
 
-impl Terminal {
     pub fn show(ctx: &egui::Context, config: &mut Config) {
         let id = Id::new("terminal");
 
@@ -185,9 +1647,33 @@ impl Terminal {
             );
 
             ctx.data().insert_persisted(id, PanelState { rect });
+        } else if config.terminal.restore_height {
+            // the last panel state is from when it was dragged closed, or from the last session, so go back
+            // to the height it was left at, or a usable size if there isn't one
+            let window_rect = ctx.available_rect();
+            let max_height = window_rect.height() - (TITLEBAR_HEIGHT as f32 / 2.0);
+            let height = config
+                .terminal
+                .height
+                .unwrap_or(window_rect.height() / 3.0)
+                .clamp(0.0, max_height.max(0.0));
+            let rect = Rect::from_two_pos(
+                pos2(0.0, window_rect.bottom() - height),
+                pos2(window_rect.right(), window_rect.bottom()),
+            );
+
+            ctx.data().insert_persisted(id, PanelState { rect });
+            config.terminal.restore_height = false;
         }
 
-        egui::TopBottomPanel::bottom(id)
+        let resize_id = id.with("__resize");
+        // the resize drag is over the bottom of the window, so letting go closes the panel
+        let mut close_pending = false;
+
+        let background = config.theme.background(&ctx.style().visuals);
+
+        let panel = egui::TopBottomPanel::bottom(id)
+            .frame(egui::Frame::side_top_panel(&ctx.style()).fill(background))
             .resizable(true)
             .default_height(0.0)
             .min_height(0.0)
@@ -212,15 +1698,18 @@ impl Terminal {
 
                 let window_close_bottom = ctx.available_rect().bottom() - close_threshold;
 
-                let resize_id = id.with("__resize");
-
                 // when mouse is outside of window, as long as we were dragging, pointer_pos is still Some()
                 // we can utilize this to allow resizing AS LONG AS mouse isn't below the window in screen coords
-                if (close_rect.contains(pointer_pos) || pointer_pos.y >= window_close_bottom)
-                    && ctx.memory().is_being_dragged(resize_id)
-                {
-                    config.terminal.open = false;
-                    config.terminal.closed_from_open = true;
+                let in_close_strip =
+                    close_rect.contains(pointer_pos) || pointer_pos.y >= window_close_bottom;
+
+                // only close once the drag ends, so resizing quickly past the bottom doesn't close it
+                if in_close_strip && ctx.memory().is_being_dragged(resize_id) {
+                    if ctx.input().pointer.any_released() {
+                        config.terminal.open = false;
+                    } else {
+                        close_pending = true;
+                    }
                 }
 
                 if config.terminal.opened_from_close {
@@ -246,188 +1735,839 @@ impl Terminal {
                 frame_rect.set_bottom(frame_rect.bottom() - 10.0);
                 frame_rect.set_top(frame_rect.top() + 10.0);
 
-                let active_tab = config.terminal.active_tab.unwrap();
-                let offset = *config
-                    .terminal
-                    .scroll_offset
-                    .get_mut(&active_tab)
-                    .unwrap_or(&mut Vec2::default());
+                //
+                // View picker
+                //
+
+                let problem_count = config
+                    .dock
+                    .tree
+                    .tabs()
+                    .map(|tab| tab.check.diagnostics.len())
+                    .sum::<usize>();
+
+                ui.horizontal(|ui| {
+                    let view = &mut config.terminal.view;
+                    ui.selectable_value(view, TerminalView::Output, "Output");
+                    ui.selectable_value(
+                        view,
+                        TerminalView::Problems,
+                        format!("Problems ({problem_count})"),
+                    );
+                    ui.selectable_value(view, TerminalView::Logs, "Logs");
+                });
+
+                match config.terminal.view {
+                    TerminalView::Problems => {
+                        problems_view(ui, id.with("problems"), config);
+                        return;
+                    }
+                    TerminalView::Logs => {
+                        logs_view(ui, config);
+                        return;
+                    }
+                    TerminalView::Output => (),
+                }
+
+                let Some(active_tab) = config.terminal.active_tab else {
+                    ui.centered_and_justified(|ui| ui.weak("no tab selected"));
+                    return;
+                };
 
                 //
-                // Parsing and caching
+                // Font size shortcuts
+                //
+
+                let output_focused = PANE_IDS
+                    .iter()
+                    .any(|pane_id| ui.memory().has_focus(id.with(pane_id)));
+
+                if output_focused || ui.rect_contains_pointer(ui.max_rect()) {
+                    let mut input = ui.input_mut();
+                    let font_size = &mut config.terminal.font_size;
+
+                    if input.consume_key(Modifiers::COMMAND, Key::PlusEquals) {
+                        *font_size = (*font_size + 1.0).min(MAX_FONT_SIZE);
+                    }
+                    if input.consume_key(Modifiers::COMMAND, Key::Minus) {
+                        *font_size = (*font_size - 1.0).max(MIN_FONT_SIZE);
+                    }
+                    if input.consume_key(Modifiers::COMMAND, Key::Num0) {
+                        *font_size = DEFAULT_FONT_SIZE;
+                    }
+                }
+
+                let font_id = config.terminal.font_id();
+
+                //
+                // Input
                 //
-                // (unstripped, strippedtext)
-                static CACHE_STDOUT: OnceCell<Mutex<HashMap<Id, (String, String)>>> =
-                    OnceCell::new();
-                static CACHE_STDERR: OnceCell<Mutex<HashMap<Id, (String, String)>>> =
-                    OnceCell::new();
-                let mut cache_stdout = CACHE_STDOUT
-                    .get_or_init(|| Mutex::new(HashMap::new()))
-                    .lock();
-                let mut cache_stderr = CACHE_STDERR
-                    .get_or_init(|| Mutex::new(HashMap::new()))
-                    .lock();
-
-                let terminal_output = config.terminal.content.entry(active_tab).or_default();
-                let (
-                    (terminal_output_stdout, terminal_output_stderr),
-                    (plain_stdout, plain_stderr),
-                ) = {
-                    if config.terminal.started_run {
-                        // clear out the cached entries to restart the term output fresh
-                        cache_stdout.remove(&active_tab);
-                        cache_stderr.remove(&active_tab);
-
-                        config.terminal.dynamic_index = (0, 0);
-                        config.terminal.started_run = false;
+
+                // the input line is laid out first so it stays at the bottom, under the output
+                // an earlier run being looked at can't be typed into
+                let can_send = config.terminal.viewed_run(active_tab).is_none()
+                    && config.terminal.is_running(ctx, active_tab)
+                    && config.terminal.stdin.contains_key(&active_tab);
+
+                let input_action = egui::TopBottomPanel::bottom(id.with("input"))
+                    .show_separator_line(false)
+                    .frame(egui::Frame::none())
+                    .show_inside(ui, |ui| {
+                        input_line(ui, &mut config.terminal.input, &font_id, can_send)
+                    })
+                    .inner;
+
+                // what was sent, to echo into the output
+                let mut sent = None;
+
+                if let Some(paste) = config.terminal.paste.take() {
+                    if let Some(stdin) = config.terminal.stdin.get(&active_tab) {
+                        let _ = stdin.send(paste.clone());
+                        sent = Some(paste);
                     }
+                }
 
-                    let (stdout_unstripped, stdout_stripped) = cache_stdout
-                        .entry(active_tab)
-                        .or_insert((String::new(), String::new()));
-                    let (stderr_unstripped, stderr_stripped) = cache_stderr
-                        .entry(active_tab)
-                        .or_insert((String::new(), String::new()));
-
-                    if let Some((stdout, stderr)) = terminal_output.as_mut() {
-                        for msg in stdout.pop_iter() {
-                            // right now, we don't really truly support overwrite mode, sorry
-                            if msg.ends_with('\r') {
-                                continue;
-                            }
+                match input_action {
+                    Some(InputAction::Send(input)) => {
+                        let input = format!("{input}\n");
+
+                        if let Some(stdin) = config.terminal.stdin.get(&active_tab) {
+                            let _ = stdin.send(input.clone());
+                            sent = Some(sent.unwrap_or_default() + &input);
+                        }
+                    }
+                    Some(InputAction::Close) => {
+                        config.terminal.stdin.remove(&active_tab);
+                    }
+                    None => (),
+                }
 
-                            stdout_unstripped.push_str(&msg);
+                //
+                // Parsing and caching
+                //
+                let mut cache = output_cache().lock();
+
+                // the output is kept for as long as the tab has content
+                config.terminal.content.entry(active_tab).or_default();
+                let merge_streams = config.terminal.merge_streams;
+
+                let ansi_colors = config.theme.get_ansi_colors();
+                let output_style = OutputStyle::new(&config.theme, ui.visuals(), font_id.clone());
+
+                let tab_output = cache.entry(active_tab).or_default();
+
+                // the run's output was taken in already, this is what came in since it was shown
+                let stderr_was_empty = std::mem::take(&mut tab_output.new_stderr)
+                    || tab_output.stderr.parser.text.is_empty();
+                let mut received = std::mem::take(&mut tab_output.received);
+
+                for message in config
+                    .terminal
+                    .messages
+                    .remove(&active_tab)
+                    .unwrap_or_default()
+                {
+                    tab_output.push(message);
+                    received = true;
+                }
+
+                if let Some(input) = sent {
+                    tab_output.push(OutputChunk::new(Stream::Stdin, input));
+                }
+
+                // drop the oldest lines once the output gets too big to handle every frame
+                tab_output.trim(config.terminal.max_scrollback_bytes);
+
+                // an earlier run picked in the header is shown in place of the latest one, whose
+                // output still comes in meanwhile
+                let viewed_run = config.terminal.viewed_run(active_tab);
+                let viewing_past = viewed_run.is_some();
+                let output_id =
+                    viewed_run.map_or(active_tab, |run| past_run_id(active_tab, run.number));
+                let tab_output = match viewed_run {
+                    Some(run) => cache
+                        .entry(output_id)
+                        .or_insert_with(|| TabOutput::from_chunks(&run.output)),
+                    None => tab_output,
+                };
+                // what the latest run printed doesn't move an earlier one around
+                received &= !viewing_past;
+
+                let (output_filter, invalid_filter) = OutputFilter::new(&config.terminal.filter);
+                let filtering = output_filter.is_some();
+
+                tab_output.update(merge_streams, output_filter.as_ref(), &output_style);
+
+                let tab_output = &*tab_output;
+
+                let mut panes = if merge_streams {
+                    vec![Pane {
+                        heading: None,
+                        id: PANE_IDS[2],
+                        scrollback: &tab_output.merged,
+                        view: tab_output.merged.view(filtering),
+                    }]
+                } else {
+                    vec![
+                        Pane {
+                            heading: Some("Standard Error"),
+                            id: PANE_IDS[1],
+                            scrollback: &tab_output.stderr,
+                            view: tab_output.stderr.view(filtering),
+                        },
+                        Pane {
+                            heading: Some("Standard Output"),
+                            id: PANE_IDS[0],
+                            scrollback: &tab_output.stdout,
+                            view: tab_output.stdout.view(filtering),
+                        },
+                    ]
+                };
+
+                // empty streams aren't shown
+                panes.retain(|pane| !pane.scrollback.parser.text.is_empty());
+
+                // stderr is shown above stdout, so once it gets its first output everything under it
+                // moves down
+                let stderr_appeared = !viewing_past && panes.len() == 2 && stderr_was_empty;
+
+                let mut follow = config
+                    .terminal
+                    .follow
+                    .get(&active_tab)
+                    .copied()
+                    .unwrap_or_default();
+
+                if received && follow.paused {
+                    follow.unread = true;
+                }
+
+                // the filtered view gets its own position, so the full output is back where it was once
+                // the filter is cleared
+                let scroll_key = (output_id, merge_streams, filtering);
+                let offset = config
+                    .terminal
+                    .scroll_offset
+                    .get(&scroll_key)
+                    .copied()
+                    .unwrap_or_default();
+
+                //
+                // Search
+                //
 
-                            let stripped =
-                                String::from_utf8(strip_ansi_escapes::strip(msg).unwrap()).unwrap();
+                config.terminal.hovered = ui.rect_contains_pointer(ui.max_rect());
+                let search = &mut config.terminal.search;
 
-                            stdout_stripped.push_str(&stripped);
+                // matches are searched for in the plain text, since that's what is displayed
+                let pane_matches = panes
+                    .iter()
+                    .map(|pane| {
+                        if search.open {
+                            find_matches(search, pane.view.text)
+                        } else {
+                            vec![]
                         }
+                    })
+                    .collect::<Vec<_>>();
 
-                        for mut msg in stderr.pop_iter() {
-                            // get indexes of last valid non-dynamic output
-                            let previous_newline_unstripped = &mut config.terminal.dynamic_index.0;
-                            let previous_newline_stripped = &mut config.terminal.dynamic_index.1;
+                let match_count = pane_matches.iter().map(Vec::len).sum::<usize>();
+                if search.current >= match_count {
+                    search.current = 0;
+                }
 
-                            if msg.ends_with('\r') {
-                                //
-                                // First, we need to strip out all previous lines
-                                //
-                                stderr_unstripped.truncate(*previous_newline_unstripped);
-                                stderr_stripped.truncate(*previous_newline_stripped);
+                //
+                // Header
+                //
 
-                                //
-                                // Now we can add the the strings to the end
-                                //
+                // the tabs which have output, to pick which one is shown
+                let output_tabs = config
+                    .dock
+                    .tree
+                    .tabs()
+                    .filter(|tab| {
+                        tab.id == active_tab
+                            || matches!(config.terminal.content.get(&tab.id), Some(Some(_)))
+                    })
+                    .map(|tab| (tab.id, tab.name.clone()))
+                    .collect::<Vec<_>>();
+
+                ui.horizontal(|ui| {
+                    for (tab, name) in output_tabs {
+                        if ui.selectable_label(tab == active_tab, name).clicked() {
+                            config.terminal.active_tab = Some(tab);
+                            ctx.request_repaint();
+                        }
+                    }
+                });
 
-                                // insert as a new line
-                                // pop off \r
-                                msg.pop();
+                // the latest run's state isn't shown while an earlier one is looked at
+                let run_state = config
+                    .terminal
+                    .run_state
+                    .get(&active_tab)
+                    .filter(|_| !viewing_past)
+                    .map(|run_state| *run_state.lock().unwrap());
 
-                                let trim_len = msg.trim_end().len();
-                                msg.truncate(trim_len);
+                // the tab's earlier runs to pick from, the newest first
+                let past_runs = config
+                    .terminal
+                    .history
+                    .get(&active_tab)
+                    .map(|runs| {
+                        runs.iter()
+                            .rev()
+                            .map(|run| (run.number, run.label(), run.command_line.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                let past_status = config
+                    .terminal
+                    .viewed_run(active_tab)
+                    .map(|run| (run.exit_code, run.finished));
+                let mut viewed = config.terminal.viewed_run(active_tab).map(|run| run.number);
+
+                let mut action = None;
+                // a location in the scratch which was clicked on
+                let mut jump = None;
+
+                ui.horizontal(|ui| {
+                    if !past_runs.is_empty() {
+                        let selected = past_runs
+                            .iter()
+                            .find(|(number, ..)| Some(*number) == viewed)
+                            .map_or_else(
+                                || "Latest run".to_string(),
+                                |(_, label, _)| label.clone(),
+                            );
+
+                        egui::ComboBox::from_id_source(id.with("past runs"))
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut viewed, None, "Latest run");
+
+                                for (number, label, command_line) in &past_runs {
+                                    let response =
+                                        ui.selectable_value(&mut viewed, Some(*number), label);
+                                    if let Some(command_line) = command_line {
+                                        response.on_hover_text(command_line);
+                                    }
+                                }
 
-                                // ignore empty messages. The next line inserted will be a real one anyways
-                                if msg.is_empty() {
-                                    continue;
+                                ui.separator();
+                                if ui.button("Clear history").clicked() {
+                                    action = Some(OutputAction::ClearHistory);
                                 }
+                            })
+                            .response
+                            .on_hover_text("Look at one of the previous runs");
+                    }
 
-                                let mut stripped =
-                                    String::from_utf8(strip_ansi_escapes::strip(&msg).unwrap())
-                                        .unwrap();
+                    if let Some((exit_code, finished)) = past_status {
+                        let (text, color) = match exit_code {
+                            Some(code) => (
+                                format!("exited with code {code} at {finished}"),
+                                if code == 0 {
+                                    ansi_colors.green
+                                } else {
+                                    ansi_colors.red
+                                },
+                            ),
+                            None => (format!("stopped at {finished}"), ansi_colors.red),
+                        };
 
-                                msg.push('\n');
-                                stripped.push('\n');
+                        ui.colored_label(color.to_color32(), text);
+                        ui.weak("read-only");
+                    } else if config.terminal.is_running(ctx, active_tab) {
+                        ui.spinner();
+                        ui.label("Running…");
 
-                                stderr_unstripped.push_str(&msg);
-                                stderr_stripped.push_str(&stripped);
+                        if ui.button("⏹ Stop").clicked() {
+                            config.terminal.kill(ctx, active_tab);
+                        }
 
-                                continue;
+                        // only shown once it's close, so it doesn't distract the rest of the time
+                        let remaining = run_state
+                            .and_then(|run_state| run_state.deadline)
+                            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                        if let Some(remaining) =
+                            remaining.filter(|remaining| remaining.as_secs() < 10)
+                        {
+                            ui.weak(format!("stops in {}s", remaining.as_secs() + 1))
+                                .on_hover_text("The run's time limit is almost up");
+                        }
+                    } else if let Some(RunState {
+                        duration: Some(duration),
+                        status,
+                        ..
+                    }) = run_state
+                    {
+                        let secs = duration.as_secs_f32();
+
+                        let (text, color) = match status {
+                            RunStatus::Exited(Some(code)) => (
+                                format!("exited with code {code} in {secs:.2}s"),
+                                if code == 0 {
+                                    ansi_colors.green
+                                } else {
+                                    ansi_colors.red
+                                },
+                            ),
+                            RunStatus::Exited(None) => (
+                                format!("terminated by signal after {secs:.2}s"),
+                                ansi_colors.red,
+                            ),
+                            RunStatus::Killed => {
+                                (format!("killed after {secs:.2}s"), ansi_colors.red)
                             }
+                            RunStatus::TimedOut(timeout) => (
+                                format!("timed out after {}s", timeout.as_secs()),
+                                ansi_colors.red,
+                            ),
+                            RunStatus::Failed => ("failed to start".to_string(), ansi_colors.red),
+                            RunStatus::Running => unreachable!("finished runs have a duration"),
+                        };
+
+                        ui.colored_label(color.to_color32(), text);
+                    }
 
-                            stderr_unstripped.push_str(&msg);
+                    if let Some(tests) = run_state.and_then(|run_state| run_state.tests) {
+                        let color = if tests.failed > 0 {
+                            ansi_colors.red
+                        } else {
+                            ansi_colors.green
+                        };
 
-                            let stripped =
-                                String::from_utf8(strip_ansi_escapes::strip(&msg).unwrap())
-                                    .unwrap();
+                        ui.colored_label(
+                            color.to_color32(),
+                            format!(
+                                "{} passed, {} failed, {} ignored",
+                                tests.passed, tests.failed, tests.ignored
+                            ),
+                        );
+                    }
 
-                            stderr_stripped.push_str(&stripped);
+                    if let Some(size) = run_state.and_then(|run_state| run_state.wasm_size) {
+                        ui.label(format!(".wasm is {}", build_cache::format_size(size)));
+                    }
 
-                            *previous_newline_unstripped += msg.len();
-                            *previous_newline_stripped += stripped.len();
+                    if let Some(run_state) = run_state {
+                        if run_state.automatic {
+                            ui.label(RichText::new("auto").small().strong())
+                                .on_hover_text("Started by auto-run");
                         }
+
+                        ui.weak(run_state.run_config.summary());
                     }
 
-                    (
-                        (&**stdout_unstripped, &**stderr_unstripped),
-                        (&**stdout_stripped, &**stderr_stripped),
-                    )
-                };
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui.button("Clear").clicked() {
+                            action = Some(OutputAction::Clear);
+                        }
 
-                let mut read_only_term_stdout = ReadOnlyString::new(plain_stdout);
-                let mut read_only_term_stderr = ReadOnlyString::new(plain_stderr);
+                        ui.menu_button("📋 Copy", |ui| copy_menu(ui, &mut action));
 
-                let ansi_colors = config.theme.get_ansi_colors();
+                        ui.toggle_value(&mut config.terminal.merge_streams, "Merge streams")
+                            .on_hover_text(
+                                "Show stdout and stderr together, in the order they were written",
+                            );
 
-                let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
-                    let mut layout_job =
-                        parse_ansi(ui.ctx(), ansi_colors, terminal_output_stdout, text);
-                    layout_job.wrap.max_width = wrap_width;
-                    ui.fonts().layout_job(layout_job)
-                };
-                let mut layouter2 = |ui: &egui::Ui, text: &str, wrap_width: f32| {
-                    let mut layout_job =
-                        parse_ansi(ui.ctx(), ansi_colors, terminal_output_stderr, text);
-                    layout_job.wrap.max_width = wrap_width;
-                    ui.fonts().layout_job(layout_job)
+                        ui.toggle_value(&mut config.terminal.filter.open, "Filter")
+                            .on_hover_text("Only show the lines matching a filter or log level");
+
+                        ui.toggle_value(&mut config.terminal.show_timestamps, "Timestamps")
+                            .on_hover_text("Show when each line arrived");
+
+                        ui.toggle_value(&mut config.terminal.wrap_lines, "Wrap")
+                            .on_hover_text("Wrap long lines instead of scrolling sideways");
+                    });
+                });
+
+                if viewed != config.terminal.viewed_run(active_tab).map(|run| run.number) {
+                    match viewed {
+                        Some(number) => config.terminal.viewed_run.insert(active_tab, number),
+                        None => config.terminal.viewed_run.remove(&active_tab),
+                    };
+                    ctx.request_repaint();
+                }
+
+                if config.terminal.search.open {
+                    search_bar(ui, &mut config.terminal.search, match_count);
+                }
+
+                if config.terminal.filter.open {
+                    let hidden = if filtering {
+                        panes
+                            .iter()
+                            .map(|pane| pane.scrollback.filtered.hidden)
+                            .sum()
+                    } else {
+                        0
+                    };
+
+                    filter_bar(ui, &mut config.terminal.filter, invalid_filter, hidden);
+                }
+
+                let search = &mut config.terminal.search;
+
+                // which pane the current match is in, and where
+                let current_match = if !search.open || match_count == 0 {
+                    None
+                } else {
+                    let mut index = search.current;
+                    pane_matches.iter().enumerate().find_map(|(pane, matches)| {
+                        match matches.get(index) {
+                            Some(range) => Some((pane, range.clone())),
+                            None => {
+                                index -= matches.len();
+                                None
+                            }
+                        }
+                    })
                 };
 
-                let text_widget_stdout = egui::TextEdit::multiline(&mut read_only_term_stdout)
-                    .font(egui::TextStyle::Monospace) // for cursor height
-                    // remove the frame and draw our own
-                    .frame(false)
-                    .desired_width(f32::INFINITY)
-                    .layouter(&mut layouter)
-                    .id(id.with("term_output_stdout"))
-                    .interactive(true);
-
-                let text_widget_stderr = egui::TextEdit::multiline(&mut read_only_term_stderr)
-                    .font(egui::TextStyle::Monospace) // for cursor height
-                    // remove the frame and draw our own
-                    .frame(false)
-                    .desired_width(f32::INFINITY)
-                    .layouter(&mut layouter2)
-                    .id(id.with("term_output_stderr"))
-                    .interactive(true);
-
-                let scrollarea = egui::ScrollArea::vertical()
-                    .max_height(f32::INFINITY)
-                    .auto_shrink([false, false])
-                    .scroll_offset(offset)
-                    .stick_to_bottom(true)
-                    .show(ui, |ui| {
-                        ui.horizontal(|ui| {
-                            ui.vertical(|ui| {
-                                ui.heading("Standard Error");
-                                ui.add(text_widget_stderr);
-                            });
-                        });
+                let match_color = Color32::from_rgba_unmultiplied(255, 210, 0, 60);
+                let current_match_color = Color32::from_rgba_unmultiplied(255, 140, 0, 160);
+
+                // matches are counted across the panes, in the order they're shown
+                let mut match_offset = 0;
+                let pane_highlights = pane_matches
+                    .into_iter()
+                    .map(|matches| {
+                        let offset = match_offset;
+                        match_offset += matches.len();
+
+                        matches
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, range)| {
+                                if search.current == offset + i {
+                                    (range, current_match_color)
+                                } else {
+                                    (range, match_color)
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+
+                let scroll_to_match = std::mem::take(&mut search.scroll_to_current);
 
-                        ui.horizontal(|ui| {
-                            ui.vertical(|ui| {
-                                ui.heading("Standard Output");
-                                ui.add(text_widget_stdout);
-                            });
+                // going to a match scrolls away from the new output
+                if scroll_to_match && current_match.is_some() {
+                    follow.paused = true;
+                }
+
+                //
+                // Output
+                //
+
+                if panes.is_empty() {
+                    let hint = if config.terminal.is_running(ctx, active_tab) {
+                        "waiting for output…"
+                    } else {
+                        "no output yet — press Ctrl+Enter to run"
+                    };
+
+                    ui.centered_and_justified(|ui| ui.weak(hint));
+                } else {
+                    // height of stderr when it's shown for the first time
+                    let mut stderr_height = 0.0;
+
+                    let wrap_lines = config.terminal.wrap_lines;
+
+                    // room to the left of the text for the timestamps
+                    let gutter_width = if config.terminal.show_timestamps {
+                        let sample = format!("[{}] ", Timestamp::default());
+                        ui.fonts()
+                            .layout_no_wrap(sample, font_id.clone(), Color32::TRANSPARENT)
+                            .size()
+                            .x
+                    } else {
+                        0.0
+                    };
+
+                    let mut copy_on_select = config.terminal.copy_on_select;
+                    let can_paste = can_send && cfg!(target_os = "windows");
+
+                    // right clicking moves the cursor, which would lose the selection the context menu
+                    // copies
+                    let right_clicked = {
+                        let input = ui.input();
+                        input.pointer.any_pressed() && input.pointer.secondary_down()
+                    };
+
+                    let scrollarea = egui::ScrollArea::new([!wrap_lines, true])
+                        .max_height(f32::INFINITY)
+                        .auto_shrink([false, false])
+                        .scroll_offset(offset)
+                        .stick_to_bottom(!follow.paused)
+                        .show(ui, |ui| {
+                            for (index, pane) in panes.iter().enumerate() {
+                                let text = pane.view.text;
+                                let links = pane.view.links;
+
+                                let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                                    let mut layout_job = LayoutJob {
+                                        text: text.into(),
+                                        sections: pane.view.sections.to_vec(),
+                                        ..Default::default()
+                                    };
+                                    style_links(ui, &mut layout_job, links);
+                                    highlight_matches(&mut layout_job, &pane_highlights[index]);
+                                    layout_job.wrap.max_width = if wrap_lines {
+                                        wrap_width
+                                    } else {
+                                        f32::INFINITY
+                                    };
+                                    ui.fonts().layout_job(layout_job)
+                                };
+
+                                let mut read_only_text = ReadOnlyString::new(text);
+
+                                let text_widget = egui::TextEdit::multiline(&mut read_only_text)
+                                    .font(font_id.clone()) // for cursor height
+                                    // remove the frame and draw our own
+                                    .frame(false)
+                                    .desired_width(f32::INFINITY)
+                                    .layouter(&mut layouter)
+                                    .id(id.with(pane.id))
+                                    .interactive(true);
+
+                                let kept_state = if right_clicked {
+                                    TextEditState::load(ctx, id.with(pane.id))
+                                } else {
+                                    None
+                                };
+
+                                let response = ui.horizontal(|ui| {
+                                    ui.vertical(|ui| {
+                                        if let Some(heading) = pane.heading {
+                                            ui.heading(heading);
+                                        }
+                                        if tab_output.truncated {
+                                            ui.weak("…output truncated…");
+                                        }
+                                        let output = ui
+                                            .horizontal(|ui| {
+                                                ui.add_space(gutter_width);
+                                                text_widget.show(ui)
+                                            })
+                                            .inner;
+
+                                        if let Some(state) = kept_state {
+                                            if output.response.hovered() {
+                                                state.store(ctx, id.with(pane.id));
+                                            }
+                                        }
+
+                                        let selection_finished = output.response.drag_released()
+                                            || output.response.double_clicked()
+                                            || output.response.triple_clicked();
+
+                                        if copy_on_select && selection_finished {
+                                            if let Some(range) = output
+                                                .cursor_range
+                                                .filter(|range| !range.is_empty())
+                                            {
+                                                ui.output().copied_text = char_range_text(
+                                                    text,
+                                                    range.as_sorted_char_range(),
+                                                );
+                                            }
+                                        }
+
+                                        if gutter_width > 0.0 {
+                                            paint_timestamps(
+                                                ui,
+                                                &output,
+                                                pane.view.line_times,
+                                                &font_id,
+                                                gutter_width,
+                                            );
+                                        }
+
+                                        if let Some((pane, range)) = &current_match {
+                                            if *pane == index && scroll_to_match {
+                                                scroll_to_byte(ui, &output, text, range.start);
+                                            }
+                                        }
+
+                                        if let Some(location) =
+                                            handle_links(ui, &output, text, links)
+                                        {
+                                            jump = Some(location);
+                                        }
+
+                                        output.response.context_menu(|ui| {
+                                            let has_selection =
+                                                selected_text(ctx, id.with(pane.id), text)
+                                                    .is_some();
+
+                                            output_menu(
+                                                ui,
+                                                &mut action,
+                                                index,
+                                                has_selection,
+                                                can_paste,
+                                                !past_runs.is_empty(),
+                                                &mut copy_on_select,
+                                            )
+                                        });
+                                    });
+                                });
+
+                                if index == 0 && stderr_appeared {
+                                    stderr_height = response.response.rect.height()
+                                        + ui.spacing().item_spacing.y;
+                                }
+                            }
                         });
-                    });
 
-                config
-                    .terminal
-                    .scroll_offset
-                    .insert(active_tab, scrollarea.state.offset);
+                    let mut new_offset = scrollarea.state.offset;
+                    let max_offset = scrollarea.content_size.y - scrollarea.inner_rect.height();
+                    let at_bottom = new_offset.y >= max_offset - 1.0;
+
+                    // the offset also goes up when the output is trimmed, so only the user scrolling
+                    // pauses or resumes following
+                    let user_scrolled = {
+                        let input = ui.input();
+                        input.scroll_delta.y != 0.0 || input.pointer.any_down()
+                    };
+
+                    if follow.paused {
+                        if user_scrolled && at_bottom {
+                            follow = Follow::default();
+                        }
+                    } else if user_scrolled && new_offset.y < offset.y {
+                        follow.paused = true;
+                    } else if !at_bottom {
+                        // the scrollarea only sticks once it's been at the bottom, so catch up with
+                        // output which came in while it wasn't
+                        new_offset.y = max_offset;
+                        ctx.request_repaint();
+                    }
+
+                    // keep what was on screen in place when stderr shows up above it. When following,
+                    // the scrollarea already moves to the new output
+                    if stderr_height > 0.0 && follow.paused {
+                        new_offset.y += stderr_height;
+                        ctx.request_repaint();
+                    }
+
+                    if follow_pill(ctx, id, scrollarea.inner_rect, &follow) {
+                        if follow.paused {
+                            follow = Follow::default();
+                            new_offset.y = max_offset;
+                        } else {
+                            follow.paused = true;
+                        }
+
+                        ctx.request_repaint();
+                    }
+
+                    config.terminal.scroll_offset.insert(scroll_key, new_offset);
+                    config.terminal.copy_on_select = copy_on_select;
+                }
+
+                config.terminal.follow.insert(active_tab, follow);
+
+                if let Some((line, col)) = jump {
+                    config
+                        .dock
+                        .commands
+                        .push(Command::TerminalCommand(TerminalCommand::JumpTo {
+                            tab: active_tab,
+                            line,
+                            col,
+                        }));
+                }
+
+                if config.terminal.merge_streams != merge_streams {
+                    ctx.request_repaint();
+                }
+
+                match action {
+                    Some(OutputAction::Clear) => {
+                        // the earlier runs are kept, it's the latest one which is cleared
+                        config.terminal.clear.insert(active_tab);
+                        config.terminal.viewed_run.remove(&active_tab);
+                        ctx.request_repaint();
+                    }
+                    Some(OutputAction::ClearHistory) => {
+                        config.terminal.history.remove(&active_tab);
+                        config.terminal.viewed_run.remove(&active_tab);
+                        ctx.request_repaint();
+                    }
+                    Some(OutputAction::Copy { target, format }) => {
+                        ui.output().copied_text =
+                            tab_output.copy_text(target, format, merge_streams);
+                    }
+                    Some(OutputAction::CopySelection(pane)) => {
+                        let pane = &panes[pane];
+                        if let Some(text) = selected_text(ctx, id.with(pane.id), pane.view.text) {
+                            ui.output().copied_text = text;
+                        }
+                    }
+                    Some(OutputAction::SelectAll(pane)) => {
+                        let pane = &panes[pane];
+                        let pane_id = id.with(pane.id);
+
+                        let mut state = TextEditState::load(ctx, pane_id).unwrap_or_default();
+                        state.set_ccursor_range(Some(CCursorRange::two(
+                            CCursor::new(0),
+                            CCursor::new(pane.view.text.chars().count()),
+                        )));
+                        state.store(ctx, pane_id);
+
+                        // the selection is only shown while it has focus
+                        ui.memory().request_focus(pane_id);
+                    }
+                    Some(OutputAction::Paste) => {
+                        config.terminal.paste = clipboard_text();
+                        ctx.request_repaint();
+                    }
+                    None => (),
+                }
+            });
+
+        if close_pending {
+            let color = ctx.style().visuals.error_fg_color;
+            let top = panel.response.rect.top();
+
+            ctx.layer_painter(LayerId::new(Order::Foreground, id.with("close_hint")))
+                .hline(panel.response.rect.x_range(), top, Stroke::new(2.0, color));
+            egui::show_tooltip_at_pointer(ctx, id.with("close_hint"), |ui| {
+                ui.colored_label(color, "Release to close")
             });
+        }
+
+        // remember the height once the user is done resizing
+        let resizing = ctx.memory().is_being_dragged(resize_id);
+        if config.terminal.open && config.terminal.resizing && !resizing {
+            config.terminal.height = Some(panel.response.rect.height());
+        }
+        config.terminal.resizing = resizing;
+
+        // double clicking the resize strip closes the panel
+        let grab_radius = ctx.style().interaction.resize_grab_radius_side;
+        let double_clicked_edge = {
+            let input = ctx.input();
+            match input.pointer.interact_pos() {
+                Some(pos) => {
+                    input.pointer.button_double_clicked(PointerButton::Primary)
+                        && (pos.y - panel.response.rect.top()).abs() <= grab_radius
+                }
+                None => false,
+            }
+        };
+
+        if double_clicked_edge {
+            config.terminal.toggle();
+        }
     }
 
+    /// The handle the closed panel is opened from, by dragging it up or double clicking it
     pub fn show_closed_handle(ctx: &egui::Context, config: &mut Config) {
         let id = Id::new("terminal-closed");
 
@@ -448,9 +2588,8 @@ impl Terminal {
                         let h_response =
                             ui.interact(center_line, center_id.with("hover"), hover_sense);
 
-                        if config.terminal.closed_from_open {
-                            ui.memory().set_dragged_id(alloc_id);
-                            config.terminal.closed_from_open = false;
+                        if response.double_clicked() {
+                            config.terminal.toggle();
                         }
 
                         let is_dragging = response.dragged();
@@ -3,8 +3,8 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
 
 use egui::{
-    lerp, vec2, CentralPanel, Color32, ColorImage, Context, Frame, Id, Image, LayerId, Pos2, Rect,
-    Rgba, Sense, Stroke, TextureHandle, Ui,
+    lerp, pos2, vec2, Button, CentralPanel, Color32, ColorImage, Context, FontId, Frame, Id, Image,
+    LayerId, Pos2, Rect, Rgba, Sense, Stroke, TextureHandle, Ui,
 };
 
 use once_cell::sync::OnceCell;
@@ -19,6 +19,7 @@ use windows::Win32::UI::WindowsAndMessaging::{
     SW_MINIMIZE, SW_RESTORE, WINDOWPLACEMENT,
 };
 
+use crate::config::{Action, Command, Config, TabCommand};
 use crate::CaptionMaxRect;
 
 pub const TITLEBAR_HEIGHT: i32 = 80;
@@ -31,6 +32,11 @@ pub const CAPTION_PADDING: u32 = 2;
 // if your mouse is showing resize handles
 pub const CAPTION_TOP_PADDING: u32 = 5;
 
+const RUN_BTN_WIDTH: f32 = 70.0;
+const RUN_BTN_PAD: f32 = 4.0;
+const SETTINGS_BTN_WIDTH: f32 = 30.0;
+const FILE_BTN_WIDTH: f32 = 40.0;
+
 macro_rules! egui_dimens {
     ($var:ident) => {
         $var as f32 / 2.0
@@ -41,6 +47,7 @@ pub fn custom_window_frame(
     ctx: &egui::Context,
     frame: &mut eframe::Frame,
     ui: &mut egui::Ui,
+    config: &mut Config,
     #[cfg(target_os = "windows")] sender: Rc<Sender<CaptionMaxRect>>,
 ) {
     let is_maximized = unsafe {
@@ -104,10 +111,27 @@ pub fn custom_window_frame(
     minimize_rect.set_right(maximize_rect.left() - CAPT_PAD);
     minimize_rect.set_bottom(capt_height);
 
+    // run rect
+    let mut run_rect = rect;
+    run_rect.set_left(minimize_rect.left() - RUN_BTN_WIDTH - RUN_BTN_PAD * 2.0);
+    run_rect.set_right(minimize_rect.left() - RUN_BTN_PAD);
+    run_rect.set_top(rect.top() + RUN_BTN_PAD);
+    run_rect.set_bottom(capt_height - RUN_BTN_PAD);
+
+    // settings rect
+    let mut settings_rect = run_rect;
+    settings_rect.set_left(run_rect.left() - SETTINGS_BTN_WIDTH - RUN_BTN_PAD);
+    settings_rect.set_right(run_rect.left() - RUN_BTN_PAD);
+
+    // file menu rect
+    let mut file_rect = settings_rect;
+    file_rect.set_left(settings_rect.left() - FILE_BTN_WIDTH - RUN_BTN_PAD);
+    file_rect.set_right(settings_rect.left() - RUN_BTN_PAD);
+
     // Interact with the title bar (drag to move window):
     let title_bar_rect = {
         let mut rect = rect;
-        rect.set_right(minimize_rect.left() + CAPT_PAD);
+        rect.set_right(file_rect.left());
         rect.set_bottom(CAPT_TITLEBAR_HEIGHT);
         rect
     };
@@ -116,6 +140,34 @@ pub fn custom_window_frame(
         frame.drag_window();
     }
 
+    let focused = config
+        .dock
+        .tree
+        .find_active_focused()
+        .map(|(_, tab)| (tab.name.clone(), tab.is_dirty()));
+    let title = window_title(
+        focused
+            .as_ref()
+            .map(|(name, dirty)| (name.as_str(), *dirty)),
+        &[],
+    );
+    set_window_title(ctx, frame, &title);
+    // centered on the window, without running into the buttons
+    let center = pos2(rect.center().x, run_rect.center().y);
+    let title_width = 2.0 * (file_rect.left() - center.x).min(center.x - rect.left()) - 16.0;
+    show_title(ui, center, title_width, &title);
+
+    run_btn(ui, run_rect, config);
+
+    let settings = ui
+        .put(settings_rect, Button::new("⚙"))
+        .on_hover_text("Settings");
+    if settings.clicked() {
+        config.settings.open = !config.settings.open;
+    }
+
+    file_menu(ui, file_rect, config);
+
     // Handle caption buttons
     //
     // CLOSE BTN
@@ -172,6 +224,173 @@ pub fn custom_window_frame(
     );
 }
 
+/// What the window's called: the focused tab's name, with a `*` when it has unsaved
+/// changes. Marks like "▶" for a running program go after the name
+pub fn window_title(tab: Option<(&str, bool)>, marks: &[&str]) -> String {
+    let Some((name, dirty)) = tab else {
+        return "RustPlay".into();
+    };
+
+    let mut title = name.to_string();
+    if dirty {
+        title.push('*');
+    }
+    for mark in marks {
+        title.push(' ');
+        title.push_str(mark);
+    }
+
+    format!("{title} — RustPlay")
+}
+
+// The os is only told the title when it changes
+fn set_window_title(ctx: &Context, frame: &mut eframe::Frame, title: &str) {
+    let id = Id::new("window_title");
+    let mut memory = ctx.memory();
+    if memory.data.get_temp::<String>(id).as_deref() != Some(title) {
+        memory.data.insert_temp(id, title.to_string());
+        frame.set_window_title(title);
+    }
+}
+
+// The title in the titlebar, cut short with a "…" when it's wider than there's room for
+fn show_title(ui: &Ui, center: Pos2, width: f32, title: &str) {
+    let font_id = FontId::proportional(14.0);
+    let color = ui.visuals().text_color();
+    let layout = |text: String| ui.fonts().layout_no_wrap(text, font_id.clone(), color);
+
+    let mut galley = layout(title.to_string());
+    let mut chars = title.chars().count();
+    while galley.size().x > width && chars > 0 {
+        chars -= 1;
+        galley = layout(format!(
+            "{}…",
+            title.chars().take(chars).collect::<String>()
+        ));
+    }
+    if galley.size().x > width {
+        return;
+    }
+
+    let pos = center - galley.size() / 2.0;
+    ui.painter().galley(pos, galley);
+}
+
+// Runs the active tab. Running again while a process is alive kills it and starts over
+fn run_btn(ui: &mut Ui, rect: Rect, config: &mut Config) {
+    let Some(active_tab) = config.terminal.active_tab else {
+        return;
+    };
+
+    let running = config.terminal.is_running(ui.ctx(), active_tab);
+
+    let (text, hover_text) = if running {
+        ("⟲ Restart", "Kill the running program and run it again")
+    } else {
+        ("▶ Run", "Run the scratch (Ctrl+Enter)")
+    };
+
+    let response = ui.put(rect, Button::new(text)).on_hover_text(hover_text);
+
+    if response.clicked() {
+        config
+            .dock
+            .commands
+            .push(Command::TabCommand(TabCommand::Play(active_tab)));
+    }
+}
+
+fn file_menu(ui: &mut Ui, rect: Rect, config: &mut Config) {
+    ui.allocate_ui_at_rect(rect, |ui| {
+        ui.menu_button("File", |ui| {
+            if ui
+                .add(
+                    Button::new("Open…")
+                        .shortcut_text(config.keybindings.combo(Action::OpenFile).to_string()),
+                )
+                .clicked()
+            {
+                config
+                    .dock
+                    .commands
+                    .push(Command::TabCommand(TabCommand::Open));
+                ui.close_menu();
+            }
+
+            ui.add_enabled_ui(!config.recent_files.is_empty(), |ui| {
+                ui.menu_button("Open Recent", |ui| recent_menu(ui, config));
+            });
+
+            if ui
+                .add_enabled(
+                    !config.dock.closed_tabs.is_empty(),
+                    Button::new("Reopen Closed Tab")
+                        .shortcut_text(config.keybindings.combo(Action::ReopenTab).to_string()),
+                )
+                .clicked()
+            {
+                config
+                    .dock
+                    .commands
+                    .push(Command::TabCommand(TabCommand::Reopen(None)));
+                ui.close_menu();
+            }
+        });
+    });
+}
+
+// The pinned files first, then the recent ones. Files which are gone can't be opened, only removed
+fn recent_menu(ui: &mut Ui, config: &mut Config) {
+    let recent = &config.recent_files;
+    let paths = recent
+        .pinned
+        .iter()
+        .chain(&recent.recent)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for path in paths {
+        let pinned = config.recent_files.is_pinned(&path);
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into(),
+        );
+
+        ui.horizontal(|ui| {
+            let pin = if pinned { "Unpin" } else { "Pin to the top" };
+            if ui.small_button("📌").on_hover_text(pin).clicked() {
+                config.recent_files.toggle_pin(&path);
+            }
+            if ui
+                .small_button("✖")
+                .on_hover_text("Remove from the list")
+                .clicked()
+            {
+                config.recent_files.remove(&path);
+            }
+
+            let exists = path.exists();
+            let hover = if exists {
+                path.display().to_string()
+            } else {
+                format!("{} (not found)", path.display())
+            };
+            let response = ui
+                .add_enabled(exists, Button::new(name))
+                .on_hover_text(&hover)
+                .on_disabled_hover_text(hover);
+            if response.clicked() {
+                // a file which is open already gets its tab focused
+                config
+                    .dock
+                    .commands
+                    .push(Command::TabCommand(TabCommand::OpenFile(path.clone())));
+                ui.close_menu();
+            }
+        });
+    }
+}
+
 macro_rules! icon {
     ($ctx:ident, $name:ident) => {{
         paste::paste! {
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use egui::{Align2, Area, Frame, Key, Modifiers, Order, RichText};
+
+use super::palette::CommandRegistry;
+use crate::config::{Config, ZOOM_STEP};
+
+// how long the zoom stays shown after it changes
+const SHOWN_FOR: Duration = Duration::from_millis(1200);
+
+/// Zooming the whole ui with Ctrl+scroll and Ctrl+= / Ctrl+-
+pub struct Zoom;
+
+impl Zoom {
+    /// The palette's commands for zooming
+    pub fn register_commands(registry: &mut CommandRegistry) {
+        registry.register("Zoom in", None, |config| {
+            config.ui.set_zoom(config.ui.zoom + ZOOM_STEP);
+        });
+        registry.register("Zoom out", None, |config| {
+            config.ui.set_zoom(config.ui.zoom - ZOOM_STEP);
+        });
+        registry.register("Reset zoom", None, |config| config.ui.set_zoom(1.0));
+    }
+
+    /// Zoom when the keys weren't taken by the terminal, which sizes its font with them while the
+    /// pointer's over it. It goes after the terminal for that
+    pub fn handle(ctx: &egui::Context, frame: &eframe::Frame, config: &mut Config) {
+        let (zoom_in, zoom_out, reset, scrolled) = {
+            let mut input = ctx.input_mut();
+            (
+                input.consume_key(Modifiers::COMMAND, Key::PlusEquals),
+                input.consume_key(Modifiers::COMMAND, Key::Minus),
+                input.consume_key(Modifiers::COMMAND, Key::Num0),
+                input.zoom_delta(),
+            )
+        };
+
+        let ui = &mut config.ui;
+        if zoom_in {
+            ui.set_zoom(ui.zoom + ZOOM_STEP);
+        }
+        if zoom_out {
+            ui.set_zoom(ui.zoom - ZOOM_STEP);
+        }
+        if reset {
+            ui.set_zoom(1.0);
+        }
+        if scrolled != 1.0 {
+            ui.set_zoom(ui.zoom * scrolled);
+        }
+
+        let native = frame.info().native_pixels_per_point.unwrap_or(1.0);
+        let pixels_per_point = native * ui.zoom;
+        if (ctx.pixels_per_point() - pixels_per_point).abs() > f32::EPSILON {
+            ctx.set_pixels_per_point(pixels_per_point);
+        }
+
+        Self::show_zoom(ctx, config);
+    }
+
+    // The zoom in the middle of the window, for a moment after it changes
+    fn show_zoom(ctx: &egui::Context, config: &Config) {
+        let Some(zoomed_at) = config.ui.zoomed_at else {
+            return;
+        };
+        let elapsed = zoomed_at.elapsed();
+        if elapsed >= SHOWN_FOR {
+            return;
+        }
+
+        Area::new("zoom")
+            .order(Order::Tooltip)
+            .interactable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    let percent = (config.ui.zoom * 100.0).round();
+                    ui.label(RichText::new(format!("{percent}%")).heading());
+                });
+            });
+
+        ctx.request_repaint_after(SHOWN_FOR - elapsed);
+    }
+}